@@ -0,0 +1,56 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use moleucle_3dview_rs::Molecule;
+use std::fmt::Write;
+use std::hint::black_box;
+
+/// A mol2 file with `n_atoms` atoms in a chain, in the layout most writers use.
+fn large_mol2(n_atoms: usize) -> String {
+    let mut mol2 = String::new();
+    let _ = write!(
+        mol2,
+        "@<TRIPOS>MOLECULE\nlarge\n{n_atoms} {} 1 0 0\nSMALL\nUSER_CHARGES\n\n@<TRIPOS>ATOM\n",
+        n_atoms - 1
+    );
+    let types = ["C.3", "N.am", "O.2", "C.ar", "H", "S.3"];
+    for i in 0..n_atoms {
+        let _ = writeln!(
+            mol2,
+            "{:>7} {:<8} {:>10.4} {:>10.4} {:>10.4} {:<8} {:>5} RES{:<4} {:>8.4}",
+            i + 1,
+            "X",
+            i as f32 * 0.37 % 97.0,
+            i as f32 * 1.13 % 89.0,
+            i as f32 * 0.71 % 83.0,
+            types[i % types.len()],
+            i / 20 + 1,
+            i / 20 + 1,
+            0.0
+        );
+    }
+    mol2.push_str("@<TRIPOS>BOND\n");
+    for i in 1..n_atoms {
+        let _ = writeln!(
+            mol2,
+            "{:>6} {:>5} {:>5} {}",
+            i,
+            i,
+            i + 1,
+            ["1", "2", "ar"][i % 3]
+        );
+    }
+    mol2
+}
+
+fn bench_mol2(c: &mut Criterion) {
+    let mol2 = large_mol2(200_000);
+    let mut group = c.benchmark_group("mol2");
+    group.throughput(Throughput::Bytes(mol2.len() as u64));
+    group.sample_size(10);
+    group.bench_function("from_mol2_str 200k atoms", |b| {
+        b.iter(|| Molecule::from_mol2_str(black_box(&mol2)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_mol2);
+criterion_main!(benches);
@@ -0,0 +1,193 @@
+//! Renders a PNG thumbnail of every structure in a directory and writes a CSV
+//! of name, formula, atom count and molecular weight next to them.
+//!
+//! cargo run --example batch_thumbnails -- <dir> [--style auto|ball-and-stick|stick|space-filling|wireframe]
+//!     [--background RRGGBB] [--size 512] [--supersample 1-4] [--memory-mb 256] [--ao 0-1]
+//!
+//! Files are loaded with `Molecule::from_file` and styled with `auto_style`
+//! unless `--style` says otherwise. Images are ray cast on the CPU from the
+//! `DisplayList`, orthographic from +Z, so this runs without a GPU or window.
+//! With `--supersample N` each image is cast at N times its size and box
+//! filtered down (see `raster::render_supersampled`), smoothing the jagged
+//! sphere outlines of a 1x render. An intermediate image over `--memory-mb`
+//! is cast in bands of rows, which gives the same bytes as casting it whole.
+//! `--ao` darkens buried atoms by that much (`ViewerSettings::ao_strength`).
+//! Files that fail to load get a row with the error and no image. One file is
+//! held in memory at a time.
+
+use moleucle_3dview_rs::camera::ViewBookmark;
+use moleucle_3dview_rs::colormap::Rgb;
+use moleucle_3dview_rs::molecule::SUPPORTED_EXTENSIONS;
+use moleucle_3dview_rs::raster::{self, Lens, Supersample, MAX_SUPERSAMPLE};
+use moleucle_3dview_rs::{DisplayList, Molecule, MoleculeViewer, RenderStyle, SelectedAtomRender};
+use nalgebra::UnitQuaternion;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+struct Options {
+    dir: PathBuf,
+    style: Option<RenderStyle>,
+    background: Rgb,
+    size: usize,
+    supersample: Supersample,
+    ao_strength: f32,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        dir: PathBuf::from("."),
+        style: None,
+        background: (1.0, 1.0, 1.0),
+        size: 512,
+        supersample: Supersample::default(),
+        ao_strength: 0.0,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg}: missing value"));
+        match arg.as_str() {
+            "--style" => {
+                options.style = match value()?.as_str() {
+                    "auto" => None,
+                    "ball-and-stick" => Some(RenderStyle::BallAndStick),
+                    "stick" | "licorice" => Some(RenderStyle::Stick),
+                    "space-filling" => Some(RenderStyle::SpaceFilling),
+                    "wireframe" => Some(RenderStyle::Wireframe),
+                    other => return Err(format!("--style: unknown style {other}")),
+                }
+            }
+            "--background" => {
+                let hex = value()?;
+                let rgb = u32::from_str_radix(hex.trim_start_matches('#'), 16)
+                    .map_err(|_| format!("--background: expected RRGGBB, got {hex}"))?;
+                let channel = |shift: u32| ((rgb >> shift) & 0xff) as f32 / 255.0;
+                options.background = (channel(16), channel(8), channel(0));
+            }
+            "--size" => {
+                options.size = value()?
+                    .parse()
+                    .ok()
+                    .filter(|&size| size > 0)
+                    .ok_or("--size: expected a positive integer")?;
+            }
+            "--supersample" => {
+                options.supersample.factor = value()?
+                    .parse()
+                    .ok()
+                    .filter(|n| (1..=MAX_SUPERSAMPLE).contains(n))
+                    .ok_or(format!("--supersample: expected 1 to {MAX_SUPERSAMPLE}"))?;
+            }
+            "--memory-mb" => {
+                let mb: usize = value()?
+                    .parse()
+                    .ok()
+                    .filter(|&mb| mb > 0)
+                    .ok_or("--memory-mb: expected a positive integer")?;
+                options.supersample.memory = mb << 20;
+            }
+            "--ao" => {
+                options.ao_strength = value()?
+                    .parse()
+                    .ok()
+                    .filter(|s| (0.0..=1.0).contains(s))
+                    .ok_or("--ao: expected 0 to 1")?;
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown flag {arg}")),
+            _ => options.dir = PathBuf::from(arg),
+        }
+    }
+    Ok(options)
+}
+
+/// An orthographic view down -Z that fits `Molecule::frame_bounds`, padded
+/// by the largest sphere, with everything in front of the eye.
+fn framing(mol: &Molecule, list: &DisplayList) -> (ViewBookmark, Lens) {
+    let (min, max) = mol.frame_bounds();
+    let padding = list.spheres.iter().map(|s| s.radius).fold(0.0, f32::max);
+    let view = ViewBookmark {
+        center: nalgebra::center(&min, &max),
+        rotation: UnitQuaternion::identity(),
+        radius: (max - min).norm() + padding,
+    };
+    let height = (max - min).xy().norm() + 2.0 * padding;
+    (view, Lens::Orthographic { height })
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Loads, styles, renders and writes one file; returns its CSV row.
+fn thumbnail(path: &Path, options: &Options) -> Result<String, String> {
+    let mol = Molecule::from_file(path)?;
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(mol);
+    if let Some(style) = options.style {
+        viewer.set_render_style(style);
+    }
+    viewer.settings.ao_strength = options.ao_strength;
+    let state = viewer
+        .display_state(viewer.active_molecule())
+        .ok_or("no display state")?;
+    let mol = viewer.molecule.as_ref().ok_or("no molecule")?;
+    let list = DisplayList::from_molecule(mol, &state);
+
+    let (view, lens) = framing(mol, &list);
+    let size = options.size;
+    raster::render_supersampled(
+        &list,
+        &view,
+        lens,
+        size,
+        size,
+        options.background,
+        options.supersample,
+    )?
+    .write_png(&path.with_extension("png"))?;
+
+    let weight = mol
+        .molecular_weight()
+        .map_or(String::new(), |w| format!("{w:.3}"));
+    Ok(format!("{},{},{weight},", mol.formula(), mol.atoms.len()))
+}
+
+fn main() -> Result<(), String> {
+    let options = parse_args()?;
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(&options.dir)
+        .map_err(|e| format!("{}: {e}", options.dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| SUPPORTED_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+        })
+        .collect();
+    paths.sort();
+
+    let mut csv = String::from("name,formula,atoms,molecular_weight,error\n");
+    let mut failed = 0;
+    for path in &paths {
+        let name = path
+            .file_stem()
+            .map_or(String::new(), |s| s.to_string_lossy().into());
+        let row = thumbnail(path, &options).unwrap_or_else(|err| {
+            failed += 1;
+            eprintln!("{}: {err}", path.display());
+            format!(",,,{}", csv_field(&err))
+        });
+        let _ = writeln!(csv, "{},{row}", csv_field(&name));
+    }
+    let csv_path = options.dir.join("thumbnails.csv");
+    std::fs::write(&csv_path, csv).map_err(|e| e.to_string())?;
+    println!(
+        "{} of {} files rendered; summary in {}",
+        paths.len() - failed,
+        paths.len(),
+        csv_path.display()
+    );
+    Ok(())
+}
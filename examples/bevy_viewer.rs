@@ -0,0 +1,76 @@
+//! Benzene in a Bevy window: `cargo run --example bevy_viewer --features bevy`.
+//! Click atoms and bonds to print them; the arrow keys orbit.
+
+use bevy::core_pipeline::tonemapping::Tonemapping;
+use bevy::prelude::*;
+use moleucle_3dview_rs::bevy_adapter::{MoleculeCamera, MoleculePicked, MoleculePlugin};
+use moleucle_3dview_rs::viewer::ViewerEvent;
+use moleucle_3dview_rs::{Camera as _, Molecule, MoleculeViewer, SelectedAtomRender};
+use std::path::Path;
+
+type Viewer = MoleculeViewer<SelectedAtomRender>;
+
+fn main() {
+    let mut viewer = Viewer::new();
+    match Molecule::from_mol2(Path::new("Benzene.mol2")) {
+        Ok(mol) => viewer.set_molecule(mol),
+        Err(e) => eprintln!("Failed to parse Benzene.mol2: {e}"),
+    }
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            MoleculePlugin::<SelectedAtomRender>::default(),
+        ))
+        .insert_non_send_resource(viewer)
+        .add_systems(Startup, setup)
+        .add_systems(Update, (orbit, report))
+        .run();
+}
+
+/// The plugin spawns the molecule itself, and re-spawns it after changes.
+fn setup(mut commands: Commands, viewer: NonSend<Viewer>) {
+    let mut camera = MoleculeCamera::default();
+    viewer.fit_camera_to_molecule(&mut camera.0);
+    commands.spawn((
+        Camera3dBundle {
+            // The default needs Bevy's `tonemapping_luts` feature.
+            tonemapping: Tonemapping::Reinhard,
+            ..default()
+        },
+        camera,
+    ));
+    commands.spawn(DirectionalLightBundle {
+        transform: Transform::from_xyz(-3.0, 5.0, 8.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+}
+
+fn orbit(keys: Res<ButtonInput<KeyCode>>, mut cameras: Query<&mut MoleculeCamera>) {
+    let step = |minus, plus| (keys.pressed(plus) as i8 - keys.pressed(minus) as i8) as f32 * 2.0;
+    let (dx, dy) = (
+        step(KeyCode::ArrowLeft, KeyCode::ArrowRight),
+        step(KeyCode::ArrowUp, KeyCode::ArrowDown),
+    );
+    if dx != 0.0 || dy != 0.0 {
+        for mut camera in &mut cameras {
+            camera.orbit(dx, dy);
+        }
+    }
+}
+
+fn report(mut picks: EventReader<MoleculePicked>, mut viewer: NonSendMut<Viewer>) {
+    for MoleculePicked(event) in picks.read() {
+        let Some(mol) = viewer.molecule.as_ref() else {
+            continue;
+        };
+        match event {
+            ViewerEvent::AtomClicked(i) => println!("Atom {i}: {}", mol.atoms[*i].element),
+            ViewerEvent::BondClicked(i) => {
+                let bond = &mol.bonds[*i];
+                println!("Bond {i}: atoms {}-{}", bond.atom_a, bond.atom_b);
+            }
+            _ => {}
+        }
+        viewer.handle_pick(event);
+    }
+}
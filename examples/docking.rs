@@ -0,0 +1,144 @@
+use graphics::winit::event::{ElementState, WindowEvent};
+use graphics::winit::keyboard::{KeyCode, PhysicalKey};
+use graphics::{run, EngineUpdates, GraphicsSettings, Scene, UiSettings};
+use moleucle_3dview_rs::molecule::{Atom, Bond};
+use moleucle_3dview_rs::{
+    camera, viewer::ViewerEvent, CameraController, Molecule, MoleculeViewer, TransformGizmo,
+    TransformGizmoRender,
+};
+use nalgebra::Point3;
+
+const GIZMO_SIZE: f32 = 2.5;
+
+fn atom(element: &str, x: f32, y: f32, z: f32) -> Atom {
+    Atom {
+        position: Point3::new(x, y, z),
+        element: element.to_string(),
+        id: 0,
+    }
+}
+
+fn bond(atom_a: usize, atom_b: usize, order: u8) -> Bond {
+    Bond {
+        atom_a,
+        atom_b,
+        order,
+    }
+}
+
+/// Water, centered at `x`.
+fn water(x: f32) -> Molecule {
+    Molecule {
+        atoms: vec![
+            atom("O", x, 0.0, 0.0),
+            atom("H", x + 0.76, 0.59, 0.0),
+            atom("H", x - 0.76, 0.59, 0.0),
+        ],
+        bonds: vec![bond(0, 1, 1), bond(0, 2, 1)],
+        ..Default::default()
+    }
+}
+
+/// Formaldehyde, centered at `x`.
+fn formaldehyde(x: f32) -> Molecule {
+    Molecule {
+        atoms: vec![
+            atom("C", x, 0.0, 0.0),
+            atom("O", x, 1.21, 0.0),
+            atom("H", x + 0.94, -0.54, 0.0),
+            atom("H", x - 0.94, -0.54, 0.0),
+        ],
+        bonds: vec![bond(0, 1, 2), bond(0, 2, 1), bond(0, 3, 1)],
+        ..Default::default()
+    }
+}
+
+/// Gizmo on the active molecule.
+fn gizmo_for(viewer: &MoleculeViewer<TransformGizmoRender>) -> Option<TransformGizmo> {
+    let mol = viewer.molecule.as_ref()?;
+    let positions: Vec<_> = mol.atoms.iter().map(|a| a.position).collect();
+    TransformGizmo::at_centroid(&positions, GIZMO_SIZE)
+}
+
+fn main() {
+    let mut viewer = MoleculeViewer::<TransformGizmoRender>::new();
+    viewer.add_molecule(water(-3.0));
+    viewer.add_molecule(formaldehyde(3.0));
+    viewer.additional_render = Some(Box::new(TransformGizmoRender::new()));
+
+    let mut controller = CameraController::<camera::OrbitalCamera>::new();
+    controller.gizmo = gizmo_for(&viewer);
+
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+
+    run(
+        (viewer, controller),
+        scene,
+        UiSettings::default(),
+        GraphicsSettings::default(),
+        // Render Handler
+        |(viewer, controller), scene, dt| {
+            let mut updates = EngineUpdates::default();
+            controller.tick(dt);
+
+            // Keep the drawn gizmo in step with the controller's.
+            if let Some(render) = &mut viewer.additional_render {
+                let highlight = controller.gizmo_drag_handle();
+                if render.gizmo != controller.gizmo || render.highlight != highlight {
+                    render.gizmo = controller.gizmo;
+                    render.highlight = highlight;
+                    viewer.mark_overlays_dirty();
+                }
+            }
+
+            let changed = viewer.update_scene(scene);
+            updates.meshes = changed.meshes;
+            updates.entities = changed.entities;
+
+            controller.update_scene_camera(scene);
+            updates.camera = true;
+
+            updates
+        },
+        // Device Event Handler
+        |_state, _event, _scene, _is_synthetic, _dt| EngineUpdates::default(),
+        // Window Event Handler
+        |(viewer, controller), event, scene, _dt| {
+            if let WindowEvent::KeyboardInput { event: key, .. } = &event {
+                if key.state == ElementState::Pressed
+                    && key.physical_key == PhysicalKey::Code(KeyCode::Tab)
+                {
+                    let next = (viewer.active_molecule() + 1) % viewer.molecule_count();
+                    viewer.set_active_molecule(next);
+                    controller.gizmo = gizmo_for(viewer);
+                }
+            }
+
+            let (event, updates) = controller.handle_event(&event, scene, viewer);
+            if let Some(ViewerEvent::GizmoDragged(motion)) = event {
+                viewer.transform_molecule(&motion);
+            }
+            updates
+        },
+        // GUI Handler
+        |(viewer, controller), ctx, _scene| {
+            egui::Window::new("Docking").show(ctx, |ui| {
+                ui.label(format!(
+                    "Moving molecule {} of {}",
+                    viewer.active_molecule() + 1,
+                    viewer.molecule_count()
+                ));
+                ui.separator();
+                ui.label("Tab: next molecule");
+                ui.label("Left Drag arrow: translate");
+                ui.label("Left Drag ring: rotate");
+                ui.label("Right Drag: Orbit");
+
+                ui.separator();
+                controller.camera_ui(ui);
+            });
+            EngineUpdates::default()
+        },
+    );
+}
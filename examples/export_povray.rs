@@ -0,0 +1,130 @@
+//! Writes a molecule's display list as JSON and as a POV-Ray scene.
+//!
+//! cargo run --example export_povray -- [input.mol2] [output.pov]
+//! povray +W800 +H600 +A output.pov
+
+use graphics::Scene;
+use moleucle_3dview_rs::display_list::{Cylinder, Sphere};
+use moleucle_3dview_rs::{DisplayList, Molecule, MoleculeViewer, SelectedAtomRender, ViewBookmark};
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+const ASPECT: f32 = 800.0 / 600.0;
+const FOV_Y: f32 = 45.0 * std::f32::consts::PI / 180.0;
+
+/// A view from +Z that fits every sphere of `list` in `FOV_Y`.
+fn framing(list: &DisplayList) -> ViewBookmark {
+    let n = list.spheres.len().max(1) as f32;
+    let sum = list
+        .spheres
+        .iter()
+        .fold(Vector3::zeros(), |sum, s| sum + s.center.coords);
+    let center = Point3::from(sum / n);
+    let extent = list
+        .spheres
+        .iter()
+        .map(|s| (s.center - center).norm() + s.radius)
+        .fold(1.0, f32::max);
+    ViewBookmark {
+        center,
+        rotation: UnitQuaternion::identity(),
+        radius: extent / (FOV_Y * 0.5).sin(),
+    }
+}
+
+fn vector(p: impl Into<[f32; 3]>) -> String {
+    let [x, y, z] = p.into();
+    format!("<{x}, {y}, {z}>")
+}
+
+fn texture(color: (f32, f32, f32), opacity: f32, shininess: f32) -> String {
+    let (r, g, b) = color;
+    format!(
+        "texture {{ pigment {{ color rgbt <{r}, {g}, {b}, {}> }} finish {{ phong {shininess} }} }}",
+        1.0 - opacity
+    )
+}
+
+/// POV-Ray is left-handed; the negative `right` vector flips it to match the
+/// viewer's right-handed coordinates.
+fn camera_block(view: &ViewBookmark) -> String {
+    let angle = 2.0 * ((FOV_Y * 0.5).tan() * ASPECT).atan();
+    format!(
+        "camera {{\n  perspective\n  location {}\n  sky {}\n  up y\n  right -x*{ASPECT}\n  angle {}\n  look_at {}\n}}\n",
+        vector(view.position()),
+        vector(view.up()),
+        angle.to_degrees(),
+        vector(view.center),
+    )
+}
+
+fn to_povray(list: &DisplayList, view: &ViewBookmark) -> String {
+    let mut pov = String::from("#version 3.7;\nglobal_settings { assumed_gamma 1.0 }\n");
+    pov.push_str("background { color rgb <1, 1, 1> }\n");
+    pov.push_str(&camera_block(view));
+    let _ = writeln!(
+        pov,
+        "light_source {{ {} color rgb <1, 1, 1> }}",
+        vector(view.position())
+    );
+    for Sphere {
+        center,
+        radius,
+        color,
+        opacity,
+        shininess,
+    } in &list.spheres
+    {
+        let _ = writeln!(
+            pov,
+            "sphere {{ {}, {radius} {} }}",
+            vector(*center),
+            texture(*color, *opacity, *shininess)
+        );
+    }
+    for Cylinder {
+        start,
+        end,
+        radius,
+        color,
+        opacity,
+        shininess,
+    } in &list.cylinders
+    {
+        let _ = writeln!(
+            pov,
+            "cylinder {{ {}, {}, {radius} open {} }}",
+            vector(*start),
+            vector(*end),
+            texture(*color, *opacity, *shininess)
+        );
+    }
+    pov
+}
+
+fn main() -> Result<(), String> {
+    let mut args = std::env::args().skip(1);
+    let input = args.next().unwrap_or_else(|| "Benzene.mol2".to_string());
+    let output = PathBuf::from(args.next().unwrap_or_else(|| "molecule.pov".to_string()));
+
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(Molecule::from_mol2(Path::new(&input))?);
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+
+    // Round-trip through JSON, as a separate pipeline stage would.
+    let json = output.with_extension("json");
+    DisplayList::from_scene(&viewer, &scene).to_json(&json)?;
+    let list = DisplayList::from_json(&json)?;
+
+    std::fs::write(&output, to_povray(&list, &framing(&list))).map_err(|e| e.to_string())?;
+    println!(
+        "{} spheres, {} cylinders -> {} and {}",
+        list.spheres.len(),
+        list.cylinders.len(),
+        json.display(),
+        output.display()
+    );
+    Ok(())
+}
@@ -1,26 +1,35 @@
 use graphics::{run, EngineUpdates, EntityUpdate, GraphicsSettings, Scene, UiSettings};
 use moleucle_3dview_rs::{
-    camera, viewer::ViewerEvent, Camera, CameraController, Molecule, MoleculeViewer,
-    SelectedAtomRender,
+    camera, viewer::ViewerEvent, CameraController, CompositionOptions, Cutaway, Molecule,
+    MoleculeViewer, SelectedAtomRender, VisibilityMode,
 };
-use std::path::Path;
+use nalgebra::Point3;
+use std::path::PathBuf;
 
 fn main() {
     // 1. Initialize State
     let mut viewer = MoleculeViewer::new();
-    let controller = CameraController::<camera::OrbitalCamera>::new();
+    let mut controller = CameraController::<camera::OrbitalCamera>::new();
+    controller.settings.drag_atoms = true;
 
-    // Load default molecule
-    let path = Path::new("Benzene.mol2");
+    // Load the molecule given on the command line, or the default one
+    let path = std::env::args_os()
+        .nth(1)
+        .map_or_else(|| PathBuf::from("Benzene.mol2"), PathBuf::from);
     if path.exists() {
-        if let Ok(mol) = Molecule::from_mol2(path) {
-            println!("Loaded molecule with {} atoms", mol.atoms.len());
-            viewer.set_molecule(mol);
-        } else {
-            eprintln!("Failed to parse Benzene.mol2");
+        match Molecule::from_file(&path) {
+            Ok(mol) => {
+                println!("Loaded molecule with {} atoms", mol.atoms.len());
+                viewer.set_molecule(mol);
+            }
+            Err(err) => eprintln!("Failed to parse {}: {err}", path.display()),
         }
     } else {
-        eprintln!("Benzene.mol2 not found at {:?}", std::env::current_dir());
+        eprintln!(
+            "{} not found at {:?}",
+            path.display(),
+            std::env::current_dir()
+        );
     }
 
     viewer.additional_render = Some(Box::new(SelectedAtomRender::new()));
@@ -28,7 +37,6 @@ fn main() {
     // 2. Initialize Scene
     let mut scene = Scene::default();
 
-
     // Initial Mesh Generation
     viewer.update_scene(&mut scene);
 
@@ -41,15 +49,18 @@ fn main() {
         UiSettings::default(),
         GraphicsSettings::default(),
         // Render Handler
-        |(viewer, controller), scene, _dt| {
+        |(viewer, controller), scene, dt| {
             let mut updates = EngineUpdates::default();
-
-            if viewer.dirty {
-                viewer.update_scene(scene);
-                updates.meshes = true;
-                updates.entities = EntityUpdate::All;
+            controller.tick(dt);
+            viewer.apply_auto_fit(controller.camera.as_mut());
+            if let Some(hover) = controller.poll_hover(viewer) {
+                viewer.handle_pick(&hover);
             }
 
+            let changed = viewer.update_scene(scene);
+            updates.meshes = changed.meshes;
+            updates.entities = changed.entities;
+
             // Controller handles camera info generation
             controller.update_scene_camera(scene);
             updates.camera = true;
@@ -60,21 +71,32 @@ fn main() {
         |_state, _event, _scene, _is_synthetic, _dt| EngineUpdates::default(),
         // Window Event Handler
         |(viewer, controller), event, scene, _dt| {
-            let (picked, updates) = controller.handle_event(&event, scene, viewer);
+            let (picked, mut updates) = controller.handle_event(&event, scene, viewer);
 
-            if let Some(event) = picked {
+            if let Some(event) = picked.filter(|e| !(viewer.bond_mode() && viewer.handle_pick(e))) {
+                controller.select_on_click(&event, viewer);
                 match &event {
-                    ViewerEvent::AtomClicked(i) => {
-                        println!("Main Trace: Atom {} Clicked", i);
-                        if let Some(selected_atom) = &mut viewer.additional_render {
-                            selected_atom.toggle_atom(*i);
-                            viewer.dirty = true;
+                    ViewerEvent::AtomClicked(i) => println!("Main Trace: Atom {} Clicked", i),
+                    ViewerEvent::AtomDragged { .. } | ViewerEvent::AtomDragEnded => {
+                        viewer.handle_pick(&event);
+                        if viewer.refresh_drag(scene) {
+                            updates.entities = EntityUpdate::All;
                         }
-
+                    }
+                    ViewerEvent::CutawayChanged(_)
+                    | ViewerEvent::AtomHovered(_)
+                    | ViewerEvent::BondHovered(_)
+                    | ViewerEvent::NothingHovered => {
+                        viewer.handle_pick(&event);
                     }
                     ViewerEvent::BondClicked(i) => println!("Main Trace: Bond {} Clicked", i),
                     ViewerEvent::NothingClicked => println!("Main Trace: Nothing Clicked"),
-
+                    ViewerEvent::EditRequested(action) => {
+                        if let Err(e) = viewer.apply_edit(*action) {
+                            println!("Main Trace: {}", e);
+                        }
+                    }
+                    _ => {}
                 }
             }
 
@@ -84,12 +106,44 @@ fn main() {
             updates
         },
         // GUI Handler
-        |(viewer, _controllers), ctx, _scene| {
+        |(viewer, controller), ctx, _scene| {
+            controller.set_keyboard_captured(ctx.wants_keyboard_input());
+            controller.selection_rect_ui(ctx);
             egui::Window::new("Controls").show(ctx, |ui| {
                 ui.label("Molecule Viewer");
                 if let Some(mol) = &viewer.molecule {
                     ui.label(format!("Atoms: {}", mol.atoms.len()));
                     ui.label(format!("Bonds: {}", mol.bonds.len()));
+                    ui.label(format!(
+                        "Selected: {} atoms, {} bonds",
+                        viewer.selected_atoms().len(),
+                        viewer.selected_bonds().len()
+                    ));
+                }
+                viewer.composition_ui(ui, CompositionOptions::default());
+                let mut bond_mode = viewer.bond_mode();
+                if ui
+                    .checkbox(&mut bond_mode, "Bond mode (click two atoms)")
+                    .changed()
+                {
+                    viewer.set_bond_mode(bond_mode);
+                }
+                let mut cutaway = viewer.cutaway().is_some();
+                if ui.checkbox(&mut cutaway, "Cut-away sphere").changed() {
+                    let cutaway = cutaway.then(|| {
+                        let (min, max) = viewer
+                            .molecule
+                            .as_ref()
+                            .map_or((Point3::origin(), Point3::origin()), |mol| {
+                                mol.frame_bounds()
+                            });
+                        Cutaway {
+                            center: nalgebra::center(&min, &max),
+                            radius: 5.0,
+                            mode: VisibilityMode::Hide,
+                        }
+                    });
+                    viewer.set_cutaway(cutaway);
                 }
 
                 ui.separator();
@@ -97,7 +151,21 @@ fn main() {
                 ui.label("Right Click: Orbit");
                 ui.label("Middle Click: Pan");
                 ui.label("Scroll: Zoom");
+                ui.label("Left Drag: Move Atom (Shift: Whole Fragment)");
+                ui.label("Ctrl + Scroll: Field of View");
+                ui.label("Alt + Left Drag / Alt + Scroll: Move / Resize Cut-away");
+                ui.label("Numpad 5: Perspective / Orthographic");
+                ui.label("F: Frame Molecule");
+                if let Some(axis) = viewer.reference_axis() {
+                    ui.label(format!("Home: {} up", axis.label));
+                }
                 ui.label("Left Click: Select");
+                ui.label("Alt + Left Drag (no cut-away): Box Select");
+                ui.label("Delete: Delete selected, Ctrl + A: Select all, Esc: Clear");
+                ui.label("H / Shift + H: Hide selected / Show all, I: Invert");
+
+                ui.separator();
+                controller.camera_ui(ui);
             });
             EngineUpdates::default()
         },
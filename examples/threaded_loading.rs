@@ -0,0 +1,80 @@
+use graphics::{run, EngineUpdates, GraphicsSettings, Scene, UiSettings};
+use moleucle_3dview_rs::{
+    camera, viewer::ViewerEvent, CameraController, Molecule, MoleculeViewer, SelectedAtomRender,
+    ViewerCommand,
+};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    let mut viewer = MoleculeViewer::new();
+    viewer.additional_render = Some(Box::new(SelectedAtomRender::new()));
+    let controller = CameraController::<camera::OrbitalCamera>::new();
+
+    // Parse on a worker thread; the viewer picks the result up in the render loop.
+    let handle = viewer.handle();
+    thread::spawn(move || {
+        // Stand-in for a slow parse or analysis.
+        thread::sleep(Duration::from_secs(1));
+        match Molecule::from_mol2(Path::new("Benzene.mol2")) {
+            Ok(mol) => {
+                let carbons: Vec<usize> = (0..mol.atoms.len())
+                    .filter(|&i| mol.atoms[i].element == "C")
+                    .collect();
+                let _ = handle.send(ViewerCommand::SetMolecule(mol));
+                let _ = handle.send(ViewerCommand::Select(carbons));
+            }
+            Err(e) => eprintln!("Failed to parse Benzene.mol2: {e}"),
+        }
+    });
+
+    let scene = Scene::default();
+
+    run(
+        (viewer, controller),
+        scene,
+        UiSettings::default(),
+        GraphicsSettings::default(),
+        // Render Handler
+        |(viewer, controller), scene, dt| {
+            let mut updates = EngineUpdates::default();
+            controller.tick(dt);
+
+            viewer.drain_commands();
+            for event in viewer.take_events() {
+                if let ViewerEvent::CommandFailed(reason) = event {
+                    eprintln!("Command failed: {reason}");
+                }
+            }
+
+            let changed = viewer.update_scene(scene);
+            updates.meshes = changed.meshes;
+            updates.entities = changed.entities;
+
+            controller.update_scene_camera(scene);
+            updates.camera = true;
+
+            updates
+        },
+        // Device Event Handler
+        |_state, _event, _scene, _is_synthetic, _dt| EngineUpdates::default(),
+        // Window Event Handler
+        |(viewer, controller), event, scene, _dt| {
+            let (_, updates) = controller.handle_event(&event, scene, viewer);
+            updates
+        },
+        // GUI Handler
+        |(viewer, controller), ctx, _scene| {
+            egui::Window::new("Controls").show(ctx, |ui| {
+                match &viewer.molecule {
+                    Some(mol) => ui.label(format!("Atoms: {}", mol.atoms.len())),
+                    None => ui.label("Loading..."),
+                };
+                ui.separator();
+                controller.camera_ui(ui);
+            });
+            EngineUpdates::default()
+        },
+    );
+}
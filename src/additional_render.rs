@@ -1,77 +1,164 @@
+use crate::camera::UpAxis;
+use crate::colormap::{self, Rgb};
+use crate::contacts::{Contact, ContactKind};
+use crate::controller::{OrbitTrail, PivotIndicator};
+use crate::convert::{quat_to_la, quat_to_na, to_la, to_na, vec_to_la, vec_to_na};
+use crate::coordination::{self, CoordinationInfo};
+use crate::dipole::Dipole;
+use crate::element;
+use crate::gizmo::{GizmoHandle, TransformGizmo};
+use crate::measurement::{Measurement, MeasurementMode, MeasurementTool};
+use crate::mesh;
 use crate::molecule::Molecule;
+use crate::selection::Selection;
+use crate::viewer::ATOM_RADIUS;
 use graphics::{Entity, Mesh, Scene};
 use lin_alg::f32::Quaternion;
 use lin_alg::f32::Vec3;
-
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+use std::collections::VecDeque;
 
 // for adding rendering works to MoleculeViewer.
 pub trait AdditionalRender {
+    /// Pushes this renderer's meshes and entities after the viewer's own.
+    /// `MoleculeViewer::update_scene` clears the scene before every rebuild,
+    /// so meshes are pushed on each call rather than cached by index.
     fn update_scene(&self, scene: &mut Scene, molecule: &Molecule);
+
+    /// Updates only this renderer's entities, without a full rebuild
+    /// (see `MoleculeViewer::refresh_overlays`). They start at
+    /// `scene.entities[first_entity]` and run to the end of the list; they may be
+    /// edited in place or truncated and re-pushed, but no meshes may be added.
+    fn refresh(&self, _scene: &mut Scene, _molecule: &Molecule, _first_entity: usize) {}
+
+    /// The selection this renderer shows, if any; the viewer copies its own
+    /// selection here whenever that changes.
+    fn selection_mut(&mut self) -> Option<&mut Selection> {
+        None
+    }
+
+    /// The coordination sphere this renderer shows, if it shows one;
+    /// `MoleculeViewer::handle_pick` fills it when a metal is clicked.
+    fn coordination_mut(&mut self) -> Option<&mut Option<CoordinationInfo>> {
+        None
+    }
+
+    /// The measurement tool this renderer shows, if it is one;
+    /// `MoleculeViewer::handle_pick` gives it atom clicks.
+    fn measurement_mut(&mut self) -> Option<&mut MeasurementTool> {
+        None
+    }
+
+    /// Called by `MoleculeViewer::set_frame` with the (possibly fractional)
+    /// trajectory frame now shown and the molecule at its positions.
+    fn trajectory_frame(&mut self, _frame: f32, _molecule: &Molecule) {}
+
+    /// Called when the trajectory is set or cleared, or the molecule replaced.
+    fn trajectory_reset(&mut self) {}
 }
 
+/// Shininess of `SelectedAtomRender` shells, well above the atoms' so the
+/// shells look glassy.
+const HIGHLIGHT_SHININESS: f32 = 0.9;
+
+/// Highlights selected atoms with translucent shells.
+///
+/// Each selected atom gets a sphere `highlight_margin` Å larger than its van
+/// der Waals radius, drawn in `highlight_color` with the alpha as opacity.
+/// All shells share one mesh.
+///
+/// The viewer keeps `selection` in step with its own (see
+/// `MoleculeViewer::select_atom`) whenever that changes. Atoms added here
+/// directly show too, until the viewer's selection next changes.
+///
+/// The viewer also draws its own selection shells, at each atom's drawn
+/// radius (`radii_table` or `element_scale`). A selection change redraws
+/// those shells and this overlay without rebuilding the molecule (see
+/// `DirtyFlags::selection`).
 #[derive(Clone)]
 pub struct SelectedAtomRender {
-    pub selected_atoms: Vec<usize>,
-    pub color: [f32; 3],
+    pub selection: Selection,
+    /// RGBA; alpha is the shell's opacity.
+    pub highlight_color: [f32; 4],
+    /// How far the shell reaches past the atom's van der Waals radius, in Å.
+    pub highlight_margin: f32,
 }
 
 impl SelectedAtomRender {
     pub fn new() -> Self {
         Self {
-            selected_atoms: Vec::new(),
-            color: [1.0, 0.0, 0.0],
+            selection: Selection::new(),
+            highlight_color: [1.0, 0.0, 0.0, 0.35],
+            highlight_margin: 0.15,
         }
     }
 }
 
+impl Default for SelectedAtomRender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AdditionalRender for SelectedAtomRender {
+    fn selection_mut(&mut self) -> Option<&mut Selection> {
+        Some(&mut self.selection)
+    }
+
     fn update_scene(&self, scene: &mut Scene, molecule: &Molecule) {
-        for atom_idx in self.selected_atoms.iter() {
-            let atom = molecule.atoms.get(*atom_idx).unwrap();
-            let pos = Vec3::new(atom.position.x, atom.position.y, atom.position.z);
-            let radius = 0.4 + 0.2;
-            let color = self.color;
-            let cyl_mesh = Mesh::new_cylinder(1.0, 1.0, 10);
-            let cyl_idx = scene.meshes.len();
-            scene.meshes.push(cyl_mesh);
-            scene.entities.push(Entity::new(
-                cyl_idx,
-                pos,
+        if self.selection.atoms().is_empty() {
+            return;
+        }
+        // One shared shell mesh for all selected atoms.
+        let shell_idx = scene.meshes.len();
+        scene.meshes.push(Mesh::new_sphere(1.0, 3));
+
+        let [r, g, b, alpha] = self.highlight_color;
+        for atom_idx in self.selection.atoms() {
+            let Some(atom) = molecule.atoms.get(*atom_idx) else {
+                continue;
+            };
+            let mut entity = Entity::new(
+                shell_idx,
+                to_la(atom.position),
                 Quaternion::new_identity(),
-                radius,
-                (color[0], color[1], color[2]),
-                0.2,
-            ));
+                element::vdw_radius(&atom.element) + self.highlight_margin,
+                (r, g, b),
+                HIGHLIGHT_SHININESS,
+            );
+            entity.opacity = alpha;
+            scene.entities.push(entity);
         }
     }
-
 }
 
-
 impl SelectedAtomRender {
+    pub fn selected_atoms(&self) -> &[usize] {
+        self.selection.atoms()
+    }
+
     pub fn add_atom(&mut self, atom_idx: usize) {
-        self.selected_atoms.push(atom_idx);
+        self.selection.insert(atom_idx);
     }
 
     pub fn remove_atom(&mut self, atom_idx: usize) {
-        self.selected_atoms.retain(|&x| x != atom_idx);
+        self.selection.remove(atom_idx);
     }
 
     pub fn toggle_atom(&mut self, atom_idx: usize) {
-        if self.selected_atoms.contains(&atom_idx) {
-            self.remove_atom(atom_idx);
-        } else {
-            self.add_atom(atom_idx);
-        }
+        self.selection.toggle(atom_idx);
     }
 }
 
+/// Length of the line `DebugRender` draws, in Å.
+const DEBUG_RAY_LENGTH: f32 = 100.0;
 
+/// Draws a picking ray, such as `CameraController::ray_from_last_mouse`, as
+/// a thin green line from its origin along its direction.
 pub struct DebugRender {
     pub ray: (Vec3, Vec3),
-   
 }
-    
+
 impl DebugRender {
     pub fn new(ray: (Vec3, Vec3)) -> Self {
         Self { ray }
@@ -79,24 +166,17 @@ impl DebugRender {
 }
 
 impl AdditionalRender for DebugRender {
-    fn update_scene(&self, _scene: &mut Scene, _molecule: &Molecule) {
-        // For debugging purposes, we can add some simple geometry or text here.
-        // For example, we could render the coordinate axes or display some text info.
-        
-        // draw ray
-        let (origin, direction) = self.ray;
-        let ray_mesh = Mesh::new_cylinder(0.05, 1.0, 10);
-        let ray_idx = _scene.meshes.len();
-        _scene.meshes.push(ray_mesh);
-        _scene.entities.push(Entity::new(
-            ray_idx,
-            origin,
-            Quaternion::new_identity(),
-            1.0,
-            (0.0, 1.0, 0.0),
-            0.2,
-        ));
-
+    fn update_scene(&self, scene: &mut Scene, _molecule: &Molecule) {
+        let origin = to_na(self.ray.0);
+        let Some(direction) = vec_to_na(self.ray.1).try_normalize(1e-6) else {
+            return;
+        };
+        let ray_idx = scene.meshes.len();
+        scene.meshes.push(Mesh::new_cylinder(1.0, 1.0, 10));
+        let end = origin + direction * DEBUG_RAY_LENGTH;
+        if let Some(entity) = segment_entity(ray_idx, origin, end, 0.05, (0.0, 1.0, 0.0)) {
+            scene.entities.push(entity);
+        }
     }
 }
 
@@ -104,4 +184,1259 @@ impl DebugRender {
     pub fn update_ray(&mut self, ray: (Vec3, Vec3)) {
         self.ray = ray;
     }
-}   
\ No newline at end of file
+}
+
+/// A flat neutral disc just below the molecule, giving a grounded,
+/// pseudo-shadow look in figures without shadow mapping.
+///
+/// Sized from the molecule's footprint across `up_axis` on every rebuild.
+/// Like every overlay it is ignored by picking, and it does not affect camera
+/// framing.
+#[derive(Clone)]
+pub struct GroundDiscRender {
+    /// Turn off for renders that should not show the disc.
+    pub enabled: bool,
+    /// The disc lies below the molecule along this axis.
+    pub up_axis: UpAxis,
+    pub color: [f32; 3],
+    /// Margin around the atoms' footprint, in Å.
+    pub padding: f32,
+    /// Gap between the lowest atom surface and the disc, in Å.
+    pub gap: f32,
+    pub thickness: f32,
+}
+
+impl GroundDiscRender {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            up_axis: UpAxis::Y,
+            color: [0.55, 0.55, 0.55],
+            padding: 2.0,
+            gap: 0.5,
+            thickness: 0.05,
+        }
+    }
+
+    /// Disc center (top face) and radius for `molecule`, or `None` if it has no atoms.
+    pub fn disc(&self, molecule: &Molecule) -> Option<(Point3<f32>, f32)> {
+        // Work in Y-up coordinates and map the center back.
+        let basis = self.up_axis.basis();
+        let positions: Vec<_> = molecule
+            .atoms
+            .iter()
+            .map(|a| basis.inverse_transform_point(&a.position))
+            .collect();
+        let first = *positions.first()?;
+        let (mut min, mut max) = (first, first);
+        for p in &positions {
+            min = min.inf(p);
+            max = max.sup(p);
+        }
+        let center = Point3::new((min.x + max.x) * 0.5, 0.0, (min.z + max.z) * 0.5);
+        let footprint = positions
+            .iter()
+            .map(|p| (p.x - center.x).hypot(p.z - center.z))
+            .fold(0.0, f32::max);
+        let y = min.y - ATOM_RADIUS - self.gap;
+        Some((
+            basis * Point3::new(center.x, y, center.z),
+            footprint + ATOM_RADIUS + self.padding,
+        ))
+    }
+}
+
+impl Default for GroundDiscRender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AdditionalRender for GroundDiscRender {
+    fn update_scene(&self, scene: &mut Scene, molecule: &Molecule) {
+        if !self.enabled {
+            return;
+        }
+        let Some((top, radius)) = self.disc(molecule) else {
+            return;
+        };
+        let disc_idx = scene.meshes.len();
+        scene.meshes.push(Mesh::new_cylinder(1.0, 1.0, 48));
+        let center = top - self.up_axis.up() * (self.thickness * 0.5);
+        let mut entity = Entity::new(
+            disc_idx,
+            to_la(center),
+            quat_to_la(self.up_axis.basis()),
+            1.0,
+            (self.color[0], self.color[1], self.color[2]),
+            0.0,
+        );
+        entity.scale_partial = Some(Vec3::new(radius, self.thickness, radius));
+        scene.entities.push(entity);
+    }
+}
+
+/// Draws a `TransformGizmo`: an arrow along each axis and a ring around it,
+/// x red, y green and z blue, with the `highlight`ed handle in yellow.
+#[derive(Clone)]
+pub struct TransformGizmoRender {
+    pub gizmo: Option<TransformGizmo>,
+    pub highlight: Option<GizmoHandle>,
+    /// Shaft and ring radius as a fraction of the gizmo size; arrow heads are
+    /// three times as thick.
+    pub thickness: f32,
+}
+
+impl TransformGizmoRender {
+    pub fn new() -> Self {
+        Self {
+            gizmo: None,
+            highlight: None,
+            thickness: 0.02,
+        }
+    }
+
+    fn color(&self, handle: GizmoHandle) -> (f32, f32, f32) {
+        if self.highlight == Some(handle) {
+            return (1.0, 0.9, 0.1);
+        }
+        match handle.axis().iamax() {
+            0 => (0.9, 0.2, 0.2),
+            1 => (0.2, 0.8, 0.2),
+            _ => (0.2, 0.4, 0.9),
+        }
+    }
+}
+
+impl Default for TransformGizmoRender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AdditionalRender for TransformGizmoRender {
+    fn update_scene(&self, scene: &mut Scene, _molecule: &Molecule) {
+        let Some(gizmo) = &self.gizmo else {
+            return;
+        };
+        let mesh_idx = scene.meshes.len();
+        scene.meshes.push(Mesh::new_cylinder(1.0, 1.0, 8));
+        let radius = gizmo.size * self.thickness;
+        for handle in TransformGizmo::handles() {
+            let color = self.color(handle);
+            let mut segments: Vec<_> = gizmo
+                .segments(handle)
+                .into_iter()
+                .map(|(a, b)| (a, b, radius))
+                .collect();
+            if let GizmoHandle::Translate(_) = handle {
+                let tip = gizmo.center + handle.axis() * gizmo.size;
+                segments.push(arrow_head(gizmo.center, tip, radius));
+            }
+            for (a, b, r) in segments {
+                if let Some(entity) = segment_entity(mesh_idx, a, b, r, color) {
+                    scene.entities.push(entity);
+                }
+            }
+        }
+    }
+}
+
+/// Shows how far the camera has orbited: a faint arc around the pivot from
+/// the drag's start direction to the current one, with a tick every 15°.
+/// The arc follows the shortest rotation between the two directions.
+///
+/// Feed it `CameraController::orbit_trail` each frame, then call
+/// `MoleculeViewer::refresh_overlays`; the molecule is not rebuilt. Sizes are
+/// fractions of the camera distance, so the arc looks the same at any zoom.
+#[derive(Clone)]
+pub struct OrbitTrailRender {
+    pub trail: Option<OrbitTrail>,
+    pub color: [f32; 3],
+    /// Opacity while dragging; it fades out with the trail after.
+    pub opacity: f32,
+    /// Arc radius as a fraction of the camera distance.
+    pub radius_fraction: f32,
+}
+
+impl OrbitTrailRender {
+    /// Angle between ticks, in degrees.
+    pub const TICK_DEG: f32 = 15.0;
+
+    pub fn new() -> Self {
+        Self {
+            trail: None,
+            color: [0.9, 0.9, 0.6],
+            opacity: 0.35,
+            radius_fraction: 0.25,
+        }
+    }
+
+    fn push_entities(&self, scene: &mut Scene, mesh_idx: usize) {
+        let Some(trail) = &self.trail else {
+            return;
+        };
+        let angle = trail.angle();
+        if angle < 1e-3 {
+            return;
+        }
+        let axis = trail
+            .start
+            .cross(&trail.current)
+            .try_normalize(1e-6)
+            .or_else(|| trail.start.cross(&Vector3::x()).try_normalize(1e-6))
+            .unwrap_or_else(Vector3::y);
+        let radius = trail.distance * self.radius_fraction;
+        let thickness = radius * 0.015;
+        let direction = |a: f32| UnitQuaternion::from_scaled_axis(axis * a) * trail.start;
+
+        // One segment per 5° of arc.
+        let steps = (angle / 5f32.to_radians()).ceil() as usize;
+        let arc: Vec<_> = (0..=steps)
+            .map(|k| trail.pivot + direction(angle * k as f32 / steps as f32) * radius)
+            .collect();
+        let mut segments: Vec<_> = arc.windows(2).map(|w| (w[0], w[1])).collect();
+        let ticks = (angle.to_degrees() / Self::TICK_DEG).floor() as usize;
+        for k in 0..=ticks {
+            let dir = direction((k as f32 * Self::TICK_DEG).to_radians());
+            segments.push((
+                trail.pivot + dir * (radius * 0.94),
+                trail.pivot + dir * (radius * 1.06),
+            ));
+        }
+
+        let color = (self.color[0], self.color[1], self.color[2]);
+        let opacity = self.opacity * trail.fade.clamp(0.0, 1.0);
+        for (a, b) in segments {
+            if let Some(mut entity) = segment_entity(mesh_idx, a, b, thickness, color) {
+                entity.opacity = opacity;
+                scene.entities.push(entity);
+            }
+        }
+    }
+}
+
+impl Default for OrbitTrailRender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AdditionalRender for OrbitTrailRender {
+    fn update_scene(&self, scene: &mut Scene, _molecule: &Molecule) {
+        // Pushed even without a trail, so `refresh` has a mesh to draw with.
+        let mesh_idx = scene.meshes.len();
+        scene.meshes.push(Mesh::new_cylinder(1.0, 1.0, 8));
+        self.push_entities(scene, mesh_idx);
+    }
+
+    fn refresh(&self, scene: &mut Scene, _molecule: &Molecule, first_entity: usize) {
+        // Overlay meshes come last, and this one is the only one.
+        let Some(mesh_idx) = scene.meshes.len().checked_sub(1) else {
+            return;
+        };
+        scene.entities.truncate(first_entity);
+        self.push_entities(scene, mesh_idx);
+    }
+}
+
+/// Marks the point the camera orbits about with a small three-axis cross,
+/// so it is clear what a drag will rotate around.
+///
+/// Feed it `CameraController::pivot_indicator` each frame, then call
+/// `MoleculeViewer::refresh_overlays`; the molecule is not rebuilt. Like
+/// every overlay it cannot be picked and does not count towards framing.
+#[derive(Clone)]
+pub struct PivotRender {
+    pub indicator: Option<PivotIndicator>,
+    pub color: [f32; 3],
+    /// Opacity while orbiting; it fades out with the indicator after.
+    pub opacity: f32,
+    /// Half the length of each arm as a fraction of the camera distance.
+    pub size_fraction: f32,
+}
+
+impl PivotRender {
+    pub fn new() -> Self {
+        Self {
+            indicator: None,
+            color: [1.0, 1.0, 1.0],
+            opacity: 0.6,
+            size_fraction: 0.015,
+        }
+    }
+
+    fn push_entities(&self, scene: &mut Scene, mesh_idx: usize) {
+        let Some(indicator) = &self.indicator else {
+            return;
+        };
+        let size = indicator.distance * self.size_fraction;
+        let color = (self.color[0], self.color[1], self.color[2]);
+        let opacity = self.opacity * indicator.fade.clamp(0.0, 1.0);
+        for axis in [Vector3::x(), Vector3::y(), Vector3::z()] {
+            let (a, b) = (indicator.pivot - axis * size, indicator.pivot + axis * size);
+            if let Some(mut entity) = segment_entity(mesh_idx, a, b, size * 0.08, color) {
+                entity.opacity = opacity;
+                scene.entities.push(entity);
+            }
+        }
+    }
+}
+
+impl Default for PivotRender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AdditionalRender for PivotRender {
+    fn update_scene(&self, scene: &mut Scene, _molecule: &Molecule) {
+        // Pushed even without an indicator, so `refresh` has a mesh to draw
+        // with.
+        let mesh_idx = scene.meshes.len();
+        scene.meshes.push(Mesh::new_cylinder(1.0, 1.0, 8));
+        self.push_entities(scene, mesh_idx);
+    }
+
+    fn refresh(&self, scene: &mut Scene, _molecule: &Molecule, first_entity: usize) {
+        // Overlay meshes come last, and this one is the only one.
+        let Some(mesh_idx) = scene.meshes.len().checked_sub(1) else {
+            return;
+        };
+        scene.entities.truncate(first_entity);
+        self.push_entities(scene, mesh_idx);
+    }
+}
+
+/// How `TrailRender` colors a trail along its length.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrailColor {
+    Solid(Rgb),
+    /// `colormap::rainbow` from the oldest recorded frame (blue) to the
+    /// newest (red).
+    ByFrame,
+    /// `colormap::rainbow` of a per-frame scalar such as the energy, indexed
+    /// by trajectory frame and interpolated between frames, over its range.
+    ByValue(Vec<f32>),
+}
+
+/// Draws where atoms have been during trajectory playback: a polyline of
+/// thin cylinders through each selected atom's positions over the last
+/// `history` frames shown.
+///
+/// The viewer feeds it from `MoleculeViewer::set_frame`. Only positions of
+/// the selected atoms are kept, at most `history` frames of them. The history
+/// starts over when the trajectory is set or cleared, when playback steps
+/// backwards, and when the selection changes. `refresh` redraws just the
+/// trails, e.g. after changing `color`, via `MoleculeViewer::refresh_overlays`.
+#[derive(Clone)]
+pub struct TrailRender {
+    pub selection: Selection,
+    /// Frames of history to keep and draw.
+    pub history: usize,
+    pub radius: f32,
+    pub color: TrailColor,
+    /// Atoms the recorded samples are for.
+    tracked: Vec<usize>,
+    /// Recorded frames, oldest first, with the positions of `tracked`.
+    samples: VecDeque<(f32, Vec<Point3<f32>>)>,
+}
+
+impl TrailRender {
+    pub fn new() -> Self {
+        Self {
+            selection: Selection::new(),
+            history: 50,
+            radius: 0.05,
+            color: TrailColor::ByFrame,
+            tracked: Vec::new(),
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// The recorded trajectory frames, oldest first.
+    pub fn frames(&self) -> impl Iterator<Item = f32> + '_ {
+        self.samples.iter().map(|(frame, _)| *frame)
+    }
+
+    /// The trail of each selected atom, in selection order, oldest first.
+    /// Empty while the recorded atoms differ from the selection.
+    pub fn trails(&self) -> Vec<Vec<Point3<f32>>> {
+        if self.tracked != self.selection.atoms() {
+            return Vec::new();
+        }
+        (0..self.tracked.len())
+            .map(|k| self.samples.iter().map(|(_, p)| p[k]).collect())
+            .collect()
+    }
+
+    fn sample_color(&self, index: usize) -> Rgb {
+        match &self.color {
+            TrailColor::Solid(color) => *color,
+            TrailColor::ByFrame => {
+                let last = self.samples.len().saturating_sub(1).max(1);
+                colormap::rainbow(index as f32 / last as f32)
+            }
+            TrailColor::ByValue(values) => {
+                let (lo, hi) = values
+                    .iter()
+                    .fold((f32::MAX, f32::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+                let frame = self.samples[index].0;
+                let (i, t) = (frame.floor() as usize, frame.fract());
+                let Some(&a) = values.get(i) else {
+                    return colormap::rainbow(0.0);
+                };
+                let value = a + (values.get(i + 1).unwrap_or(&a) - a) * t;
+                colormap::rainbow((value - lo) / (hi - lo).max(f32::EPSILON))
+            }
+        }
+    }
+
+    fn push_entities(&self, scene: &mut Scene, mesh_idx: usize) {
+        for trail in self.trails() {
+            for (i, pair) in trail.windows(2).enumerate() {
+                let color = self.sample_color(i + 1);
+                if let Some(entity) = segment_entity(mesh_idx, pair[0], pair[1], self.radius, color)
+                {
+                    scene.entities.push(entity);
+                }
+            }
+        }
+    }
+}
+
+impl Default for TrailRender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AdditionalRender for TrailRender {
+    fn update_scene(&self, scene: &mut Scene, _molecule: &Molecule) {
+        // Pushed even without a trail, so `refresh` has a mesh to draw with.
+        let mesh_idx = scene.meshes.len();
+        scene.meshes.push(Mesh::new_cylinder(1.0, 1.0, 6));
+        self.push_entities(scene, mesh_idx);
+    }
+
+    fn refresh(&self, scene: &mut Scene, _molecule: &Molecule, first_entity: usize) {
+        let Some(mesh_idx) = scene.meshes.len().checked_sub(1) else {
+            return;
+        };
+        scene.entities.truncate(first_entity);
+        self.push_entities(scene, mesh_idx);
+    }
+
+    fn selection_mut(&mut self) -> Option<&mut Selection> {
+        Some(&mut self.selection)
+    }
+
+    fn trajectory_frame(&mut self, frame: f32, molecule: &Molecule) {
+        let stepped_back = self.samples.back().is_some_and(|&(last, _)| frame < last);
+        if stepped_back || self.tracked != self.selection.atoms() {
+            self.trajectory_reset();
+            self.tracked = self.selection.atoms().to_vec();
+        }
+        if self.samples.back().is_some_and(|&(last, _)| last == frame) {
+            return;
+        }
+        let Some(positions) = self
+            .tracked
+            .iter()
+            .map(|&i| molecule.atoms.get(i).map(|atom| atom.position))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return;
+        };
+        self.samples.push_back((frame, positions));
+        while self.samples.len() > self.history {
+            self.samples.pop_front();
+        }
+    }
+
+    fn trajectory_reset(&mut self) {
+        self.samples.clear();
+    }
+}
+
+/// Draws intermolecular contacts as dashed connectors colored by `ContactKind`.
+///
+/// Endpoints are captured from both molecules at construction, so the drawing
+/// does not depend on which molecule the viewer is showing.
+#[derive(Clone)]
+pub struct ContactRender {
+    segments: Vec<(Point3<f32>, Point3<f32>, ContactKind)>,
+    pub radius: f32,
+    pub dash_length: f32,
+    pub gap_length: f32,
+}
+
+impl ContactRender {
+    /// `contacts` as returned by `contacts_between(mol_a, mol_b, ..)`.
+    pub fn new(mol_a: &Molecule, mol_b: &Molecule, contacts: &[Contact]) -> Self {
+        let segments = contacts
+            .iter()
+            .filter_map(|c| {
+                let a = mol_a.atoms.get(c.atom_a)?.position;
+                let b = mol_b.atoms.get(c.atom_b)?.position;
+                Some((a, b, c.kind))
+            })
+            .collect();
+        Self {
+            segments,
+            radius: 0.04,
+            dash_length: 0.25,
+            gap_length: 0.15,
+        }
+    }
+
+    pub fn color(kind: ContactKind) -> (f32, f32, f32) {
+        match kind {
+            ContactKind::Hydrophobic => (0.6, 0.6, 0.3),
+            ContactKind::Polar => (0.8, 0.4, 0.8),
+            ContactKind::HBond => (0.2, 0.7, 1.0),
+            ContactKind::Clash => (1.0, 0.1, 0.1),
+        }
+    }
+}
+
+impl AdditionalRender for ContactRender {
+    fn update_scene(&self, scene: &mut Scene, _molecule: &Molecule) {
+        if self.segments.is_empty() {
+            return;
+        }
+        let dash_idx = scene.meshes.len();
+        scene.meshes.push(Mesh::new_cylinder(1.0, 1.0, 8));
+        for (a, b, kind) in &self.segments {
+            for (start, end) in dashes(*a, *b, self.dash_length, self.gap_length) {
+                if let Some(entity) =
+                    segment_entity(dash_idx, start, end, self.radius, Self::color(*kind))
+                {
+                    scene.entities.push(entity);
+                }
+            }
+        }
+    }
+}
+
+/// Draws each measurement as a dashed line between its atoms, read from the
+/// molecule on each build so the lines follow trajectory frames.
+/// Scene meshes `MeasurementTool` draws with.
+#[derive(Debug, Clone, Copy, Default)]
+struct MeasurementMeshes {
+    dash: usize,
+    /// The first of the glyphs in `GLYPH_CHARS` order.
+    first_glyph: usize,
+    plane: usize,
+    mark: usize,
+}
+
+/// Radius of the marks on a dihedral's atoms, as a multiple of `ATOM_RADIUS`.
+const DIHEDRAL_MARK_SCALE: f32 = 1.2;
+
+impl MeasurementTool {
+    /// The dashes, arcs, dihedral planes and marks, then the value labels, in
+    /// push order. Labels face the scene's camera.
+    fn entities(
+        &self,
+        scene: &Scene,
+        molecule: &Molecule,
+        meshes: MeasurementMeshes,
+    ) -> Vec<Entity> {
+        let frame = LabelFrame::new(scene);
+        let mut lines = Vec::new();
+        let mut labels = Vec::new();
+        for measurement in self.measurements() {
+            let Some(positions) = measurement
+                .atoms()
+                .iter()
+                .map(|&i| molecule.atoms.get(i).map(|atom| atom.position))
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+            let segments = match *positions.as_slice() {
+                [a, b] => dashes(a, b, self.dash_length, self.gap_length),
+                [a, b, c] => angle_arc(a, b, c, self.arc_radius),
+                [a, b, c, d] => dihedral_arc(a, b, c, d, self.arc_radius),
+                _ => Vec::new(),
+            };
+            let Some(&(start, end)) = segments.get(segments.len() / 2) else {
+                continue;
+            };
+            let arms = match *positions.as_slice() {
+                [a, b, c] if self.angle_arms => [
+                    dashes(b, a, self.dash_length, self.gap_length),
+                    dashes(b, c, self.dash_length, self.gap_length),
+                ]
+                .concat(),
+                _ => Vec::new(),
+            };
+            lines.extend(segments.iter().chain(&arms).filter_map(|&(start, end)| {
+                segment_entity(meshes.dash, start, end, self.radius, self.color)
+            }));
+            if let [a, b, c, d] = *positions.as_slice() {
+                if self.dihedral_planes {
+                    lines.extend(self.dihedral_entities([a, b, c, d], meshes));
+                }
+            }
+            if self.label_size > 0.0 {
+                // Just above the middle of the line or arc.
+                let center = nalgebra::center(&start, &end).coords + frame.up * self.label_size;
+                // Measured again so the label follows atoms moved since.
+                let current =
+                    Measurement::measure(molecule, measurement.atoms()).unwrap_or(*measurement);
+                let glyphs = frame.layout(&current.label(), center, self.label_size);
+                labels.extend(glyphs.into_iter().map(|(glyph, pos, orientation)| {
+                    Entity::new(
+                        meshes.first_glyph + glyph,
+                        pos,
+                        orientation,
+                        self.label_size,
+                        self.color,
+                        0.0,
+                    )
+                }));
+            }
+        }
+        lines.extend(labels);
+        lines
+    }
+
+    /// The planes a–b–c and b–c–d, each a rectangle along the b–c bond out
+    /// to the height of a or d over it, then a mark on each atom.
+    fn dihedral_entities(&self, atoms: [Point3<f32>; 4], meshes: MeasurementMeshes) -> Vec<Entity> {
+        let [a, b, c, d] = atoms;
+        let hinge = c - b;
+        let Some(axis) = hinge.try_normalize(1e-6) else {
+            return Vec::new();
+        };
+        let mut entities = Vec::new();
+        for side in [a, d] {
+            let out = (side - b) - axis * axis.dot(&(side - b));
+            let height = out.norm();
+            if height < 1e-6 {
+                continue;
+            }
+            let up = out / height;
+            let basis = [axis, up, axis.cross(&up)];
+            let rotation = UnitQuaternion::from_rotation_matrix(
+                &nalgebra::Rotation3::from_basis_unchecked(&basis),
+            );
+            let center = nalgebra::center(&b, &c) + out / 2.0;
+            let mut entity = Entity::new(
+                meshes.plane,
+                to_la(center),
+                quat_to_la(rotation),
+                1.0,
+                self.color,
+                0.0,
+            );
+            entity.scale_partial = Some(Vec3::new(hinge.norm(), height, 1.0));
+            entity.opacity = self.plane_opacity;
+            entities.push(entity);
+        }
+        entities.extend(atoms.map(|atom| {
+            let mut entity = Entity::new(
+                meshes.mark,
+                to_la(atom),
+                Quaternion::new_identity(),
+                ATOM_RADIUS * DIHEDRAL_MARK_SCALE,
+                self.color,
+                0.0,
+            );
+            entity.opacity = self.plane_opacity;
+            entity
+        }));
+        entities
+    }
+}
+
+/// Draws distances as dashed lines, angles as an arc at the middle atom
+/// (and, with `angle_arms`, dashed arms to the other two) and dihedrals as
+/// an arc around the middle bond (and, with `dihedral_planes`, their
+/// planes), each labeled with its value in
+/// seven-segment digits. Labels face the camera as of the last build; call
+/// `MoleculeViewer::refresh_overlays` after camera moves to re-orient them.
+impl AdditionalRender for MeasurementTool {
+    fn measurement_mut(&mut self) -> Option<&mut MeasurementTool> {
+        Some(self)
+    }
+
+    fn update_scene(&self, scene: &mut Scene, molecule: &Molecule) {
+        if self.measurements().is_empty() {
+            return;
+        }
+        let mut meshes = MeasurementMeshes {
+            dash: scene.meshes.len(),
+            ..Default::default()
+        };
+        scene.meshes.push(Mesh::new_cylinder(1.0, 1.0, 8));
+        if self.label_size > 0.0 {
+            meshes.first_glyph = scene.meshes.len();
+            scene.meshes.extend((0..GLYPH_CHARS.len()).map(glyph_mesh));
+        }
+        let dihedrals = self
+            .measurements()
+            .iter()
+            .any(|m| m.mode() == MeasurementMode::Dihedral);
+        if self.dihedral_planes && dihedrals {
+            meshes.plane = scene.meshes.len();
+            scene.meshes.push(mesh::square());
+            meshes.mark = scene.meshes.len();
+            scene.meshes.push(Mesh::new_sphere(1.0, 2));
+        }
+        let entities = self.entities(scene, molecule, meshes);
+        scene.entities.extend(entities);
+    }
+
+    fn refresh(&self, scene: &mut Scene, molecule: &Molecule, first_entity: usize) {
+        // Redrawn with the meshes `update_scene` pushed, which end the scene.
+        let glyphs = if self.label_size > 0.0 {
+            GLYPH_CHARS.len()
+        } else {
+            0
+        };
+        let dihedrals = self
+            .measurements()
+            .iter()
+            .any(|m| m.mode() == MeasurementMode::Dihedral);
+        let planes = if self.dihedral_planes && dihedrals {
+            2
+        } else {
+            0
+        };
+        let Some(dash) = scene.meshes.len().checked_sub(1 + glyphs + planes) else {
+            return;
+        };
+        let meshes = MeasurementMeshes {
+            dash,
+            first_glyph: dash + 1,
+            plane: dash + 1 + glyphs,
+            mark: dash + 2 + glyphs,
+        };
+        let entities = self.entities(scene, molecule, meshes);
+        scene.entities.truncate(first_entity);
+        scene.entities.extend(entities);
+    }
+
+    fn trajectory_frame(&mut self, _frame: f32, molecule: &Molecule) {
+        self.update_values(molecule);
+    }
+}
+
+/// Largest turn between the segments of a measurement arc, in radians.
+const ARC_STEP: f32 = std::f32::consts::PI / 18.0;
+
+/// Arc at `vertex` from the direction of `a` to that of `c`, `radius` from the
+/// vertex or less to stay inside the shorter leg.
+fn angle_arc(
+    a: Point3<f32>,
+    vertex: Point3<f32>,
+    c: Point3<f32>,
+    radius: f32,
+) -> Vec<(Point3<f32>, Point3<f32>)> {
+    let (u, v) = (a - vertex, c - vertex);
+    let radius = radius.min(0.8 * u.norm().min(v.norm()));
+    if radius < 0.001 {
+        return Vec::new();
+    }
+    // A straight angle has no plane of its own; any perpendicular will do.
+    let normal = u.cross(&v);
+    let normal = if normal.norm() > 1e-6 {
+        normal
+    } else {
+        let other = if u.x.abs() < 0.9 * u.norm() {
+            Vector3::x()
+        } else {
+            Vector3::y()
+        };
+        u.cross(&other)
+    };
+    arc(vertex, u, normal, u.angle(&v), radius)
+}
+
+/// Arc around the `b`–`c` bond, at its middle, turning from the direction of
+/// `a` to that of `d` as seen along the bond.
+fn dihedral_arc(
+    a: Point3<f32>,
+    b: Point3<f32>,
+    c: Point3<f32>,
+    d: Point3<f32>,
+    radius: f32,
+) -> Vec<(Point3<f32>, Point3<f32>)> {
+    let axis = c - b;
+    if axis.norm() < 1e-6 {
+        return Vec::new();
+    }
+    let axis = axis.normalize();
+    let u = (a - b) - axis * axis.dot(&(a - b));
+    let v = (d - c) - axis * axis.dot(&(d - c));
+    if u.norm() < 1e-6 || v.norm() < 1e-6 {
+        return Vec::new();
+    }
+    let angle = axis.dot(&u.cross(&v)).atan2(u.dot(&v));
+    arc(nalgebra::center(&b, &c), u, axis, angle, radius)
+}
+
+/// Segments of the arc of `radius` around `center` that turns the direction
+/// `from` by `angle` radians about `axis`, which is perpendicular to it.
+fn arc(
+    center: Point3<f32>,
+    from: Vector3<f32>,
+    axis: Vector3<f32>,
+    angle: f32,
+    radius: f32,
+) -> Vec<(Point3<f32>, Point3<f32>)> {
+    let axis = nalgebra::Unit::new_normalize(axis);
+    let start = from.normalize() * radius;
+    let n = (angle.abs() / ARC_STEP).ceil().max(1.0) as usize;
+    let points: Vec<Point3<f32>> = (0..=n)
+        .map(|k| {
+            let turn = UnitQuaternion::from_axis_angle(&axis, angle * k as f32 / n as f32);
+            center + turn * start
+        })
+        .collect();
+    points.windows(2).map(|pair| (pair[0], pair[1])).collect()
+}
+
+/// Draws the angle at one atom to two others, as `Molecule::angle` measures
+/// it: solid arms from the vertex, an arc between them in the plane of the
+/// three atoms, and the angle in degrees as a seven-segment label. Positions
+/// are read from the molecule on each build, so it follows trajectory
+/// frames. Call `MoleculeViewer::refresh_overlays` after camera moves to
+/// re-orient the label.
+#[derive(Clone)]
+pub struct AngleMeasurementRender {
+    /// The vertex, then the atoms at the ends of the arms.
+    pub atoms: [usize; 3],
+    pub radius: f32,
+    /// Radius of the arc, in Å; it shrinks to fit inside short arms.
+    pub arc_radius: f32,
+    /// Height of the label, in Å; 0 draws none.
+    pub label_size: f32,
+    pub color: (f32, f32, f32),
+}
+
+impl AngleMeasurementRender {
+    pub fn new(vertex: usize, a: usize, b: usize) -> Self {
+        Self {
+            atoms: [vertex, a, b],
+            radius: 0.03,
+            arc_radius: 0.5,
+            label_size: 0.3,
+            color: (1.0, 0.9, 0.3),
+        }
+    }
+
+    /// The arms, the arc segments, then the label glyphs, in push order.
+    /// `line` is the cylinder mesh and `first_glyph` that of glyph 0.
+    fn entities(
+        &self,
+        scene: &Scene,
+        molecule: &Molecule,
+        line: usize,
+        first_glyph: usize,
+    ) -> Vec<Entity> {
+        let [vertex, a, b] = self.atoms;
+        let (Some(degrees), Some(p)) = (
+            molecule.angle(vertex, a, b),
+            self.atoms
+                .iter()
+                .map(|&i| molecule.atoms.get(i).map(|atom| atom.position))
+                .collect::<Option<Vec<_>>>(),
+        ) else {
+            return Vec::new();
+        };
+        let arc = angle_arc(p[1], p[0], p[2], self.arc_radius);
+        let mut entities: Vec<Entity> = [(p[0], p[1]), (p[0], p[2])]
+            .iter()
+            .chain(&arc)
+            .filter_map(|&(start, end)| segment_entity(line, start, end, self.radius, self.color))
+            .collect();
+        if let (true, Some(&(start, end))) = (self.label_size > 0.0, arc.get(arc.len() / 2)) {
+            let frame = LabelFrame::new(scene);
+            // Just beyond the middle of the arc.
+            let center = nalgebra::center(&start, &end);
+            let out = (center - p[0]).normalize() * self.label_size;
+            let glyphs = frame.layout(
+                &format!("{degrees:.1}"),
+                center.coords + out,
+                self.label_size,
+            );
+            entities.extend(glyphs.into_iter().map(|(glyph, pos, orientation)| {
+                Entity::new(
+                    first_glyph + glyph,
+                    pos,
+                    orientation,
+                    self.label_size,
+                    self.color,
+                    0.0,
+                )
+            }));
+        }
+        entities
+    }
+}
+
+impl AdditionalRender for AngleMeasurementRender {
+    fn update_scene(&self, scene: &mut Scene, molecule: &Molecule) {
+        let line = scene.meshes.len();
+        scene.meshes.push(Mesh::new_cylinder(1.0, 1.0, 8));
+        if self.label_size > 0.0 {
+            scene.meshes.extend((0..GLYPH_CHARS.len()).map(glyph_mesh));
+        }
+        let entities = self.entities(scene, molecule, line, line + 1);
+        scene.entities.extend(entities);
+    }
+
+    fn refresh(&self, scene: &mut Scene, molecule: &Molecule, first_entity: usize) {
+        // Redrawn with the meshes `update_scene` pushed, which end the scene.
+        let glyphs = if self.label_size > 0.0 {
+            GLYPH_CHARS.len()
+        } else {
+            0
+        };
+        let Some(line) = scene.meshes.len().checked_sub(1 + glyphs) else {
+            return;
+        };
+        let entities = self.entities(scene, molecule, line, line + 1);
+        scene.entities.truncate(first_entity);
+        scene.entities.extend(entities);
+    }
+}
+
+/// Draws thin coordination bonds from a metal to its ligands, as found by
+/// `Molecule::coordination_sphere`. Atom positions are read from the molecule
+/// on each build, so the bonds follow trajectory frames.
+#[derive(Clone)]
+pub struct CoordinationRender {
+    pub info: Option<CoordinationInfo>,
+    pub radius: f32,
+    pub color: (f32, f32, f32),
+}
+
+impl CoordinationRender {
+    pub fn new() -> Self {
+        Self {
+            info: None,
+            radius: 0.05,
+            color: (0.6, 0.4, 0.9),
+        }
+    }
+}
+
+impl Default for CoordinationRender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AdditionalRender for CoordinationRender {
+    fn coordination_mut(&mut self) -> Option<&mut Option<CoordinationInfo>> {
+        Some(&mut self.info)
+    }
+
+    fn update_scene(&self, scene: &mut Scene, molecule: &Molecule) {
+        let Some(info) = &self.info else {
+            return;
+        };
+        let segments = coordination::ligand_segments(molecule, info);
+        if segments.is_empty() {
+            return;
+        }
+        let bond_idx = scene.meshes.len();
+        scene.meshes.push(Mesh::new_cylinder(1.0, 1.0, 8));
+        for (start, end) in segments {
+            if let Some(entity) = segment_entity(bond_idx, start, end, self.radius, self.color) {
+                scene.entities.push(entity);
+            }
+        }
+    }
+}
+
+/// Draws dipole moments as arrows from each group's centroid, pointing from
+/// negative to positive charge, `debye_per_angstrom` Debye per Å of length.
+#[derive(Clone)]
+pub struct DipoleRender {
+    pub dipoles: Vec<Dipole>,
+    pub debye_per_angstrom: f32,
+    /// Shaft radius; the head is three times as thick.
+    pub radius: f32,
+    pub color: (f32, f32, f32),
+}
+
+impl DipoleRender {
+    /// `dipoles` as returned by `Molecule::dipoles`.
+    pub fn new(dipoles: Vec<Dipole>) -> Self {
+        Self {
+            dipoles,
+            debye_per_angstrom: 1.0,
+            radius: 0.05,
+            color: (0.1, 0.7, 0.7),
+        }
+    }
+}
+
+impl AdditionalRender for DipoleRender {
+    fn update_scene(&self, scene: &mut Scene, _molecule: &Molecule) {
+        if self.dipoles.is_empty() || self.debye_per_angstrom <= 0.0 {
+            return;
+        }
+        let mesh_idx = scene.meshes.len();
+        scene.meshes.push(Mesh::new_cylinder(1.0, 1.0, 8));
+        for dipole in &self.dipoles {
+            let start = dipole.centroid;
+            let tip = start + dipole.moment / self.debye_per_angstrom;
+            for (a, b, r) in [
+                (start, tip, self.radius),
+                arrow_head(start, tip, self.radius),
+            ] {
+                if let Some(entity) = segment_entity(mesh_idx, a, b, r, self.color) {
+                    scene.entities.push(entity);
+                }
+            }
+        }
+    }
+}
+
+/// Head of an arrow with a `radius` shaft from `start` to `tip`: the last
+/// fifth, three times as thick.
+pub(crate) fn arrow_head(
+    start: Point3<f32>,
+    tip: Point3<f32>,
+    radius: f32,
+) -> (Point3<f32>, Point3<f32>, f32) {
+    (tip - (tip - start) * 0.2, tip, radius * 3.0)
+}
+
+/// Splits `start..end` into dashes of `dash` length separated by `gap`, with a
+/// dash at each end. Lines shorter than one dash stay whole.
+pub(crate) fn dashes(
+    start: Point3<f32>,
+    end: Point3<f32>,
+    dash: f32,
+    gap: f32,
+) -> Vec<(Point3<f32>, Point3<f32>)> {
+    let length = (end - start).norm();
+    let period = dash + gap;
+    if dash <= 0.0 || period <= 0.0 || length <= dash {
+        return vec![(start, end)];
+    }
+    // Stretch the gaps so the pattern ends on a full dash.
+    let n = ((length + gap) / period).floor().max(1.0) as usize;
+    let gap = if n > 1 {
+        (length - n as f32 * dash) / (n - 1) as f32
+    } else {
+        0.0
+    };
+    let dir = (end - start) / length;
+    (0..n)
+        .map(|k| {
+            let s = k as f32 * (dash + gap);
+            (start + dir * s, start + dir * (s + dash).min(length))
+        })
+        .collect()
+}
+
+/// Entity stretching the unit Y-axis cylinder mesh `mesh` from `start` to `end`.
+/// `None` for degenerate segments.
+pub(crate) fn segment_entity(
+    mesh: usize,
+    start: Point3<f32>,
+    end: Point3<f32>,
+    radius: f32,
+    color: (f32, f32, f32),
+) -> Option<Entity> {
+    let diff = end - start;
+    let len = diff.norm();
+    if len < 0.001 {
+        return None;
+    }
+    let orientation = Quaternion::from_unit_vecs(Vec3::new(0.0, 1.0, 0.0), vec_to_la(diff / len));
+    let mut entity = Entity::new(
+        mesh,
+        to_la(nalgebra::center(&start, &end)),
+        orientation,
+        1.0,
+        color,
+        0.1,
+    );
+    entity.scale_partial = Some(Vec3::new(radius, len, radius));
+    Some(entity)
+}
+
+/// Labels atoms with their index using procedural seven-segment digits, so
+/// indices show up without a text rendering stack (e.g. in headless exports).
+///
+/// Glyphs face the camera as of the last build; call
+/// `MoleculeViewer::refresh_overlays` after camera moves to re-orient them.
+#[derive(Clone)]
+pub struct IndexGlyphRender {
+    /// Digit height, in Å.
+    pub glyph_size: f32,
+    pub color: [f32; 3],
+    /// Molecules with more atoms than this are not labeled at all.
+    pub max_atoms: usize,
+}
+
+impl IndexGlyphRender {
+    pub fn new() -> Self {
+        Self {
+            glyph_size: 0.35,
+            color: [1.0, 1.0, 1.0],
+            max_atoms: 2000,
+        }
+    }
+
+    // Yields (digit, position, orientation) for every glyph, in push order.
+    fn glyphs(&self, scene: &Scene, molecule: &Molecule) -> Vec<(usize, Vec3, Quaternion)> {
+        let frame = LabelFrame::new(scene);
+        let lift = frame.up * (ATOM_RADIUS + self.glyph_size);
+        let mut glyphs = Vec::new();
+        for (i, atom) in molecule.atoms.iter().enumerate() {
+            let center = atom.position.coords + lift;
+            glyphs.extend(frame.layout(&i.to_string(), center, self.glyph_size));
+        }
+        glyphs
+    }
+}
+
+impl Default for IndexGlyphRender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AdditionalRender for IndexGlyphRender {
+    fn update_scene(&self, scene: &mut Scene, molecule: &Molecule) {
+        if molecule.atoms.len() > self.max_atoms {
+            return;
+        }
+        let first_digit_mesh = scene.meshes.len();
+        scene.meshes.extend((0..10).map(glyph_mesh));
+
+        let color = (self.color[0], self.color[1], self.color[2]);
+        for (digit, pos, orientation) in self.glyphs(scene, molecule) {
+            scene.entities.push(Entity::new(
+                first_digit_mesh + digit,
+                pos,
+                orientation,
+                self.glyph_size,
+                color,
+                0.0,
+            ));
+        }
+    }
+
+    fn refresh(&self, scene: &mut Scene, molecule: &Molecule, first_entity: usize) {
+        if molecule.atoms.len() > self.max_atoms {
+            return;
+        }
+        let glyphs = self.glyphs(scene, molecule);
+        for (entity, (_, pos, orientation)) in scene.entities[first_entity..].iter_mut().zip(glyphs)
+        {
+            entity.position = pos;
+            entity.orientation = orientation;
+        }
+    }
+}
+
+/// Characters seven-segment labels can show, by glyph index: the digits
+/// first, so a digit's glyph index is its value.
+const GLYPH_CHARS: &str = "0123456789.-";
+
+/// Glyph index of the decimal point, which is a dot rather than segments.
+const DOT_GLYPH: usize = 10;
+
+// Seven-segment layout: a (top), b (upper right), c (lower right), d (bottom),
+// e (lower left), f (upper left), g (middle). Bit 0 is a.
+const GLYPH_SEGMENTS: [u8; 12] = [
+    0b0111111, 0b0000110, 0b1011011, 0b1001111, 0b1100110, 0b1101101, 0b1111101, 0b0000111,
+    0b1111111, 0b1101111, 0, 0b1000000,
+];
+
+/// Where seven-segment labels go on screen, as seen from the scene's camera.
+struct LabelFrame {
+    up: Vector3<f32>,
+    right: Vector3<f32>,
+    orientation: Quaternion,
+}
+
+impl LabelFrame {
+    fn new(scene: &Scene) -> Self {
+        let cam = quat_to_na(scene.camera.orientation);
+        // Camera looks down its local +Z, so screen-right is forward × up.
+        let up = cam * Vector3::y();
+        let right = (cam * Vector3::z()).cross(&up);
+        // Glyph meshes are built in the XY plane; turn them so +X maps to screen-right.
+        let orientation = quat_to_la(
+            cam * UnitQuaternion::from_axis_angle(&Vector3::y_axis(), std::f32::consts::PI),
+        );
+        Self {
+            up,
+            right,
+            orientation,
+        }
+    }
+
+    /// (glyph, position, orientation) of each character of `label` in
+    /// `GLYPH_CHARS`, `size` high and centered on `center`. Other characters
+    /// leave a gap.
+    fn layout(
+        &self,
+        label: &str,
+        center: Vector3<f32>,
+        size: f32,
+    ) -> Vec<(usize, Vec3, Quaternion)> {
+        let advance = 0.8 * size;
+        let n = label.chars().count() as f32;
+        label
+            .chars()
+            .enumerate()
+            .filter_map(|(k, c)| {
+                let glyph = GLYPH_CHARS.find(c)?;
+                let shift = self.right * ((k as f32 - (n - 1.0) / 2.0) * advance);
+                Some((glyph, vec_to_la(center + shift), self.orientation))
+            })
+            .collect()
+    }
+}
+
+/// Unit-height mesh of glyph `glyph` (see `GLYPH_CHARS`) in the XY plane,
+/// centered on the origin.
+fn glyph_mesh(glyph: usize) -> Mesh {
+    let half_w = 0.25;
+    let thickness = 0.06;
+    let horizontal =
+        UnitQuaternion::from_axis_angle(&Vector3::z_axis(), std::f32::consts::FRAC_PI_2);
+    let vertical = UnitQuaternion::identity();
+    // (rotation, center) for segments a..g; cylinders are 0.5 long along Y.
+    let segments = [
+        (horizontal, Vector3::new(0.0, 0.5, 0.0)),
+        (vertical, Vector3::new(half_w, 0.25, 0.0)),
+        (vertical, Vector3::new(half_w, -0.25, 0.0)),
+        (horizontal, Vector3::new(0.0, -0.5, 0.0)),
+        (vertical, Vector3::new(-half_w, -0.25, 0.0)),
+        (vertical, Vector3::new(-half_w, 0.25, 0.0)),
+        (horizontal, Vector3::new(0.0, 0.0, 0.0)),
+    ];
+
+    if glyph == DOT_GLYPH {
+        let dot = Mesh::new_cylinder(thickness * 2.0, thickness * 1.5, 6);
+        return mesh::transformed(dot, vertical, Vector3::new(0.0, -0.5 + thickness, 0.0));
+    }
+    let lit = GLYPH_SEGMENTS[glyph];
+    let mut shape: Option<Mesh> = None;
+    for (bit, (rotation, center)) in segments.into_iter().enumerate() {
+        if lit & (1 << bit) == 0 {
+            continue;
+        }
+        let segment = mesh::transformed(Mesh::new_cylinder(0.5, thickness, 6), rotation, center);
+        match shape.as_mut() {
+            Some(g) => mesh::append(g, segment),
+            None => shape = Some(segment),
+        }
+    }
+    shape.expect("every glyph but the dot lights a segment")
+}
@@ -0,0 +1,74 @@
+//! Comparing two conformations of a molecule: positional RMSD, and the
+//! Kabsch superposition that minimizes it (Kabsch, Acta Cryst. 1976, A32,
+//! 922). Atoms are paired by index; elements are not checked.
+
+use crate::molecule::Molecule;
+use nalgebra::{Matrix3, Rotation3, UnitQuaternion, Vector3};
+
+impl Molecule {
+    /// Root-mean-square distance between the atoms of `self` and `other`,
+    /// paired by index, in `metadata.unit`. Fails if the atom counts differ
+    /// or there are no atoms.
+    pub fn rmsd(&self, other: &Molecule) -> Result<f32, String> {
+        check_pairing(self, other)?;
+        let sum: f32 = self
+            .atoms
+            .iter()
+            .zip(&other.atoms)
+            .map(|(a, b)| (a.position - b.position).norm_squared())
+            .sum();
+        Ok((sum / self.atoms.len() as f32).sqrt())
+    }
+
+    /// The rotation and translation that move `self` onto `other` with the
+    /// least RMSD, found with the Kabsch algorithm, and that RMSD. A point
+    /// `p` of `self` goes to `rotation * p + translation`. Reflections are
+    /// never used, so mirror images do not superimpose. Fails like `rmsd`.
+    pub fn rmsd_after_kabsch_alignment(
+        &self,
+        other: &Molecule,
+    ) -> Result<(f32, UnitQuaternion<f32>, Vector3<f32>), String> {
+        check_pairing(self, other)?;
+        let (from, to) = (self.centroid(), other.centroid());
+        let covariance: Matrix3<f32> = self
+            .atoms
+            .iter()
+            .zip(&other.atoms)
+            .map(|(a, b)| (a.position - from) * (b.position - to).transpose())
+            .sum();
+        let svd = covariance.svd(true, true);
+        let (Some(u), Some(v_t)) = (svd.u, svd.v_t) else {
+            return Err("rmsd: alignment did not converge".to_string());
+        };
+        // Flip the least significant axis if the best fit is a reflection.
+        let d = (v_t.transpose() * u.transpose()).determinant().signum();
+        let correction = Matrix3::from_diagonal(&Vector3::new(1.0, 1.0, d));
+        let matrix = v_t.transpose() * correction * u.transpose();
+        let rotation =
+            UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(matrix));
+        let translation = to.coords - rotation * from.coords;
+
+        let sum: f32 = self
+            .atoms
+            .iter()
+            .zip(&other.atoms)
+            .map(|(a, b)| (rotation * a.position + translation - b.position).norm_squared())
+            .sum();
+        let rmsd = (sum / self.atoms.len() as f32).sqrt();
+        Ok((rmsd, rotation, translation))
+    }
+}
+
+fn check_pairing(a: &Molecule, b: &Molecule) -> Result<(), String> {
+    if a.atoms.len() != b.atoms.len() {
+        return Err(format!(
+            "rmsd: {} atoms against {}",
+            a.atoms.len(),
+            b.atoms.len()
+        ));
+    }
+    if a.atoms.is_empty() {
+        return Err("rmsd: no atoms".to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,115 @@
+//! Stable atom handles that survive edits.
+//!
+//! Everything short-lived addresses atoms by their index in `Molecule::atoms`,
+//! which shifts when an earlier atom is deleted. Anything long-lived, such as a
+//! saved measurement or annotation, should hold an `AtomId` instead and resolve
+//! it with `Molecule::index_of` when needed.
+
+use crate::molecule::Molecule;
+use crate::units::LengthUnit;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static NEXT_ATOM_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Handle to one atom, assigned when it is loaded or created. Ids are never
+/// reused within a process, so a handle cannot silently come to mean another
+/// atom; clones of a molecule keep the ids of their atoms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AtomId(u64);
+
+impl AtomId {
+    fn fresh() -> Self {
+        AtomId(NEXT_ATOM_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl fmt::Display for AtomId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// The ids of a molecule's atoms, indexed like `Molecule::atoms`, with the
+/// reverse lookup kept in sync. `Molecule::remove_atoms` carries them along;
+/// atoms pushed directly get theirs from `Molecule::assign_atom_ids`.
+#[derive(Debug, Clone, Default)]
+pub struct AtomIds {
+    ids: Vec<AtomId>,
+    index: HashMap<AtomId, usize>,
+}
+
+impl AtomIds {
+    pub fn get(&self, atom_idx: usize) -> Option<AtomId> {
+        self.ids.get(atom_idx).copied()
+    }
+
+    pub fn index_of(&self, id: AtomId) -> Option<usize> {
+        self.index.get(&id).copied()
+    }
+
+    /// Number of atoms with an id, normally all of them.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Gives new atoms past the end fresh ids, or drops the ids past
+    /// `n_atoms` if atoms were truncated.
+    fn resize(&mut self, n_atoms: usize) {
+        for id in self.ids.drain(n_atoms.min(self.ids.len())..) {
+            self.index.remove(&id);
+        }
+        while self.ids.len() < n_atoms {
+            let id = AtomId::fresh();
+            self.index.insert(id, self.ids.len());
+            self.ids.push(id);
+        }
+    }
+
+    /// Keeps the ids of the atoms marked in `keep`, renumbering the lookup.
+    pub(crate) fn retain(&mut self, keep: &[bool]) {
+        let mut kept = keep.iter().chain(std::iter::repeat(&true));
+        self.ids.retain(|_| *kept.next().unwrap());
+        self.index = self
+            .ids
+            .iter()
+            .enumerate()
+            .map(|(i, &id)| (id, i))
+            .collect();
+    }
+}
+
+impl Molecule {
+    /// Gives every atom without an id a fresh one. Loaders and the viewer call
+    /// this; call it yourself after pushing atoms to a molecule you keep ids
+    /// for. Atoms removed other than by `remove_atoms` take the ids at the end
+    /// with them, so those ids no longer match the right atoms.
+    pub fn assign_atom_ids(&mut self) {
+        self.atom_ids.resize(self.atoms.len());
+    }
+
+    /// The id of atom `atom_idx`, if it has been assigned one.
+    pub fn atom_id(&self, atom_idx: usize) -> Option<AtomId> {
+        self.atom_ids.get(atom_idx)
+    }
+
+    /// Current index of the atom `id`, `None` once it has been removed.
+    pub fn index_of(&self, id: AtomId) -> Option<usize> {
+        self.atom_ids.index_of(id)
+    }
+
+    /// Resolves each id to its current index; fails on the first unknown id.
+    pub fn indices_of(&self, ids: &[AtomId]) -> Result<Vec<usize>, AtomId> {
+        ids.iter().map(|&id| self.index_of(id).ok_or(id)).collect()
+    }
+
+    /// `distance` between atoms given by id.
+    pub fn distance_by_id(&self, a: AtomId, b: AtomId, unit: Option<LengthUnit>) -> Option<f32> {
+        self.distance(self.index_of(a)?, self.index_of(b)?, unit)
+    }
+}
@@ -0,0 +1,236 @@
+//! Bevy glue, behind the `bevy` feature: spawns the viewer's `DisplayList` as
+//! PBR entities, re-spawning it after edits, and turns clicks and cursor moves
+//! into the `ViewerEvent`s `MoleculeViewer::pick` reports.
+//!
+//! The viewer is a non-send resource (`App::insert_non_send_resource`), since
+//! overlays need not be `Send`. Add `MoleculePlugin::<T>::default()`, where
+//! `T` is the viewer's overlay type, and put a `MoleculeCamera` on the 3D
+//! camera entity. See the `bevy_viewer` example.
+
+use crate::additional_render::AdditionalRender;
+use crate::camera::{Camera, OrbitalCamera, ProjectionType};
+use crate::colormap::Rgb;
+use crate::convert::{bevy_to_la, vec_to_bevy};
+use crate::display_list::DisplayList;
+use crate::viewer::{MoleculeViewer, ViewerEvent};
+use bevy::app::{App, Plugin, Update};
+use bevy::asset::{Assets, Handle};
+use bevy::color::Color;
+use bevy::ecs::prelude::*;
+use bevy::input::mouse::MouseButton;
+use bevy::input::ButtonInput;
+use bevy::math::primitives;
+use bevy::math::{Quat, Vec3};
+use bevy::pbr::{PbrBundle, StandardMaterial};
+use bevy::prelude::{AlphaMode, Deref, DerefMut, GlobalTransform, Mesh, Transform};
+use bevy::render::camera::{
+    Camera as BevyCamera, OrthographicProjection, PerspectiveProjection, Projection, ScalingMode,
+};
+use bevy::window::{CursorMoved, PrimaryWindow, Window};
+use graphics::Scene;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Adds `MoleculePicked` and the `sync_display_list`, `sync_camera` and
+/// `forward_pointer` systems for a `MoleculeViewer<T>`.
+pub struct MoleculePlugin<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for MoleculePlugin<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: AdditionalRender + 'static> Plugin for MoleculePlugin<T> {
+    fn build(&self, app: &mut App) {
+        app.add_event::<MoleculePicked>().add_systems(
+            Update,
+            (sync_display_list::<T>, sync_camera, forward_pointer::<T>).chain(),
+        );
+    }
+}
+
+/// The crate's `Camera` for a Bevy camera entity. `sync_camera` copies its
+/// view onto the entity's `Transform` and `Projection` every frame, so move
+/// the view through this (`orbit`, `MoleculeViewer::fit_camera_to_molecule`,
+/// ...) rather than the `Transform`.
+#[derive(Component, Default, Deref, DerefMut)]
+pub struct MoleculeCamera(pub OrbitalCamera);
+
+/// Marks the entities `spawn_display_list` spawned, to despawn them before
+/// spawning a new list (see `sync_display_list`).
+#[derive(Component, Debug, Clone, Copy)]
+pub struct MoleculeShape;
+
+/// What `MoleculeViewer::pick` found under the cursor: `AtomClicked`,
+/// `BondClicked` or `NothingClicked` on a left click, and `AtomHovered`,
+/// `BondHovered` or `NothingHovered` when the cursor moves onto something
+/// else. Hand it to `MoleculeViewer::handle_pick` to select and highlight.
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct MoleculePicked(pub ViewerEvent);
+
+/// Spawns each sphere and cylinder of `list` as a `PbrBundle` with
+/// `MoleculeShape`, sharing one unit mesh of each kind and one material per
+/// color. Returns the entities in list order, spheres first.
+pub fn spawn_display_list(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    list: &DisplayList,
+) -> Vec<Entity> {
+    let sphere = meshes.add(primitives::Sphere::new(1.0));
+    let cylinder = meshes.add(primitives::Cylinder::new(1.0, 1.0));
+    let mut cache: HashMap<[u32; 5], Handle<StandardMaterial>> = HashMap::new();
+    let mut material = |(r, g, b): Rgb, opacity: f32, shininess: f32| {
+        let key = [r, g, b, opacity, shininess].map(f32::to_bits);
+        cache
+            .entry(key)
+            .or_insert_with(|| {
+                materials.add(StandardMaterial {
+                    base_color: Color::srgba(r, g, b, opacity),
+                    // Blinn-Phong exponent to GGX roughness.
+                    perceptual_roughness: (2.0 / (shininess.max(0.0) + 2.0)).powf(0.25),
+                    alpha_mode: if opacity < 1.0 {
+                        AlphaMode::Blend
+                    } else {
+                        AlphaMode::Opaque
+                    },
+                    ..Default::default()
+                })
+            })
+            .clone()
+    };
+
+    let mut entities = Vec::with_capacity(list.spheres.len() + list.cylinders.len());
+    for s in &list.spheres {
+        let bundle = PbrBundle {
+            mesh: sphere.clone(),
+            material: material(s.color, s.opacity, s.shininess),
+            transform: Transform::from_translation(vec_to_bevy(s.center.coords))
+                .with_scale(Vec3::splat(s.radius)),
+            ..Default::default()
+        };
+        entities.push(commands.spawn((bundle, MoleculeShape)).id());
+    }
+    for c in &list.cylinders {
+        let axis = vec_to_bevy(c.end - c.start);
+        let bundle = PbrBundle {
+            mesh: cylinder.clone(),
+            material: material(c.color, c.opacity, c.shininess),
+            transform: Transform {
+                translation: vec_to_bevy(nalgebra::center(&c.start, &c.end).coords),
+                rotation: Quat::from_rotation_arc(Vec3::Y, axis.normalize_or(Vec3::Y)),
+                scale: Vec3::new(c.radius, axis.length(), c.radius),
+            },
+            ..Default::default()
+        };
+        entities.push(commands.spawn((bundle, MoleculeShape)).id());
+    }
+    entities
+}
+
+/// Re-spawns the `MoleculeShape` entities from the viewer whenever it has
+/// something to redraw (`MoleculeViewer::needs_update`): a new molecule, an
+/// edit, a style change or the selection. The first run spawns the molecule
+/// loaded at startup.
+pub fn sync_display_list<T: AdditionalRender + 'static>(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut viewer: NonSendMut<MoleculeViewer<T>>,
+    shapes: Query<Entity, With<MoleculeShape>>,
+) {
+    if !viewer.needs_update() {
+        return;
+    }
+    for entity in &shapes {
+        commands.entity(entity).despawn();
+    }
+    // Built from scratch each time, so the viewer can't update it in place.
+    let mut scene = Scene::default();
+    viewer.mark_geometry_dirty();
+    viewer.update_scene(&mut scene);
+    let list = DisplayList::from_scene(&viewer, &scene);
+    spawn_display_list(&mut commands, &mut meshes, &mut materials, &list);
+}
+
+/// Copies each `MoleculeCamera`'s view onto its entity, after matching its
+/// aspect to the primary window's.
+pub fn sync_camera(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cameras: Query<(&mut MoleculeCamera, &mut Transform, &mut Projection)>,
+) {
+    let aspect = windows
+        .get_single()
+        .ok()
+        .map(|window| window.width() / window.height())
+        .filter(|aspect| aspect.is_finite() && *aspect > 0.0);
+    for (mut camera, mut transform, mut projection) in &mut cameras {
+        if let Some(aspect) = aspect.filter(|&aspect| aspect != camera.aspect) {
+            camera.set_aspect(aspect);
+        }
+        *transform = Transform::from_translation(vec_to_bevy(camera.position().coords)).looking_at(
+            vec_to_bevy(camera.target().coords),
+            vec_to_bevy(camera.up()),
+        );
+        *projection = match camera.projection() {
+            ProjectionType::Perspective => Projection::Perspective(PerspectiveProjection {
+                fov: camera.fov_y(),
+                aspect_ratio: camera.aspect,
+                near: camera.near(),
+                far: camera.far(),
+            }),
+            ProjectionType::Orthographic => Projection::Orthographic(OrthographicProjection {
+                near: camera.near(),
+                far: camera.far(),
+                scaling_mode: ScalingMode::FixedVertical(camera.ortho_scale()),
+                ..Default::default()
+            }),
+        };
+    }
+}
+
+/// Picks under the cursor through the `MoleculeCamera`'s Bevy camera on
+/// left clicks and cursor moves, and sends `MoleculePicked`. Hovers are only
+/// sent when they change.
+pub fn forward_pointer<T: AdditionalRender + 'static>(
+    buttons: Res<ButtonInput<MouseButton>>,
+    mut moves: EventReader<CursorMoved>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&BevyCamera, &GlobalTransform), With<MoleculeCamera>>,
+    viewer: NonSend<MoleculeViewer<T>>,
+    mut last_hover: Local<Option<ViewerEvent>>,
+    mut picks: EventWriter<MoleculePicked>,
+) {
+    let moved = moves.read().count() > 0;
+    let clicked = buttons.just_pressed(MouseButton::Left);
+    if !moved && !clicked {
+        return;
+    }
+    let Some(cursor) = windows.get_single().ok().and_then(Window::cursor_position) else {
+        return;
+    };
+    let Ok((camera, transform)) = cameras.get_single() else {
+        return;
+    };
+    let Some(ray) = camera.viewport_to_world(transform, cursor) else {
+        return;
+    };
+    let Some(event) = viewer.pick(bevy_to_la(ray.origin), bevy_to_la(*ray.direction)) else {
+        return;
+    };
+    if moved {
+        let hover = match event {
+            ViewerEvent::AtomClicked(i) => ViewerEvent::AtomHovered(i),
+            ViewerEvent::BondClicked(i) => ViewerEvent::BondHovered(i),
+            _ => ViewerEvent::NothingHovered,
+        };
+        if last_hover.as_ref() != Some(&hover) {
+            *last_hover = Some(hover.clone());
+            picks.send(MoleculePicked(hover));
+        }
+    }
+    if clicked {
+        picks.send(MoleculePicked(event));
+    }
+}
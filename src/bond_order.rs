@@ -0,0 +1,298 @@
+//! Heuristic bonds from geometry: connectivity for formats that record none
+//! (XYZ, PDB files without CONECT records), and bond orders for formats that
+//! only record connectivity.
+
+use crate::element;
+use crate::molecule::{Bond, Molecule};
+use crate::spatial::NeighborGrid;
+use nalgebra::Vector3;
+use std::collections::HashSet;
+
+/// Default `Molecule::infer_bonds` tolerance, in Å.
+pub const BOND_TOLERANCE: f32 = 0.4;
+
+/// Smallest sum of neighbor angles, in degrees, for a three-connected atom to
+/// count as planar (sp²).
+const PLANAR_ANGLE_SUM: f32 = 350.0;
+
+/// Smallest angle, in degrees, for a two-connected atom to count as linear (sp).
+const LINEAR_ANGLE: f32 = 165.0;
+
+/// A bond order `perceive_bond_orders` would change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BondOrderChange {
+    pub bond: usize,
+    pub old_order: u8,
+    pub new_order: u8,
+}
+
+impl Molecule {
+    /// Adds a single bond between every two atoms closer than the sum of
+    /// their `element::covalent_radius` plus `tolerance` Å, and returns how
+    /// many were added. Pairs already bonded keep their bond; atoms of
+    /// elements without a radius get no bonds.
+    pub fn infer_bonds(&mut self, tolerance: f32) -> usize {
+        self.infer_bonds_by(|radius_sum| radius_sum + tolerance)
+    }
+
+    /// `infer_bonds` with the cutoff `factor` times the sum of the covalent
+    /// radii, e.g. 1.2 for 20% over it.
+    pub fn infer_bonds_scaled(&mut self, factor: f32) -> usize {
+        self.infer_bonds_by(|radius_sum| radius_sum * factor)
+    }
+
+    /// Bonds atoms closer than `cutoff` of their radius sum; `cutoff` must
+    /// grow with the sum.
+    fn infer_bonds_by(&mut self, cutoff: impl Fn(f32) -> f32) -> usize {
+        let radii: Vec<Option<f32>> = self
+            .atoms
+            .iter()
+            .map(|atom| element::covalent_radius(&atom.element))
+            .collect();
+        let max_radius = radii.iter().flatten().copied().fold(0.0, f32::max);
+        let reach = cutoff(2.0 * max_radius);
+        if !(reach.is_finite() && reach > 0.0) {
+            return 0;
+        }
+        let positions: Vec<_> = self.atoms.iter().map(|atom| atom.position).collect();
+        let grid = NeighborGrid::new(&positions, reach);
+        let mut bonded: HashSet<(usize, usize)> = self
+            .bonds
+            .iter()
+            .map(|b| (b.atom_a.min(b.atom_b), b.atom_a.max(b.atom_b)))
+            .collect();
+        let before = self.bonds.len();
+        for (a, radius_a) in radii.iter().enumerate() {
+            let Some(radius_a) = radius_a else {
+                continue;
+            };
+            let mut neighbors = grid.within(positions[a], reach);
+            neighbors.retain(|&b| b > a);
+            for b in neighbors {
+                let Some(radius_b) = radii[b] else {
+                    continue;
+                };
+                let distance = (positions[b] - positions[a]).norm();
+                if distance < cutoff(radius_a + radius_b) && bonded.insert((a, b)) {
+                    self.bonds.push(Bond {
+                        atom_a: a,
+                        atom_b: b,
+                        order: 1,
+                    });
+                }
+            }
+        }
+        self.bonds.len() - before
+    }
+
+    /// Assigns bond orders from bond lengths and neighbor geometry, and returns
+    /// what changed. See `propose_bond_orders` for the rules.
+    pub fn perceive_bond_orders(&mut self) -> Vec<BondOrderChange> {
+        let changes = self.propose_bond_orders();
+        for change in &changes {
+            self.bonds[change.bond].order = change.new_order;
+        }
+        changes
+    }
+
+    /// Dry run of `perceive_bond_orders`: the changes it would make, in bond order.
+    ///
+    /// Bonds between elements with tabulated lengths (C, N, O, S, P) start out
+    /// single; other bonds keep their order. Each such bond is proposed the
+    /// order whose typical length is closest to its own, limited to double at
+    /// planar (sp²) centers and triple at linear (sp) ones. Proposals are then
+    /// granted from the shortest bond up, as far as both atoms' default
+    /// valences allow. Aromatic rings therefore come out as alternating single
+    /// and double bonds.
+    pub fn propose_bond_orders(&self) -> Vec<BondOrderChange> {
+        let adjacency = self.adjacency();
+        let mut orders: Vec<u8> = self.bonds.iter().map(|b| b.order).collect();
+        let mut candidates = Vec::new();
+        for (i, bond) in self.bonds.iter().enumerate() {
+            let (a, b) = (bond.atom_a, bond.atom_b);
+            if a >= adjacency.len() || b >= adjacency.len() || a == b {
+                continue;
+            }
+            let Some(lengths) = typical_lengths(&self.atoms[a].element, &self.atoms[b].element)
+            else {
+                continue;
+            };
+            orders[i] = 1;
+            let length = (self.atoms[a].position - self.atoms[b].position).norm();
+            let nearest = lengths
+                .iter()
+                .enumerate()
+                .filter(|(_, typical)| typical.is_finite())
+                .min_by(|(_, x), (_, y)| (length - *x).abs().total_cmp(&(length - *y).abs()))
+                .map_or(1, |(k, _)| k as u8 + 1);
+            let order = nearest
+                .min(self.max_multiple_order(&adjacency, a))
+                .min(self.max_multiple_order(&adjacency, b));
+            if order > 1 {
+                candidates.push((length, i, order));
+            }
+        }
+
+        // Spare valence per atom, with every bond counted at its current order.
+        let mut spare: Vec<i32> = self
+            .atoms
+            .iter()
+            .map(|atom| valence(&atom.element).map_or(0, i32::from))
+            .collect();
+        for (bond, &order) in self.bonds.iter().zip(&orders) {
+            for atom in [bond.atom_a, bond.atom_b] {
+                if let Some(s) = spare.get_mut(atom) {
+                    // Aromatic bonds (order 0) use at least one.
+                    *s -= i32::from(order.max(1));
+                }
+            }
+        }
+
+        candidates.sort_by(|x, y| x.0.total_cmp(&y.0).then(x.1.cmp(&y.1)));
+        for &(_, i, order) in &candidates {
+            let (a, b) = (self.bonds[i].atom_a, self.bonds[i].atom_b);
+            let extra = i32::from(order - 1).min(spare[a]).min(spare[b]);
+            if extra > 0 {
+                orders[i] += extra as u8;
+                spare[a] -= extra;
+                spare[b] -= extra;
+            }
+        }
+
+        // Greedy choices can strand atoms in conjugated systems (e.g. taking two
+        // opposite benzene bonds first). Repair by flipping alternating paths of
+        // single and double candidate bonds between two atoms with spare valence.
+        let mut double_candidates = vec![Vec::new(); self.atoms.len()];
+        for &(_, i, _) in &candidates {
+            let (a, b) = (self.bonds[i].atom_a, self.bonds[i].atom_b);
+            double_candidates[a].push((i, b));
+            double_candidates[b].push((i, a));
+        }
+        for start in 0..self.atoms.len() {
+            while spare[start] > 0 {
+                let mut visited = vec![false; self.atoms.len()];
+                visited[start] = true;
+                let mut path = Vec::new();
+                let Some(end) = alternating_path(
+                    &double_candidates,
+                    &orders,
+                    &spare,
+                    start,
+                    &mut visited,
+                    &mut path,
+                ) else {
+                    break;
+                };
+                for (step, &bond) in path.iter().enumerate() {
+                    if step % 2 == 0 {
+                        orders[bond] += 1;
+                    } else {
+                        orders[bond] -= 1;
+                    }
+                }
+                spare[start] -= 1;
+                spare[end] -= 1;
+            }
+        }
+
+        self.bonds
+            .iter()
+            .zip(orders)
+            .enumerate()
+            .filter(|(_, (bond, order))| bond.order != *order)
+            .map(|(bond, (old, new_order))| BondOrderChange {
+                bond,
+                old_order: old.order,
+                new_order,
+            })
+            .collect()
+    }
+
+    /// Highest bond order the geometry around `atom` allows: 3 for terminal or
+    /// linear atoms, 2 for bent or planar ones, 1 for anything else.
+    fn max_multiple_order(&self, adjacency: &[Vec<usize>], atom: usize) -> u8 {
+        let center = self.atoms[atom].position;
+        let directions: Vec<Vector3<f32>> = adjacency[atom]
+            .iter()
+            .filter_map(|&n| (self.atoms[n].position - center).try_normalize(1e-6))
+            .collect();
+        let angle =
+            |u: &Vector3<f32>, v: &Vector3<f32>| u.dot(v).clamp(-1.0, 1.0).acos().to_degrees();
+        match directions.as_slice() {
+            [] | [_] => 3,
+            [u, v] if angle(u, v) >= LINEAR_ANGLE => 3,
+            [_, _] => 2,
+            [u, v, w] if angle(u, v) + angle(v, w) + angle(w, u) >= PLANAR_ANGLE_SUM => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Depth-first search from `atom` for a path that alternates a single candidate
+/// bond, a double candidate bond, a single one, ... and ends at an unvisited atom
+/// with spare valence. Pushes the path's bonds onto `path` and returns its end.
+fn alternating_path(
+    candidates: &[Vec<(usize, usize)>],
+    orders: &[u8],
+    spare: &[i32],
+    atom: usize,
+    visited: &mut [bool],
+    path: &mut Vec<usize>,
+) -> Option<usize> {
+    for &(bond, next) in &candidates[atom] {
+        if visited[next] || orders[bond] != 1 {
+            continue;
+        }
+        visited[next] = true;
+        path.push(bond);
+        if spare[next] > 0 {
+            return Some(next);
+        }
+        for &(back, after) in &candidates[next] {
+            if visited[after] || orders[back] != 2 {
+                continue;
+            }
+            visited[after] = true;
+            path.push(back);
+            if let Some(end) = alternating_path(candidates, orders, spare, after, visited, path) {
+                return Some(end);
+            }
+            path.pop();
+        }
+        path.pop();
+    }
+    None
+}
+
+/// Default valence used to cap the total bond order of an atom.
+fn valence(element: &str) -> Option<u8> {
+    let valence = match element.to_ascii_uppercase().as_str() {
+        "H" | "F" | "CL" | "BR" | "I" => 1,
+        "O" | "S" | "SE" => 2,
+        "B" | "N" | "P" => 3,
+        "C" | "SI" => 4,
+        _ => return None,
+    };
+    Some(valence)
+}
+
+/// Typical single, double and triple bond lengths in Å between two elements;
+/// `NAN` where that order does not occur.
+fn typical_lengths(a: &str, b: &str) -> Option<[f32; 3]> {
+    let (a, b) = (a.to_ascii_uppercase(), b.to_ascii_uppercase());
+    let (a, b) = if a <= b { (a, b) } else { (b, a) };
+    let lengths = match (a.as_str(), b.as_str()) {
+        ("C", "C") => [1.54, 1.34, 1.20],
+        ("C", "N") => [1.47, 1.28, 1.16],
+        ("C", "O") => [1.43, 1.21, 1.13],
+        ("C", "S") => [1.82, 1.60, f32::NAN],
+        ("C", "P") => [1.84, 1.67, f32::NAN],
+        ("N", "N") => [1.45, 1.25, 1.10],
+        ("N", "O") => [1.40, 1.21, f32::NAN],
+        ("O", "O") => [1.48, 1.21, f32::NAN],
+        ("O", "S") => [1.57, 1.43, f32::NAN],
+        ("O", "P") => [1.60, 1.48, f32::NAN],
+        _ => return None,
+    };
+    Some(lengths)
+}
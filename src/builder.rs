@@ -0,0 +1,414 @@
+//! One-expression construction of a configured `MoleculeViewer`.
+//!
+//! ```no_run
+//! use moleucle_3dview_rs::{Molecule, MoleculeViewer, RenderStyle, SelectedAtomRender};
+//! use std::path::Path;
+//!
+//! let molecule = Molecule::from_file(Path::new("Benzene.mol2")).unwrap();
+//! let (viewer, warnings) = MoleculeViewer::builder()
+//!     .molecule(molecule)
+//!     .render_style(RenderStyle::Stick)
+//!     .hide_hydrogens(true)
+//!     .additional_render(SelectedAtomRender::new())
+//!     .build();
+//! ```
+//!
+//! `build` checks the options against each other and against the initial
+//! molecule. Options that cannot work are dropped or adjusted rather than
+//! failing the build, with one warning per change.
+
+use crate::additional_render::AdditionalRender;
+use crate::capture::EventMask;
+use crate::colormap::{ColorScheme, Rgb, TintMode};
+use crate::molecule::Molecule;
+use crate::style::{
+    AtomRadiiTable, BondColoring, Cutaway, Detail, ElementScale, MoleculeDisplayState,
+    PinnedMotion, RenderStyle, Shading, Slab, ViewerSettings, VisibilityMode, BOND_RADIUS,
+};
+use crate::viewer::{LoadOptions, MoleculeViewer};
+use nalgebra::Vector3;
+use std::path::PathBuf;
+
+/// Options for a new `MoleculeViewer`; see the module docs. Unset options
+/// keep the values of `MoleculeViewer::new`.
+pub struct ViewerBuilder<T: AdditionalRender> {
+    settings: ViewerSettings,
+    render_style: Option<RenderStyle>,
+    // Explicit values that win over `auto_style`.
+    detail: Option<Detail>,
+    hide_hydrogens: Option<bool>,
+    auto_style: bool,
+    molecule: Option<Molecule>,
+    additional_render: Option<Box<T>>,
+    renderers: Vec<Box<dyn AdditionalRender>>,
+    default_state: Option<MoleculeDisplayState>,
+    reference_axis: Option<(Vector3<f32>, String)>,
+    bond_mode: bool,
+    capture_hook: Option<(EventMask, PathBuf)>,
+}
+
+impl<T: AdditionalRender> Default for ViewerBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: AdditionalRender> MoleculeViewer<T> {
+    pub fn builder() -> ViewerBuilder<T> {
+        ViewerBuilder::new()
+    }
+}
+
+impl<T: AdditionalRender> ViewerBuilder<T> {
+    pub fn new() -> Self {
+        Self {
+            settings: ViewerSettings::default(),
+            render_style: None,
+            detail: None,
+            hide_hydrogens: None,
+            auto_style: true,
+            molecule: None,
+            additional_render: None,
+            renderers: Vec::new(),
+            default_state: None,
+            reference_axis: None,
+            bond_mode: false,
+            capture_hook: None,
+        }
+    }
+
+    /// Replaces every setting at once. Later setters change single fields.
+    /// As with `MoleculeViewer::set_molecule`, `auto_style` replaces its
+    /// `detail` and `hide_hydrogens`; use the setters to keep your own.
+    pub fn settings(mut self, settings: ViewerSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Overrides the style `auto_style` picks for the initial molecule.
+    pub fn render_style(mut self, render_style: RenderStyle) -> Self {
+        self.render_style = Some(render_style);
+        self
+    }
+
+    /// Run `auto_style` on the initial molecule (the default). Styles set
+    /// explicitly on the builder win over its choice either way.
+    pub fn auto_style(mut self, auto_style: bool) -> Self {
+        self.auto_style = auto_style;
+        self
+    }
+
+    pub fn molecule(mut self, molecule: Molecule) -> Self {
+        self.molecule = Some(molecule);
+        self
+    }
+
+    pub fn additional_render(mut self, additional_render: T) -> Self {
+        self.additional_render = Some(Box::new(additional_render));
+        self
+    }
+
+    /// Adds an overlay renderer after `additional_render`, as
+    /// `MoleculeViewer::add_renderer` does; their ids follow the call order,
+    /// from 1.
+    pub fn renderer(mut self, renderer: impl AdditionalRender + 'static) -> Self {
+        self.renderers.push(Box::new(renderer));
+        self
+    }
+
+    /// State given to molecules added later with `add_molecule`.
+    pub fn default_state(mut self, default_state: MoleculeDisplayState) -> Self {
+        self.default_state = Some(default_state);
+        self
+    }
+
+    /// See `MoleculeViewer::set_reference_axis`.
+    pub fn reference_axis(mut self, direction: Vector3<f32>, label: impl Into<String>) -> Self {
+        self.reference_axis = Some((direction, label.into()));
+        self
+    }
+
+    /// See `MoleculeViewer::set_bond_mode`.
+    pub fn bond_mode(mut self, bond_mode: bool) -> Self {
+        self.bond_mode = bond_mode;
+        self
+    }
+
+    /// See `MoleculeViewer::set_capture_hook`.
+    pub fn capture_hook(mut self, events: EventMask, dir: impl Into<PathBuf>) -> Self {
+        self.capture_hook = Some((events, dir.into()));
+        self
+    }
+
+    pub fn shading(mut self, shading: Shading) -> Self {
+        self.settings.shading = shading;
+        self
+    }
+
+    /// Overrides the detail `auto_style` picks for the initial molecule.
+    pub fn detail(mut self, detail: Detail) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+
+    /// Overrides the choice `auto_style` makes for the initial molecule.
+    pub fn hide_hydrogens(mut self, hide_hydrogens: bool) -> Self {
+        self.hide_hydrogens = Some(hide_hydrogens);
+        self
+    }
+
+    pub fn hydrogen_mode(mut self, mode: VisibilityMode) -> Self {
+        self.settings.hydrogen_mode = mode;
+        self
+    }
+
+    pub fn ambient_occlusion(mut self, strength: f32, radius: f32) -> Self {
+        self.settings.ao_strength = strength;
+        self.settings.ao_radius = radius;
+        self
+    }
+
+    pub fn slab(mut self, slab: Slab, mode: VisibilityMode) -> Self {
+        self.settings.slab = Some(slab);
+        self.settings.slab_mode = mode;
+        self
+    }
+
+    pub fn slab_cap_color(mut self, color: Option<Rgb>) -> Self {
+        self.settings.slab_cap_color = color;
+        self
+    }
+
+    pub fn cutaway(mut self, cutaway: Cutaway) -> Self {
+        self.settings.cutaway = Some(cutaway);
+        self
+    }
+
+    pub fn bond_stubs(mut self, fraction: Option<f32>) -> Self {
+        self.settings.bond_stubs = fraction;
+        self
+    }
+
+    pub fn pick_ghosts(mut self, pick_ghosts: bool) -> Self {
+        self.settings.pick_ghosts = pick_ghosts;
+        self
+    }
+
+    pub fn color_rings_by_planarity(mut self, enabled: bool) -> Self {
+        self.settings.color_rings_by_planarity = enabled;
+        self
+    }
+
+    pub fn element_scale(mut self, scale: Option<ElementScale>) -> Self {
+        self.settings.element_scale = scale;
+        self
+    }
+
+    pub fn radii_table(mut self, table: Option<AtomRadiiTable>) -> Self {
+        self.settings.radii_table = table;
+        self
+    }
+
+    pub fn color_override(mut self, atom: usize, color: Rgb) -> Self {
+        self.settings.color_overrides.insert(atom, color);
+        self
+    }
+
+    pub fn hidden_atoms(mut self, atoms: impl IntoIterator<Item = usize>) -> Self {
+        self.settings.hidden_atoms.extend(atoms);
+        self
+    }
+
+    pub fn tint(mut self, tint: TintMode) -> Self {
+        self.settings.tint = Some(tint);
+        self
+    }
+
+    pub fn coordination_on_click(mut self, enabled: bool) -> Self {
+        self.settings.coordination_on_click = enabled;
+        self
+    }
+
+    pub fn drag_falloff_shells(mut self, shells: usize) -> Self {
+        self.settings.drag_falloff_shells = shells;
+        self
+    }
+
+    pub fn pinned_motion(mut self, pinned_motion: PinnedMotion) -> Self {
+        self.settings.pinned_motion = pinned_motion;
+        self
+    }
+
+    pub fn stick_radius(mut self, radius: f32) -> Self {
+        self.settings.stick_radius = radius;
+        self
+    }
+
+    pub fn show_bond_order(mut self, show: bool) -> Self {
+        self.settings.show_bond_order = show;
+        self
+    }
+
+    pub fn bond_coloring(mut self, coloring: BondColoring) -> Self {
+        self.settings.bond_coloring = coloring;
+        self
+    }
+
+    pub fn color_scheme(mut self, scheme: ColorScheme) -> Self {
+        self.settings.color_scheme = scheme;
+        self
+    }
+
+    /// The configured viewer, with a warning for each option that was
+    /// dropped or adjusted to fit the others:
+    /// - ghost opacities outside 0..=1 are clamped;
+    /// - a slab or cutaway of no size is dropped;
+    /// - a stick radius that is not positive is reset to `BOND_RADIUS`;
+    /// - hidden atoms and color overrides past the initial molecule's atoms
+    ///   are dropped;
+    /// - `coordination_on_click` is turned off if no overlay can show
+    ///   coordination spheres;
+    /// - a zero reference axis is not set.
+    ///
+    /// Bond stubs in space-filling style only get a warning, since a later
+    /// style change can use them.
+    pub fn build(mut self) -> (MoleculeViewer<T>, Vec<String>) {
+        let mut warnings = Vec::new();
+        self.validate(&mut warnings);
+
+        let mut viewer = MoleculeViewer::new();
+        viewer.settings = self.settings;
+        viewer.additional_render = self.additional_render;
+        for renderer in self.renderers {
+            viewer.add_renderer(renderer);
+        }
+        if let Some(default_state) = self.default_state {
+            viewer.default_state = default_state;
+        }
+        if let Some(molecule) = self.molecule {
+            let options = LoadOptions {
+                auto_style: self.auto_style,
+                ..LoadOptions::default()
+            };
+            viewer.set_molecule_with(molecule, options);
+        }
+        if let Some(render_style) = self.render_style {
+            viewer.render_style = render_style;
+        }
+        if let Some(detail) = self.detail {
+            viewer.settings.detail = detail;
+        }
+        if let Some(hide_hydrogens) = self.hide_hydrogens {
+            viewer.settings.hide_hydrogens = hide_hydrogens;
+        }
+        if viewer.render_style == RenderStyle::SpaceFilling && viewer.settings.bond_stubs.is_some()
+        {
+            warnings.push("bond stubs have no effect in the space-filling style".to_string());
+        }
+        if let Some((direction, label)) = self.reference_axis {
+            if !viewer.set_reference_axis(direction, label) {
+                warnings.push("reference axis: zero direction, not set".to_string());
+            }
+        }
+        viewer.set_bond_mode(self.bond_mode);
+        if let Some((events, dir)) = self.capture_hook {
+            viewer.set_capture_hook(events, dir);
+        }
+        viewer.dirty = true;
+        (viewer, warnings)
+    }
+
+    fn validate(&mut self, warnings: &mut Vec<String>) {
+        let settings = &mut self.settings;
+        let modes = [
+            ("hydrogen mode", &mut settings.hydrogen_mode),
+            ("slab mode", &mut settings.slab_mode),
+        ];
+        for (name, mode) in modes {
+            clamp_ghost(name, mode, warnings);
+        }
+        if let Some(cutaway) = &mut settings.cutaway {
+            clamp_ghost("cutaway mode", &mut cutaway.mode, warnings);
+        }
+        if settings
+            .slab
+            .is_some_and(|slab| !positive(slab.half_thickness))
+        {
+            settings.slab = None;
+            warnings.push("slab: thickness is not positive, dropped".to_string());
+        }
+        if settings.cutaway.is_some_and(|c| !positive(c.radius)) {
+            settings.cutaway = None;
+            warnings.push("cutaway: radius is not positive, dropped".to_string());
+        }
+        if !positive(settings.stick_radius) {
+            warnings.push(format!(
+                "stick radius: {} is not positive, reset to {BOND_RADIUS}",
+                settings.stick_radius
+            ));
+            settings.stick_radius = BOND_RADIUS;
+        }
+
+        if let Some(mol) = &self.molecule {
+            let n_atoms = mol.atoms.len();
+            let hidden = settings.hidden_atoms.split_off(&n_atoms);
+            if let Some(first) = hidden.first() {
+                warnings.push(format!(
+                    "hidden atoms: {} past the molecule's {n_atoms} atoms (from {first}), dropped",
+                    hidden.len()
+                ));
+            }
+            let mut dropped: Vec<usize> = settings
+                .color_overrides
+                .keys()
+                .copied()
+                .filter(|&i| i >= n_atoms)
+                .collect();
+            dropped.sort_unstable();
+            for i in &dropped {
+                settings.color_overrides.remove(i);
+            }
+            if let Some(first) = dropped.first() {
+                warnings.push(format!(
+                    "color overrides: {} past the molecule's {n_atoms} atoms (from {first}), dropped",
+                    dropped.len()
+                ));
+            }
+        }
+
+        if settings.coordination_on_click {
+            let shows_coordination = self
+                .additional_render
+                .as_deref_mut()
+                .is_some_and(|render| render.coordination_mut().is_some())
+                || self
+                    .renderers
+                    .iter_mut()
+                    .any(|render| render.coordination_mut().is_some());
+            if !shows_coordination {
+                settings.coordination_on_click = false;
+                warnings.push(
+                    "coordination on click: no overlay can show coordination spheres, \
+                     turned off"
+                        .to_string(),
+                );
+            }
+        }
+    }
+}
+
+fn positive(x: f32) -> bool {
+    x.is_finite() && x > 0.0
+}
+
+fn clamp_ghost(name: &str, mode: &mut VisibilityMode, warnings: &mut Vec<String>) {
+    if let VisibilityMode::Ghost { opacity } = mode {
+        let clamped = opacity.clamp(0.0, 1.0);
+        if clamped != *opacity {
+            warnings.push(format!(
+                "{name}: ghost opacity {opacity} is outside 0..=1, clamped to {clamped}"
+            ));
+            *opacity = clamped;
+        }
+    }
+}
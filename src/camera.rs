@@ -1,13 +1,113 @@
+#[cfg(feature = "render")]
+use crate::convert;
+use crate::molecule::Molecule;
 use nalgebra::{
-    Isometry3, Matrix4, Orthographic3, Perspective3, Point3, Unit, UnitQuaternion, Vector2, Vector3, Vector4
+    Isometry3, Matrix4, Orthographic3, Perspective3, Point3, Rotation3, Unit, UnitQuaternion,
+    Vector2, Vector3,
 };
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ProjectionType {
     Perspective,
     Orthographic,
 }
 
+/// Smallest and largest vertical field of view accepted by `Camera::set_fov`, in radians.
+pub const MIN_FOV_Y: f32 = 10.0 * std::f32::consts::PI / 180.0;
+pub const MAX_FOV_Y: f32 = 120.0 * std::f32::consts::PI / 180.0;
+
+/// How an orthographic view's extent follows window resizes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ResizePolicy {
+    /// Keep the visible height; a wider window shows more to the sides.
+    #[default]
+    FixedVertical,
+    /// Keep the visible width; a taller window shows more above and below.
+    FixedHorizontal,
+    /// Keep whatever fit in the shorter window side visible, in both directions.
+    FitContent,
+}
+
+impl ResizePolicy {
+    /// New visible height for a view `height` tall when the aspect ratio
+    /// (width / height) changes from `old_aspect` to `new_aspect`.
+    pub fn rescale(self, height: f32, old_aspect: f32, new_aspect: f32) -> f32 {
+        if old_aspect <= 0.0 || new_aspect <= 0.0 {
+            return height;
+        }
+        match self {
+            Self::FixedVertical => height,
+            Self::FixedHorizontal => height * old_aspect / new_aspect,
+            Self::FitContent => {
+                // Side of the largest centered square that was visible.
+                let content = height * old_aspect.min(1.0);
+                content / new_aspect.min(1.0)
+            }
+        }
+    }
+}
+
+/// Which world axis points up. The crate's conventions are written for Y-up;
+/// Z-up, common in crystallography and materials, maps them through `basis`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum UpAxis {
+    #[default]
+    Y,
+    Z,
+}
+
+impl UpAxis {
+    pub fn up(self) -> Vector3<f32> {
+        match self {
+            Self::Y => Vector3::y(),
+            Self::Z => Vector3::z(),
+        }
+    }
+
+    /// Rotation from Y-up directions to this convention: the identity for Y;
+    /// for Z a quarter turn about X, taking +Y to +Z and +Z to -Y, so the
+    /// front view looks along +Y.
+    pub fn basis(self) -> UnitQuaternion<f32> {
+        match self {
+            Self::Y => UnitQuaternion::identity(),
+            Self::Z => {
+                UnitQuaternion::from_axis_angle(&Vector3::x_axis(), std::f32::consts::FRAC_PI_2)
+            }
+        }
+    }
+}
+
+/// Axis-aligned views, as on Blender's numpad.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViewPreset {
+    Front,
+    Back,
+    Right,
+    Left,
+    /// Looking down the up axis, with the back of the scene at screen top.
+    Top,
+    Bottom,
+}
+
+impl ViewPreset {
+    /// The view of `center` from `radius` away under `up_axis`. With Y-up,
+    /// front looks down -Z; with Z-up, along +Y.
+    pub fn view(self, up_axis: UpAxis, center: Point3<f32>, radius: f32) -> ViewBookmark {
+        // Eye direction from the center and screen up, for Y-up.
+        let (eye, up) = match self {
+            Self::Front => (Vector3::z(), Vector3::y()),
+            Self::Back => (-Vector3::z(), Vector3::y()),
+            Self::Right => (Vector3::x(), Vector3::y()),
+            Self::Left => (-Vector3::x(), Vector3::y()),
+            Self::Top => (Vector3::y(), -Vector3::z()),
+            Self::Bottom => (-Vector3::y(), Vector3::z()),
+        };
+        let basis = up_axis.basis();
+        ViewBookmark::from_look_at(center + basis * eye * radius, center, basis * up)
+    }
+}
+
 pub trait Camera {
     fn view_matrix(&self) -> Matrix4<f32>;
     fn projection_matrix(&self) -> Matrix4<f32>;
@@ -19,8 +119,20 @@ pub trait Camera {
     fn target(&self) -> Point3<f32>;
     fn up(&self) -> Vector3<f32>;
 
+    /// The world up this camera orbits about.
+    fn up_axis(&self) -> UpAxis {
+        UpAxis::Y
+    }
+
     fn set_aspect(&mut self, aspect: f32);
 
+    /// How the view extent follows `set_aspect`. Cameras without an
+    /// orthographic mode ignore it.
+    fn set_resize_policy(&mut self, _policy: ResizePolicy) {}
+    fn resize_policy(&self) -> ResizePolicy {
+        ResizePolicy::FixedVertical
+    }
+
     fn orbit(&mut self, delta_x: f32, delta_y: f32);
     fn pan(&mut self, delta: Vector2<f32>);
     fn dolly(&mut self, delta: f32);
@@ -29,21 +141,54 @@ pub trait Camera {
     fn near(&self) -> f32;
     fn far(&self) -> f32;
 
+    /// Sets the vertical field of view in radians, clamped to
+    /// `MIN_FOV_Y..=MAX_FOV_Y`. No-op for cameras with a fixed fov.
+    fn set_fov(&mut self, _fov_y: f32) {}
+
+    fn projection(&self) -> ProjectionType {
+        ProjectionType::Perspective
+    }
+    /// No-op for cameras that only support perspective.
+    fn set_projection(&mut self, _projection: ProjectionType) {}
+
+    /// Visible height in world units when orthographic. Cameras that only
+    /// support perspective give the frustum's height at the target.
+    fn ortho_scale(&self) -> f32 {
+        2.0 * (self.target() - self.position()).norm() * (self.fov_y() / 2.0).tan()
+    }
+
     // Optional helper to set look_at if possible, otherwise it might be specific implementation dependent
     fn look_at(&mut self, eye: Point3<f32>, target: Point3<f32>, up: Vector3<f32>);
 
+    /// Points the camera at `mol` from its current direction, just far
+    /// enough back that a sphere around `Molecule::frame_bounds`, `margin`
+    /// Å beyond its corners, fills the vertical field of view (or the
+    /// orthographic view height).
+    fn fit_to_molecule(&mut self, mol: &Molecule, margin: f32) {
+        let (min, max) = mol.frame_bounds();
+        let center = nalgebra::center(&min, &max);
+        let radius = (max - min).norm() / 2.0 + margin.max(0.0);
+        let distance = radius / (self.fov_y() / 2.0).tan();
+        let back = (self.position() - self.target())
+            .try_normalize(1e-6)
+            .unwrap_or_else(Vector3::z);
+        let up = self.up();
+        // An orthographic camera sizes its view from the distance when it
+        // switches back.
+        let projection = self.projection();
+        self.set_projection(ProjectionType::Perspective);
+        self.look_at(center + back * distance, center, up);
+        self.set_projection(projection);
+    }
+
     // Ray casting from screen coordinates to world coordinates
     // u, v: screen coordinates (pixels)
     // width, height: screen dimensions (pixels)
     // returns: (origin, direction)
-    fn ray_from_screen(
-        &self,
-        u: f32,
-        v: f32,
-        width: f32,
-        height: f32,
-    ) -> (lin_alg::f32::Vec3, lin_alg::f32::Vec3) {
-
+    //
+    // Perspective rays start at the eye. Orthographic rays are parallel to the
+    // view direction and start on the near plane under the cursor.
+    fn screen_ray(&self, u: f32, v: f32, width: f32, height: f32) -> (Point3<f32>, Vector3<f32>) {
         let inv_vp = self
             .view_projection()
             .try_inverse()
@@ -51,45 +196,187 @@ pub trait Camera {
 
         // Screen center origin assumed
         let ndc_x = -1.0 + 2.0 * u / width;
-        let ndc_y = 1.0 - 2.0 * v / height; 
+        let ndc_y = 1.0 - 2.0 * v / height;
 
         // D3D / Metal depth range
-        let point_ndc_near = Point3::new(ndc_x, ndc_y, -1.0).to_homogeneous();
-        let point_ndc_far  = Point3::new(ndc_x, ndc_y, 0.0).to_homogeneous();
+        let unproject = |ndc_z: f32| {
+            let world = inv_vp * Point3::new(ndc_x, ndc_y, ndc_z).to_homogeneous();
+            Point3::from(world.xyz() / world.w)
+        };
+        let p_far = unproject(0.0);
 
-        let world_near = inv_vp * point_ndc_near;
-        let world_far  = inv_vp * point_ndc_far;
+        let camera_pos = self.position();
+        let origin = match self.projection() {
+            ProjectionType::Perspective => camera_pos,
+            ProjectionType::Orthographic => unproject(-1.0),
+        };
 
-        let p_near = world_near.xyz() / world_near.w;
-        let p_far  = world_far.xyz()  / world_far.w;
+        let ray_direction = (p_far - origin).normalize();
 
-        let camera_pos = self.position();
+        // Mirrored in x about the eye, the origin as the direction.
+        (
+            Point3::new(2.0 * camera_pos.x - origin.x, origin.y, origin.z),
+            Vector3::new(-ray_direction.x, ray_direction.y, ray_direction.z),
+        )
+    }
+
+    /// The view as PyMOL's `get_view`/`set_view` 18-float list:
+    ///
+    /// - 0..9: the world-to-camera rotation, column-major. Both use a
+    ///   right-handed camera space looking down -Z with +Y up, so the identity
+    ///   means looking down world -Z.
+    /// - 9..12: the orbit center in camera space, `(0, 0, -distance)`.
+    /// - 12..15: the orbit center in world space.
+    /// - 15, 16: `near` and `far`, PyMOL's front and rear clip distances.
+    /// - 17: `fov_y` in degrees, negated for perspective (PyMOL's ortho flag).
+    fn to_pymol_view(&self) -> [f32; 18] {
+        let view = ViewBookmark::from_camera(self);
+        let to_camera = view.rotation.inverse().to_rotation_matrix();
+        let mut out = [0.0; 18];
+        out[..9].copy_from_slice(to_camera.matrix().as_slice());
+        out[9..12].copy_from_slice(&[0.0, 0.0, -view.radius]);
+        out[12..15].copy_from_slice(view.center.coords.as_slice());
+        out[15] = self.near();
+        out[16] = self.far();
+        let fov = self.fov_y().to_degrees();
+        out[17] = match self.projection() {
+            ProjectionType::Orthographic => fov,
+            ProjectionType::Perspective => -fov,
+        };
+        out
+    }
+
+    /// `screen_ray` in the `graphics` crate's vector type.
+    #[cfg(feature = "render")]
+    fn ray_from_screen(
+        &self,
+        u: f32,
+        v: f32,
+        width: f32,
+        height: f32,
+    ) -> (lin_alg::f32::Vec3, lin_alg::f32::Vec3) {
+        let (origin, direction) = self.screen_ray(u, v, width, height);
+        (convert::to_la(origin), convert::vec_to_la(direction))
+    }
+}
+
+/// A saved camera view: orbit center, camera-to-world rotation and distance.
+///
+/// The camera sits at `center + rotation * (0, 0, radius)` looking at `center`,
+/// with `rotation * Y` as its up vector (the `OrbitalCamera` convention). All
+/// frames are right-handed; in camera space +X is screen right, +Y screen up,
+/// and the camera looks down -Z, as in OpenGL, Blender and PyMOL.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ViewBookmark {
+    pub center: Point3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+    pub radius: f32,
+}
+
+impl ViewBookmark {
+    pub fn from_camera<C: Camera + ?Sized>(camera: &C) -> Self {
+        Self::from_look_at(camera.position(), camera.target(), camera.up())
+    }
 
-        // if p_far and p_near and eye are not on the same line, it is error.
-        if (p_far - camera_pos.coords).normalize().dot(&(p_near - camera_pos.coords).normalize()) < 0.999 {
-            eprintln!("Warning: ray_from_screen may be inaccurate due to non-linear projection. Consider using a linear projection for accurate picking.");
+    /// The view from `eye` towards `target`, rolled so `up` projects to
+    /// screen up; `up` need not be perpendicular to the view direction. When
+    /// it is parallel, or `eye == target`, the nearest of world +Y, +Z stands
+    /// in, and a zero-length view looks down -Z.
+    pub fn from_look_at(eye: Point3<f32>, target: Point3<f32>, up: Vector3<f32>) -> Self {
+        let forward = (target - eye).try_normalize(1e-6).unwrap_or(-Vector3::z());
+        let perpendicular = |v: Vector3<f32>| (v - forward * v.dot(&forward)).try_normalize(1e-4);
+        let up = perpendicular(up)
+            .or_else(|| perpendicular(Vector3::y()))
+            .or_else(|| perpendicular(Vector3::z()))
+            .unwrap();
+        let right = forward.cross(&up);
+        let basis = Rotation3::from_basis_unchecked(&[right, up, -forward]);
+        Self {
+            center: target,
+            rotation: UnitQuaternion::from_rotation_matrix(&basis),
+            radius: (eye - target).magnitude(),
         }
-        println!("Ray from screen: near {:?}, far {:?}, camera_pos {:?}", p_near, p_far, camera_pos);
+    }
 
-        let ray_origin = lin_alg::f32::Vec3::new(
-            camera_pos.x,
-            camera_pos.y,
-            camera_pos.z,
-        );
+    /// `(eye, target, up)` for this view, `up` a unit vector perpendicular to
+    /// the view direction. `from_look_at` takes them back.
+    pub fn to_look_at(&self) -> (Point3<f32>, Point3<f32>, Vector3<f32>) {
+        (self.position(), self.center, self.up())
+    }
 
-        let ray_direction = (p_far - camera_pos.coords).normalize();
+    /// The view with camera-to-world rotation `Rz(yaw) * Ry(pitch) *
+    /// Rx(roll)`: roll about the camera's own X, then pitch about Y, then
+    /// yaw about world Z. Angles in radians, positive counterclockwise
+    /// looking down the axis towards the origin. All zero looks down world -Z
+    /// with +Y up.
+    pub fn from_euler_zyx(
+        center: Point3<f32>,
+        radius: f32,
+        yaw: f32,
+        pitch: f32,
+        roll: f32,
+    ) -> Self {
+        Self {
+            center,
+            rotation: UnitQuaternion::from_euler_angles(roll, pitch, yaw),
+            radius,
+        }
+    }
 
+    /// `(yaw, pitch, roll)` as taken by `from_euler_zyx`, with pitch in
+    /// `[-π/2, π/2]` and yaw, roll in `(-π, π]`. At pitch ±π/2 only yaw ∓ roll
+    /// is determined, and it is all put in yaw.
+    pub fn to_euler_zyx(&self) -> (f32, f32, f32) {
+        // nalgebra's `euler_angles` loses yaw and roll near the gimbal lock in
+        // f32, so read them off the matrix with atan2 instead.
+        let m = self.rotation.to_rotation_matrix().into_inner();
+        let cos_pitch = m[(0, 0)].hypot(m[(1, 0)]);
+        let pitch = (-m[(2, 0)]).atan2(cos_pitch);
+        if cos_pitch < 1e-4 {
+            return ((-m[(0, 1)]).atan2(m[(1, 1)]), pitch, 0.0);
+        }
         (
-            ray_origin,
-            lin_alg::f32::Vec3::new(
-                -ray_direction.x,
-                ray_direction.y,
-                ray_direction.z,
-            ),
+            m[(1, 0)].atan2(m[(0, 0)]),
+            pitch,
+            m[(2, 1)].atan2(m[(2, 2)]),
         )
     }
-}
 
+    pub fn position(&self) -> Point3<f32> {
+        self.center + self.rotation * Vector3::new(0.0, 0.0, self.radius)
+    }
+
+    pub fn up(&self) -> Vector3<f32> {
+        self.rotation * Vector3::y()
+    }
+
+    /// This view turned so `axis` points straight up on screen, keeping the
+    /// center and radius. Uses the smallest rotation that does it: a roll about
+    /// the view direction, or a tilt when looking (almost) along `axis`.
+    pub fn upright(&self, axis: Vector3<f32>) -> Self {
+        let Some(axis) = axis.try_normalize(1e-6) else {
+            return *self;
+        };
+        let forward = self.rotation * -Vector3::z();
+        let screen_axis = (axis - forward * axis.dot(&forward))
+            .try_normalize(1e-3)
+            .unwrap_or(axis);
+        let turn =
+            UnitQuaternion::rotation_between(&self.up(), &screen_axis).unwrap_or_else(|| {
+                // Upside down: half a turn about the view direction.
+                UnitQuaternion::from_axis_angle(&Unit::new_normalize(forward), std::f32::consts::PI)
+            });
+        Self {
+            rotation: turn * self.rotation,
+            ..*self
+        }
+    }
+
+    /// Moves `camera` to this view.
+    pub fn apply<C: Camera + ?Sized>(&self, camera: &mut C) {
+        camera.look_at(self.position(), self.center, self.up());
+    }
+}
 
 // =========================================================================
 // Orbital Camera
@@ -104,6 +391,13 @@ pub struct OrbitalCamera {
     pub aspect: f32,
     pub near: f32,
     pub far: f32,
+
+    pub projection: ProjectionType,
+    /// Visible height in world units when orthographic. Width follows from `aspect`.
+    pub ortho_scale: f32,
+    pub resize_policy: ResizePolicy,
+    /// Horizontal orbiting turns about this axis, keeping the horizon level.
+    pub up_axis: UpAxis,
 }
 
 impl Default for OrbitalCamera {
@@ -116,19 +410,22 @@ impl Default for OrbitalCamera {
             aspect: 1.0,
             near: 0.1,
             far: 100.0,
+            projection: ProjectionType::Perspective,
+            ortho_scale: 10.0,
+            resize_policy: ResizePolicy::FixedVertical,
+            up_axis: UpAxis::Y,
         }
     }
 }
 
 impl OrbitalCamera {
-    fn view_to_world(&self) -> Isometry3<f32> {
-        let eye = self.position();
-        let target = self.target();
-        let up = self.up();
-        Isometry3::look_at_rh(&eye, &target, &up)
-    }
-    fn world_to_view(&self) -> Isometry3<f32> {
-        self.view_to_world().inverse()
+    /// The default camera for `up_axis`, showing the front view.
+    pub fn with_up_axis(up_axis: UpAxis) -> Self {
+        Self {
+            rotation: up_axis.basis(),
+            up_axis,
+            ..Default::default()
+        }
     }
 }
 
@@ -141,7 +438,17 @@ impl Camera for OrbitalCamera {
     }
 
     fn projection_matrix(&self) -> Matrix4<f32> {
-        Perspective3::new(self.aspect, self.fov_y, self.near, self.far).to_homogeneous()
+        match self.projection {
+            ProjectionType::Perspective => {
+                Perspective3::new(self.aspect, self.fov_y, self.near, self.far).to_homogeneous()
+            }
+            ProjectionType::Orthographic => {
+                let half_h = self.ortho_scale * 0.5;
+                let half_w = half_h * self.aspect;
+                Orthographic3::new(-half_w, half_w, -half_h, half_h, self.near, self.far)
+                    .to_homogeneous()
+            }
+        }
     }
 
     fn position(&self) -> Point3<f32> {
@@ -156,25 +463,39 @@ impl Camera for OrbitalCamera {
         self.rotation * Vector3::y()
     }
 
+    fn up_axis(&self) -> UpAxis {
+        self.up_axis
+    }
+
     fn set_aspect(&mut self, aspect: f32) {
+        self.ortho_scale = self
+            .resize_policy
+            .rescale(self.ortho_scale, self.aspect, aspect);
         self.aspect = aspect;
     }
 
+    fn set_resize_policy(&mut self, policy: ResizePolicy) {
+        self.resize_policy = policy;
+    }
+
+    fn resize_policy(&self) -> ResizePolicy {
+        self.resize_policy
+    }
+
     fn orbit(&mut self, delta_x: f32, delta_y: f32) {
         // let local_rot_axis = self.view_to_world() * (delta_x * Vector3::y() + delta_y * Vector3::x());
         // let rot = UnitQuaternion::from_axis_angle(&Unit::new_normalize(local_rot_axis), local_rot_axis.magnitude());
         // self.rotation = rot * self.rotation;
-        if delta_x < delta_y{
+        if delta_x < delta_y {
             // let rot_y = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), delta_x);
             let rot_x = UnitQuaternion::from_axis_angle(&Vector3::x_axis(), delta_y);
-            self.rotation =   rot_x *self.rotation;
-        }else{
-            let rot_y = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), delta_x);
+            self.rotation = rot_x * self.rotation;
+        } else {
+            let rot_y =
+                UnitQuaternion::from_axis_angle(&Unit::new_unchecked(self.up_axis.up()), delta_x);
             // let rot_x = UnitQuaternion::from_axis_angle(&Vector3::x_axis(), delta_y);
-            self.rotation =   rot_y *self.rotation;
+            self.rotation = rot_y * self.rotation;
         }
-       
-
     }
 
     fn pan(&mut self, delta: Vector2<f32>) {
@@ -201,14 +522,38 @@ impl Camera for OrbitalCamera {
         self.far
     }
 
-    fn look_at(&mut self, eye: Point3<f32>, target: Point3<f32>, up: Vector3<f32>) {
-        self.center = target;
+    fn set_fov(&mut self, fov_y: f32) {
+        self.fov_y = fov_y.clamp(MIN_FOV_Y, MAX_FOV_Y);
+    }
 
-        let dir = eye - target;
-        self.radius = dir.magnitude();
+    fn projection(&self) -> ProjectionType {
+        self.projection
+    }
+
+    fn ortho_scale(&self) -> f32 {
+        self.ortho_scale
+    }
 
-        let iso = Isometry3::look_at_rh(&eye, &target, &up);
-        self.rotation = iso.rotation.inverse();
+    /// Switching keeps the subject the same size on screen: the orthographic
+    /// height matches the perspective frustum's height at the orbit center.
+    fn set_projection(&mut self, projection: ProjectionType) {
+        let half_tan = (self.fov_y * 0.5).tan();
+        match (self.projection, projection) {
+            (ProjectionType::Perspective, ProjectionType::Orthographic) => {
+                self.ortho_scale = 2.0 * self.radius * half_tan;
+            }
+            (ProjectionType::Orthographic, ProjectionType::Perspective) => {
+                self.radius = (self.ortho_scale / (2.0 * half_tan)).max(0.1);
+            }
+            _ => {}
+        }
+        self.projection = projection;
     }
 
+    fn look_at(&mut self, eye: Point3<f32>, target: Point3<f32>, up: Vector3<f32>) {
+        let view = ViewBookmark::from_look_at(eye, target, up);
+        self.center = view.center;
+        self.rotation = view.rotation;
+        self.radius = view.radius;
+    }
 }
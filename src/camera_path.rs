@@ -0,0 +1,167 @@
+//! Keyframed camera paths for scripted fly-throughs.
+
+use crate::camera::ViewBookmark;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::{Add, Mul, Sub};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CameraPathError {
+    /// A path needs at least two keyframes to be sampled.
+    TooFewKeyframes(usize),
+    /// Keyframe `index` has the same time as the one before it.
+    DuplicateTime { index: usize, time: f32 },
+    /// Keyframe `index` is earlier than the one before it.
+    NonMonotonic { index: usize, time: f32 },
+    /// Keyframe `index` has an infinite or NaN time.
+    NonFiniteTime { index: usize, time: f32 },
+    /// `sample` was asked for a NaN time.
+    NanSampleTime,
+    /// Playback duration must be positive.
+    InvalidDuration(f32),
+}
+
+impl fmt::Display for CameraPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooFewKeyframes(n) => {
+                write!(f, "camera path needs at least 2 keyframes, has {}", n)
+            }
+            Self::DuplicateTime { index, time } => write!(
+                f,
+                "keyframe {} has the same time ({}) as the previous keyframe",
+                index, time
+            ),
+            Self::NonMonotonic { index, time } => write!(
+                f,
+                "keyframe {} at time {} is earlier than the previous keyframe",
+                index, time
+            ),
+            Self::NonFiniteTime { index, time } => {
+                write!(f, "keyframe {} has a non-finite time ({})", index, time)
+            }
+            Self::NanSampleTime => write!(f, "cannot sample a camera path at time NaN"),
+            Self::InvalidDuration(d) => write!(f, "path duration must be positive, got {}", d),
+        }
+    }
+}
+
+impl std::error::Error for CameraPathError {}
+
+/// A camera path through timed `ViewBookmark`s.
+///
+/// Orbit center and distance follow a Catmull-Rom spline (with tangents scaled
+/// for uneven keyframe spacing); orientation is slerped between keyframes.
+/// Keyframes must be added in strictly increasing time order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CameraPath {
+    keyframes: Vec<(f32, ViewBookmark)>,
+}
+
+impl CameraPath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn keyframe(mut self, time: f32, view: ViewBookmark) -> Self {
+        self.keyframes.push((time, view));
+        self
+    }
+
+    pub fn keyframes(&self) -> &[(f32, ViewBookmark)] {
+        &self.keyframes
+    }
+
+    pub fn validate(&self) -> Result<(), CameraPathError> {
+        if self.keyframes.len() < 2 {
+            return Err(CameraPathError::TooFewKeyframes(self.keyframes.len()));
+        }
+        if let Some((index, &(time, _))) = self
+            .keyframes
+            .iter()
+            .enumerate()
+            .find(|(_, k)| !k.0.is_finite())
+        {
+            return Err(CameraPathError::NonFiniteTime { index, time });
+        }
+        for (index, pair) in self.keyframes.windows(2).enumerate() {
+            let (prev, time) = (pair[0].0, pair[1].0);
+            if time == prev {
+                return Err(CameraPathError::DuplicateTime {
+                    index: index + 1,
+                    time,
+                });
+            }
+            if time < prev {
+                return Err(CameraPathError::NonMonotonic {
+                    index: index + 1,
+                    time,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Time of the first keyframe, or 0 for an empty path.
+    pub fn start_time(&self) -> f32 {
+        self.keyframes.first().map_or(0.0, |k| k.0)
+    }
+
+    /// Time of the last keyframe, or 0 for an empty path.
+    pub fn end_time(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.0)
+    }
+
+    /// Interpolated view at `time`, clamped to the path's time range.
+    pub fn sample(&self, time: f32) -> Result<ViewBookmark, CameraPathError> {
+        self.validate()?;
+        if time.is_nan() {
+            return Err(CameraPathError::NanSampleTime);
+        }
+        let keys = &self.keyframes;
+        let time = time.clamp(self.start_time(), self.end_time());
+        let i = keys
+            .windows(2)
+            .position(|pair| time <= pair[1].0)
+            .unwrap_or(keys.len() - 2);
+
+        let (t0, k0) = keys[i];
+        let (t1, k1) = keys[i + 1];
+        let u = (time - t0) / (t1 - t0);
+
+        let center = hermite_segment(keys, i, time, |k| k.center.coords);
+        let radius = hermite_segment(keys, i, time, |k| k.radius);
+        let rotation = k0
+            .rotation
+            .try_slerp(&k1.rotation, u, 1e-6)
+            .unwrap_or(if u < 0.5 { k0.rotation } else { k1.rotation });
+
+        Ok(ViewBookmark {
+            center: center.into(),
+            rotation,
+            radius: radius.max(0.0),
+        })
+    }
+}
+
+/// Evaluates the Catmull-Rom segment between keyframes `i` and `i + 1` at `time`.
+fn hermite_segment<T, F>(keys: &[(f32, ViewBookmark)], i: usize, time: f32, value: F) -> T
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T>,
+    F: Fn(&ViewBookmark) -> T,
+{
+    let tangent = |k: usize| {
+        let prev = k.saturating_sub(1);
+        let next = (k + 1).min(keys.len() - 1);
+        (value(&keys[next].1) - value(&keys[prev].1)) * (1.0 / (keys[next].0 - keys[prev].0))
+    };
+    let (t0, t1) = (keys[i].0, keys[i + 1].0);
+    let h = t1 - t0;
+    let u = (time - t0) / h;
+    let (u2, u3) = (u * u, u * u * u);
+
+    value(&keys[i].1) * (2.0 * u3 - 3.0 * u2 + 1.0)
+        + tangent(i) * ((u3 - 2.0 * u2 + u) * h)
+        + value(&keys[i + 1].1) * (-2.0 * u3 + 3.0 * u2)
+        + tangent(i + 1) * ((u3 - u2) * h)
+}
@@ -0,0 +1,260 @@
+//! Automatic captures of the viewer's state when notable events happen, so a
+//! vague bug report can come with files that reproduce what the user saw.
+//!
+//! Install a hook with `MoleculeViewer::set_capture_hook`. Each event the
+//! hook's `EventMask` selects records what the active molecule looked like
+//! at that moment, and the camera view on screen then: the one passed to the
+//! last `MoleculeViewer::write_captures`, or, before the first call, the one
+//! passed to it next. `write_captures` writes each capture as an image,
+//! `capture-<unix ms>-<n>.png`, ray cast on the CPU by `raster::render`, and
+//! a JSON file of the same name:
+//!
+//! ```text
+//! {
+//!   "event": "AtomClicked(3)",
+//!   "unix_time_ms": 1760000000000,
+//!   "view": { "center": [x, y, z], "rotation": [x, y, z, w], "radius": r },
+//!   "image": "capture-1760000000000-0.png",
+//!   "display_list": { ... }   // see `display_list`
+//! }
+//! ```
+//!
+//! Capturing needs the `export` feature. Without it the hook can still be
+//! set, but records and writes nothing.
+// Without `export`, `MoleculeViewer` never hands the hook an event.
+#![cfg_attr(not(feature = "export"), allow(dead_code))]
+
+use crate::camera::ViewBookmark;
+use crate::clock::{Clock, MonotonicClock};
+use crate::display_list::DisplayList;
+#[cfg(feature = "export")]
+use crate::raster;
+use crate::raster::Lens;
+use crate::viewer::ViewerEvent;
+use serde::{Deserialize, Serialize};
+use std::ops::BitOr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Default `CaptureHook::min_interval`.
+pub const CAPTURE_MIN_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Default `CaptureHook::image_size`.
+pub const CAPTURE_IMAGE_SIZE: (usize, usize) = (640, 480);
+
+/// A set of `ViewerEvent` kinds. Combine with `|`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EventMask(u32);
+
+impl EventMask {
+    pub const NONE: Self = Self(0);
+    pub const ATOM_CLICKED: Self = Self(1 << 0);
+    pub const BOND_CLICKED: Self = Self(1 << 1);
+    pub const NOTHING_CLICKED: Self = Self(1 << 2);
+    /// `AtomHovered`, `BondHovered` and `NothingHovered`.
+    pub const HOVERED: Self = Self(1 << 3);
+    pub const AUTO_STYLE_APPLIED: Self = Self(1 << 4);
+    pub const GIZMO_DRAGGED: Self = Self(1 << 5);
+    pub const COMMAND_FAILED: Self = Self(1 << 6);
+    pub const ATOMS_DELETED: Self = Self(1 << 7);
+    pub const EDIT_REQUESTED: Self = Self(1 << 8);
+    /// `BondCreated` and `BondDeleted`.
+    pub const BOND_EDITED: Self = Self(1 << 9);
+    /// `AtomDragged` and `AtomDragEnded`.
+    pub const ATOM_DRAGGED: Self = Self(1 << 10);
+    pub const ATOMS_MOVED: Self = Self(1 << 11);
+    pub const CUTAWAY_CHANGED: Self = Self(1 << 12);
+    pub const SELECTION_CHANGED: Self = Self(1 << 13);
+    pub const ATOMS_SELECTED: Self = Self(1 << 14);
+    /// The three click events.
+    pub const CLICKS: Self = Self(0b111);
+    pub const ALL: Self = Self((1 << 15) - 1);
+
+    /// The kind of `event`.
+    pub fn of(event: &ViewerEvent) -> Self {
+        match event {
+            ViewerEvent::AtomClicked(_) => Self::ATOM_CLICKED,
+            ViewerEvent::BondClicked(_) => Self::BOND_CLICKED,
+            ViewerEvent::NothingClicked => Self::NOTHING_CLICKED,
+            ViewerEvent::AtomHovered(_)
+            | ViewerEvent::BondHovered(_)
+            | ViewerEvent::NothingHovered => Self::HOVERED,
+            ViewerEvent::AutoStyleApplied(_) => Self::AUTO_STYLE_APPLIED,
+            ViewerEvent::GizmoDragged(_) => Self::GIZMO_DRAGGED,
+            ViewerEvent::CommandFailed(_) => Self::COMMAND_FAILED,
+            ViewerEvent::AtomsDeleted(_) => Self::ATOMS_DELETED,
+            ViewerEvent::EditRequested(_) => Self::EDIT_REQUESTED,
+            ViewerEvent::BondCreated(_) | ViewerEvent::BondDeleted(_) => Self::BOND_EDITED,
+            ViewerEvent::AtomDragged { .. } | ViewerEvent::AtomDragEnded => Self::ATOM_DRAGGED,
+            ViewerEvent::AtomsMoved(_) => Self::ATOMS_MOVED,
+            ViewerEvent::CutawayChanged(_) => Self::CUTAWAY_CHANGED,
+            ViewerEvent::SelectionChanged(_) => Self::SELECTION_CHANGED,
+            ViewerEvent::AtomsSelected(_) => Self::ATOMS_SELECTED,
+        }
+    }
+
+    pub fn contains(self, event: &ViewerEvent) -> bool {
+        self.0 & Self::of(event).0 != 0
+    }
+}
+
+impl BitOr for EventMask {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// One capture file, as written by `MoleculeViewer::write_captures`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaptureRecord {
+    /// The event, as printed with `{:?}`.
+    pub event: String,
+    /// When the event happened, in milliseconds since the Unix epoch.
+    pub unix_time_ms: u64,
+    /// The camera view on screen when the event happened.
+    pub view: ViewBookmark,
+    /// File name of the image, in the same directory. Empty in captures
+    /// written before images were.
+    #[serde(default)]
+    pub image: String,
+    /// The active molecule as drawn when the event happened.
+    pub display_list: DisplayList,
+}
+
+impl CaptureRecord {
+    pub fn from_json(path: &Path) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&json).map_err(|e| e.to_string())
+    }
+}
+
+/// Captured event waiting for `write_captures`.
+struct PendingCapture {
+    event: ViewerEvent,
+    unix_time_ms: u64,
+    /// `None` when the event came before any view was known.
+    view: Option<(ViewBookmark, Lens)>,
+    display_list: DisplayList,
+}
+
+/// Which events `MoleculeViewer` captures, and where to. See the module docs.
+pub struct CaptureHook {
+    pub events: EventMask,
+    pub dir: PathBuf,
+    /// Matching events less than this long after the last capture are not
+    /// captured, so a burst of clicks or failures yields one file.
+    pub min_interval: Duration,
+    /// Width and height of the images, in pixels.
+    pub image_size: (usize, usize),
+    clock: Box<dyn Clock>,
+    last_capture: Option<Duration>,
+    /// The view passed to the last `write`, on screen until the next.
+    view: Option<(ViewBookmark, Lens)>,
+    pending: Vec<PendingCapture>,
+    written: usize,
+}
+
+impl CaptureHook {
+    pub fn new(events: EventMask, dir: PathBuf) -> Self {
+        Self {
+            events,
+            dir,
+            min_interval: CAPTURE_MIN_INTERVAL,
+            image_size: CAPTURE_IMAGE_SIZE,
+            clock: Box::new(MonotonicClock::new()),
+            last_capture: None,
+            view: None,
+            pending: Vec::new(),
+            written: 0,
+        }
+    }
+
+    /// Replaces the time source used for `min_interval`.
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.clock = Box::new(clock);
+    }
+
+    /// Captures waiting to be written.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Whether `event` is to be captured now. Starts the `min_interval` wait
+    /// if so.
+    pub(crate) fn accept(&mut self, event: &ViewerEvent) -> bool {
+        if !self.events.contains(event) {
+            return false;
+        }
+        let now = self.clock.now();
+        if let Some(last) = self.last_capture {
+            if now.saturating_sub(last) < self.min_interval {
+                return false;
+            }
+        }
+        self.last_capture = Some(now);
+        true
+    }
+
+    pub(crate) fn record(&mut self, event: ViewerEvent, display_list: DisplayList) {
+        let unix_time_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |t| t.as_millis() as u64);
+        self.pending.push(PendingCapture {
+            event,
+            unix_time_ms,
+            view: self.view,
+            display_list,
+        });
+    }
+
+    /// Writes the pending captures, those without a view with `view`,
+    /// creating `dir` if needed, and keeps `view` for the captures recorded
+    /// until the next call. Captures that could not be written are dropped.
+    #[cfg(feature = "export")]
+    pub(crate) fn write(&mut self, view: ViewBookmark, lens: Lens) -> Result<Vec<PathBuf>, String> {
+        self.view = Some((view, lens));
+        let pending = std::mem::take(&mut self.pending);
+        if pending.is_empty() {
+            return Ok(Vec::new());
+        }
+        std::fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        let mut paths = Vec::with_capacity(pending.len());
+        let (width, height) = self.image_size;
+        for capture in pending {
+            let (view, lens) = capture.view.unwrap_or((view, lens));
+            let name = format!("capture-{}-{}", capture.unix_time_ms, self.written);
+            self.written += 1;
+            let image = self.dir.join(format!("{name}.png"));
+            raster::render(
+                &capture.display_list,
+                &view,
+                lens,
+                width,
+                height,
+                raster::BACKGROUND,
+            )
+            .write_png(&image)?;
+            let record = CaptureRecord {
+                event: format!("{:?}", capture.event),
+                unix_time_ms: capture.unix_time_ms,
+                view,
+                image: format!("{name}.png"),
+                display_list: capture.display_list,
+            };
+            let path = self.dir.join(format!("{name}.json"));
+            let json = serde_json::to_string_pretty(&record).map_err(|e| e.to_string())?;
+            std::fs::write(&path, json).map_err(|e| format!("{}: {e}", path.display()))?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    /// Without the `export` feature nothing is recorded, so nothing is written.
+    #[cfg(not(feature = "export"))]
+    pub(crate) fn write(&mut self, view: ViewBookmark, lens: Lens) -> Result<Vec<PathBuf>, String> {
+        self.view = Some((view, lens));
+        Ok(Vec::new())
+    }
+}
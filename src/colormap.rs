@@ -0,0 +1,157 @@
+//! Atom color schemes, color-space helpers and per-molecule tints for
+//! telling copies of the same molecule apart (symmetry mates, trajectory
+//! ghosts).
+
+use crate::element;
+
+/// Linear RGB color with channels in 0..=1, as used by `graphics::Entity`.
+pub type Rgb = (f32, f32, f32);
+
+/// Color of atoms whose element `cpk_color` does not know.
+pub const UNKNOWN_ELEMENT_COLOR: Rgb = (0.7, 0.7, 0.7);
+
+/// How atoms are colored before overrides and tint.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ColorScheme {
+    /// By element, with `cpk_color`.
+    #[default]
+    Cpk,
+    /// Every atom in one color.
+    Monochrome(f32, f32, f32),
+    /// By `Molecule::chains`, one `categorical` color per chain letter.
+    /// Atoms with a blank chain, or molecules without chains, fall back to
+    /// `Cpk`.
+    ByChain,
+    /// By `Molecule::residues`, one `categorical` color per residue number.
+    /// Molecules without residues fall back to `Cpk`.
+    ByResidue,
+}
+
+/// Color of `element` in the CPK convention (carbon black, oxygen red,
+/// nitrogen blue, ...), with the less common elements as in Jmol. Symbols
+/// are matched ignoring case; unknown ones get `UNKNOWN_ELEMENT_COLOR`.
+pub fn cpk_color(symbol: &str) -> Rgb {
+    let Some(symbol) = element::symbol(symbol) else {
+        return UNKNOWN_ELEMENT_COLOR;
+    };
+    match symbol {
+        "H" => (0.9, 0.9, 0.9),
+        "He" => (0.85, 1.0, 1.0),
+        "Li" => (0.8, 0.5, 1.0),
+        "Be" => (0.76, 1.0, 0.0),
+        "B" => (1.0, 0.71, 0.71),
+        "C" => (0.1, 0.1, 0.1),
+        "N" => (0.1, 0.1, 0.9),
+        "O" => (0.9, 0.1, 0.1),
+        "F" => (0.56, 0.88, 0.31),
+        "Ne" => (0.7, 0.89, 0.96),
+        "Na" => (0.67, 0.36, 0.95),
+        "Mg" => (0.54, 1.0, 0.0),
+        "Al" => (0.75, 0.65, 0.65),
+        "Si" => (0.94, 0.78, 0.63),
+        "P" => (1.0, 0.6, 0.0),
+        "S" => (0.9, 0.9, 0.1),
+        "Cl" => (0.1, 0.9, 0.1),
+        "Ar" => (0.5, 0.82, 0.89),
+        "K" => (0.56, 0.25, 0.83),
+        "Ca" => (0.24, 1.0, 0.0),
+        "Ti" => (0.75, 0.76, 0.78),
+        "Cr" => (0.54, 0.6, 0.78),
+        "Mn" => (0.61, 0.48, 0.78),
+        "Fe" => (0.88, 0.4, 0.2),
+        "Co" => (0.94, 0.56, 0.63),
+        "Ni" => (0.31, 0.82, 0.31),
+        "Cu" => (0.78, 0.5, 0.2),
+        "Zn" => (0.49, 0.5, 0.69),
+        "Se" => (1.0, 0.63, 0.0),
+        "Br" => (0.65, 0.16, 0.16),
+        "Ag" => (0.75, 0.75, 0.75),
+        "Sn" => (0.4, 0.5, 0.5),
+        "I" => (0.58, 0.0, 0.58),
+        "Pt" => (0.82, 0.82, 0.88),
+        "Au" => (1.0, 0.82, 0.14),
+        "Hg" => (0.72, 0.72, 0.82),
+        _ => UNKNOWN_ELEMENT_COLOR,
+    }
+}
+
+/// The `index`-th of an endless run of colors, each hue a golden angle on
+/// from the last, so neighbouring indices always differ clearly.
+pub fn categorical(index: usize) -> Rgb {
+    const GOLDEN_ANGLE: f32 = 137.507_77;
+    hsl_to_rgb((index % 360) as f32 * GOLDEN_ANGLE, 0.65, 0.55)
+}
+
+/// Adjustment applied to every color of one molecule after its color scheme
+/// and any per-atom override.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TintMode {
+    /// Rotates the hue by this many degrees. Greys are unaffected.
+    HueShift(f32),
+    /// Blends toward grey of the same lightness; 0 keeps the color, 1 removes
+    /// all saturation.
+    Desaturate(f32),
+    /// Blends toward black; 0 keeps the color, 1 is black.
+    Darken(f32),
+}
+
+impl TintMode {
+    pub fn apply(self, color: Rgb) -> Rgb {
+        match self {
+            Self::HueShift(degrees) => {
+                let (h, s, l) = rgb_to_hsl(color);
+                hsl_to_rgb(h + degrees, s, l)
+            }
+            Self::Desaturate(amount) => {
+                let (h, s, l) = rgb_to_hsl(color);
+                hsl_to_rgb(h, s * (1.0 - amount.clamp(0.0, 1.0)), l)
+            }
+            Self::Darken(amount) => {
+                let keep = 1.0 - amount.clamp(0.0, 1.0);
+                (color.0 * keep, color.1 * keep, color.2 * keep)
+            }
+        }
+    }
+}
+
+/// Hue in degrees (0..360), saturation and lightness (0..=1).
+pub fn rgb_to_hsl((r, g, b): Rgb) -> (f32, f32, f32) {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) * 0.5;
+    let d = max - min;
+    if d <= f32::EPSILON {
+        return (0.0, 0.0, l);
+    }
+    let s = d / (1.0 - (2.0 * l - 1.0).abs());
+    let h = if max == r {
+        ((g - b) / d).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / d + 2.0
+    } else {
+        (r - g) / d + 4.0
+    };
+    (h * 60.0, s.min(1.0), l)
+}
+
+pub fn hsl_to_rgb(h: f32, s: f32, l: f32) -> Rgb {
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let h = h.rem_euclid(360.0) / 60.0;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = l - c * 0.5;
+    (r + m, g + m, b + m)
+}
+
+/// Rainbow colormap for scalars: `t` = 0 is blue, through cyan, green and
+/// yellow, to red at 1. Values outside 0..=1 are clamped.
+pub fn rainbow(t: f32) -> Rgb {
+    hsl_to_rgb(240.0 * (1.0 - t.clamp(0.0, 1.0)), 1.0, 0.5)
+}
@@ -0,0 +1,234 @@
+//! Thread-safe command queue for driving a `MoleculeViewer` from other threads.
+//!
+//! Worker threads send `ViewerCommand`s through a cloneable `ViewerHandle`
+//! (from `MoleculeViewer::handle`); the render thread applies them in order
+//! once per frame with `MoleculeViewer::drain_commands`. Commands that cannot be
+//! applied raise `ViewerEvent::CommandFailed` instead of panicking.
+
+use crate::atom_id::AtomId;
+use crate::colormap::{Rgb, TintMode};
+use crate::molecule::Molecule;
+use crate::selection::Selection;
+use crate::viewer::{AddResult, MoleculeViewer, RenderStyle};
+use crate::AdditionalRender;
+use nalgebra::Point3;
+use std::sync::mpsc::Sender;
+
+#[derive(Debug, Clone)]
+pub enum ViewerCommand {
+    /// Replaces the active molecule (`MoleculeViewer::set_molecule`).
+    SetMolecule(Molecule),
+    /// Adds a molecule in a new slot (`MoleculeViewer::add_molecule`); fails
+    /// for duplicates and other conformers of a loaded molecule.
+    AddMolecule(Molecule),
+    SetActiveMolecule(usize),
+    /// Replaces the selection with these atoms of the active molecule
+    /// (`MoleculeViewer::set_selection`).
+    Select(Vec<usize>),
+    /// `Select` by atom id.
+    SelectIds(Vec<AtomId>),
+    /// Render style of the active molecule.
+    SetStyle(RenderStyle),
+    /// Sets (or with `None`, removes) the color override of one atom of the
+    /// active molecule.
+    SetColorOverride {
+        atom: usize,
+        color: Option<Rgb>,
+    },
+    /// `SetColorOverride` by atom id.
+    SetColorOverrideById {
+        atom: AtomId,
+        color: Option<Rgb>,
+    },
+    SetTint {
+        molecule: usize,
+        tint: Option<TintMode>,
+    },
+    /// New positions for every atom of the active molecule, e.g. from a
+    /// simulation step.
+    UpdatePositions(Vec<Point3<f32>>),
+    /// An editing action on the active molecule (`MoleculeViewer::apply_edit`).
+    Edit(EditAction),
+}
+
+/// Edits bound to `CameraController`'s keyboard shortcuts, which report them
+/// as `ViewerEvent::EditRequested` for the host to apply or veto.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditAction {
+    /// Delete the selected atoms (`MoleculeViewer::delete_atoms`). Undoable.
+    DeleteSelected,
+    /// Select every drawn atom.
+    SelectAll,
+    /// Deselect everything and clear the measurements of every overlay that
+    /// is a `MeasurementTool`.
+    ClearSelection,
+    /// Add the selected atoms to `ViewerSettings::hidden_atoms`.
+    HideSelected,
+    /// Clear `ViewerSettings::hidden_atoms`.
+    ShowAll,
+    /// Select the drawn atoms that are not selected, deselecting the rest.
+    InvertSelection,
+    /// Drop the first atom picked in bond mode
+    /// (`MoleculeViewer::cancel_pending_bond`).
+    CancelBond,
+    /// Pin or unpin an atom (`MoleculeViewer::toggle_pin`).
+    TogglePin(usize),
+}
+
+/// Sends commands to the viewer it came from. Cheap to clone, `Send + Sync`.
+#[derive(Debug, Clone)]
+pub struct ViewerHandle {
+    sender: Sender<ViewerCommand>,
+}
+
+impl ViewerHandle {
+    pub(crate) fn new(sender: Sender<ViewerCommand>) -> Self {
+        Self { sender }
+    }
+
+    /// Queues `command`. Gives it back if the viewer has been dropped.
+    // Unboxed, like `mpsc::SendError`; the error path is rare.
+    #[allow(clippy::result_large_err)]
+    pub fn send(&self, command: ViewerCommand) -> Result<(), ViewerCommand> {
+        self.sender.send(command).map_err(|e| e.0)
+    }
+}
+
+impl<T: AdditionalRender> MoleculeViewer<T> {
+    /// Applies one command right away and sets `dirty` as needed.
+    /// `drain_commands` calls this for queued commands.
+    pub fn apply_command(&mut self, command: ViewerCommand) -> Result<(), String> {
+        match command {
+            ViewerCommand::SetMolecule(molecule) => self.set_molecule(molecule),
+            ViewerCommand::AddMolecule(molecule) => match self.add_molecule(molecule) {
+                AddResult::Added(_) => {}
+                AddResult::Duplicate(id) => {
+                    return Err(format!("add: duplicate of molecule {id}"));
+                }
+                AddResult::SameTopology(id) => {
+                    return Err(format!("add: same topology as molecule {id}"));
+                }
+            },
+            ViewerCommand::SetActiveMolecule(id) => {
+                if !self.set_active_molecule(id) {
+                    return Err(format!("no molecule {id}"));
+                }
+            }
+            ViewerCommand::Select(atoms) => {
+                let n_atoms = self.active_atom_count();
+                if let Some(&atom) = atoms.iter().find(|&&i| i >= n_atoms) {
+                    return Err(format!("select: no atom {atom}"));
+                }
+                self.set_selection(Selection::from_atoms(atoms));
+            }
+            ViewerCommand::SelectIds(ids) => {
+                let atoms = self
+                    .resolve_ids(&ids)
+                    .map_err(|id| format!("select: no atom {id}"))?;
+                self.apply_command(ViewerCommand::Select(atoms))?;
+            }
+            ViewerCommand::SetStyle(style) => self.set_render_style(style),
+            ViewerCommand::SetColorOverride { atom, color } => {
+                if atom >= self.active_atom_count() {
+                    return Err(format!("color override: no atom {atom}"));
+                }
+                match color {
+                    Some(color) => self.settings.color_overrides.insert(atom, color),
+                    None => self.settings.color_overrides.remove(&atom),
+                };
+                self.mark_colors_dirty();
+            }
+            ViewerCommand::SetColorOverrideById { atom, color } => {
+                let atom = self
+                    .resolve_ids(&[atom])
+                    .map_err(|id| format!("color override: no atom {id}"))?[0];
+                self.apply_command(ViewerCommand::SetColorOverride { atom, color })?;
+            }
+            ViewerCommand::SetTint { molecule, tint } => {
+                if !self.set_tint(molecule, tint) {
+                    return Err(format!("tint: no molecule {molecule}"));
+                }
+            }
+            ViewerCommand::UpdatePositions(positions) => {
+                let n_atoms = self.active_atom_count();
+                let Some(mol) = self
+                    .molecule
+                    .as_mut()
+                    .filter(|_| positions.len() == n_atoms)
+                else {
+                    return Err(format!(
+                        "update positions: {} positions for {n_atoms} atoms",
+                        positions.len()
+                    ));
+                };
+                for (atom, position) in mol.atoms.iter_mut().zip(positions) {
+                    atom.position = position;
+                }
+                self.dirty = true;
+            }
+            ViewerCommand::Edit(action) => self.apply_edit(action)?,
+        }
+        Ok(())
+    }
+
+    /// Applies an editing action to the viewer's selection, or for `ShowAll`,
+    /// `CancelBond` and `TogglePin`, as described there.
+    pub fn apply_edit(&mut self, action: EditAction) -> Result<(), String> {
+        match action {
+            EditAction::DeleteSelected => {
+                let atoms = self.selection().atoms().to_vec();
+                return self.delete_atoms(&atoms);
+            }
+            EditAction::SelectAll => {
+                let drawn = self.drawn_atoms();
+                self.set_selection(Selection::from_atoms(drawn));
+            }
+            EditAction::ClearSelection => {
+                self.clear_selection();
+                for renderer in self.overlay_renderers_mut() {
+                    if let Some(tool) = renderer.measurement_mut() {
+                        tool.clear();
+                    }
+                }
+                self.mark_overlays_dirty();
+            }
+            EditAction::HideSelected => {
+                let atoms = self.selection().atoms().to_vec();
+                self.settings.hidden_atoms.extend(atoms);
+                self.dirty = true;
+            }
+            EditAction::ShowAll => {
+                if !self.settings.hidden_atoms.is_empty() {
+                    self.settings.hidden_atoms.clear();
+                    self.dirty = true;
+                }
+            }
+            EditAction::InvertSelection => {
+                let selection = self.selection();
+                let unselected = self
+                    .drawn_atoms()
+                    .into_iter()
+                    .filter(|&i| !selection.contains(i));
+                let inverted = Selection::from_atoms(unselected);
+                self.set_selection(inverted);
+            }
+            EditAction::CancelBond => self.cancel_pending_bond(),
+            EditAction::TogglePin(atom) => {
+                self.toggle_pin(atom)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn active_atom_count(&self) -> usize {
+        self.molecule.as_ref().map_or(0, |mol| mol.atoms.len())
+    }
+
+    /// Current indices of `ids` in the active molecule.
+    fn resolve_ids(&self, ids: &[AtomId]) -> Result<Vec<usize>, AtomId> {
+        match &self.molecule {
+            Some(mol) => mol.indices_of(ids),
+            None => ids.first().map_or(Ok(Vec::new()), |&id| Err(id)),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Element composition of a molecule, for drawing as a stacked bar.
+
+#[cfg(feature = "render")]
+use crate::colormap::cpk_color;
+use crate::colormap::Rgb;
+use crate::element;
+use crate::molecule::Molecule;
+#[cfg(feature = "render")]
+use crate::style::Style;
+
+/// Which atoms `MoleculeViewer::composition_bar` counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CompositionOptions {
+    /// Leave out hydrogens.
+    pub heavy_atoms_only: bool,
+    /// Count only atoms the current filters draw, ghosted or not.
+    pub visible_only: bool,
+}
+
+/// One element's share of the counted atoms.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompositionEntry {
+    pub element: String,
+    pub count: usize,
+    /// `count` over all counted atoms.
+    pub fraction: f32,
+    /// The element's atom color under the current tint. Per-atom overrides
+    /// are not reflected.
+    pub color: Rgb,
+}
+
+/// Counts the elements of `mol` per `options`, most abundant first; ties go
+/// alphabetically.
+#[cfg(feature = "render")]
+pub(crate) fn composition(
+    mol: &Molecule,
+    style: Style,
+    options: CompositionOptions,
+) -> Vec<CompositionEntry> {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    let mut total = 0;
+    for (i, atom) in mol.atoms.iter().enumerate() {
+        if (options.heavy_atoms_only && atom.element.eq_ignore_ascii_case("H"))
+            || (options.visible_only && !style.atom_drawn(i, atom))
+        {
+            continue;
+        }
+        total += 1;
+        match counts.iter_mut().find(|(e, _)| *e == atom.element) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((&atom.element, 1)),
+        }
+    }
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    counts
+        .into_iter()
+        .map(|(element, count)| CompositionEntry {
+            element: element.to_string(),
+            count,
+            fraction: count as f32 / total as f32,
+            color: style.tint(cpk_color(element)),
+        })
+        .collect()
+}
+
+impl Molecule {
+    /// Molecular formula in Hill order: C, then H, then the rest
+    /// alphabetically, or all alphabetically without carbon ("C2H6O",
+    /// "ClNa"). Elements are spelled as in the periodic table; unknown ones
+    /// as stored.
+    pub fn formula(&self) -> String {
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for atom in &self.atoms {
+            let symbol = element::symbol(&atom.element).unwrap_or(&atom.element);
+            match counts.iter_mut().find(|(e, _)| e == symbol) {
+                Some((_, n)) => *n += 1,
+                None => counts.push((symbol.to_string(), 1)),
+            }
+        }
+        let has_carbon = counts.iter().any(|(e, _)| e == "C");
+        let rank = |e: &str| match e {
+            "C" if has_carbon => 0,
+            "H" if has_carbon => 1,
+            _ => 2,
+        };
+        counts.sort_by(|a, b| rank(&a.0).cmp(&rank(&b.0)).then(a.0.cmp(&b.0)));
+        counts
+            .into_iter()
+            .map(|(e, n)| if n == 1 { e } else { format!("{e}{n}") })
+            .collect()
+    }
+
+    /// Sum of the atoms' `element::atomic_mass` in g/mol, or `None` if any
+    /// element is unknown.
+    pub fn molecular_weight(&self) -> Option<f32> {
+        self.atoms
+            .iter()
+            .map(|atom| element::atomic_mass(&atom.element))
+            .sum()
+    }
+}
@@ -0,0 +1,112 @@
+//! Close contacts between two molecules (e.g. ligand and protein, or two
+//! symmetry mates), classified by element and distance.
+
+use crate::element;
+use crate::molecule::Molecule;
+use crate::spatial::NeighborGrid;
+use std::fmt::Write;
+
+/// Donor/acceptor contacts are H-bond capable up to this distance, in Å.
+pub const HBOND_MAX_DISTANCE: f32 = 3.5;
+/// Atoms closer than the sum of their van der Waals radii minus this overlap clash, in Å.
+pub const CLASH_OVERLAP: f32 = 0.4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContactKind {
+    /// Both atoms non-polar (C, S, H, halogens).
+    Hydrophobic,
+    /// At least one polar atom, not an H-bond.
+    Polar,
+    /// N/O pair within `HBOND_MAX_DISTANCE`.
+    HBond,
+    /// Van der Waals spheres overlap by more than `CLASH_OVERLAP`.
+    Clash,
+}
+
+impl ContactKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Hydrophobic => "hydrophobic",
+            Self::Polar => "polar",
+            Self::HBond => "hbond",
+            Self::Clash => "clash",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contact {
+    /// Atom index in the first molecule.
+    pub atom_a: usize,
+    /// Atom index in the second molecule.
+    pub atom_b: usize,
+    pub distance: f32,
+    pub kind: ContactKind,
+}
+
+/// Every atom pair, one from each molecule, within `cutoff` Å, sorted by
+/// `atom_a` then `atom_b`. Uses a neighbor grid over `mol_b`, so cost scales
+/// with the number of contacts rather than the product of the atom counts.
+pub fn contacts_between(mol_a: &Molecule, mol_b: &Molecule, cutoff: f32) -> Vec<Contact> {
+    if cutoff <= 0.0 {
+        return Vec::new();
+    }
+    let positions: Vec<_> = mol_b.atoms.iter().map(|a| a.position).collect();
+    let grid = NeighborGrid::new(&positions, cutoff);
+    let mut contacts = Vec::new();
+    for (i, a) in mol_a.atoms.iter().enumerate() {
+        let start = contacts.len();
+        grid.for_each_within(a.position, cutoff, |j, distance| {
+            let kind = classify(&a.element, &mol_b.atoms[j].element, distance);
+            contacts.push(Contact {
+                atom_a: i,
+                atom_b: j,
+                distance,
+                kind,
+            });
+        });
+        contacts[start..].sort_by_key(|c| c.atom_b);
+    }
+    contacts
+}
+
+fn classify(element_a: &str, element_b: &str, distance: f32) -> ContactKind {
+    let vdw_sum = element::vdw_radius(element_a) + element::vdw_radius(element_b);
+    let is_polar = |e: &str| ["N", "O"].iter().any(|p| p.eq_ignore_ascii_case(e));
+    if distance < vdw_sum - CLASH_OVERLAP {
+        ContactKind::Clash
+    } else if is_polar(element_a) && is_polar(element_b) && distance <= HBOND_MAX_DISTANCE {
+        ContactKind::HBond
+    } else if is_polar(element_a) || is_polar(element_b) {
+        ContactKind::Polar
+    } else {
+        ContactKind::Hydrophobic
+    }
+}
+
+/// Tab-separated table of `contacts` with element names, one contact per line
+/// after a header row. The distance column header names `mol_a`'s unit.
+pub fn contact_report(mol_a: &Molecule, mol_b: &Molecule, contacts: &[Contact]) -> String {
+    let mut report = format!(
+        "atom_a\telement_a\tatom_b\telement_b\tdistance ({})\tkind\n",
+        mol_a.metadata.unit.symbol()
+    );
+    let element = |mol: &Molecule, i: usize| {
+        mol.atoms
+            .get(i)
+            .map_or("?".to_string(), |a| a.element.clone())
+    };
+    for c in contacts {
+        let _ = writeln!(
+            report,
+            "{}\t{}\t{}\t{}\t{:.3}\t{}",
+            c.atom_a,
+            element(mol_a, c.atom_a),
+            c.atom_b,
+            element(mol_b, c.atom_b),
+            c.distance,
+            c.kind.name()
+        );
+    }
+    report
+}
@@ -1,6 +1,17 @@
 use crate::{
     additional_render::AdditionalRender,
-    camera::Camera,
+    camera::{
+        Camera, ProjectionType, ResizePolicy, ViewBookmark, ViewPreset, MAX_FOV_Y, MIN_FOV_Y,
+    },
+    camera_path::{CameraPath, CameraPathError},
+    clock::{Clock, MonotonicClock},
+    command::EditAction,
+    convert::{to_la, to_na, vec_to_la, vec_to_na},
+    gizmo::{GizmoDrag, GizmoHandle, TransformGizmo},
+    molecule::Molecule,
+    picking,
+    selection::Selection,
+    style::Cutaway,
     viewer::{MoleculeViewer, ViewerEvent},
 };
 use graphics::winit::keyboard::{KeyCode, PhysicalKey};
@@ -8,45 +19,945 @@ use graphics::{
     winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent},
     EngineUpdates, Scene,
 };
-use nalgebra::{Point2, Vector2};
+use nalgebra::{Point2, Point3, Unit, UnitQuaternion, Vector2, Vector3};
+use std::time::Duration;
+
+/// Field-of-view change per scroll line with Ctrl held, in radians.
+const FOV_STEP: f32 = 2.0 * std::f32::consts::PI / 180.0;
+
+/// Field of view the scene camera draws orthographic views with, in radians.
+/// `graphics` only draws in perspective; seen through 1° from far enough back
+/// to show `Camera::ortho_scale` at the target, sizes change by under 1% per
+/// 5 Å of depth in a 10 Å view.
+pub const ORTHO_FOV_Y: f32 = std::f32::consts::PI / 180.0;
+
+/// Factor the cutaway radius changes by per scroll line with Alt held.
+const CUTAWAY_SCROLL_STEP: f32 = 1.1;
+
+/// Smallest cutaway radius Alt + scroll shrinks to, in Å.
+pub const MIN_CUTAWAY_RADIUS: f32 = 0.5;
+
+/// Length of the `align_up` animation, in seconds.
+const ALIGN_UP_DURATION: f32 = 0.5;
+
+/// Room `CameraController::frame_molecule` leaves around the atom centers,
+/// in Å: enough for ball-and-stick atoms.
+pub const FRAME_MARGIN: f32 = 1.0;
+
+/// How long the orbit trail stays, fading out, after the last orbit motion
+/// once the drag has ended, in seconds.
+pub const ORBIT_TRAIL_LINGER: f32 = 1.0;
+
+/// How long the pivot indicator stays, fading out, after orbiting ends, in
+/// seconds.
+pub const PIVOT_INDICATOR_LINGER: f32 = 0.5;
+
+/// Input handling options for `CameraController`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControllerSettings {
+    /// Pick under the cursor as it moves and report `AtomHovered`,
+    /// `BondHovered` or `NothingHovered`.
+    pub hover: bool,
+    /// Minimum time between hover picks. Moves in between are coalesced into
+    /// one pick, made by the next move or `poll_hover` after the interval.
+    pub hover_min_interval: Duration,
+    /// Don't report a hover event equal to the previous one.
+    pub hover_dedupe: bool,
+    /// Only hover-pick while no mouse button is held, e.g. not while orbiting.
+    pub hover_only_without_buttons: bool,
+    /// When the hover pick hits nothing, report `BondHovered` for the bond
+    /// whose axis is within this many pixels of the cursor (see
+    /// `MoleculeViewer::bond_near_pixel`). 0 hovers only exact hits.
+    pub bond_hover_tolerance: f32,
+    /// Time constant in seconds of the low-pass filter on orbit, pan and dolly
+    /// drags, applied in `tick`. 0 applies drags as they arrive.
+    pub smoothing_time_constant: f32,
+    /// Left-dragging an atom moves it, reported as `ViewerEvent::AtomDragged`
+    /// and then `AtomDragEnded` for `MoleculeViewer::handle_pick`. The atom
+    /// moves in the plane through it facing the camera; with Shift held when
+    /// the drag starts, its whole fragment moves rigidly. Ctrl + click pins
+    /// or unpins the atom instead (`EditAction::TogglePin`).
+    pub drag_atoms: bool,
+    /// When `CameraController::pivot_indicator` reports the orbit pivot.
+    pub pivot_indicator: PivotVisibility,
+}
+
+impl Default for ControllerSettings {
+    fn default() -> Self {
+        Self {
+            hover: true,
+            hover_min_interval: Duration::from_millis(30),
+            hover_dedupe: true,
+            hover_only_without_buttons: false,
+            bond_hover_tolerance: 4.0,
+            smoothing_time_constant: 0.0,
+            drag_atoms: false,
+            pivot_indicator: PivotVisibility::WhileOrbiting,
+        }
+    }
+}
+
+/// When the orbit pivot is shown; see `ControllerSettings::pivot_indicator`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PivotVisibility {
+    Off,
+    /// While orbiting, fading out over `PIVOT_INDICATOR_LINGER` after.
+    WhileOrbiting,
+    Always,
+}
+
+/// What a mouse drag does to the camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DragMode {
+    Orbit,
+    Pan,
+    Dolly,
+}
+
+impl DragMode {
+    const ALL: [Self; 3] = [Self::Orbit, Self::Pan, Self::Dolly];
+}
+
+/// Critically damped follower of the summed drag input of one `DragMode`.
+/// `offset` is how far the applied motion lags the input.
+#[derive(Clone, Copy, Default)]
+struct Damper {
+    offset: Vector2<f32>,
+    velocity: Vector2<f32>,
+}
+
+impl Damper {
+    /// Advances by `dt` with time constant `tau`, returning the motion to
+    /// apply. Uses the exact solution, so the result does not depend on how
+    /// time is split into steps, and the applied motion sums to the input.
+    fn step(&mut self, dt: f32, tau: f32) -> Vector2<f32> {
+        let before = self.offset;
+        if tau <= 0.0 {
+            *self = Self::default();
+            return before;
+        }
+        let w = 1.0 / tau;
+        let decay = (-w * dt).exp();
+        let k = self.velocity + self.offset * w;
+        self.offset = (self.offset + k * dt) * decay;
+        self.velocity = (self.velocity - k * (w * dt)) * decay;
+        if self.offset.norm() < 1e-7 && self.velocity.norm() < 1e-6 {
+            self.offset = Vector2::zeros();
+            self.velocity = Vector2::zeros();
+        }
+        before - self.offset
+    }
+}
+
+/// Slow rotation of the camera while the user is idle, e.g. for kiosks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IdleSpin {
+    /// World axis to orbit the camera target around; `UpAxis::up` of the
+    /// camera for a turntable.
+    pub axis: Vector3<f32>,
+    pub rate_deg_per_s: f32,
+    /// Seconds without input before spinning starts.
+    pub resume_after_s: f32,
+}
+
+impl Default for IdleSpin {
+    fn default() -> Self {
+        Self {
+            axis: Vector3::y(),
+            rate_deg_per_s: 10.0,
+            resume_after_s: 5.0,
+        }
+    }
+}
+
+/// How far the camera has orbited in the current drag, for
+/// `OrbitTrailRender`. Directions are unit vectors from the pivot towards the
+/// camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrbitTrail {
+    pub pivot: Point3<f32>,
+    /// Direction when the drag started.
+    pub start: Vector3<f32>,
+    pub current: Vector3<f32>,
+    /// Camera distance from the pivot.
+    pub distance: f32,
+    /// 1 while dragging, falling to 0 over `ORBIT_TRAIL_LINGER` after.
+    pub fade: f32,
+}
+
+impl OrbitTrail {
+    /// Angle between `start` and `current`, in radians.
+    pub fn angle(&self) -> f32 {
+        self.start.angle(&self.current)
+    }
+}
+
+/// The point the camera orbits about, for `PivotRender`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PivotIndicator {
+    pub pivot: Point3<f32>,
+    /// Camera distance from the pivot.
+    pub distance: f32,
+    /// 1 while orbiting, falling to 0 over `PIVOT_INDICATOR_LINGER` after.
+    pub fade: f32,
+}
+
+/// A rectangle dragged with Alt + LMB to select atoms, in normalized device
+/// coordinates: x right and y up, from -1 to 1 across the viewport.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelectionRect {
+    /// Where the drag started.
+    pub start: Point2<f32>,
+    /// Where the cursor is now.
+    pub end: Point2<f32>,
+}
+
+impl SelectionRect {
+    pub fn min(&self) -> Point2<f32> {
+        self.start.inf(&self.end)
+    }
+
+    pub fn max(&self) -> Point2<f32> {
+        self.start.sup(&self.end)
+    }
+
+    /// Whether `ndc` is inside the rectangle or on its edge.
+    pub fn contains(&self, ndc: Point2<f32>) -> bool {
+        let (min, max) = (self.min(), self.max());
+        (min.x..=max.x).contains(&ndc.x) && (min.y..=max.y).contains(&ndc.y)
+    }
+}
+
+/// An atom grabbed with the left button: it moves in the plane through it
+/// facing the camera, keeping the grabbed point under the cursor.
+#[derive(Clone, Copy)]
+struct AtomGrab {
+    atom: usize,
+    plane_point: Point3<f32>,
+    normal: Vector3<f32>,
+    /// From the grabbed point on the plane to the atom center.
+    offset: Vector3<f32>,
+    rigid: bool,
+    dragged: bool,
+}
+
+/// A cutaway being moved with Alt + LMB, in the plane through its center
+/// facing the camera.
+struct CutawayGrab {
+    plane_point: Point3<f32>,
+    normal: Vector3<f32>,
+    /// From the grabbed point on the plane to the cutaway center.
+    offset: Vector3<f32>,
+}
+
+struct PathPlayback {
+    path: CameraPath,
+    duration: f32,
+    elapsed: f32,
+}
 
 pub struct CameraController<T: Camera + Default> {
     pub camera: Box<T>,
+    /// While set, left-dragging one of its handles moves it and reports
+    /// `ViewerEvent::GizmoDragged` instead of picking.
+    pub gizmo: Option<TransformGizmo>,
+    pub settings: ControllerSettings,
+    clock: Box<dyn Clock>,
     last_mouse_pos: Point2<f32>,
     mouse_lb_pressed: bool,
     mouse_mb_pressed: bool,
     mouse_rb_pressed: bool,
     shift_pressed: bool,
     ctrl_pressed: bool,
+    alt_pressed: bool,
     width: f32,
     height: f32,
+    path_playback: Option<PathPlayback>,
+    gizmo_drag: Option<GizmoDrag>,
+    atom_grab: Option<AtomGrab>,
+    cutaway_grab: Option<CutawayGrab>,
+    /// Where, in window pixels, an Alt + LMB selection rectangle started.
+    rect_select_start: Option<Point2<f32>>,
+    /// When the last hover pick ran, per `clock`.
+    last_hover_pick: Option<Duration>,
+    /// The last hover event reported, for `hover_dedupe`.
+    last_hover: Option<ViewerEvent>,
+    /// The cursor moved since the last hover pick.
+    hover_pending: bool,
+    idle_spin: Option<IdleSpin>,
+    /// Seconds of `tick` time since the last input.
+    idle_time: f32,
+    /// Drag input not yet applied, per `DragMode::ALL`.
+    smoothing: [Damper; 3],
+    /// Set by the host while a GUI widget has keyboard focus.
+    keyboard_captured: bool,
+    orbit_trail: Option<OrbitTrail>,
+    /// When, per `clock`, the camera last orbited or an orbit drag ended.
+    last_orbit: Option<Duration>,
+    /// Whether the held middle or right button has orbited the camera since
+    /// it was pressed.
+    orbit_drag: bool,
+}
+
+impl<T: Camera + Default> Default for CameraController<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T: Camera + Default> CameraController<T> {
     pub fn new() -> Self {
+        Self::with_camera(T::default())
+    }
+
+    /// A controller driving `camera`, e.g. `OrbitalCamera::with_up_axis`.
+    pub fn with_camera(mut camera: T) -> Self {
         let width = 800.0;
         let height = 600.0;
-        let mut camera = T::default();
         camera.set_aspect(width / height);
 
         Self {
             camera: Box::new(camera),
+            gizmo: None,
+            settings: ControllerSettings::default(),
+            clock: Box::new(MonotonicClock::new()),
             last_mouse_pos: Point2::origin(),
             mouse_lb_pressed: false,
             mouse_mb_pressed: false,
             mouse_rb_pressed: false,
             shift_pressed: false,
             ctrl_pressed: false,
+            alt_pressed: false,
             width,
             height,
+            path_playback: None,
+            gizmo_drag: None,
+            atom_grab: None,
+            cutaway_grab: None,
+            rect_select_start: None,
+            last_hover_pick: None,
+            last_hover: None,
+            hover_pending: false,
+            idle_spin: None,
+            idle_time: 0.0,
+            smoothing: [Damper::default(); 3],
+            keyboard_captured: false,
+            orbit_trail: None,
+            last_orbit: None,
+            orbit_drag: false,
+        }
+    }
+
+    /// Replaces the time source used to throttle hover picks.
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.clock = Box::new(clock);
+    }
+
+    /// Moves the cursor to `(x, y)` in window pixels and hover-picks there,
+    /// subject to `settings`. `handle_event` calls this on `CursorMoved`.
+    pub fn hover_at<U: AdditionalRender>(
+        &mut self,
+        x: f32,
+        y: f32,
+        viewer: &MoleculeViewer<U>,
+    ) -> Option<ViewerEvent> {
+        self.last_mouse_pos = Point2::new(x, y);
+        self.hover_pending = true;
+        self.poll_hover(viewer)
+    }
+
+    /// Runs a hover pick coalesced by the throttle, once `hover_min_interval`
+    /// has passed. Call every frame so the hover catches up after the cursor
+    /// stops.
+    pub fn poll_hover<U: AdditionalRender>(
+        &mut self,
+        viewer: &MoleculeViewer<U>,
+    ) -> Option<ViewerEvent> {
+        let buttons_down = self.mouse_lb_pressed || self.mouse_mb_pressed || self.mouse_rb_pressed;
+        if !self.settings.hover
+            || !self.hover_pending
+            || (self.settings.hover_only_without_buttons && buttons_down)
+        {
+            return None;
+        }
+        let now = self.clock.now();
+        if let Some(last) = self.last_hover_pick {
+            if now.saturating_sub(last) < self.settings.hover_min_interval {
+                return None;
+            }
+        }
+        self.last_hover_pick = Some(now);
+        self.hover_pending = false;
+
+        let (origin, dir) = self.ray_from_last_mouse();
+        let event = match viewer.pick(origin, dir)? {
+            ViewerEvent::AtomClicked(i) => ViewerEvent::AtomHovered(i),
+            ViewerEvent::BondClicked(i) => ViewerEvent::BondHovered(i),
+            _ => match viewer.bond_near_pixel(
+                self.camera.as_ref(),
+                self.last_mouse_pos,
+                self.settings.bond_hover_tolerance,
+                self.width,
+                self.height,
+            ) {
+                Some(i) => ViewerEvent::BondHovered(i),
+                None => ViewerEvent::NothingHovered,
+            },
+        };
+        if self.settings.hover_dedupe && self.last_hover.as_ref() == Some(&event) {
+            return None;
+        }
+        self.last_hover = Some(event.clone());
+        Some(event)
+    }
+
+    /// Selects what a click picked, for the host to call with the
+    /// `handle_event` result: a plain click replaces the viewer's selection
+    /// with the atom or bond, or clears it on empty space, and a Ctrl-click
+    /// adds the atom or bond, or removes it if it was selected (with
+    /// `settings.drag_atoms`, a Ctrl-click on an atom pins it instead).
+    /// `AtomsSelected` from a selection rectangle replaces the selection with
+    /// its atoms, or with Ctrl adds them. Does nothing for other events or in
+    /// bond mode. Returns whether the selection changed.
+    pub fn select_on_click<U: AdditionalRender>(
+        &self,
+        event: &ViewerEvent,
+        viewer: &mut MoleculeViewer<U>,
+    ) -> bool {
+        if viewer.bond_mode() {
+            return false;
+        }
+        let mut selection = if self.ctrl_pressed {
+            viewer.selection().clone()
+        } else {
+            Selection::new()
+        };
+        match event {
+            ViewerEvent::AtomClicked(i) => {
+                selection.toggle(*i);
+            }
+            ViewerEvent::BondClicked(i) => {
+                selection.toggle_bond(*i);
+            }
+            ViewerEvent::AtomsSelected(atoms) => {
+                for &i in atoms {
+                    selection.insert(i);
+                }
+            }
+            ViewerEvent::NothingClicked if !self.ctrl_pressed => {}
+            _ => return false,
+        }
+        viewer.set_selection(selection)
+    }
+
+    /// The selection rectangle being dragged with Alt + LMB, if any.
+    pub fn selection_rect(&self) -> Option<SelectionRect> {
+        let start = self.rect_select_start?;
+        Some(SelectionRect {
+            start: self.pixel_to_ndc(start),
+            end: self.pixel_to_ndc(self.last_mouse_pos),
+        })
+    }
+
+    /// Drawn atoms of the viewer's active molecule whose centers project
+    /// into `rect` through the camera's `view_projection`, ascending. Atoms
+    /// behind the camera are left out.
+    pub fn atoms_in_rect<U: AdditionalRender>(
+        &self,
+        rect: &SelectionRect,
+        viewer: &MoleculeViewer<U>,
+    ) -> Vec<usize> {
+        let Some(mol) = viewer.displayed_molecule() else {
+            return Vec::new();
+        };
+        let view_projection = self.camera.view_projection();
+        viewer
+            .drawn_atoms()
+            .into_iter()
+            .filter(|&i| {
+                let clip = view_projection * mol.atoms[i].position.to_homogeneous();
+                clip.w > 0.0 && rect.contains(Point2::new(clip.x / clip.w, clip.y / clip.w))
+            })
+            .collect()
+    }
+
+    /// Draws `selection_rect`, if any, over everything else in `ctx`.
+    pub fn selection_rect_ui(&self, ctx: &egui::Context) {
+        let Some(start) = self.rect_select_start else {
+            return;
+        };
+        let scale = ctx.pixels_per_point();
+        let rect = egui::Rect::from_two_pos(
+            egui::pos2(start.x / scale, start.y / scale),
+            egui::pos2(self.last_mouse_pos.x / scale, self.last_mouse_pos.y / scale),
+        );
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("selection_rect"),
+        ));
+        let color = egui::Color32::from_rgb(120, 170, 255);
+        painter.rect_filled(rect, 0.0, color.gamma_multiply(0.15));
+        painter.rect_stroke(
+            rect,
+            0.0,
+            egui::Stroke::new(1.0, color),
+            egui::StrokeKind::Inside,
+        );
+    }
+
+    fn pixel_to_ndc(&self, pixel: Point2<f32>) -> Point2<f32> {
+        Point2::new(
+            -1.0 + 2.0 * pixel.x / self.width,
+            1.0 - 2.0 * pixel.y / self.height,
+        )
+    }
+
+    /// Tells the controller whether the host's GUI has keyboard focus, e.g.
+    /// `ctx.wants_keyboard_input()` for egui. While captured, key shortcuts
+    /// don't fire; modifier keys are still tracked.
+    pub fn set_keyboard_captured(&mut self, captured: bool) {
+        self.keyboard_captured = captured;
+    }
+
+    pub fn keyboard_captured(&self) -> bool {
+        self.keyboard_captured
+    }
+
+    /// Handles a key press or release. `handle_event` calls this on
+    /// `KeyboardInput`; see there for the shortcuts.
+    pub fn key_input<U: AdditionalRender>(
+        &mut self,
+        key: KeyCode,
+        pressed: bool,
+        viewer: &MoleculeViewer<U>,
+    ) -> (Option<ViewerEvent>, EngineUpdates) {
+        let mut updates = EngineUpdates::default();
+        match key {
+            KeyCode::ShiftLeft | KeyCode::ShiftRight => self.shift_pressed = pressed,
+            KeyCode::ControlLeft | KeyCode::ControlRight => self.ctrl_pressed = pressed,
+            KeyCode::AltLeft | KeyCode::AltRight => self.alt_pressed = pressed,
+            _ => {}
         }
+        if !pressed || self.keyboard_captured {
+            return (None, updates);
+        }
+        let edit = match key {
+            KeyCode::Numpad5 => {
+                self.toggle_projection();
+                updates.camera = true;
+                None
+            }
+            KeyCode::Numpad1 | KeyCode::Numpad3 | KeyCode::Numpad7 => {
+                let preset = match (key, self.ctrl_pressed) {
+                    (KeyCode::Numpad1, false) => ViewPreset::Front,
+                    (KeyCode::Numpad1, true) => ViewPreset::Back,
+                    (KeyCode::Numpad3, false) => ViewPreset::Right,
+                    (KeyCode::Numpad3, true) => ViewPreset::Left,
+                    (_, false) => ViewPreset::Top,
+                    (_, true) => ViewPreset::Bottom,
+                };
+                self.view_preset(preset);
+                updates.camera = true;
+                None
+            }
+            KeyCode::KeyF if !self.ctrl_pressed => {
+                self.stop_path();
+                viewer.fit_camera_to_molecule(self.camera.as_mut());
+                updates.camera = true;
+                None
+            }
+            KeyCode::Home => {
+                if let Some(axis) = viewer.reference_axis() {
+                    self.align_up(axis.direction);
+                }
+                None
+            }
+            KeyCode::Delete | KeyCode::Backspace => Some(EditAction::DeleteSelected),
+            KeyCode::KeyA if self.ctrl_pressed => Some(EditAction::SelectAll),
+            KeyCode::Escape if viewer.pending_bond().is_some() => Some(EditAction::CancelBond),
+            KeyCode::Escape => Some(EditAction::ClearSelection),
+            KeyCode::KeyH if !self.ctrl_pressed => Some(if self.shift_pressed {
+                EditAction::ShowAll
+            } else {
+                EditAction::HideSelected
+            }),
+            KeyCode::KeyI if !self.ctrl_pressed => Some(EditAction::InvertSelection),
+            _ => None,
+        };
+        (edit.map(ViewerEvent::EditRequested), updates)
+    }
+
+    /// Starts animating the camera along `path` over `duration` seconds; keyframe
+    /// times are rescaled to fit. Advance the animation with `tick`.
+    pub fn follow_path(&mut self, path: CameraPath, duration: f32) -> Result<(), CameraPathError> {
+        path.validate()?;
+        if duration <= 0.0 || !duration.is_finite() {
+            return Err(CameraPathError::InvalidDuration(duration));
+        }
+        self.path_playback = Some(PathPlayback {
+            path,
+            duration,
+            elapsed: 0.0,
+        });
+        Ok(())
+    }
+
+    pub fn is_following_path(&self) -> bool {
+        self.path_playback.is_some()
+    }
+
+    pub fn stop_path(&mut self) {
+        self.path_playback = None;
+    }
+
+    /// Spins the camera by `spin` whenever `tick` has run for
+    /// `spin.resume_after_s` seconds without input; `None` turns it off.
+    pub fn set_idle_spin(&mut self, spin: Option<IdleSpin>) {
+        self.idle_spin = spin;
+    }
+
+    /// The orbit of the current or just-ended drag, while it should be shown.
+    /// Copy it into an `OrbitTrailRender` each frame and call
+    /// `MoleculeViewer::refresh_overlays`.
+    pub fn orbit_trail(&self) -> Option<OrbitTrail> {
+        self.orbit_trail
+    }
+
+    /// The orbit pivot, while `settings.pivot_indicator` says to show it.
+    /// Copy it into a `PivotRender` each frame and call
+    /// `MoleculeViewer::refresh_overlays`.
+    pub fn pivot_indicator(&self) -> Option<PivotIndicator> {
+        let fade = match self.settings.pivot_indicator {
+            PivotVisibility::Off => return None,
+            PivotVisibility::Always => 1.0,
+            PivotVisibility::WhileOrbiting => {
+                let last = self.last_orbit?;
+                if self.orbit_drag {
+                    1.0
+                } else {
+                    let since = self.clock.now().saturating_sub(last).as_secs_f32();
+                    1.0 - since / PIVOT_INDICATOR_LINGER
+                }
+            }
+        };
+        let pivot = self.camera.target();
+        (fade > 0.0).then(|| PivotIndicator {
+            pivot,
+            distance: (self.camera.position() - pivot).norm(),
+            fade,
+        })
+    }
+
+    pub fn idle_spin(&self) -> Option<&IdleSpin> {
+        self.idle_spin.as_ref()
+    }
+
+    /// Restarts the idle timer, stopping any idle spin. `handle_event` calls
+    /// this for mouse, keyboard and touch input; call it for input the
+    /// controller does not see, such as GUI interaction.
+    pub fn reset_idle(&mut self) {
+        self.idle_time = 0.0;
+    }
+
+    /// Whether the next `tick` will spin the camera.
+    pub fn is_idle_spinning(&self) -> bool {
+        self.path_playback.is_none()
+            && self
+                .idle_spin
+                .is_some_and(|spin| self.idle_time >= spin.resume_after_s)
+    }
+
+    /// Advances time-based camera motion by `dt` seconds: smoothed drags, and
+    /// path playback or else the idle spin. Returns `true` if the camera moved and the scene
+    /// camera needs updating.
+    pub fn tick(&mut self, dt: f32) -> bool {
+        self.idle_time += dt;
+        self.tick_orbit_trail(dt);
+        let smoothed = self.tick_smoothing(dt);
+        let Some(playback) = &mut self.path_playback else {
+            return self.tick_idle_spin(dt) || smoothed;
+        };
+        playback.elapsed = (playback.elapsed + dt).min(playback.duration);
+        let progress = playback.elapsed / playback.duration;
+        let path = &playback.path;
+        let time = path.start_time() + progress * (path.end_time() - path.start_time());
+        if let Ok(view) = path.sample(time) {
+            view.apply(self.camera.as_mut());
+        }
+        if progress >= 1.0 {
+            self.path_playback = None;
+        }
+        true
+    }
+
+    /// Moves the camera for a mouse drag of `delta` pixels. With
+    /// `settings.smoothing_time_constant` set, the motion is queued for `tick`
+    /// and `false` is returned; otherwise it is applied at once.
+    pub fn camera_drag(&mut self, delta: Vector2<f32>, mode: DragMode) -> bool {
+        let amount = match mode {
+            DragMode::Orbit => delta * 0.005, // radians per pixel
+            DragMode::Pan => delta * 0.01,
+            DragMode::Dolly => Vector2::new(0.0, delta.y * 0.1),
+        };
+        if self.settings.smoothing_time_constant > 0.0 {
+            self.smoothing[mode as usize].offset += amount;
+            return false;
+        }
+        self.apply_drag(amount, mode);
+        true
+    }
+
+    fn apply_drag(&mut self, amount: Vector2<f32>, mode: DragMode) {
+        match mode {
+            DragMode::Orbit => {
+                let before = self.orbit_direction();
+                self.camera.orbit(amount.x, amount.y);
+                let (pivot, current) = (self.camera.target(), self.orbit_direction());
+                let start = self.orbit_trail.map_or(before, |trail| trail.start);
+                self.orbit_trail = Some(OrbitTrail {
+                    pivot,
+                    start,
+                    current,
+                    distance: (self.camera.position() - pivot).norm(),
+                    fade: 1.0,
+                });
+                self.last_orbit = Some(self.clock.now());
+            }
+            DragMode::Pan => self.camera.pan(amount),
+            DragMode::Dolly => self.camera.dolly(amount.y),
+        }
+    }
+
+    fn orbit_direction(&self) -> Vector3<f32> {
+        (self.camera.position() - self.camera.target())
+            .try_normalize(1e-9)
+            .unwrap_or_else(Vector3::z)
+    }
+
+    /// Fades the orbit trail once no button is held, dropping it after
+    /// `ORBIT_TRAIL_LINGER`. A smoothed orbit still catching up restores it.
+    fn tick_orbit_trail(&mut self, dt: f32) {
+        if self.mouse_mb_pressed || self.mouse_rb_pressed {
+            return;
+        }
+        let Some(trail) = &mut self.orbit_trail else {
+            return;
+        };
+        trail.fade -= dt / ORBIT_TRAIL_LINGER;
+        if trail.fade <= 0.0 {
+            self.orbit_trail = None;
+        }
+    }
+
+    /// Applies the part of the queued drags that the filter lets through.
+    fn tick_smoothing(&mut self, dt: f32) -> bool {
+        let tau = self.settings.smoothing_time_constant;
+        let mut moved = false;
+        for mode in DragMode::ALL {
+            let amount = self.smoothing[mode as usize].step(dt, tau);
+            if amount != Vector2::zeros() {
+                self.apply_drag(amount, mode);
+                moved = true;
+            }
+        }
+        moved
+    }
+
+    /// Orbits about the spin axis through the target. The angle between the
+    /// view direction and the axis never changes, so the camera cannot drift
+    /// towards a pole.
+    fn tick_idle_spin(&mut self, dt: f32) -> bool {
+        let Some(spin) = self.idle_spin.filter(|_| self.is_idle_spinning()) else {
+            return false;
+        };
+        let Some(axis) = Unit::try_new(spin.axis, 1e-6) else {
+            return false;
+        };
+        let angle = (spin.rate_deg_per_s * dt).to_radians();
+        if angle == 0.0 {
+            return false;
+        }
+        let mut view = ViewBookmark::from_camera(self.camera.as_ref());
+        view.rotation = UnitQuaternion::from_axis_angle(&axis, angle) * view.rotation;
+        view.apply(self.camera.as_mut());
+        true
+    }
+
+    /// Animates the camera so `axis` points up on screen, keeping the target and
+    /// distance (see `ViewBookmark::upright`). Advance the animation with `tick`.
+    pub fn align_up(&mut self, axis: Vector3<f32>) {
+        let from = ViewBookmark::from_camera(self.camera.as_ref());
+        let path = CameraPath::new()
+            .keyframe(0.0, from)
+            .keyframe(1.0, from.upright(axis));
+        self.path_playback = Some(PathPlayback {
+            path,
+            duration: ALIGN_UP_DURATION,
+            elapsed: 0.0,
+        });
+    }
+
+    /// Starts dragging the handle of `gizmo` under the mouse, if any, and returns it.
+    pub fn begin_gizmo_drag(&mut self) -> Option<GizmoHandle> {
+        let gizmo = self.gizmo?;
+        let (origin, dir) = self.ray_from_last_mouse();
+        let (origin, dir) = (to_na(origin), vec_to_na(dir));
+        let handle = gizmo.pick(origin, dir)?;
+        self.gizmo_drag = Some(GizmoDrag::begin(&gizmo, handle, origin, dir)?);
+        Some(handle)
+    }
+
+    /// The handle being dragged, if any.
+    pub fn gizmo_drag_handle(&self) -> Option<GizmoHandle> {
+        self.gizmo_drag.map(|drag| drag.handle())
+    }
+
+    /// Grabs atom `atom` under the cursor for `settings.drag_atoms`.
+    fn grab_atom<U: AdditionalRender>(&mut self, atom: usize, viewer: &MoleculeViewer<U>) {
+        let Some(position) = viewer
+            .displayed_molecule()
+            .and_then(|mol| Some(mol.atoms.get(atom)?.position))
+        else {
+            return;
+        };
+        let normal = (self.camera.target() - self.camera.position())
+            .try_normalize(1e-9)
+            .unwrap_or_else(Vector3::z);
+        let (origin, dir) = self.ray_from_last_mouse();
+        let Some(hit) = picking::ray_plane(to_na(origin), vec_to_na(dir), position, normal) else {
+            return;
+        };
+        self.atom_grab = Some(AtomGrab {
+            atom,
+            plane_point: position,
+            normal,
+            offset: position - hit.point,
+            rigid: self.shift_pressed,
+            dragged: false,
+        });
+    }
+
+    /// Starts moving the viewer's cutaway, if it has one. Returns whether it
+    /// did.
+    fn grab_cutaway<U: AdditionalRender>(&mut self, viewer: &MoleculeViewer<U>) -> bool {
+        let Some(cutaway) = viewer.cutaway() else {
+            return false;
+        };
+        let normal = (self.camera.target() - self.camera.position())
+            .try_normalize(1e-9)
+            .unwrap_or_else(Vector3::z);
+        let (origin, dir) = self.ray_from_last_mouse();
+        let Some(hit) = picking::ray_plane(to_na(origin), vec_to_na(dir), cutaway.center, normal)
+        else {
+            return false;
+        };
+        self.cutaway_grab = Some(CutawayGrab {
+            plane_point: cutaway.center,
+            normal,
+            offset: cutaway.center - hit.point,
+        });
+        true
+    }
+
+    /// Jumps to an axis-aligned view of the target at the current distance,
+    /// following the camera's `up_axis`.
+    pub fn view_preset(&mut self, preset: ViewPreset) {
+        let target = self.camera.target();
+        let distance = (self.camera.position() - target).norm();
+        preset
+            .view(self.camera.up_axis(), target, distance)
+            .apply(self.camera.as_mut());
+    }
+
+    /// Frames `mol` from the current direction with `Camera::fit_to_molecule`,
+    /// leaving `FRAME_MARGIN` around the atom centers.
+    pub fn frame_molecule(&mut self, mol: &Molecule) {
+        self.stop_path();
+        self.camera.fit_to_molecule(mol, FRAME_MARGIN);
+    }
+
+    /// Switches between perspective and orthographic projection.
+    pub fn toggle_projection(&mut self) {
+        let next = match self.camera.projection() {
+            ProjectionType::Perspective => ProjectionType::Orthographic,
+            ProjectionType::Orthographic => ProjectionType::Perspective,
+        };
+        self.camera.set_projection(next);
+    }
+
+    /// Changes the field of view by `delta` radians and dollies to compensate,
+    /// so the subject at the target keeps its apparent size ("dolly zoom").
+    pub fn dolly_zoom(&mut self, delta: f32) {
+        let old_fov = self.camera.fov_y();
+        self.camera.set_fov(old_fov + delta);
+        let new_fov = self.camera.fov_y();
+        let distance = (self.camera.position() - self.camera.target()).norm();
+        let new_distance = distance * (old_fov * 0.5).tan() / (new_fov * 0.5).tan();
+        self.camera.dolly(distance - new_distance);
+    }
+
+    /// Field-of-view slider and projection switch for an egui panel.
+    /// Returns `true` if the camera changed.
+    pub fn camera_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        let mut projection = self.camera.projection();
+        ui.horizontal(|ui| {
+            changed |= ui
+                .selectable_value(&mut projection, ProjectionType::Perspective, "Perspective")
+                .changed();
+            changed |= ui
+                .selectable_value(
+                    &mut projection,
+                    ProjectionType::Orthographic,
+                    "Orthographic",
+                )
+                .changed();
+        });
+        if projection != self.camera.projection() {
+            self.camera.set_projection(projection);
+        }
+        let mut fov_deg = self.camera.fov_y().to_degrees();
+        let slider = egui::Slider::new(
+            &mut fov_deg,
+            MIN_FOV_Y.to_degrees()..=MAX_FOV_Y.to_degrees(),
+        )
+        .text("FOV (°)");
+        if ui.add(slider).changed() {
+            self.dolly_zoom(fov_deg.to_radians() - self.camera.fov_y());
+            changed = true;
+        }
+        changed
     }
 
     /// Blender-style navigation:
     /// - MMB drag: orbit
     /// - Shift + MMB: pan
     /// - Ctrl + MMB: dolly
-    /// - LMB: pick
+    /// - Ctrl + scroll: field of view, with dolly compensation
+    /// - Numpad 5: toggle perspective / orthographic
+    /// - Numpad 1 / 3 / 7: front, right, top view; with Ctrl: back, left,
+    ///   bottom (see `ViewPreset`)
+    /// - Home: turn the viewer's reference axis (if set) screen-up
+    /// - F: frame the viewer's active molecule (see
+    ///   `MoleculeViewer::fit_camera_to_molecule`)
+    /// - Delete / Backspace, Ctrl + A, Escape, H, Shift + H, I: delete,
+    ///   select all, clear selection and measurements, hide selected, show
+    ///   all, invert selection, reported as `ViewerEvent::EditRequested` and
+    ///   not applied;
+    ///   Escape cancels instead while bond mode has a first atom pending
+    /// - key shortcuts are off while `set_keyboard_captured(true)`
+    /// - LMB: pick, or drag a handle of `gizmo`, or with
+    ///   `settings.drag_atoms` drag an atom (Shift: its whole fragment);
+    ///   `select_on_click` selects what was picked, Ctrl adding to the
+    ///   selection
+    /// - Ctrl + LMB on an atom with `settings.drag_atoms`: pin or unpin it,
+    ///   reported as `ViewerEvent::EditRequested`
+    /// - Alt + LMB drag, Alt + scroll: move the viewer's cutaway (if set) in
+    ///   the view plane, resize it; reported as `ViewerEvent::CutawayChanged`
+    /// - Alt + LMB drag without a cutaway: drag a selection rectangle (see
+    ///   `selection_rect`, `selection_rect_ui`); on release its atoms are
+    ///   reported as `ViewerEvent::AtomsSelected` for `select_on_click`
+    /// - Cursor motion: hover-pick, throttled per `settings`
     pub fn handle_event<U: AdditionalRender>(
         &mut self,
         event: &WindowEvent,
@@ -56,6 +967,17 @@ impl<T: Camera + Default> CameraController<T> {
         let mut updates = EngineUpdates::default();
         let mut picked_event = None;
 
+        if matches!(
+            event,
+            WindowEvent::KeyboardInput { .. }
+                | WindowEvent::MouseInput { .. }
+                | WindowEvent::CursorMoved { .. }
+                | WindowEvent::MouseWheel { .. }
+                | WindowEvent::Touch(_)
+        ) {
+            self.reset_idle();
+        }
+
         match event {
             WindowEvent::Resized(size) => {
                 self.width = size.width as f32;
@@ -66,15 +988,7 @@ impl<T: Camera + Default> CameraController<T> {
             WindowEvent::KeyboardInput { event, .. } => {
                 let pressed = event.state == ElementState::Pressed;
                 if let PhysicalKey::Code(keycode) = event.physical_key {
-                    match keycode {
-                        KeyCode::ShiftLeft | KeyCode::ShiftRight => {
-                            self.shift_pressed = pressed;
-                        }
-                        KeyCode::ControlLeft | KeyCode::ControlRight => {
-                            self.ctrl_pressed = pressed;
-                        }
-                        _ => {}
-                    }
+                    (picked_event, updates) = self.key_input(keycode, pressed, viewer);
                 }
             }
             WindowEvent::MouseInput { state, button, .. } => {
@@ -82,7 +996,23 @@ impl<T: Camera + Default> CameraController<T> {
                 match button {
                     MouseButton::Left => {
                         self.mouse_lb_pressed = pressed;
-                        if pressed {
+                        if !pressed {
+                            self.gizmo_drag = None;
+                            self.cutaway_grab = None;
+                            if self.atom_grab.take().is_some_and(|grab| grab.dragged) {
+                                picked_event = Some(ViewerEvent::AtomDragEnded);
+                            }
+                            if let Some(rect) = self.selection_rect() {
+                                self.rect_select_start = None;
+                                picked_event = Some(ViewerEvent::AtomsSelected(
+                                    self.atoms_in_rect(&rect, viewer),
+                                ));
+                            }
+                        } else if self.alt_pressed && self.grab_cutaway(viewer) {
+                            // Moving the cutaway instead of picking.
+                        } else if self.alt_pressed {
+                            self.rect_select_start = Some(self.last_mouse_pos);
+                        } else if self.begin_gizmo_drag().is_none() {
                             // Picking
                             let (ray_origin, ray_dir) = self.camera.ray_from_screen(
                                 self.last_mouse_pos.x,
@@ -91,42 +1021,125 @@ impl<T: Camera + Default> CameraController<T> {
                                 self.height,
                             );
                             picked_event = viewer.pick(ray_origin, ray_dir);
+                            if let Some(ViewerEvent::AtomClicked(atom)) = picked_event {
+                                if self.settings.drag_atoms && self.ctrl_pressed {
+                                    picked_event = Some(ViewerEvent::EditRequested(
+                                        EditAction::TogglePin(atom),
+                                    ));
+                                } else if self.settings.drag_atoms {
+                                    self.grab_atom(atom, viewer);
+                                }
+                            }
                         }
                     }
                     MouseButton::Middle => self.mouse_mb_pressed = pressed,
                     MouseButton::Right => self.mouse_rb_pressed = pressed,
                     _ => {}
                 }
+                // A new drag starts a new orbit trail.
+                if pressed && matches!(button, MouseButton::Middle | MouseButton::Right) {
+                    self.orbit_trail = None;
+                }
+                // The pivot indicator fades from when the orbit drag ends.
+                if !pressed && self.orbit_drag && !self.mouse_mb_pressed && !self.mouse_rb_pressed {
+                    self.orbit_drag = false;
+                    self.last_orbit = Some(self.clock.now());
+                }
             }
             WindowEvent::CursorMoved { position, .. } => {
                 let new_pos = Point2::new(position.x as f32, position.y as f32);
                 let delta = new_pos - self.last_mouse_pos;
 
+                if self.gizmo_drag.is_some() {
+                    self.last_mouse_pos = new_pos;
+                    let (origin, dir) = self.ray_from_last_mouse();
+                    if let Some(drag) = &mut self.gizmo_drag {
+                        if let Some(motion) = drag.update(to_na(origin), vec_to_na(dir)) {
+                            if let Some(gizmo) = &mut self.gizmo {
+                                gizmo.center = drag.center();
+                            }
+                            picked_event = Some(ViewerEvent::GizmoDragged(motion));
+                        }
+                    }
+                    return (picked_event, updates);
+                }
+
+                if let Some(grab) = &mut self.atom_grab {
+                    self.last_mouse_pos = new_pos;
+                    let (origin, dir) =
+                        self.camera
+                            .ray_from_screen(new_pos.x, new_pos.y, self.width, self.height);
+                    let (origin, dir) = (to_na(origin), vec_to_na(dir));
+                    if let Some(hit) =
+                        picking::ray_plane(origin, dir, grab.plane_point, grab.normal)
+                    {
+                        grab.dragged = true;
+                        picked_event = Some(ViewerEvent::AtomDragged {
+                            atom: grab.atom,
+                            target: hit.point + grab.offset,
+                            rigid: grab.rigid,
+                        });
+                    }
+                    return (picked_event, updates);
+                }
+
+                if self.rect_select_start.is_some() {
+                    self.last_mouse_pos = new_pos;
+                    return (picked_event, updates);
+                }
+
+                if let Some(grab) = &self.cutaway_grab {
+                    self.last_mouse_pos = new_pos;
+                    let (origin, dir) =
+                        self.camera
+                            .ray_from_screen(new_pos.x, new_pos.y, self.width, self.height);
+                    let hit = picking::ray_plane(
+                        to_na(origin),
+                        vec_to_na(dir),
+                        grab.plane_point,
+                        grab.normal,
+                    );
+                    if let (Some(hit), Some(cutaway)) = (hit, viewer.cutaway()) {
+                        picked_event = Some(ViewerEvent::CutawayChanged(Cutaway {
+                            center: hit.point + grab.offset,
+                            ..cutaway
+                        }));
+                    }
+                    return (picked_event, updates);
+                }
+
                 // Orbit with MMB (or RMB for convenience)
                 if self.mouse_mb_pressed || self.mouse_rb_pressed {
-                    if self.shift_pressed {
-                        // Pan
-                        let sensitivity = 0.01;
-                        self.camera
-                            .pan(Vector2::new(delta.x * sensitivity, delta.y * sensitivity));
+                    let mode = if self.shift_pressed {
+                        DragMode::Pan
                     } else if self.ctrl_pressed {
-                        // Dolly
-                        self.camera.dolly(delta.y * 0.1);
+                        DragMode::Dolly
                     } else {
-                        // Orbit
-                        // Sensitivity: 0.005 radians per pixel
-                        self.camera.orbit(delta.x * 0.005, delta.y * 0.005);
-                    }
-                    updates.camera = true;
+                        DragMode::Orbit
+                    };
+                    self.orbit_drag |= mode == DragMode::Orbit;
+                    updates.camera = self.camera_drag(delta, mode);
                 }
-                self.last_mouse_pos = new_pos;
+                picked_event = self.hover_at(new_pos.x, new_pos.y, viewer);
             }
             WindowEvent::MouseWheel { delta, .. } => {
                 let scroll = match delta {
                     MouseScrollDelta::LineDelta(_, y) => *y * 1.0,
                     MouseScrollDelta::PixelDelta(pos) => pos.y as f32 * 0.1,
                 };
-                self.camera.dolly(scroll);
+                if let Some(cutaway) = viewer.cutaway().filter(|_| self.alt_pressed) {
+                    let radius = cutaway.radius * CUTAWAY_SCROLL_STEP.powf(scroll);
+                    picked_event = Some(ViewerEvent::CutawayChanged(Cutaway {
+                        radius: radius.max(MIN_CUTAWAY_RADIUS),
+                        ..cutaway
+                    }));
+                    return (picked_event, updates);
+                }
+                if self.ctrl_pressed {
+                    self.dolly_zoom(-scroll * FOV_STEP);
+                } else {
+                    self.camera.dolly(scroll);
+                }
                 updates.camera = true;
             }
             _ => {}
@@ -137,25 +1150,38 @@ impl<T: Camera + Default> CameraController<T> {
 
     /// Synchronize camera state into rendering scene.
     pub fn update_scene_camera(&self, scene: &mut Scene) {
-        let pos = self.camera.position();
+        let mut pos = self.camera.position();
         let target = self.camera.target();
 
-        // Bridge nalgebra to lin_alg
-        scene.camera.position = lin_alg::f32::Vec3::new(pos.x, pos.y, pos.z);
-
         // Calculate orientation
         let fwd = (target - pos).normalize();
+
+        let (mut fov_y, mut near, mut far) =
+            (self.camera.fov_y(), self.camera.near(), self.camera.far());
+        if self.camera.projection() == ProjectionType::Orthographic {
+            // Back off along the view until `ORTHO_FOV_Y` spans the
+            // orthographic height at the target, keeping the clip planes
+            // where they were.
+            let distance = self.camera.ortho_scale() / (2.0 * (ORTHO_FOV_Y / 2.0).tan());
+            let back = distance - (target - pos).norm();
+            pos = target - fwd * distance;
+            fov_y = ORTHO_FOV_Y;
+            near += back;
+            far += back;
+        }
+        scene.camera.position = to_la(pos);
+
         // Assuming default forward is (0,0,1) or (0,0,-1).
         // WGPU often uses +Z or -Z.
         // Let's use from_unit_vecs similarly to how viewer.rs handles cylinders.
         scene.camera.orientation = lin_alg::f32::Quaternion::from_unit_vecs(
             lin_alg::f32::Vec3::new(0.0, 0.0, 1.0),
-            lin_alg::f32::Vec3::new(fwd.x, fwd.y, fwd.z),
+            vec_to_la(fwd),
         );
 
-        scene.camera.fov_y = self.camera.fov_y();
-        scene.camera.near = self.camera.near();
-        scene.camera.far = self.camera.far();
+        scene.camera.fov_y = fov_y;
+        scene.camera.near = near;
+        scene.camera.far = far;
         // Aspect
         scene.camera.aspect = self.width / self.height;
 
@@ -163,6 +1189,11 @@ impl<T: Camera + Default> CameraController<T> {
         scene.camera.update_proj_mat();
     }
 
+    /// Sets how the camera's orthographic extent follows window resizes.
+    pub fn set_resize_policy(&mut self, policy: ResizePolicy) {
+        self.camera.set_resize_policy(policy);
+    }
+
     pub fn ray_from_last_mouse(&self) -> (lin_alg::f32::Vec3, lin_alg::f32::Vec3) {
         self.camera.ray_from_screen(
             self.last_mouse_pos.x,
@@ -0,0 +1,51 @@
+//! Conversions between `nalgebra` (used for molecule data and camera math) and
+//! `lin_alg` (used by the `graphics` crate), and with the `bevy` feature,
+//! Bevy's `Vec3`.
+//!
+//! Both crates use Hamilton quaternions that rotate vectors as `q * v * q⁻¹`,
+//! so quaternion conversion is a plain component copy.
+
+use lin_alg::f32::{Quaternion, Vec3};
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+
+/// nalgebra point -> lin_alg vector.
+pub fn to_la(p: Point3<f32>) -> Vec3 {
+    Vec3::new(p.x, p.y, p.z)
+}
+
+/// lin_alg vector -> nalgebra point.
+pub fn to_na(v: Vec3) -> Point3<f32> {
+    Point3::new(v.x, v.y, v.z)
+}
+
+/// nalgebra vector -> lin_alg vector.
+pub fn vec_to_la(v: Vector3<f32>) -> Vec3 {
+    Vec3::new(v.x, v.y, v.z)
+}
+
+/// lin_alg vector -> nalgebra vector.
+pub fn vec_to_na(v: Vec3) -> Vector3<f32> {
+    Vector3::new(v.x, v.y, v.z)
+}
+
+/// nalgebra unit quaternion -> lin_alg quaternion.
+pub fn quat_to_la(q: UnitQuaternion<f32>) -> Quaternion {
+    Quaternion::new(q.w, q.i, q.j, q.k)
+}
+
+/// lin_alg quaternion -> nalgebra unit quaternion (renormalized).
+pub fn quat_to_na(q: Quaternion) -> UnitQuaternion<f32> {
+    UnitQuaternion::from_quaternion(nalgebra::Quaternion::new(q.w, q.x, q.y, q.z))
+}
+
+/// nalgebra vector -> Bevy vector.
+#[cfg(feature = "bevy")]
+pub fn vec_to_bevy(v: Vector3<f32>) -> bevy::math::Vec3 {
+    bevy::math::Vec3::new(v.x, v.y, v.z)
+}
+
+/// Bevy vector -> lin_alg vector.
+#[cfg(feature = "bevy")]
+pub fn bevy_to_la(v: bevy::math::Vec3) -> Vec3 {
+    Vec3::new(v.x, v.y, v.z)
+}
@@ -0,0 +1,225 @@
+//! Coordination spheres of metal ions: the donor atoms around them and the
+//! ideal geometry they best match.
+
+use crate::element;
+use crate::molecule::Molecule;
+use nalgebra::{Point3, Vector3};
+use std::fmt;
+
+/// Default search radius around the metal, in Å.
+pub const COORDINATION_CUTOFF: f32 = 3.0;
+/// Largest RMS angle deviation, in degrees, for a geometry to be recognized.
+pub const GEOMETRY_MAX_RMS: f32 = 15.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoordinationGeometry {
+    Tetrahedral,
+    SquarePlanar,
+    /// Also five-coordinate square pyramids and other octahedra with vacant sites.
+    Octahedral,
+    Unknown,
+}
+
+impl CoordinationGeometry {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Tetrahedral => "tetrahedral",
+            Self::SquarePlanar => "square planar",
+            Self::Octahedral => "octahedral",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ligand {
+    pub atom: usize,
+    pub distance: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoordinationInfo {
+    pub center: usize,
+    pub element: String,
+    /// Donor atoms within the cutoff, nearest first.
+    pub ligands: Vec<Ligand>,
+    pub geometry: CoordinationGeometry,
+    /// RMS deviation in degrees from the closest template, if there was one to
+    /// compare against (four to six ligands).
+    pub geometry_rms: Option<f32>,
+}
+
+impl CoordinationInfo {
+    pub fn coordination_number(&self) -> usize {
+        self.ligands.len()
+    }
+
+    pub fn average_distance(&self) -> Option<f32> {
+        if self.ligands.is_empty() {
+            return None;
+        }
+        Some(self.ligands.iter().map(|l| l.distance).sum::<f32>() / self.ligands.len() as f32)
+    }
+}
+
+impl fmt::Display for CoordinationInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}: CN {}",
+            self.element,
+            self.center,
+            self.ligands.len()
+        )?;
+        if let Some(mean) = self.average_distance() {
+            write!(f, ", mean {mean:.2} Å")?;
+        }
+        write!(f, ", {}", self.geometry.name())
+    }
+}
+
+impl Molecule {
+    /// The O, N and S atoms within `cutoff` Å of atom `atom_idx`, and the
+    /// geometry they form. `None` if there is no such atom. Does not check that
+    /// the center is a metal; see `element::is_metal`.
+    pub fn coordination_sphere(&self, atom_idx: usize, cutoff: f32) -> Option<CoordinationInfo> {
+        let center = self.atoms.get(atom_idx)?;
+        let mut ligands: Vec<Ligand> = self
+            .atoms
+            .iter()
+            .enumerate()
+            .filter(|(i, atom)| *i != atom_idx && is_donor(&atom.element))
+            .filter_map(|(i, atom)| {
+                let distance = (atom.position - center.position).norm();
+                (distance <= cutoff && distance > 0.0).then_some(Ligand { atom: i, distance })
+            })
+            .collect();
+        ligands.sort_by(|a, b| a.distance.total_cmp(&b.distance).then(a.atom.cmp(&b.atom)));
+
+        let directions: Vec<_> = ligands
+            .iter()
+            .map(|l| (self.atoms[l.atom].position - center.position).normalize())
+            .collect();
+        let (geometry, geometry_rms) = match classify(&directions) {
+            Some((geometry, rms)) if rms <= GEOMETRY_MAX_RMS => (geometry, Some(rms)),
+            Some((_, rms)) => (CoordinationGeometry::Unknown, Some(rms)),
+            None => (CoordinationGeometry::Unknown, None),
+        };
+        Some(CoordinationInfo {
+            center: atom_idx,
+            element: center.element.clone(),
+            ligands,
+            geometry,
+            geometry_rms,
+        })
+    }
+}
+
+fn is_donor(element: &str) -> bool {
+    ["O", "N", "S"]
+        .iter()
+        .any(|donor| donor.eq_ignore_ascii_case(element))
+}
+
+/// Closest template geometry to the ligand `directions` and its RMS angle
+/// deviation, comparing sorted pairwise angles. Templates with more sites than
+/// ligands are tried with every choice of occupied sites. `None` for fewer than
+/// four or more than six ligands.
+fn classify(directions: &[Vector3<f32>]) -> Option<(CoordinationGeometry, f32)> {
+    let n = directions.len();
+    if !(4..=6).contains(&n) {
+        return None;
+    }
+    let observed = sorted_angles(directions);
+    let s = 1.0 / 3f32.sqrt();
+    let templates = [
+        (
+            CoordinationGeometry::Tetrahedral,
+            vec![
+                Vector3::new(s, s, s),
+                Vector3::new(s, -s, -s),
+                Vector3::new(-s, s, -s),
+                Vector3::new(-s, -s, s),
+            ],
+        ),
+        (
+            CoordinationGeometry::SquarePlanar,
+            vec![Vector3::x(), -Vector3::x(), Vector3::y(), -Vector3::y()],
+        ),
+        (
+            CoordinationGeometry::Octahedral,
+            vec![
+                Vector3::x(),
+                -Vector3::x(),
+                Vector3::y(),
+                -Vector3::y(),
+                Vector3::z(),
+                -Vector3::z(),
+            ],
+        ),
+    ];
+
+    let mut best: Option<(CoordinationGeometry, f32)> = None;
+    for (geometry, sites) in &templates {
+        for mask in 0u32..1 << sites.len() {
+            if mask.count_ones() as usize != n {
+                continue;
+            }
+            let chosen: Vec<_> = (0..sites.len())
+                .filter(|i| mask & (1 << i) != 0)
+                .map(|i| sites[i])
+                .collect();
+            let expected = sorted_angles(&chosen);
+            let sum_sq: f32 = observed
+                .iter()
+                .zip(&expected)
+                .map(|(a, b)| (a - b).powi(2))
+                .sum();
+            let rms = (sum_sq / observed.len() as f32).sqrt();
+            // Strictly better only, so earlier, full templates win ties.
+            if best.is_none_or(|(_, best_rms)| rms < best_rms) {
+                best = Some((*geometry, rms));
+            }
+        }
+    }
+    best
+}
+
+/// Angles in degrees between every pair of `directions`, ascending.
+fn sorted_angles(directions: &[Vector3<f32>]) -> Vec<f32> {
+    let mut angles = Vec::new();
+    for (i, a) in directions.iter().enumerate() {
+        for b in &directions[i + 1..] {
+            angles.push(a.angle(b).to_degrees());
+        }
+    }
+    angles.sort_by(f32::total_cmp);
+    angles
+}
+
+/// Atoms of `molecule` whose element is a metal, in index order.
+pub fn metal_atoms(molecule: &Molecule) -> Vec<usize> {
+    molecule
+        .atoms
+        .iter()
+        .enumerate()
+        .filter(|(_, atom)| element::is_metal(&atom.element))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Center and ligand positions of `info` in `molecule`, skipping atoms that are
+/// out of range (e.g. after the molecule was replaced).
+#[cfg_attr(not(feature = "render"), allow(dead_code))]
+pub(crate) fn ligand_segments(
+    molecule: &Molecule,
+    info: &CoordinationInfo,
+) -> Vec<(Point3<f32>, Point3<f32>)> {
+    let Some(center) = molecule.atoms.get(info.center) else {
+        return Vec::new();
+    };
+    info.ligands
+        .iter()
+        .filter_map(|l| Some((center.position, molecule.atoms.get(l.atom)?.position)))
+        .collect()
+}
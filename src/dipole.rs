@@ -0,0 +1,110 @@
+//! Electric dipole moments from partial charges, for a whole molecule or per
+//! residue or bonded fragment.
+
+use crate::molecule::Molecule;
+use nalgebra::{Point3, Vector3};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Debye per e·Å.
+pub const DEBYE_PER_E_ANGSTROM: f32 = 4.803_204;
+
+/// How `Molecule::dipoles` splits the atoms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DipoleGroups {
+    Molecule,
+    /// By `Molecule::residues`; nothing if the molecule has none.
+    Residue,
+    /// By `Molecule::fragments`.
+    Fragment,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dipole {
+    /// Atoms of the group, ascending.
+    pub atoms: Vec<usize>,
+    /// Geometric center of the atoms, where the dipole is drawn from.
+    pub centroid: Point3<f32>,
+    /// Dipole moment in Debye, pointing from negative to positive charge.
+    pub moment: Vector3<f32>,
+}
+
+impl Molecule {
+    /// Dipole moment of the atoms `indices`, in Debye, about their center of
+    /// charge (weighted by absolute charge, so charged groups get a
+    /// well-defined origin). `None` without `charges`, for an empty or
+    /// out-of-range `indices`.
+    pub fn dipole_moment(&self, indices: &[usize]) -> Option<Vector3<f32>> {
+        let charges = self.charges.as_ref()?;
+        if indices.is_empty() {
+            return None;
+        }
+        let mut weight = 0.0;
+        let mut weighted = Vector3::zeros();
+        let mut centroid = Vector3::zeros();
+        for &i in indices {
+            let (q, p) = (*charges.get(i)?, self.atoms.get(i)?.position.coords);
+            weight += q.abs();
+            weighted += p * q.abs();
+            centroid += p;
+        }
+        let center = if weight > 0.0 {
+            weighted / weight
+        } else {
+            centroid / indices.len() as f32
+        };
+        let moment = indices.iter().fold(Vector3::zeros(), |sum, &i| {
+            sum + (self.atoms[i].position.coords - center) * charges[i]
+        });
+        Some(moment * DEBYE_PER_E_ANGSTROM)
+    }
+
+    /// Dipole of each group of atoms. Empty without `charges`.
+    pub fn dipoles(&self, groups: DipoleGroups) -> Vec<Dipole> {
+        let groups = match groups {
+            DipoleGroups::Molecule => vec![(0..self.atoms.len()).collect()],
+            DipoleGroups::Residue => {
+                let mut by_residue: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+                for (i, &residue) in self.residues.iter().flatten().enumerate() {
+                    by_residue.entry(residue).or_default().push(i);
+                }
+                by_residue.into_values().collect()
+            }
+            DipoleGroups::Fragment => self.fragments(),
+        };
+        groups
+            .into_iter()
+            .filter_map(|atoms: Vec<usize>| {
+                let moment = self.dipole_moment(&atoms)?;
+                let sum = atoms.iter().fold(Vector3::zeros(), |sum, &i| {
+                    sum + self.atoms[i].position.coords
+                });
+                Some(Dipole {
+                    centroid: Point3::from(sum / atoms.len() as f32),
+                    atoms,
+                    moment,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Tab-separated table of `dipoles`, one per line after a header row.
+pub fn dipole_report(dipoles: &[Dipole]) -> String {
+    let mut report = String::from("group\tatoms\tcentroid\tx (D)\ty (D)\tz (D)\tmagnitude (D)\n");
+    for (i, d) in dipoles.iter().enumerate() {
+        let _ = writeln!(
+            report,
+            "{i}\t{}\t{:.3},{:.3},{:.3}\t{:.3}\t{:.3}\t{:.3}\t{:.3}",
+            d.atoms.len(),
+            d.centroid.x,
+            d.centroid.y,
+            d.centroid.z,
+            d.moment.x,
+            d.moment.y,
+            d.moment.z,
+            d.moment.norm()
+        );
+    }
+    report
+}
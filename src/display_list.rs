@@ -0,0 +1,169 @@
+//! What the viewer drew, as analytic primitives, for external renderers such
+//! as POV-Ray or OSPRay that trace spheres and cylinders exactly.
+//!
+//! # JSON schema
+//!
+//! `DisplayList::to_json` writes one object; lengths are in the molecule's
+//! unit (Å), colors are linear RGB in `0..=1`:
+//!
+//! ```text
+//! {
+//!   "version": 1,
+//!   "spheres":   [{ "center": [x, y, z], "radius": r,
+//!                   "color": [r, g, b], "opacity": a, "shininess": s }],
+//!   "cylinders": [{ "start": [x, y, z], "end": [x, y, z], "radius": r,
+//!                   "color": [r, g, b], "opacity": a, "shininess": s }]
+//! }
+//! ```
+//!
+//! Cylinders are open-ended; atom spheres, or small cap spheres where a bond
+//! is cut or stubbed, cover bond ends. `opacity` below
+//! 1 marks ghosted atoms and bonds. Files with a newer `version` are rejected.
+
+use crate::colormap::Rgb;
+#[cfg(feature = "render")]
+use crate::convert::{quat_to_na, to_na};
+use crate::molecule::Molecule;
+use crate::style::{self, BondShape, MoleculeDisplayState};
+#[cfg(feature = "render")]
+use crate::viewer::{EntityKind, MoleculeViewer};
+#[cfg(feature = "render")]
+use crate::AdditionalRender;
+#[cfg(feature = "render")]
+use graphics::Scene;
+use nalgebra::Point3;
+#[cfg(feature = "render")]
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Schema version written by `to_json`.
+pub const DISPLAY_LIST_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sphere {
+    pub center: Point3<f32>,
+    pub radius: f32,
+    pub color: Rgb,
+    pub opacity: f32,
+    pub shininess: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cylinder {
+    pub start: Point3<f32>,
+    pub end: Point3<f32>,
+    pub radius: f32,
+    pub color: Rgb,
+    pub opacity: f32,
+    pub shininess: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DisplayList {
+    pub version: u32,
+    pub spheres: Vec<Sphere>,
+    pub cylinders: Vec<Cylinder>,
+}
+
+impl Default for DisplayList {
+    fn default() -> Self {
+        Self {
+            version: DISPLAY_LIST_VERSION,
+            spheres: Vec::new(),
+            cylinders: Vec::new(),
+        }
+    }
+}
+
+impl DisplayList {
+    /// Atoms and bonds of `mol` as `state` would draw them, without building
+    /// a scene: the same primitives, in the same order, as `from_scene` gives
+    /// for a viewer showing only this molecule, minus the reference axis.
+    /// Available without the `render` feature.
+    pub fn from_molecule(mol: &Molecule, state: &MoleculeDisplayState) -> Self {
+        let style = state.style();
+        let mut list = Self::default();
+        list.spheres
+            .extend(style::atom_spheres(mol, style).into_iter().map(|(_, s)| s));
+        for (_, shape) in style::bond_shapes(mol, style) {
+            match shape {
+                BondShape::Cylinder(cylinder) => list.cylinders.push(cylinder),
+                BondShape::Cap(cap) => list.spheres.push(cap),
+            }
+        }
+        list
+    }
+
+    /// Atoms, bonds and the reference axis of a scene built by
+    /// `viewer.update_scene`, in scene order. Overlay entities are left out,
+    /// since their meshes are not known to be spheres or cylinders, and so
+    /// are the cutaway wireframe, pin markers and selection shells.
+    #[cfg(feature = "render")]
+    pub fn from_scene<U: AdditionalRender>(viewer: &MoleculeViewer<U>, scene: &Scene) -> Self {
+        let mut list = Self::default();
+        for (i, entity) in scene.entities.iter().enumerate() {
+            let center = to_na(entity.position);
+            match viewer.classify_entity(i) {
+                Some(EntityKind::Atom { .. }) => list.spheres.push(sphere(center, entity)),
+                Some(EntityKind::Bond { .. } | EntityKind::ReferenceAxis) => {
+                    // Cylinders are the unit Y cylinder scaled to
+                    // (radius, length, radius); see `segment_entity`. Bond
+                    // entities without that scale are the spheres capping
+                    // slab cuts and stubs.
+                    let Some(scale) = entity.scale_partial else {
+                        list.spheres.push(sphere(center, entity));
+                        continue;
+                    };
+                    let half: Vector3<f32> =
+                        quat_to_na(entity.orientation) * Vector3::y() * (scale.y * 0.5);
+                    list.cylinders.push(Cylinder {
+                        start: center - half,
+                        end: center + half,
+                        radius: scale.x,
+                        color: entity.color,
+                        opacity: entity.opacity,
+                        shininess: entity.shinyness,
+                    });
+                }
+                Some(
+                    EntityKind::Overlay { .. }
+                    | EntityKind::Cutaway
+                    | EntityKind::PinMarker { .. }
+                    | EntityKind::SelectedAtom { .. }
+                    | EntityKind::SelectedBond { .. },
+                )
+                | None => {}
+            }
+        }
+        list
+    }
+
+    pub fn to_json(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+
+    pub fn from_json(path: &Path) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let list: Self = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+        if list.version > DISPLAY_LIST_VERSION {
+            return Err(format!(
+                "display list version {} is newer than supported ({DISPLAY_LIST_VERSION})",
+                list.version
+            ));
+        }
+        Ok(list)
+    }
+}
+
+#[cfg(feature = "render")]
+fn sphere(center: Point3<f32>, entity: &graphics::Entity) -> Sphere {
+    Sphere {
+        center,
+        radius: entity.scale,
+        color: entity.color,
+        opacity: entity.opacity,
+        shininess: entity.shinyness,
+    }
+}
@@ -0,0 +1,153 @@
+//! Per-element reference data. Lookups ignore case, since loaders differ on
+//! whether they store "Cl" or "CL".
+
+/// Element of atoms whose element could not be determined.
+pub const UNKNOWN: &str = "X";
+
+/// The element `symbol` names, spelled as in the periodic table ("Cl"), or
+/// `None` if it is not an element.
+pub fn symbol(symbol: &str) -> Option<&'static str> {
+    SYMBOLS
+        .iter()
+        .find(|s| s.eq_ignore_ascii_case(symbol))
+        .copied()
+}
+
+/// Van der Waals radius in Å (Bondi). Unknown elements get 1.7 Å.
+pub fn vdw_radius(element: &str) -> f32 {
+    lookup(VDW_RADII, element).unwrap_or(1.7)
+}
+
+/// Single-bond covalent radius in Å (Cordero et al. 2008), or `None` for
+/// elements without a tabulated one.
+pub fn covalent_radius(element: &str) -> Option<f32> {
+    lookup(COVALENT_RADII, element)
+}
+
+/// Periodic table row (1 for H and He, 2 for Li to Ne, ...), or `None` for
+/// unknown symbols.
+pub fn period(element: &str) -> Option<u8> {
+    let z = SYMBOLS
+        .iter()
+        .position(|symbol| symbol.eq_ignore_ascii_case(element))?
+        + 1;
+    let period = match z {
+        1..=2 => 1,
+        3..=10 => 2,
+        11..=18 => 3,
+        19..=36 => 4,
+        37..=54 => 5,
+        55..=86 => 6,
+        _ => 7,
+    };
+    Some(period)
+}
+
+/// Standard atomic weight in g/mol (Da), or for elements without stable
+/// isotopes the mass number of the longest-lived one. `None` for unknown
+/// symbols.
+pub fn atomic_mass(element: &str) -> Option<f32> {
+    let i = SYMBOLS
+        .iter()
+        .position(|symbol| symbol.eq_ignore_ascii_case(element))?;
+    Some(ATOMIC_MASSES[i])
+}
+
+/// Whether `element` is a metal; false for nonmetals, metalloids and unknown
+/// symbols.
+pub fn is_metal(element: &str) -> bool {
+    SYMBOLS.iter().any(|s| s.eq_ignore_ascii_case(element))
+        && !NON_METALS.iter().any(|s| s.eq_ignore_ascii_case(element))
+}
+
+fn lookup(table: &[(&str, f32)], element: &str) -> Option<f32> {
+    table
+        .iter()
+        .find(|(symbol, _)| symbol.eq_ignore_ascii_case(element))
+        .map(|(_, value)| *value)
+}
+
+const VDW_RADII: &[(&str, f32)] = &[
+    ("H", 1.20),
+    ("He", 1.40),
+    ("Li", 1.82),
+    ("B", 1.92),
+    ("C", 1.70),
+    ("N", 1.55),
+    ("O", 1.52),
+    ("F", 1.47),
+    ("Ne", 1.54),
+    ("Na", 2.27),
+    ("Mg", 1.73),
+    ("Al", 1.84),
+    ("Si", 2.10),
+    ("P", 1.80),
+    ("S", 1.80),
+    ("Cl", 1.75),
+    ("Ar", 1.88),
+    ("K", 2.75),
+    ("Ca", 2.31),
+    ("Fe", 2.04),
+    ("Cu", 1.40),
+    ("Zn", 1.39),
+    ("Se", 1.90),
+    ("Br", 1.85),
+    ("I", 1.98),
+];
+
+pub(crate) const COVALENT_RADII: &[(&str, f32)] = &[
+    ("H", 0.31),
+    ("Li", 1.28),
+    ("B", 0.84),
+    ("C", 0.76),
+    ("N", 0.71),
+    ("O", 0.66),
+    ("F", 0.57),
+    ("Na", 1.66),
+    ("Mg", 1.41),
+    ("Al", 1.21),
+    ("Si", 1.11),
+    ("P", 1.07),
+    ("S", 1.05),
+    ("Cl", 1.02),
+    ("K", 2.03),
+    ("Ca", 1.76),
+    ("Fe", 1.32),
+    ("Cu", 1.32),
+    ("Zn", 1.22),
+    ("Se", 1.20),
+    ("Br", 1.20),
+    ("I", 1.39),
+];
+
+/// Nonmetals, noble gases and metalloids.
+const NON_METALS: &[&str] = &[
+    "H", "He", "B", "C", "N", "O", "F", "Ne", "Si", "P", "S", "Cl", "Ar", "Ge", "As", "Se", "Br",
+    "Kr", "Sb", "Te", "I", "Xe", "At", "Rn", "Ts", "Og",
+];
+
+/// Element symbols in atomic number order.
+const SYMBOLS: &[&str] = &[
+    "H", "He", "Li", "Be", "B", "C", "N", "O", "F", "Ne", "Na", "Mg", "Al", "Si", "P", "S", "Cl",
+    "Ar", "K", "Ca", "Sc", "Ti", "V", "Cr", "Mn", "Fe", "Co", "Ni", "Cu", "Zn", "Ga", "Ge", "As",
+    "Se", "Br", "Kr", "Rb", "Sr", "Y", "Zr", "Nb", "Mo", "Tc", "Ru", "Rh", "Pd", "Ag", "Cd", "In",
+    "Sn", "Sb", "Te", "I", "Xe", "Cs", "Ba", "La", "Ce", "Pr", "Nd", "Pm", "Sm", "Eu", "Gd", "Tb",
+    "Dy", "Ho", "Er", "Tm", "Yb", "Lu", "Hf", "Ta", "W", "Re", "Os", "Ir", "Pt", "Au", "Hg", "Tl",
+    "Pb", "Bi", "Po", "At", "Rn", "Fr", "Ra", "Ac", "Th", "Pa", "U", "Np", "Pu", "Am", "Cm", "Bk",
+    "Cf", "Es", "Fm", "Md", "No", "Lr", "Rf", "Db", "Sg", "Bh", "Hs", "Mt", "Ds", "Rg", "Cn", "Nh",
+    "Fl", "Mc", "Lv", "Ts", "Og",
+];
+
+/// Indexed like `SYMBOLS`.
+const ATOMIC_MASSES: [f32; 118] = [
+    1.008, 4.0026, 6.94, 9.0122, 10.81, 12.011, 14.007, 15.999, 18.998, 20.180, 22.990, 24.305,
+    26.982, 28.085, 30.974, 32.06, 35.45, 39.948, 39.098, 40.078, 44.956, 47.867, 50.942, 51.996,
+    54.938, 55.845, 58.933, 58.693, 63.546, 65.38, 69.723, 72.630, 74.922, 78.971, 79.904, 83.798,
+    85.468, 87.62, 88.906, 91.224, 92.906, 95.95, 98.0, 101.07, 102.91, 106.42, 107.87, 112.41,
+    114.82, 118.71, 121.76, 127.60, 126.90, 131.29, 132.91, 137.33, 138.91, 140.12, 140.91, 144.24,
+    145.0, 150.36, 151.96, 157.25, 158.93, 162.50, 164.93, 167.26, 168.93, 173.05, 174.97, 178.49,
+    180.95, 183.84, 186.21, 190.23, 192.22, 195.08, 196.97, 200.59, 204.38, 207.2, 208.98, 209.0,
+    210.0, 222.0, 223.0, 226.0, 227.0, 232.04, 231.04, 238.03, 237.0, 244.0, 243.0, 247.0, 247.0,
+    251.0, 252.0, 257.0, 258.0, 259.0, 266.0, 267.0, 268.0, 269.0, 270.0, 277.0, 278.0, 281.0,
+    282.0, 285.0, 286.0, 289.0, 290.0, 293.0, 294.0, 294.0,
+];
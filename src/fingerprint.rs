@@ -0,0 +1,150 @@
+//! Hashes for telling whether two molecules are the same, e.g. to catch a file
+//! loaded twice. Values are only comparable within one build of the crate.
+
+use crate::molecule::Molecule;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Positions closer than this, in Å, count as the same by
+/// `Molecule::same_positions` and round alike in `coordinate_hash`.
+pub const COORDINATE_TOLERANCE: f32 = 1e-3;
+
+fn hash_of(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Both hashes of a molecule, kept to compare it with others without
+/// rehashing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint {
+    /// `Molecule::topology_hash`.
+    pub topology: u64,
+    /// `Molecule::coordinate_hash`.
+    pub coordinates: u64,
+}
+
+impl Molecule {
+    pub fn fingerprint(&self) -> Fingerprint {
+        Fingerprint {
+            topology: self.topology_hash(),
+            coordinates: self.coordinate_hash(),
+        }
+    }
+
+    /// Hash of the bond graph with element and bond order labels, independent
+    /// of atom order and coordinates. Atom labels are refined from their
+    /// neighbours (Weisfeiler-Lehman) until the partition stops changing, so
+    /// isomers hash differently in all but contrived cases.
+    pub fn topology_hash(&self) -> u64 {
+        let n = self.atoms.len();
+        let mut neighbors: Vec<Vec<(u8, usize)>> = vec![Vec::new(); n];
+        for bond in &self.bonds {
+            let (a, b) = (bond.atom_a, bond.atom_b);
+            if a < n && b < n && a != b {
+                neighbors[a].push((bond.order, b));
+                neighbors[b].push((bond.order, a));
+            }
+        }
+        let mut labels: Vec<u64> = self
+            .atoms
+            .iter()
+            .map(|atom| hash_of(atom.element.to_ascii_uppercase()))
+            .collect();
+        let distinct = |labels: &[u64]| {
+            let mut sorted = labels.to_vec();
+            sorted.sort_unstable();
+            sorted.dedup();
+            sorted.len()
+        };
+        let mut classes = distinct(&labels);
+        for _ in 0..n {
+            let next: Vec<u64> = (0..n)
+                .map(|i| {
+                    let mut around: Vec<(u8, u64)> = neighbors[i]
+                        .iter()
+                        .map(|&(order, j)| (order, labels[j]))
+                        .collect();
+                    around.sort_unstable();
+                    hash_of((labels[i], around))
+                })
+                .collect();
+            labels = next;
+            let refined = distinct(&labels);
+            if refined == classes {
+                break;
+            }
+            classes = refined;
+        }
+        labels.sort_unstable();
+        hash_of((n, self.bonds.len(), labels))
+    }
+
+    /// Whether both molecules have the same elements and the same bonds, as
+    /// (element, element, order), counted with multiplicity: the check to
+    /// confirm equal `topology_hash`es with, since hashes can collide. Not a
+    /// full isomorphism test.
+    pub fn same_topology(&self, other: &Molecule) -> bool {
+        let elements = |mol: &Molecule| {
+            let mut elements: Vec<String> = mol
+                .atoms
+                .iter()
+                .map(|atom| atom.element.to_ascii_uppercase())
+                .collect();
+            elements.sort_unstable();
+            elements
+        };
+        let bonds = |mol: &Molecule| {
+            let n = mol.atoms.len();
+            let mut bonds: Vec<(String, String, u8)> = mol
+                .bonds
+                .iter()
+                .filter(|bond| bond.atom_a < n && bond.atom_b < n && bond.atom_a != bond.atom_b)
+                .map(|bond| {
+                    let a = mol.atoms[bond.atom_a].element.to_ascii_uppercase();
+                    let b = mol.atoms[bond.atom_b].element.to_ascii_uppercase();
+                    if a <= b {
+                        (a, b, bond.order)
+                    } else {
+                        (b, a, bond.order)
+                    }
+                })
+                .collect();
+            bonds.sort_unstable();
+            bonds
+        };
+        self.atoms.len() == other.atoms.len()
+            && self.bonds.len() == other.bonds.len()
+            && elements(self) == elements(other)
+            && bonds(self) == bonds(other)
+    }
+
+    /// Hash of the positions in atom order, rounded to `COORDINATE_TOLERANCE`.
+    /// Equal hashes mean equal positions; positions within the tolerance may
+    /// still round apart, so confirm misses with `same_positions`.
+    pub fn coordinate_hash(&self) -> u64 {
+        let rounded: Vec<[i64; 3]> = self
+            .atoms
+            .iter()
+            .map(|atom| {
+                atom.position
+                    .coords
+                    .map(|c| (c / COORDINATE_TOLERANCE).round() as i64)
+            })
+            .map(|p| [p.x, p.y, p.z])
+            .collect();
+        hash_of(rounded)
+    }
+
+    /// Whether both molecules have their atoms, in the same order, within
+    /// `COORDINATE_TOLERANCE` of each other.
+    pub fn same_positions(&self, other: &Molecule) -> bool {
+        self.atoms.len() == other.atoms.len()
+            && self
+                .atoms
+                .iter()
+                .zip(&other.atoms)
+                .all(|(a, b)| (a.position - b.position).abs().max() <= COORDINATE_TOLERANCE)
+    }
+}
@@ -0,0 +1,225 @@
+//! Transform gizmo: translation arrows and rotation rings around a point, with
+//! the drag math that turns mouse rays into rigid-body motions.
+//!
+//! `TransformGizmoRender` draws a gizmo; `CameraController::begin_gizmo_drag`
+//! starts a drag, which then reports `ViewerEvent::GizmoDragged` motions for the
+//! host to apply (e.g. with `MoleculeViewer::transform_atoms`).
+
+use crate::picking;
+use nalgebra::{Isometry3, Point3, Translation3, Unit, UnitQuaternion, Vector3};
+
+/// Segments per rotation ring, for drawing and picking.
+pub const RING_SEGMENTS: usize = 32;
+
+/// Ring radius as a fraction of the gizmo size.
+const RING_SCALE: f32 = 0.8;
+/// Handle pick radius as a fraction of the gizmo size.
+const PICK_SCALE: f32 = 0.08;
+/// Drags whose ray is within this many radians of the axis (translation) or
+/// the ring plane (rotation) are ignored, since the motion is ill-defined.
+const MIN_DRAG_ANGLE: f32 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoHandle {
+    /// Arrow along axis 0 (x), 1 (y) or 2 (z).
+    Translate(usize),
+    /// Ring around axis 0 (x), 1 (y) or 2 (z).
+    Rotate(usize),
+}
+
+impl GizmoHandle {
+    pub fn axis(self) -> Vector3<f32> {
+        let (Self::Translate(i) | Self::Rotate(i)) = self;
+        Vector3::ith(i.min(2), 1.0)
+    }
+}
+
+/// World-aligned gizmo centered on `center`, with arrows `size` long.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransformGizmo {
+    pub center: Point3<f32>,
+    pub size: f32,
+}
+
+impl TransformGizmo {
+    pub fn new(center: Point3<f32>, size: f32) -> Self {
+        Self { center, size }
+    }
+
+    /// Gizmo on the centroid of `positions`, or `None` if there are none.
+    pub fn at_centroid(positions: &[Point3<f32>], size: f32) -> Option<Self> {
+        if positions.is_empty() {
+            return None;
+        }
+        let sum = positions
+            .iter()
+            .fold(Vector3::zeros(), |sum, p| sum + p.coords);
+        Some(Self::new(Point3::from(sum / positions.len() as f32), size))
+    }
+
+    pub fn ring_radius(&self) -> f32 {
+        self.size * RING_SCALE
+    }
+
+    /// Segments making up `handle`: one for an arrow, `RING_SEGMENTS` for a ring.
+    pub fn segments(&self, handle: GizmoHandle) -> Vec<(Point3<f32>, Point3<f32>)> {
+        match handle {
+            GizmoHandle::Translate(_) => {
+                vec![(self.center, self.center + handle.axis() * self.size)]
+            }
+            GizmoHandle::Rotate(_) => {
+                let points = self.ring_points(handle.axis());
+                (0..RING_SEGMENTS)
+                    .map(|k| (points[k], points[(k + 1) % RING_SEGMENTS]))
+                    .collect()
+            }
+        }
+    }
+
+    /// The handle hit first by the ray, if any. Handles are picked as capsules
+    /// `PICK_SCALE * size` thick around their segments.
+    pub fn pick(&self, origin: Point3<f32>, dir: Vector3<f32>) -> Option<GizmoHandle> {
+        let radius = self.size * PICK_SCALE;
+        let mut best: Option<(f32, GizmoHandle)> = None;
+        for handle in Self::handles() {
+            for (a, b) in self.segments(handle) {
+                if let Some(hit) = picking::ray_capsule(origin, dir, a, b, radius) {
+                    if best.is_none_or(|(t, _)| hit.t < t) {
+                        best = Some((hit.t, handle));
+                    }
+                }
+            }
+        }
+        best.map(|(_, handle)| handle)
+    }
+
+    pub fn handles() -> [GizmoHandle; 6] {
+        [
+            GizmoHandle::Translate(0),
+            GizmoHandle::Translate(1),
+            GizmoHandle::Translate(2),
+            GizmoHandle::Rotate(0),
+            GizmoHandle::Rotate(1),
+            GizmoHandle::Rotate(2),
+        ]
+    }
+
+    fn ring_points(&self, axis: Vector3<f32>) -> Vec<Point3<f32>> {
+        // Two unit vectors spanning the ring plane.
+        let u = axis
+            .cross(&Vector3::ith((axis.iamax() + 1) % 3, 1.0))
+            .normalize();
+        let v = axis.cross(&u);
+        (0..RING_SEGMENTS)
+            .map(|k| {
+                let angle = k as f32 / RING_SEGMENTS as f32 * std::f32::consts::TAU;
+                self.center + (u * angle.cos() + v * angle.sin()) * self.ring_radius()
+            })
+            .collect()
+    }
+}
+
+/// An in-progress drag of one gizmo handle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GizmoDrag {
+    handle: GizmoHandle,
+    center: Point3<f32>,
+    // Grabbed offset along the axis from the center (translation), or last
+    // direction in the ring plane under the mouse (rotation).
+    last: DragAnchor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DragAnchor {
+    Along(f32),
+    Around(Vector3<f32>),
+}
+
+impl GizmoDrag {
+    /// Starts dragging `handle` of `gizmo` with the mouse ray `origin`, `dir`.
+    /// `None` if the ray is too close to parallel with the handle's motion.
+    pub fn begin(
+        gizmo: &TransformGizmo,
+        handle: GizmoHandle,
+        origin: Point3<f32>,
+        dir: Vector3<f32>,
+    ) -> Option<Self> {
+        let last = anchor(handle, gizmo.center, origin, dir)?;
+        Some(Self {
+            handle,
+            center: gizmo.center,
+            last,
+        })
+    }
+
+    pub fn handle(&self) -> GizmoHandle {
+        self.handle
+    }
+
+    /// Where the gizmo center is after the motions reported so far.
+    pub fn center(&self) -> Point3<f32> {
+        self.center
+    }
+
+    /// Motion since the last update for the mouse ray `origin`, `dir`:
+    /// a translation along the arrow's axis, or a rotation about the ring's
+    /// axis through the gizmo center. `None` if the ray gives no usable position.
+    pub fn update(&mut self, origin: Point3<f32>, dir: Vector3<f32>) -> Option<Isometry3<f32>> {
+        let next = anchor(self.handle, self.center, origin, dir)?;
+        let axis = self.handle.axis();
+        let motion = match (self.last, next) {
+            (DragAnchor::Along(s0), DragAnchor::Along(s1)) => Isometry3::from_parts(
+                Translation3::from(axis * (s1 - s0)),
+                UnitQuaternion::identity(),
+            ),
+            (DragAnchor::Around(v0), DragAnchor::Around(v1)) => {
+                let angle = v0.cross(&v1).dot(&axis).atan2(v0.dot(&v1));
+                let rotation = UnitQuaternion::from_axis_angle(&Unit::new_unchecked(axis), angle);
+                let pivot = Translation3::from(self.center.coords);
+                pivot * Isometry3::from_parts(Translation3::identity(), rotation) * pivot.inverse()
+            }
+            _ => return None,
+        };
+        self.center = motion * self.center;
+        // A translation carries the grabbed point along, so it stays at the same
+        // offset from the center; a rotation leaves the center in place.
+        if let DragAnchor::Around(_) = next {
+            self.last = next;
+        }
+        Some(motion)
+    }
+}
+
+/// Drag position for `handle` around `center`: the parameter of the point on
+/// the axis line closest to the ray, or the unit direction from `center` to
+/// where the ray meets the ring plane.
+fn anchor(
+    handle: GizmoHandle,
+    center: Point3<f32>,
+    origin: Point3<f32>,
+    dir: Vector3<f32>,
+) -> Option<DragAnchor> {
+    let dir = dir.try_normalize(1e-9)?;
+    let axis = handle.axis();
+    let cos = dir.dot(&axis);
+    match handle {
+        GizmoHandle::Translate(_) => {
+            let sin2 = 1.0 - cos * cos;
+            if sin2 < MIN_DRAG_ANGLE.sin().powi(2) {
+                return None;
+            }
+            // Closest points between the axis line and the ray line.
+            let w = center - origin;
+            let s = (cos * w.dot(&dir) - w.dot(&axis)) / sin2;
+            Some(DragAnchor::Along(s))
+        }
+        GizmoHandle::Rotate(_) => {
+            if cos.abs() < MIN_DRAG_ANGLE.sin() {
+                return None;
+            }
+            let t = (center - origin).dot(&axis) / cos;
+            let v = origin + dir * t - center;
+            v.try_normalize(1e-6).map(DragAnchor::Around)
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Crystal lattices and fractional coordinates.
+//!
+//! A molecule from a periodic structure can carry its unit cell in
+//! `Molecule::lattice`. Fractional coordinates are positions expressed in
+//! multiples of the cell vectors, so (0.5, 0.5, 0.5) is the cell center
+//! whatever its shape. They are measured from the lattice's `origin`, the
+//! corner of the cell, which is the Cartesian origin unless set with
+//! `Lattice::with_origin`.
+
+use crate::molecule::Molecule;
+use nalgebra::{Matrix3, Point3, UnitQuaternion, Vector3};
+
+/// The three cell vectors of a crystal lattice, in the molecule's
+/// `metadata.unit`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lattice {
+    /// Cell vectors a, b, c as columns.
+    vectors: Matrix3<f32>,
+    /// Inverse of `vectors`, mapping Cartesian to fractional coordinates.
+    inverse: Matrix3<f32>,
+    /// Corner of the cell the vectors start from.
+    origin: Point3<f32>,
+}
+
+impl Lattice {
+    /// The lattice spanned by `a`, `b` and `c`, or `None` if they are
+    /// (nearly) coplanar.
+    pub fn new(a: Vector3<f32>, b: Vector3<f32>, c: Vector3<f32>) -> Option<Self> {
+        let vectors = Matrix3::from_columns(&[a, b, c]);
+        let volume = vectors.determinant().abs();
+        if !volume.is_finite() || volume <= 1e-6 * a.norm() * b.norm() * c.norm() {
+            return None;
+        }
+        Some(Self {
+            vectors,
+            inverse: vectors.try_inverse()?,
+            origin: Point3::origin(),
+        })
+    }
+
+    /// Cube with edges of `edge` along x, y and z.
+    pub fn cubic(edge: f32) -> Option<Self> {
+        Self::new(
+            Vector3::x() * edge,
+            Vector3::y() * edge,
+            Vector3::z() * edge,
+        )
+    }
+
+    /// Cell vectors a, b, c as columns.
+    pub fn vectors(&self) -> &Matrix3<f32> {
+        &self.vectors
+    }
+
+    /// Corner of the cell the vectors start from.
+    pub fn origin(&self) -> Point3<f32> {
+        self.origin
+    }
+
+    /// The same cell with its corner at `origin`.
+    pub fn with_origin(&self, origin: Point3<f32>) -> Self {
+        Self { origin, ..*self }
+    }
+
+    /// Volume of the cell.
+    pub fn volume(&self) -> f32 {
+        self.vectors.determinant().abs()
+    }
+
+    pub fn to_fractional(&self, position: Point3<f32>) -> Vector3<f32> {
+        self.inverse * (position - self.origin)
+    }
+
+    pub fn to_cartesian(&self, fractional: Vector3<f32>) -> Point3<f32> {
+        self.origin + self.vectors * fractional
+    }
+
+    /// `position` moved by whole cell vectors into the cell, fractional
+    /// coordinates in [0, 1).
+    pub fn wrap(&self, position: Point3<f32>) -> Point3<f32> {
+        let fractional = self.to_fractional(position);
+        self.to_cartesian(fractional.map(|f| f - f.floor()))
+    }
+
+    /// The twelve edges of the cell as start and end points, for drawing it.
+    pub fn edges(&self) -> [(Point3<f32>, Point3<f32>); 12] {
+        let corner = |a: f32, b: f32, c: f32| self.to_cartesian(Vector3::new(a, b, c));
+        // Four edges along each axis, one from each corner of the face the
+        // axis leaves.
+        std::array::from_fn(|i| {
+            let (s, t) = ((i & 1) as f32, ((i >> 1) & 1) as f32);
+            match i / 4 {
+                0 => (corner(0.0, s, t), corner(1.0, s, t)),
+                1 => (corner(s, 0.0, t), corner(s, 1.0, t)),
+                _ => (corner(s, t, 0.0), corner(s, t, 1.0)),
+            }
+        })
+    }
+
+    /// The lattice with every vector and its origin multiplied by `factor`,
+    /// for unit changes.
+    pub fn scaled(&self, factor: f32) -> Self {
+        Self {
+            vectors: self.vectors * factor,
+            inverse: self.inverse / factor,
+            origin: self.origin * factor,
+        }
+    }
+
+    /// The lattice with every vector turned by `rotation`, and its origin
+    /// turned about `pivot`.
+    pub fn rotated(&self, rotation: &UnitQuaternion<f32>, pivot: Point3<f32>) -> Self {
+        let r = rotation.to_rotation_matrix().into_inner();
+        Self {
+            vectors: r * self.vectors,
+            inverse: self.inverse * r.transpose(),
+            origin: pivot + rotation * (self.origin - pivot),
+        }
+    }
+}
+
+impl Molecule {
+    /// Position of atom `i` in fractional coordinates, `None` without a
+    /// lattice or for an out-of-range atom.
+    pub fn fractional_position(&self, i: usize) -> Option<Vector3<f32>> {
+        Some(self.lattice?.to_fractional(self.atoms.get(i)?.position))
+    }
+
+    /// Moves atom `i` to `fractional` coordinates. Returns `false`, changing
+    /// nothing, without a lattice or for an out-of-range atom.
+    pub fn set_fractional_position(&mut self, i: usize, fractional: Vector3<f32>) -> bool {
+        let (Some(lattice), Some(atom)) = (self.lattice, self.atoms.get_mut(i)) else {
+            return false;
+        };
+        atom.position = lattice.to_cartesian(fractional);
+        true
+    }
+
+    /// Rounds the fractional coordinates of atom `i` to the nearest multiple
+    /// of 1/`denominator`, e.g. 4 to idealize positions like (1/4, 1/2, 0).
+    /// Returns `false`, changing nothing, without a lattice, for an
+    /// out-of-range atom or a zero denominator.
+    pub fn snap_to_fraction(&mut self, i: usize, denominator: u32) -> bool {
+        let Some(fractional) = self.fractional_position(i).filter(|_| denominator > 0) else {
+            return false;
+        };
+        let n = denominator as f32;
+        self.set_fractional_position(i, fractional.map(|f| (f * n).round() / n))
+    }
+}
@@ -1,18 +1,21 @@
 //! A lightweight 3D molecule visualization library.
 //!
 //! This crate provides a `MoleculeViewer` struct that relies on the `graphics` crate (WGPU-based)
-//! to render molecules loaded via `bio_files`.
+//! to render molecules loaded by its own structure file parsers.
 //!
 //! # Example
 //!
 //! ```no_run
-//! use graphics::{run, Scene, UiSettings, GraphicsSettings, EngineUpdates, EntityUpdate, ControlScheme};
-//! use lin_alg::f32::Vec3;
-//! use moleucle_3dview_rs::{Molecule, MoleculeViewer, DebugRender, AdditionalRender};
-//! use std::path::Path;
-//!
+//! # #[cfg(not(feature = "render"))]
+//! # fn main() {}
+//! # #[cfg(feature = "render")]
 //! fn main() {
-//!     let mut viewer = MoleculeViewer::new();
+//!     use graphics::Scene;
+//!     use lin_alg::f32::Vec3;
+//!     use moleucle_3dview_rs::{DebugRender, Molecule, MoleculeViewer};
+//!     use std::path::Path;
+//!
+//!     let mut viewer = MoleculeViewer::<DebugRender>::new();
 //!     // viewer.set_molecule(Molecule::from_mol2(Path::new("Benzene.mol2")).unwrap());
 //!
 //!     let mut scene = Scene::default();
@@ -23,15 +26,112 @@
 //!     // ... standard graphics::run loop setup
 //! }
 //! ```
+//!
+//! # Features
+//!
+//! - `render` (default): `MoleculeViewer`, `CameraController`, the
+//!   `AdditionalRender` overlays, snapshots and the `graphics`/`lin_alg`/egui
+//!   glue. Pulls in WGPU and winit.
+//! - `bevy`: `render` plus `bevy_adapter`, which spawns a `DisplayList` as
+//!   Bevy entities and forwards clicks and cursor moves to
+//!   `MoleculeViewer::pick`.
+//! - `export`: the files `MoleculeViewer::set_capture_hook` captures;
+//!   without it the hook records and writes nothing.
+//!
+//! With `default-features = false` the crate is the nalgebra-only core:
+//! `Molecule` parsing and analysis, camera math, picking, selection, and
+//! `DisplayList::from_molecule` for exporting what a style would draw, and
+//! `raster` for casting it into a PNG on the CPU.
+//! `cargo check-core` checks that build.
 
+#[cfg(feature = "render")]
 pub mod additional_render;
+pub mod alignment;
+pub mod atom_id;
+#[cfg(feature = "bevy")]
+pub mod bevy_adapter;
+pub mod bond_order;
+#[cfg(feature = "render")]
+pub mod builder;
 pub mod camera;
+pub mod camera_path;
+#[cfg(feature = "render")]
+pub mod capture;
+pub mod clock;
+pub mod colormap;
+#[cfg(feature = "render")]
+pub mod command;
+pub mod composition;
+pub mod contacts;
+#[cfg(feature = "render")]
 pub mod controller;
+#[cfg(feature = "render")]
+pub mod convert;
+pub mod coordination;
+pub mod dipole;
+pub mod display_list;
+pub mod element;
+pub mod fingerprint;
+pub mod gizmo;
+pub mod lattice;
+pub mod measurement;
+#[cfg(feature = "render")]
+pub mod mesh;
 pub mod molecule;
+pub mod picking;
+pub mod raster;
+pub mod rings;
+pub mod selection;
+#[cfg(feature = "render")]
+pub mod snapshot;
+pub mod spatial;
+pub mod style;
+pub mod trajectory;
+pub mod units;
+#[cfg(feature = "render")]
 pub mod viewer;
 
-pub use additional_render::{AdditionalRender, SelectedAtomRender, DebugRender};
-pub use camera::{Camera, OrbitalCamera, ProjectionType};
-pub use controller::CameraController;
-pub use molecule::Molecule;
-pub use viewer::MoleculeViewer;
+#[cfg(feature = "render")]
+pub use additional_render::{
+    AdditionalRender, AngleMeasurementRender, ContactRender, CoordinationRender, DebugRender,
+    DipoleRender, GroundDiscRender, IndexGlyphRender, OrbitTrailRender, PivotRender,
+    SelectedAtomRender, TrailRender, TransformGizmoRender,
+};
+pub use atom_id::AtomId;
+#[cfg(feature = "render")]
+pub use builder::ViewerBuilder;
+pub use camera::{
+    Camera, OrbitalCamera, ProjectionType, ResizePolicy, UpAxis, ViewBookmark, ViewPreset,
+};
+pub use camera_path::CameraPath;
+#[cfg(feature = "render")]
+pub use capture::{CaptureHook, EventMask};
+pub use clock::{Clock, ManualClock, MonotonicClock};
+pub use colormap::{ColorScheme, TintMode};
+#[cfg(feature = "render")]
+pub use command::{EditAction, ViewerCommand, ViewerHandle};
+pub use composition::{CompositionEntry, CompositionOptions};
+#[cfg(feature = "render")]
+pub use controller::{
+    CameraController, ControllerSettings, DragMode, IdleSpin, OrbitTrail, PivotIndicator,
+    PivotVisibility, SelectionRect,
+};
+pub use coordination::{CoordinationGeometry, CoordinationInfo};
+pub use dipole::{Dipole, DipoleGroups};
+pub use display_list::DisplayList;
+pub use gizmo::{GizmoHandle, TransformGizmo};
+pub use lattice::Lattice;
+pub use measurement::{Measurement, MeasurementMode, MeasurementTool};
+pub use molecule::{Format, Molecule, MoleculeError, MoleculeMetadata, ParseOptions};
+pub use selection::Selection;
+pub use style::{
+    AtomRadiiTable, BondColoring, Cutaway, ElementScale, MoleculeDisplayState, PinnedMotion,
+    RenderStyle, Shading, Slab, ViewerSettings, VisibilityMode,
+};
+pub use trajectory::Trajectory;
+pub use units::LengthUnit;
+#[cfg(feature = "render")]
+pub use viewer::{
+    AddOptions, AddResult, DirtyFlags, LoadOptions, MeshHandles, MoleculeViewer, PickResult,
+    PickTarget, ReferenceAxis,
+};
@@ -0,0 +1,244 @@
+//! Distances, angles and dihedrals between picked atoms.
+//!
+//! `MeasurementTool` collects atom picks in groups of as many atoms as its
+//! `MeasurementMode` needs: two for a distance, three for an angle, four for a
+//! dihedral. Earlier picks are held as pending, the last one completes a
+//! `Measurement`, and the next click starts another group. As an overlay it
+//! draws distances as thin dashed lines and angles and dihedrals as arcs,
+//! each labeled with its value; `MoleculeViewer::handle_pick` feeds it atom
+//! clicks and `MoleculeViewer::measure_selection` measures selected atoms.
+
+use crate::molecule::Molecule;
+
+/// What `MeasurementTool` measures, and so how many atoms it picks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MeasurementMode {
+    #[default]
+    Distance,
+    Angle,
+    Dihedral,
+}
+
+impl MeasurementMode {
+    /// Atoms picked per measurement.
+    pub fn atom_count(self) -> usize {
+        match self {
+            Self::Distance => 2,
+            Self::Angle => 3,
+            Self::Dihedral => 4,
+        }
+    }
+}
+
+/// A measurement between atoms of the active molecule, as of the last pick,
+/// trajectory frame or `MoleculeViewer` scene update.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Measurement {
+    /// In the molecule's `metadata.unit`.
+    Distance { atoms: [usize; 2], distance: f32 },
+    /// At the middle atom, in degrees.
+    Angle { atoms: [usize; 3], degrees: f32 },
+    /// About the bond between the middle two atoms, in degrees; see
+    /// `Molecule::dihedral`.
+    Dihedral { atoms: [usize; 4], degrees: f32 },
+}
+
+impl Measurement {
+    /// Measures `atoms` of `molecule`: a distance, angle or dihedral by how
+    /// many there are. `None` for other counts and where the molecule gives
+    /// none (see `Molecule::angle`, `Molecule::dihedral`).
+    pub fn measure(molecule: &Molecule, atoms: &[usize]) -> Option<Self> {
+        match *atoms {
+            [a, b] => Some(Self::Distance {
+                atoms: [a, b],
+                distance: molecule.distance(a, b, None)?,
+            }),
+            [a, b, c] => Some(Self::Angle {
+                atoms: [a, b, c],
+                degrees: molecule.angle(b, a, c)?,
+            }),
+            [a, b, c, d] => Some(Self::Dihedral {
+                atoms: [a, b, c, d],
+                degrees: molecule.dihedral(a, b, c, d)?,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn mode(&self) -> MeasurementMode {
+        match self {
+            Self::Distance { .. } => MeasurementMode::Distance,
+            Self::Angle { .. } => MeasurementMode::Angle,
+            Self::Dihedral { .. } => MeasurementMode::Dihedral,
+        }
+    }
+
+    /// The measured atoms, in pick order.
+    pub fn atoms(&self) -> &[usize] {
+        match self {
+            Self::Distance { atoms, .. } => atoms,
+            Self::Angle { atoms, .. } => atoms,
+            Self::Dihedral { atoms, .. } => atoms,
+        }
+    }
+
+    /// The distance or angle.
+    pub fn value(&self) -> f32 {
+        match *self {
+            Self::Distance { distance, .. } => distance,
+            Self::Angle { degrees, .. } | Self::Dihedral { degrees, .. } => degrees,
+        }
+    }
+
+    /// The value as drawn next to the measurement: distances to two decimals,
+    /// angles to one.
+    pub fn label(&self) -> String {
+        match self {
+            Self::Distance { distance, .. } => format!("{distance:.2}"),
+            Self::Angle { degrees, .. } | Self::Dihedral { degrees, .. } => {
+                format!("{degrees:.1}")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MeasurementTool {
+    mode: MeasurementMode,
+    pending: Vec<usize>,
+    measurements: Vec<Measurement>,
+    pub radius: f32,
+    pub color: (f32, f32, f32),
+    pub dash_length: f32,
+    pub gap_length: f32,
+    /// Radius of angle and dihedral arcs, in Å. Angle arcs shrink to fit
+    /// short bonds.
+    pub arc_radius: f32,
+    /// Also draw an angle's arms, from the middle atom to the other two, as
+    /// dashed lines.
+    pub angle_arms: bool,
+    /// Also draw a dihedral's two planes, a–b–c and b–c–d, as translucent
+    /// rectangles hinged on the b–c bond, and mark its four atoms.
+    pub dihedral_planes: bool,
+    /// Opacity of the dihedral planes and atom marks.
+    pub plane_opacity: f32,
+    /// Height of the value labels, in Å; 0 draws none.
+    pub label_size: f32,
+}
+
+impl MeasurementTool {
+    pub fn new() -> Self {
+        Self {
+            mode: MeasurementMode::Distance,
+            pending: Vec::new(),
+            measurements: Vec::new(),
+            radius: 0.03,
+            color: (1.0, 0.9, 0.3),
+            dash_length: 0.2,
+            gap_length: 0.12,
+            arc_radius: 0.5,
+            angle_arms: true,
+            dihedral_planes: true,
+            plane_opacity: 0.25,
+            label_size: 0.3,
+        }
+    }
+
+    pub fn mode(&self) -> MeasurementMode {
+        self.mode
+    }
+
+    /// Measures `mode` from the next pick on. Drops the pending atoms if the
+    /// mode changes; completed measurements stay.
+    pub fn set_mode(&mut self, mode: MeasurementMode) {
+        if mode != self.mode {
+            self.mode = mode;
+            self.pending.clear();
+        }
+    }
+
+    /// Completed measurements, oldest first.
+    pub fn measurements(&self) -> &[Measurement] {
+        &self.measurements
+    }
+
+    /// The atoms picked so far for the next measurement, in pick order.
+    pub fn pending(&self) -> &[usize] {
+        &self.pending
+    }
+
+    /// Takes a pick of `atom`: holds it as pending, or, if it is the last
+    /// atom the mode needs, measures the pending atoms to it and returns the
+    /// new measurement. Picking a pending atom again, or an atom `molecule`
+    /// does not have, does nothing. Atoms that cannot be measured (e.g. a
+    /// dihedral along a straight chain) are dropped.
+    pub fn pick(&mut self, molecule: &Molecule, atom: usize) -> Option<Measurement> {
+        if atom >= molecule.atoms.len() || self.pending.contains(&atom) {
+            return None;
+        }
+        self.pending.push(atom);
+        if self.pending.len() < self.mode.atom_count() {
+            return None;
+        }
+        let atoms = std::mem::take(&mut self.pending);
+        self.measure(molecule, &atoms)
+    }
+
+    /// Measures `atoms` directly, without picks, and keeps the measurement
+    /// alongside the picked ones. `None`, adding nothing, where
+    /// `Measurement::measure` gives none.
+    pub fn measure(&mut self, molecule: &Molecule, atoms: &[usize]) -> Option<Measurement> {
+        let measurement = Measurement::measure(molecule, atoms)?;
+        self.measurements.push(measurement);
+        Some(measurement)
+    }
+
+    /// Forgets every measurement and the pending atoms. Returns whether there
+    /// was anything to forget.
+    pub fn clear(&mut self) -> bool {
+        let had_any = !self.pending.is_empty() || !self.measurements.is_empty();
+        self.pending.clear();
+        self.measurements.clear();
+        had_any
+    }
+
+    /// Renumbers the measured and pending atoms with `new_index`, e.g. after
+    /// atoms were deleted, dropping measurements of atoms it maps to `None`
+    /// and, if any pending atom goes, the pending atoms. Values are kept;
+    /// see `update_values`.
+    pub fn remap_atoms(&mut self, new_index: impl Fn(usize) -> Option<usize>) {
+        self.measurements.retain_mut(|measurement| {
+            let atoms: Option<Vec<usize>> =
+                measurement.atoms().iter().map(|&i| new_index(i)).collect();
+            let Some(atoms) = atoms else {
+                return false;
+            };
+            match measurement {
+                Measurement::Distance { atoms: old, .. } => old.copy_from_slice(&atoms),
+                Measurement::Angle { atoms: old, .. } => old.copy_from_slice(&atoms),
+                Measurement::Dihedral { atoms: old, .. } => old.copy_from_slice(&atoms),
+            }
+            true
+        });
+        match self.pending.iter().map(|&i| new_index(i)).collect() {
+            Some(pending) => self.pending = pending,
+            None => self.pending.clear(),
+        }
+    }
+
+    /// Recomputes the measurements from `molecule`'s positions, e.g. after
+    /// its atoms moved. Measurements it cannot give keep their value.
+    pub fn update_values(&mut self, molecule: &Molecule) {
+        for measurement in &mut self.measurements {
+            if let Some(updated) = Measurement::measure(molecule, measurement.atoms()) {
+                *measurement = updated;
+            }
+        }
+    }
+}
+
+impl Default for MeasurementTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
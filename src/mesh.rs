@@ -0,0 +1,101 @@
+//! Mesh post-processing helpers used to build shading variants, and the
+//! simple shapes the stock meshes lack.
+
+use crate::convert::{vec_to_la, vec_to_na};
+use graphics::{Mesh, Vertex};
+use nalgebra::{UnitQuaternion, Vector3};
+
+/// Rotates then translates every vertex (and rotates normals) in place.
+pub fn transformed(mut mesh: Mesh, rotation: UnitQuaternion<f32>, offset: Vector3<f32>) -> Mesh {
+    for v in mesh.vertices.iter_mut() {
+        let p = rotation * Vector3::from(v.position) + offset;
+        v.position = p.into();
+        v.normal = vec_to_la(rotation * vec_to_na(v.normal));
+    }
+    mesh
+}
+
+/// Appends `other`'s triangles to `target`.
+pub fn append(target: &mut Mesh, other: Mesh) {
+    let base = target.vertices.len();
+    target.vertices.extend(other.vertices);
+    target
+        .indices
+        .extend(other.indices.into_iter().map(|i| i + base));
+}
+
+/// Re-emits every triangle with its own three vertices carrying the face normal,
+/// so lighting is constant across each face.
+pub fn flat_shaded(mut mesh: Mesh) -> Mesh {
+    let mut vertices = Vec::with_capacity(mesh.indices.len());
+    for tri in mesh.indices.chunks_exact(3) {
+        let p: Vec<Vector3<f32>> = tri
+            .iter()
+            .map(|&i| Vector3::from(mesh.vertices[i].position))
+            .collect();
+        let mut normal = (p[1] - p[0]).cross(&(p[2] - p[0]));
+        // Keep the face normal on the same side as the original smooth normals,
+        // whatever the winding convention of the source mesh.
+        let smooth: Vector3<f32> = tri
+            .iter()
+            .map(|&i| vec_to_na(mesh.vertices[i].normal))
+            .sum();
+        if normal.dot(&smooth) < 0.0 {
+            normal = -normal;
+        }
+        let normal = vec_to_la(normal.try_normalize(1e-12).unwrap_or(smooth));
+        for corner in &p {
+            vertices.push(Vertex::new([corner.x, corner.y, corner.z], normal));
+        }
+    }
+    mesh.indices = (0..vertices.len()).collect();
+    mesh.vertices = vertices;
+    mesh
+}
+
+/// Flat-shades the mesh and snaps each face normal so its angle to `light_dir`
+/// falls on one of `bands` discrete values, giving cel-style banded lighting
+/// from that direction without shader changes.
+pub fn toon_shaded(mesh: Mesh, bands: u32, light_dir: Vector3<f32>) -> Mesh {
+    let mut mesh = flat_shaded(mesh);
+    let light = light_dir.normalize();
+    let bands = bands.max(1) as f32;
+    let band_width = std::f32::consts::PI / bands;
+    for v in mesh.vertices.iter_mut() {
+        let n = vec_to_na(v.normal);
+        let angle = n.dot(&light).clamp(-1.0, 1.0).acos();
+        let band = (angle / band_width).floor().min(bands - 1.0);
+        let snapped = (band + 0.5) * band_width;
+        let tangent = (n - light * n.dot(&light))
+            .try_normalize(1e-6)
+            .unwrap_or_else(|| any_perpendicular(&light));
+        v.normal = vec_to_la(light * snapped.cos() + tangent * snapped.sin());
+    }
+    mesh
+}
+
+/// Unit square in the XY plane, centered on the origin. Each face is in it
+/// twice, facing +Z and -Z, so it shows from both sides.
+pub fn square() -> Mesh {
+    // A stock mesh for the fields other than the triangles.
+    let mut mesh = Mesh::new_cylinder(1.0, 1.0, 4);
+    let corners = [[-0.5, -0.5], [0.5, -0.5], [0.5, 0.5], [-0.5, 0.5]];
+    mesh.vertices = [1.0f32, -1.0]
+        .iter()
+        .flat_map(|&side| {
+            let normal = vec_to_la(Vector3::z() * side);
+            corners.map(|[x, y]| Vertex::new([x, y, 0.0], normal))
+        })
+        .collect();
+    mesh.indices = vec![0, 1, 2, 0, 2, 3, 4, 6, 5, 4, 7, 6];
+    mesh
+}
+
+fn any_perpendicular(v: &Vector3<f32>) -> Vector3<f32> {
+    let helper = if v.x.abs() < 0.9 {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    };
+    v.cross(&helper).normalize()
+}
@@ -1,6 +1,39 @@
-use nalgebra::Point3;
+//! Molecules, their structure file parsers, and a mol2 writer.
+//!
+//! Every format has the same three entry points, so content that is not a
+//! file (a download, an embedded asset, a test fixture) parses the same way:
+//! - `from_<fmt>(path)` reads the file and hands it to
+//! - `from_<fmt>_str(content)`, which parses with default `ParseOptions`;
+//! - `from_<fmt>_str_with(content, options)` takes them explicitly.
+//!
+//! `Molecule::from_file` and `Molecule::from_reader` pick the parser by
+//! extension or `Format`. New formats should follow the same pattern and
+//! report problems as `MoleculeError`.
+//!
+//! `Molecule::write_mol2` and `Molecule::to_mol2_string` write a molecule
+//! back out as mol2.
+
+use crate::atom_id::AtomIds;
+use crate::bond_order::BOND_TOLERANCE;
+use crate::element;
+use crate::lattice::Lattice;
+use crate::trajectory::Trajectory;
+use crate::units::LengthUnit;
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::io::Read;
 use std::path::Path;
 
+/// File extensions `Molecule::from_file` recognizes.
+pub const SUPPORTED_EXTENSIONS: &[&str] = &["mol2", "xyz", "gro", "pdb", "sdf", "mol"];
+
+/// Smallest side of the `Molecule::frame_bounds` box, in Å.
+pub const MIN_FRAME_EXTENT: f32 = 5.0;
+
+/// Mass `Molecule::center_of_mass` gives atoms of unknown elements, in u.
+pub const UNKNOWN_ATOM_MASS: f32 = 12.0;
+
 #[derive(Debug, Clone)]
 pub struct Atom {
     pub position: Point3<f32>,
@@ -12,25 +45,278 @@ pub struct Atom {
 pub struct Bond {
     pub atom_a: usize,
     pub atom_b: usize,
+    /// 1 to 3, or 0 for an aromatic bond (mol2 "ar", SDF type 4). Other
+    /// bond types are read as 1.
     pub order: u8,
 }
 
+/// Where a molecule's coordinates came from and what unit they are in now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MoleculeMetadata {
+    /// Unit of the file the molecule was loaded from.
+    pub source_unit: LengthUnit,
+    /// Unit of `Atom::position`. Loaders always produce Å; only
+    /// `Molecule::convert_units` changes it. The viewer assumes Å.
+    pub unit: LengthUnit,
+}
+
+/// Why a structure could not be loaded. Line numbers are 1-based, counted
+/// from the start of the file.
+#[derive(Debug)]
+pub enum MoleculeError {
+    /// The file could not be read, or is not UTF-8.
+    Io(std::io::Error),
+    /// A file type or format version the crate does not parse.
+    UnsupportedFormat(String),
+    /// A part the format requires is missing or cut short, such as the atom
+    /// count of an XYZ frame or some of its atoms.
+    MissingSection {
+        format: &'static str,
+        section: String,
+    },
+    InvalidAtomRecord {
+        format: &'static str,
+        line: usize,
+        reason: String,
+    },
+    InvalidBondRecord {
+        format: &'static str,
+        line: usize,
+        reason: String,
+    },
+    /// A bond on `line` names atom `index`, outside the `len` atoms read.
+    IndexOutOfRange {
+        format: &'static str,
+        line: usize,
+        index: usize,
+        len: usize,
+    },
+    /// Frame `frame` of a trajectory does not match frame 0.
+    FrameMismatch { frame: usize, reason: String },
+}
+
+impl fmt::Display for MoleculeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::UnsupportedFormat(what) => write!(f, "unsupported format: {}", what),
+            Self::MissingSection { format, section } => {
+                write!(f, "{}: missing {}", format, section)
+            }
+            Self::InvalidAtomRecord {
+                format,
+                line,
+                reason,
+            }
+            | Self::InvalidBondRecord {
+                format,
+                line,
+                reason,
+            } => write!(f, "{} line {}: {}", format, line, reason),
+            Self::IndexOutOfRange {
+                format,
+                line,
+                index,
+                len,
+            } => write!(
+                f,
+                "{} line {}: atom {} out of range 1-{}",
+                format, line, index, len
+            ),
+            Self::FrameMismatch { frame, reason } => {
+                write!(f, "xyz frame {}: {}", frame, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MoleculeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for MoleculeError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// For callers that report errors as strings, as the rest of the crate does.
+impl From<MoleculeError> for String {
+    fn from(e: MoleculeError) -> Self {
+        e.to_string()
+    }
+}
+
+/// A structure file format `Molecule` can parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    Mol2,
+    Xyz,
+    Gro,
+    Pdb,
+    /// SDF or a single MDL molfile.
+    Sdf,
+}
+
+impl Format {
+    /// The format of files with `extension`, one of `SUPPORTED_EXTENSIONS`
+    /// in any case.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "mol2" => Some(Self::Mol2),
+            "xyz" => Some(Self::Xyz),
+            "gro" => Some(Self::Gro),
+            "pdb" => Some(Self::Pdb),
+            "sdf" | "mol" => Some(Self::Sdf),
+            _ => None,
+        }
+    }
+}
+
+/// Options for the `_with` parsers, e.g. `Molecule::from_file_with`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Read number fields written with a decimal comma ("1,5" for 1.5), as
+    /// some software does in locales that use one, and note it in
+    /// `warnings`. When off, such fields are malformed.
+    pub decimal_comma: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            decimal_comma: true,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct Molecule {
     pub atoms: Vec<Atom>,
     pub bonds: Vec<Bond>,
+    pub metadata: MoleculeMetadata,
+    /// Name of the molecule (mol2 molecule name, SDF header line), if the
+    /// file gave one.
+    pub name: Option<String>,
+    /// Partial charge of each atom in e, indexed like `atoms`, if the file
+    /// had them.
+    pub charges: Option<Vec<f32>>,
+    /// Residue (mol2 substructure, PDB residue sequence) number of each atom,
+    /// indexed like `atoms`, if known.
+    pub residues: Option<Vec<usize>>,
+    /// PDB residue name of each atom ("ALA", "HOH"), indexed like `atoms`,
+    /// if known.
+    pub residue_names: Option<Vec<String>>,
+    /// PDB chain identifier of each atom, indexed like `atoms`, if the file
+    /// named any chains.
+    pub chains: Option<Vec<char>>,
+    /// Formal charge of each atom in e, indexed like `atoms`, if the file had
+    /// them (SDF does).
+    pub formal_charges: Option<Vec<i8>>,
+    /// Problems the loader worked around, such as atoms whose element could
+    /// not be determined.
+    pub warnings: Vec<String>,
+    /// Stable ids of the atoms; see `assign_atom_ids`. Empty for molecules
+    /// built by hand until ids are assigned.
+    pub atom_ids: AtomIds,
+    /// Unit cell of a periodic structure, in `metadata.unit`, if the file
+    /// had one; see `fractional_position`.
+    pub lattice: Option<Lattice>,
+    /// Atoms that edits moving several atoms leave in place, by index; see
+    /// `MoleculeViewer::pin_atoms`.
+    pub pinned: BTreeSet<usize>,
 }
 
 impl Molecule {
-    pub fn from_mol2(path: &Path) -> Result<Self, String> {
-        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
-        let mut atoms = Vec::new();
-        let mut bonds = Vec::new();
+    /// Loads `path` with the parser for its extension, one of
+    /// `SUPPORTED_EXTENSIONS` in any case. Only the first record of an SDF
+    /// file is read; see `from_sdf_all` for all of them.
+    pub fn from_file(path: &Path) -> Result<Self, MoleculeError> {
+        Self::from_file_with(path, ParseOptions::default())
+    }
+
+    pub fn from_file_with(path: &Path, options: ParseOptions) -> Result<Self, MoleculeError> {
+        let format = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(Format::from_extension)
+            .ok_or_else(|| MoleculeError::UnsupportedFormat(path.display().to_string()))?;
+        let content = std::fs::read_to_string(path)?;
+        Self::from_str_as(&content, format, options)
+    }
+
+    /// Reads all of `reader` and parses it as `format`, for structures that
+    /// are not files: embedded assets, downloads, or targets without a
+    /// filesystem. The content must be UTF-8.
+    pub fn from_reader(reader: impl Read, format: Format) -> Result<Self, MoleculeError> {
+        Self::from_reader_with(reader, format, ParseOptions::default())
+    }
+
+    pub fn from_reader_with(
+        mut reader: impl Read,
+        format: Format,
+        options: ParseOptions,
+    ) -> Result<Self, MoleculeError> {
+        let mut content = String::new();
+        reader.read_to_string(&mut content)?;
+        Self::from_str_as(&content, format, options)
+    }
+
+    /// Parses `content` with the `_str_with` parser for `format`.
+    pub fn from_str_as(
+        content: &str,
+        format: Format,
+        options: ParseOptions,
+    ) -> Result<Self, MoleculeError> {
+        match format {
+            Format::Mol2 => Self::from_mol2_str_with(content, options),
+            Format::Xyz => Self::from_xyz_str_with(content, options),
+            Format::Gro => Self::from_gro_str_with(content, options),
+            Format::Pdb => Self::from_pdb_str_with(content, options),
+            Format::Sdf => Self::from_sdf_str_with(content, options),
+        }
+    }
+
+    pub fn from_mol2(path: &Path) -> Result<Self, MoleculeError> {
+        let content = std::fs::read_to_string(path)?;
+        Self::from_mol2_str(&content)
+    }
+
+    /// Parses the ATOM and BOND sections of a Tripos mol2 file. Elements come
+    /// from the SYBYL atom type ("C.ar" -> "C"), or from the atom name when the
+    /// type is missing or not an element (see `mol2_element`), uppercased.
+    /// Atoms with neither get `element::UNKNOWN` and a warning. Malformed
+    /// records are skipped. Charges and residues are kept if every atom has
+    /// them and the charge type is not NO_CHARGES.
+    pub fn from_mol2_str(content: &str) -> Result<Self, MoleculeError> {
+        Self::from_mol2_str_with(content, ParseOptions::default())
+    }
+
+    pub fn from_mol2_str_with(content: &str, options: ParseOptions) -> Result<Self, MoleculeError> {
+        // Reserve from the MOLECULE counts record, if the file has one, but no
+        // more than the file could hold (each record is well over 8 bytes).
+        let (n_atoms, n_bonds) = mol2_counts(content).unwrap_or((0, 0));
+        let (n_atoms, n_bonds) = (
+            n_atoms.min(content.len() / 8),
+            n_bonds.min(content.len() / 8),
+        );
+        let mut atoms = Vec::with_capacity(n_atoms);
+        let mut bonds = Vec::with_capacity(n_bonds);
+        let mut charges = Some(Vec::with_capacity(n_atoms))
+            .filter(|_| mol2_molecule_line(content, 4) != Some("NO_CHARGES"));
+        let mut residues = Some(Vec::with_capacity(n_atoms));
+        let mut warnings = Vec::new();
+        let mut numbers = Numbers::new(options);
 
         let mut section = "";
 
         for line in content.lines() {
-            let line = line.trim();
+            let line = line.trim_ascii();
             if line.is_empty() {
                 continue;
             }
@@ -43,48 +329,57 @@ impl Molecule {
             match section {
                 "@<TRIPOS>ATOM" => {
                     // id name x y z type ...
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    if parts.len() >= 6 {
-                        if let (Ok(x), Ok(y), Ok(z)) = (
-                            parts[2].parse::<f32>(),
-                            parts[3].parse::<f32>(),
-                            parts[4].parse::<f32>(),
-                        ) {
-                            // Type often "C.ar", "H", etc. Take first char or split by dot.
-                            // let element = parts[1].chars().next().map(|c| c.to_string()).unwrap_or("?".to_string()); // Unused
-                            // Better: use the type field parts[5]
-                            let type_str = parts[5];
-                            let element = type_str.split('.').next().unwrap_or("?").to_uppercase();
-
-                            atoms.push(Atom {
-                                position: Point3::new(x, y, z),
-                                element,
-                                id: atoms.len() + 1, // 1-based usually in file, but we use index
-                            });
-                        }
+                    if let Some(Mol2Atom {
+                        position,
+                        element,
+                        residue,
+                        charge,
+                    }) = parse_mol2_atom(line, &mut numbers)
+                    {
+                        let element = element.unwrap_or_else(|| {
+                            warnings.push(format!(
+                                "mol2 atom {}: no element in type or name of {line:?}",
+                                atoms.len() + 1
+                            ));
+                            element::UNKNOWN.to_string()
+                        });
+                        // One atom without a value drops the column.
+                        charges = charges.zip(charge).map(|(mut c, q)| {
+                            c.push(q);
+                            c
+                        });
+                        residues = residues.zip(residue).map(|(mut r, id)| {
+                            r.push(id);
+                            r
+                        });
+                        atoms.push(Atom {
+                            position,
+                            element,
+                            id: atoms.len() + 1, // 1-based usually in file, but we use index
+                        });
                     }
                 }
                 "@<TRIPOS>BOND" => {
                     // id atom1 atom2 type ...
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    if parts.len() >= 4 {
-                        if let (Ok(a_id), Ok(b_id)) =
-                            (parts[1].parse::<usize>(), parts[2].parse::<usize>())
-                        {
-                            let order = match parts[3] {
-                                "2" => 2,
-                                "3" => 3,
-                                "ar" => 1, // aromatic, often drawn as 1.5 or 1
-                                _ => 1,
-                            };
-                            // Adjust 1-based to 0-based
-                            if a_id > 0 && b_id > 0 && a_id <= atoms.len() && b_id <= atoms.len() {
-                                bonds.push(Bond {
-                                    atom_a: a_id - 1,
-                                    atom_b: b_id - 1,
-                                    order,
-                                });
-                            }
+                    let mut parts = line.split_ascii_whitespace().skip(1);
+                    let (Some(a), Some(b), Some(kind)) = (parts.next(), parts.next(), parts.next())
+                    else {
+                        continue;
+                    };
+                    if let (Ok(a_id), Ok(b_id)) = (a.parse::<usize>(), b.parse::<usize>()) {
+                        let order = match kind {
+                            "2" => 2,
+                            "3" => 3,
+                            "ar" => 0,
+                            _ => 1,
+                        };
+                        // Adjust 1-based to 0-based
+                        if a_id > 0 && b_id > 0 && a_id <= atoms.len() && b_id <= atoms.len() {
+                            bonds.push(Bond {
+                                atom_a: a_id - 1,
+                                atom_b: b_id - 1,
+                                order,
+                            });
                         }
                     }
                 }
@@ -92,6 +387,1220 @@ impl Molecule {
             }
         }
 
-        Ok(Molecule { atoms, bonds })
+        warnings.extend(numbers.warning("mol2"));
+        let mut mol = Molecule {
+            name: mol2_molecule_line(content, 1)
+                .filter(|name| !name.is_empty() && *name != "****")
+                .map(str::to_string),
+            charges: charges.filter(|c| !c.is_empty()),
+            residues: residues.filter(|r| !r.is_empty()),
+            atoms,
+            bonds,
+            warnings,
+            ..Default::default()
+        };
+        mol.assign_atom_ids();
+        Ok(mol)
+    }
+
+    /// Writes `to_mol2_string` to `path`.
+    pub fn write_mol2(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, self.to_mol2_string()).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// The molecule as a Tripos mol2 file, with coordinates in Å.
+    /// Atoms are named by element and a count per element ("C1", "C2", "Cl1").
+    pub fn to_mol2_string(&self) -> String {
+        let residue = |i: usize| {
+            self.residues
+                .as_ref()
+                .and_then(|r| r.get(i))
+                .map_or(1, |&r| r)
+        };
+        let n_substructures = match &self.residues {
+            Some(residues) => residues.iter().collect::<BTreeSet<_>>().len(),
+            None => 1,
+        };
+        let charge_type = if self.charges.is_some() {
+            "USER_CHARGES"
+        } else {
+            "NO_CHARGES"
+        };
+        let name = self
+            .name
+            .as_deref()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .unwrap_or("****");
+        let mut out = format!(
+            "@<TRIPOS>MOLECULE\n{name}\n{} {} {n_substructures} 0 0\nSMALL\n{charge_type}\n\n\n",
+            self.atoms.len(),
+            self.bonds.len()
+        );
+
+        out.push_str("@<TRIPOS>ATOM\n");
+        let to_angstrom = |v: f32| self.metadata.unit.convert(v, LengthUnit::Angstrom);
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for (i, atom) in self.atoms.iter().enumerate() {
+            let kind = element::symbol(&atom.element).unwrap_or(&atom.element);
+            let count = counts.entry(kind).or_default();
+            *count += 1;
+            let name = format!("{kind}{count}");
+            let p = atom.position.map(to_angstrom);
+            let residue_name = self
+                .residue_names
+                .as_ref()
+                .and_then(|names| names.get(i))
+                .map(String::as_str)
+                .filter(|name| !name.is_empty())
+                .unwrap_or("****");
+            let charge = self
+                .charges
+                .as_ref()
+                .and_then(|c| c.get(i))
+                .map_or(0.0, |&q| q);
+            out.push_str(&format!(
+                "{:>7} {name:<6}{:>12.4}{:>10.4}{:>10.4} {kind:<8}{:>3} {residue_name:>8}{charge:>10.4}\n",
+                i + 1,
+                p.x,
+                p.y,
+                p.z,
+                residue(i),
+            ));
+        }
+
+        out.push_str("@<TRIPOS>BOND\n");
+        for (i, bond) in self.bonds.iter().enumerate() {
+            let kind = match bond.order {
+                0 => "ar",
+                2 => "2",
+                3 => "3",
+                _ => "1",
+            };
+            out.push_str(&format!(
+                "{:>6}{:>6}{:>6}{kind:>6}\n",
+                i + 1,
+                bond.atom_a + 1,
+                bond.atom_b + 1
+            ));
+        }
+        out
+    }
+
+    pub fn from_xyz(path: &Path) -> Result<Self, MoleculeError> {
+        let content = std::fs::read_to_string(path)?;
+        Self::from_xyz_str(&content)
+    }
+
+    /// Parses the first frame of an XYZ file: an atom count, a comment line, then
+    /// `element x y z` per atom, in Å. XYZ records no bonds, so they are
+    /// inferred with `infer_bonds` and `BOND_TOLERANCE`.
+    pub fn from_xyz_str(content: &str) -> Result<Self, MoleculeError> {
+        Self::from_xyz_str_with(content, ParseOptions::default())
+    }
+
+    pub fn from_xyz_str_with(content: &str, options: ParseOptions) -> Result<Self, MoleculeError> {
+        let mut numbers = Numbers::new(options);
+        let mut lines = content.lines().enumerate();
+        let atoms = parse_xyz_frame(&mut lines, &mut numbers)?;
+        Ok(xyz_molecule(atoms, &numbers))
+    }
+
+    pub fn from_xyz_trajectory(path: &Path) -> Result<(Self, Trajectory), MoleculeError> {
+        let content = std::fs::read_to_string(path)?;
+        Self::from_xyz_trajectory_str(&content)
+    }
+
+    /// Parses every frame of a multi-frame XYZ file, in sequence, into the
+    /// first frame's molecule (as `from_xyz_str` gives it) and a trajectory
+    /// of all frames for `MoleculeViewer::set_trajectory`. Every frame must
+    /// list the same elements in the same order.
+    pub fn from_xyz_trajectory_str(content: &str) -> Result<(Self, Trajectory), MoleculeError> {
+        Self::from_xyz_trajectory_str_with(content, ParseOptions::default())
+    }
+
+    pub fn from_xyz_trajectory_str_with(
+        content: &str,
+        options: ParseOptions,
+    ) -> Result<(Self, Trajectory), MoleculeError> {
+        let mut numbers = Numbers::new(options);
+        let mut lines = content.lines().enumerate().peekable();
+        let first = parse_xyz_frame(&mut lines, &mut numbers)?;
+        let mut frames = vec![first.iter().map(|atom| atom.position).collect()];
+        loop {
+            // Blank lines between or after frames are not frames.
+            while lines.next_if(|(_, line)| line.trim().is_empty()).is_some() {}
+            if lines.peek().is_none() {
+                break;
+            }
+            let atoms = parse_xyz_frame(&mut lines, &mut numbers)?;
+            if atoms.len() != first.len() {
+                return Err(MoleculeError::FrameMismatch {
+                    frame: frames.len(),
+                    reason: format!("{} atoms, frame 0 has {}", atoms.len(), first.len()),
+                });
+            }
+            if let Some(i) = (0..atoms.len()).find(|&i| atoms[i].element != first[i].element) {
+                return Err(MoleculeError::FrameMismatch {
+                    frame: frames.len(),
+                    reason: format!(
+                        "atom {} is {}, in frame 0 {}",
+                        i + 1,
+                        atoms[i].element,
+                        first[i].element
+                    ),
+                });
+            }
+            frames.push(atoms.into_iter().map(|atom| atom.position).collect());
+        }
+        // Every frame was checked against frame 0 above.
+        let trajectory = Trajectory::new(frames).expect("frames of equal length");
+        Ok((xyz_molecule(first, &numbers), trajectory))
+    }
+
+    pub fn from_gro(path: &Path) -> Result<Self, MoleculeError> {
+        let content = std::fs::read_to_string(path)?;
+        Self::from_gro_str(&content)
+    }
+
+    /// Parses the first frame of a GROMACS .gro file. Coordinates are stored in
+    /// nm and converted to Å. Elements are guessed from atom names; .gro records
+    /// no bonds. The box line after the atoms becomes `lattice`, unless it is
+    /// missing, malformed or has no volume.
+    pub fn from_gro_str(content: &str) -> Result<Self, MoleculeError> {
+        Self::from_gro_str_with(content, ParseOptions::default())
+    }
+
+    pub fn from_gro_str_with(content: &str, options: ParseOptions) -> Result<Self, MoleculeError> {
+        let mut numbers = Numbers::new(options);
+        let mut lines = content.lines();
+        lines.next(); // title
+        let count = parse_count(lines.next()).ok_or_else(|| MoleculeError::MissingSection {
+            format: "gro",
+            section: "atom count (line 2)".to_string(),
+        })?;
+        let mut atoms = Vec::with_capacity(count);
+        for (i, line) in lines.take(count).enumerate() {
+            // Fixed columns: residue number, residue name, atom name, atom
+            // number (5 each), then x, y, z (8 each).
+            let column = |start: usize, end: usize| line.get(start..end).map(str::trim);
+            let position = match (column(20, 28), column(28, 36), column(36, 44)) {
+                (Some(x), Some(y), Some(z)) => numbers.point(x, y, z),
+                _ => None,
+            }
+            .ok_or_else(|| MoleculeError::InvalidAtomRecord {
+                format: "gro",
+                line: i + 3,
+                reason: "expected x y z in columns 21-44".to_string(),
+            })?;
+            let residue = column(5, 10).unwrap_or("");
+            let name = column(10, 15).unwrap_or("");
+            atoms.push(Atom {
+                position: position * LengthUnit::Nanometer.in_angstrom(),
+                element: gro_element(residue, name),
+                id: i + 1,
+            });
+        }
+        if atoms.len() < count {
+            return Err(missing_atoms("gro", count, atoms.len()));
+        }
+        let lattice = content
+            .lines()
+            .nth(count + 2)
+            .and_then(|line| parse_gro_box(line, &mut numbers))
+            .map(|lattice| lattice.scaled(LengthUnit::Nanometer.in_angstrom()));
+        let mut mol = Molecule {
+            atoms,
+            lattice,
+            warnings: numbers.warning("gro").into_iter().collect(),
+            metadata: MoleculeMetadata {
+                source_unit: LengthUnit::Nanometer,
+                unit: LengthUnit::Angstrom,
+            },
+            ..Default::default()
+        };
+        mol.assign_atom_ids();
+        Ok(mol)
+    }
+
+    pub fn from_pdb(path: &Path) -> Result<Self, MoleculeError> {
+        let content = std::fs::read_to_string(path)?;
+        Self::from_pdb_str(&content)
+    }
+
+    /// Parses the ATOM and HETATM records of the first model of a PDB file,
+    /// with CONECT records, wherever they are before END, as bonds (of order
+    /// 1; PDB records no orders). Without CONECT records the molecule has no
+    /// bonds.
+    ///
+    /// Elements come from columns 77-78, or, when those are blank, from the
+    /// atom name as for mol2 atoms without a type (see `mol2_element`), so
+    /// the alpha carbon CA of a residue is carbon and a CA ion is calcium.
+    /// Only the first alternate location of an atom is kept. Residue numbers
+    /// and names are kept if every atom has them, and chains if any atom
+    /// names one.
+    pub fn from_pdb_str(content: &str) -> Result<Self, MoleculeError> {
+        Self::from_pdb_str_with(content, ParseOptions::default())
+    }
+
+    pub fn from_pdb_str_with(content: &str, options: ParseOptions) -> Result<Self, MoleculeError> {
+        let mut numbers = Numbers::new(options);
+        let mut atoms = Vec::new();
+        let mut residues = Some(Vec::new());
+        let mut residue_names = Some(Vec::new());
+        let mut chains = Vec::new();
+        let mut warnings = Vec::new();
+        // Atom index by serial number, for CONECT records.
+        let mut serials = HashMap::new();
+        let mut conect = Vec::new();
+        // CONECT records follow the last model, so later models are skipped
+        // rather than ending the scan.
+        let mut first_model_done = false;
+
+        for (i, line) in content.lines().enumerate() {
+            let record = line.get(..6).unwrap_or(line).trim_end();
+            match record {
+                "ATOM" | "HETATM" if !first_model_done => {}
+                "CONECT" => {
+                    conect.push(line);
+                    continue;
+                }
+                "ENDMDL" => {
+                    first_model_done = true;
+                    continue;
+                }
+                "END" => break,
+                _ => continue,
+            }
+            if !matches!(column(line, 16, 17), "" | "A") {
+                continue;
+            }
+            let position = numbers
+                .point(
+                    column(line, 30, 38),
+                    column(line, 38, 46),
+                    column(line, 46, 54),
+                )
+                .ok_or_else(|| MoleculeError::InvalidAtomRecord {
+                    format: "pdb",
+                    line: i + 1,
+                    reason: "expected x y z in columns 31-54".to_string(),
+                })?;
+            let serial = column(line, 6, 11).parse::<usize>().ok();
+            let name = column(line, 12, 16);
+            let residue_name = Some(column(line, 17, 20)).filter(|r| !r.is_empty());
+            let element = element::symbol(column(line, 76, 78))
+                .or_else(|| mol2_element("", name, residue_name))
+                .map(str::to_ascii_uppercase)
+                .unwrap_or_else(|| {
+                    warnings.push(format!(
+                        "pdb atom {}: no element in columns 77-78 or name of {line:?}",
+                        atoms.len() + 1
+                    ));
+                    element::UNKNOWN.to_string()
+                });
+            if let Some(serial) = serial {
+                serials.insert(serial, atoms.len());
+            }
+            residues = residues
+                .zip(column(line, 22, 26).parse::<usize>().ok())
+                .map(|(mut r, id)| {
+                    r.push(id);
+                    r
+                });
+            residue_names = residue_names.zip(residue_name).map(|(mut r, name)| {
+                r.push(name.to_string());
+                r
+            });
+            chains.push(column(line, 21, 22).chars().next().unwrap_or(' '));
+            atoms.push(Atom {
+                position,
+                element,
+                id: serial.unwrap_or(atoms.len() + 1),
+            });
+        }
+
+        // Each pair is usually listed from both ends; keep one bond.
+        let mut bonds = Vec::new();
+        let mut bonded = HashSet::new();
+        for line in conect {
+            let serial = |k: usize| {
+                let field = line.get(6 + 5 * k..(11 + 5 * k).min(line.len()))?;
+                serials.get(&field.trim().parse::<usize>().ok()?).copied()
+            };
+            let Some(a) = serial(0) else {
+                continue;
+            };
+            for b in (1..5).filter_map(serial) {
+                if a != b && bonded.insert((a.min(b), a.max(b))) {
+                    bonds.push(Bond {
+                        atom_a: a,
+                        atom_b: b,
+                        order: 1,
+                    });
+                }
+            }
+        }
+
+        warnings.extend(numbers.warning("pdb"));
+        let mut mol = Molecule {
+            residues: residues.filter(|r| !r.is_empty()),
+            residue_names: residue_names.filter(|r| !r.is_empty()),
+            chains: Some(chains).filter(|c| c.iter().any(|&c| c != ' ')),
+            atoms,
+            bonds,
+            warnings,
+            ..Default::default()
+        };
+        mol.assign_atom_ids();
+        Ok(mol)
+    }
+
+    pub fn from_sdf(path: &Path) -> Result<Self, MoleculeError> {
+        let content = std::fs::read_to_string(path)?;
+        Self::from_sdf_str(&content)
+    }
+
+    /// Parses the first record of an SDF (MDL V2000 molfiles separated by
+    /// `$$$$`), or a single molfile: the counts line, atom block, bond block
+    /// and the `M  CHG` property, which replaces the atom block's charges as
+    /// the format specifies. Data items after `M  END` are skipped. V3000
+    /// records and a file without records are an error. Later records are
+    /// not read; see `from_sdf_all_str` for them.
+    ///
+    /// Elements are uppercased; query atoms such as "R#" or "*" get
+    /// `element::UNKNOWN` and a warning. Aromatic bonds (type 4) get order 0,
+    /// as in mol2, and stereo flags are ignored.
+    pub fn from_sdf_str(content: &str) -> Result<Self, MoleculeError> {
+        Self::from_sdf_str_with(content, ParseOptions::default())
+    }
+
+    pub fn from_sdf_str_with(content: &str, options: ParseOptions) -> Result<Self, MoleculeError> {
+        let (start, record) =
+            sdf_records(content)
+                .next()
+                .ok_or_else(|| MoleculeError::MissingSection {
+                    format: "sdf",
+                    section: "records".to_string(),
+                })?;
+        parse_sdf_record(&record, 1, start, options)
+    }
+
+    pub fn from_sdf_all(path: &Path) -> Result<Vec<Self>, MoleculeError> {
+        let content = std::fs::read_to_string(path)?;
+        Self::from_sdf_all_str(&content)
+    }
+
+    /// Every record of an SDF, each parsed as by `from_sdf_str`. One
+    /// malformed record fails the whole file.
+    pub fn from_sdf_all_str(content: &str) -> Result<Vec<Self>, MoleculeError> {
+        Self::from_sdf_all_str_with(content, ParseOptions::default())
+    }
+
+    pub fn from_sdf_all_str_with(
+        content: &str,
+        options: ParseOptions,
+    ) -> Result<Vec<Self>, MoleculeError> {
+        sdf_records(content)
+            .enumerate()
+            .map(|(i, (start, record))| parse_sdf_record(&record, i + 1, start, options))
+            .collect()
+    }
+
+    /// Rescales all positions into `target` and records it in `metadata.unit`.
+    pub fn convert_units(&mut self, target: LengthUnit) {
+        let factor = self.metadata.unit.convert(1.0, target);
+        for atom in &mut self.atoms {
+            atom.position *= factor;
+        }
+        self.lattice = self.lattice.map(|lattice| lattice.scaled(factor));
+        self.metadata.unit = target;
+    }
+
+    /// Distance between atoms `a` and `b` in `unit`, or in `metadata.unit` if
+    /// `None`. `None` if either index is out of range.
+    pub fn distance(&self, a: usize, b: usize, unit: Option<LengthUnit>) -> Option<f32> {
+        let distance = (self.atoms.get(a)?.position - self.atoms.get(b)?.position).norm();
+        Some(
+            self.metadata
+                .unit
+                .convert(distance, unit.unwrap_or(self.metadata.unit)),
+        )
+    }
+
+    /// Length of the bond between atoms `a` and `b`, in `metadata.unit`.
+    /// `None` if an index is out of range or the atoms are not bonded; see
+    /// `distance` for any two atoms.
+    pub fn bond_length(&self, a: usize, b: usize) -> Option<f32> {
+        self.find_bond(a, b)?;
+        self.distance(a, b, None)
+    }
+
+    /// Angle b–a–c at atom `a`, in degrees: the vertex comes first. `None` if
+    /// an index is out of range or `b` or `c` sits on `a`.
+    pub fn angle(&self, a: usize, b: usize, c: usize) -> Option<f32> {
+        let vertex = self.atoms.get(a)?.position;
+        let ab = self.atoms.get(b)?.position - vertex;
+        let ac = self.atoms.get(c)?.position - vertex;
+        if ab.norm() < f32::EPSILON || ac.norm() < f32::EPSILON {
+            return None;
+        }
+        Some(ab.angle(&ac).to_degrees())
+    }
+
+    /// Dihedral (torsion) angle a–b–c–d about the b–c bond, in degrees
+    /// between -180 and 180; positive when, looking from `b` to `c`, `a` turns
+    /// clockwise onto `d`. `None` if an index is out of range or either
+    /// a–b–c or b–c–d is collinear.
+    pub fn dihedral(&self, a: usize, b: usize, c: usize, d: usize) -> Option<f32> {
+        let [pa, pb, pc, pd] = [a, b, c, d].map(|i| self.atoms.get(i).map(|atom| atom.position));
+        let (b1, b2, b3) = (pb? - pa?, pc? - pb?, pd? - pc?);
+        let n1 = b1.cross(&b2);
+        let n2 = b2.cross(&b3);
+        if n1.norm() < f32::EPSILON || n2.norm() < f32::EPSILON {
+            return None;
+        }
+        let y = b2.norm() * b1.dot(&n2);
+        let x = n1.dot(&n2);
+        Some(y.atan2(x).to_degrees())
+    }
+
+    /// Bonded neighbours of each atom, indexed like `atoms`. Bonds referring to
+    /// missing atoms are ignored.
+    pub fn adjacency(&self) -> Vec<Vec<usize>> {
+        let mut neighbors = vec![Vec::new(); self.atoms.len()];
+        for bond in &self.bonds {
+            let (a, b) = (bond.atom_a, bond.atom_b);
+            if a < neighbors.len() && b < neighbors.len() && a != b {
+                neighbors[a].push(b);
+                neighbors[b].push(a);
+            }
+        }
+        neighbors
+    }
+
+    /// Index of the bond between atoms `a` and `b`, in either direction.
+    pub fn find_bond(&self, a: usize, b: usize) -> Option<usize> {
+        self.bonds.iter().position(|bond| {
+            (bond.atom_a, bond.atom_b) == (a, b) || (bond.atom_a, bond.atom_b) == (b, a)
+        })
+    }
+
+    /// Bonds atoms `a` and `b` and returns the new bond's index. Fails,
+    /// changing nothing, for out-of-range atoms, `a == b`, or atoms already
+    /// bonded.
+    pub fn add_bond(&mut self, a: usize, b: usize, order: u8) -> Result<usize, String> {
+        if let Some(atom) = [a, b].into_iter().find(|&i| i >= self.atoms.len()) {
+            return Err(format!("bond: no atom {atom}"));
+        }
+        if a == b {
+            return Err(format!("bond: atom {a} cannot bond to itself"));
+        }
+        if let Some(existing) = self.find_bond(a, b) {
+            return Err(format!(
+                "bond: atoms {a} and {b} are already bonded (bond {existing})"
+            ));
+        }
+        self.bonds.push(Bond {
+            atom_a: a,
+            atom_b: b,
+            order,
+        });
+        Ok(self.bonds.len() - 1)
+    }
+
+    /// Removes the atoms `indices`, the bonds to them, and their charges,
+    /// formal charges, residues and residue names, chains, ids and pins.
+    /// Out-of-range indices are ignored. Returns the new index of each old
+    /// atom, `None` for removed ones.
+    pub fn remove_atoms(&mut self, indices: &[usize]) -> Vec<Option<usize>> {
+        let mut keep = vec![true; self.atoms.len()];
+        for &i in indices {
+            if let Some(k) = keep.get_mut(i) {
+                *k = false;
+            }
+        }
+        let mut next = 0;
+        let new_index: Vec<Option<usize>> = keep
+            .iter()
+            .map(|&k| {
+                k.then(|| {
+                    next += 1;
+                    next - 1
+                })
+            })
+            .collect();
+
+        retain_kept(&mut self.atoms, &keep);
+        if let Some(charges) = &mut self.charges {
+            retain_kept(charges, &keep);
+        }
+        if let Some(residues) = &mut self.residues {
+            retain_kept(residues, &keep);
+        }
+        if let Some(residue_names) = &mut self.residue_names {
+            retain_kept(residue_names, &keep);
+        }
+        if let Some(chains) = &mut self.chains {
+            retain_kept(chains, &keep);
+        }
+        if let Some(formal_charges) = &mut self.formal_charges {
+            retain_kept(formal_charges, &keep);
+        }
+        self.atom_ids.retain(&keep);
+        self.pinned = self
+            .pinned
+            .iter()
+            .filter_map(|&i| new_index.get(i).copied().flatten())
+            .collect();
+        self.bonds.retain_mut(|bond| {
+            let (Some(Some(a)), Some(Some(b))) =
+                (new_index.get(bond.atom_a), new_index.get(bond.atom_b))
+            else {
+                return false;
+            };
+            bond.atom_a = *a;
+            bond.atom_b = *b;
+            true
+        });
+        new_index
+    }
+
+    pub fn is_pinned(&self, i: usize) -> bool {
+        self.pinned.contains(&i)
+    }
+
+    /// Mean position of the atoms; the origin for an empty molecule.
+    pub fn centroid(&self) -> Point3<f32> {
+        if self.atoms.is_empty() {
+            return Point3::origin();
+        }
+        let sum = self
+            .atoms
+            .iter()
+            .fold(Vector3::zeros(), |sum, atom| sum + atom.position.coords);
+        Point3::from(sum / self.atoms.len() as f32)
+    }
+
+    /// Mass-weighted mean position of the atoms. `masses` are in u, indexed
+    /// like `atoms`; atoms past its end, or every atom if it is `None`, get
+    /// their element's `element::atomic_mass`, or `UNKNOWN_ATOM_MASS`. Falls
+    /// back to `centroid` if the masses sum to zero.
+    pub fn center_of_mass(&self, masses: Option<&[f32]>) -> Point3<f32> {
+        let mut total = 0.0;
+        let mut sum = Vector3::zeros();
+        for (i, atom) in self.atoms.iter().enumerate() {
+            let mass = masses
+                .and_then(|masses| masses.get(i).copied())
+                .or_else(|| element::atomic_mass(&atom.element))
+                .unwrap_or(UNKNOWN_ATOM_MASS);
+            total += mass;
+            sum += atom.position.coords * mass;
+        }
+        if total == 0.0 {
+            return self.centroid();
+        }
+        Point3::from(sum / total)
+    }
+
+    /// Minimum and maximum corners of the atoms' axis-aligned bounding box;
+    /// both the origin for an empty molecule.
+    pub fn bounding_box(&self) -> (Point3<f32>, Point3<f32>) {
+        let (mut min, mut max) = match self.atoms.first() {
+            Some(atom) => (atom.position, atom.position),
+            None => (Point3::origin(), Point3::origin()),
+        };
+        for atom in &self.atoms {
+            min = min.inf(&atom.position);
+            max = max.sup(&atom.position);
+        }
+        (min, max)
+    }
+
+    /// Axis-aligned box to frame the camera on: `bounding_box`, grown about
+    /// its center to at least `MIN_FRAME_EXTENT` on each side so single
+    /// atoms and flat molecules get a sensible view. An empty molecule gives
+    /// that box around the origin.
+    pub fn frame_bounds(&self) -> (Point3<f32>, Point3<f32>) {
+        let (min, max) = self.bounding_box();
+        let center = nalgebra::center(&min, &max);
+        let half = (max - min).map(|extent| extent.max(MIN_FRAME_EXTENT) / 2.0);
+        (center - half, center + half)
+    }
+
+    /// Moves every atom by `offset`, and the lattice's origin with them.
+    pub fn translate(&mut self, offset: Vector3<f32>) {
+        for atom in &mut self.atoms {
+            atom.position += offset;
+        }
+        self.lattice = self
+            .lattice
+            .map(|lattice| lattice.with_origin(lattice.origin() + offset));
+    }
+
+    /// Turns every atom by `rotation` about `pivot`. The lattice's cell
+    /// vectors and origin turn with them.
+    pub fn rotate(&mut self, rotation: UnitQuaternion<f32>, pivot: Point3<f32>) {
+        for atom in &mut self.atoms {
+            atom.position = pivot + rotation * (atom.position - pivot);
+        }
+        self.lattice = self
+            .lattice
+            .map(|lattice| lattice.rotated(&rotation, pivot));
+    }
+
+    /// Scales the molecule by `factor` about its `centroid`, lattice
+    /// included. Unlike `convert_units`, `metadata.unit` is unchanged.
+    pub fn scale(&mut self, factor: f32) {
+        let centroid = self.centroid();
+        for atom in &mut self.atoms {
+            atom.position = centroid + (atom.position - centroid) * factor;
+        }
+        self.lattice = self.lattice.map(|lattice| {
+            lattice
+                .scaled(factor)
+                .with_origin(centroid + (lattice.origin() - centroid) * factor)
+        });
+    }
+
+    /// A copy moved by `offset`; see `translate`.
+    pub fn translated(&self, offset: Vector3<f32>) -> Self {
+        let mut mol = self.clone();
+        mol.translate(offset);
+        mol
+    }
+
+    /// A copy turned by `rotation` about `pivot`; see `rotate`.
+    pub fn rotated(&self, rotation: UnitQuaternion<f32>, pivot: Point3<f32>) -> Self {
+        let mut mol = self.clone();
+        mol.rotate(rotation, pivot);
+        mol
+    }
+
+    /// A copy scaled by `factor` about its centroid; see `scale`.
+    pub fn scaled(&self, factor: f32) -> Self {
+        let mut mol = self.clone();
+        mol.scale(factor);
+        mol
+    }
+
+    /// Connected components of the bond graph, each in ascending atom order,
+    /// ordered by their lowest atom. Unbonded atoms are fragments of their own.
+    pub fn fragments(&self) -> Vec<Vec<usize>> {
+        let adjacency = self.adjacency();
+        let mut seen = vec![false; adjacency.len()];
+        let mut fragments = Vec::new();
+        for start in 0..adjacency.len() {
+            if seen[start] {
+                continue;
+            }
+            seen[start] = true;
+            let mut fragment = vec![start];
+            let mut stack = vec![start];
+            while let Some(i) = stack.pop() {
+                for &j in &adjacency[i] {
+                    if !seen[j] {
+                        seen[j] = true;
+                        fragment.push(j);
+                        stack.push(j);
+                    }
+                }
+            }
+            fragment.sort_unstable();
+            fragments.push(fragment);
+        }
+        fragments
+    }
+
+    /// Atoms at most `max_shells` bonds from `atom`, each with its bond
+    /// distance, nearest first: `atom` itself at 0, then its neighbours at 1,
+    /// and so on. Empty if `atom` is out of range.
+    pub fn bond_shells(&self, atom: usize, max_shells: usize) -> Vec<(usize, usize)> {
+        let adjacency = self.adjacency();
+        if atom >= adjacency.len() {
+            return Vec::new();
+        }
+        let mut seen = vec![false; adjacency.len()];
+        seen[atom] = true;
+        let mut shells = vec![(atom, 0)];
+        let mut next = 0;
+        while let Some(&(i, shell)) = shells.get(next) {
+            next += 1;
+            if shell == max_shells {
+                continue;
+            }
+            for &j in &adjacency[i] {
+                if !seen[j] {
+                    seen[j] = true;
+                    shells.push((j, shell + 1));
+                }
+            }
+        }
+        shells
+    }
+}
+
+/// The box line of a .gro file: v1(x) v2(y) v3(z) for a rectangular box, or
+/// followed by v1(y) v1(z) v2(x) v2(z) v3(x) v3(y) for a triclinic one, in nm.
+fn parse_gro_box(line: &str, numbers: &mut Numbers) -> Option<Lattice> {
+    let values: Vec<f32> = line
+        .split_ascii_whitespace()
+        .map(|value| numbers.f32(value))
+        .collect::<Option<_>>()?;
+    let v = match values[..] {
+        [xx, yy, zz] => [xx, yy, zz, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+        [xx, yy, zz, xy, xz, yx, yz, zx, zy] => [xx, yy, zz, xy, xz, yx, yz, zx, zy],
+        _ => return None,
+    };
+    Lattice::new(
+        Vector3::new(v[0], v[3], v[4]),
+        Vector3::new(v[5], v[1], v[6]),
+        Vector3::new(v[7], v[8], v[2]),
+    )
+}
+
+/// The lines of each record of an SDF, without the `$$$$` lines. The last
+/// record may lack its `$$$$`; blank lines after it are not a record.
+fn sdf_records(content: &str) -> impl Iterator<Item = (usize, Vec<&str>)> {
+    let mut lines = content.lines().peekable();
+    let mut start = 0;
+    std::iter::from_fn(move || {
+        let record: Vec<&str> = lines
+            .by_ref()
+            .take_while(|line| line.trim_end() != "$$$$")
+            .collect();
+        let blank_tail = lines.peek().is_none() && record.iter().all(|l| l.trim().is_empty());
+        let record_start = start;
+        start += record.len() + 1;
+        (!blank_tail).then_some((record_start, record))
+    })
+}
+
+/// Parses one V2000 molfile, record `n` (1-based) of an SDF starting at
+/// line index `start`, for `Molecule::from_sdf_str` and `from_sdf_all_str`.
+fn parse_sdf_record(
+    lines: &[&str],
+    n: usize,
+    start: usize,
+    options: ParseOptions,
+) -> Result<Molecule, MoleculeError> {
+    let missing = |section: String| MoleculeError::MissingSection {
+        format: "sdf",
+        section: format!("{section} of record {n}"),
+    };
+    let counts = lines
+        .get(3)
+        .ok_or_else(|| missing("the counts line".to_string()))?;
+    if column(counts, 33, 39) == "V3000" {
+        return Err(MoleculeError::UnsupportedFormat(format!(
+            "sdf record {n} is V3000"
+        )));
+    }
+    let (Ok(n_atoms), Ok(n_bonds)) = (
+        column(counts, 0, 3).parse::<usize>(),
+        column(counts, 3, 6).parse::<usize>(),
+    ) else {
+        return Err(missing(format!(
+            "atom and bond counts in columns 1-6 of {counts:?}"
+        )));
+    };
+    let atom_lines = lines.get(4..4 + n_atoms);
+    let bond_lines = lines.get(4 + n_atoms..4 + n_atoms + n_bonds);
+    let (Some(atom_lines), Some(bond_lines)) = (atom_lines, bond_lines) else {
+        return Err(missing(format!(
+            "some of the {n_atoms} atoms and {n_bonds} bonds"
+        )));
+    };
+    // 1-based file line of atom `i` and bond `i` (0-based).
+    let atom_line = |i: usize| start + 5 + i;
+    let bond_line = |i: usize| start + 5 + n_atoms + i;
+
+    let mut numbers = Numbers::new(options);
+    let mut warnings = Vec::new();
+    let mut atoms = Vec::with_capacity(n_atoms);
+    let mut formal_charges = Vec::with_capacity(n_atoms);
+    for (i, line) in atom_lines.iter().enumerate() {
+        // x, y, z (10 each), a space, the symbol (3), mass difference (2),
+        // then the charge code (3).
+        let position = numbers
+            .point(
+                column(line, 0, 10),
+                column(line, 10, 20),
+                column(line, 20, 30),
+            )
+            .ok_or_else(|| MoleculeError::InvalidAtomRecord {
+                format: "sdf",
+                line: atom_line(i),
+                reason: "expected x y z".to_string(),
+            })?;
+        let symbol = column(line, 31, 34);
+        let element = element::symbol(symbol)
+            .map(str::to_ascii_uppercase)
+            .unwrap_or_else(|| {
+                warnings.push(format!(
+                    "sdf record {n} atom {}: no element {symbol:?}",
+                    i + 1
+                ));
+                element::UNKNOWN.to_string()
+            });
+        // 1-7 stand for +3 to -3, 4 for a doublet radical.
+        let charge = match column(line, 36, 39) {
+            "1" => 3,
+            "2" => 2,
+            "3" => 1,
+            "5" => -1,
+            "6" => -2,
+            "7" => -3,
+            _ => 0,
+        };
+        formal_charges.push(charge);
+        atoms.push(Atom {
+            position,
+            element,
+            id: i + 1,
+        });
+    }
+
+    let mut bonds = Vec::with_capacity(n_bonds);
+    for (i, line) in bond_lines.iter().enumerate() {
+        let (Ok(a), Ok(b)) = (
+            column(line, 0, 3).parse::<usize>(),
+            column(line, 3, 6).parse::<usize>(),
+        ) else {
+            return Err(MoleculeError::InvalidBondRecord {
+                format: "sdf",
+                line: bond_line(i),
+                reason: "expected two atoms".to_string(),
+            });
+        };
+        if let Some(index) = [a, b].into_iter().find(|&k| k == 0 || k > n_atoms) {
+            return Err(MoleculeError::IndexOutOfRange {
+                format: "sdf",
+                line: bond_line(i),
+                index,
+                len: n_atoms,
+            });
+        }
+        let order = match column(line, 6, 9) {
+            "2" => 2,
+            "3" => 3,
+            "4" => 0,
+            _ => 1,
+        };
+        bonds.push(Bond {
+            atom_a: a - 1,
+            atom_b: b - 1,
+            order,
+        });
+    }
+
+    let properties = lines[4 + n_atoms + n_bonds..]
+        .iter()
+        .take_while(|line| !line.starts_with("M  END"));
+    let mut reset = false;
+    for line in properties.filter(|line| line.starts_with("M  CHG")) {
+        // M  CHGnn8 aaa vvv ...
+        if !std::mem::replace(&mut reset, true) {
+            formal_charges.fill(0);
+        }
+        let fields: Vec<&str> = line[6..].split_ascii_whitespace().skip(1).collect();
+        for pair in fields.chunks_exact(2) {
+            if let (Ok(a), Ok(q)) = (pair[0].parse::<usize>(), pair[1].parse::<i8>()) {
+                if let Some(charge) = a.checked_sub(1).and_then(|a| formal_charges.get_mut(a)) {
+                    *charge = q;
+                }
+            }
+        }
+    }
+
+    warnings.extend(numbers.warning("sdf"));
+    let mut mol = Molecule {
+        name: lines
+            .first()
+            .map(|line| line.trim())
+            .filter(|name| !name.is_empty())
+            .map(str::to_string),
+        atoms,
+        bonds,
+        formal_charges: Some(formal_charges),
+        warnings,
+        ..Default::default()
+    };
+    mol.assign_atom_ids();
+    Ok(mol)
+}
+
+/// Columns `start..end` (0-based) of a fixed-column record, trimmed; empty
+/// past the end of the line.
+fn column(line: &str, start: usize, end: usize) -> &str {
+    line.get(start..end.min(line.len()))
+        .map(str::trim)
+        .unwrap_or("")
+}
+
+/// Reads one XYZ frame from `lines` (numbered from 0): the atom count, the
+/// comment line and the atoms.
+fn parse_xyz_frame<'a>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    numbers: &mut Numbers,
+) -> Result<Vec<Atom>, MoleculeError> {
+    let (count_line, count) = match lines.next() {
+        Some((n, line)) => (n + 1, parse_count(Some(line))),
+        None => (1, parse_count(None)),
+    };
+    let count = count.ok_or_else(|| MoleculeError::MissingSection {
+        format: "xyz",
+        section: format!("atom count (line {count_line})"),
+    })?;
+    lines.next(); // comment
+    let mut atoms = Vec::with_capacity(count);
+    for (n, line) in lines.take(count) {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let position = match parts.as_slice() {
+            [_, x, y, z, ..] => numbers.point(x, y, z),
+            _ => None,
+        }
+        .ok_or_else(|| MoleculeError::InvalidAtomRecord {
+            format: "xyz",
+            line: n + 1,
+            reason: "expected element x y z".to_string(),
+        })?;
+        // Some writers number atoms ("C1"); keep the element letters only.
+        let element: String = parts[0]
+            .chars()
+            .take_while(|c| c.is_ascii_alphabetic())
+            .collect();
+        atoms.push(Atom {
+            position,
+            element: element.to_uppercase(),
+            id: atoms.len() + 1,
+        });
+    }
+    if atoms.len() < count {
+        return Err(missing_atoms("xyz", count, atoms.len()));
+    }
+    Ok(atoms)
+}
+
+/// The molecule of XYZ `atoms`, with inferred bonds.
+fn xyz_molecule(atoms: Vec<Atom>, numbers: &Numbers) -> Molecule {
+    let mut mol = Molecule {
+        atoms,
+        warnings: numbers.warning("xyz").into_iter().collect(),
+        ..Default::default()
+    };
+    mol.infer_bonds(BOND_TOLERANCE);
+    mol.assign_atom_ids();
+    mol
+}
+
+/// Keeps the values whose index is `true` in `keep`, and none past its end.
+fn retain_kept<T>(values: &mut Vec<T>, keep: &[bool]) {
+    let mut kept = keep.iter();
+    values.retain(|_| kept.next().copied().unwrap_or(false));
+}
+
+/// Line `n` (1 for the name) of the `@<TRIPOS>MOLECULE` record, trimmed.
+fn mol2_molecule_line(content: &str, n: usize) -> Option<&str> {
+    content
+        .lines()
+        .skip_while(|l| l.trim() != "@<TRIPOS>MOLECULE")
+        .nth(n)
+        .map(str::trim)
+}
+
+/// Atom and bond counts from the line after the molecule name in the
+/// `@<TRIPOS>MOLECULE` record.
+fn mol2_counts(content: &str) -> Option<(usize, usize)> {
+    let mut counts = mol2_molecule_line(content, 2)?.split_whitespace();
+    let atoms = counts.next()?.parse().ok()?;
+    let bonds = counts.next().map_or(Some(0), |b| b.parse().ok())?;
+    Some((atoms, bonds))
+}
+
+/// Fields of a mol2 ATOM line used by `from_mol2_str`.
+struct Mol2Atom {
+    position: Point3<f32>,
+    /// `None` if neither the type nor the name gives one.
+    element: Option<String>,
+    /// Substructure id, an optional column.
+    residue: Option<usize>,
+    /// An optional column.
+    charge: Option<f32>,
+}
+
+/// Parses a mol2 ATOM line without collecting its fields.
+fn parse_mol2_atom(line: &str, numbers: &mut Numbers) -> Option<Mol2Atom> {
+    let mut parts = line.split_ascii_whitespace().skip(1);
+    let (name, x, y, z) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+    let kind = parts.next().unwrap_or("");
+    let residue = parts.next().and_then(|id| id.parse().ok());
+    // "****" is mol2 for an empty field.
+    let residue_name = parts.next().filter(|&r| r != "****");
+    let charge = parts.next().and_then(|q| numbers.f32(q));
+    let position = numbers.point(x, y, z)?;
+    let element = mol2_element(kind, name, residue_name).map(str::to_ascii_uppercase);
+    Some(Mol2Atom {
+        position,
+        element,
+        residue,
+        charge,
+    })
+}
+
+/// Two-letter starts of biomolecular atom names that are also element
+/// symbols, e.g. CA (alpha carbon, calcium) or HG (gamma hydrogen, mercury).
+const AMBIGUOUS_NAMES: &[&str] = &[
+    "CA", "CD", "CE", "CN", "HE", "HF", "HG", "HO", "HS", "NA", "NB", "ND", "NE", "NH", "OG", "SG",
+];
+
+/// Element of a mol2 atom with SYBYL type `kind`, atom `name` and substructure
+/// `residue_name`, in this order of preference:
+///
+/// 1. The type's part before the dot, if it is an element. Two-letter symbols
+///    must be spelled as SYBYL does ("Cl"), so all-caps force-field types
+///    such as "CA" or "CT" fall through.
+/// 2. The name's letters, ignoring digits: the first two, then the first
+///    one. Names starting like `AMBIGUOUS_NAMES` take the first letter when
+///    the atom is in a residue named otherwise (an alpha carbon), and the
+///    two-letter element when there is no residue or it is named after the
+///    atom (a calcium ion).
+fn mol2_element(kind: &str, name: &str, residue_name: Option<&str>) -> Option<&'static str> {
+    let token = kind.split('.').next().unwrap_or("");
+    if let Some(symbol) = element::symbol(token).filter(|&s| token.len() == 1 || s == token) {
+        return Some(symbol);
+    }
+    let letters = |s: &str| -> String {
+        s.chars()
+            .filter(|c| !c.is_ascii_digit())
+            .take_while(|c| c.is_ascii_alphabetic())
+            .collect()
+    };
+    let name = letters(name);
+    let one = name.get(..1).and_then(element::symbol);
+    let two = name.get(..2).and_then(element::symbol);
+    let ambiguous = name.get(..2).is_some_and(|start| {
+        AMBIGUOUS_NAMES
+            .iter()
+            .any(|n| n.eq_ignore_ascii_case(start))
+    });
+    let in_residue = residue_name.is_some_and(|r| !letters(r).eq_ignore_ascii_case(&name));
+    match (one, two) {
+        (Some(one), Some(_)) if ambiguous && in_residue => Some(one),
+        (_, Some(two)) => Some(two),
+        (one, None) => one,
+    }
+}
+
+fn parse_count(line: Option<&str>) -> Option<usize> {
+    line.and_then(|l| l.trim().parse().ok())
+}
+
+fn missing_atoms(format: &'static str, count: usize, found: usize) -> MoleculeError {
+    MoleculeError::MissingSection {
+        format,
+        section: format!("{} of {count} atoms", count - found),
+    }
+}
+
+/// Reads the number fields of one file per `ParseOptions`, counting those
+/// written with a decimal comma.
+struct Numbers {
+    options: ParseOptions,
+    comma_fields: usize,
+}
+
+impl Numbers {
+    fn new(options: ParseOptions) -> Self {
+        Self {
+            options,
+            comma_fields: 0,
+        }
+    }
+
+    /// `parse_f32`, also reading a single ',' as the decimal point if the
+    /// options allow it.
+    fn f32(&mut self, s: &str) -> Option<f32> {
+        if let Some(value) = parse_f32(s) {
+            return Some(value);
+        }
+        if !self.options.decimal_comma || s.contains('.') {
+            return None;
+        }
+        let (whole, fraction) = s.split_once(',')?;
+        if fraction.contains(',') {
+            return None;
+        }
+        let value = parse_f32(&format!("{whole}.{fraction}"))?;
+        self.comma_fields += 1;
+        Some(value)
+    }
+
+    fn point(&mut self, x: &str, y: &str, z: &str) -> Option<Point3<f32>> {
+        Some(Point3::new(self.f32(x)?, self.f32(y)?, self.f32(z)?))
+    }
+
+    /// The warning for `Molecule::warnings`, if any field had a decimal comma.
+    fn warning(&self, format: &str) -> Option<String> {
+        (self.comma_fields > 0).then(|| {
+            format!(
+                "{format}: read {} numbers with a decimal comma",
+                self.comma_fields
+            )
+        })
+    }
+}
+
+/// `s.parse::<f32>()`, with a fast path for plain decimals such as "-1.2345".
+///
+/// When the digits, read as an integer, are at most 2^24 and there are at most
+/// 10 decimals, both that integer and the power of ten are exact in `f32`, so
+/// one correctly rounded division gives exactly what `parse` would.
+fn parse_f32(s: &str) -> Option<f32> {
+    const POW10: [f32; 11] = [1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10];
+    let (negative, digits) = match s.as_bytes() {
+        [b'-', rest @ ..] => (true, rest),
+        bytes => (false, bytes),
+    };
+    let mut mantissa: u32 = 0;
+    let mut any_digit = false;
+    let mut decimals = None;
+    for (i, &b) in digits.iter().enumerate() {
+        match b {
+            b'0'..=b'9' => {
+                mantissa = mantissa * 10 + u32::from(b - b'0');
+                any_digit = true;
+                if mantissa > 1 << 24 {
+                    return s.parse().ok();
+                }
+            }
+            b'.' if decimals.is_none() => decimals = Some(digits.len() - i - 1),
+            _ => return s.parse().ok(),
+        }
+    }
+    let decimals = decimals.unwrap_or(0);
+    if !any_digit || decimals >= POW10.len() {
+        return s.parse().ok();
+    }
+    let value = mantissa as f32 / POW10[decimals];
+    Some(if negative { -value } else { value })
+}
+
+/// Element of a .gro atom: the first letter of its name, past any leading
+/// digits ("1HB"), except for single-atom residues such as ions ("NA", "CL"),
+/// where the residue name is the element.
+fn gro_element(residue: &str, name: &str) -> String {
+    let letters: String = name
+        .chars()
+        .skip_while(|c| c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect::<String>()
+        .to_uppercase();
+    if letters.len() == 2
+        && letters.eq_ignore_ascii_case(residue)
+        && element::period(&letters).is_some()
+    {
+        return letters;
     }
+    letters.chars().take(1).collect()
 }
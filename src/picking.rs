@@ -0,0 +1,291 @@
+//! Ray intersection tests, shared by `MoleculeViewer::pick` and available to
+//! overlays that want their own geometry to be pickable.
+//!
+//! Every test takes a ray `origin` and direction `dir` and returns the first
+//! surface hit at `t >= 0`. `dir` need not be unit length; `t` is measured in
+//! multiples of it. A ray starting inside a solid hits its surface on the way out.
+
+use nalgebra::{Point3, Vector3};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    /// Ray parameter: the hit is at `origin + t * dir`.
+    pub t: f32,
+    pub point: Point3<f32>,
+    /// Unit surface normal at `point`, pointing out of the solid. For triangles,
+    /// the face normal on the side the ray comes from.
+    pub normal: Vector3<f32>,
+}
+
+impl Hit {
+    fn at(origin: Point3<f32>, dir: Vector3<f32>, t: f32, normal: Vector3<f32>) -> Self {
+        Self {
+            t,
+            point: origin + dir * t,
+            normal: normal.normalize(),
+        }
+    }
+}
+
+/// Sphere of `radius` around `center`.
+pub fn ray_sphere(
+    origin: Point3<f32>,
+    dir: Vector3<f32>,
+    center: Point3<f32>,
+    radius: f32,
+) -> Option<Hit> {
+    let (t_in, t_out) = sphere_roots(origin, dir, center, radius)?;
+    let t = first_non_negative([t_in, t_out])?;
+    Some(Hit::at(origin, dir, t, origin + dir * t - center))
+}
+
+/// Solid cylinder of `radius` around the segment from `a` to `b`, with flat caps.
+pub fn ray_cylinder(
+    origin: Point3<f32>,
+    dir: Vector3<f32>,
+    a: Point3<f32>,
+    b: Point3<f32>,
+    radius: f32,
+) -> Option<Hit> {
+    let axis = Axis::new(a, b)?;
+    let mut best: Option<Hit> = None;
+    if let Some(roots) = axis.body_roots(origin, dir, radius) {
+        for t in roots {
+            let y = axis.height(origin + dir * t);
+            if (0.0..=axis.length).contains(&y) {
+                keep_first(&mut best, origin, dir, t, axis.radial(origin + dir * t));
+            }
+        }
+    }
+    let dy = dir.dot(&axis.unit);
+    if dy != 0.0 {
+        let oy = axis.height(origin);
+        for (height, normal) in [(0.0, -axis.unit), (axis.length, axis.unit)] {
+            let t = (height - oy) / dy;
+            if axis.radial(origin + dir * t).norm_squared() <= radius * radius {
+                keep_first(&mut best, origin, dir, t, normal);
+            }
+        }
+    }
+    best
+}
+
+/// Capsule: the points within `radius` of the segment from `a` to `b`, i.e. a
+/// cylinder with hemispherical caps.
+pub fn ray_capsule(
+    origin: Point3<f32>,
+    dir: Vector3<f32>,
+    a: Point3<f32>,
+    b: Point3<f32>,
+    radius: f32,
+) -> Option<Hit> {
+    let Some(axis) = Axis::new(a, b) else {
+        return ray_sphere(origin, dir, a, radius);
+    };
+    let mut best: Option<Hit> = None;
+    if let Some(roots) = axis.body_roots(origin, dir, radius) {
+        for t in roots {
+            let y = axis.height(origin + dir * t);
+            if (0.0..=axis.length).contains(&y) {
+                keep_first(&mut best, origin, dir, t, axis.radial(origin + dir * t));
+            }
+        }
+    }
+    // Only the outer half of each end sphere is capsule surface; the inner half
+    // lies inside the body.
+    for (center, outer) in [(a, -1.0), (b, 1.0)] {
+        let Some((t_in, t_out)) = sphere_roots(origin, dir, center, radius) else {
+            continue;
+        };
+        for t in [t_in, t_out] {
+            let point = origin + dir * t;
+            if outer * (point - center).dot(&axis.unit) >= 0.0 {
+                keep_first(&mut best, origin, dir, t, point - center);
+            }
+        }
+    }
+    best
+}
+
+/// Axis-aligned box from `min` to `max`.
+pub fn ray_aabb(
+    origin: Point3<f32>,
+    dir: Vector3<f32>,
+    min: Point3<f32>,
+    max: Point3<f32>,
+) -> Option<Hit> {
+    let (mut t_near, mut t_far) = (f32::NEG_INFINITY, f32::INFINITY);
+    let (mut near_axis, mut far_axis) = (0, 0);
+    for i in 0..3 {
+        if dir[i] == 0.0 {
+            if origin[i] < min[i] || origin[i] > max[i] {
+                return None;
+            }
+            continue;
+        }
+        let (t0, t1) = ((min[i] - origin[i]) / dir[i], (max[i] - origin[i]) / dir[i]);
+        let (t0, t1) = (t0.min(t1), t0.max(t1));
+        if t0 > t_near {
+            t_near = t0;
+            near_axis = i;
+        }
+        if t1 < t_far {
+            t_far = t1;
+            far_axis = i;
+        }
+    }
+    if t_near > t_far || t_far < 0.0 {
+        return None;
+    }
+    // Entering, the normal opposes the ray; leaving, it follows it.
+    let (t, axis, sign) = if t_near >= 0.0 {
+        (t_near, near_axis, -dir[near_axis].signum())
+    } else {
+        (t_far, far_axis, dir[far_axis].signum())
+    };
+    let mut normal = Vector3::zeros();
+    normal[axis] = sign;
+    Some(Hit::at(origin, dir, t, normal))
+}
+
+/// Triangle `v0 v1 v2`, hit from either side.
+pub fn ray_triangle(
+    origin: Point3<f32>,
+    dir: Vector3<f32>,
+    v0: Point3<f32>,
+    v1: Point3<f32>,
+    v2: Point3<f32>,
+) -> Option<Hit> {
+    // Möller-Trumbore.
+    let (e1, e2) = (v1 - v0, v2 - v0);
+    let p = dir.cross(&e2);
+    let det = e1.dot(&p);
+    if det.abs() < f32::EPSILON * e1.norm() * e2.norm() * dir.norm() {
+        return None;
+    }
+    let s = origin - v0;
+    let u = s.dot(&p) / det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(&e1);
+    let v = dir.dot(&q) / det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = e2.dot(&q) / det;
+    if t < 0.0 {
+        return None;
+    }
+    let normal = e1.cross(&e2);
+    let normal = if normal.dot(&dir) > 0.0 {
+        -normal
+    } else {
+        normal
+    };
+    Some(Hit::at(origin, dir, t, normal))
+}
+
+/// Infinite plane through `point` with normal `normal`, hit from either side.
+pub fn ray_plane(
+    origin: Point3<f32>,
+    dir: Vector3<f32>,
+    point: Point3<f32>,
+    normal: Vector3<f32>,
+) -> Option<Hit> {
+    let cos = dir.dot(&normal);
+    if cos.abs() < f32::EPSILON * dir.norm() * normal.norm() {
+        return None;
+    }
+    let t = (point - origin).dot(&normal) / cos;
+    if t < 0.0 {
+        return None;
+    }
+    let normal = if cos > 0.0 { -normal } else { normal };
+    Some(Hit::at(origin, dir, t, normal))
+}
+
+/// Entry and exit parameters of the ray's line through the sphere.
+fn sphere_roots(
+    origin: Point3<f32>,
+    dir: Vector3<f32>,
+    center: Point3<f32>,
+    radius: f32,
+) -> Option<(f32, f32)> {
+    let a = dir.norm_squared();
+    if a == 0.0 || radius <= 0.0 {
+        return None;
+    }
+    let oc = origin - center;
+    let b = oc.dot(&dir);
+    let c = oc.norm_squared() - radius * radius;
+    let disc = b * b - a * c;
+    if disc < 0.0 {
+        return None;
+    }
+    let root = disc.sqrt();
+    Some(((-b - root) / a, (-b + root) / a))
+}
+
+fn first_non_negative(ts: [f32; 2]) -> Option<f32> {
+    ts.into_iter().filter(|t| *t >= 0.0).reduce(f32::min)
+}
+
+fn keep_first(
+    best: &mut Option<Hit>,
+    origin: Point3<f32>,
+    dir: Vector3<f32>,
+    t: f32,
+    normal: Vector3<f32>,
+) {
+    if t >= 0.0 && best.is_none_or(|hit| t < hit.t) {
+        *best = Some(Hit::at(origin, dir, t, normal));
+    }
+}
+
+/// Segment from `start` along `unit` for `length`.
+struct Axis {
+    start: Point3<f32>,
+    unit: Vector3<f32>,
+    length: f32,
+}
+
+impl Axis {
+    fn new(a: Point3<f32>, b: Point3<f32>) -> Option<Self> {
+        let length = (b - a).norm();
+        let unit = (b - a).try_normalize(1e-6)?;
+        Some(Self {
+            start: a,
+            unit,
+            length,
+        })
+    }
+
+    fn height(&self, p: Point3<f32>) -> f32 {
+        (p - self.start).dot(&self.unit)
+    }
+
+    /// Component of `p - start` perpendicular to the axis.
+    fn radial(&self, p: Point3<f32>) -> Vector3<f32> {
+        let v = p - self.start;
+        v - self.unit * v.dot(&self.unit)
+    }
+
+    /// Where the ray's line crosses the infinite cylinder of `radius`.
+    fn body_roots(&self, origin: Point3<f32>, dir: Vector3<f32>, radius: f32) -> Option<[f32; 2]> {
+        let d = dir - self.unit * dir.dot(&self.unit);
+        let o = self.radial(origin);
+        let a = d.norm_squared();
+        if a < 1e-12 {
+            return None;
+        }
+        let b = o.dot(&d);
+        let c = o.norm_squared() - radius * radius;
+        let disc = b * b - a * c;
+        if disc < 0.0 {
+            return None;
+        }
+        let root = disc.sqrt();
+        Some([(-b - root) / a, (-b + root) / a])
+    }
+}
@@ -0,0 +1,396 @@
+//! A CPU ray caster for `DisplayList`s, for images made without a GPU or a
+//! window: camera path frames (`MoleculeViewer::render_path`), capture
+//! screenshots and the `batch_thumbnails` example.
+//!
+//! Spheres and cylinders are intersected exactly and lit by one light above
+//! and left of the camera. Opacity is ignored; ghosted atoms come out solid.
+//! `render_supersampled` smooths the jagged outlines of a one-ray-per-pixel
+//! image, casting in bands of rows to stay within a memory budget.
+
+use crate::camera::{Camera, ProjectionType, ViewBookmark};
+use crate::colormap::Rgb;
+use crate::display_list::DisplayList;
+use crate::picking::{self, Hit};
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+use std::f32::consts::FRAC_PI_2;
+use std::ops::Range;
+use std::path::Path;
+
+/// Background of the images the crate renders itself.
+pub const BACKGROUND: Rgb = (1.0, 1.0, 1.0);
+
+/// Largest `Supersample::factor`.
+pub const MAX_SUPERSAMPLE: usize = 4;
+
+/// How `render_supersampled` casts an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Supersample {
+    /// Rays per pixel along each axis, 1 to `MAX_SUPERSAMPLE`; each pixel is
+    /// the mean of its `factor` x `factor` rays.
+    pub factor: usize,
+    /// Bytes the supersampled rows being cast may take. A larger image is
+    /// cast in bands of rows, with the same result.
+    pub memory: usize,
+}
+
+impl Default for Supersample {
+    /// One ray per pixel, within 256 MB.
+    fn default() -> Self {
+        Self {
+            factor: 1,
+            memory: 256 << 20,
+        }
+    }
+}
+
+/// How `render` projects the view onto the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lens {
+    /// Vertical field of view in radians.
+    Perspective { fov_y: f32 },
+    /// Visible height in world units.
+    Orthographic { height: f32 },
+}
+
+impl Default for Lens {
+    /// The 45° perspective of `OrbitalCamera::default`.
+    fn default() -> Self {
+        Self::Perspective {
+            fov_y: 45.0f32.to_radians(),
+        }
+    }
+}
+
+impl Lens {
+    /// The projection `camera` uses now.
+    pub fn of<C: Camera + ?Sized>(camera: &C) -> Self {
+        match camera.projection() {
+            ProjectionType::Perspective => Self::Perspective {
+                fov_y: camera.fov_y(),
+            },
+            ProjectionType::Orthographic => Self::Orthographic {
+                height: camera.ortho_scale(),
+            },
+        }
+    }
+}
+
+/// An RGB image, rows top to bottom.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Rgb>,
+}
+
+impl Image {
+    pub fn encode_png(&self) -> Vec<u8> {
+        encode_png(&self.pixels, self.width, self.height)
+    }
+
+    pub fn write_png(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, self.encode_png()).map_err(|e| format!("{}: {e}", path.display()))
+    }
+}
+
+/// Casts `list` as seen from `view` through `lens` into a `width` by
+/// `height` image. Orthographic rays start on the plane through the eye, so
+/// what is behind the eye is not drawn, as with a near plane there.
+pub fn render(
+    list: &DisplayList,
+    view: &ViewBookmark,
+    lens: Lens,
+    width: usize,
+    height: usize,
+    background: Rgb,
+) -> Image {
+    let projector = Projector::new(view, lens, width, height);
+    cast(list, &projector, 0..height, background).image
+}
+
+/// `render` with `supersample.factor` squared rays per pixel, box filtered
+/// down. Fails for a factor out of range, or if even one row of pixels is
+/// over the memory budget.
+pub fn render_supersampled(
+    list: &DisplayList,
+    view: &ViewBookmark,
+    lens: Lens,
+    width: usize,
+    height: usize,
+    background: Rgb,
+    supersample: Supersample,
+) -> Result<Image, String> {
+    let factor = supersample.factor;
+    if !(1..=MAX_SUPERSAMPLE).contains(&factor) {
+        return Err(format!(
+            "supersample: factor {factor} is not 1 to {MAX_SUPERSAMPLE}"
+        ));
+    }
+    let projector = Projector::new(view, lens, width * factor, height * factor);
+    // Rows per band: whole blocks of supersampled rows that fit.
+    let row_bytes = (width * factor * factor * Canvas::PIXEL_BYTES).max(1);
+    let band = (supersample.memory / row_bytes).min(height);
+    if band == 0 && height > 0 {
+        return Err(format!(
+            "supersample {factor}: one row of {width} pixels needs {} MB, over the budget",
+            row_bytes.div_ceil(1 << 20)
+        ));
+    }
+    let mut pixels = Vec::with_capacity(width * height);
+    for start in (0..height).step_by(band.max(1)) {
+        let end = (start + band).min(height);
+        let canvas = cast(list, &projector, start * factor..end * factor, background);
+        pixels.extend(downsample(&canvas.image, factor));
+    }
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// Casts rows `rows` of `projector`'s image.
+fn cast(list: &DisplayList, projector: &Projector, rows: Range<usize>, background: Rgb) -> Canvas {
+    let n = projector.width * rows.len();
+    let mut canvas = Canvas {
+        depth: vec![f32::INFINITY; n],
+        image: Image {
+            width: projector.width,
+            height: rows.len(),
+            pixels: vec![background; n],
+        },
+        rows,
+    };
+    for s in &list.spheres {
+        let bounds = projector.pixel_box(s.center, s.radius);
+        canvas.draw(projector, bounds, s.color, s.shininess, |o, d| {
+            picking::ray_sphere(o, d, s.center, s.radius)
+        });
+    }
+    for c in &list.cylinders {
+        let middle = nalgebra::center(&c.start, &c.end);
+        let reach = (c.end - c.start).norm() / 2.0 + c.radius;
+        let bounds = projector.pixel_box(middle, reach);
+        canvas.draw(projector, bounds, c.color, c.shininess, |o, d| {
+            picking::ray_cylinder(o, d, c.start, c.end, c.radius)
+        });
+    }
+    canvas
+}
+
+/// `image` shrunk `factor` times, each pixel the mean of a `factor` x
+/// `factor` block.
+fn downsample(image: &Image, factor: usize) -> Vec<Rgb> {
+    let (width, height) = (image.width / factor, image.height / factor);
+    let weight = 1.0 / (factor * factor) as f32;
+    let mut out = Vec::with_capacity(width * height);
+    for row in 0..height {
+        for col in 0..width {
+            let mut sum = (0.0, 0.0, 0.0);
+            for dy in 0..factor {
+                let start = (row * factor + dy) * image.width + col * factor;
+                for &(r, g, b) in &image.pixels[start..start + factor] {
+                    sum = (sum.0 + r, sum.1 + g, sum.2 + b);
+                }
+            }
+            out.push((sum.0 * weight, sum.1 * weight, sum.2 * weight));
+        }
+    }
+    out
+}
+
+/// Pixel rays of a view.
+struct Projector {
+    eye: Point3<f32>,
+    rotation: UnitQuaternion<f32>,
+    lens: Lens,
+    /// Half the image's width and height: tangents of the half angles for a
+    /// perspective lens, world lengths for an orthographic one.
+    half_width: f32,
+    half_height: f32,
+    width: usize,
+    height: usize,
+}
+
+impl Projector {
+    fn new(view: &ViewBookmark, lens: Lens, width: usize, height: usize) -> Self {
+        let half_height = match lens {
+            Lens::Perspective { fov_y } => (fov_y / 2.0).tan(),
+            Lens::Orthographic { height } => height / 2.0,
+        };
+        Self {
+            eye: view.position(),
+            rotation: view.rotation,
+            lens,
+            half_width: half_height * width as f32 / height.max(1) as f32,
+            half_height,
+            width,
+            height,
+        }
+    }
+
+    /// Ray through the center of pixel (`col`, `row`).
+    fn ray(&self, col: usize, row: usize) -> (Point3<f32>, Vector3<f32>) {
+        let u = ((col as f32 + 0.5) / self.width as f32 * 2.0 - 1.0) * self.half_width;
+        let v = (1.0 - (row as f32 + 0.5) / self.height as f32 * 2.0) * self.half_height;
+        match self.lens {
+            Lens::Perspective { .. } => (
+                self.eye,
+                self.rotation * Vector3::new(u, v, -1.0).normalize(),
+            ),
+            Lens::Orthographic { .. } => (
+                self.eye + self.rotation * Vector3::new(u, v, 0.0),
+                self.rotation * -Vector3::z(),
+            ),
+        }
+    }
+
+    /// Columns and rows whose rays might meet the ball of `radius` about
+    /// `center`.
+    fn pixel_box(&self, center: Point3<f32>, radius: f32) -> (Range<usize>, Range<usize>) {
+        let c = self.rotation.inverse() * (center - self.eye);
+        let (x, y) = match self.lens {
+            Lens::Perspective { .. } => (slopes(c.x, -c.z, radius), slopes(c.y, -c.z, radius)),
+            Lens::Orthographic { .. } => {
+                ((c.x - radius, c.x + radius), (c.y - radius, c.y + radius))
+            }
+        };
+        let pixel = |t: f32, size: usize| (t.max(0.0) as usize).min(size);
+        let col = |u: f32| (u / self.half_width + 1.0) / 2.0 * self.width as f32;
+        let row = |v: f32| (1.0 - v / self.half_height) / 2.0 * self.height as f32;
+        (
+            pixel(col(x.0).floor(), self.width)..pixel(col(x.1).ceil(), self.width),
+            pixel(row(y.1).floor(), self.height)..pixel(row(y.0).ceil(), self.height),
+        )
+    }
+}
+
+/// Least and greatest `x / depth` over the disk of `radius` about
+/// (`x`, `depth`): the slopes of its tangents through the eye. Unbounded
+/// where the disk reaches the eye's plane.
+fn slopes(x: f32, depth: f32, radius: f32) -> (f32, f32) {
+    let distance = x.hypot(depth);
+    if distance <= radius {
+        return (f32::NEG_INFINITY, f32::INFINITY);
+    }
+    let angle = x.atan2(depth);
+    let spread = (radius / distance).asin();
+    let slope = |a: f32| {
+        if a.abs() < FRAC_PI_2 {
+            a.tan()
+        } else {
+            a.signum() * f32::INFINITY
+        }
+    };
+    (slope(angle - spread), slope(angle + spread))
+}
+
+/// A band of rows of the image being cast, with the nearest hit per pixel.
+struct Canvas {
+    depth: Vec<f32>,
+    image: Image,
+    /// Rows of the whole image this band holds.
+    rows: Range<usize>,
+}
+
+impl Canvas {
+    /// Bytes per pixel held while casting.
+    const PIXEL_BYTES: usize = std::mem::size_of::<f32>() + std::mem::size_of::<Rgb>();
+
+    fn draw(
+        &mut self,
+        projector: &Projector,
+        (cols, rows): (Range<usize>, Range<usize>),
+        color: Rgb,
+        shininess: f32,
+        intersect: impl Fn(Point3<f32>, Vector3<f32>) -> Option<Hit>,
+    ) {
+        let to_view = projector.rotation.inverse();
+        for row in rows.start.max(self.rows.start)..rows.end.min(self.rows.end) {
+            for col in cols.clone() {
+                let (origin, dir) = projector.ray(col, row);
+                let Some(hit) = intersect(origin, dir) else {
+                    continue;
+                };
+                let i = (row - self.rows.start) * self.image.width + col;
+                if hit.t < self.depth[i] {
+                    self.depth[i] = hit.t;
+                    self.image.pixels[i] = shade(color, shininess, to_view * hit.normal);
+                }
+            }
+        }
+    }
+}
+
+/// Lambert plus Blinn-Phong from a light above and left of the camera, for
+/// a `normal` in view space (the camera looking down -Z).
+pub fn shade(color: Rgb, shininess: f32, normal: Vector3<f32>) -> Rgb {
+    let light = Vector3::new(-0.4, 0.5, 1.0).normalize();
+    let half = (light + Vector3::z()).normalize();
+    let diffuse = 0.25 + 0.75 * normal.dot(&light).max(0.0);
+    let specular = 0.3 * normal.dot(&half).max(0.0).powf(shininess.max(1.0));
+    let (r, g, b) = color;
+    let channel = |c: f32| (c * diffuse + specular).min(1.0);
+    (channel(r), channel(g), channel(b))
+}
+
+/// 8-bit RGB PNG of `pixels`, rows top to bottom, with stored (uncompressed)
+/// deflate blocks.
+pub fn encode_png(pixels: &[Rgb], width: usize, height: usize) -> Vec<u8> {
+    let mut raw = Vec::with_capacity(height * (3 * width + 1));
+    for row in pixels.chunks(width) {
+        raw.push(0); // filter: none
+        for &(r, g, b) in row {
+            raw.extend([r, g, b].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8));
+        }
+    }
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(0xffff).peekable();
+    while let Some(block) = blocks.next() {
+        zlib.push(blocks.peek().is_none() as u8);
+        let len = block.len() as u16;
+        zlib.extend(len.to_le_bytes());
+        zlib.extend((!len).to_le_bytes());
+        zlib.extend(block);
+    }
+    zlib.extend(adler32(&raw).to_be_bytes());
+
+    let mut header = Vec::new();
+    header.extend((width as u32).to_be_bytes());
+    header.extend((height as u32).to_be_bytes());
+    header.extend([8, 2, 0, 0, 0]); // 8-bit truecolor, no interlace
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    for (kind, data) in [(b"IHDR", header), (b"IDAT", zlib), (b"IEND", Vec::new())] {
+        png.extend((data.len() as u32).to_be_bytes());
+        let start = png.len();
+        png.extend(kind);
+        png.extend(data);
+        let crc = crc32(&png[start..]);
+        png.extend(crc.to_be_bytes());
+    }
+    png
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
@@ -0,0 +1,180 @@
+//! Ring perception and ring conformation analysis (planarity, Cremer-Pople
+//! puckering coordinates).
+
+use crate::molecule::Molecule;
+use nalgebra::{Matrix3, Point3, Vector3};
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+/// Largest ring `find_rings` looks for.
+pub const MAX_RING_SIZE: usize = 8;
+
+/// Cremer-Pople puckering coordinates (Cremer & Pople, JACS 1975, 97, 1354).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CremerPople {
+    /// Total puckering amplitude Q, in Å.
+    pub q: f32,
+    /// Phase angle φ₂ in radians, in `[0, 2π)`.
+    pub phi: f32,
+    /// Polar angle θ in radians, in `[0, π]`, for 6-membered rings only:
+    /// 0 or π for a chair, π/2 for boat and twist-boat forms.
+    pub theta: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RingAnalysis {
+    /// Ring atoms in bonded order.
+    pub atoms: Vec<usize>,
+    /// RMS deviation from the best-fit plane, in Å.
+    pub planarity: f32,
+    /// `None` for rings other than 5- and 6-membered.
+    pub pucker: Option<CremerPople>,
+}
+
+impl RingAnalysis {
+    pub fn size(&self) -> usize {
+        self.atoms.len()
+    }
+}
+
+impl Molecule {
+    /// Smallest ring through each bond, up to `MAX_RING_SIZE` atoms, without
+    /// duplicates. Each ring lists its atoms in bonded order.
+    pub fn find_rings(&self) -> Vec<Vec<usize>> {
+        let adjacency = self.adjacency();
+        let mut rings: Vec<Vec<usize>> = Vec::new();
+        let mut seen: Vec<Vec<usize>> = Vec::new();
+        for bond in &self.bonds {
+            let (a, b) = (bond.atom_a, bond.atom_b);
+            if a >= adjacency.len() || b >= adjacency.len() || a == b {
+                continue;
+            }
+            let Some(ring) = shortest_path_avoiding(&adjacency, a, b, MAX_RING_SIZE) else {
+                continue;
+            };
+            let mut key = ring.clone();
+            key.sort_unstable();
+            if !seen.contains(&key) {
+                seen.push(key);
+                rings.push(ring);
+            }
+        }
+        rings
+    }
+
+    /// RMS distance of the ring atoms from their least-squares plane, in Å.
+    /// `None` for fewer than three atoms or an index outside the molecule.
+    pub fn ring_planarity(&self, ring: &[usize]) -> Option<f32> {
+        let centered = self.centered_ring(ring)?;
+        let covariance: Matrix3<f32> = centered.iter().map(|d| d * d.transpose()).sum();
+        // The smallest singular value is the squared-deviation sum along the normal.
+        let smallest = covariance.svd(false, false).singular_values.min().max(0.0);
+        Some((smallest / ring.len() as f32).sqrt())
+    }
+
+    /// Puckering coordinates of a 5- or 6-membered ring given in bonded order.
+    /// `None` for other ring sizes or an index outside the molecule.
+    pub fn cremer_pople(&self, ring: &[usize]) -> Option<CremerPople> {
+        let n = ring.len();
+        if n != 5 && n != 6 {
+            return None;
+        }
+        let centered = self.centered_ring(ring)?;
+        let angle = |j: usize, m: usize| 2.0 * PI * (m * j) as f32 / n as f32;
+
+        let (mut r1, mut r2) = (Vector3::zeros(), Vector3::zeros());
+        for (j, d) in centered.iter().enumerate() {
+            r1 += d * angle(j, 1).sin();
+            r2 += d * angle(j, 1).cos();
+        }
+        let normal = r1.cross(&r2).try_normalize(1e-12)?;
+        let z: Vec<f32> = centered.iter().map(|d| d.dot(&normal)).collect();
+
+        let norm = (2.0 / n as f32).sqrt();
+        let (mut c, mut s) = (0.0, 0.0);
+        for (j, zj) in z.iter().enumerate() {
+            c += zj * angle(j, 2).cos();
+            s -= zj * angle(j, 2).sin();
+        }
+        let (q2_cos, q2_sin) = (norm * c, norm * s);
+        let q2 = q2_cos.hypot(q2_sin);
+        let phi = q2_sin.atan2(q2_cos).rem_euclid(2.0 * PI);
+        let q = z.iter().map(|zj| zj * zj).sum::<f32>().sqrt();
+
+        let theta = (n == 6).then(|| {
+            let q3: f32 = z
+                .iter()
+                .enumerate()
+                .map(|(j, zj)| if j % 2 == 0 { *zj } else { -zj })
+                .sum::<f32>()
+                / (n as f32).sqrt();
+            q2.atan2(q3)
+        });
+        Some(CremerPople { q, phi, theta })
+    }
+
+    /// Size, planarity and pucker of every ring found by `find_rings`.
+    pub fn analyze_rings(&self) -> Vec<RingAnalysis> {
+        self.find_rings()
+            .into_iter()
+            .filter_map(|atoms| {
+                let planarity = self.ring_planarity(&atoms)?;
+                let pucker = self.cremer_pople(&atoms);
+                Some(RingAnalysis {
+                    atoms,
+                    planarity,
+                    pucker,
+                })
+            })
+            .collect()
+    }
+
+    fn centered_ring(&self, ring: &[usize]) -> Option<Vec<Vector3<f32>>> {
+        if ring.len() < 3 {
+            return None;
+        }
+        let positions: Vec<Point3<f32>> = ring
+            .iter()
+            .map(|&i| self.atoms.get(i).map(|a| a.position))
+            .collect::<Option<_>>()?;
+        let centroid = positions.iter().map(|p| p.coords).sum::<Vector3<f32>>() / ring.len() as f32;
+        Some(positions.iter().map(|p| p.coords - centroid).collect())
+    }
+}
+
+/// Shortest path from `start` to `end` that does not use the direct bond
+/// between them, as a list of atoms, if one exists within `max_len` atoms.
+fn shortest_path_avoiding(
+    adjacency: &[Vec<usize>],
+    start: usize,
+    end: usize,
+    max_len: usize,
+) -> Option<Vec<usize>> {
+    let mut parent = vec![usize::MAX; adjacency.len()];
+    let mut depth = vec![0usize; adjacency.len()];
+    parent[start] = start;
+    let mut queue = VecDeque::from([start]);
+    while let Some(i) = queue.pop_front() {
+        if depth[i] + 1 >= max_len {
+            continue;
+        }
+        for &j in &adjacency[i] {
+            if (i == start && j == end) || parent[j] != usize::MAX {
+                continue;
+            }
+            parent[j] = i;
+            depth[j] = depth[i] + 1;
+            if j == end {
+                let mut path = vec![end];
+                let mut k = end;
+                while k != start {
+                    k = parent[k];
+                    path.push(k);
+                }
+                return Some(path);
+            }
+            queue.push_back(j);
+        }
+    }
+    None
+}
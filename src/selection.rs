@@ -0,0 +1,247 @@
+//! Atom and bond selections and the set operations used to grow and shrink
+//! them. The set operations work on atoms and leave the bonds alone.
+//!
+//! Every operation returns how many atoms were added to or removed from the
+//! selection, so callers can skip the redraw when nothing changed.
+
+use crate::molecule::Molecule;
+use crate::spatial::NeighborGrid;
+
+/// Sets of selected atom and bond indices, each kept sorted and free of
+/// duplicates.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selection {
+    atoms: Vec<usize>,
+    bonds: Vec<usize>,
+}
+
+impl Selection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_atoms<I: IntoIterator<Item = usize>>(atoms: I) -> Self {
+        let mut selection = Self::new();
+        selection.extend(atoms);
+        selection
+    }
+
+    /// Selected atom indices in ascending order.
+    pub fn atoms(&self) -> &[usize] {
+        &self.atoms
+    }
+
+    /// Selected bond indices in ascending order.
+    pub fn bonds(&self) -> &[usize] {
+        &self.bonds
+    }
+
+    /// Number of selected atoms.
+    pub fn len(&self) -> usize {
+        self.atoms.len()
+    }
+
+    /// Whether no atom and no bond is selected.
+    pub fn is_empty(&self) -> bool {
+        self.atoms.is_empty() && self.bonds.is_empty()
+    }
+
+    pub fn contains(&self, atom_idx: usize) -> bool {
+        self.atoms.binary_search(&atom_idx).is_ok()
+    }
+
+    /// Returns `true` if the atom was not selected before.
+    pub fn insert(&mut self, atom_idx: usize) -> bool {
+        insert_sorted(&mut self.atoms, atom_idx)
+    }
+
+    /// Returns `true` if the atom was selected.
+    pub fn remove(&mut self, atom_idx: usize) -> bool {
+        remove_sorted(&mut self.atoms, atom_idx)
+    }
+
+    /// Returns `true` if the atom is selected afterwards.
+    pub fn toggle(&mut self, atom_idx: usize) -> bool {
+        if self.remove(atom_idx) {
+            false
+        } else {
+            self.insert(atom_idx)
+        }
+    }
+
+    pub fn contains_bond(&self, bond_idx: usize) -> bool {
+        self.bonds.binary_search(&bond_idx).is_ok()
+    }
+
+    /// Returns `true` if the bond was not selected before.
+    pub fn insert_bond(&mut self, bond_idx: usize) -> bool {
+        insert_sorted(&mut self.bonds, bond_idx)
+    }
+
+    /// Returns `true` if the bond was selected.
+    pub fn remove_bond(&mut self, bond_idx: usize) -> bool {
+        remove_sorted(&mut self.bonds, bond_idx)
+    }
+
+    /// Returns `true` if the bond is selected afterwards.
+    pub fn toggle_bond(&mut self, bond_idx: usize) -> bool {
+        if self.remove_bond(bond_idx) {
+            false
+        } else {
+            self.insert_bond(bond_idx)
+        }
+    }
+
+    /// Deselects every atom and bond, returning how many there were.
+    pub fn clear(&mut self) -> usize {
+        let removed = self.atoms.len() + self.bonds.len();
+        self.atoms.clear();
+        self.bonds.clear();
+        removed
+    }
+
+    /// Adds `atoms`, returning how many were not already selected.
+    pub fn extend<I: IntoIterator<Item = usize>>(&mut self, atoms: I) -> usize {
+        let before = self.atoms.len();
+        self.atoms.extend(atoms);
+        self.atoms.sort_unstable();
+        self.atoms.dedup();
+        self.atoms.len() - before
+    }
+
+    /// Adds every atom within `n_shells` bonds of the selection.
+    pub fn expand_bonded(&mut self, molecule: &Molecule, n_shells: usize) -> usize {
+        let adjacency = molecule.adjacency();
+        let mut seen = vec![false; adjacency.len()];
+        let mut frontier: Vec<usize> = self
+            .atoms
+            .iter()
+            .copied()
+            .filter(|&i| i < seen.len())
+            .collect();
+        for &i in &frontier {
+            seen[i] = true;
+        }
+        let mut added = Vec::new();
+        for _ in 0..n_shells {
+            let mut next = Vec::new();
+            for i in frontier {
+                for &j in &adjacency[i] {
+                    if !seen[j] {
+                        seen[j] = true;
+                        next.push(j);
+                    }
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            added.extend_from_slice(&next);
+            frontier = next;
+        }
+        self.extend(added)
+    }
+
+    /// Adds every atom whose center lies within `radius` Å of a selected atom's center.
+    pub fn expand_radius(&mut self, molecule: &Molecule, radius: f32) -> usize {
+        if self.atoms.is_empty() || radius <= 0.0 {
+            return 0;
+        }
+        let positions: Vec<_> = molecule.atoms.iter().map(|a| a.position).collect();
+        let grid = NeighborGrid::new(&positions, radius);
+        let nearby: Vec<usize> = self
+            .atoms
+            .iter()
+            .filter_map(|&i| positions.get(i))
+            .flat_map(|&center| grid.within(center, radius))
+            .collect();
+        self.extend(nearby)
+    }
+
+    /// Selects exactly the atoms of `molecule` that were not selected. Indices
+    /// outside the molecule are dropped.
+    pub fn invert(&mut self, molecule: &Molecule) -> usize {
+        let inverted: Vec<usize> = (0..molecule.atoms.len())
+            .filter(|&i| !self.contains(i))
+            .collect();
+        let changed = self.atoms.len() + inverted.len();
+        self.atoms = inverted;
+        changed
+    }
+
+    /// Adds every atom with the same element as any selected atom.
+    pub fn select_same_element(&mut self, molecule: &Molecule) -> usize {
+        let mut elements: Vec<String> = self
+            .atoms
+            .iter()
+            .filter_map(|&i| molecule.atoms.get(i))
+            .map(|a| a.element.to_ascii_uppercase())
+            .collect();
+        elements.sort_unstable();
+        elements.dedup();
+        let matching: Vec<usize> = molecule
+            .atoms
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| {
+                elements
+                    .binary_search(&a.element.to_ascii_uppercase())
+                    .is_ok()
+            })
+            .map(|(i, _)| i)
+            .collect();
+        self.extend(matching)
+    }
+
+    /// Adds every atom in the same residue as any selected atom: the same
+    /// `Molecule::residues` number and, if the molecule names chains, the
+    /// same chain. Adds nothing if the molecule has no residues.
+    pub fn select_same_residue(&mut self, molecule: &Molecule) -> usize {
+        let Some(residues) = &molecule.residues else {
+            return 0;
+        };
+        let chains = molecule.chains.as_deref();
+        let residue_of = |i: usize| Some((chains.and_then(|c| c.get(i)), residues.get(i)?));
+        let mut selected: Vec<_> = self.atoms.iter().filter_map(|&i| residue_of(i)).collect();
+        selected.sort_unstable();
+        selected.dedup();
+        let matching: Vec<usize> = (0..molecule.atoms.len())
+            .filter(|&i| residue_of(i).is_some_and(|r| selected.binary_search(&r).is_ok()))
+            .collect();
+        self.extend(matching)
+    }
+
+    /// Keeps only atoms that are also in `atoms`.
+    pub fn intersect_with(&mut self, atoms: &[usize]) -> usize {
+        let before = self.atoms.len();
+        self.atoms.retain(|i| atoms.contains(i));
+        before - self.atoms.len()
+    }
+
+    /// Removes `atoms` from the selection.
+    pub fn subtract(&mut self, atoms: &[usize]) -> usize {
+        let before = self.atoms.len();
+        self.atoms.retain(|i| !atoms.contains(i));
+        before - self.atoms.len()
+    }
+}
+
+fn insert_sorted(list: &mut Vec<usize>, index: usize) -> bool {
+    match list.binary_search(&index) {
+        Ok(_) => false,
+        Err(pos) => {
+            list.insert(pos, index);
+            true
+        }
+    }
+}
+
+fn remove_sorted(list: &mut Vec<usize>, index: usize) -> bool {
+    match list.binary_search(&index) {
+        Ok(pos) => {
+            list.remove(pos);
+            true
+        }
+        Err(_) => false,
+    }
+}
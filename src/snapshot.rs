@@ -0,0 +1,91 @@
+//! Canonical text form of a built scene, for golden-file regression tests.
+//!
+//! The text lists what this crate decides (which entities exist, where, how big,
+//! what color) and leaves out what the `graphics` crate decides (mesh vertices),
+//! so snapshots only change when the viewer's output does.
+
+use crate::convert::quat_to_na;
+use crate::viewer::{EntityKind, MoleculeViewer};
+use crate::AdditionalRender;
+use graphics::Scene;
+use nalgebra::Vector3;
+use std::fmt::Write;
+
+/// One line per entity, in scene order (see `MoleculeViewer::update_scene`),
+/// after a header with the mesh count. Numbers are rounded to 4 decimals.
+///
+/// Orientation is written as the direction the mesh's +Y axis points, which is
+/// the bond axis for cylinders and does not depend on the quaternion's sign.
+pub fn scene_snapshot<U: AdditionalRender>(viewer: &MoleculeViewer<U>, scene: &Scene) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "meshes {}", scene.meshes.len());
+    for (i, entity) in scene.entities.iter().enumerate() {
+        let kind = match viewer.classify_entity(i) {
+            Some(EntityKind::Atom { molecule, index }) => format!("atom {molecule}:{index}"),
+            Some(EntityKind::Bond { molecule, index }) => format!("bond {molecule}:{index}"),
+            Some(EntityKind::Overlay { renderer_id }) => format!("overlay {renderer_id}"),
+            Some(EntityKind::ReferenceAxis) => "reference_axis".to_string(),
+            Some(EntityKind::Cutaway) => "cutaway".to_string(),
+            Some(EntityKind::PinMarker { index }) => format!("pin {index}"),
+            Some(EntityKind::SelectedAtom { index }) => format!("selected atom {index}"),
+            Some(EntityKind::SelectedBond { index }) => format!("selected bond {index}"),
+            None => "unknown".to_string(),
+        };
+        let axis = quat_to_na(entity.orientation) * Vector3::y();
+        let scale = match entity.scale_partial {
+            Some(s) => triple(s.x, s.y, s.z),
+            None => number(entity.scale),
+        };
+        let (r, g, b) = entity.color;
+        let _ = writeln!(
+            out,
+            "{kind} mesh={} pos={} axis={} scale={} color={} opacity={} shiny={}",
+            entity.mesh,
+            triple(entity.position.x, entity.position.y, entity.position.z),
+            triple(axis.x, axis.y, axis.z),
+            scale,
+            triple(r, g, b),
+            number(entity.opacity),
+            number(entity.shinyness),
+        );
+    }
+    out
+}
+
+/// Line-by-line differences between two snapshots, or `None` if they match.
+/// Each differing line is reported with its number as `-expected` / `+actual`.
+pub fn snapshot_diff(expected: &str, actual: &str) -> Option<String> {
+    let expected: Vec<_> = expected.lines().collect();
+    let actual: Vec<_> = actual.lines().collect();
+    let mut out = String::new();
+    for i in 0..expected.len().max(actual.len()) {
+        let (e, a) = (expected.get(i), actual.get(i));
+        if e == a {
+            continue;
+        }
+        if let Some(e) = e {
+            let _ = writeln!(out, "{:>4} -{e}", i + 1);
+        }
+        if let Some(a) = a {
+            let _ = writeln!(out, "{:>4} +{a}", i + 1);
+        }
+    }
+    (!out.is_empty()).then_some(out)
+}
+
+fn number(x: f32) -> String {
+    let s = format!("{x:.4}");
+    // Rounding can leave "-0.0000", which would differ from "0.0000" by sign alone.
+    if s.trim_start_matches('-')
+        .bytes()
+        .all(|c| c == b'0' || c == b'.')
+    {
+        s.trim_start_matches('-').to_string()
+    } else {
+        s
+    }
+}
+
+fn triple(x: f32, y: f32, z: f32) -> String {
+    format!("({},{},{})", number(x), number(y), number(z))
+}
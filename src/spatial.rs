@@ -0,0 +1,282 @@
+use nalgebra::{Point3, Vector3};
+use std::collections::HashMap;
+
+/// Uniform grid over atom positions for fixed-radius neighbor queries.
+///
+/// Queries with a radius up to `cell_size` only visit the 27 surrounding cells;
+/// larger radii still work but visit proportionally more cells.
+#[derive(Debug, Clone)]
+pub struct NeighborGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32, i32), Vec<usize>>,
+    positions: Vec<Point3<f32>>,
+}
+
+impl NeighborGrid {
+    pub fn new(positions: &[Point3<f32>], cell_size: f32) -> Self {
+        let cell_size = cell_size.max(1e-3);
+        let mut cells: HashMap<(i32, i32, i32), Vec<usize>> = HashMap::new();
+        for (i, p) in positions.iter().enumerate() {
+            cells
+                .entry(Self::cell_of(p, cell_size))
+                .or_default()
+                .push(i);
+        }
+        Self {
+            cell_size,
+            cells,
+            positions: positions.to_vec(),
+        }
+    }
+
+    fn cell_of(p: &Point3<f32>, cell_size: f32) -> (i32, i32, i32) {
+        (
+            (p.x / cell_size).floor() as i32,
+            (p.y / cell_size).floor() as i32,
+            (p.z / cell_size).floor() as i32,
+        )
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Calls `f(index, distance)` for every point within `radius` of `center`.
+    pub fn for_each_within<F: FnMut(usize, f32)>(
+        &self,
+        center: Point3<f32>,
+        radius: f32,
+        mut f: F,
+    ) {
+        let reach = (radius / self.cell_size).ceil() as i32;
+        let (cx, cy, cz) = Self::cell_of(&center, self.cell_size);
+        let r2 = radius * radius;
+        for x in cx - reach..=cx + reach {
+            for y in cy - reach..=cy + reach {
+                for z in cz - reach..=cz + reach {
+                    let Some(indices) = self.cells.get(&(x, y, z)) else {
+                        continue;
+                    };
+                    for &i in indices {
+                        let d2 = (self.positions[i] - center).norm_squared();
+                        if d2 <= r2 {
+                            f(i, d2.sqrt());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Calls `f(center, indices)` for every non-empty cell, where `center` is the
+    /// middle of the cell's cube. Lets callers cull whole cells against shapes
+    /// other than spheres.
+    pub fn for_each_cell<F: FnMut(Point3<f32>, &[usize])>(&self, mut f: F) {
+        for (&(x, y, z), indices) in &self.cells {
+            let center = Point3::new(x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5);
+            f(center * self.cell_size, indices);
+        }
+    }
+
+    /// Indices of all points within `radius` of `center`, in ascending order.
+    pub fn within(&self, center: Point3<f32>, radius: f32) -> Vec<usize> {
+        let mut result = Vec::new();
+        self.for_each_within(center, radius, |i, _| result.push(i));
+        result.sort_unstable();
+        result
+    }
+}
+
+/// Uniform grid over axis-aligned boxes for casting rays: `cast` visits the
+/// cells a ray passes through front to back, so it only tests the items near
+/// the ray and stops at the first cell past the nearest hit.
+#[derive(Debug, Clone)]
+pub struct RayGrid {
+    cell_size: f32,
+    min: Point3<f32>,
+    max: Point3<f32>,
+    cells: HashMap<(i32, i32, i32), Vec<usize>>,
+}
+
+impl RayGrid {
+    /// Item `i` is `boxes[i]`, given as `(min, max)`; it is listed in every
+    /// cell its box overlaps.
+    pub fn new(boxes: &[(Point3<f32>, Point3<f32>)], cell_size: f32) -> Self {
+        let cell_size = cell_size.max(1e-3);
+        let (mut min, mut max) = (Point3::origin(), Point3::origin());
+        if let Some(&(lo, hi)) = boxes.first() {
+            (min, max) = (lo, hi);
+        }
+        for (lo, hi) in boxes {
+            min = min.inf(lo);
+            max = max.sup(hi);
+        }
+        let mut cells: HashMap<(i32, i32, i32), Vec<usize>> = HashMap::new();
+        for (i, (lo, hi)) in boxes.iter().enumerate() {
+            let cell = |p: &Point3<f32>| NeighborGrid::cell_of(&(p - min.coords), cell_size);
+            let ((x0, y0, z0), (x1, y1, z1)) = (cell(lo), cell(hi));
+            for x in x0..=x1 {
+                for y in y0..=y1 {
+                    for z in z0..=z1 {
+                        cells.entry((x, y, z)).or_default().push(i);
+                    }
+                }
+            }
+        }
+        Self {
+            cell_size,
+            min,
+            max,
+            cells,
+        }
+    }
+
+    /// Casts the ray from `origin` along `dir`: calls `hit(i)` for the items
+    /// in each cell it passes, which returns where the ray hits item `i`, if
+    /// anywhere, as a multiple of `dir`. Returns the nearest hit and its `t`;
+    /// of equal hits, the lowest index. Items in several cells may be tested
+    /// more than once.
+    pub fn cast<F: FnMut(usize) -> Option<f32>>(
+        &self,
+        origin: Point3<f32>,
+        dir: Vector3<f32>,
+        mut hit: F,
+    ) -> Option<(usize, f32)> {
+        let mut best: Option<(usize, f32)> = None;
+        self.walk(origin, dir, 0, |cell, t_exit| {
+            for &i in self.items(cell) {
+                let Some(t) = hit(i) else {
+                    continue;
+                };
+                let nearer = match best {
+                    None => true,
+                    Some((j, best_t)) => t < best_t || (t == best_t && i < j),
+                };
+                if nearer {
+                    best = Some((i, t));
+                }
+            }
+            // Hits in later cells are no nearer than this cell's exit.
+            !best.is_some_and(|(_, t)| t < t_exit)
+        });
+        best
+    }
+
+    /// Every item in a cell the ray from `origin` along `dir` passes, in
+    /// ascending order: all the items it can hit, and some it misses.
+    pub fn along(&self, origin: Point3<f32>, dir: Vector3<f32>) -> Vec<usize> {
+        let mut result = Vec::new();
+        self.walk(origin, dir, 0, |cell, _| {
+            result.extend_from_slice(self.items(cell));
+            true
+        });
+        result.sort_unstable();
+        result.dedup();
+        result
+    }
+
+    /// Every item in a cell within `reach` of the ray from `origin` along
+    /// `dir`, in ascending order: all the items within `reach` of it, and
+    /// some further.
+    pub fn near(&self, origin: Point3<f32>, dir: Vector3<f32>, reach: f32) -> Vec<usize> {
+        let k = (reach.max(0.0) / self.cell_size).ceil() as i32;
+        let mut result = Vec::new();
+        self.walk(origin, dir, k, |(x, y, z), _| {
+            for dx in -k..=k {
+                for dy in -k..=k {
+                    for dz in -k..=k {
+                        result.extend_from_slice(self.items((x + dx, y + dy, z + dz)));
+                    }
+                }
+            }
+            true
+        });
+        result.sort_unstable();
+        result.dedup();
+        result
+    }
+
+    fn items(&self, cell: (i32, i32, i32)) -> &[usize] {
+        self.cells.get(&cell).map_or(&[], Vec::as_slice)
+    }
+
+    /// Calls `visit(cell, t_exit)` for each cell along the ray, front to
+    /// back, with the `t` at which the ray leaves the cell, until it returns
+    /// `false`. The grid is taken to extend `pad` cells past its items.
+    fn walk<F: FnMut((i32, i32, i32), f32) -> bool>(
+        &self,
+        origin: Point3<f32>,
+        dir: Vector3<f32>,
+        pad: i32,
+        mut visit: F,
+    ) {
+        if self.cells.is_empty() {
+            return;
+        }
+        let margin = Vector3::repeat(pad as f32 * self.cell_size);
+        let (min, max) = (self.min - margin, self.max + margin);
+        // Where the ray is inside the grid's bounds.
+        let (mut t_enter, mut t_leave) = (0.0f32, f32::INFINITY);
+        for axis in 0..3 {
+            if dir[axis] == 0.0 {
+                if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                    return;
+                }
+                continue;
+            }
+            let t0 = (min[axis] - origin[axis]) / dir[axis];
+            let t1 = (max[axis] - origin[axis]) / dir[axis];
+            t_enter = t_enter.max(t0.min(t1));
+            t_leave = t_leave.min(t0.max(t1));
+        }
+        if t_enter > t_leave {
+            return;
+        }
+
+        // Amanatides & Woo: step to whichever cell boundary the ray meets first.
+        let start = origin + dir * t_enter - self.min.coords;
+        let last = NeighborGrid::cell_of(&(self.max - self.min.coords), self.cell_size);
+        let first = NeighborGrid::cell_of(&start, self.cell_size);
+        let mut cell = [
+            first.0.clamp(-pad, last.0 + pad),
+            first.1.clamp(-pad, last.1 + pad),
+            first.2.clamp(-pad, last.2 + pad),
+        ];
+        let last = [last.0 + pad, last.1 + pad, last.2 + pad];
+        let mut step = [0i32; 3];
+        let mut t_next = [f32::INFINITY; 3];
+        let mut t_delta = [f32::INFINITY; 3];
+        for axis in 0..3 {
+            if dir[axis] == 0.0 {
+                continue;
+            }
+            step[axis] = if dir[axis] > 0.0 { 1 } else { -1 };
+            let boundary = (cell[axis] + (step[axis] > 0) as i32) as f32 * self.cell_size;
+            t_next[axis] = t_enter + (boundary - start[axis]) / dir[axis];
+            t_delta[axis] = self.cell_size / dir[axis].abs();
+        }
+
+        loop {
+            let axis = (0..3)
+                .min_by(|&a, &b| t_next[a].total_cmp(&t_next[b]))
+                .expect("three axes");
+            let t_exit = t_next[axis];
+            if !visit((cell[0], cell[1], cell[2]), t_exit) {
+                return;
+            }
+            cell[axis] += step[axis];
+            if t_exit > t_leave || cell[axis] < -pad || cell[axis] > last[axis] {
+                return;
+            }
+            t_next[axis] += t_delta[axis];
+        }
+    }
+}
@@ -0,0 +1,1024 @@
+//! How molecules are styled and filtered for drawing, independent of any
+//! renderer. `MoleculeViewer` turns the shapes built here into scene
+//! entities, and `DisplayList::from_molecule` exports them directly.
+
+use crate::colormap::{categorical, cpk_color, ColorScheme, Rgb, TintMode};
+use crate::display_list::{Cylinder, Sphere};
+use crate::element;
+use crate::molecule::{Atom, Bond, Molecule};
+#[cfg(feature = "render")]
+use crate::picking::{self, Hit};
+use crate::spatial::NeighborGrid;
+use nalgebra::{Point3, Vector3};
+use std::collections::{BTreeSet, HashMap};
+
+/// How atoms and bonds are represented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderStyle {
+    /// `ATOM_RADIUS` spheres joined by `ViewerSettings::stick_radius`
+    /// cylinders.
+    #[default]
+    BallAndStick,
+    /// Bonds only, with atoms shrunk to joints as thick as the bonds (also
+    /// called licorice).
+    Stick,
+    /// Van der Waals spheres, no bonds.
+    SpaceFilling,
+    /// `WIREFRAME_RADIUS` lines with no atom spheres, for large structures
+    /// and seeing through to the backbone. Atoms without a drawn bond keep
+    /// a joint so they can still be seen and picked.
+    Wireframe,
+}
+
+/// Tessellation level of the atom and bond meshes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Detail {
+    Full,
+    Low,
+}
+
+/// Sphere radius used for atoms, in Å. Shared by rendering, picking and overlays.
+pub const ATOM_RADIUS: f32 = 0.4;
+/// Default `ViewerSettings::stick_radius`, in Å.
+pub const BOND_RADIUS: f32 = 0.15;
+/// Bond and joint radius of `RenderStyle::Wireframe`, in Å.
+pub const WIREFRAME_RADIUS: f32 = 0.04;
+
+/// Ring planarity (RMS deviation, Å) shown fully red by `color_rings_by_planarity`.
+pub const RING_PLANARITY_COLOR_MAX: f32 = 0.3;
+
+/// Light direction the toon bands are computed against.
+pub const TOON_LIGHT_DIR: [f32; 3] = [0.3, 1.0, 0.6];
+
+/// How atom and bond meshes are lit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shading {
+    /// Smooth per-vertex normals.
+    Standard,
+    /// One normal per face, for a faceted look.
+    Flat,
+    /// Flat faces with normals snapped into `bands` brightness steps relative to
+    /// `TOON_LIGHT_DIR`. Banding is exact for atoms lit from that direction
+    /// and within `TOON_BOND_MESHES` steps of it for bonds.
+    Toon { bands: u32 },
+}
+
+/// How bonds are colored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BondColoring {
+    /// Every bond in one color.
+    Uniform(Rgb),
+    /// Each half of a bond in the color of the atom at that end: its
+    /// `ViewerSettings::color_scheme` color, or its `color_overrides` entry.
+    SplitByElement,
+}
+
+impl Default for BondColoring {
+    fn default() -> Self {
+        Self::Uniform(BOND_COLOR)
+    }
+}
+
+/// Two parallel clipping planes `half_thickness` either side of `center`, facing
+/// along `normal`. Atoms outside the slab are hidden.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Slab {
+    pub center: Point3<f32>,
+    pub normal: Vector3<f32>,
+    pub half_thickness: f32,
+}
+
+impl Slab {
+    pub fn new(center: Point3<f32>, normal: Vector3<f32>, half_thickness: f32) -> Self {
+        Self {
+            center,
+            normal: normal.try_normalize(1e-12).unwrap_or_else(Vector3::z),
+            half_thickness,
+        }
+    }
+
+    /// Signed distance of `p` from the slab's mid-plane.
+    pub fn depth(&self, p: &Point3<f32>) -> f32 {
+        (p - self.center).dot(&self.normal)
+    }
+
+    pub fn contains(&self, p: &Point3<f32>) -> bool {
+        self.depth(p).abs() <= self.half_thickness
+    }
+
+    /// Parameter range `(t0, t1)` of the segment `a + t * (b - a)`, `t` in `[0, 1]`,
+    /// that lies inside the slab, or `None` if the segment misses it.
+    pub fn clip_segment(&self, a: &Point3<f32>, b: &Point3<f32>) -> Option<(f32, f32)> {
+        let (da, db) = (self.depth(a), self.depth(b));
+        let h = self.half_thickness;
+        let delta = db - da;
+        if delta.abs() < 1e-6 {
+            return (da.abs() <= h).then_some((0.0, 1.0));
+        }
+        let (lo, hi) = ((-h - da) / delta, (h - da) / delta);
+        let (t0, t1) = (lo.min(hi).max(0.0), lo.max(hi).min(1.0));
+        (t0 < t1).then_some((t0, t1))
+    }
+}
+
+/// A sphere whose atoms are filtered out as set by `mode`, to open a view
+/// into the molecule, e.g. onto a buried ligand.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cutaway {
+    pub center: Point3<f32>,
+    pub radius: f32,
+    pub mode: VisibilityMode,
+}
+
+impl Cutaway {
+    pub fn contains(&self, p: &Point3<f32>) -> bool {
+        (p - self.center).norm_squared() <= self.radius * self.radius
+    }
+}
+
+/// How a filter (hydrogens, slab, cutaway) treats the atoms it rejects.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VisibilityMode {
+    /// Not drawn and not pickable.
+    Hide,
+    /// Drawn translucent so spatial context is kept. Not pickable unless
+    /// `ViewerSettings::pick_ghosts` is set.
+    Ghost { opacity: f32 },
+}
+
+/// How transforms and drags treat the pinned atoms among those they move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinnedMotion {
+    /// Move the other atoms and leave pinned ones in place.
+    Skip,
+    /// Fail, moving nothing.
+    Refuse,
+}
+
+impl VisibilityMode {
+    /// Combines two filters rejecting the same atom: `Hide` wins over any ghost,
+    /// and the fainter of two ghosts wins.
+    pub fn most_hidden(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Ghost { opacity: a }, Self::Ghost { opacity: b }) => {
+                Self::Ghost { opacity: a.min(b) }
+            }
+            _ => Self::Hide,
+        }
+    }
+
+    fn opacity(self) -> Option<f32> {
+        match self {
+            Self::Hide => None,
+            Self::Ghost { opacity } => Some(opacity.clamp(0.0, 1.0)),
+        }
+    }
+}
+
+/// Opacity under the combined `filter`, or `None` if it hides.
+pub(crate) fn filter_opacity(filter: Option<VisibilityMode>) -> Option<f32> {
+    match filter {
+        None => Some(1.0),
+        Some(mode) => mode.opacity(),
+    }
+}
+
+fn combine(a: Option<VisibilityMode>, b: Option<VisibilityMode>) -> Option<VisibilityMode> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.most_hidden(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Part of a bond left visible by the current filters and slab.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BondSegment {
+    pub(crate) start: Point3<f32>,
+    pub(crate) end: Point3<f32>,
+    /// Whether each end was cut by a slab plane rather than ending at an atom.
+    cut: [bool; 2],
+    /// Whether `end` is the free end of a stub towards a hidden atom.
+    stub: bool,
+    opacity: f32,
+    #[cfg_attr(not(feature = "render"), allow(dead_code))]
+    pub(crate) pickable: bool,
+}
+
+/// Multipliers on the ball-and-stick atom radius by periodic table row, so
+/// hydrogens draw smaller than carbons and heavy atoms larger.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ElementScale {
+    /// H and He.
+    pub period_1: f32,
+    /// Li to Ne, and symbols the element table does not know.
+    pub period_2: f32,
+    /// Na to Ar.
+    pub period_3: f32,
+    /// K and heavier.
+    pub heavier: f32,
+}
+
+impl Default for ElementScale {
+    fn default() -> Self {
+        Self {
+            period_1: 0.6,
+            period_2: 1.0,
+            period_3: 1.2,
+            heavier: 1.35,
+        }
+    }
+}
+
+impl ElementScale {
+    pub fn factor(&self, element: &str) -> f32 {
+        match element::period(element) {
+            Some(1) => self.period_1,
+            Some(2) | None => self.period_2,
+            Some(3) => self.period_3,
+            Some(_) => self.heavier,
+        }
+    }
+}
+
+/// Ball-and-stick atom radii by element, in Å. The default table holds the
+/// covalent radii of `element::covalent_radius`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtomRadiiTable {
+    // Keyed by `element::symbol` spelling where there is one.
+    radii: HashMap<String, f32>,
+    /// Radius of elements not in the table.
+    pub fallback: f32,
+}
+
+impl Default for AtomRadiiTable {
+    fn default() -> Self {
+        Self {
+            radii: element::COVALENT_RADII
+                .iter()
+                .map(|&(symbol, radius)| (symbol.to_string(), radius))
+                .collect(),
+            fallback: ATOM_RADIUS,
+        }
+    }
+}
+
+impl AtomRadiiTable {
+    /// The table with `element` drawn at `radius`, e.g. to make hydrogens
+    /// stand out.
+    pub fn with_entry(mut self, element: &str, radius: f32) -> Self {
+        self.radii.insert(radii_key(element).to_string(), radius);
+        self
+    }
+
+    /// Radius of `element`, ignoring case.
+    pub fn radius(&self, element: &str) -> f32 {
+        self.radii
+            .get(radii_key(element))
+            .copied()
+            .unwrap_or(self.fallback)
+    }
+}
+
+fn radii_key(element: &str) -> &str {
+    element::symbol(element).unwrap_or(element)
+}
+
+/// Cap spheres on slab-cut bonds are this much wider than the bond itself.
+const SLAB_CAP_SCALE: f32 = 1.2;
+
+/// A good `ViewerSettings::bond_stubs` for ball-and-stick: long enough to show
+/// the bond's direction without suggesting where the hidden atom is.
+pub const BOND_STUB_FRACTION: f32 = 0.2;
+
+/// Color of `BondColoring::default()`.
+pub const BOND_COLOR: Rgb = (0.5, 0.5, 0.5);
+
+/// Radius of each line of a bond drawn by its order, as a fraction of the
+/// bond radius. Two lines side by side are as wide as a single bond.
+const BOND_LINE_SCALE: f32 = 0.5;
+
+/// Length of each dash of an aromatic bond's dashed line, in Å. Gaps are as
+/// long.
+const AROMATIC_DASH: f32 = 0.15;
+
+/// View direction of `MoleculeDisplayState` styles, which know no camera: an
+/// unrotated camera looks down +Z.
+pub(crate) const DEFAULT_VIEW_DIR: Vector3<f32> = Vector3::new(0.0, 0.0, 1.0);
+
+/// Rendering options for `MoleculeViewer`. Set `dirty` after changing them.
+#[derive(Debug, Clone)]
+pub struct ViewerSettings {
+    pub shading: Shading,
+    pub detail: Detail,
+    /// Filter out hydrogens (and their bonds) as set by `hydrogen_mode`.
+    pub hide_hydrogens: bool,
+    pub hydrogen_mode: VisibilityMode,
+    /// How much fully buried atoms are darkened by ambient occlusion (0 = off, 1 = black).
+    pub ao_strength: f32,
+    /// Neighbor search radius for ambient occlusion, in Å.
+    pub ao_radius: f32,
+    /// Filters out atoms outside the slab as set by `slab_mode`. When hiding,
+    /// bonds crossing a slab plane are drawn up to it.
+    pub slab: Option<Slab>,
+    pub slab_mode: VisibilityMode,
+    /// Color of the caps marking where the slab cuts a bond; `None` draws no caps.
+    pub slab_cap_color: Option<(f32, f32, f32)>,
+    /// Filters out atoms inside the sphere as set by its `mode`. Bonds to
+    /// them are dropped or stubbed like those of any filtered atom. Set with
+    /// `MoleculeViewer::set_cutaway`.
+    pub cutaway: Option<Cutaway>,
+    /// Draw bonds between a drawn and a hidden atom as stubs, ending this
+    /// fraction of the bond length beyond the drawn atom's surface and capped
+    /// in the bond color. This applies to every filter, slab included (which
+    /// then stubs bonds instead of cutting them at its planes). `None` drops
+    /// such bonds, apart from the slab cuts.
+    pub bond_stubs: Option<f32>,
+    /// Let picking hit ghosted atoms and bonds.
+    pub pick_ghosts: bool,
+    /// Color ring atoms from green (planar) to red (RMS deviation of
+    /// `RING_PLANARITY_COLOR_MAX` or more) instead of by `color_scheme`.
+    pub color_rings_by_planarity: bool,
+    /// Per-element scaling of ball-and-stick atoms; `None` draws every atom at
+    /// `ATOM_RADIUS`.
+    pub element_scale: Option<ElementScale>,
+    /// Per-element ball-and-stick atom radii, used instead of `ATOM_RADIUS`
+    /// and `element_scale` when set.
+    pub radii_table: Option<AtomRadiiTable>,
+    /// Atom colors by index, replacing the element (or ring planarity) color.
+    pub color_overrides: HashMap<usize, Rgb>,
+    /// Atoms hidden one by one, by index, e.g. by `EditAction::HideSelected`.
+    pub hidden_atoms: BTreeSet<usize>,
+    /// Applied to every atom and bond color after `color_overrides`, e.g. to
+    /// tell copies of one molecule apart (see `MoleculeViewer::set_tint`).
+    pub tint: Option<TintMode>,
+    /// Make `handle_pick` show the coordination sphere of clicked metal atoms
+    /// in the first overlay that can (see `CoordinationRender`).
+    pub coordination_on_click: bool,
+    /// How many bonds out an atom drag reaches (see
+    /// `MoleculeViewer::begin_atom_drag`). Atoms `n` bonds from the grabbed
+    /// one move by 1/2^n of its displacement; 0 drags the atom alone.
+    pub drag_falloff_shells: usize,
+    /// What edits moving several atoms do when some are pinned
+    /// (`Molecule::pinned`).
+    pub pinned_motion: PinnedMotion,
+    /// Bond cylinder radius, and joint radius in stick style, in Å. The
+    /// wireframe style always uses `WIREFRAME_RADIUS`.
+    pub stick_radius: f32,
+    /// Draw double and triple bonds as two and three thinner lines, and
+    /// aromatic bonds (order 0) as a solid and a dashed line with the dashes
+    /// toward the ring centre. The lines lie in the plane of the bond's ring
+    /// or of a neighbouring atom, so a ring looks flat from any angle; bonds
+    /// with neither (such as a triple bond's) face the camera, and the
+    /// viewer rebuilds the scene when it turns. Picking still treats each
+    /// bond as one cylinder.
+    pub show_bond_order: bool,
+    /// `BondColoring::SplitByElement` draws each bond (or each of its
+    /// `show_bond_order` lines) as two cylinders meeting at its midpoint.
+    pub bond_coloring: BondColoring,
+    /// Atom colors, unless `color_rings_by_planarity` or `color_overrides`
+    /// say otherwise.
+    pub color_scheme: ColorScheme,
+}
+
+impl Default for ViewerSettings {
+    fn default() -> Self {
+        Self {
+            shading: Shading::Standard,
+            detail: Detail::Full,
+            hide_hydrogens: false,
+            hydrogen_mode: VisibilityMode::Hide,
+            ao_strength: 0.0,
+            ao_radius: 4.0,
+            slab: None,
+            slab_mode: VisibilityMode::Hide,
+            slab_cap_color: Some((1.0, 0.8, 0.2)),
+            cutaway: None,
+            bond_stubs: None,
+            pick_ghosts: false,
+            color_rings_by_planarity: false,
+            element_scale: Some(ElementScale::default()),
+            radii_table: None,
+            color_overrides: HashMap::new(),
+            hidden_atoms: BTreeSet::new(),
+            tint: None,
+            coordination_on_click: false,
+            drag_falloff_shells: 3,
+            pinned_motion: PinnedMotion::Skip,
+            stick_radius: BOND_RADIUS,
+            show_bond_order: false,
+            bond_coloring: BondColoring::default(),
+            color_scheme: ColorScheme::default(),
+        }
+    }
+}
+
+/// Style and filters of one loaded molecule. Each molecule slot keeps its own,
+/// so switching the active molecule does not lose its configuration.
+#[derive(Debug, Clone, Default)]
+pub struct MoleculeDisplayState {
+    pub render_style: RenderStyle,
+    pub settings: ViewerSettings,
+}
+
+impl MoleculeDisplayState {
+    pub(crate) fn style(&self) -> Style<'_> {
+        Style {
+            render_style: self.render_style,
+            settings: &self.settings,
+            view_dir: DEFAULT_VIEW_DIR,
+        }
+    }
+}
+
+/// Style and filters applied while building or picking one molecule.
+#[derive(Clone, Copy)]
+pub(crate) struct Style<'a> {
+    pub(crate) render_style: RenderStyle,
+    pub(crate) settings: &'a ViewerSettings,
+    /// Unit direction the camera looks in, which `show_bond_order` lines are
+    /// spread across.
+    pub(crate) view_dir: Vector3<f32>,
+}
+
+impl Style<'_> {
+    /// Display radius of `atom` under the current style. Picking uses the same value.
+    pub(crate) fn atom_radius(&self, atom: &Atom) -> f32 {
+        match self.render_style {
+            RenderStyle::BallAndStick => {
+                match (&self.settings.radii_table, &self.settings.element_scale) {
+                    (Some(table), _) => table.radius(&atom.element),
+                    (None, Some(scale)) => ATOM_RADIUS * scale.factor(&atom.element),
+                    (None, None) => ATOM_RADIUS,
+                }
+            }
+            RenderStyle::Stick | RenderStyle::Wireframe => self.bond_radius(),
+            RenderStyle::SpaceFilling => element::vdw_radius(&atom.element),
+        }
+    }
+
+    /// Display radius of bonds under the current style. Picking uses the
+    /// same value.
+    pub(crate) fn bond_radius(&self) -> f32 {
+        match self.render_style {
+            RenderStyle::Wireframe => WIREFRAME_RADIUS,
+            _ => self.settings.stick_radius,
+        }
+    }
+
+    /// `None` if atom `i` passes every active filter, otherwise the combined
+    /// mode of the filters rejecting it.
+    fn atom_filter(&self, i: usize, atom: &Atom) -> Option<VisibilityMode> {
+        combine(self.filter_ignoring_slab(i, atom), self.slab_filter(atom))
+    }
+
+    /// The filters that go by element and position, leaving out `hidden_atoms`.
+    #[cfg_attr(not(feature = "render"), allow(dead_code))]
+    pub(crate) fn filter_by_atom(&self, atom: &Atom) -> Option<VisibilityMode> {
+        combine(
+            combine(self.hydrogen_filter(atom), self.cutaway_filter(atom)),
+            self.slab_filter(atom),
+        )
+    }
+
+    // The slab is handled separately for bonds, which it cuts instead of dropping.
+    fn filter_ignoring_slab(&self, i: usize, atom: &Atom) -> Option<VisibilityMode> {
+        let hidden = self.settings.hidden_atoms.contains(&i);
+        combine(
+            combine(self.hydrogen_filter(atom), self.cutaway_filter(atom)),
+            hidden.then_some(VisibilityMode::Hide),
+        )
+    }
+
+    fn cutaway_filter(&self, atom: &Atom) -> Option<VisibilityMode> {
+        let cutaway = self.settings.cutaway?;
+        cutaway.contains(&atom.position).then_some(cutaway.mode)
+    }
+
+    fn hydrogen_filter(&self, atom: &Atom) -> Option<VisibilityMode> {
+        let hydrogen = self.settings.hide_hydrogens && atom.element.eq_ignore_ascii_case("H");
+        hydrogen.then_some(self.settings.hydrogen_mode)
+    }
+
+    fn slab_filter(&self, atom: &Atom) -> Option<VisibilityMode> {
+        let outside_slab = self
+            .settings
+            .slab
+            .is_some_and(|slab| !slab.contains(&atom.position));
+        outside_slab.then_some(self.settings.slab_mode)
+    }
+
+    /// Opacity atom `i` is drawn at, or `None` if it is hidden.
+    pub(crate) fn atom_opacity(&self, i: usize, atom: &Atom) -> Option<f32> {
+        filter_opacity(self.atom_filter(i, atom))
+    }
+
+    /// Whether atom `i` is drawn at all, ghosted or not. Bonds are stubbed or
+    /// dropped by this alone, whichever filter hides the atom.
+    pub(crate) fn atom_drawn(&self, i: usize, atom: &Atom) -> bool {
+        self.atom_opacity(i, atom).is_some()
+    }
+
+    pub(crate) fn atom_pickable(&self, i: usize, atom: &Atom) -> bool {
+        match self.atom_filter(i, atom) {
+            None => true,
+            Some(VisibilityMode::Ghost { .. }) => self.settings.pick_ghosts,
+            Some(VisibilityMode::Hide) => false,
+        }
+    }
+
+    /// Drawn extent of `bond`, shared by rendering and picking. With a slab active,
+    /// a bond with an end outside it is clipped at the slab plane(s).
+    pub(crate) fn bond_segment(&self, mol: &Molecule, bond: &Bond) -> Option<BondSegment> {
+        if !self.draws_bonds() {
+            return None;
+        }
+        let (ia, ib) = (bond.atom_a, bond.atom_b);
+        let (a, b) = (&mol.atoms[ia], &mol.atoms[ib]);
+        if let Some(fraction) = self.settings.bond_stubs {
+            match (self.atom_drawn(ia, a), self.atom_drawn(ib, b)) {
+                (true, true) => {}
+                (true, false) => return Some(self.stub(ia, a, b, fraction)),
+                (false, true) => return Some(self.stub(ib, b, a, fraction)),
+                (false, false) => return None,
+            }
+        }
+        let mut mode = combine(
+            self.filter_ignoring_slab(ia, a),
+            self.filter_ignoring_slab(ib, b),
+        );
+        let (t0, t1) = match &self.settings.slab {
+            Some(slab) if self.settings.slab_mode == VisibilityMode::Hide => {
+                slab.clip_segment(&a.position, &b.position)?
+            }
+            Some(slab) => {
+                // A ghosting slab ghosts the whole bond if either end is outside.
+                if !slab.contains(&a.position) || !slab.contains(&b.position) {
+                    mode = combine(mode, Some(self.settings.slab_mode));
+                }
+                (0.0, 1.0)
+            }
+            None => (0.0, 1.0),
+        };
+        let opacity = filter_opacity(mode)?;
+        let diff = b.position - a.position;
+        Some(BondSegment {
+            start: a.position + diff * t0,
+            end: a.position + diff * t1,
+            cut: [t0 > 0.0, t1 < 1.0],
+            stub: false,
+            opacity,
+            pickable: mode.is_none() || self.settings.pick_ghosts,
+        })
+    }
+
+    /// Bond from the drawn atom `from` (index `i`) towards the hidden atom
+    /// `to`, reaching `fraction` of the bond length past `from`'s surface.
+    fn stub(&self, i: usize, from: &Atom, to: &Atom, fraction: f32) -> BondSegment {
+        let diff = to.position - from.position;
+        let len = diff.norm();
+        let reach = (self.atom_radius(from) + fraction.max(0.0) * len).min(len);
+        BondSegment {
+            start: from.position,
+            end: from.position + diff * (reach / len.max(f32::EPSILON)),
+            cut: [false; 2],
+            stub: true,
+            opacity: self.atom_opacity(i, from).unwrap_or(1.0),
+            pickable: self.atom_pickable(i, from),
+        }
+    }
+
+    /// Whether atom spheres are drawn for atoms with a drawn bond.
+    fn draws_bonded_atoms(&self) -> bool {
+        self.render_style != RenderStyle::Wireframe
+    }
+
+    fn draws_bonds(&self) -> bool {
+        self.render_style != RenderStyle::SpaceFilling
+    }
+
+    /// Whether bonds read as capsules: in stick and wireframe mode the
+    /// joints are as thick as the bonds, so every bond end, including slab
+    /// cuts and stub tips, looks rounded.
+    #[cfg(feature = "render")]
+    pub(crate) fn capsule_bonds(&self) -> bool {
+        matches!(
+            self.render_style,
+            RenderStyle::Stick | RenderStyle::Wireframe
+        )
+    }
+
+    /// Where the ray first hits `segment` as drawn: a capsule under
+    /// `capsule_bonds`, else the cheaper flat-ended cylinder.
+    #[cfg(feature = "render")]
+    pub(crate) fn pick_bond(
+        &self,
+        origin: Point3<f32>,
+        dir: Vector3<f32>,
+        segment: &BondSegment,
+    ) -> Option<Hit> {
+        let radius = self.bond_radius();
+        if self.capsule_bonds() {
+            picking::ray_capsule(origin, dir, segment.start, segment.end, radius)
+        } else {
+            picking::ray_cylinder(origin, dir, segment.start, segment.end, radius)
+        }
+    }
+
+    /// Offset from the bond axis of each line drawing a bond of `order`
+    /// along unit `axis`, and whether it is dashed, with the radius of the
+    /// lines. One line on the axis, unless `show_bond_order` splits it.
+    /// `plane` is the unit direction, perpendicular to `axis`, in which to
+    /// spread the lines (see `bond_planes`); without one they spread across
+    /// the screen.
+    fn bond_lines(
+        &self,
+        order: u8,
+        axis: Vector3<f32>,
+        plane: Option<Vector3<f32>>,
+    ) -> (f32, Vec<(Vector3<f32>, bool)>) {
+        let radius = self.bond_radius();
+        if !self.settings.show_bond_order || !matches!(order, 0 | 2 | 3) {
+            return (radius, vec![(Vector3::zeros(), false)]);
+        }
+        let line_radius = radius * BOND_LINE_SCALE;
+        // In the bond's plane, else across the bond on screen; any
+        // perpendicular for a bond pointing at the camera.
+        let side = plane
+            .or_else(|| axis.cross(&self.view_dir).try_normalize(1e-4))
+            .unwrap_or_else(|| {
+                let other = if axis.x.abs() < 0.9 {
+                    Vector3::x()
+                } else {
+                    Vector3::y()
+                };
+                axis.cross(&other).normalize()
+            });
+        let lines = match order {
+            2 => vec![(side * line_radius, false), (-side * line_radius, false)],
+            0 => vec![(-side * line_radius, false), (side * line_radius, true)],
+            _ => {
+                // Touching lines, the middle one nearest the camera.
+                let toward = -self.view_dir;
+                let depth = axis.cross(&side);
+                let depth = if depth.dot(&toward) < 0.0 {
+                    -depth
+                } else {
+                    depth
+                };
+                let reach = 2.0 * line_radius / 3f32.sqrt();
+                [90f32, 210.0, 330.0]
+                    .iter()
+                    .map(|angle| {
+                        let (sin, cos) = angle.to_radians().sin_cos();
+                        ((side * cos + depth * sin) * reach, false)
+                    })
+                    .collect()
+            }
+        };
+        (line_radius, lines)
+    }
+
+    /// Colors of the halves of `bond` at its first and second atom, before
+    /// tint.
+    fn bond_colors(&self, mol: &Molecule, bond: &Bond) -> [Rgb; 2] {
+        match self.settings.bond_coloring {
+            BondColoring::Uniform(color) => [color; 2],
+            BondColoring::SplitByElement => [bond.atom_a, bond.atom_b].map(|i| {
+                self.settings
+                    .color_overrides
+                    .get(&i)
+                    .copied()
+                    .unwrap_or_else(|| self.scheme_color(mol, i))
+            }),
+        }
+    }
+
+    /// Color of atom `i` under `color_scheme`, before overrides and tint.
+    pub(crate) fn scheme_color(&self, mol: &Molecule, i: usize) -> Rgb {
+        let cpk = || cpk_color(&mol.atoms[i].element);
+        match self.settings.color_scheme {
+            ColorScheme::Cpk => cpk(),
+            ColorScheme::Monochrome(r, g, b) => (r, g, b),
+            ColorScheme::ByChain => match mol.chains.as_ref().and_then(|c| c.get(i)) {
+                Some(&chain) if chain != ' ' => categorical(chain as usize),
+                _ => cpk(),
+            },
+            ColorScheme::ByResidue => mol
+                .residues
+                .as_ref()
+                .and_then(|r| r.get(i))
+                .map_or_else(cpk, |&residue| categorical(residue)),
+        }
+    }
+
+    /// `color` after the molecule's tint, if any.
+    pub(crate) fn tint(&self, color: Rgb) -> Rgb {
+        self.settings.tint.map_or(color, |tint| tint.apply(color))
+    }
+
+    /// Toon shading reads better without specular highlights.
+    fn shininess_scale(&self) -> f32 {
+        match self.settings.shading {
+            Shading::Toon { .. } => 0.0,
+            _ => 1.0,
+        }
+    }
+}
+
+/// A drawn part of a bond: its cylinder, or a sphere capping a slab cut or stub.
+#[derive(Debug, Clone)]
+pub(crate) enum BondShape {
+    Cylinder(Cylinder),
+    Cap(Sphere),
+}
+
+/// A sphere per drawn atom of `mol`, in index order, with the atom's index.
+/// The wireframe style leaves out atoms that have a drawn bond.
+pub(crate) fn atom_spheres(mol: &Molecule, style: Style) -> Vec<(usize, Sphere)> {
+    let mut spheres = Vec::new();
+    let mut bonded = Vec::new();
+    if !style.draws_bonded_atoms() {
+        bonded = vec![false; mol.atoms.len()];
+        for bond in &mol.bonds {
+            if style.bond_segment(mol, bond).is_some() {
+                bonded[bond.atom_a] = true;
+                bonded[bond.atom_b] = true;
+            }
+        }
+    }
+
+    let occlusion = if style.settings.ao_strength > 0.0 {
+        ambient_occlusion(mol, style.settings.ao_radius)
+    } else {
+        Vec::new()
+    };
+
+    // Worst planarity of any ring each atom belongs to.
+    let mut ring_planarity: Vec<Option<f32>> = Vec::new();
+    if style.settings.color_rings_by_planarity {
+        ring_planarity = vec![None; mol.atoms.len()];
+        for ring in mol.analyze_rings() {
+            for &i in &ring.atoms {
+                let worst = ring_planarity[i].map_or(ring.planarity, |p| p.max(ring.planarity));
+                ring_planarity[i] = Some(worst);
+            }
+        }
+    }
+
+    for (i, atom) in mol.atoms.iter().enumerate() {
+        if bonded.get(i).copied().unwrap_or(false) {
+            continue;
+        }
+        let Some(opacity) = style.atom_opacity(i, atom) else {
+            continue;
+        };
+        let color = match ring_planarity.get(i).copied().flatten() {
+            Some(rms) => {
+                let t = (rms / RING_PLANARITY_COLOR_MAX).clamp(0.0, 1.0);
+                (0.1 + 0.8 * t, 0.8 - 0.7 * t, 0.1)
+            }
+            None => style.scheme_color(mol, i),
+        };
+        let color = style
+            .settings
+            .color_overrides
+            .get(&i)
+            .copied()
+            .unwrap_or(color);
+        let color = style.tint(color);
+        let color = match occlusion.get(i) {
+            Some(occ) => {
+                let shade = 1.0 - style.settings.ao_strength.clamp(0.0, 1.0) * occ;
+                (color.0 * shade, color.1 * shade, color.2 * shade)
+            }
+            None => color,
+        };
+
+        spheres.push((
+            i,
+            Sphere {
+                center: atom.position,
+                radius: style.atom_radius(atom),
+                color,
+                opacity,
+                shininess: 0.2 * style.shininess_scale(), // Low shininess
+            },
+        ));
+    }
+    spheres
+}
+
+/// Each drawn bond of `mol` in index order, as one cylinder or (see
+/// `show_bond_order` and `bond_coloring`) several, followed by its caps, with
+/// the bond's index.
+pub(crate) fn bond_shapes(mol: &Molecule, style: Style) -> Vec<(usize, BondShape)> {
+    bond_shapes_of(mol, style, 0..mol.bonds.len())
+}
+
+/// `bond_shapes` for just the bonds `indices`, in the order given.
+pub(crate) fn bond_shapes_of(
+    mol: &Molecule,
+    style: Style,
+    indices: impl IntoIterator<Item = usize>,
+) -> Vec<(usize, BondShape)> {
+    let planes = if style.settings.show_bond_order {
+        bond_planes(mol)
+    } else {
+        HashMap::new()
+    };
+    let mut shapes = Vec::new();
+    for i in indices {
+        let Some(bond) = mol.bonds.get(i) else {
+            continue;
+        };
+        let Some(segment) = style.bond_segment(mol, bond) else {
+            continue;
+        };
+        // If atoms are overlapping, skip bond
+        if (segment.end - segment.start).norm() < 0.001 {
+            continue;
+        }
+        let diff = segment.end - segment.start;
+        let axis = diff.normalize();
+        let plane = planes
+            .get(&i)
+            .and_then(|toward| (toward - axis * toward.dot(&axis)).try_normalize(1e-4));
+        let (radius, lines) = style.bond_lines(bond.order, axis, plane);
+        let colors = style.bond_colors(mol, bond);
+        for (offset, dashed) in lines {
+            let (start, end) = (segment.start + offset, segment.end + offset);
+            let pieces = if dashed {
+                dashes(start, end)
+            } else {
+                vec![(start, end)]
+            };
+            let pieces = pieces
+                .into_iter()
+                .flat_map(|(start, end)| split_at_midpoint(mol, bond, start, end, colors));
+            for (start, end, color) in pieces {
+                shapes.push((
+                    i,
+                    BondShape::Cylinder(Cylinder {
+                        start,
+                        end,
+                        radius,
+                        color: style.tint(color),
+                        opacity: segment.opacity,
+                        shininess: 0.1 * style.shininess_scale(),
+                    }),
+                ));
+            }
+        }
+
+        // Mark the cross-section where the slab cut the bond.
+        if let Some(cap_color) = style.settings.slab_cap_color {
+            for (cut, end) in segment.cut.into_iter().zip([segment.start, segment.end]) {
+                if !cut {
+                    continue;
+                }
+                let cap = Sphere {
+                    center: end,
+                    radius: style.bond_radius() * SLAB_CAP_SCALE,
+                    color: style.tint(cap_color),
+                    opacity: segment.opacity,
+                    shininess: 0.2 * style.shininess_scale(),
+                };
+                shapes.push((i, BondShape::Cap(cap)));
+            }
+        }
+
+        // Round off the free end of a stub.
+        if segment.stub {
+            let tip = colors[usize::from(past_midpoint(mol, bond, segment.end) > 0.0)];
+            let cap = Sphere {
+                center: segment.end,
+                radius: style.bond_radius(),
+                color: style.tint(tip),
+                opacity: segment.opacity,
+                shininess: 0.1 * style.shininess_scale(),
+            };
+            shapes.push((i, BondShape::Cap(cap)));
+        }
+    }
+    shapes
+}
+
+/// How far `p` is past the midpoint of `bond`, along it, as a fraction of
+/// its length: negative on the first atom's half.
+fn past_midpoint(mol: &Molecule, bond: &Bond, p: Point3<f32>) -> f32 {
+    let a = mol.atoms[bond.atom_a].position;
+    let diff = mol.atoms[bond.atom_b].position - a;
+    (p - a).dot(&diff) / diff.norm_squared().max(f32::EPSILON) - 0.5
+}
+
+/// The piece of `bond` from `start` to `end`, cut where it crosses the
+/// bond's midpoint, with each part in the color of `colors` for the half it
+/// lies in. Dashes and stubs that do not reach the midpoint stay whole.
+fn split_at_midpoint(
+    mol: &Molecule,
+    bond: &Bond,
+    start: Point3<f32>,
+    end: Point3<f32>,
+    colors: [Rgb; 2],
+) -> Vec<(Point3<f32>, Point3<f32>, Rgb)> {
+    let half = |t: f32| colors[usize::from(t > 0.0)];
+    let (t0, t1) = (
+        past_midpoint(mol, bond, start),
+        past_midpoint(mol, bond, end),
+    );
+    if colors[0] == colors[1] || t0 * t1 >= 0.0 {
+        return vec![(start, end, half(t0 + t1))];
+    }
+    let mid = start + (end - start) * (t0 / (t0 - t1));
+    vec![(start, mid, half(t0)), (mid, end, half(t1))]
+}
+
+/// For each double, triple or aromatic bond of `mol`, a direction off its
+/// axis to spread its lines in: toward the centre of its smallest ring, or
+/// else toward a neighbour of either atom that is not on the bond's line.
+/// Bonds with neither, like those of an alkyne, are left out.
+fn bond_planes(mol: &Molecule) -> HashMap<usize, Vector3<f32>> {
+    let mut planes = HashMap::new();
+    if !mol.bonds.iter().any(|b| matches!(b.order, 0 | 2 | 3)) {
+        return planes;
+    }
+    // Smallest first, so a bond shared by fused rings goes with the smaller.
+    let mut rings = mol.find_rings();
+    rings.sort_by_key(Vec::len);
+    for ring in &rings {
+        let centroid = ring
+            .iter()
+            .map(|&a| mol.atoms[a].position.coords)
+            .sum::<Vector3<f32>>()
+            / ring.len() as f32;
+        for (k, &a) in ring.iter().enumerate() {
+            let b = ring[(k + 1) % ring.len()];
+            if let Some(i) = mol.find_bond(a, b) {
+                planes
+                    .entry(i)
+                    .or_insert(centroid - mol.atoms[a].position.coords);
+            }
+        }
+    }
+    let adjacency = mol.adjacency();
+    for (i, bond) in mol.bonds.iter().enumerate() {
+        let (a, b) = (bond.atom_a, bond.atom_b);
+        if !matches!(bond.order, 0 | 2 | 3)
+            || planes.contains_key(&i)
+            || a.max(b) >= adjacency.len()
+        {
+            continue;
+        }
+        let axis = mol.atoms[b].position - mol.atoms[a].position;
+        let toward = [(a, b), (b, a)]
+            .iter()
+            .flat_map(|&(from, other)| {
+                adjacency[from]
+                    .iter()
+                    .filter(move |&&n| n != other)
+                    .map(move |&n| mol.atoms[n].position - mol.atoms[from].position)
+            })
+            .find(|d| d.cross(&axis).norm() > 1e-3 * d.norm() * axis.norm());
+        if let Some(toward) = toward {
+            planes.insert(i, toward);
+        }
+    }
+    planes
+}
+
+/// Dashes of `AROMATIC_DASH` from `start` to `end`, evenly spaced, with one
+/// at either end.
+fn dashes(start: Point3<f32>, end: Point3<f32>) -> Vec<(Point3<f32>, Point3<f32>)> {
+    let diff = end - start;
+    let n = ((diff.norm() / AROMATIC_DASH + 1.0) / 2.0).round().max(1.0) as usize;
+    let piece = diff / (2 * n - 1) as f32;
+    (0..n)
+        .map(|k| {
+            let from = start + piece * (2 * k) as f32;
+            (from, from + piece)
+        })
+        .collect()
+}
+
+/// Neighbor count at which an atom is treated as fully buried.
+const AO_SATURATION: f32 = 12.0;
+
+/// View-independent occlusion estimate per atom in `[0, 1]`, from the
+/// distance-weighted number of neighbors within `radius`.
+fn ambient_occlusion(mol: &Molecule, radius: f32) -> Vec<f32> {
+    let positions: Vec<_> = mol.atoms.iter().map(|a| a.position).collect();
+    let grid = NeighborGrid::new(&positions, radius);
+    positions
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let mut weight = 0.0;
+            grid.for_each_within(*p, radius, |j, dist| {
+                if j != i {
+                    weight += 1.0 - dist / radius;
+                }
+            });
+            (weight / AO_SATURATION).min(1.0)
+        })
+        .collect()
+}
@@ -0,0 +1,62 @@
+//! Per-frame atom coordinates for animating a molecule with fixed topology.
+
+use nalgebra::Point3;
+
+/// Atom coordinates for each frame. Every frame has the same number of atoms,
+/// in the same order as the molecule they animate.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trajectory {
+    frames: Vec<Vec<Point3<f32>>>,
+}
+
+impl Trajectory {
+    pub fn new(frames: Vec<Vec<Point3<f32>>>) -> Result<Self, String> {
+        if let Some(first) = frames.first() {
+            if let Some(i) = frames.iter().position(|f| f.len() != first.len()) {
+                return Err(format!(
+                    "frame {} has {} atoms, frame 0 has {}",
+                    i,
+                    frames[i].len(),
+                    first.len()
+                ));
+            }
+        }
+        Ok(Self { frames })
+    }
+
+    /// Number of frames.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn n_atoms(&self) -> usize {
+        self.frames.first().map_or(0, Vec::len)
+    }
+
+    pub fn frame(&self, index: usize) -> Option<&[Point3<f32>]> {
+        self.frames.get(index).map(Vec::as_slice)
+    }
+
+    /// Positions at a fractional frame index, linearly interpolated between the
+    /// neighbouring frames and clamped to the first/last frame.
+    pub fn positions_at(&self, frame: f32) -> Vec<Point3<f32>> {
+        let Some(last) = self.frames.len().checked_sub(1) else {
+            return Vec::new();
+        };
+        let frame = frame.clamp(0.0, last as f32);
+        let i = (frame.floor() as usize).min(last);
+        let u = frame - i as f32;
+        if u == 0.0 || i == last {
+            return self.frames[i].clone();
+        }
+        self.frames[i]
+            .iter()
+            .zip(&self.frames[i + 1])
+            .map(|(a, b)| a + (b - a) * u)
+            .collect()
+    }
+}
@@ -0,0 +1,44 @@
+//! Length units of coordinate files. Loaders convert to Å, the unit the viewer
+//! and all measurements work in, and record what the file used in
+//! `MoleculeMetadata`.
+
+/// Å per Bohr radius.
+pub const BOHR_IN_ANGSTROM: f32 = 0.529_177_2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LengthUnit {
+    /// Most formats (mol2, xyz, PDB).
+    #[default]
+    Angstrom,
+    /// GROMACS (.gro).
+    Nanometer,
+    /// Gaussian cube files (atomic units).
+    Bohr,
+}
+
+impl LengthUnit {
+    /// Length of one of this unit, in Å.
+    pub fn in_angstrom(self) -> f32 {
+        match self {
+            Self::Angstrom => 1.0,
+            Self::Nanometer => 10.0,
+            Self::Bohr => BOHR_IN_ANGSTROM,
+        }
+    }
+
+    /// `value` in this unit, expressed in `target`.
+    pub fn convert(self, value: f32, target: LengthUnit) -> f32 {
+        if self == target {
+            return value;
+        }
+        value * self.in_angstrom() / target.in_angstrom()
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::Angstrom => "Å",
+            Self::Nanometer => "nm",
+            Self::Bohr => "bohr",
+        }
+    }
+}
@@ -1,19 +1,399 @@
-use crate::molecule::Molecule;
+use crate::additional_render::segment_entity;
+use crate::camera::{Camera, ViewBookmark};
+use crate::camera_path::CameraPath;
+use crate::capture::{CaptureHook, EventMask};
+use crate::colormap::{Rgb, TintMode};
+use crate::command::{EditAction, ViewerCommand, ViewerHandle};
+use crate::composition::{self, CompositionEntry, CompositionOptions};
+use crate::convert::{quat_to_la, quat_to_na, to_la, to_na, vec_to_na};
+use crate::coordination::COORDINATION_CUTOFF;
+use crate::display_list::{Cylinder, DisplayList, Sphere};
+use crate::element;
+use crate::fingerprint::Fingerprint;
+use crate::measurement::Measurement;
+use crate::mesh;
+use crate::molecule::{Atom, Molecule};
+use crate::picking;
+use crate::raster::{self, Lens};
+use crate::selection::Selection;
+use crate::spatial::{NeighborGrid, RayGrid};
+use crate::style::{self, filter_opacity, BondShape, Style, DEFAULT_VIEW_DIR};
+use crate::trajectory::Trajectory;
 use crate::AdditionalRender;
-use graphics::{Entity, Mesh, Scene};
+use graphics::{EngineUpdates, Entity, EntityUpdate, Mesh, Scene};
 use lin_alg::f32::{Quaternion, Vec3};
+use nalgebra::{Isometry3, Point2, Point3, Rotation3, UnitQuaternion, Vector3};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::f32::consts::PI;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
 
-#[derive(Debug, Clone)]
+pub use crate::style::{
+    AtomRadiiTable, BondColoring, Cutaway, Detail, ElementScale, MoleculeDisplayState,
+    PinnedMotion, RenderStyle, Shading, Slab, ViewerSettings, VisibilityMode, ATOM_RADIUS,
+    BOND_COLOR, BOND_RADIUS, BOND_STUB_FRACTION, RING_PLANARITY_COLOR_MAX, TOON_LIGHT_DIR,
+    WIREFRAME_RADIUS,
+};
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ViewerEvent {
     AtomClicked(usize),
     BondClicked(usize),
     NothingClicked,
+    /// The cursor moved over this atom; see `ControllerSettings` for throttling.
+    AtomHovered(usize),
+    BondHovered(usize),
+    NothingHovered,
+    /// `auto_style` picked this style for a newly loaded molecule.
+    AutoStyleApplied(RenderStyle),
+    /// A transform gizmo drag moved by this much since the previous event.
+    /// Apply it to whatever the gizmo controls, e.g. with `transform_atoms`.
+    GizmoDragged(Isometry3<f32>),
+    /// A queued `ViewerCommand` could not be applied, and why.
+    CommandFailed(String),
+    /// `delete_atoms` removed these atoms, by their index before the edit,
+    /// from the active molecule. `undo` puts them back.
+    AtomsDeleted(Vec<usize>),
+    /// A keyboard shortcut asks for this edit; nothing has changed yet. Apply
+    /// it with `apply_edit` (or `ViewerCommand::Edit`), or drop it to veto.
+    EditRequested(EditAction),
+    /// `add_bond` created this bond of the active molecule. `undo` removes it.
+    BondCreated(usize),
+    /// `delete_bond` removed the bond with this index before the edit.
+    BondDeleted(usize),
+    /// The mouse is dragging `atom` of the active molecule, which should now
+    /// be at `target`; with `rigid`, its whole fragment follows. Apply it with
+    /// `handle_pick`. See `ControllerSettings::drag_atoms`.
+    AtomDragged {
+        atom: usize,
+        target: Point3<f32>,
+        rigid: bool,
+    },
+    /// The mouse button was released after `AtomDragged`. `handle_pick` ends
+    /// the drag with `end_atom_drag`.
+    AtomDragEnded,
+    /// A finished atom drag moved these atoms of the active molecule by these
+    /// displacements. `undo` moves them back.
+    AtomsMoved(Vec<(usize, Vector3<f32>)>),
+    /// The mouse moved or resized the cutaway, which should now be this one.
+    /// Apply it with `handle_pick`.
+    CutawayChanged(Cutaway),
+    /// The viewer's selection changed; these atoms of the active molecule are
+    /// now selected. `selected_bonds` has the bonds.
+    SelectionChanged(Vec<usize>),
+    /// A selection rectangle was dragged over these drawn atoms of the active
+    /// molecule; nothing has been selected yet. Select them with
+    /// `CameraController::select_on_click` or `set_selection`.
+    AtomsSelected(Vec<usize>),
+}
+
+/// Options for `MoleculeViewer::set_molecule_with`.
+#[derive(Debug, Clone)]
+pub struct LoadOptions {
+    /// Run `auto_style` on the new molecule.
+    pub auto_style: bool,
+    /// Frame the new molecule at the next `apply_auto_fit`. The viewer has
+    /// no camera of its own, so the application passes one in.
+    pub auto_fit: bool,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self {
+            auto_style: true,
+            auto_fit: true,
+        }
+    }
+}
+
+/// Options for `MoleculeViewer::add_molecule_with`.
+#[derive(Debug, Clone, Default)]
+pub struct AddOptions {
+    /// Add the molecule even if it duplicates, or is another conformer of, a
+    /// loaded one.
+    pub force: bool,
+}
+
+/// Outcome of `MoleculeViewer::add_molecule`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddResult {
+    /// Added with this id.
+    Added(usize),
+    /// Not added: molecule `id` has the same topology and positions.
+    Duplicate(usize),
+    /// Not added: molecule `id` has the same topology in another conformation
+    /// (or place). Force-add to load it as an additional conformer.
+    SameTopology(usize),
+}
+
+impl AddResult {
+    /// The new molecule's id, if it was added.
+    pub fn added(self) -> Option<usize> {
+        match self {
+            Self::Added(id) => Some(id),
+            _ => None,
+        }
+    }
+}
+
+/// Atom counts up to which `auto_style` keeps full ball-and-stick, then stick.
+pub const AUTO_STYLE_FULL_DETAIL_MAX: usize = 5_000;
+pub const AUTO_STYLE_STICK_MAX: usize = 50_000;
+
+/// Atom count from which `update_scene` indexes the active molecule for
+/// `pick`; smaller molecules are scanned atom by atom.
+pub const PICK_INDEX_MIN_ATOMS: usize = 1_000;
+/// Cell edge of the pick index, in Å: about one ball-and-stick atom across.
+const PICK_CELL_SIZE: f32 = 2.0;
+
+/// An atom or bond of the active molecule, by index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickTarget {
+    Atom(usize),
+    Bond(usize),
+}
+
+/// Where a ray from `MoleculeViewer::pick_hit` or `pick_all` hits an atom or
+/// bond.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PickResult {
+    pub target: PickTarget,
+    /// Along the ray, as a multiple of its direction.
+    pub t: f32,
+    /// The hit point on the atom's or bond's surface.
+    pub world_pos: Point3<f32>,
+}
+
+/// What a scene entity built by `update_scene` represents. See `update_scene`
+/// for the order entities are pushed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityKind {
+    Atom {
+        molecule: usize,
+        index: usize,
+    },
+    Bond {
+        molecule: usize,
+        index: usize,
+    },
+    Overlay {
+        renderer_id: usize,
+    },
+    /// The line drawn for the viewer's reference axis.
+    ReferenceAxis,
+    /// One segment of the wireframe drawn for the active molecule's cutaway.
+    Cutaway,
+    /// The ring marking pinned atom `index` of the active molecule.
+    PinMarker {
+        index: usize,
+    },
+    /// The shell around selected atom `index` of the active molecule.
+    SelectedAtom {
+        index: usize,
+    },
+    /// The sleeve around selected bond `index` of the active molecule.
+    SelectedBond {
+        index: usize,
+    },
+}
+
+/// What changed since the last `update_scene`, and so what it redraws. Set
+/// with `MoleculeViewer::mark_geometry_dirty` and its siblings; the viewer's
+/// own edits mark the narrowest that covers them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DirtyFlags {
+    /// Positions, visibility, style or anything else: rebuilds the scene.
+    /// Kept in `MoleculeViewer::dirty`.
+    pub geometry: bool,
+    /// Atom and bond colors, opacity or the hover highlight: rewrites the
+    /// molecule entities in place, keeping the meshes.
+    pub colors: bool,
+    /// The selection: re-pushes the selection shells and the overlays.
+    pub selection: bool,
+    /// Overlay contents: re-pushes the overlays.
+    pub overlays: bool,
+    /// The camera turned while bond orders are shown: moves the bond lines
+    /// to face it, in place like `colors`. Set by `update_scene` itself.
+    pub view: bool,
+}
+
+impl DirtyFlags {
+    pub fn any(&self) -> bool {
+        self.geometry || self.colors || self.selection || self.overlays || self.view
+    }
+}
+
+/// Scene meshes the molecule entities are scaled from; see
+/// `MoleculeViewer::ensure_meshes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MeshHandles {
+    /// Unit sphere, for atoms and bond caps.
+    pub sphere: usize,
+    /// Unit cylinder along Y, for bonds. With `Shading::Toon`, the first of
+    /// `TOON_BOND_MESHES` such cylinders, one per range of bond angles to
+    /// `TOON_LIGHT_DIR`.
+    pub cylinder: usize,
+}
+
+/// Cylinders pushed for `Shading::Toon`. Each is banded for bonds within
+/// 180° / `TOON_BOND_MESHES` of one angle to the light, so bond bands are
+/// that close to exact.
+pub const TOON_BOND_MESHES: usize = 8;
+
+/// Color of the line drawn for the reference axis.
+pub const REFERENCE_AXIS_COLOR: (f32, f32, f32) = (0.9, 0.2, 0.9);
+const REFERENCE_AXIS_RADIUS: f32 = 0.05;
+/// How far the reference axis line extends past the molecule at each end, in Å.
+const REFERENCE_AXIS_MARGIN: f32 = 2.0;
+
+/// Color of the wireframe drawn for the cutaway sphere.
+pub const CUTAWAY_COLOR: (f32, f32, f32) = (0.6, 0.75, 1.0);
+const CUTAWAY_LINE_RADIUS: f32 = 0.02;
+const CUTAWAY_LINE_OPACITY: f32 = 0.35;
+/// Segments per circle of the cutaway wireframe.
+const CUTAWAY_SEGMENTS: usize = 32;
+
+/// How far, as a distance between unit vectors, the camera's view direction
+/// turns before `show_bond_order` lines are rebuilt to face it again.
+const VIEW_DIR_TOLERANCE: f32 = 1e-3;
+
+/// How far the hovered atom or bond is lightened toward white.
+pub const HOVER_LIGHTEN: f32 = 0.35;
+/// Size of the hovered atom or bond (its radius, for a bond) relative to
+/// its normal size.
+pub const HOVER_SCALE: f32 = 1.1;
+
+/// Color of the rim drawn around pinned atoms.
+pub const PIN_MARKER_COLOR: (f32, f32, f32) = (0.05, 0.05, 0.08);
+/// Size of the pin rim relative to the atom.
+const PIN_MARKER_SCALE: f32 = 1.08;
+
+/// Color of the translucent shell drawn around selected atoms and bonds.
+pub const SELECTION_COLOR: (f32, f32, f32) = (1.0, 0.8, 0.2);
+pub const SELECTION_OPACITY: f32 = 0.35;
+/// Size of the selection shell (its radius, for a bond) relative to the atom
+/// or bond.
+pub const SELECTION_SCALE: f32 = 1.4;
+
+/// A direction in the molecule's own frame, such as a membrane normal or a
+/// crystal axis, that the camera can be turned to show pointing up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReferenceAxis {
+    /// Unit vector.
+    pub direction: Vector3<f32>,
+    pub label: String,
+}
+
+/// The active molecule before an undoable edit, with the index-keyed state
+/// a deletion remaps.
+struct UndoStep {
+    molecule: Molecule,
+    color_overrides: HashMap<usize, Rgb>,
+    hidden_atoms: BTreeSet<usize>,
+    selection: Option<Selection>,
+}
+
+/// An atom drag in progress: each atom that follows the grabbed one (first),
+/// with its weight and its position when the drag began.
+struct AtomDrag {
+    atoms: Vec<(usize, f32, Point3<f32>)>,
+    // Moved since the last `refresh_drag`.
+    stale: bool,
+}
+
+/// Where one renderer's entities and meshes went in the last `update_scene`.
+struct OverlayRange {
+    renderer_id: usize,
+    entities: Range<usize>,
+    meshes: Range<usize>,
 }
 
+/// Where the parts `update_scene` can redo on their own went in the scene.
+/// Everything from `selection` on is the selection shells and then the
+/// overlays.
+struct SceneLayout {
+    // Entities of each molecule drawn, by id.
+    molecules: Vec<(usize, Range<usize>)>,
+    // (first entity, first mesh) of the selection shells and of the overlays.
+    selection: (usize, usize),
+    overlays: (usize, usize),
+    mesh_count: usize,
+    // The active molecule's meshes.
+    meshes: MeshHandles,
+}
+
+struct MoleculeSlot {
+    molecule: Option<Molecule>,
+    state: MoleculeDisplayState,
+    // Of `molecule`, which only changes while active.
+    fingerprint: Option<Fingerprint>,
+}
+
+/// Shows one or more molecules. The active molecule lives in `molecule`,
+/// `render_style` and `settings`; the others keep their own
+/// `MoleculeDisplayState` until made active with `set_active_molecule`.
+///
+/// Overlays are drawn by `additional_render`, renderer id 0, and then by the
+/// renderers added with `add_renderer`, whose ids start at 1.
 pub struct MoleculeViewer<T: AdditionalRender> {
     pub molecule: Option<Molecule>,
+    /// Rebuild the whole scene at the next `update_scene`; the `geometry`
+    /// part of `dirty_flags`.
     pub dirty: bool,
+    pub settings: ViewerSettings,
+    pub render_style: RenderStyle,
     pub additional_render: Option<Box<T>>,
+    /// State given to molecules added with `add_molecule`.
+    pub default_state: MoleculeDisplayState,
+    // One entry per scene entity pushed by the last update_scene, in push order.
+    entity_kinds: Vec<EntityKind>,
+    pending_events: Vec<ViewerEvent>,
+    trajectory: Option<Trajectory>,
+    current_frame: f32,
+    // Positions of the current trajectory frame; `None` shows the molecule's own.
+    display_positions: Option<Vec<Point3<f32>>>,
+    // One entry per molecule; `None` at `active`, whose state is in the public fields.
+    slots: Vec<Option<MoleculeSlot>>,
+    active: usize,
+    reference_axis: Option<ReferenceAxis>,
+    // Created by the first `handle` call.
+    commands: Option<(Sender<ViewerCommand>, Receiver<ViewerCommand>)>,
+    // Edits on the active molecule, oldest first, for `undo`.
+    undo_stack: Vec<UndoStep>,
+    bond_mode: bool,
+    // First atom clicked in bond mode, waiting for the second.
+    pending_bond: Option<usize>,
+    atom_drag: Option<AtomDrag>,
+    capture_hook: Option<CaptureHook>,
+    // Last `composition_bar`, valid until the next rebuild or `dirty`.
+    composition_cache: Option<(CompositionOptions, Vec<CompositionEntry>)>,
+    // Camera view direction at the last rebuild, for `show_bond_order`.
+    view_dir: Vector3<f32>,
+    // A load asked for `apply_auto_fit`.
+    fit_pending: bool,
+    hovered: Option<PickTarget>,
+    // Of the active molecule; mirrored into the overlays' selections.
+    selection: Selection,
+    // From `add_renderer`, with their ids, in drawing order.
+    renderers: Vec<(usize, Box<dyn AdditionalRender>)>,
+    next_renderer_id: usize,
+    // Of the renderers that drew in the last update_scene, in push order.
+    overlay_ranges: Vec<OverlayRange>,
+    // The non-geometry parts of `dirty_flags`.
+    dirty_parts: DirtyFlags,
+    // Of the last full rebuild; `None` until one, or if there was nothing to draw.
+    layout: Option<SceneLayout>,
+    // The active molecule's atoms, then bonds, as of the last full rebuild;
+    // `None` for small molecules or once atoms have moved.
+    pick_index: Option<RayGrid>,
+}
+
+impl<T: AdditionalRender> Default for MoleculeViewer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T: AdditionalRender> MoleculeViewer<T> {
@@ -21,208 +401,2553 @@ impl<T: AdditionalRender> MoleculeViewer<T> {
         Self {
             molecule: None,
             dirty: false,
+            settings: ViewerSettings::default(),
+            render_style: RenderStyle::BallAndStick,
             additional_render: None,
+            default_state: MoleculeDisplayState::default(),
+            entity_kinds: Vec::new(),
+            pending_events: Vec::new(),
+            trajectory: None,
+            current_frame: 0.0,
+            display_positions: None,
+            slots: Vec::new(),
+            active: 0,
+            reference_axis: None,
+            commands: None,
+            undo_stack: Vec::new(),
+            bond_mode: false,
+            pending_bond: None,
+            atom_drag: None,
+            capture_hook: None,
+            composition_cache: None,
+            view_dir: DEFAULT_VIEW_DIR,
+            fit_pending: false,
+            hovered: None,
+            selection: Selection::new(),
+            renderers: Vec::new(),
+            next_renderer_id: 1,
+            overlay_ranges: Vec::new(),
+            dirty_parts: DirtyFlags::default(),
+            layout: None,
+            pick_index: None,
         }
     }
 
+    /// Loads a molecule with default `LoadOptions` (style chosen by `auto_style`,
+    /// camera framed by `apply_auto_fit`). Replaces the active molecule.
     pub fn set_molecule(&mut self, molecule: Molecule) {
+        self.set_molecule_with(molecule, LoadOptions::default());
+    }
+
+    pub fn set_molecule_with(&mut self, mut molecule: Molecule, options: LoadOptions) {
+        molecule.assign_atom_ids();
         self.molecule = Some(molecule);
+        self.trajectory = None;
+        self.display_positions = None;
+        self.reset_overlay_trajectory();
+        self.remap_measurements(|_| None);
+        self.undo_stack.clear();
+        self.pending_bond = None;
+        self.atom_drag = None;
+        self.hovered = None;
+        self.clear_selection();
         self.dirty = true;
+        self.fit_pending = options.auto_fit;
+        if options.auto_style {
+            self.auto_style();
+        }
     }
 
-    pub fn pick(&self, ray_origin: Vec3, ray_dir: Vec3) -> Option<ViewerEvent> {
-        let mut closest_t = f32::MAX;
-        let mut picked = None;
-
-        if let Some(mol) = &self.molecule {
-            // Check Atoms
-            for (i, atom) in mol.atoms.iter().enumerate() {
-                let pos = Vec3::new(atom.position.x, atom.position.y, atom.position.z);
-                let radius = 0.4; // Must match update_scene
-                if let Some(t) = Self::ray_sphere_intersect(ray_origin, ray_dir, pos, radius) {
-                    if t < closest_t && t > 0.0 {
-                        closest_t = t;
-                        picked = Some(ViewerEvent::AtomClicked(i));
-                    }
-                }
-            }
+    /// Frames the active molecule as displayed with `Camera::fit_to_molecule`,
+    /// leaving room for the largest atom as currently drawn. Does nothing
+    /// without a molecule.
+    pub fn fit_camera_to_molecule<C: Camera + ?Sized>(&self, camera: &mut C) {
+        let Some(mol) = self.displayed_molecule() else {
+            return;
+        };
+        let margin = mol
+            .atoms
+            .iter()
+            .map(|atom| self.atom_radius(atom))
+            .fold(0.0, f32::max);
+        camera.fit_to_molecule(&mol, margin);
+    }
 
-            // Check Bonds
-            for (i, bond) in mol.bonds.iter().enumerate() {
-                let a = mol.atoms[bond.atom_a].position;
-                let b = mol.atoms[bond.atom_b].position;
-                let p1 = Vec3::new(a.x, a.y, a.z);
-                let p2 = Vec3::new(b.x, b.y, b.z);
-                let radius = 0.15; // Must match update_scene
+    /// Calls `fit_camera_to_molecule` if a molecule was loaded with
+    /// `LoadOptions::auto_fit` since the last call, and returns whether it
+    /// did. Call it once per frame with the camera the scene is drawn from.
+    pub fn apply_auto_fit<C: Camera + ?Sized>(&mut self, camera: &mut C) -> bool {
+        if !std::mem::take(&mut self.fit_pending) || self.molecule.is_none() {
+            return false;
+        }
+        self.fit_camera_to_molecule(camera);
+        true
+    }
 
-                if let Some(t) = Self::ray_cylinder_intersect(ray_origin, ray_dir, p1, p2, radius) {
-                    if t < closest_t && t > 0.0 {
-                        closest_t = t;
-                        picked = Some(ViewerEvent::BondClicked(i));
-                    }
-                }
+    /// Adds a molecule with default `AddOptions`: unless it duplicates, or is
+    /// another conformer of, a loaded molecule (see `Molecule::topology_hash`).
+    pub fn add_molecule(&mut self, molecule: Molecule) -> AddResult {
+        self.add_molecule_with(molecule, AddOptions::default())
+    }
+
+    /// Adds a molecule in a new slot with a copy of `default_state`. The first
+    /// molecule loaded becomes active.
+    pub fn add_molecule_with(&mut self, molecule: Molecule, options: AddOptions) -> AddResult {
+        if !options.force {
+            if let Some(existing) = self.find_same_topology(&molecule) {
+                return existing;
             }
         }
+        AddResult::Added(self.push_molecule(molecule))
+    }
 
-        picked.or(Some(ViewerEvent::NothingClicked))
+    /// The first loaded molecule with `molecule`'s topology, as a duplicate if
+    /// the positions match too, preferring duplicates. Inactive molecules are
+    /// compared by their slots' fingerprints.
+    fn find_same_topology(&self, molecule: &Molecule) -> Option<AddResult> {
+        let fingerprint = molecule.fingerprint();
+        let mut same_topology = None;
+        for id in 0..self.molecule_count() {
+            let (other, other_fingerprint) = if id == self.active {
+                let Some(other) = &self.molecule else {
+                    continue;
+                };
+                (other, other.fingerprint())
+            } else {
+                let Some(Some(MoleculeSlot {
+                    molecule: Some(other),
+                    fingerprint: Some(other_fingerprint),
+                    ..
+                })) = self.slots.get(id)
+                else {
+                    continue;
+                };
+                (other, *other_fingerprint)
+            };
+            if other_fingerprint.topology != fingerprint.topology || !other.same_topology(molecule)
+            {
+                continue;
+            }
+            if other_fingerprint.coordinates == fingerprint.coordinates
+                || other.same_positions(molecule)
+            {
+                return Some(AddResult::Duplicate(id));
+            }
+            same_topology.get_or_insert(AddResult::SameTopology(id));
+        }
+        same_topology
     }
 
-    fn ray_sphere_intersect(
-        ray_origin: Vec3,
-        ray_dir: Vec3,
-        center: Vec3,
-        radius: f32,
-    ) -> Option<f32> {
-        let l = center - ray_origin;
-        let tca = l.dot(ray_dir);
-        if tca < 0.0 {
-            return None;
+    fn push_molecule(&mut self, mut molecule: Molecule) -> usize {
+        molecule.assign_atom_ids();
+        self.dirty = true;
+        if self.molecule.is_none() && self.slots.iter().all(Option::is_none) {
+            self.slots = vec![None];
+            self.active = 0;
+            self.molecule = Some(molecule);
+            self.render_style = self.default_state.render_style;
+            self.settings = self.default_state.settings.clone();
+            self.trajectory = None;
+            self.display_positions = None;
+            return 0;
         }
-        let d2 = l.dot(l) - tca * tca;
-        let r2 = radius * radius;
-        if d2 > r2 {
-            return None;
+        if self.slots.is_empty() {
+            self.slots.push(None);
+        }
+        self.slots.push(Some(MoleculeSlot {
+            fingerprint: Some(molecule.fingerprint()),
+            molecule: Some(molecule),
+            state: self.default_state.clone(),
+        }));
+        self.slots.len() - 1
+    }
+
+    /// Force-adds each molecule (they are typically copies of one) and tints
+    /// them with hue shifts spread evenly around the color wheel, the first
+    /// unshifted. Returns their ids.
+    pub fn add_molecules_with_auto_tint(
+        &mut self,
+        molecules: impl IntoIterator<Item = Molecule>,
+    ) -> Vec<usize> {
+        let ids: Vec<usize> = molecules
+            .into_iter()
+            .map(|mol| self.push_molecule(mol))
+            .collect();
+        for (k, &id) in ids.iter().enumerate() {
+            let degrees = 360.0 * k as f32 / ids.len() as f32;
+            self.set_tint(id, Some(TintMode::HueShift(degrees)));
+        }
+        ids
+    }
+
+    /// Sets the tint of molecule `id`, active or not (see `ViewerSettings::tint`).
+    /// Returns `false` for an unknown id.
+    pub fn set_tint(&mut self, id: usize, tint: Option<TintMode>) -> bool {
+        let settings = if id == self.active && id < self.molecule_count() {
+            &mut self.settings
+        } else if let Some(state) = self.display_state_mut(id) {
+            &mut state.settings
+        } else {
+            return false;
+        };
+        settings.tint = tint;
+        self.mark_colors_dirty();
+        true
+    }
+
+    pub fn molecule_count(&self) -> usize {
+        self.slots.len().max(self.molecule.is_some() as usize)
+    }
+
+    pub fn active_molecule(&self) -> usize {
+        self.active
+    }
+
+    /// Makes molecule `id` the one `molecule`, `render_style`, `settings`,
+    /// picking and overlays refer to. The previously active molecule keeps its
+    /// state in its slot. Drops any trajectory. Returns `false` for an unknown id.
+    pub fn set_active_molecule(&mut self, id: usize) -> bool {
+        if id == self.active {
+            return id < self.molecule_count();
+        }
+        let Some(slot) = self.slots.get_mut(id).and_then(Option::take) else {
+            return false;
+        };
+        let previous = MoleculeSlot {
+            fingerprint: self.molecule.as_ref().map(Molecule::fingerprint),
+            molecule: self.molecule.take(),
+            state: MoleculeDisplayState {
+                render_style: self.render_style,
+                settings: std::mem::replace(&mut self.settings, slot.state.settings),
+            },
+        };
+        self.slots[self.active] = Some(previous);
+        self.molecule = slot.molecule;
+        self.render_style = slot.state.render_style;
+        self.active = id;
+        self.trajectory = None;
+        self.display_positions = None;
+        self.reset_overlay_trajectory();
+        self.remap_measurements(|_| None);
+        self.undo_stack.clear();
+        self.pending_bond = None;
+        self.atom_drag = None;
+        self.hovered = None;
+        self.clear_selection();
+        self.dirty = true;
+        true
+    }
+
+    /// Molecule `id`, active or not.
+    pub fn molecule_at(&self, id: usize) -> Option<&Molecule> {
+        if id == self.active {
+            return self.molecule.as_ref();
+        }
+        self.slots.get(id)?.as_ref()?.molecule.as_ref()
+    }
+
+    /// Display state of molecule `id`; for the active molecule, a copy of the
+    /// public fields.
+    pub fn display_state(&self, id: usize) -> Option<MoleculeDisplayState> {
+        if id == self.active && id < self.molecule_count() {
+            return Some(MoleculeDisplayState {
+                render_style: self.render_style,
+                settings: self.settings.clone(),
+            });
+        }
+        self.slots.get(id)?.as_ref().map(|slot| slot.state.clone())
+    }
+
+    /// Mutable display state of an inactive molecule. Set `dirty` after changing it.
+    pub fn display_state_mut(&mut self, id: usize) -> Option<&mut MoleculeDisplayState> {
+        self.slots.get_mut(id)?.as_mut().map(|slot| &mut slot.state)
+    }
+
+    pub fn set_render_style(&mut self, style: RenderStyle) {
+        self.render_style = style;
+        self.dirty = true;
+    }
+
+    /// Chooses a style that stays responsive for the molecule's size:
+    /// - up to `AUTO_STYLE_FULL_DETAIL_MAX` atoms: ball-and-stick, full detail;
+    /// - up to `AUTO_STYLE_STICK_MAX` atoms: stick, low detail, hydrogens hidden;
+    /// - above that: low-detail space-filling (no bonds).
+    ///
+    /// Overwrites `render_style`, `settings.detail` and `settings.hide_hydrogens`,
+    /// which can be changed freely afterwards. Queues `ViewerEvent::AutoStyleApplied`.
+    pub fn auto_style(&mut self) -> Option<RenderStyle> {
+        let n_atoms = self.molecule.as_ref()?.atoms.len();
+        let (style, detail, hide_hydrogens) = if n_atoms <= AUTO_STYLE_FULL_DETAIL_MAX {
+            (RenderStyle::BallAndStick, Detail::Full, false)
+        } else if n_atoms <= AUTO_STYLE_STICK_MAX {
+            (RenderStyle::Stick, Detail::Low, true)
+        } else {
+            (RenderStyle::SpaceFilling, Detail::Low, false)
+        };
+        self.render_style = style;
+        self.settings.detail = detail;
+        self.settings.hide_hydrogens = hide_hydrogens;
+        self.dirty = true;
+        self.raise(ViewerEvent::AutoStyleApplied(style));
+        Some(style)
+    }
+
+    /// Animates the current molecule with `trajectory`, starting at frame 0.
+    /// Fails if there is no molecule or the atom counts differ.
+    pub fn set_trajectory(&mut self, trajectory: Trajectory) -> Result<(), String> {
+        let n_atoms = self.molecule.as_ref().map_or(0, |mol| mol.atoms.len());
+        if trajectory.is_empty() || trajectory.n_atoms() != n_atoms {
+            return Err(format!(
+                "trajectory has {} atoms per frame, molecule has {}",
+                trajectory.n_atoms(),
+                n_atoms
+            ));
         }
-        let thc = (r2 - d2).sqrt();
-        Some(tca - thc)
+        self.end_atom_drag();
+        self.trajectory = Some(trajectory);
+        self.reset_overlay_trajectory();
+        self.set_frame(0.0);
+        Ok(())
+    }
+
+    /// Drops the trajectory and shows the molecule's own coordinates again.
+    pub fn clear_trajectory(&mut self) {
+        self.trajectory = None;
+        self.display_positions = None;
+        self.reset_overlay_trajectory();
+        self.dirty = true;
     }
 
-    fn ray_cylinder_intersect(
-        ray_origin: Vec3,
-        ray_dir: Vec3,
-        p1: Vec3,
-        p2: Vec3,
-        radius: f32,
-    ) -> Option<f32> {
-        let ba = p2 - p1;
-        let oa = ray_origin - p1;
-        let baba = ba.dot(ba);
-        let bard = ba.dot(ray_dir);
-        let baoa = ba.dot(oa);
-        let roa = oa.dot(ray_dir);
-        let oaoa = oa.dot(oa);
+    fn reset_overlay_trajectory(&mut self) {
+        for (_, renderer) in overlays_mut(&mut self.additional_render, &mut self.renderers) {
+            renderer.trajectory_reset();
+        }
+    }
 
-        let a = baba - bard * bard;
-        let b = baba * roa - baoa * bard;
-        let c = baba * oaoa - baoa * baoa - radius * radius * baba;
-        let h = b * b - a * c;
+    pub fn trajectory(&self) -> Option<&Trajectory> {
+        self.trajectory.as_ref()
+    }
 
-        if h >= 0.0 {
-            let t = (-b - h.sqrt()) / a;
-            let y = baoa + t * bard;
-            // Check body
-            if y > 0.0 && y < baba {
-                return Some(t);
+    /// Shows the trajectory at a (possibly fractional) frame index. Rendering and
+    /// picking both use the interpolated positions.
+    pub fn set_frame(&mut self, frame: f32) {
+        let Some(trajectory) = &self.trajectory else {
+            return;
+        };
+        self.current_frame = frame.clamp(0.0, (trajectory.len() - 1) as f32);
+        self.display_positions = Some(trajectory.positions_at(self.current_frame));
+        self.dirty = true;
+        if let Some(mol) = displayed(&self.molecule, &self.display_positions) {
+            for (_, renderer) in overlays_mut(&mut self.additional_render, &mut self.renderers) {
+                renderer.trajectory_frame(self.current_frame, &mol);
             }
-            // Caps are not checked here for simplicity, but usually fine for picking
         }
-        None
     }
 
-    /// Updates the graphics scene based on the current molecule data.
-    pub fn update_scene(&mut self, scene: &mut Scene) {
-        if !self.dirty {
+    pub fn current_frame(&self) -> f32 {
+        self.current_frame
+    }
+
+    /// The molecule as currently displayed: borrowed as-is when static, or a copy
+    /// at the current trajectory frame's positions during playback.
+    pub fn displayed_molecule(&self) -> Option<Cow<'_, Molecule>> {
+        displayed(&self.molecule, &self.display_positions)
+    }
+
+    /// Moves the given atoms of the active molecule by `transform`; indices out of
+    /// range are skipped. Pinned atoms stay in place, or with
+    /// `PinnedMotion::Refuse` nothing moves and `ViewerEvent::CommandFailed` is
+    /// raised. Trajectory frames are not changed, so the move only shows once
+    /// the trajectory is cleared.
+    pub fn transform_atoms(&mut self, atoms: &[usize], transform: &Isometry3<f32>) {
+        let pinned = self
+            .molecule
+            .as_ref()
+            .and_then(|mol| atoms.iter().find(|&&i| mol.is_pinned(i)));
+        if let Some(pinned) = pinned.filter(|_| self.settings.pinned_motion == PinnedMotion::Refuse)
+        {
+            self.raise(ViewerEvent::CommandFailed(format!(
+                "transform: atom {pinned} is pinned"
+            )));
             return;
         }
-        self.dirty = false;
+        let Some(mol) = &mut self.molecule else {
+            return;
+        };
+        for &i in atoms {
+            if mol.is_pinned(i) {
+                continue;
+            }
+            if let Some(atom) = mol.atoms.get_mut(i) {
+                atom.position = transform * atom.position;
+            }
+        }
+        self.dirty = true;
+    }
 
-        if let Some(mol) = &self.molecule {
-            scene.meshes.clear();
-            scene.entities.clear();
-
-            // 1. Create Meshes
-            // Sphere for atoms (Radius 1.0, but we scale it)
-            // 3 subdivisions gives a decent sphere.
-            let sphere_mesh = Mesh::new_sphere(1.0, 3);
-            let sphere_idx = scene.meshes.len();
-            scene.meshes.push(sphere_mesh);
-
-            // Cylinder for bonds (Length 1.0, Radius 1.0, along Y)
-            // 10 sides is enough for thin bonds
-            let cyl_mesh = Mesh::new_cylinder(1.0, 1.0, 10);
-            let cyl_idx = scene.meshes.len();
-            scene.meshes.push(cyl_mesh);
-
-            // 2. Create Entities
-            // Atoms
-            for atom in &mol.atoms {
-                // Convert nalgebra Point3 to graphics Vec3
-                // Assuming nalgebra::Point3 fields are x, y, z or coords[0], etc.
-                // But atom.position is Point3 from nalgebra.
-                let pos = Vec3::new(atom.position.x, atom.position.y, atom.position.z);
-
-                let color = match atom.element.as_str() {
-                    "C" => (0.1, 0.1, 0.1),  // Black/Dark Grey
-                    "H" => (0.9, 0.9, 0.9),  // White
-                    "O" => (0.9, 0.1, 0.1),  // Red
-                    "N" => (0.1, 0.1, 0.9),  // Blue
-                    "S" => (0.9, 0.9, 0.1),  // Yellow
-                    "P" => (1.0, 0.6, 0.0),  // Orange
-                    "Cl" => (0.1, 0.9, 0.1), // Green
-                    _ => (0.7, 0.7, 0.7),    // Grey
-                };
+    /// Pins `atoms` of the active molecule, so that transforms and drags
+    /// leave them in place (see `settings.pinned_motion`). Pins are part of
+    /// the molecule: they follow deletions and are restored by `undo`.
+    /// Fails, changing nothing, for out-of-range atoms.
+    pub fn pin_atoms(&mut self, atoms: &[usize]) -> Result<(), String> {
+        let n_atoms = self.molecule.as_ref().map_or(0, |mol| mol.atoms.len());
+        if let Some(&atom) = atoms.iter().find(|&&i| i >= n_atoms) {
+            return Err(format!("pin: no atom {atom}"));
+        }
+        if let Some(mol) = &mut self.molecule {
+            mol.pinned.extend(atoms);
+            self.dirty = true;
+        }
+        Ok(())
+    }
+
+    pub fn unpin_atoms(&mut self, atoms: &[usize]) {
+        if let Some(mol) = &mut self.molecule {
+            for i in atoms {
+                mol.pinned.remove(i);
+            }
+            self.dirty = true;
+        }
+    }
 
-                let radius = 0.4; // Base radius
+    pub fn unpin_all(&mut self) {
+        if let Some(mol) = self.molecule.as_mut().filter(|mol| !mol.pinned.is_empty()) {
+            mol.pinned.clear();
+            self.dirty = true;
+        }
+    }
 
-                scene.entities.push(Entity::new(
-                    sphere_idx,
-                    pos,
-                    Quaternion::new_identity(),
-                    radius, // Uniform scale
-                    color,
-                    0.2, // Low shininess
-                ));
+    /// Pins `atom` if it is not pinned and unpins it otherwise. Returns
+    /// whether it is pinned now.
+    pub fn toggle_pin(&mut self, atom: usize) -> Result<bool, String> {
+        let pinned = self
+            .molecule
+            .as_ref()
+            .is_some_and(|mol| mol.is_pinned(atom));
+        if pinned {
+            self.unpin_atoms(&[atom]);
+        } else {
+            self.pin_atoms(&[atom])?;
+        }
+        Ok(!pinned)
+    }
+
+    /// Moves the whole active molecule by `transform` (see `transform_atoms`).
+    pub fn transform_molecule(&mut self, transform: &Isometry3<f32>) {
+        let n_atoms = self.molecule.as_ref().map_or(0, |mol| mol.atoms.len());
+        let atoms: Vec<usize> = (0..n_atoms).collect();
+        self.transform_atoms(&atoms, transform);
+    }
+
+    /// Removes `atoms` and their bonds from the active molecule and reports
+    /// `ViewerEvent::AtomsDeleted`. The selection, `color_overrides` and
+    /// `hidden_atoms` follow the renumbered atoms. Fails, changing nothing,
+    /// for out-of-range atoms or while a trajectory is loaded (its frames
+    /// would no longer match).
+    pub fn delete_atoms(&mut self, atoms: &[usize]) -> Result<(), String> {
+        let n_atoms = self.molecule.as_ref().map_or(0, |mol| mol.atoms.len());
+        if let Some(&atom) = atoms.iter().find(|&&i| i >= n_atoms) {
+            return Err(format!("delete: no atom {atom}"));
+        }
+        if self.trajectory.is_some() {
+            return Err("delete: not while a trajectory is loaded".to_string());
+        }
+        self.end_atom_drag();
+        let mut deleted = atoms.to_vec();
+        deleted.sort_unstable();
+        deleted.dedup();
+        let Some(molecule) = self.molecule.as_mut().filter(|_| !deleted.is_empty()) else {
+            return Ok(());
+        };
+        self.undo_stack.push(UndoStep {
+            molecule: molecule.clone(),
+            color_overrides: self.settings.color_overrides.clone(),
+            hidden_atoms: self.settings.hidden_atoms.clone(),
+            selection: Some(self.selection.clone()),
+        });
+        self.pending_bond = None;
+        self.hovered = None;
+
+        // Bonds keep their order; those touching a deleted atom go.
+        let mut next_bond = 0;
+        let new_bond_index: Vec<Option<usize>> = molecule
+            .bonds
+            .iter()
+            .map(|bond| {
+                let kept = deleted.binary_search(&bond.atom_a).is_err()
+                    && deleted.binary_search(&bond.atom_b).is_err();
+                kept.then(|| {
+                    next_bond += 1;
+                    next_bond - 1
+                })
+            })
+            .collect();
+        let new_index = molecule.remove_atoms(&deleted);
+        let renumber = |i: usize| new_index.get(i).copied().flatten();
+        let overrides = std::mem::take(&mut self.settings.color_overrides);
+        self.settings.color_overrides = overrides
+            .into_iter()
+            .filter_map(|(i, color)| Some((renumber(i)?, color)))
+            .collect();
+        let hidden = std::mem::take(&mut self.settings.hidden_atoms);
+        self.settings.hidden_atoms = hidden.into_iter().filter_map(renumber).collect();
+        let mut selection =
+            Selection::from_atoms(self.selection.atoms().iter().filter_map(|&i| renumber(i)));
+        self.remap_measurements(renumber);
+        for &bond in self.selection.bonds() {
+            if let Some(Some(bond)) = new_bond_index.get(bond) {
+                selection.insert_bond(*bond);
+            }
+        }
+        self.dirty = true;
+        self.raise(ViewerEvent::AtomsDeleted(deleted));
+        self.set_selection(selection);
+        Ok(())
+    }
+
+    /// Bonds atoms `a` and `b` of the active molecule with a single bond
+    /// (`Molecule::add_bond`) and reports `ViewerEvent::BondCreated`.
+    /// Undoable. Fails, changing nothing, for self-bonds, atoms already
+    /// bonded and out-of-range atoms.
+    pub fn add_bond(&mut self, a: usize, b: usize) -> Result<usize, String> {
+        let molecule = self.molecule.as_mut().ok_or("bond: no molecule")?;
+        let before = molecule.clone();
+        let bond = molecule.add_bond(a, b, 1)?;
+        self.push_undo(before);
+        self.dirty = true;
+        self.raise(ViewerEvent::BondCreated(bond));
+        Ok(bond)
+    }
+
+    /// Removes bond `bond` of the active molecule and reports
+    /// `ViewerEvent::BondDeleted`. Selected bonds follow the renumbering.
+    /// Undoable.
+    pub fn delete_bond(&mut self, bond: usize) -> Result<(), String> {
+        let molecule = self
+            .molecule
+            .as_mut()
+            .filter(|mol| bond < mol.bonds.len())
+            .ok_or_else(|| format!("delete bond: no bond {bond}"))?;
+        let before = molecule.clone();
+        molecule.bonds.remove(bond);
+        self.undo_stack.push(UndoStep {
+            molecule: before,
+            color_overrides: self.settings.color_overrides.clone(),
+            hidden_atoms: self.settings.hidden_atoms.clone(),
+            selection: Some(self.selection.clone()),
+        });
+        let mut selection = Selection::from_atoms(self.selection.atoms().iter().copied());
+        for &other in self.selection.bonds() {
+            if other != bond {
+                selection.insert_bond(if other > bond { other - 1 } else { other });
+            }
+        }
+        self.hovered = None;
+        self.dirty = true;
+        self.raise(ViewerEvent::BondDeleted(bond));
+        self.set_selection(selection);
+        Ok(())
+    }
+
+    /// Rounds the fractional coordinates of `atom` of the active molecule to
+    /// the nearest multiple of 1/`denominator` (`Molecule::snap_to_fraction`).
+    /// Undoable. Fails, changing nothing, without a lattice, for a pinned or
+    /// out-of-range atom or a zero denominator.
+    pub fn snap_to_fraction(&mut self, atom: usize, denominator: u32) -> Result<(), String> {
+        let molecule = self.molecule.as_mut().ok_or("snap: no molecule")?;
+        if molecule.lattice.is_none() {
+            return Err("snap: the molecule has no lattice".to_string());
+        }
+        if molecule.is_pinned(atom) {
+            return Err(format!("snap: atom {atom} is pinned"));
+        }
+        let before = molecule.clone();
+        if !molecule.snap_to_fraction(atom, denominator) {
+            return Err(format!("snap: no atom {atom} or zero denominator"));
+        }
+        self.push_undo(before);
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// Records `molecule` for `undo` of an edit that renumbers no atoms.
+    fn push_undo(&mut self, molecule: Molecule) {
+        self.undo_stack.push(UndoStep {
+            molecule,
+            color_overrides: self.settings.color_overrides.clone(),
+            hidden_atoms: self.settings.hidden_atoms.clone(),
+            selection: None,
+        });
+    }
+
+    /// Starts dragging `atom` of the active molecule, ending any drag in
+    /// progress. Atoms within `settings.drag_falloff_shells` bonds follow it
+    /// with halving weights per bond, or with `rigid` its whole fragment
+    /// follows. Pinned followers stay in place; with `PinnedMotion::Refuse`
+    /// a rigid drag of a fragment with pinned atoms fails. The drag is one
+    /// undo step. Fails for a pinned or out-of-range atom or while a
+    /// trajectory is loaded.
+    pub fn begin_atom_drag(&mut self, atom: usize, rigid: bool) -> Result<(), String> {
+        self.end_atom_drag();
+        if self.trajectory.is_some() {
+            return Err("drag: not while a trajectory is loaded".to_string());
+        }
+        let Some(mol) = self.molecule.as_ref().filter(|mol| atom < mol.atoms.len()) else {
+            return Err(format!("drag: no atom {atom}"));
+        };
+        if mol.is_pinned(atom) {
+            return Err(format!("drag: atom {atom} is pinned"));
+        }
+        let max_shells = match rigid {
+            true => usize::MAX,
+            false => self.settings.drag_falloff_shells,
+        };
+        let shells = mol.bond_shells(atom, max_shells);
+        if rigid && self.settings.pinned_motion == PinnedMotion::Refuse {
+            if let Some(&(pinned, _)) = shells.iter().find(|&&(i, _)| mol.is_pinned(i)) {
+                return Err(format!("drag: atom {pinned} of the fragment is pinned"));
             }
+        }
+        let atoms = shells
+            .into_iter()
+            .filter(|&(i, _)| !mol.is_pinned(i))
+            .map(|(i, shell)| {
+                let weight = if rigid {
+                    1.0
+                } else {
+                    0.5f32.powi(shell as i32)
+                };
+                (i, weight, mol.atoms[i].position)
+            })
+            .collect();
+        self.push_undo(mol.clone());
+        self.atom_drag = Some(AtomDrag {
+            atoms,
+            stale: false,
+        });
+        Ok(())
+    }
 
-            // Bonds
-            for bond in &mol.bonds {
-                let a = mol.atoms[bond.atom_a].position;
-                let b = mol.atoms[bond.atom_b].position;
+    /// Moves the dragged atom to `target` and its followers by their share of
+    /// the displacement. Does not set `dirty`; show the move with
+    /// `refresh_drag`. Returns `false` if no drag is in progress.
+    pub fn drag_atom_to(&mut self, target: Point3<f32>) -> bool {
+        let (Some(drag), Some(mol)) = (&mut self.atom_drag, &mut self.molecule) else {
+            return false;
+        };
+        let delta = target - drag.atoms[0].2;
+        for &(i, weight, start) in &drag.atoms {
+            mol.atoms[i].position = start + delta * weight;
+        }
+        drag.stale = true;
+        self.pick_index = None;
+        true
+    }
 
-                let p1 = Vec3::new(a.x, a.y, a.z);
-                let p2 = Vec3::new(b.x, b.y, b.z);
+    /// The atom being dragged, if any.
+    pub fn dragged_atom(&self) -> Option<usize> {
+        self.atom_drag.as_ref().map(|drag| drag.atoms[0].0)
+    }
 
-                let diff = p2 - p1;
-                let len = diff.magnitude();
+    /// Shows the latest `drag_atom_to` without rebuilding the scene: moves
+    /// the dragged atoms' entities, redraws their bonds in place and
+    /// refreshes the overlays. Filters, colors and ambient occlusion catch
+    /// up when the drag ends. Returns `true` if entities changed and need
+    /// pushing to the engine; if the scene could not be patched, sets
+    /// `dirty` instead so the next `update_scene` rebuilds it.
+    pub fn refresh_drag(&mut self, scene: &mut Scene) -> bool {
+        let Some(drag) = self.atom_drag.as_mut().filter(|drag| drag.stale) else {
+            return false;
+        };
+        drag.stale = false;
+        let moved: BTreeSet<usize> = drag.atoms.iter().map(|&(i, ..)| i).collect();
+        if self.dirty || !self.patch_moved_atoms(scene, &moved) {
+            self.dirty = true;
+            return false;
+        }
+        self.refresh_overlays(scene);
+        true
+    }
 
-                // If atoms are overlapping, skip bond
-                if len < 0.001 {
-                    continue;
+    /// Moves the entities of the active molecule's `moved` atoms and rebuilds
+    /// those of their bonds. `false` if the scene no longer matches what
+    /// `update_scene` built, or a bond now needs different entities.
+    fn patch_moved_atoms(&self, scene: &mut Scene, moved: &BTreeSet<usize>) -> bool {
+        let Some(mol) = &self.molecule else {
+            return false;
+        };
+        let Some(layout) = &self.layout else {
+            return false;
+        };
+        if self.entity_kinds.len() > scene.entities.len() {
+            return false;
+        }
+        let bonds: Vec<usize> = (0..mol.bonds.len())
+            .filter(|&i| {
+                let bond = &mol.bonds[i];
+                moved.contains(&bond.atom_a) || moved.contains(&bond.atom_b)
+            })
+            .collect();
+        let mut shapes =
+            style::bond_shapes_of(mol, self.style(), bonds.iter().copied()).into_iter();
+        for (kind, entity) in self.entity_kinds.iter().zip(&mut scene.entities) {
+            match *kind {
+                EntityKind::Atom { molecule, index }
+                    if molecule == self.active && moved.contains(&index) =>
+                {
+                    entity.position = to_la(mol.atoms[index].position);
+                }
+                EntityKind::Bond { molecule, index }
+                    if molecule == self.active && bonds.binary_search(&index).is_ok() =>
+                {
+                    *entity = match shapes.next() {
+                        Some((i, BondShape::Cylinder(cylinder))) if i == index => {
+                            bond_entity(layout.meshes.cylinder, self.style(), &cylinder)
+                        }
+                        Some((i, BondShape::Cap(cap))) if i == index => {
+                            sphere_entity(entity.mesh, &cap)
+                        }
+                        _ => return false,
+                    };
                 }
+                _ => {}
+            }
+        }
+        shapes.next().is_none()
+    }
 
-                let mid = (p1 + p2) * 0.5;
-
-                // Orientation: Rotate Y-up cylinder to match `diff` direction
-                let dir = diff.to_normalized();
-                let up = Vec3::new(0.0, 1.0, 0.0);
-
-                // Calculate rotation from UP to DIR
-                // Quaternion from cross product?
-                // Let's rely on standard way:
-                // axis = cross(u, v)
-                // angle = acos(dot(u, v))
-                // but we need to handle parallel case.
-
-                let orientation = Quaternion::from_unit_vecs(up, dir);
-
-                let bond_radius = 0.15;
-                let scale_partial = Vec3::new(bond_radius, len, bond_radius);
-
-                let mut entity = Entity::new(
-                    cyl_idx,
-                    mid,
-                    orientation,
-                    1.0,             // Base scale, overridden by partial
-                    (0.5, 0.5, 0.5), // Grey bonds
-                    0.1,
-                );
-                entity.scale_partial = Some(scale_partial);
-                scene.entities.push(entity);
+    /// Finishes the atom drag, reports `ViewerEvent::AtomsMoved` and sets
+    /// `dirty` for a full redraw. A drag that moved nothing leaves no undo
+    /// step. Returns `false` if no atom moved or no drag was in progress.
+    pub fn end_atom_drag(&mut self) -> bool {
+        let (Some(drag), Some(mol)) = (self.atom_drag.take(), &self.molecule) else {
+            return false;
+        };
+        let moved: Vec<(usize, Vector3<f32>)> = drag
+            .atoms
+            .iter()
+            .map(|&(i, _, start)| (i, mol.atoms[i].position - start))
+            .filter(|(_, delta)| *delta != Vector3::zeros())
+            .collect();
+        if moved.is_empty() {
+            self.undo_stack.pop();
+            return false;
+        }
+        self.dirty = true;
+        self.raise(ViewerEvent::AtomsMoved(moved));
+        true
+    }
+
+    /// Reverts the last `delete_atoms`, `add_bond`, `delete_bond` or atom drag,
+    /// restoring the molecule and the state a deletion renumbered. Returns
+    /// `false` if there is nothing to undo; loading or switching the active
+    /// molecule forgets the history.
+    pub fn undo(&mut self) -> bool {
+        let Some(step) = self.undo_stack.pop() else {
+            return false;
+        };
+        // Measurements follow their atoms back to where a deletion took them
+        // from, by atom id.
+        if let Some(current) = self.molecule.take() {
+            self.remap_measurements(|i| step.molecule.index_of(current.atom_id(i)?));
+        }
+        self.molecule = Some(step.molecule);
+        self.settings.color_overrides = step.color_overrides;
+        self.settings.hidden_atoms = step.hidden_atoms;
+        if let Some(before) = step.selection {
+            self.set_selection(before);
+        }
+        self.pending_bond = None;
+        self.atom_drag = None;
+        self.hovered = None;
+        self.dirty = true;
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    /// A handle other threads can queue commands on. All handles share one queue.
+    pub fn handle(&mut self) -> ViewerHandle {
+        let (sender, _) = self.commands.get_or_insert_with(mpsc::channel);
+        ViewerHandle::new(sender.clone())
+    }
+
+    /// Applies queued commands in the order they were sent; call once per frame.
+    /// Failures are reported through `take_events` as `ViewerEvent::CommandFailed`.
+    /// Returns how many commands were taken off the queue.
+    pub fn drain_commands(&mut self) -> usize {
+        let Some((_, receiver)) = &self.commands else {
+            return 0;
+        };
+        let commands: Vec<ViewerCommand> = receiver.try_iter().collect();
+        let count = commands.len();
+        for command in commands {
+            if let Err(reason) = self.apply_command(command) {
+                self.raise(ViewerEvent::CommandFailed(reason));
+            }
+        }
+        count
+    }
+
+    /// Turns bond mode on or off, dropping any pending first atom. In bond
+    /// mode `handle_pick` edits bonds instead of reacting to clicks as usual.
+    pub fn set_bond_mode(&mut self, on: bool) {
+        self.bond_mode = on;
+        self.cancel_pending_bond();
+    }
+
+    pub fn bond_mode(&self) -> bool {
+        self.bond_mode
+    }
+
+    /// The atom clicked first in bond mode, waiting for the one to bond it to.
+    pub fn pending_bond(&self) -> Option<usize> {
+        self.pending_bond
+    }
+
+    /// Forgets the pending first atom of bond mode, if any, and clears the
+    /// selection that highlighted it.
+    pub fn cancel_pending_bond(&mut self) {
+        if self.pending_bond.take().is_none() {
+            return;
+        }
+        self.clear_selection();
+        self.dirty = true;
+    }
+
+    /// Bond mode's reaction to a click: the first atom clicked is selected
+    /// to show it is pending, and the second is bonded to it with
+    /// `add_bond`. Clicking a bond deletes it and clicking empty space
+    /// cancels. Failed edits are reported as `ViewerEvent::CommandFailed`;
+    /// a self-bond keeps the first atom pending.
+    fn bond_tool_pick(&mut self, event: &ViewerEvent) -> bool {
+        let result = match (event, self.pending_bond) {
+            (ViewerEvent::AtomClicked(i), None) => {
+                self.pending_bond = Some(*i);
+                self.set_selection(Selection::from_atoms([*i]));
+                self.dirty = true;
+                return true;
             }
+            (ViewerEvent::AtomClicked(i), Some(first)) if *i == first => {
+                Err(format!("bond: atom {first} cannot bond to itself"))
+            }
+            (ViewerEvent::AtomClicked(i), Some(first)) => {
+                self.cancel_pending_bond();
+                self.add_bond(first, *i).map(drop)
+            }
+            (ViewerEvent::BondClicked(i), _) => {
+                self.cancel_pending_bond();
+                self.delete_bond(*i)
+            }
+            (ViewerEvent::NothingClicked, pending) => {
+                self.cancel_pending_bond();
+                return pending.is_some();
+            }
+            _ => return false,
+        };
+        if let Err(reason) = result {
+            self.raise(ViewerEvent::CommandFailed(reason));
+        }
+        true
+    }
+
+    /// The selected atoms and bonds of the active molecule. `update_scene`
+    /// draws a translucent shell around them (see `SELECTION_COLOR`), and an
+    /// overlay with a selection, like `SelectedAtomRender`, gets a copy.
+    pub fn selection(&self) -> &Selection {
+        &self.selection
+    }
 
-            if let Some(additional_render) = &self.additional_render {
-                additional_render.update_scene(scene, mol);
+    /// Selected atoms in ascending order.
+    pub fn selected_atoms(&self) -> &[usize] {
+        self.selection.atoms()
+    }
+
+    /// Selected bonds in ascending order.
+    pub fn selected_bonds(&self) -> &[usize] {
+        self.selection.bonds()
+    }
+
+    /// Replaces the selection. Like the other selection edits, reports
+    /// `ViewerEvent::SelectionChanged` and returns `true` if it changed.
+    /// Indices are not checked against the molecule.
+    pub fn set_selection(&mut self, selection: Selection) -> bool {
+        if self.selection == selection {
+            return false;
+        }
+        self.selection = selection;
+        for (_, renderer) in overlays_mut(&mut self.additional_render, &mut self.renderers) {
+            if let Some(mirror) = renderer.selection_mut() {
+                *mirror = self.selection.clone();
             }
         }
+        self.mark_selection_dirty();
+        let atoms = self.selection.atoms().to_vec();
+        self.raise(ViewerEvent::SelectionChanged(atoms));
+        true
+    }
+
+    /// Applies `edit` to a copy of the selection and keeps the result.
+    fn edit_selection(&mut self, edit: impl FnOnce(&mut Selection)) -> bool {
+        let mut selection = self.selection.clone();
+        edit(&mut selection);
+        self.set_selection(selection)
+    }
+
+    /// Adds `atom` of the active molecule to the selection. Returns whether
+    /// the selection changed; out-of-range atoms change nothing.
+    pub fn select_atom(&mut self, atom: usize) -> bool {
+        let n_atoms = self.molecule.as_ref().map_or(0, |mol| mol.atoms.len());
+        atom < n_atoms
+            && self.edit_selection(|s| {
+                s.insert(atom);
+            })
+    }
+
+    pub fn deselect_atom(&mut self, atom: usize) -> bool {
+        self.edit_selection(|s| {
+            s.remove(atom);
+        })
+    }
+
+    /// Selects `atom` if it was not selected, and deselects it otherwise.
+    /// Returns whether the selection changed.
+    pub fn toggle_atom(&mut self, atom: usize) -> bool {
+        if self.selection.contains(atom) {
+            self.deselect_atom(atom)
+        } else {
+            self.select_atom(atom)
+        }
+    }
+
+    /// Adds `bond` of the active molecule to the selection. Returns whether
+    /// the selection changed; out-of-range bonds change nothing.
+    pub fn select_bond(&mut self, bond: usize) -> bool {
+        let n_bonds = self.molecule.as_ref().map_or(0, |mol| mol.bonds.len());
+        bond < n_bonds
+            && self.edit_selection(|s| {
+                s.insert_bond(bond);
+            })
+    }
+
+    pub fn deselect_bond(&mut self, bond: usize) -> bool {
+        self.edit_selection(|s| {
+            s.remove_bond(bond);
+        })
+    }
+
+    pub fn toggle_bond(&mut self, bond: usize) -> bool {
+        if self.selection.contains_bond(bond) {
+            self.deselect_bond(bond)
+        } else {
+            self.select_bond(bond)
+        }
+    }
+
+    /// Deselects every atom and bond. Returns whether anything was selected.
+    pub fn clear_selection(&mut self) -> bool {
+        self.set_selection(Selection::new())
+    }
+
+    /// The atom or bond `update_scene` highlights as under the cursor.
+    pub fn hovered(&self) -> Option<PickTarget> {
+        self.hovered
+    }
+
+    /// Highlights `target` (see `HOVER_LIGHTEN` and `HOVER_SCALE`) from the
+    /// next `update_scene`, or nothing. `handle_pick` calls this for hover
+    /// events. Returns whether the highlight changed.
+    pub fn set_hovered(&mut self, target: Option<PickTarget>) -> bool {
+        if self.hovered == target {
+            return false;
+        }
+        self.hovered = target;
+        self.mark_colors_dirty();
+        true
+    }
+
+    /// Measures the selected atoms into the first `MeasurementTool` overlay,
+    /// where it is drawn and labeled like a picked one: the distance between
+    /// two atoms, or the angle at one of three. The angle's vertex is the
+    /// atom bonded to the other two, or else the one with the widest angle.
+    /// Fails for other selections or if no overlay measures.
+    pub fn measure_selection(&mut self) -> Result<Measurement, String> {
+        let mol =
+            displayed(&self.molecule, &self.display_positions).ok_or("measure: no molecule")?;
+        let atoms = match *self.selection.atoms() {
+            [a, b] => vec![a, b],
+            [a, b, c] => {
+                let bonded = |v: usize, x: usize, y: usize| {
+                    mol.find_bond(v, x).is_some() && mol.find_bond(v, y).is_some()
+                };
+                let angle = |v: usize, x: usize, y: usize| mol.angle(v, x, y).unwrap_or(0.0);
+                let candidates = [(a, b, c), (b, a, c), (c, a, b)];
+                let vertex = candidates
+                    .into_iter()
+                    .find(|&(v, x, y)| bonded(v, x, y))
+                    .or_else(|| {
+                        candidates
+                            .into_iter()
+                            .max_by(|p, q| angle(p.0, p.1, p.2).total_cmp(&angle(q.0, q.1, q.2)))
+                    });
+                let (v, x, y) = vertex.expect("three candidates");
+                vec![x, v, y]
+            }
+            ref atoms => {
+                return Err(format!(
+                    "measure: select two or three atoms, not {}",
+                    atoms.len()
+                ))
+            }
+        };
+        let tool = overlays_mut(&mut self.additional_render, &mut self.renderers)
+            .find_map(|(_, renderer)| renderer.measurement_mut())
+            .ok_or("measure: no measurement overlay")?;
+        let measurement = tool
+            .measure(&mol, &atoms)
+            .ok_or(format!("measure: cannot measure atoms {atoms:?}"))?;
+        self.mark_overlays_dirty();
+        Ok(measurement)
+    }
+
+    /// Reacts to a pick result from the controller. `AtomDragged` starts or
+    /// continues an atom drag (see `begin_atom_drag`; call `refresh_drag` to
+    /// show it) and `AtomDragEnded` ends it. `CutawayChanged` sets the
+    /// cutaway. Hover events set `hovered`. In bond mode (see
+    /// `set_bond_mode`) clicks create and delete bonds. Otherwise the first
+    /// `MeasurementTool` overlay takes atom clicks (`MeasurementTool::pick`),
+    /// and with `settings.coordination_on_click`, clicking a metal atom puts
+    /// its coordination sphere (within `COORDINATION_CUTOFF`) in the first
+    /// overlay that shows one, and clicking empty space clears it. Returns whether the event was handled,
+    /// i.e. the molecule or overlay changed or an edit was refused.
+    pub fn handle_pick(&mut self, event: &ViewerEvent) -> bool {
+        self.notice(event);
+        match event {
+            ViewerEvent::AtomDragged {
+                atom,
+                target,
+                rigid,
+            } => {
+                if self.dragged_atom() != Some(*atom) {
+                    if let Err(reason) = self.begin_atom_drag(*atom, *rigid) {
+                        self.raise(ViewerEvent::CommandFailed(reason));
+                        return true;
+                    }
+                }
+                return self.drag_atom_to(*target);
+            }
+            ViewerEvent::AtomDragEnded => return self.end_atom_drag(),
+            ViewerEvent::CutawayChanged(cutaway) => {
+                self.set_cutaway(Some(*cutaway));
+                return true;
+            }
+            ViewerEvent::AtomHovered(i) => return self.set_hovered(Some(PickTarget::Atom(*i))),
+            ViewerEvent::BondHovered(i) => return self.set_hovered(Some(PickTarget::Bond(*i))),
+            ViewerEvent::NothingHovered => return self.set_hovered(None),
+            _ => {}
+        }
+        if self.bond_mode {
+            return self.bond_tool_pick(event);
+        }
+        if let ViewerEvent::AtomClicked(i) = event {
+            let tool = overlays_mut(&mut self.additional_render, &mut self.renderers)
+                .find_map(|(_, renderer)| renderer.measurement_mut());
+            if let (Some(tool), Some(mol)) =
+                (tool, displayed(&self.molecule, &self.display_positions))
+            {
+                tool.pick(&mol, *i);
+                self.mark_overlays_dirty();
+                return true;
+            }
+        }
+        if !self.settings.coordination_on_click {
+            return false;
+        }
+        let info = match event {
+            ViewerEvent::AtomClicked(i) => {
+                let Some(mol) = self.displayed_molecule() else {
+                    return false;
+                };
+                if !mol
+                    .atoms
+                    .get(*i)
+                    .is_some_and(|atom| element::is_metal(&atom.element))
+                {
+                    return false;
+                }
+                mol.coordination_sphere(*i, COORDINATION_CUTOFF)
+            }
+            ViewerEvent::NothingClicked => None,
+            _ => return false,
+        };
+        let Some(slot) = overlays_mut(&mut self.additional_render, &mut self.renderers)
+            .find_map(|(_, renderer)| renderer.coordination_mut())
+        else {
+            return false;
+        };
+        if slot.is_none() && info.is_none() {
+            return false;
+        }
+        *slot = info;
+        self.mark_overlays_dirty();
+        true
+    }
+
+    /// Queues `event` for `take_events`, capturing it first if the capture
+    /// hook asks for it.
+    fn raise(&mut self, event: ViewerEvent) {
+        self.notice(&event);
+        self.pending_events.push(event);
+    }
+
+    /// Records a capture of the active molecule for `event` if the capture
+    /// hook matches it. Does nothing without the `export` feature.
+    #[cfg(feature = "export")]
+    fn notice(&mut self, event: &ViewerEvent) {
+        if !self
+            .capture_hook
+            .as_mut()
+            .is_some_and(|hook| hook.accept(event))
+        {
+            return;
+        }
+        let display_list = self.active_display_list();
+        if let Some(hook) = &mut self.capture_hook {
+            hook.record(event.clone(), display_list);
+        }
+    }
+
+    #[cfg(not(feature = "export"))]
+    fn notice(&mut self, _event: &ViewerEvent) {}
+
+    /// The active molecule as `DisplayList::from_molecule` draws it, at its
+    /// displayed positions; empty without a molecule.
+    fn active_display_list(&self) -> DisplayList {
+        match displayed(&self.molecule, &self.display_positions) {
+            Some(mol) => DisplayList::from_molecule(
+                &mol,
+                &self.display_state(self.active).unwrap_or_default(),
+            ),
+            None => DisplayList::default(),
+        }
+    }
+
+    /// Renders `n_frames` views evenly spaced in time along `path`, its first
+    /// and last keyframes included, to `frame-0000.png`, `frame-0001.png`,
+    /// ... in `dir`, and returns their paths. Frames are `size` (width,
+    /// height) pixels of the active molecule as `DisplayList::from_molecule`
+    /// draws it, ray cast on the CPU by `raster::render` through
+    /// `Lens::default()`. Creates `dir` if needed.
+    pub fn render_path(
+        &self,
+        dir: &Path,
+        path: &CameraPath,
+        n_frames: usize,
+        size: (usize, usize),
+    ) -> Result<Vec<PathBuf>, String> {
+        path.validate().map_err(|e| e.to_string())?;
+        let (width, height) = size;
+        if n_frames == 0 || width == 0 || height == 0 {
+            return Err(format!(
+                "render_path: need at least one frame of at least 1x1 pixels, got {n_frames} of {width}x{height}"
+            ));
+        }
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+        let list = self.active_display_list();
+        let digits = (n_frames - 1).to_string().len().max(4);
+        let step = (path.end_time() - path.start_time()) / (n_frames - 1).max(1) as f32;
+        let mut paths = Vec::with_capacity(n_frames);
+        for i in 0..n_frames {
+            let view = path
+                .sample(path.start_time() + step * i as f32)
+                .map_err(|e| e.to_string())?;
+            let image = raster::render(
+                &list,
+                &view,
+                Lens::default(),
+                width,
+                height,
+                raster::BACKGROUND,
+            );
+            let file = dir.join(format!("frame-{i:0digits$}.png"));
+            image.write_png(&file)?;
+            paths.push(file);
+        }
+        Ok(paths)
+    }
+
+    /// Captures the active molecule whenever an event in `events` happens,
+    /// at most once per `CaptureHook::min_interval`, for `write_captures` to
+    /// save in `dir` (see the `capture` module). Events the viewer raises
+    /// count, and so do picks passed to `handle_pick`. Replaces any previous
+    /// hook, dropping its unwritten captures. A no-op without the `export`
+    /// feature.
+    pub fn set_capture_hook(&mut self, events: EventMask, dir: PathBuf) {
+        self.capture_hook = Some(CaptureHook::new(events, dir));
+    }
+
+    pub fn clear_capture_hook(&mut self) {
+        self.capture_hook = None;
+    }
+
+    /// The capture hook, e.g. to change its `min_interval`.
+    pub fn capture_hook_mut(&mut self) -> Option<&mut CaptureHook> {
+        self.capture_hook.as_mut()
+    }
+
+    /// Writes the captures recorded since the last call and returns the paths
+    /// of their JSON files. Each is drawn from the view the previous call was
+    /// given, which was on screen when its event happened; `camera` stands
+    /// in on the first call, and is kept for the captures until the next.
+    /// Call once per frame, after drawing, next to `take_events`; does
+    /// nothing without a hook.
+    pub fn write_captures(&mut self, camera: &dyn Camera) -> Result<Vec<PathBuf>, String> {
+        match &mut self.capture_hook {
+            Some(hook) => hook.write(ViewBookmark::from_camera(camera), Lens::of(camera)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Returns and clears events raised by the viewer itself (as opposed to
+    /// picking results, which are returned directly).
+    pub fn take_events(&mut self) -> Vec<ViewerEvent> {
+        std::mem::take(&mut self.pending_events)
+    }
+
+    /// Registers a direction in the molecule's frame to draw through the active
+    /// molecule and to align the camera with (the controller's Home key).
+    /// Returns `false`, leaving the axis unchanged, if `direction` is zero.
+    pub fn set_reference_axis(
+        &mut self,
+        direction: Vector3<f32>,
+        label: impl Into<String>,
+    ) -> bool {
+        let Some(direction) = direction.try_normalize(1e-6) else {
+            return false;
+        };
+        self.reference_axis = Some(ReferenceAxis {
+            direction,
+            label: label.into(),
+        });
+        self.dirty = true;
+        true
+    }
+
+    /// Sets or, with `None`, removes the active molecule's cutaway sphere
+    /// (`settings.cutaway`), which is drawn as a faint wireframe.
+    pub fn set_cutaway(&mut self, cutaway: Option<Cutaway>) {
+        if self.settings.cutaway != cutaway {
+            self.settings.cutaway = cutaway;
+            self.dirty = true;
+        }
+    }
+
+    pub fn cutaway(&self) -> Option<Cutaway> {
+        self.settings.cutaway
+    }
+
+    pub fn clear_reference_axis(&mut self) {
+        if self.reference_axis.take().is_some() {
+            self.dirty = true;
+        }
+    }
+
+    pub fn reference_axis(&self) -> Option<&ReferenceAxis> {
+        self.reference_axis.as_ref()
+    }
+
+    fn style(&self) -> Style<'_> {
+        Style {
+            render_style: self.render_style,
+            settings: &self.settings,
+            view_dir: self.view_dir,
+        }
+    }
+
+    /// Whether any molecule draws bond orders, which face the camera.
+    fn shows_bond_order(&self) -> bool {
+        self.settings.show_bond_order
+            || self
+                .slots
+                .iter()
+                .flatten()
+                .any(|slot| slot.state.settings.show_bond_order)
+    }
+
+    /// Display radius of `atom` under the current style. Picking uses the same value.
+    pub fn atom_radius(&self, atom: &Atom) -> f32 {
+        self.style().atom_radius(atom)
+    }
+
+    /// `None` if `atom` passes every active filter, otherwise the combined mode
+    /// of the filters rejecting it. `hidden_atoms` goes by index and is not
+    /// taken into account; see `drawn_atoms`.
+    pub fn atom_filter(&self, atom: &Atom) -> Option<VisibilityMode> {
+        self.style().filter_by_atom(atom)
+    }
+
+    /// Opacity `atom` is drawn at, or `None` if it is hidden. Like
+    /// `atom_filter`, ignores `hidden_atoms`.
+    pub fn atom_opacity(&self, atom: &Atom) -> Option<f32> {
+        filter_opacity(self.atom_filter(atom))
+    }
+
+    /// Atoms of the active molecule that are drawn, ghosted or not, by every
+    /// filter including `hidden_atoms`. Ascending.
+    pub fn drawn_atoms(&self) -> Vec<usize> {
+        let Some(mol) = displayed(&self.molecule, &self.display_positions) else {
+            return Vec::new();
+        };
+        let style = self.style();
+        (0..mol.atoms.len())
+            .filter(|&i| style.atom_drawn(i, &mol.atoms[i]))
+            .collect()
+    }
+
+    /// Element composition of the active molecule, most abundant first, for
+    /// drawing as a stacked bar (see `composition_ui`). Cached: recomputed
+    /// only for new `options` or after a change that set `dirty`.
+    pub fn composition_bar(&mut self, options: CompositionOptions) -> &[CompositionEntry] {
+        let fresh = !self.dirty
+            && self
+                .composition_cache
+                .as_ref()
+                .is_some_and(|(cached, _)| *cached == options);
+        if !fresh {
+            let entries = match displayed(&self.molecule, &self.display_positions) {
+                Some(mol) => composition::composition(&mol, self.style(), options),
+                None => Vec::new(),
+            };
+            self.composition_cache = Some((options, entries));
+        }
+        self.composition_cache
+            .as_ref()
+            .map_or(&[], |(_, entries)| entries)
+    }
+
+    /// Draws `composition_bar` as a full-width stacked bar, with each
+    /// element's count and share on hover.
+    pub fn composition_ui(&mut self, ui: &mut egui::Ui, options: CompositionOptions) {
+        let (rect, response) =
+            ui.allocate_exact_size(egui::vec2(ui.available_width(), 14.0), egui::Sense::hover());
+        let mut hovered = None;
+        let mut left = rect.left();
+        for entry in self.composition_bar(options) {
+            let right = left + rect.width() * entry.fraction;
+            let segment = egui::Rect::from_x_y_ranges(left..=right, rect.y_range());
+            let (r, g, b) = entry.color;
+            let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+            let color = egui::Color32::from_rgb(channel(r), channel(g), channel(b));
+            ui.painter().rect_filled(segment, 0.0, color);
+            if response.hover_pos().is_some_and(|p| segment.contains(p)) {
+                hovered = Some(format!(
+                    "{}: {} ({:.1}%)",
+                    entry.element,
+                    entry.count,
+                    entry.fraction * 100.0
+                ));
+            }
+            left = right;
+        }
+        if let Some(text) = hovered {
+            response.on_hover_text(text);
+        }
+    }
+
+    /// Whether `atom` passes every filter and is drawn normally.
+    pub fn atom_visible(&self, atom: &Atom) -> bool {
+        self.atom_filter(atom).is_none()
+    }
+
+    /// Maps a scene entity index back to the atom, bond or overlay it was built from.
+    /// Returns `None` for entities not created by the last `update_scene`.
+    pub fn classify_entity(&self, entity_idx: usize) -> Option<EntityKind> {
+        self.entity_kinds.get(entity_idx).copied()
+    }
+
+    /// Lets the overlay renderers update their own entities (e.g. after a
+    /// camera move) without rebuilding the molecule. Each renderer's
+    /// `refresh` sees only its own entities and meshes at the end of the
+    /// scene, so it may truncate and re-push them. Returns `true` if any
+    /// renderer ran, in which case the host should push an entity update to
+    /// the engine.
+    pub fn refresh_overlays(&mut self, scene: &mut Scene) -> bool {
+        self.update_measurement_values();
+        let Some(mol) = displayed(&self.molecule, &self.display_positions) else {
+            return false;
+        };
+        let Some(first_overlay) = self
+            .overlay_ranges
+            .first()
+            .map(|range| range.entities.start)
+        else {
+            return false;
+        };
+        let last = self.overlay_ranges.last().expect("not empty");
+        if scene.entities.len() != last.entities.end || scene.meshes.len() != last.meshes.end {
+            // The scene was rebuilt elsewhere since our last update_scene.
+            return false;
+        }
+        self.entity_kinds.truncate(first_overlay);
+        let mut shift = 0isize;
+        for range in &mut self.overlay_ranges {
+            let id = range.renderer_id;
+            let Some(renderer) = overlays_mut(&mut self.additional_render, &mut self.renderers)
+                .find_map(|(renderer_id, renderer)| (renderer_id == id).then_some(renderer))
+            else {
+                continue;
+            };
+            let start = range.entities.start.saturating_add_signed(shift);
+            let end = range.entities.end.saturating_add_signed(shift);
+            let later_entities = scene.entities.split_off(end);
+            let later_meshes = scene.meshes.split_off(range.meshes.end);
+            renderer.refresh(scene, &mol, start);
+            let end_now = scene.entities.len();
+            scene.entities.extend(later_entities);
+            scene.meshes.extend(later_meshes);
+            shift += end_now as isize - end as isize;
+            range.entities = start..end_now;
+        }
+        for range in &self.overlay_ranges {
+            self.entity_kinds.extend(std::iter::repeat_n(
+                EntityKind::Overlay {
+                    renderer_id: range.renderer_id,
+                },
+                range.entities.len(),
+            ));
+        }
+        true
+    }
+
+    /// Adds an overlay renderer, drawn after `additional_render` and the
+    /// renderers added before it, and returns its id for `remove_renderer`.
+    /// Ids are not reused. The renderer gets the viewer's selection now and
+    /// the same hooks as `additional_render` from then on.
+    pub fn add_renderer(&mut self, mut renderer: Box<dyn AdditionalRender>) -> usize {
+        let id = self.next_renderer_id;
+        self.next_renderer_id += 1;
+        if let Some(mirror) = renderer.selection_mut() {
+            *mirror = self.selection.clone();
+        }
+        self.renderers.push((id, renderer));
+        self.mark_overlays_dirty();
+        id
+    }
+
+    /// Removes and returns the renderer `add_renderer` gave `id`.
+    pub fn remove_renderer(&mut self, id: usize) -> Option<Box<dyn AdditionalRender>> {
+        let index = self.renderers.iter().position(|(i, _)| *i == id)?;
+        self.mark_overlays_dirty();
+        Some(self.renderers.remove(index).1)
+    }
+
+    /// The renderer `add_renderer` gave `id`.
+    pub fn renderer(&self, id: usize) -> Option<&dyn AdditionalRender> {
+        let (_, renderer) = self.renderers.iter().find(|(i, _)| *i == id)?;
+        Some(renderer.as_ref())
+    }
+
+    /// The renderer `add_renderer` gave `id`. Set `dirty` after changing
+    /// what it draws.
+    pub fn renderer_mut(&mut self, id: usize) -> Option<&mut dyn AdditionalRender> {
+        let (_, renderer) = self.renderers.iter_mut().find(|(i, _)| *i == id)?;
+        Some(renderer.as_mut())
+    }
+
+    /// Renumbers the measurement overlays' atoms with `new_index`, dropping
+    /// measurements of atoms it maps to `None` (see
+    /// `MeasurementTool::remap_atoms`).
+    fn remap_measurements(&mut self, new_index: impl Fn(usize) -> Option<usize>) {
+        for renderer in self.overlay_renderers_mut() {
+            if let Some(tool) = renderer.measurement_mut() {
+                tool.remap_atoms(&new_index);
+            }
+        }
+        self.mark_overlays_dirty();
+    }
+
+    /// Measures the measurement overlays' atoms again at their displayed
+    /// positions, e.g. after a drag or `transform_atoms`.
+    fn update_measurement_values(&mut self) {
+        let Some(mol) = displayed(&self.molecule, &self.display_positions) else {
+            return;
+        };
+        for (_, renderer) in overlays_mut(&mut self.additional_render, &mut self.renderers) {
+            if let Some(tool) = renderer.measurement_mut() {
+                tool.update_values(&mol);
+            }
+        }
+    }
+
+    /// `additional_render`, then the added renderers.
+    pub(crate) fn overlay_renderers_mut(
+        &mut self,
+    ) -> impl Iterator<Item = &mut dyn AdditionalRender> {
+        overlays_mut(&mut self.additional_render, &mut self.renderers).map(|(_, r)| r)
+    }
+
+    /// Ids of the renderers added with `add_renderer`, in drawing order.
+    pub fn renderer_ids(&self) -> Vec<usize> {
+        self.renderers.iter().map(|(id, _)| *id).collect()
+    }
+
+    /// Picks the closest atom or bond of the active molecule hit by the ray.
+    /// From `PICK_INDEX_MIN_ATOMS` atoms it only tests those near the ray,
+    /// using an index `update_scene` builds with the scene; until the next
+    /// rebuild after `dirty` is set or an atom is dragged, it tests them
+    /// all. See `pick_hit` for where the ray hits.
+    pub fn pick(&self, ray_origin: Vec3, ray_dir: Vec3) -> Option<ViewerEvent> {
+        Some(match self.pick_hit(ray_origin, ray_dir) {
+            Some(PickResult {
+                target: PickTarget::Atom(i),
+                ..
+            }) => ViewerEvent::AtomClicked(i),
+            Some(PickResult {
+                target: PickTarget::Bond(i),
+                ..
+            }) => ViewerEvent::BondClicked(i),
+            None => ViewerEvent::NothingClicked,
+        })
+    }
+
+    /// Like `pick`, with where the ray hits. `None` if it hits nothing.
+    pub fn pick_hit(&self, ray_origin: Vec3, ray_dir: Vec3) -> Option<PickResult> {
+        self.cast(ray_origin, ray_dir, false).into_iter().next()
+    }
+
+    /// Every atom and bond of the active molecule that the ray hits, nearest
+    /// first, e.g. to click through translucent atoms. Each is hit where the
+    /// ray enters it; of equal hits, atoms come before bonds and lower
+    /// indices first, as with `pick`.
+    pub fn pick_all(&self, ray_origin: Vec3, ray_dir: Vec3) -> Vec<PickResult> {
+        self.cast(ray_origin, ray_dir, true)
+    }
+
+    /// The hits of `pick_all`, or with `!all` just the first.
+    fn cast(&self, ray_origin: Vec3, ray_dir: Vec3, all: bool) -> Vec<PickResult> {
+        let (origin, dir) = (to_na(ray_origin), vec_to_na(ray_dir));
+        let style = self.style();
+        let Some(mol) = displayed(&self.molecule, &self.display_positions) else {
+            return Vec::new();
+        };
+        let mol = &*mol;
+        let n_atoms = mol.atoms.len();
+        // Item `i` is atom `i`, or bond `i - n_atoms`.
+        let hit = |i: usize| -> Option<f32> {
+            if let Some(atom) = mol.atoms.get(i) {
+                if !style.atom_pickable(i, atom) {
+                    return None;
+                }
+                let radius = style.atom_radius(atom);
+                return picking::ray_sphere(origin, dir, atom.position, radius).map(|hit| hit.t);
+            }
+            let segment = style.bond_segment(mol, &mol.bonds[i - n_atoms])?;
+            if !segment.pickable {
+                return None;
+            }
+            style.pick_bond(origin, dir, &segment).map(|hit| hit.t)
+        };
+        let result = |(i, t): (usize, f32)| PickResult {
+            target: if i < n_atoms {
+                PickTarget::Atom(i)
+            } else {
+                PickTarget::Bond(i - n_atoms)
+            },
+            t,
+            world_pos: origin + dir * t,
+        };
+
+        // The index is stale while a rebuild is pending.
+        let index = self.pick_index.as_ref().filter(|_| !self.dirty);
+        let items = 0..n_atoms + mol.bonds.len();
+        if all {
+            let items = index.map_or_else(|| items.collect(), |index| index.along(origin, dir));
+            let mut hits: Vec<_> = items
+                .into_iter()
+                .filter_map(|i| Some((i, hit(i)?)))
+                .collect();
+            hits.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+            return hits.into_iter().map(result).collect();
+        }
+        let nearest = match index {
+            Some(index) => index.cast(origin, dir, hit),
+            None => items.filter_map(|i| Some((i, hit(i)?))).fold(
+                None,
+                |best: Option<(usize, f32)>, (i, t)| match best {
+                    Some((_, best_t)) if best_t <= t => best,
+                    _ => Some((i, t)),
+                },
+            ),
+        };
+        nearest.into_iter().map(result).collect()
+    }
+
+    /// The bond of the active molecule whose axis passes closest to `pixel`
+    /// on a `width` x `height` screen, if within `max_distance` pixels; for
+    /// hovering bonds too thin to hit exactly. Bonds are placed on screen by
+    /// `camera.view_projection`; those reaching behind the camera, and bonds
+    /// `pick` skips, are left out. Only bonds near the pixel's ray in `pick`'s
+    /// index, if it has one, are measured.
+    pub fn bond_near_pixel(
+        &self,
+        camera: &dyn Camera,
+        pixel: Point2<f32>,
+        max_distance: f32,
+        width: f32,
+        height: f32,
+    ) -> Option<usize> {
+        let mol = displayed(&self.molecule, &self.display_positions)?;
+        let style = self.style();
+        let view_projection = camera.view_projection();
+        let to_screen = |p: Point3<f32>| {
+            let clip = view_projection * p.to_homogeneous();
+            (clip.w > 0.0).then(|| {
+                Point2::new(
+                    (clip.x / clip.w + 1.0) * 0.5 * width,
+                    (1.0 - clip.y / clip.w) * 0.5 * height,
+                )
+            })
+        };
+        let n_atoms = mol.atoms.len();
+        let index = self.pick_index.as_ref().filter(|_| !self.dirty);
+        let tube = || pixel_tube(camera, &mol, pixel, max_distance, width, height);
+        let candidates: Vec<usize> = match index.zip(tube()) {
+            Some((index, (origin, dir, reach))) => index
+                .near(origin, dir, reach)
+                .into_iter()
+                .filter_map(|i| i.checked_sub(n_atoms))
+                .collect(),
+            None => (0..mol.bonds.len()).collect(),
+        };
+        let mut nearest = None;
+        let mut nearest_distance = max_distance;
+        for i in candidates {
+            let bond = &mol.bonds[i];
+            let Some(segment) = style.bond_segment(&mol, bond).filter(|s| s.pickable) else {
+                continue;
+            };
+            let (Some(a), Some(b)) = (to_screen(segment.start), to_screen(segment.end)) else {
+                continue;
+            };
+            let ab = b - a;
+            let along = if ab.norm_squared() > 0.0 {
+                ((pixel - a).dot(&ab) / ab.norm_squared()).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let distance = (pixel - (a + ab * along)).norm();
+            if distance <= nearest_distance {
+                nearest = Some(i);
+                nearest_distance = distance;
+            }
+        }
+        nearest
+    }
+
+    /// Atoms of the active molecule whose display sphere overlaps the sphere of
+    /// `radius` around `center`, in ascending order. Like picking, skips atoms
+    /// that are hidden, or ghosted without `pick_ghosts`.
+    pub fn atoms_in_sphere(&self, center: Point3<f32>, radius: f32) -> Vec<usize> {
+        let Some(mol) = displayed(&self.molecule, &self.display_positions) else {
+            return Vec::new();
+        };
+        let style = self.style();
+        let radius = radius.max(0.0);
+        let reach = radius + max_atom_radius(&mol, style);
+        let positions: Vec<_> = mol.atoms.iter().map(|a| a.position).collect();
+        let grid = NeighborGrid::new(&positions, reach);
+        let mut result = Vec::new();
+        grid.for_each_within(center, reach, |i, dist| {
+            let atom = &mol.atoms[i];
+            if style.atom_pickable(i, atom) && dist <= radius + style.atom_radius(atom) {
+                result.push(i);
+            }
+        });
+        result.sort_unstable();
+        result
+    }
+
+    /// Atoms of the active molecule at least partly inside the view volume behind
+    /// `screen_rect`, given as two opposite corners in pixels on a `width` x
+    /// `height` screen. The volume's edges are `camera.ray_from_screen` at the
+    /// rectangle's corners, so results agree with `pick`. Returns atoms in
+    /// ascending order, filtered like `atoms_in_sphere`; a rectangle with no area
+    /// selects nothing.
+    pub fn atoms_in_frustum(
+        &self,
+        camera: &dyn Camera,
+        screen_rect: (Point2<f32>, Point2<f32>),
+        width: f32,
+        height: f32,
+    ) -> Vec<usize> {
+        let Some(mol) = displayed(&self.molecule, &self.display_positions) else {
+            return Vec::new();
+        };
+        let (a, b) = screen_rect;
+        let (x0, x1) = (a.x.min(b.x), a.x.max(b.x));
+        let (y0, y1) = (a.y.min(b.y), a.y.max(b.y));
+        let mut apex = Point3::origin();
+        let edges = [(x0, y0), (x1, y0), (x1, y1), (x0, y1)].map(|(u, v)| {
+            let (origin, dir) = camera.ray_from_screen(u, v, width, height);
+            apex = to_na(origin);
+            vec_to_na(dir)
+        });
+        let Some(planes) = frustum_planes(&edges) else {
+            return Vec::new();
+        };
+        let inside = |p: &Point3<f32>, margin: f32| {
+            planes
+                .iter()
+                .all(|normal| normal.dot(&(p - apex)) >= -margin)
+        };
+
+        let style = self.style();
+        let positions: Vec<_> = mol.atoms.iter().map(|a| a.position).collect();
+        let grid = NeighborGrid::new(&positions, FRUSTUM_CELL_SIZE);
+        // Half the cell diagonal, plus room for the largest atom in the cell.
+        let cell_margin = FRUSTUM_CELL_SIZE * 0.5 * 3f32.sqrt() + max_atom_radius(&mol, style);
+        let mut result = Vec::new();
+        grid.for_each_cell(|cell_center, indices| {
+            if !inside(&cell_center, cell_margin) {
+                return;
+            }
+            for &i in indices {
+                let atom = &mol.atoms[i];
+                if style.atom_pickable(i, atom) && inside(&atom.position, style.atom_radius(atom)) {
+                    result.push(i);
+                }
+            }
+        });
+        result.sort_unstable();
+        result
+    }
+
+    /// What the next `update_scene` will redo.
+    pub fn dirty_flags(&self) -> DirtyFlags {
+        DirtyFlags {
+            geometry: self.dirty,
+            ..self.dirty_parts
+        }
+    }
+
+    /// Whether `update_scene` has anything to do, camera turns aside.
+    pub fn needs_update(&self) -> bool {
+        self.dirty_flags().any()
+    }
+
+    /// Rebuilds the whole scene at the next `update_scene`; the same as
+    /// setting `dirty`.
+    pub fn mark_geometry_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Recolors the molecules at the next `update_scene`, e.g. after editing
+    /// `settings.color_overrides` or another color setting.
+    pub fn mark_colors_dirty(&mut self) {
+        self.dirty_parts.colors = true;
+    }
+
+    /// Redraws the selection shells and the overlays at the next
+    /// `update_scene`. The selection methods do this themselves.
+    pub fn mark_selection_dirty(&mut self) {
+        self.dirty_parts.selection = true;
+    }
+
+    /// Redraws the overlays at the next `update_scene`, e.g. after changing
+    /// an overlay renderer's state.
+    pub fn mark_overlays_dirty(&mut self) {
+        self.dirty_parts.overlays = true;
+    }
+
+    /// Updates the graphics scene based on the current molecule data. Every loaded
+    /// molecule is drawn with its own display state; overlays follow the active one.
+    /// Does nothing unless `needs_update`, or, while bond orders are shown (see
+    /// `ViewerSettings::show_bond_order`), the scene's camera has turned.
+    ///
+    /// Redoes only what `dirty_flags` asks for where it can: recolors or, for
+    /// a camera turn, re-lays the molecule entities in place, or re-pushes the selection shells and
+    /// overlays at the end of the scene. It rebuilds everything if `dirty`,
+    /// or if the scene or the number of drawn atoms and bonds changed since
+    /// the last rebuild. Returns what changed for the engine: `meshes` only
+    /// when meshes were pushed, and `EntityUpdate::All` when anything was.
+    ///
+    /// Entity order is part of the API and does not depend on settings:
+    /// 1. molecules in slot order (the order they were added, regardless of
+    ///    which is active);
+    /// 2. within a molecule, visible atoms in index order, then drawn bonds in
+    ///    index order, each bond's lines (several for a multiple bond with
+    ///    `show_bond_order`, each in two halves with split `bond_coloring`)
+    ///    followed by its slab or stub caps (if any);
+    /// 3. the reference axis line, if one is set;
+    /// 4. the segments of the cutaway wireframe, if the active molecule has
+    ///    a cutaway;
+    /// 5. a marker for each drawn pinned atom of the active molecule, in
+    ///    index order;
+    /// 6. a shell around each drawn selected atom of the active molecule, in
+    ///    index order, then a sleeve around each drawn selected bond;
+    /// 7. the overlay renderers' entities: `additional_render`'s, then each
+    ///    added renderer's in `renderer_ids` order, each in the order it
+    ///    pushes them.
+    ///
+    /// `classify_entity` reports the same order.
+    ///
+    /// # Custom assembly
+    ///
+    /// `ensure_meshes`, `emit_atom_entities` and `emit_bond_entities` build the
+    /// active molecule's part of this into buffers of your own. Scenes put
+    /// together that way are yours to keep consistent:
+    /// - keep the entities' position and scale: `pick`, `atoms_in_sphere` and
+    ///   `atoms_in_frustum` test `atom_radius` and the style's bond radius
+    ///   around the molecule's positions, not what the scene shows;
+    /// - rebuild after changing the molecule, trajectory frame, style or
+    ///   settings; the pieces neither read nor clear `dirty`;
+    /// - `classify_entity` and `refresh_overlays` only know about entities
+    ///   from `update_scene`, so neither they nor `DisplayList::from_scene`
+    ///   and `scene_snapshot` can be used on a custom scene;
+    /// - overlays are not included; call the overlay renderers yourself.
+    pub fn update_scene(&mut self, scene: &mut Scene) -> EngineUpdates {
+        let view_dir = quat_to_na(scene.camera.orientation) * Vector3::z();
+        if self.shows_bond_order() && (view_dir - self.view_dir).norm() > VIEW_DIR_TOLERANCE {
+            self.dirty_parts.view = true;
+        }
+        if !self.needs_update() {
+            return EngineUpdates::default();
+        }
+        self.update_measurement_values();
+        let parts = std::mem::take(&mut self.dirty_parts);
+        if !self.dirty {
+            if parts.view {
+                self.view_dir = view_dir;
+            }
+            if let Some(meshes) = self.update_parts(scene, parts) {
+                return EngineUpdates {
+                    meshes,
+                    entities: EntityUpdate::All,
+                    ..Default::default()
+                };
+            }
+        }
+        self.rebuild(scene, view_dir);
+        EngineUpdates {
+            meshes: true,
+            entities: EntityUpdate::All,
+            ..Default::default()
+        }
+    }
+
+    /// Redoes `parts` of the last rebuild in place. Returns whether meshes
+    /// were pushed, or `None`, having changed nothing, if only a rebuild will
+    /// do.
+    fn update_parts(&mut self, scene: &mut Scene, parts: DirtyFlags) -> Option<bool> {
+        let layout = self.layout.as_ref()?;
+        if scene.entities.len() != self.entity_kinds.len()
+            || scene.meshes.len() != layout.mesh_count
+        {
+            // The scene was rebuilt elsewhere since our last update_scene.
+            return None;
+        }
+        let mol = displayed(&self.molecule, &self.display_positions)?;
+        let style = Style {
+            render_style: self.render_style,
+            settings: &self.settings,
+            view_dir: self.view_dir,
+        };
+        if parts.colors || parts.view {
+            let mut recolored = Vec::with_capacity(layout.molecules.len());
+            for (id, range) in &layout.molecules {
+                let mut entities = Vec::with_capacity(range.len());
+                let mut kinds = Vec::with_capacity(range.len());
+                let handles = MeshHandles {
+                    sphere: 0,
+                    cylinder: 0,
+                };
+                if *id == self.active {
+                    atom_entities(&mut entities, &mut kinds, &mol, style, 0, *id);
+                    bond_entities(&mut entities, &mut kinds, &mol, style, handles, *id);
+                } else {
+                    let Some(Some(MoleculeSlot {
+                        molecule: Some(other),
+                        state,
+                        ..
+                    })) = self.slots.get(*id)
+                    else {
+                        return None;
+                    };
+                    let style = Style {
+                        view_dir: self.view_dir,
+                        ..state.style()
+                    };
+                    atom_entities(&mut entities, &mut kinds, other, style, 0, *id);
+                    bond_entities(&mut entities, &mut kinds, other, style, handles, *id);
+                }
+                if kinds[..] != self.entity_kinds[range.clone()] {
+                    // Something was shown or hidden.
+                    return None;
+                }
+                recolored.push((range.start, entities));
+            }
+            for (start, entities) in recolored {
+                for (old, mut new) in scene.entities[start..].iter_mut().zip(entities) {
+                    new.mesh = old.mesh;
+                    *old = new;
+                }
+            }
+            self.highlight_hovered(scene);
+        }
+        let from = if parts.selection {
+            layout.selection
+        } else if parts.overlays {
+            layout.overlays
+        } else {
+            return Some(false);
+        };
+        scene.entities.truncate(from.0);
+        scene.meshes.truncate(from.1);
+        self.entity_kinds.truncate(from.0);
+        if parts.selection {
+            for (kind, entity) in selection_entities(scene, &mol, style, &self.selection) {
+                scene.entities.push(entity);
+                self.entity_kinds.push(kind);
+            }
+        }
+        let overlays = (scene.entities.len(), scene.meshes.len());
+        let ranges = self.push_overlays(scene, &mol);
+        self.record_overlays(ranges);
+        let layout = self.layout.as_mut().expect("checked above");
+        layout.overlays = overlays;
+        layout.mesh_count = scene.meshes.len();
+        Some(true)
+    }
+
+    /// Clears `scene` and draws everything into it.
+    fn rebuild(&mut self, scene: &mut Scene, view_dir: Vector3<f32>) {
+        self.dirty = false;
+        self.view_dir = view_dir;
+        self.composition_cache = None;
+
+        scene.meshes.clear();
+        scene.entities.clear();
+        self.entity_kinds.clear();
+        self.overlay_ranges.clear();
+        self.layout = None;
+        self.pick_index = None;
+        // With nothing loaded the scene is left empty rather than stale.
+        let Some(mol) = displayed(&self.molecule, &self.display_positions) else {
+            return;
+        };
+
+        let style = Style {
+            render_style: self.render_style,
+            settings: &self.settings,
+            view_dir,
+        };
+        let mut molecules = Vec::new();
+        let mut meshes = None;
+        for id in 0..self.slots.len().max(1) {
+            let start = scene.entities.len();
+            if id == self.active {
+                meshes = Some(build_molecule(
+                    scene,
+                    &mut self.entity_kinds,
+                    &mol,
+                    style,
+                    id,
+                ));
+            } else if let Some(Some(MoleculeSlot {
+                molecule: Some(other),
+                state,
+                ..
+            })) = self.slots.get(id)
+            {
+                let style = Style {
+                    view_dir,
+                    ..state.style()
+                };
+                build_molecule(scene, &mut self.entity_kinds, other, style, id);
+            } else {
+                continue;
+            }
+            molecules.push((id, start..scene.entities.len()));
+        }
+        self.highlight_hovered(scene);
+        self.pick_index =
+            (mol.atoms.len() >= PICK_INDEX_MIN_ATOMS).then(|| pick_index(&mol, style));
+
+        if let Some(axis) = &self.reference_axis {
+            if let Some(entity) = reference_axis_entity(scene, &mol, axis.direction) {
+                scene.entities.push(entity);
+                self.entity_kinds.push(EntityKind::ReferenceAxis);
+            }
+        }
+
+        if let Some(cutaway) = &self.settings.cutaway {
+            for entity in cutaway_entities(scene, cutaway) {
+                scene.entities.push(entity);
+                self.entity_kinds.push(EntityKind::Cutaway);
+            }
+        }
+
+        for (index, entity) in pin_marker_entities(scene, &mol, style) {
+            scene.entities.push(entity);
+            self.entity_kinds.push(EntityKind::PinMarker { index });
+        }
+
+        let selection = (scene.entities.len(), scene.meshes.len());
+        for (kind, entity) in selection_entities(scene, &mol, style, &self.selection) {
+            scene.entities.push(entity);
+            self.entity_kinds.push(kind);
+        }
+        let overlays = (scene.entities.len(), scene.meshes.len());
+        let ranges = self.push_overlays(scene, &mol);
+        self.record_overlays(ranges);
+        self.layout = meshes.map(|meshes| SceneLayout {
+            molecules,
+            selection,
+            overlays,
+            mesh_count: scene.meshes.len(),
+            meshes,
+        });
+    }
+
+    /// Applies the hover highlight to the molecule entities.
+    fn highlight_hovered(&self, scene: &mut Scene) {
+        let Some(target) = self.hovered else {
+            return;
+        };
+        let hovered = match target {
+            PickTarget::Atom(index) => EntityKind::Atom {
+                molecule: self.active,
+                index,
+            },
+            PickTarget::Bond(index) => EntityKind::Bond {
+                molecule: self.active,
+                index,
+            },
+        };
+        for (entity, kind) in scene.entities.iter_mut().zip(&self.entity_kinds) {
+            if *kind == hovered {
+                highlight_hovered(entity);
+            }
+        }
+    }
+
+    /// Pushes every overlay renderer's entities and returns where they went,
+    /// for `record_overlays`.
+    fn push_overlays(&self, scene: &mut Scene, mol: &Molecule) -> Vec<OverlayRange> {
+        let mut ranges = Vec::new();
+        for (renderer_id, renderer) in overlays(&self.additional_render, &self.renderers) {
+            let (first_entity, first_mesh) = (scene.entities.len(), scene.meshes.len());
+            renderer.update_scene(scene, mol);
+            ranges.push(OverlayRange {
+                renderer_id,
+                entities: first_entity..scene.entities.len(),
+                meshes: first_mesh..scene.meshes.len(),
+            });
+        }
+        ranges
+    }
+
+    fn record_overlays(&mut self, ranges: Vec<OverlayRange>) {
+        for range in &ranges {
+            self.entity_kinds.extend(std::iter::repeat_n(
+                EntityKind::Overlay {
+                    renderer_id: range.renderer_id,
+                },
+                range.entities.len(),
+            ));
+        }
+        self.overlay_ranges = ranges;
+    }
+
+    /// Pushes the unit sphere and cylinder that the active molecule's entities
+    /// are scaled from, built for its `detail` and `shading`. Meshes are added
+    /// on every call, so call it once per rebuild; see `update_scene` on
+    /// custom assembly.
+    pub fn ensure_meshes(&self, scene: &mut Scene) -> MeshHandles {
+        push_meshes(scene, self.style())
+    }
+
+    /// Appends the active molecule's atoms to `out` as `update_scene` draws
+    /// them: one entity per drawn atom, in index order. `mesh_idx` is the
+    /// sphere from `ensure_meshes`.
+    pub fn emit_atom_entities(&self, out: &mut Vec<Entity>, mesh_idx: usize) {
+        if let Some(mol) = displayed(&self.molecule, &self.display_positions) {
+            let id = self.active;
+            atom_entities(out, &mut Vec::new(), &mol, self.style(), mesh_idx, id);
+        }
+    }
+
+    /// Appends the active molecule's bonds to `out` as `update_scene` draws
+    /// them: each drawn bond in index order, followed by its slab or stub caps.
+    pub fn emit_bond_entities(&self, out: &mut Vec<Entity>, meshes: MeshHandles) {
+        if let Some(mol) = displayed(&self.molecule, &self.display_positions) {
+            let id = self.active;
+            bond_entities(out, &mut Vec::new(), &mol, self.style(), meshes, id);
+        }
+    }
+}
+
+/// Pushes meshes and entities for one molecule, recording each entity's kind.
+fn build_molecule(
+    scene: &mut Scene,
+    entity_kinds: &mut Vec<EntityKind>,
+    mol: &Molecule,
+    style: Style,
+    molecule_id: usize,
+) -> MeshHandles {
+    let meshes = push_meshes(scene, style);
+    atom_entities(
+        &mut scene.entities,
+        entity_kinds,
+        mol,
+        style,
+        meshes.sphere,
+        molecule_id,
+    );
+    bond_entities(
+        &mut scene.entities,
+        entity_kinds,
+        mol,
+        style,
+        meshes,
+        molecule_id,
+    );
+    meshes
+}
+
+/// Pushes the unit sphere and cylinder for `style`'s detail and shading.
+fn push_meshes(scene: &mut Scene, style: Style) -> MeshHandles {
+    // Sphere for atoms (Radius 1.0, but we scale it)
+    // 3 subdivisions gives a decent sphere.
+    // Cylinder for bonds (Length 1.0, Radius 1.0, along Y)
+    // 10 sides is enough for thin bonds
+    let (sphere_mesh, cyl_mesh) = match style.settings.detail {
+        Detail::Full => (Mesh::new_sphere(1.0, 3), Mesh::new_cylinder(1.0, 1.0, 10)),
+        Detail::Low => (Mesh::new_sphere(1.0, 1), Mesh::new_cylinder(1.0, 1.0, 5)),
+    };
+
+    let (sphere_mesh, cyl_meshes) = match style.settings.shading {
+        Shading::Standard => (sphere_mesh, vec![cyl_mesh]),
+        Shading::Flat => (
+            mesh::flat_shaded(sphere_mesh),
+            vec![mesh::flat_shaded(cyl_mesh)],
+        ),
+        Shading::Toon { bands } => {
+            // Spheres are drawn unrotated, so their mesh is banded against
+            // the light itself; cylinders, against the light as each bond's
+            // rotation (see `toon_cylinder_entity`) sees it.
+            let light = Vector3::from(TOON_LIGHT_DIR);
+            let cylinders = (0..TOON_BOND_MESHES)
+                .map(|k| {
+                    let angle = (k as f32 + 0.5) * PI / TOON_BOND_MESHES as f32;
+                    let light = Vector3::new(angle.sin(), angle.cos(), 0.0);
+                    mesh::toon_shaded(cyl_mesh.clone(), bands, light)
+                })
+                .collect();
+            (mesh::toon_shaded(sphere_mesh, bands, light), cylinders)
+        }
+    };
+    let sphere = scene.meshes.len();
+    scene.meshes.push(sphere_mesh);
+    let cylinder = scene.meshes.len();
+    scene.meshes.extend(cyl_meshes);
+    MeshHandles { sphere, cylinder }
+}
+
+/// Appends an entity per drawn atom, in index order, and its kind.
+fn atom_entities(
+    out: &mut Vec<Entity>,
+    entity_kinds: &mut Vec<EntityKind>,
+    mol: &Molecule,
+    style: Style,
+    sphere_idx: usize,
+    molecule_id: usize,
+) {
+    for (i, sphere) in style::atom_spheres(mol, style) {
+        out.push(sphere_entity(sphere_idx, &sphere));
+        entity_kinds.push(EntityKind::Atom {
+            molecule: molecule_id,
+            index: i,
+        });
+    }
+}
+
+/// Appends each drawn bond, in index order, followed by its caps, and their kinds.
+fn bond_entities(
+    out: &mut Vec<Entity>,
+    entity_kinds: &mut Vec<EntityKind>,
+    mol: &Molecule,
+    style: Style,
+    meshes: MeshHandles,
+    molecule_id: usize,
+) {
+    for (i, shape) in style::bond_shapes(mol, style) {
+        out.push(match shape {
+            BondShape::Cylinder(cylinder) => bond_entity(meshes.cylinder, style, &cylinder),
+            BondShape::Cap(cap) => sphere_entity(meshes.sphere, &cap),
+        });
+        entity_kinds.push(EntityKind::Bond {
+            molecule: molecule_id,
+            index: i,
+        });
+    }
+}
+
+/// Lightens `entity` and grows it across (a cylinder keeps its length).
+fn highlight_hovered(entity: &mut Entity) {
+    let (r, g, b) = entity.color;
+    let lighten = |c: f32| c + (1.0 - c) * HOVER_LIGHTEN;
+    entity.color = (lighten(r), lighten(g), lighten(b));
+    match &mut entity.scale_partial {
+        Some(scale) => {
+            scale.x *= HOVER_SCALE;
+            scale.z *= HOVER_SCALE;
+        }
+        None => entity.scale *= HOVER_SCALE,
+    }
+}
+
+fn sphere_entity(mesh: usize, sphere: &Sphere) -> Entity {
+    let mut entity = Entity::new(
+        mesh,
+        to_la(sphere.center),
+        Quaternion::new_identity(),
+        sphere.radius, // Uniform scale
+        sphere.color,
+        sphere.shininess,
+    );
+    entity.opacity = sphere.opacity;
+    entity
+}
+
+fn cylinder_entity(mesh: usize, cylinder: &Cylinder) -> Entity {
+    let p1 = to_la(cylinder.start);
+    let p2 = to_la(cylinder.end);
+
+    let diff = p2 - p1;
+    let len = diff.magnitude();
+    let mid = (p1 + p2) * 0.5;
+
+    // Orientation: Rotate Y-up cylinder to match `diff` direction
+    let dir = diff.to_normalized();
+    let up = Vec3::new(0.0, 1.0, 0.0);
+    let orientation = Quaternion::from_unit_vecs(up, dir);
+
+    let scale_partial = Vec3::new(cylinder.radius, len, cylinder.radius);
+
+    let mut entity = Entity::new(
+        mesh,
+        mid,
+        orientation,
+        1.0, // Base scale, overridden by partial
+        cylinder.color,
+        cylinder.shininess,
+    );
+    entity.scale_partial = Some(scale_partial);
+    entity.opacity = cylinder.opacity;
+    entity
+}
+
+/// A bond's cylinder for `style`, from `first_mesh` of `MeshHandles::cylinder`.
+fn bond_entity(first_mesh: usize, style: Style, cylinder: &Cylinder) -> Entity {
+    match style.settings.shading {
+        Shading::Toon { .. } => toon_cylinder_entity(first_mesh, cylinder),
+        Shading::Standard | Shading::Flat => cylinder_entity(first_mesh, cylinder),
+    }
+}
+
+/// `cylinder_entity` on the toon cylinder, of the `TOON_BOND_MESHES` from
+/// `first_mesh`, banded for the bond's angle to `TOON_LIGHT_DIR`, rolled so
+/// the mesh's +X faces the light as it does in that mesh's banding.
+fn toon_cylinder_entity(first_mesh: usize, cylinder: &Cylinder) -> Entity {
+    let light = Vector3::from(TOON_LIGHT_DIR).normalize();
+    let Some(axis) = (cylinder.end - cylinder.start).try_normalize(1e-6) else {
+        return cylinder_entity(first_mesh, cylinder);
+    };
+    let k = (axis.angle(&light) / PI * TOON_BOND_MESHES as f32) as usize;
+    let toward = (light - axis * axis.dot(&light))
+        .try_normalize(1e-6)
+        .or_else(|| axis.cross(&Vector3::x()).try_normalize(1e-6))
+        .unwrap_or_else(|| axis.cross(&Vector3::y()).normalize());
+    let basis = Rotation3::from_basis_unchecked(&[toward, axis, toward.cross(&axis)]);
+    let mut entity = cylinder_entity(first_mesh + k.min(TOON_BOND_MESHES - 1), cylinder);
+    entity.orientation = quat_to_la(UnitQuaternion::from_rotation_matrix(&basis));
+    entity
+}
+
+/// Line along `direction` through the centroid of `mol`, spanning the molecule
+/// plus `REFERENCE_AXIS_MARGIN` at each end. Pushes its own mesh.
+fn reference_axis_entity(
+    scene: &mut Scene,
+    mol: &Molecule,
+    direction: Vector3<f32>,
+) -> Option<Entity> {
+    if mol.atoms.is_empty() {
+        return None;
+    }
+    let centroid = mol.centroid();
+    let reach = mol
+        .atoms
+        .iter()
+        .map(|atom| (atom.position - centroid).dot(&direction).abs())
+        .fold(0.0, f32::max)
+        + REFERENCE_AXIS_MARGIN;
+    let mesh_idx = scene.meshes.len();
+    scene.meshes.push(Mesh::new_cylinder(1.0, 1.0, 8));
+    segment_entity(
+        mesh_idx,
+        centroid - direction * reach,
+        centroid + direction * reach,
+        REFERENCE_AXIS_RADIUS,
+        REFERENCE_AXIS_COLOR,
+    )
+}
+
+/// Three great circles of `cutaway`, in the xy, yz and zx planes, as thin
+/// translucent segments. Pushes its own mesh.
+fn cutaway_entities(scene: &mut Scene, cutaway: &Cutaway) -> Vec<Entity> {
+    let mesh_idx = scene.meshes.len();
+    scene.meshes.push(Mesh::new_cylinder(1.0, 1.0, 4));
+    let axes = [
+        (Vector3::x(), Vector3::y()),
+        (Vector3::y(), Vector3::z()),
+        (Vector3::z(), Vector3::x()),
+    ];
+    let point = |(u, v): (Vector3<f32>, Vector3<f32>), k: usize| {
+        let angle = std::f32::consts::TAU * k as f32 / CUTAWAY_SEGMENTS as f32;
+        cutaway.center + (u * angle.cos() + v * angle.sin()) * cutaway.radius
+    };
+    let mut entities = Vec::new();
+    for plane in axes {
+        for k in 0..CUTAWAY_SEGMENTS {
+            let (a, b) = (point(plane, k), point(plane, k + 1));
+            if let Some(mut entity) =
+                segment_entity(mesh_idx, a, b, CUTAWAY_LINE_RADIUS, CUTAWAY_COLOR)
+            {
+                entity.opacity = CUTAWAY_LINE_OPACITY;
+                entities.push(entity);
+            }
+        }
+    }
+    entities
+}
+
+/// Dark inverted-hull rims around the drawn pinned atoms of `mol`: spheres
+/// just larger than the atom, drawn with a negative scale. Mirroring flips
+/// the triangle winding, so back-face culling keeps only the inside of the
+/// far hemisphere, a rim around the atom's silhouette. Pushes its own mesh if
+/// any atom is pinned.
+fn pin_marker_entities(scene: &mut Scene, mol: &Molecule, style: Style) -> Vec<(usize, Entity)> {
+    let drawn: Vec<(usize, f32)> = mol
+        .pinned
+        .iter()
+        .filter_map(|&i| {
+            let atom = mol.atoms.get(i)?;
+            style.atom_opacity(i, atom)?;
+            Some((i, style.atom_radius(atom)))
+        })
+        .collect();
+    if drawn.is_empty() {
+        return Vec::new();
+    }
+    let mesh_idx = scene.meshes.len();
+    scene.meshes.push(Mesh::new_sphere(1.0, 2));
+    drawn
+        .into_iter()
+        .map(|(i, radius)| {
+            let entity = Entity::new(
+                mesh_idx,
+                to_la(mol.atoms[i].position),
+                Quaternion::new_identity(),
+                -radius * PIN_MARKER_SCALE,
+                PIN_MARKER_COLOR,
+                0.0,
+            );
+            (i, entity)
+        })
+        .collect()
+}
+
+/// Translucent shells around the drawn atoms of `selection`, then sleeves
+/// around its drawn bonds. Pushes its own meshes if anything is selected.
+fn selection_entities(
+    scene: &mut Scene,
+    mol: &Molecule,
+    style: Style,
+    selection: &Selection,
+) -> Vec<(EntityKind, Entity)> {
+    if selection.is_empty() {
+        return Vec::new();
+    }
+    let sphere_idx = scene.meshes.len();
+    scene.meshes.push(Mesh::new_sphere(1.0, 2));
+    let cylinder_idx = scene.meshes.len();
+    scene.meshes.push(Mesh::new_cylinder(1.0, 1.0, 10));
+
+    let mut entities = Vec::new();
+    for &index in selection.atoms() {
+        let Some(atom) = mol.atoms.get(index) else {
+            continue;
+        };
+        if style.atom_opacity(index, atom).is_none() {
+            continue;
+        }
+        let mut entity = Entity::new(
+            sphere_idx,
+            to_la(atom.position),
+            Quaternion::new_identity(),
+            style.atom_radius(atom) * SELECTION_SCALE,
+            SELECTION_COLOR,
+            0.0,
+        );
+        entity.opacity = SELECTION_OPACITY;
+        entities.push((EntityKind::SelectedAtom { index }, entity));
+    }
+    let radius = style.bond_radius() * SELECTION_SCALE;
+    for &index in selection.bonds() {
+        let Some(segment) = mol
+            .bonds
+            .get(index)
+            .and_then(|b| style.bond_segment(mol, b))
+        else {
+            continue;
+        };
+        let entity = segment_entity(
+            cylinder_idx,
+            segment.start,
+            segment.end,
+            radius,
+            SELECTION_COLOR,
+        );
+        if let Some(mut entity) = entity {
+            entity.opacity = SELECTION_OPACITY;
+            entities.push((EntityKind::SelectedBond { index }, entity));
+        }
+    }
+    entities
+}
+
+/// `additional_render` as renderer 0, then the added renderers, with their
+/// ids.
+fn overlays<'a, T: AdditionalRender>(
+    primary: &'a Option<Box<T>>,
+    added: &'a [(usize, Box<dyn AdditionalRender>)],
+) -> impl Iterator<Item = (usize, &'a dyn AdditionalRender)> {
+    let primary = primary.as_deref().map(|r| (0, r as &dyn AdditionalRender));
+    primary
+        .into_iter()
+        .chain(added.iter().map(|(id, r)| (*id, r.as_ref())))
+}
+
+/// Like `overlays`, mutably; a free function so the molecule can be borrowed
+/// alongside.
+fn overlays_mut<'a, T: AdditionalRender>(
+    primary: &'a mut Option<Box<T>>,
+    added: &'a mut [(usize, Box<dyn AdditionalRender>)],
+) -> impl Iterator<Item = (usize, &'a mut dyn AdditionalRender)> {
+    let primary = primary
+        .as_deref_mut()
+        .map(|r| (0, r as &mut dyn AdditionalRender));
+    primary.into_iter().chain(
+        added
+            .iter_mut()
+            .map(|(id, r)| (*id, r.as_mut() as &mut dyn AdditionalRender)),
+    )
+}
+
+fn displayed<'a>(
+    molecule: &'a Option<Molecule>,
+    positions: &Option<Vec<Point3<f32>>>,
+) -> Option<Cow<'a, Molecule>> {
+    let mol = molecule.as_ref()?;
+    let Some(positions) = positions else {
+        return Some(Cow::Borrowed(mol));
+    };
+    let mut mol = mol.clone();
+    for (atom, position) in mol.atoms.iter_mut().zip(positions) {
+        atom.position = *position;
+    }
+    Some(Cow::Owned(mol))
+}
+
+/// Grid cell size used to cull atoms in `atoms_in_frustum`, in Å.
+const FRUSTUM_CELL_SIZE: f32 = 5.0;
+
+/// `RayGrid` over `mol`'s atoms as `style` sizes them, then its bonds from
+/// atom to atom.
+fn pick_index(mol: &Molecule, style: Style) -> RayGrid {
+    // A little extra so rounding cannot leave a hit outside its box.
+    let pad = |r: f32| Vector3::repeat(r + 1e-3);
+    let atoms = mol.atoms.iter().map(|atom| {
+        let r = pad(style.atom_radius(atom));
+        (atom.position - r, atom.position + r)
+    });
+    let bonds = mol.bonds.iter().map(|bond| {
+        let (a, b) = (
+            mol.atoms[bond.atom_a].position,
+            mol.atoms[bond.atom_b].position,
+        );
+        let r = pad(style.bond_radius());
+        (a.inf(&b) - r, a.sup(&b) + r)
+    });
+    RayGrid::new(&atoms.chain(bonds).collect::<Vec<_>>(), PICK_CELL_SIZE)
+}
+
+/// A ray along the line of sight through `pixel`, as `camera.view_projection`
+/// places it, starting before `mol`, and how far from it, in world units,
+/// points of `mol` within `distance` pixels of `pixel` on screen can be.
+fn pixel_tube(
+    camera: &dyn Camera,
+    mol: &Molecule,
+    pixel: Point2<f32>,
+    distance: f32,
+    width: f32,
+    height: f32,
+) -> Option<(Point3<f32>, Vector3<f32>, f32)> {
+    let inverse = camera.view_projection().try_inverse()?;
+    let line = |u: f32, v: f32| {
+        let ndc = |z: f32| Point3::new(2.0 * u / width - 1.0, 1.0 - 2.0 * v / height, z);
+        let near = Point3::from_homogeneous(inverse * ndc(-1.0).to_homogeneous())?;
+        let far = Point3::from_homogeneous(inverse * ndc(0.0).to_homogeneous())?;
+        Some((near, (far - near).try_normalize(0.0)?))
+    };
+    let (min, max) = mol.frame_bounds();
+    let (center, radius) = (nalgebra::center(&min, &max), (max - min).norm() / 2.0);
+    let (point, dir) = line(pixel.x, pixel.y)?;
+    let origin = point + dir * ((center - point).dot(&dir) - radius);
+    let ends = [origin, origin + dir * 2.0 * radius];
+    let mut reach = 0.0f32;
+    for (du, dv) in [(distance, 0.0), (0.0, distance)] {
+        let (p, d) = line(pixel.x + du, pixel.y + dv)?;
+        for end in ends {
+            let off = end - p;
+            reach = reach.max((off - d * off.dot(&d)).norm());
+        }
+    }
+    Some((origin, dir, reach))
+}
+
+fn max_atom_radius(mol: &Molecule, style: Style) -> f32 {
+    mol.atoms
+        .iter()
+        .map(|atom| style.atom_radius(atom))
+        .fold(0.0, f32::max)
+}
+
+/// Inward unit normals of the side planes of the pyramid whose edges run along
+/// `edges`, listed in order around its base. `None` if the pyramid is flat.
+fn frustum_planes(edges: &[Vector3<f32>; 4]) -> Option<[Vector3<f32>; 4]> {
+    let axis: Vector3<f32> = edges.iter().sum();
+    let mut planes = [Vector3::zeros(); 4];
+    for (i, plane) in planes.iter_mut().enumerate() {
+        let normal = edges[i].cross(&edges[(i + 1) % 4]).try_normalize(1e-12)?;
+        *plane = if normal.dot(&axis) < 0.0 {
+            -normal
+        } else {
+            normal
+        };
     }
+    Some(planes)
 }
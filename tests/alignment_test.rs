@@ -0,0 +1,66 @@
+use moleucle_3dview_rs::molecule::Atom;
+use moleucle_3dview_rs::Molecule;
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+use std::path::Path;
+
+fn benzene() -> Molecule {
+    Molecule::from_mol2(Path::new("Benzene.mol2")).unwrap()
+}
+
+/// A chiral, non-planar arrangement of four atoms.
+fn tetrahedron() -> Molecule {
+    let atom = |x: f32, y: f32, z: f32| Atom {
+        position: Point3::new(x, y, z),
+        element: "C".to_string(),
+        id: 0,
+    };
+    Molecule {
+        atoms: vec![
+            atom(0.0, 0.0, 0.0),
+            atom(1.5, 0.0, 0.0),
+            atom(0.0, 2.0, 0.0),
+            atom(0.0, 0.0, 2.5),
+        ],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_rmsd_pairs_atoms_by_index() {
+    let mol = benzene();
+    assert_eq!(mol.rmsd(&mol).unwrap(), 0.0);
+    let shifted = mol.translated(Vector3::new(0.0, 3.0, 4.0));
+    assert!((mol.rmsd(&shifted).unwrap() - 5.0).abs() < 1e-5);
+
+    assert!(mol.rmsd(&tetrahedron()).is_err());
+    assert!(Molecule::default().rmsd(&Molecule::default()).is_err());
+    assert!(mol.rmsd_after_kabsch_alignment(&tetrahedron()).is_err());
+}
+
+#[test]
+fn test_kabsch_recovers_a_rigid_motion() {
+    let turn = UnitQuaternion::from_euler_angles(0.3, -1.2, 2.0);
+    let offset = Vector3::new(5.0, -2.0, 1.0);
+    for mol in [benzene(), tetrahedron()] {
+        let moved = mol.rotated(turn, Point3::origin()).translated(offset);
+        assert!(mol.rmsd(&moved).unwrap() > 1.0);
+
+        let (rmsd, rotation, translation) = mol.rmsd_after_kabsch_alignment(&moved).unwrap();
+        assert!(rmsd < 1e-4, "{rmsd}");
+        for (a, b) in mol.atoms.iter().zip(&moved.atoms) {
+            let fitted = rotation * a.position + translation;
+            assert!((fitted - b.position).norm() < 1e-4);
+        }
+    }
+
+    // A mirror image cannot be superimposed by a rotation.
+    let mol = tetrahedron();
+    let mut mirrored = mol.clone();
+    for atom in &mut mirrored.atoms {
+        atom.position.x = -atom.position.x;
+    }
+    let (rmsd, rotation, _) = mol.rmsd_after_kabsch_alignment(&mirrored).unwrap();
+    assert!(rmsd > 0.1, "{rmsd}");
+    assert!((rotation.to_rotation_matrix().matrix().determinant() - 1.0).abs() < 1e-4);
+    assert!(rmsd <= mol.rmsd(&mirrored).unwrap());
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use common::atom;
+use moleucle_3dview_rs::molecule::Bond;
+use moleucle_3dview_rs::{Molecule, MoleculeViewer, SelectedAtomRender, ViewerCommand};
+
+/// H-O-C-H in a line.
+fn chain() -> Molecule {
+    Molecule {
+        atoms: vec![
+            atom("H", 0.0, 0.0, 0.0),
+            atom("O", 1.0, 0.0, 0.0),
+            atom("C", 2.4, 0.0, 0.0),
+            atom("H", 3.4, 0.0, 0.0),
+        ],
+        bonds: (0..3)
+            .map(|i| Bond {
+                atom_a: i,
+                atom_b: i + 1,
+                order: 1,
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_measurement_survives_deletion() {
+    let mut mol = chain();
+    assert_eq!(mol.atom_id(0), None);
+    mol.assign_atom_ids();
+    let (o, c) = (mol.atom_id(1).unwrap(), mol.atom_id(2).unwrap());
+    let before = mol.distance_by_id(o, c, None).unwrap();
+    let first = mol.atom_id(0).unwrap();
+
+    mol.remove_atoms(&[0]);
+    assert_eq!((mol.index_of(o), mol.index_of(c)), (Some(0), Some(1)));
+    assert_eq!(mol.atoms[0].element, "O");
+    assert_eq!(mol.distance_by_id(o, c, None), Some(before));
+    assert_eq!(mol.index_of(first), None);
+    assert_eq!(mol.indices_of(&[c, first]), Err(first));
+
+    // New atoms get new ids; the removed one is not reused.
+    mol.atoms.push(atom("N", 5.0, 0.0, 0.0));
+    mol.assign_atom_ids();
+    let n = mol.atom_id(3).unwrap();
+    assert!(n != first && mol.index_of(n) == Some(3));
+}
+
+#[test]
+fn test_viewer_commands_by_id() {
+    let mut viewer = MoleculeViewer::new();
+    viewer.additional_render = Some(Box::new(SelectedAtomRender::new()));
+    viewer.set_molecule(chain());
+    let mol = viewer.molecule.as_ref().unwrap();
+    let ids: Vec<_> = (0..4).map(|i| mol.atom_id(i).unwrap()).collect();
+    viewer.delete_atoms(&[0]).unwrap();
+
+    viewer
+        .apply_command(ViewerCommand::SelectIds(vec![ids[3]]))
+        .unwrap();
+    let overlay = viewer.additional_render.as_ref().unwrap();
+    assert_eq!(overlay.selection.atoms(), [2]);
+    viewer
+        .apply_command(ViewerCommand::SetColorOverrideById {
+            atom: ids[1],
+            color: Some((1.0, 0.0, 0.0)),
+        })
+        .unwrap();
+    assert!(viewer.settings.color_overrides.contains_key(&0));
+
+    let err = viewer
+        .apply_command(ViewerCommand::SelectIds(vec![ids[0]]))
+        .unwrap_err();
+    assert_eq!(err, format!("select: no atom {}", ids[0]));
+
+    // Undo brings the atom back under its old id.
+    assert!(viewer.undo());
+    assert_eq!(viewer.molecule.as_ref().unwrap().index_of(ids[0]), Some(0));
+}
@@ -0,0 +1,89 @@
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use moleucle_3dview_rs::bevy_adapter::{spawn_display_list, sync_display_list, MoleculeShape};
+use moleucle_3dview_rs::style::MoleculeDisplayState;
+use moleucle_3dview_rs::{DisplayList, Molecule, MoleculeViewer, SelectedAtomRender};
+use std::path::Path;
+
+fn benzene_list() -> DisplayList {
+    let mol = Molecule::from_mol2(Path::new("Benzene.mol2")).unwrap();
+    DisplayList::from_molecule(&mol, &MoleculeDisplayState::default())
+}
+
+#[test]
+fn test_spawned_shapes_match_the_display_list() {
+    let list = benzene_list();
+    let mut world = World::new();
+    world.init_resource::<Assets<Mesh>>();
+    world.init_resource::<Assets<StandardMaterial>>();
+    let spawned = {
+        let list = list.clone();
+        world.run_system_once(
+            move |mut commands: Commands,
+                  mut meshes: ResMut<Assets<Mesh>>,
+                  mut materials: ResMut<Assets<StandardMaterial>>| {
+                spawn_display_list(&mut commands, &mut meshes, &mut materials, &list)
+            },
+        )
+    };
+    assert_eq!(spawned.len(), list.spheres.len() + list.cylinders.len());
+    assert_eq!(
+        world
+            .query_filtered::<(), With<MoleculeShape>>()
+            .iter(&world)
+            .count(),
+        spawned.len()
+    );
+    // Two meshes, and a material per color: carbon, hydrogen and the gray
+    // bonds.
+    assert_eq!(world.resource::<Assets<Mesh>>().len(), 2);
+    assert_eq!(world.resource::<Assets<StandardMaterial>>().len(), 3);
+
+    let sphere = &list.spheres[0];
+    let transform = world.get::<Transform>(spawned[0]).unwrap();
+    assert!((transform.translation.x - sphere.center.x).abs() < 1e-6);
+    assert!((transform.scale - Vec3::splat(sphere.radius)).length() < 1e-6);
+
+    // The unit cylinder along Y is stretched from one end to the other.
+    let cylinder = &list.cylinders[0];
+    let transform = world.get::<Transform>(spawned[list.spheres.len()]).unwrap();
+    let end = |p: nalgebra::Point3<f32>| Vec3::new(p.x, p.y, p.z);
+    let top = transform.transform_point(Vec3::new(0.0, 0.5, 0.0));
+    let bottom = transform.transform_point(Vec3::new(0.0, -0.5, 0.0));
+    assert!((top - end(cylinder.end)).length() < 1e-4, "{top}");
+    assert!((bottom - end(cylinder.start)).length() < 1e-4, "{bottom}");
+    assert!((transform.scale.x - cylinder.radius).abs() < 1e-6);
+}
+
+#[test]
+fn test_display_list_is_respawned_after_edits() {
+    type Viewer = MoleculeViewer<SelectedAtomRender>;
+    let mut viewer = Viewer::new();
+    viewer.set_molecule(Molecule::from_mol2(Path::new("Benzene.mol2")).unwrap());
+    let mut world = World::new();
+    world.init_resource::<Assets<Mesh>>();
+    world.init_resource::<Assets<StandardMaterial>>();
+    world.insert_non_send_resource(viewer);
+    let shapes = |world: &mut World| -> Vec<Entity> {
+        world
+            .query_filtered::<Entity, With<MoleculeShape>>()
+            .iter(world)
+            .collect()
+    };
+
+    world.run_system_once(sync_display_list::<SelectedAtomRender>);
+    let first = shapes(&mut world);
+    assert!(first.len() >= 24);
+    assert!(!world.non_send_resource::<Viewer>().needs_update());
+    // Nothing changed: the same entities stay.
+    world.run_system_once(sync_display_list::<SelectedAtomRender>);
+    assert_eq!(shapes(&mut world), first);
+
+    // A hydrogen and its bond go.
+    let mut viewer = world.non_send_resource_mut::<Viewer>();
+    viewer.delete_atoms(&[11]).unwrap();
+    world.run_system_once(sync_display_list::<SelectedAtomRender>);
+    let second = shapes(&mut world);
+    assert_eq!(second.len(), first.len() - 2);
+    assert!(second.iter().all(|entity| !first.contains(entity)));
+}
@@ -0,0 +1,183 @@
+mod common;
+
+use common::atom;
+use moleucle_3dview_rs::bond_order::BOND_TOLERANCE;
+use moleucle_3dview_rs::molecule::Bond;
+use moleucle_3dview_rs::Molecule;
+use std::path::Path;
+
+fn single(atom_a: usize, atom_b: usize) -> Bond {
+    Bond {
+        atom_a,
+        atom_b,
+        order: 1,
+    }
+}
+
+/// Sum of bond orders per atom.
+fn total_orders(mol: &Molecule) -> Vec<u8> {
+    let mut totals = vec![0; mol.atoms.len()];
+    for bond in &mol.bonds {
+        totals[bond.atom_a] += bond.order;
+        totals[bond.atom_b] += bond.order;
+    }
+    totals
+}
+
+#[test]
+fn test_benzene_alternates() {
+    // As if converted from XYZ: connectivity only.
+    let mut mol = Molecule::from_mol2(Path::new("Benzene.mol2")).unwrap();
+    for bond in &mut mol.bonds {
+        bond.order = 1;
+    }
+    let changes = mol.perceive_bond_orders();
+    assert_eq!(changes.len(), 3);
+
+    // Every carbon gets exactly one double bond; hydrogens stay single.
+    let totals = total_orders(&mol);
+    assert!(totals[..6].iter().all(|&t| t == 4));
+    assert!(totals[6..].iter().all(|&t| t == 1));
+}
+
+#[test]
+fn test_carbon_dioxide() {
+    let mut mol = Molecule {
+        atoms: vec![
+            atom("O", -1.16, 0.0, 0.0),
+            atom("C", 0.0, 0.0, 0.0),
+            atom("O", 1.16, 0.0, 0.0),
+        ],
+        bonds: vec![single(0, 1), single(1, 2)],
+        ..Default::default()
+    };
+    mol.perceive_bond_orders();
+    assert_eq!(mol.bonds[0].order, 2);
+    assert_eq!(mol.bonds[1].order, 2);
+}
+
+#[test]
+fn test_acetylene_and_ethane() {
+    let mut acetylene = Molecule {
+        atoms: vec![
+            atom("H", -1.66, 0.0, 0.0),
+            atom("C", -0.6, 0.0, 0.0),
+            atom("C", 0.6, 0.0, 0.0),
+            atom("H", 1.66, 0.0, 0.0),
+        ],
+        bonds: vec![single(0, 1), single(1, 2), single(2, 3)],
+        ..Default::default()
+    };
+    acetylene.perceive_bond_orders();
+    assert_eq!(acetylene.bonds[1].order, 3);
+
+    // Tetrahedral carbons stay single even with a short bond.
+    let mut ethane = Molecule {
+        atoms: vec![
+            atom("C", 0.0, 0.0, 0.0),
+            atom("C", 1.4, 0.0, 0.0),
+            atom("H", -0.36, 1.03, 0.0),
+            atom("H", -0.36, -0.51, 0.89),
+            atom("H", -0.36, -0.51, -0.89),
+        ],
+        bonds: vec![single(0, 1), single(0, 2), single(0, 3), single(0, 4)],
+        ..Default::default()
+    };
+    assert!(ethane.perceive_bond_orders().is_empty());
+}
+
+#[test]
+fn test_propose_is_a_dry_run() {
+    let mol = Molecule {
+        atoms: vec![atom("C", 0.0, 0.0, 0.0), atom("O", 1.21, 0.0, 0.0)],
+        bonds: vec![single(0, 1)],
+        ..Default::default()
+    };
+    let proposed = mol.propose_bond_orders();
+    assert_eq!(proposed.len(), 1);
+    assert_eq!((proposed[0].old_order, proposed[0].new_order), (1, 2));
+    assert_eq!(mol.bonds[0].order, 1);
+
+    let mut applied = mol.clone();
+    assert_eq!(applied.perceive_bond_orders(), proposed);
+    assert_eq!(applied.bonds[0].order, 2);
+}
+
+#[test]
+fn test_infer_bonds_matches_mol2_connectivity() {
+    let mol2 = Molecule::from_mol2(Path::new("Benzene.mol2")).unwrap();
+    let mut bare = Molecule {
+        atoms: mol2.atoms.clone(),
+        ..Default::default()
+    };
+    assert_eq!(bare.infer_bonds(BOND_TOLERANCE), 12);
+    let pairs = |mol: &Molecule| {
+        let mut pairs: Vec<_> = mol
+            .bonds
+            .iter()
+            .map(|b| (b.atom_a.min(b.atom_b), b.atom_a.max(b.atom_b)))
+            .collect();
+        pairs.sort_unstable();
+        pairs
+    };
+    assert_eq!(pairs(&bare), pairs(&mol2));
+    assert!(bare.bonds.iter().all(|b| b.order == 1));
+    // Bonds already there are kept, not doubled.
+    assert_eq!(bare.infer_bonds(BOND_TOLERANCE), 0);
+}
+
+#[test]
+fn test_xyz_water_dimer_bonds() {
+    // The hydrogen bond O-H...O is 1.95 Å, too long to count.
+    let dimer = Molecule::from_xyz_str(
+        "7\nwater dimer and an unknown atom\n\
+         O  0.000  0.000  0.000\n\
+         H  0.957  0.000  0.000\n\
+         H -0.240  0.927  0.000\n\
+         O  2.910  0.000  0.000\n\
+         H  3.150  0.927  0.000\n\
+         H  3.150 -0.463  0.800\n\
+         Xx 0.500  0.500  0.000\n",
+    )
+    .unwrap();
+    let mut pairs: Vec<_> = dimer.bonds.iter().map(|b| (b.atom_a, b.atom_b)).collect();
+    pairs.sort_unstable();
+    assert_eq!(pairs, [(0, 1), (0, 2), (3, 4), (3, 5)]);
+
+    // A tighter tolerance drops the long bond.
+    let mut stretched = Molecule {
+        atoms: vec![atom("C", 0.0, 0.0, 0.0), atom("C", 1.7, 0.0, 0.0)],
+        ..Default::default()
+    };
+    assert_eq!(stretched.infer_bonds(0.1), 0);
+    assert_eq!(stretched.infer_bonds(BOND_TOLERANCE), 1);
+}
+
+#[test]
+fn test_water_cluster_from_file_and_scaled_cutoff() {
+    // A water trimer: every O-H within a molecule, no bonds between them.
+    let xyz = "9\nwater trimer\n\
+               O  0.000  0.000  0.000\nH  0.957  0.000  0.000\nH -0.240  0.927  0.000\n\
+               O  2.850  0.000  0.000\nH  3.090  0.927  0.000\nH  3.090 -0.463  0.800\n\
+               O  1.420  2.480  0.000\nH  1.200  3.410  0.000\nH  0.620  1.940  0.000\n";
+    let path = std::env::temp_dir().join(format!("water_trimer_{}.xyz", std::process::id()));
+    std::fs::write(&path, xyz).unwrap();
+    let cluster = Molecule::from_xyz(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(cluster.bonds.len(), 6);
+    for bond in &cluster.bonds {
+        let (a, b) = (bond.atom_a, bond.atom_b);
+        let elements = [&cluster.atoms[a].element, &cluster.atoms[b].element];
+        assert!(elements.contains(&&"O".to_string()) && elements.contains(&&"H".to_string()));
+        assert_eq!(a / 3, b / 3, "bond {a}-{b} crosses molecules");
+    }
+
+    // 0.957 Å against an O-H radius sum of 0.97 Å.
+    let mut bare = Molecule {
+        atoms: cluster.atoms.clone(),
+        ..Default::default()
+    };
+    assert_eq!(bare.infer_bonds_scaled(0.95), 0);
+    assert_eq!(bare.infer_bonds_scaled(1.2), 6);
+    assert_eq!(bare.infer_bonds_scaled(f32::NAN), 0);
+}
@@ -0,0 +1,106 @@
+use moleucle_3dview_rs::viewer::{Detail, VisibilityMode, BOND_RADIUS};
+use moleucle_3dview_rs::{
+    CoordinationRender, Cutaway, EventMask, Molecule, MoleculeViewer, PinnedMotion, RenderStyle,
+    SelectedAtomRender, ViewerBuilder,
+};
+use nalgebra::{Point3, Vector3};
+use std::path::Path;
+
+fn benzene() -> Molecule {
+    Molecule::from_mol2(Path::new("Benzene.mol2")).unwrap()
+}
+
+#[test]
+fn test_builder_defaults_match_new() {
+    let (viewer, warnings) = MoleculeViewer::<SelectedAtomRender>::builder().build();
+    let plain = MoleculeViewer::<SelectedAtomRender>::new();
+    assert!(warnings.is_empty());
+    assert!(viewer.molecule.is_none() && viewer.additional_render.is_none());
+    assert_eq!(viewer.render_style, plain.render_style);
+    assert_eq!(
+        format!("{:?}", viewer.settings),
+        format!("{:?}", plain.settings)
+    );
+    assert!(!viewer.bond_mode() && viewer.reference_axis().is_none());
+}
+
+#[test]
+fn test_builder_explicit_style_wins_over_auto_style() {
+    let (viewer, warnings) = ViewerBuilder::new()
+        .molecule(benzene())
+        .render_style(RenderStyle::Stick)
+        .detail(Detail::Low)
+        .pinned_motion(PinnedMotion::Refuse)
+        .additional_render(SelectedAtomRender::new())
+        .reference_axis(Vector3::z(), "normal")
+        .bond_mode(true)
+        .build();
+    assert!(warnings.is_empty(), "{warnings:?}");
+    assert_eq!(viewer.molecule.as_ref().unwrap().atoms.len(), 12);
+    assert_eq!(viewer.render_style, RenderStyle::Stick);
+    assert_eq!(viewer.settings.detail, Detail::Low);
+    // Left to auto_style, which shows a small molecule's hydrogens.
+    assert!(!viewer.settings.hide_hydrogens);
+    assert_eq!(viewer.settings.pinned_motion, PinnedMotion::Refuse);
+    assert_eq!(viewer.reference_axis().unwrap().label, "normal");
+    assert!(viewer.bond_mode() && viewer.dirty);
+
+    let (viewer, _) = MoleculeViewer::<SelectedAtomRender>::builder()
+        .settings(viewer.settings.clone())
+        .molecule(benzene())
+        .auto_style(false)
+        .build();
+    assert_eq!(viewer.render_style, RenderStyle::BallAndStick);
+    assert_eq!(viewer.settings.detail, Detail::Low);
+}
+
+#[test]
+fn test_builder_drops_what_cannot_work() {
+    let (viewer, warnings) = ViewerBuilder::<SelectedAtomRender>::new()
+        .molecule(benzene())
+        .hidden_atoms([1, 12, 20])
+        .color_override(3, (1.0, 0.0, 0.0))
+        .color_override(15, (0.0, 1.0, 0.0))
+        .hydrogen_mode(VisibilityMode::Ghost { opacity: 1.5 })
+        .cutaway(Cutaway {
+            center: Point3::origin(),
+            radius: 0.0,
+            mode: VisibilityMode::Hide,
+        })
+        .coordination_on_click(true)
+        .reference_axis(Vector3::zeros(), "none")
+        .stick_radius(-0.1)
+        .build();
+    assert_eq!(warnings.len(), 7, "{warnings:?}");
+    let settings = &viewer.settings;
+    assert_eq!(
+        settings.hidden_atoms.iter().copied().collect::<Vec<_>>(),
+        [1]
+    );
+    assert_eq!(settings.color_overrides.len(), 1);
+    assert!(settings.color_overrides.contains_key(&3));
+    assert_eq!(
+        settings.hydrogen_mode,
+        VisibilityMode::Ghost { opacity: 1.0 }
+    );
+    assert!(settings.cutaway.is_none());
+    assert!(!settings.coordination_on_click);
+    assert!(viewer.reference_axis().is_none());
+    assert_eq!(settings.stick_radius, BOND_RADIUS);
+
+    // Without a molecule there is nothing to check indices against, and a
+    // coordination overlay takes the clicks.
+    let (mut viewer, warnings) = ViewerBuilder::new()
+        .hidden_atoms([20])
+        .coordination_on_click(true)
+        .additional_render(CoordinationRender::new())
+        .render_style(RenderStyle::SpaceFilling)
+        .bond_stubs(Some(0.2))
+        .capture_hook(EventMask::CLICKS, std::env::temp_dir())
+        .build();
+    assert_eq!(warnings.len(), 1, "{warnings:?}");
+    assert!(warnings[0].contains("space-filling"));
+    assert!(viewer.settings.hidden_atoms.contains(&20));
+    assert!(viewer.settings.coordination_on_click);
+    assert!(viewer.capture_hook_mut().is_some());
+}
@@ -0,0 +1,219 @@
+use moleucle_3dview_rs::camera::{Camera, OrbitalCamera, ViewBookmark};
+use moleucle_3dview_rs::camera_path::{CameraPath, CameraPathError};
+use moleucle_3dview_rs::molecule::Atom;
+use moleucle_3dview_rs::{
+    CameraController, IdleSpin, Molecule, MoleculeViewer, SelectedAtomRender,
+};
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+
+fn view(x: f32, yaw: f32, radius: f32) -> ViewBookmark {
+    ViewBookmark {
+        center: Point3::new(x, 0.0, 0.0),
+        rotation: UnitQuaternion::from_axis_angle(&Vector3::y_axis(), yaw),
+        radius,
+    }
+}
+
+fn path() -> CameraPath {
+    CameraPath::new()
+        .keyframe(0.0, view(0.0, 0.0, 10.0))
+        .keyframe(1.0, view(1.0, 0.5, 8.0))
+        .keyframe(3.0, view(3.0, 1.0, 12.0))
+}
+
+#[test]
+fn test_invalid_paths() {
+    assert_eq!(
+        CameraPath::new().sample(0.0),
+        Err(CameraPathError::TooFewKeyframes(0))
+    );
+    assert_eq!(
+        CameraPath::new()
+            .keyframe(0.0, view(0.0, 0.0, 10.0))
+            .sample(0.0),
+        Err(CameraPathError::TooFewKeyframes(1))
+    );
+    let duplicate = path().keyframe(3.0, view(0.0, 0.0, 5.0));
+    assert_eq!(
+        duplicate.validate(),
+        Err(CameraPathError::DuplicateTime {
+            index: 3,
+            time: 3.0
+        })
+    );
+    let backwards = path().keyframe(2.0, view(0.0, 0.0, 5.0));
+    assert_eq!(
+        backwards.validate(),
+        Err(CameraPathError::NonMonotonic {
+            index: 3,
+            time: 2.0
+        })
+    );
+}
+
+#[test]
+fn test_non_finite_times() {
+    let nan_key = path().keyframe(f32::NAN, view(0.0, 0.0, 5.0));
+    assert!(matches!(
+        nan_key.validate(),
+        Err(CameraPathError::NonFiniteTime { index: 3, time }) if time.is_nan()
+    ));
+    assert!(nan_key.sample(1.0).is_err());
+    let infinite_first = CameraPath::new()
+        .keyframe(f32::NEG_INFINITY, view(0.0, 0.0, 10.0))
+        .keyframe(1.0, view(1.0, 0.0, 10.0));
+    assert!(matches!(
+        infinite_first.validate(),
+        Err(CameraPathError::NonFiniteTime { index: 0, .. })
+    ));
+    assert_eq!(path().sample(f32::NAN), Err(CameraPathError::NanSampleTime));
+    // Infinite times clamp like any other.
+    assert_eq!(path().sample(f32::INFINITY), path().sample(3.0));
+}
+
+#[test]
+fn test_sample_passes_through_keyframes() {
+    let path = path();
+    for (time, key) in path.keyframes() {
+        let sampled = path.sample(*time).unwrap();
+        assert!((sampled.center - key.center).norm() < 1e-5);
+        assert!((sampled.radius - key.radius).abs() < 1e-5);
+        assert!(sampled.rotation.angle_to(&key.rotation) < 1e-5);
+    }
+
+    // Clamped outside the range.
+    let before = path.sample(-5.0).unwrap();
+    assert!((before.center - Point3::origin()).norm() < 1e-5);
+    let after = path.sample(10.0).unwrap();
+    assert!((after.center - Point3::new(3.0, 0.0, 0.0)).norm() < 1e-5);
+}
+
+#[test]
+fn test_sample_interpolates() {
+    // Centers move at a constant 1 unit/s, which Catmull-Rom reproduces exactly.
+    let path = path();
+    let mid = path.sample(2.0).unwrap();
+    assert!((mid.center - Point3::new(2.0, 0.0, 0.0)).norm() < 1e-4);
+
+    let quarter = path.sample(0.5).unwrap();
+    let expected = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), 0.25);
+    assert!(quarter.rotation.angle_to(&expected) < 1e-4);
+}
+
+#[test]
+fn test_path_serde_round_trip() {
+    let path = path();
+    let json = serde_json::to_string(&path).unwrap();
+    let back: CameraPath = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, path);
+}
+
+#[test]
+fn test_controller_follows_path() {
+    let mut controller = CameraController::<OrbitalCamera>::new();
+    assert_eq!(
+        controller.follow_path(CameraPath::new(), 1.0),
+        Err(CameraPathError::TooFewKeyframes(0))
+    );
+    assert!(controller.follow_path(path(), 0.0).is_err());
+
+    controller.follow_path(path(), 2.0).unwrap();
+    assert!(controller.tick(1.0));
+    assert!(controller.is_following_path());
+    assert!(controller.tick(1.5));
+    assert!(!controller.is_following_path());
+    assert!(!controller.tick(0.1));
+
+    let end = view(3.0, 1.0, 12.0);
+    assert!((controller.camera.target() - end.center).norm() < 1e-4);
+    assert!((controller.camera.position() - end.position()).norm() < 1e-3);
+}
+
+#[test]
+fn test_idle_spin() {
+    let mut controller = CameraController::<OrbitalCamera>::new();
+    view(0.0, 0.0, 10.0).apply(controller.camera.as_mut());
+    // Tilted 30° towards the pole.
+    controller.camera.orbit(0.0, 30f32.to_radians());
+    let height = |c: &CameraController<OrbitalCamera>| c.camera.position().y;
+    let start_height = height(&controller);
+
+    controller.set_idle_spin(Some(IdleSpin {
+        axis: Vector3::y(),
+        rate_deg_per_s: 90.0,
+        resume_after_s: 2.0,
+    }));
+    assert!(!controller.tick(1.5));
+    assert!(controller.tick(1.0));
+    assert!(controller.is_idle_spinning());
+
+    // A quarter turn a second, around the target, at a constant elevation.
+    let before = controller.camera.position();
+    assert!(controller.tick(1.0));
+    let after = controller.camera.position();
+    assert!((before.coords.xz().angle(&after.coords.xz()) - 90f32.to_radians()).abs() < 1e-3);
+    for _ in 0..100 {
+        controller.tick(0.37);
+    }
+    assert!((height(&controller) - start_height).abs() < 1e-3);
+    assert!((controller.camera.target() - Point3::origin()).norm() < 1e-4);
+
+    // Input stops it until the timeout passes again.
+    controller.reset_idle();
+    assert!(!controller.tick(1.9));
+    assert!(controller.tick(0.2));
+
+    controller.set_idle_spin(None);
+    assert!(!controller.tick(10.0));
+}
+
+#[test]
+fn test_idle_spin_pauses_during_path() {
+    let mut controller = CameraController::<OrbitalCamera>::new();
+    controller.set_idle_spin(Some(IdleSpin {
+        resume_after_s: 0.0,
+        ..Default::default()
+    }));
+    controller.follow_path(path(), 1.0).unwrap();
+    assert!(!controller.is_idle_spinning());
+    controller.tick(1.0);
+    let end = view(3.0, 1.0, 12.0);
+    assert!((controller.camera.position() - end.position()).norm() < 1e-3);
+    // Spinning resumes once the path is done.
+    assert!(controller.is_idle_spinning());
+    assert!(controller.tick(0.5));
+}
+
+#[test]
+fn test_render_path_writes_frames() {
+    let dir = std::env::temp_dir().join(format!("{}_path_frames", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(Molecule {
+        atoms: vec![Atom {
+            position: Point3::origin(),
+            element: "C".to_string(),
+            id: 1,
+        }],
+        ..Default::default()
+    });
+    assert!(viewer
+        .render_path(&dir, &CameraPath::new(), 3, (8, 8))
+        .is_err());
+    assert!(viewer.render_path(&dir, &path(), 0, (8, 8)).is_err());
+
+    let paths = viewer.render_path(&dir, &path(), 3, (16, 8)).unwrap();
+    let names: Vec<_> = paths
+        .iter()
+        .map(|p| p.file_name().unwrap().to_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        ["frame-0000.png", "frame-0001.png", "frame-0002.png"]
+    );
+    let png = std::fs::read(&paths[0]).unwrap();
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+    // IHDR width and height.
+    assert_eq!(png[16..24], [0, 0, 0, 16, 0, 0, 0, 8]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
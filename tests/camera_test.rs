@@ -1,4 +1,14 @@
-use moleucle_3dview_rs::camera::{Camera, OrbitalCamera};
+use graphics::winit::keyboard::KeyCode;
+use graphics::Scene;
+use moleucle_3dview_rs::camera::{
+    Camera, OrbitalCamera, ProjectionType, ResizePolicy, UpAxis, ViewBookmark, ViewPreset,
+};
+use moleucle_3dview_rs::controller::ORTHO_FOV_Y;
+use moleucle_3dview_rs::molecule::Atom;
+use moleucle_3dview_rs::viewer::ViewerEvent;
+use moleucle_3dview_rs::{
+    CameraController, GroundDiscRender, Molecule, MoleculeViewer, SelectedAtomRender,
+};
 use nalgebra::{Point3, Vector2, Vector3};
 
 #[test]
@@ -69,3 +79,461 @@ fn test_ray_cast_default() {
     assert!((dir.y - 0.0).abs() < 1e-5);
     assert!((dir.z + 1.0).abs() < 1e-5); // -(-1) = 1
 }
+
+fn ortho_camera(policy: ResizePolicy) -> OrbitalCamera {
+    let mut cam = OrbitalCamera {
+        projection: ProjectionType::Orthographic,
+        ortho_scale: 4.0,
+        ..Default::default()
+    };
+    cam.set_resize_policy(policy);
+    cam.set_aspect(1.0);
+    cam
+}
+
+#[test]
+fn test_ortho_pick_off_centre() {
+    // One atom at the target and one above it, seen from (0, 0, 10).
+    let atom = |y| Atom {
+        position: Point3::new(0.0, y, 0.0),
+        element: "C".to_string(),
+        id: 0,
+    };
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(Molecule {
+        atoms: vec![atom(0.0), atom(1.5)],
+        ..Default::default()
+    });
+    let (w, h) = (800.0, 600.0);
+    let mut cam = OrbitalCamera::default();
+    cam.set_aspect(w / h);
+    cam.set_projection(ProjectionType::Orthographic);
+
+    // The cursor over the upper atom.
+    let ndc = cam
+        .view_projection()
+        .transform_point(&Point3::new(0.0, 1.5, 0.0));
+    let (u, v) = ((ndc.x + 1.0) * 0.5 * w, (1.0 - ndc.y) * 0.5 * h);
+    let (origin, dir) = cam.screen_ray(u, v, w, h);
+    // Along the view, from the near plane under the cursor.
+    assert!((dir - -Vector3::z()).norm() < 1e-5, "{dir}");
+    assert!((origin - Point3::new(0.0, 1.5, 10.0 - cam.near)).norm() < 1e-3);
+    let (origin, dir) = cam.ray_from_screen(u, v, w, h);
+    assert_eq!(viewer.pick(origin, dir), Some(ViewerEvent::AtomClicked(1)));
+
+    // Rays off to the side stay parallel.
+    let (_, side) = cam.screen_ray(100.0, 500.0, w, h);
+    assert!((side - -Vector3::z()).norm() < 1e-5, "{side}");
+}
+
+// Fraction of the viewport width and height covered by a unit square at the target.
+fn unit_square_extent(cam: &OrbitalCamera) -> (f32, f32) {
+    let vp = cam.view_projection();
+    let corner = vp.transform_point(&Point3::new(0.5, 0.5, 0.0));
+    let opposite = vp.transform_point(&Point3::new(-0.5, -0.5, 0.0));
+    (
+        (corner.x - opposite.x).abs() / 2.0,
+        (corner.y - opposite.y).abs() / 2.0,
+    )
+}
+
+#[test]
+fn test_ortho_resize_fixed_horizontal() {
+    let mut cam = ortho_camera(ResizePolicy::FixedHorizontal);
+    let (width_before, _) = unit_square_extent(&cam);
+    // Doubling the window width doubles the aspect ratio.
+    cam.set_aspect(2.0);
+    let (width_after, _) = unit_square_extent(&cam);
+    assert!((width_before - width_after).abs() < 1e-5);
+    assert!((cam.ortho_scale - 2.0).abs() < 1e-5);
+}
+
+#[test]
+fn test_ortho_resize_fixed_vertical() {
+    let mut cam = ortho_camera(ResizePolicy::FixedVertical);
+    let (_, height_before) = unit_square_extent(&cam);
+    cam.set_aspect(0.5);
+    let (_, height_after) = unit_square_extent(&cam);
+    assert!((height_before - height_after).abs() < 1e-5);
+}
+
+#[test]
+fn test_ortho_resize_fit_content() {
+    let mut cam = ortho_camera(ResizePolicy::FitContent);
+    let (w0, h0) = unit_square_extent(&cam);
+    // Narrowing the window shrinks the content so it still fits horizontally.
+    cam.set_aspect(0.5);
+    let (w1, h1) = unit_square_extent(&cam);
+    assert!((w1 - w0).abs() < 1e-5);
+    assert!(h1 < h0);
+    // Widening past square keeps the original height.
+    cam.set_aspect(2.0);
+    let (_, h2) = unit_square_extent(&cam);
+    assert!((h2 - h0).abs() < 1e-5);
+}
+
+#[test]
+fn test_fov_is_clamped() {
+    let mut cam = OrbitalCamera::default();
+    cam.set_fov(1.0f32.to_radians());
+    assert!((cam.fov_y().to_degrees() - 10.0).abs() < 1e-3);
+    cam.set_fov(170.0f32.to_radians());
+    assert!((cam.fov_y().to_degrees() - 120.0).abs() < 1e-3);
+}
+
+#[test]
+fn test_dolly_zoom_keeps_apparent_size() {
+    let mut controller = CameraController::<OrbitalCamera>::new();
+    let (_, before) = unit_square_extent(&controller.camera);
+    controller.dolly_zoom(20.0f32.to_radians());
+    assert!((controller.camera.fov_y().to_degrees() - 65.0).abs() < 1e-3);
+    let (_, after) = unit_square_extent(&controller.camera);
+    assert!((before - after).abs() < 1e-4);
+}
+
+#[test]
+fn test_projection_toggle_keeps_apparent_size() {
+    let mut controller = CameraController::<OrbitalCamera>::new();
+    let (_, before) = unit_square_extent(&controller.camera);
+    controller.toggle_projection();
+    assert_eq!(controller.camera.projection(), ProjectionType::Orthographic);
+    let (_, ortho) = unit_square_extent(&controller.camera);
+    assert!((before - ortho).abs() < 1e-4);
+    controller.toggle_projection();
+    assert_eq!(controller.camera.projection(), ProjectionType::Perspective);
+    assert!((controller.camera.radius - 10.0).abs() < 1e-3);
+}
+
+#[test]
+fn test_scene_camera_follows_projection() {
+    let mut controller = CameraController::<OrbitalCamera>::new();
+    let mut scene = Scene::default();
+    controller.update_scene_camera(&mut scene);
+    let perspective = (scene.camera.fov_y, scene.camera.position.z);
+    assert_eq!(perspective, (controller.camera.fov_y, 10.0));
+
+    // Orthographic: the narrow fov spans the orthographic height at the
+    // target, from further back along the same view.
+    controller.toggle_projection();
+    controller.update_scene_camera(&mut scene);
+    let camera = &scene.camera;
+    assert_eq!(camera.fov_y, ORTHO_FOV_Y);
+    let (x, y, z) = (camera.position.x, camera.position.y, camera.position.z);
+    assert!(x.abs() < 1e-4 && y.abs() < 1e-4, "{x} {y}");
+    let height = 2.0 * z * (camera.fov_y / 2.0).tan();
+    assert!(
+        (height - controller.camera.ortho_scale).abs() < 1e-3,
+        "{height}"
+    );
+    // The clip planes stay at the same depths around the target.
+    assert!((z - camera.near - (10.0 - controller.camera.near)).abs() < 1e-2);
+    assert!((camera.far - z - (controller.camera.far - 10.0)).abs() < 1e-2);
+
+    // Zooming the orthographic view reaches the scene camera too.
+    controller.camera.ortho_scale /= 2.0;
+    controller.update_scene_camera(&mut scene);
+    assert!((scene.camera.position.z - z / 2.0).abs() < 1e-2);
+
+    controller.toggle_projection();
+    controller.update_scene_camera(&mut scene);
+    assert_eq!(scene.camera.fov_y, controller.camera.fov_y);
+    assert!((scene.camera.position.z - controller.camera.radius).abs() < 1e-4);
+}
+
+/// Angle in degrees between screen-up and the on-screen direction of `axis`
+/// drawn from the camera target.
+fn screen_angle_from_up(camera: &dyn Camera, axis: Vector3<f32>) -> f32 {
+    let vp = camera.view_projection();
+    let project = |p: Point3<f32>| {
+        let h = vp * p.to_homogeneous();
+        Vector2::new(h.x / h.w, h.y / h.w)
+    };
+    let target = camera.target();
+    let d = project(target + axis * 0.5) - project(target);
+    d.x.atan2(d.y).to_degrees().abs()
+}
+
+#[test]
+fn test_align_up_points_axis_up() {
+    let axis = Vector3::new(0.3, -0.2, 0.9).normalize();
+    for projection in [ProjectionType::Perspective, ProjectionType::Orthographic] {
+        for (dx, dy) in [(0.7, 0.0), (0.0, 1.2), (2.5, 0.0)] {
+            let mut controller = CameraController::<OrbitalCamera>::new();
+            controller.camera.center = Point3::new(1.0, 2.0, 3.0);
+            controller.camera.orbit(dx, dy);
+            controller.camera.set_projection(projection);
+            let (target, distance) = (controller.camera.target(), controller.camera.radius);
+
+            controller.align_up(axis);
+            assert!(controller.is_following_path());
+            while controller.tick(0.1) {}
+
+            let angle = screen_angle_from_up(controller.camera.as_ref(), axis);
+            assert!(angle < 1.0, "{projection:?} ({dx}, {dy}): {angle}°");
+            assert!((controller.camera.target() - target).norm() < 1e-4);
+            assert!((controller.camera.radius - distance).abs() < 1e-3);
+        }
+    }
+}
+
+#[test]
+fn test_align_up_is_a_roll_when_possible() {
+    let mut controller = CameraController::<OrbitalCamera>::new();
+    controller.camera.orbit(0.4, 0.0);
+    let eye = controller.camera.position();
+
+    controller.align_up(Vector3::new(1.0, 1.0, 0.0));
+    while controller.tick(0.1) {}
+    assert!((controller.camera.position() - eye).norm() < 1e-3);
+
+    // Looking straight along the axis needs a tilt instead.
+    let mut controller = CameraController::<OrbitalCamera>::new();
+    controller.align_up(Vector3::z());
+    while controller.tick(0.1) {}
+    let angle = screen_angle_from_up(controller.camera.as_ref(), Vector3::z());
+    assert!(angle < 1.0, "{angle}°");
+}
+
+fn orientation_grid() -> Vec<ViewBookmark> {
+    let center = Point3::new(1.0, -2.0, 0.5);
+    let mut views = Vec::new();
+    for yaw in [-180.0f32, -135.0, -30.0, 0.0, 45.0, 90.0, 170.0] {
+        // Near and at the ±90° pitch gimbal lock too.
+        for pitch in [-90.0f32, -89.9, -60.0, 0.0, 30.0, 89.9, 90.0] {
+            for roll in [-120.0f32, 0.0, 15.0, 90.0] {
+                let (y, p, r) = (yaw.to_radians(), pitch.to_radians(), roll.to_radians());
+                views.push(ViewBookmark::from_euler_zyx(center, 7.5, y, p, r));
+            }
+        }
+    }
+    views
+}
+
+fn assert_same_view(a: &ViewBookmark, b: &ViewBookmark) {
+    assert!((a.center - b.center).norm() < 1e-4, "{a:?} vs {b:?}");
+    assert!((a.radius - b.radius).abs() < 1e-4, "{a:?} vs {b:?}");
+    assert!(a.rotation.angle_to(&b.rotation) < 1e-3, "{a:?} vs {b:?}");
+}
+
+#[test]
+fn test_look_at_round_trip() {
+    for view in orientation_grid() {
+        let (eye, target, up) = view.to_look_at();
+        assert!(up.dot(&(target - eye)).abs() < 1e-4);
+        assert_same_view(&ViewBookmark::from_look_at(eye, target, up), &view);
+
+        let mut cam = OrbitalCamera::default();
+        view.apply(&mut cam);
+        assert_same_view(&ViewBookmark::from_camera(&cam), &view);
+    }
+
+    // A skewed up is straightened; a parallel one falls back to +Y.
+    let view = ViewBookmark::from_look_at(
+        Point3::new(0.0, 0.0, 5.0),
+        Point3::origin(),
+        Vector3::new(0.0, 1.0, 1.0),
+    );
+    assert!((view.up() - Vector3::y()).norm() < 1e-5);
+    let view =
+        ViewBookmark::from_look_at(Point3::new(0.0, 0.0, 5.0), Point3::origin(), Vector3::z());
+    assert!((view.up() - Vector3::y()).norm() < 1e-5);
+}
+
+#[test]
+fn test_euler_zyx_round_trip() {
+    for view in orientation_grid() {
+        let (yaw, pitch, roll) = view.to_euler_zyx();
+        assert!(pitch.abs() <= std::f32::consts::FRAC_PI_2 + 1e-6);
+        let back = ViewBookmark::from_euler_zyx(view.center, view.radius, yaw, pitch, roll);
+        assert_same_view(&back, &view);
+    }
+
+    // Zero angles look down -Z; yaw turns about world Z, pitch about Y.
+    let at = |yaw: f32, pitch: f32| {
+        let view = ViewBookmark::from_euler_zyx(Point3::origin(), 1.0, yaw, pitch, 0.0);
+        (view.position(), view.up())
+    };
+    let (eye, up) = at(0.0, 0.0);
+    assert!((eye - Point3::new(0.0, 0.0, 1.0)).norm() < 1e-6 && (up - Vector3::y()).norm() < 1e-6);
+    let (_, up) = at(90f32.to_radians(), 0.0);
+    assert!((up + Vector3::x()).norm() < 1e-6);
+    let (eye, _) = at(0.0, 90f32.to_radians());
+    assert!((eye - Point3::new(1.0, 0.0, 0.0)).norm() < 1e-6);
+
+    // In gimbal lock only yaw - roll matters at +90° pitch.
+    let pitch = 90f32.to_radians();
+    let a = ViewBookmark::from_euler_zyx(Point3::origin(), 1.0, 0.5, pitch, 0.2);
+    let b = ViewBookmark::from_euler_zyx(Point3::origin(), 1.0, 0.8, pitch, 0.5);
+    assert_same_view(&a, &b);
+}
+
+#[test]
+fn test_pymol_view() {
+    let cam = OrbitalCamera::default();
+    let view = cam.to_pymol_view();
+    let expected = [
+        1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, // looking down -Z
+        0.0, 0.0, -10.0, 0.0, 0.0, 0.0, 0.1, 100.0, -45.0,
+    ];
+    for (a, b) in view.iter().zip(expected) {
+        assert!((a - b).abs() < 1e-5, "{view:?}");
+    }
+
+    // From +X: world X maps to camera +Z, the first column-major column.
+    let mut cam = OrbitalCamera {
+        projection: ProjectionType::Orthographic,
+        ..Default::default()
+    };
+    cam.look_at(
+        Point3::new(4.0, 2.0, 0.0),
+        Point3::new(0.0, 2.0, 0.0),
+        Vector3::y(),
+    );
+    let view = cam.to_pymol_view();
+    let expected = [
+        0.0, 0.0, 1.0, 0.0, 1.0, 0.0, -1.0, 0.0, 0.0, //
+        0.0, 0.0, -4.0, 0.0, 2.0, 0.0, 0.1, 100.0, 45.0,
+    ];
+    for (a, b) in view.iter().zip(expected) {
+        assert!((a - b).abs() < 1e-5, "{view:?}");
+    }
+}
+
+fn assert_close(a: Vector3<f32>, b: Vector3<f32>) {
+    assert!((a - b).norm() < 1e-5, "{a:?} vs {b:?}");
+}
+
+/// The basic camera behavior under both conventions: Z-up is Y-up turned by
+/// `UpAxis::basis`, +Y becoming +Z and +Z becoming -Y.
+#[test]
+fn test_orbital_camera_up_axes() {
+    for up_axis in [UpAxis::Y, UpAxis::Z] {
+        let basis = up_axis.basis();
+        let cam = OrbitalCamera::with_up_axis(up_axis);
+        assert_close(cam.position().coords, basis * Vector3::new(0.0, 0.0, 10.0));
+        assert_close(cam.up(), up_axis.up());
+        assert_eq!(cam.up_axis(), up_axis);
+
+        // Orbiting sideways turns about world up: the horizon stays level.
+        let mut orbited = OrbitalCamera::with_up_axis(up_axis);
+        orbited.orbit(0.7, 0.0);
+        assert_close(orbited.up(), up_axis.up());
+        assert!(orbited.position().coords.dot(&up_axis.up()).abs() < 1e-5);
+
+        let mut panned = OrbitalCamera::with_up_axis(up_axis);
+        panned.pan(Vector2::new(1.0, 1.0));
+        // 0.01 of the distance per unit, right and up.
+        assert_close(panned.center.coords, (Vector3::x() + up_axis.up()) * 0.1);
+
+        let mut cam = OrbitalCamera::with_up_axis(up_axis);
+        cam.set_aspect(800.0 / 600.0);
+        let (_, dir) = cam.screen_ray(400.0, 300.0, 800.0, 600.0);
+        assert_close(dir, basis * -Vector3::z());
+
+        let eye = Point3::from(basis * Vector3::new(3.0, 0.0, -4.0));
+        cam.look_at(eye, Point3::origin(), up_axis.up());
+        assert_close(cam.position().coords, eye.coords);
+        assert_close(cam.up(), up_axis.up());
+    }
+}
+
+#[test]
+fn test_view_presets_follow_up_axis() {
+    let viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    let cases = [
+        (UpAxis::Y, ViewPreset::Front, Vector3::z(), Vector3::y()),
+        (UpAxis::Y, ViewPreset::Top, Vector3::y(), -Vector3::z()),
+        (UpAxis::Y, ViewPreset::Left, -Vector3::x(), Vector3::y()),
+        (UpAxis::Z, ViewPreset::Front, -Vector3::y(), Vector3::z()),
+        (UpAxis::Z, ViewPreset::Top, Vector3::z(), Vector3::y()),
+        (UpAxis::Z, ViewPreset::Bottom, -Vector3::z(), -Vector3::y()),
+        (UpAxis::Z, ViewPreset::Right, Vector3::x(), Vector3::z()),
+    ];
+    for (up_axis, preset, eye, up) in cases {
+        let mut camera = OrbitalCamera::with_up_axis(up_axis);
+        camera.center = Point3::new(1.0, 2.0, 3.0);
+        camera.orbit(0.3, 0.0);
+        let mut controller = CameraController::with_camera(camera);
+        controller.view_preset(preset);
+        let cam = controller.camera.as_ref();
+        assert_close(cam.position() - cam.target(), eye * 10.0);
+        assert_close(cam.up(), up);
+        assert_eq!(cam.target(), Point3::new(1.0, 2.0, 3.0));
+    }
+
+    // Numpad 7 is top, "looking down -Z" for Z-up; Ctrl flips it.
+    let mut controller = CameraController::with_camera(OrbitalCamera::with_up_axis(UpAxis::Z));
+    controller.key_input(KeyCode::Numpad7, true, &viewer);
+    assert_close(controller.camera.position().coords, Vector3::z() * 10.0);
+    controller.key_input(KeyCode::ControlLeft, true, &viewer);
+    controller.key_input(KeyCode::Numpad1, true, &viewer);
+    assert_close(controller.camera.position().coords, Vector3::y() * 10.0);
+}
+
+#[test]
+fn test_ground_disc_follows_up_axis() {
+    let atom = |x: f32, y: f32, z: f32| Atom {
+        position: Point3::new(x, y, z),
+        element: "C".to_string(),
+        id: 0,
+    };
+    let mol = Molecule {
+        atoms: vec![atom(-1.0, 0.0, 2.0), atom(3.0, 4.0, 6.0)],
+        ..Default::default()
+    };
+    let mut disc = GroundDiscRender::new();
+    let (y_top, y_radius) = disc.disc(&mol).unwrap();
+    assert!(y_top.y < 0.0 && (y_top.x, y_top.z) == (1.0, 4.0));
+
+    disc.up_axis = UpAxis::Z;
+    let (z_top, z_radius) = disc.disc(&mol).unwrap();
+    assert!(z_top.z < 2.0);
+    assert!((z_top.x - 1.0).abs() < 1e-5 && (z_top.y - 2.0).abs() < 1e-5);
+    // The same gap below the lowest atom, and the footprint across X and Y.
+    assert!(((2.0 - z_top.z) - (0.0 - y_top.y)).abs() < 1e-5);
+    assert!(z_radius < y_radius + 1e-5);
+}
+
+#[test]
+fn test_fit_to_molecule_frames_any_size() {
+    // A chain of atoms along x, long enough to need a step back.
+    let chain = |n: usize| Molecule {
+        atoms: (0..n)
+            .map(|i| Atom {
+                position: Point3::new(1.5 * i as f32, 0.0, 0.0),
+                element: "C".to_string(),
+                id: 0,
+            })
+            .collect(),
+        ..Default::default()
+    };
+    let mut cam = OrbitalCamera::default();
+    let small = chain(3);
+    cam.fit_to_molecule(&small, 1.0);
+    assert!((cam.target() - Point3::new(1.5, 0.0, 0.0)).norm() < 1e-4);
+    let near = (cam.position() - cam.target()).norm();
+
+    let large = chain(100);
+    cam.fit_to_molecule(&large, 1.0);
+    let far = (cam.position() - cam.target()).norm();
+    assert!(far > 10.0 * near);
+    let view_projection = cam.view_projection();
+    for atom in &large.atoms {
+        let ndc = view_projection.transform_point(&atom.position);
+        assert!(ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0, "{ndc:?}");
+    }
+
+    // The controller does the same, from F with the viewer's molecule.
+    let mut controller = CameraController::<OrbitalCamera>::new();
+    controller.frame_molecule(&large);
+    assert!(
+        ((controller.camera.position() - controller.camera.target()).norm() - far).abs() < 1e-2
+    );
+
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(small);
+    let mut controller = CameraController::<OrbitalCamera>::new();
+    let (event, updates) = controller.key_input(KeyCode::KeyF, true, &viewer);
+    assert!(event.is_none() && updates.camera);
+    assert!((controller.camera.target() - Point3::new(1.5, 0.0, 0.0)).norm() < 1e-4);
+}
@@ -0,0 +1,116 @@
+#[cfg(feature = "export")]
+use moleucle_3dview_rs::camera::Camera;
+#[cfg(feature = "export")]
+use moleucle_3dview_rs::capture::CaptureRecord;
+use moleucle_3dview_rs::molecule::Atom;
+use moleucle_3dview_rs::viewer::ViewerEvent;
+use moleucle_3dview_rs::{EventMask, Molecule, MoleculeViewer, OrbitalCamera, SelectedAtomRender};
+#[cfg(feature = "export")]
+use moleucle_3dview_rs::{ManualClock, ViewBookmark};
+use nalgebra::Point3;
+#[cfg(feature = "export")]
+use std::time::Duration;
+
+fn viewer() -> MoleculeViewer<SelectedAtomRender> {
+    let mut viewer = MoleculeViewer::new();
+    viewer.set_molecule(Molecule {
+        atoms: vec![Atom {
+            position: Point3::new(1.0, 0.0, 0.0),
+            element: "C".to_string(),
+            id: 1,
+        }],
+        ..Default::default()
+    });
+    viewer
+}
+
+#[test]
+fn test_event_mask() {
+    let mask = EventMask::ATOM_CLICKED | EventMask::COMMAND_FAILED;
+    assert!(mask.contains(&ViewerEvent::AtomClicked(3)));
+    assert!(mask.contains(&ViewerEvent::CommandFailed(String::new())));
+    assert!(!mask.contains(&ViewerEvent::NothingClicked));
+    assert!(EventMask::CLICKS.contains(&ViewerEvent::NothingClicked));
+    assert!(EventMask::ALL.contains(&ViewerEvent::AtomDragEnded));
+    assert!(!EventMask::NONE.contains(&ViewerEvent::AtomClicked(0)));
+}
+
+#[cfg(feature = "export")]
+#[test]
+fn test_capture_hook_writes_rate_limited_records() {
+    let dir = std::env::temp_dir().join(format!("{}_captures", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let mut viewer = viewer();
+    let camera = OrbitalCamera::default();
+    assert!(viewer.write_captures(&camera).unwrap().is_empty());
+
+    viewer.set_capture_hook(EventMask::CLICKS | EventMask::COMMAND_FAILED, dir.clone());
+    let clock = ManualClock::new();
+    let hook = viewer.capture_hook_mut().unwrap();
+    hook.set_clock(clock.clone());
+    hook.min_interval = Duration::from_secs(1);
+
+    viewer.handle_pick(&ViewerEvent::AtomClicked(0));
+    // Too soon after the click, and not in the mask.
+    viewer.handle_pick(&ViewerEvent::NothingClicked);
+    viewer.handle_pick(&ViewerEvent::AtomHovered(0));
+    clock.advance(Duration::from_secs(2));
+    viewer.handle_pick(&ViewerEvent::AtomHovered(0));
+    // Events the viewer raises itself are seen too.
+    assert!(viewer.delete_bond(0).is_err());
+    viewer.handle_pick(&ViewerEvent::AtomDragged {
+        atom: 5,
+        target: Point3::origin(),
+        rigid: false,
+    });
+    assert_eq!(viewer.capture_hook_mut().unwrap().pending(), 2);
+
+    let paths = viewer.write_captures(&camera).unwrap();
+    assert_eq!(paths.len(), 2);
+    let click = CaptureRecord::from_json(&paths[0]).unwrap();
+    assert_eq!(click.event, "AtomClicked(0)");
+    assert_eq!(click.view, ViewBookmark::from_camera(&camera));
+    let png = std::fs::read(dir.join(&click.image)).unwrap();
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+    assert_eq!(png[16..24], [0, 0, 2, 128, 0, 0, 1, 224]);
+    assert_eq!(click.display_list.spheres.len(), 1);
+    assert_eq!(
+        click.display_list.spheres[0].center,
+        Point3::new(1.0, 0.0, 0.0)
+    );
+    let failure = CaptureRecord::from_json(&paths[1]).unwrap();
+    assert!(failure.event.starts_with("CommandFailed(\"drag: no atom 5"));
+    assert_ne!(paths[0], paths[1]);
+    assert_ne!(click.image, failure.image);
+    assert!(viewer.write_captures(&camera).unwrap().is_empty());
+
+    // A capture shows the view on screen when its event happened, not the
+    // one the camera has moved to by the time it is written.
+    clock.advance(Duration::from_secs(2));
+    viewer.handle_pick(&ViewerEvent::AtomClicked(0));
+    let mut moved = OrbitalCamera::default();
+    moved.orbit(1.0, 0.0);
+    let paths = viewer.write_captures(&moved).unwrap();
+    let click = CaptureRecord::from_json(&paths[0]).unwrap();
+    assert_eq!(click.view, ViewBookmark::from_camera(&camera));
+
+    viewer.clear_capture_hook();
+    clock.advance(Duration::from_secs(2));
+    viewer.handle_pick(&ViewerEvent::AtomClicked(0));
+    assert!(viewer.write_captures(&camera).unwrap().is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(not(feature = "export"))]
+#[test]
+fn test_capture_hook_is_a_no_op_without_export() {
+    let dir = std::env::temp_dir().join(format!("{}_no_captures", std::process::id()));
+    let mut viewer = viewer();
+    viewer.set_capture_hook(EventMask::ALL, dir.clone());
+    viewer.handle_pick(&ViewerEvent::AtomClicked(0));
+    assert!(viewer.delete_bond(0).is_err());
+    assert_eq!(viewer.capture_hook_mut().unwrap().pending(), 0);
+    let camera = OrbitalCamera::default();
+    assert!(viewer.write_captures(&camera).unwrap().is_empty());
+    assert!(!dir.exists());
+}
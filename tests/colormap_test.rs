@@ -0,0 +1,161 @@
+use graphics::Scene;
+use moleucle_3dview_rs::colormap::{
+    cpk_color, hsl_to_rgb, rgb_to_hsl, ColorScheme, Rgb, TintMode, UNKNOWN_ELEMENT_COLOR,
+};
+use moleucle_3dview_rs::molecule::Atom;
+use moleucle_3dview_rs::viewer::EntityKind;
+use moleucle_3dview_rs::{Molecule, MoleculeViewer, SelectedAtomRender};
+use nalgebra::Point3;
+
+fn close(a: Rgb, b: Rgb) -> bool {
+    (a.0 - b.0).abs() < 1e-4 && (a.1 - b.1).abs() < 1e-4 && (a.2 - b.2).abs() < 1e-4
+}
+
+fn oxygen(x: f32) -> Molecule {
+    Molecule {
+        atoms: vec![Atom {
+            position: Point3::new(x, 0.0, 0.0),
+            element: "O".to_string(),
+            id: 0,
+        }],
+        ..Default::default()
+    }
+}
+
+/// Color of atom 0 of each molecule, by molecule id.
+fn atom_colors(viewer: &mut MoleculeViewer<SelectedAtomRender>) -> Vec<Rgb> {
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    let mut colors = vec![(0.0, 0.0, 0.0); viewer.molecule_count()];
+    for (i, entity) in scene.entities.iter().enumerate() {
+        if let Some(EntityKind::Atom { molecule, index: 0 }) = viewer.classify_entity(i) {
+            colors[molecule] = entity.color;
+        }
+    }
+    colors
+}
+
+#[test]
+fn test_hsl_round_trip() {
+    for color in [
+        (0.9, 0.1, 0.1),
+        (0.2, 0.7, 0.3),
+        (0.1, 0.2, 0.9),
+        (0.5, 0.5, 0.5),
+        (1.0, 0.6, 0.0),
+        (0.0, 0.0, 0.0),
+    ] {
+        let (h, s, l) = rgb_to_hsl(color);
+        assert!(close(hsl_to_rgb(h, s, l), color), "{color:?}");
+    }
+    assert!(close(hsl_to_rgb(120.0, 1.0, 0.5), (0.0, 1.0, 0.0)));
+}
+
+#[test]
+fn test_tint_modes() {
+    let red = (0.9, 0.1, 0.1);
+    assert!(close(TintMode::HueShift(120.0).apply(red), (0.1, 0.9, 0.1)));
+    assert!(close(
+        TintMode::HueShift(-240.0).apply(red),
+        (0.1, 0.9, 0.1)
+    ));
+    assert!(close(
+        TintMode::HueShift(90.0).apply((0.4, 0.4, 0.4)),
+        (0.4, 0.4, 0.4)
+    ));
+    assert!(close(TintMode::Desaturate(1.0).apply(red), (0.5, 0.5, 0.5)));
+    assert!(close(TintMode::Desaturate(0.0).apply(red), red));
+    assert!(close(TintMode::Darken(0.5).apply(red), (0.45, 0.05, 0.05)));
+}
+
+#[test]
+fn test_tint_per_molecule_after_override() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    let ids = viewer.add_molecules_with_auto_tint([oxygen(0.0), oxygen(3.0), oxygen(6.0)]);
+    assert_eq!(ids, [0, 1, 2]);
+
+    // Red oxygen shifted by 0, 120 and 240 degrees.
+    let colors = atom_colors(&mut viewer);
+    assert!(close(colors[0], (0.9, 0.1, 0.1)), "{colors:?}");
+    assert!(close(colors[1], (0.1, 0.9, 0.1)), "{colors:?}");
+    assert!(close(colors[2], (0.1, 0.1, 0.9)), "{colors:?}");
+
+    // The override replaces the element color, then the tint applies to it.
+    assert!(viewer.set_tint(0, Some(TintMode::Darken(0.5))));
+    viewer.settings.color_overrides.insert(0, (0.2, 0.4, 0.6));
+    assert!(viewer.set_tint(1, None));
+    let colors = atom_colors(&mut viewer);
+    assert!(close(colors[0], (0.1, 0.2, 0.3)), "{colors:?}");
+    assert!(close(colors[1], (0.9, 0.1, 0.1)), "{colors:?}");
+
+    // Tints stay with their molecule when the active one changes.
+    assert!(viewer.set_active_molecule(2));
+    assert_eq!(viewer.settings.tint, Some(TintMode::HueShift(240.0)));
+    assert_eq!(
+        viewer.display_state(0).unwrap().settings.tint,
+        Some(TintMode::Darken(0.5))
+    );
+    assert!(!viewer.set_tint(7, None));
+}
+
+#[test]
+fn test_cpk_colors() {
+    let common = [
+        "H", "He", "Li", "B", "C", "N", "O", "F", "Ne", "Na", "Mg", "Al", "Si", "P", "S", "Cl",
+        "Ar", "K", "Ca", "Ti", "Cr", "Mn", "Fe", "Co", "Ni", "Cu", "Zn", "Se", "Br", "I", "Pt",
+        "Au",
+    ];
+    for symbol in common {
+        assert_ne!(cpk_color(symbol), UNKNOWN_ELEMENT_COLOR, "{symbol}");
+    }
+    assert_eq!(cpk_color("CL"), cpk_color("Cl"));
+    assert_eq!(cpk_color("O"), (0.9, 0.1, 0.1));
+    assert_eq!(cpk_color("Xx"), UNKNOWN_ELEMENT_COLOR);
+}
+
+#[test]
+fn test_color_schemes() {
+    // Two chains of two residues each; the last atom has no chain.
+    let mut mol = Molecule {
+        atoms: (0..5)
+            .map(|i| Atom {
+                position: Point3::new(2.0 * i as f32, 0.0, 0.0),
+                element: "N".to_string(),
+                id: 0,
+            })
+            .collect(),
+        chains: Some(vec!['A', 'A', 'B', 'B', ' ']),
+        residues: Some(vec![1, 2, 3, 3, 4]),
+        ..Default::default()
+    };
+    let colors = |mol: &Molecule, scheme: ColorScheme| {
+        let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+        viewer.set_molecule(mol.clone());
+        viewer.settings.color_scheme = scheme;
+        let mut scene = Scene::default();
+        viewer.update_scene(&mut scene);
+        (0..mol.atoms.len())
+            .map(|i| scene.entities[i].color)
+            .collect::<Vec<_>>()
+    };
+
+    let blue = cpk_color("N");
+    assert_eq!(colors(&mol, ColorScheme::Cpk), [blue; 5]);
+    assert_eq!(
+        colors(&mol, ColorScheme::Monochrome(0.2, 0.3, 0.4)),
+        [(0.2, 0.3, 0.4); 5]
+    );
+    let by_chain = colors(&mol, ColorScheme::ByChain);
+    assert_eq!(by_chain[0], by_chain[1]);
+    assert_ne!(by_chain[1], by_chain[2]);
+    assert_eq!(by_chain[4], blue);
+    let by_residue = colors(&mol, ColorScheme::ByResidue);
+    assert_ne!(by_residue[0], by_residue[1]);
+    assert_eq!(by_residue[2], by_residue[3]);
+
+    // Without the data, both fall back to element colors.
+    mol.chains = None;
+    mol.residues = None;
+    assert_eq!(colors(&mol, ColorScheme::ByChain), [blue; 5]);
+    assert_eq!(colors(&mol, ColorScheme::ByResidue), [blue; 5]);
+}
@@ -0,0 +1,155 @@
+use lin_alg::f32::Vec3;
+use moleucle_3dview_rs::molecule::Atom;
+use moleucle_3dview_rs::viewer::{RenderStyle, ViewerEvent};
+use moleucle_3dview_rs::{
+    AdditionalRender, DebugRender, Molecule, MoleculeViewer, SelectedAtomRender, TintMode,
+    ViewerCommand, ViewerHandle,
+};
+use nalgebra::Point3;
+use std::thread;
+
+fn chain(n: usize) -> Molecule {
+    Molecule {
+        atoms: (0..n)
+            .map(|i| Atom {
+                position: Point3::new(i as f32 * 1.5, 0.0, 0.0),
+                element: "C".to_string(),
+                id: i,
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+fn failures<U: AdditionalRender>(viewer: &mut MoleculeViewer<U>) -> Vec<String> {
+    viewer
+        .take_events()
+        .into_iter()
+        .filter_map(|event| match event {
+            ViewerEvent::CommandFailed(reason) => Some(reason),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_handle_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync + Clone>() {}
+    assert_send_sync::<ViewerHandle>();
+}
+
+#[test]
+fn test_commands_from_threads_apply_in_order() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.additional_render = Some(Box::new(SelectedAtomRender::new()));
+    let handle = viewer.handle();
+    assert_eq!(viewer.drain_commands(), 0);
+
+    let worker = handle.clone();
+    thread::spawn(move || {
+        worker.send(ViewerCommand::SetMolecule(chain(4))).unwrap();
+        worker.send(ViewerCommand::Select(vec![3, 1])).unwrap();
+        // Later commands see the effect of earlier ones: loading a molecule
+        // clears the selection and picks its style automatically.
+        worker.send(ViewerCommand::SetMolecule(chain(2))).unwrap();
+        worker.send(ViewerCommand::Select(vec![1, 0])).unwrap();
+        worker
+            .send(ViewerCommand::SetStyle(RenderStyle::Stick))
+            .unwrap();
+        worker
+            .send(ViewerCommand::UpdatePositions(vec![
+                Point3::new(0.0, 1.0, 0.0),
+                Point3::new(0.0, 2.0, 0.0),
+            ]))
+            .unwrap();
+        worker
+            .send(ViewerCommand::SetColorOverride {
+                atom: 1,
+                color: Some((1.0, 0.0, 1.0)),
+            })
+            .unwrap();
+        worker
+            .send(ViewerCommand::SetTint {
+                molecule: 0,
+                tint: Some(TintMode::Darken(0.2)),
+            })
+            .unwrap();
+    })
+    .join()
+    .unwrap();
+
+    viewer.dirty = false;
+    assert_eq!(viewer.drain_commands(), 8);
+    assert!(viewer.dirty);
+    assert!(failures(&mut viewer).is_empty());
+
+    let mol = viewer.molecule.as_ref().unwrap();
+    assert_eq!(mol.atoms.len(), 2);
+    assert_eq!(mol.atoms[1].position, Point3::new(0.0, 2.0, 0.0));
+    assert_eq!(viewer.render_style, RenderStyle::Stick);
+    assert_eq!(
+        viewer.settings.color_overrides.get(&1),
+        Some(&(1.0, 0.0, 1.0))
+    );
+    assert_eq!(viewer.settings.tint, Some(TintMode::Darken(0.2)));
+    let render = viewer.additional_render.as_ref().unwrap();
+    assert_eq!(render.selected_atoms(), [0, 1]);
+}
+
+#[test]
+fn test_invalid_commands_fail_without_panicking() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    let handle = viewer.handle();
+    handle.send(ViewerCommand::SetMolecule(chain(3))).unwrap();
+    // The viewer holds the selection; no overlay is needed.
+    handle.send(ViewerCommand::Select(vec![0])).unwrap();
+    handle
+        .send(ViewerCommand::SetColorOverride {
+            atom: 3,
+            color: None,
+        })
+        .unwrap();
+    handle.send(ViewerCommand::SetActiveMolecule(5)).unwrap();
+    handle
+        .send(ViewerCommand::SetTint {
+            molecule: 2,
+            tint: None,
+        })
+        .unwrap();
+    handle
+        .send(ViewerCommand::UpdatePositions(vec![Point3::origin()]))
+        .unwrap();
+    assert_eq!(viewer.drain_commands(), 6);
+    assert_eq!(failures(&mut viewer).len(), 4);
+    assert_eq!(viewer.molecule.as_ref().unwrap().atoms.len(), 3);
+    assert_eq!(viewer.selected_atoms(), [0]);
+
+    // Bad indices are rejected before the selection is touched.
+    viewer.additional_render = Some(Box::new(SelectedAtomRender::new()));
+    handle.send(ViewerCommand::Select(vec![0, 9])).unwrap();
+    viewer.drain_commands();
+    assert_eq!(failures(&mut viewer), ["select: no atom 9"]);
+    assert_eq!(viewer.selected_atoms(), [0]);
+    assert!(viewer
+        .additional_render
+        .as_ref()
+        .unwrap()
+        .selected_atoms()
+        .is_empty());
+
+    // Overlays without a selection of their own don't stop Select.
+    let mut debug = MoleculeViewer::<DebugRender>::new();
+    debug.set_molecule(chain(1));
+    debug.additional_render = Some(Box::new(DebugRender::new((
+        Vec3::new_zero(),
+        Vec3::new_zero(),
+    ))));
+    assert!(debug.apply_command(ViewerCommand::Select(vec![0])).is_ok());
+    assert_eq!(debug.selected_atoms(), [0]);
+
+    // Sending fails once the viewer is gone.
+    drop(viewer);
+    assert!(handle
+        .send(ViewerCommand::SetStyle(RenderStyle::Stick))
+        .is_err());
+}
@@ -0,0 +1,24 @@
+//! Fixtures shared by the integration tests. Each test crate compiles its
+//! own copy and uses only some of them.
+#![allow(dead_code)]
+
+use moleucle_3dview_rs::molecule::{Atom, Bond};
+use nalgebra::Point3;
+
+/// An atom of `element` at (`x`, `y`, `z`), with no id assigned.
+pub fn atom(element: &str, x: f32, y: f32, z: f32) -> Atom {
+    Atom {
+        position: Point3::new(x, y, z),
+        element: element.to_string(),
+        id: 0,
+    }
+}
+
+/// A single bond between `atom_a` and `atom_b`.
+pub fn bond(atom_a: usize, atom_b: usize) -> Bond {
+    Bond {
+        atom_a,
+        atom_b,
+        order: 1,
+    }
+}
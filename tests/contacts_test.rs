@@ -0,0 +1,74 @@
+use graphics::Scene;
+use moleucle_3dview_rs::contacts::{contact_report, contacts_between, ContactKind};
+use moleucle_3dview_rs::molecule::Atom;
+use moleucle_3dview_rs::{AdditionalRender, ContactRender, Molecule};
+use nalgebra::Point3;
+
+fn mol(atoms: &[(&str, f32)]) -> Molecule {
+    Molecule {
+        atoms: atoms
+            .iter()
+            .map(|&(element, x)| Atom {
+                position: Point3::new(x, 0.0, 0.0),
+                element: element.to_string(),
+                id: 0,
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_contact_classification() {
+    // Ligand atoms sit 10 Å apart so each only sees its own partner.
+    let ligand = mol(&[("O", 0.0), ("C", 10.0), ("C", 20.0), ("N", 30.0)]);
+    let protein = mol(&[
+        ("N", 2.9),
+        ("C", 14.0),
+        ("C", 21.5),
+        ("C", 33.6),
+        ("O", 50.0),
+    ]);
+    let contacts = contacts_between(&ligand, &protein, 4.0);
+
+    let found: Vec<_> = contacts
+        .iter()
+        .map(|c| (c.atom_a, c.atom_b, c.kind))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (0, 0, ContactKind::HBond),
+            (1, 1, ContactKind::Hydrophobic),
+            (2, 2, ContactKind::Clash),
+            (3, 3, ContactKind::Polar),
+        ]
+    );
+    assert!((contacts[0].distance - 2.9).abs() < 1e-4);
+
+    assert!(contacts_between(&ligand, &protein, 0.0).is_empty());
+    assert!(contacts_between(&ligand, &Molecule::default(), 4.0).is_empty());
+}
+
+#[test]
+fn test_contact_report_and_render() {
+    let a = mol(&[("O", 0.0)]);
+    let b = mol(&[("N", 3.0)]);
+    let contacts = contacts_between(&a, &b, 4.0);
+
+    let report = contact_report(&a, &b, &contacts);
+    let lines: Vec<_> = report.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1], "0\tO\t0\tN\t3.000\thbond");
+
+    let render = ContactRender::new(&a, &b, &contacts);
+    let mut scene = Scene::default();
+    render.update_scene(&mut scene, &a);
+    // 3 Å with 0.25 Å dashes and 0.15 Å gaps: 7 dashes on one shared mesh.
+    assert_eq!(scene.meshes.len(), 1);
+    assert_eq!(scene.entities.len(), 7);
+    assert!(scene
+        .entities
+        .iter()
+        .all(|e| e.color == ContactRender::color(ContactKind::HBond)));
+}
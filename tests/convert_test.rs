@@ -0,0 +1,57 @@
+use moleucle_3dview_rs::convert::{quat_to_la, quat_to_na, to_la, to_na, vec_to_la, vec_to_na};
+use nalgebra::{Point3, Unit, UnitQuaternion, Vector3};
+
+fn sample_rotations() -> Vec<UnitQuaternion<f32>> {
+    let mut rotations = Vec::new();
+    let axes = [
+        Vector3::x(),
+        Vector3::y(),
+        Vector3::z(),
+        Vector3::new(1.0, 1.0, 0.0),
+        Vector3::new(-0.3, 0.8, 0.5),
+    ];
+    for axis in axes {
+        for i in -4..=4 {
+            let angle = i as f32 * 0.7;
+            rotations.push(UnitQuaternion::from_axis_angle(
+                &Unit::new_normalize(axis),
+                angle,
+            ));
+        }
+    }
+    rotations
+}
+
+#[test]
+fn test_point_round_trip() {
+    let p = Point3::new(1.5, -2.25, 3.0);
+    assert_eq!(to_na(to_la(p)), p);
+
+    let v = Vector3::new(-0.5, 4.0, 1e-3);
+    assert_eq!(vec_to_na(vec_to_la(v)), v);
+}
+
+#[test]
+fn test_quaternion_round_trip() {
+    for q in sample_rotations() {
+        let back = quat_to_na(quat_to_la(q));
+        assert!(q.angle_to(&back) < 1e-5, "{:?} != {:?}", q, back);
+    }
+}
+
+#[test]
+fn test_quaternion_rotates_same_way() {
+    // A handedness or component-order mistake shows up as a different rotated vector.
+    let v = Vector3::new(0.3, -1.2, 2.0);
+    for q in sample_rotations() {
+        let expected = q * v;
+        let actual = vec_to_na(quat_to_la(q).rotate_vec(vec_to_la(v)));
+        assert!(
+            (expected - actual).norm() < 1e-4,
+            "{:?}: expected {:?}, got {:?}",
+            q,
+            expected,
+            actual
+        );
+    }
+}
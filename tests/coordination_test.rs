@@ -0,0 +1,152 @@
+mod common;
+
+use common::atom;
+use graphics::Scene;
+use moleucle_3dview_rs::coordination::{metal_atoms, COORDINATION_CUTOFF};
+use moleucle_3dview_rs::element;
+use moleucle_3dview_rs::viewer::ViewerEvent;
+use moleucle_3dview_rs::{
+    CoordinationGeometry, CoordinationRender, Molecule, MoleculeViewer, SelectedAtomRender,
+};
+use nalgebra::Vector3;
+
+/// A metal at the origin with donors at `distance` along `directions`, plus a
+/// carbon and a far oxygen that must not count.
+fn complex(metal: &str, donor: &str, distance: f32, directions: &[Vector3<f32>]) -> Molecule {
+    let mut atoms = vec![atom(metal, 0.0, 0.0, 0.0)];
+    for d in directions {
+        let p = d.normalize() * distance;
+        atoms.push(atom(donor, p.x, p.y, p.z));
+    }
+    atoms.push(atom("C", 0.0, 0.0, 1.5));
+    atoms.push(atom("O", 0.0, 0.0, -3.5));
+    Molecule {
+        atoms,
+        ..Default::default()
+    }
+}
+
+fn octahedron() -> Vec<Vector3<f32>> {
+    vec![
+        Vector3::x(),
+        -Vector3::x(),
+        Vector3::y(),
+        -Vector3::y(),
+        Vector3::z(),
+        -Vector3::z(),
+    ]
+}
+
+#[test]
+fn test_geometry_classification() {
+    let tetrahedron = [
+        Vector3::new(1.0, 1.0, 1.0),
+        Vector3::new(1.0, -1.0, -1.0),
+        Vector3::new(-1.0, 1.0, -1.0),
+        Vector3::new(-1.0, -1.0, 1.0),
+    ];
+    let info = complex("Zn", "S", 2.3, &tetrahedron)
+        .coordination_sphere(0, COORDINATION_CUTOFF)
+        .unwrap();
+    assert_eq!(info.coordination_number(), 4);
+    assert_eq!(info.geometry, CoordinationGeometry::Tetrahedral);
+    assert!((info.average_distance().unwrap() - 2.3).abs() < 1e-4);
+    assert!(info.geometry_rms.unwrap() < 1e-2);
+
+    let square = &octahedron()[..4];
+    let info = complex("Pt", "N", 2.0, square)
+        .coordination_sphere(0, COORDINATION_CUTOFF)
+        .unwrap();
+    assert_eq!(info.geometry, CoordinationGeometry::SquarePlanar);
+
+    // Six oxygens, one pulled 10° off its axis; the carbon at 1.5 Å is no donor.
+    let mut sites = octahedron();
+    sites[4] = Vector3::new(10f32.to_radians().sin(), 0.0, 10f32.to_radians().cos());
+    let info = complex("Mg", "O", 2.1, &sites)
+        .coordination_sphere(0, COORDINATION_CUTOFF)
+        .unwrap();
+    assert_eq!(info.coordination_number(), 6);
+    assert_eq!(info.geometry, CoordinationGeometry::Octahedral);
+    assert_eq!(info.to_string(), "Mg 0: CN 6, mean 2.10 Å, octahedral");
+
+    // Five sites of an octahedron: a square pyramid.
+    let info = complex("Cu", "N", 2.0, &octahedron()[..5])
+        .coordination_sphere(0, COORDINATION_CUTOFF)
+        .unwrap();
+    assert_eq!(info.geometry, CoordinationGeometry::Octahedral);
+}
+
+#[test]
+fn test_unrecognized_geometries_are_unknown() {
+    // Too few ligands to classify.
+    let info = complex("Na", "O", 2.4, &[Vector3::x(), Vector3::y()])
+        .coordination_sphere(0, COORDINATION_CUTOFF)
+        .unwrap();
+    assert_eq!(info.geometry, CoordinationGeometry::Unknown);
+    assert_eq!(info.geometry_rms, None);
+
+    // Four ligands crowded into one hemisphere match nothing.
+    let crowded = [
+        Vector3::new(1.0, 0.0, 2.0),
+        Vector3::new(-1.0, 0.0, 2.0),
+        Vector3::new(0.0, 1.0, 2.0),
+        Vector3::new(0.0, -1.0, 2.0),
+    ];
+    let info = complex("Ca", "O", 2.4, &crowded)
+        .coordination_sphere(0, COORDINATION_CUTOFF)
+        .unwrap();
+    assert_eq!(info.geometry, CoordinationGeometry::Unknown);
+    assert!(info.geometry_rms.unwrap() > 15.0);
+
+    assert!(Molecule::default().coordination_sphere(0, 3.0).is_none());
+}
+
+#[test]
+fn test_metal_detection() {
+    for metal in ["Zn", "FE", "na", "Mg", "Pt"] {
+        assert!(element::is_metal(metal), "{metal}");
+    }
+    for other in ["C", "O", "Si", "As", "Xx", ""] {
+        assert!(!element::is_metal(other), "{other}");
+    }
+    let mol = complex("Zn", "S", 2.3, &octahedron()[..4]);
+    assert_eq!(metal_atoms(&mol), [0]);
+}
+
+#[test]
+fn test_clicking_metal_fills_overlay() {
+    let mut viewer = MoleculeViewer::<CoordinationRender>::new();
+    viewer.set_molecule(complex("Zn", "O", 2.0, &octahedron()[..4]));
+    viewer.additional_render = Some(Box::new(CoordinationRender::new()));
+    let info = |viewer: &MoleculeViewer<CoordinationRender>| {
+        viewer.additional_render.as_ref().unwrap().info.clone()
+    };
+
+    // Off by default.
+    assert!(!viewer.handle_pick(&ViewerEvent::AtomClicked(0)));
+    assert!(info(&viewer).is_none());
+
+    viewer.settings.coordination_on_click = true;
+    // Non-metals and hovering leave it alone; hovering only highlights.
+    assert!(!viewer.handle_pick(&ViewerEvent::AtomClicked(1)));
+    viewer.handle_pick(&ViewerEvent::AtomHovered(0));
+    assert!(info(&viewer).is_none());
+    assert!(viewer.handle_pick(&ViewerEvent::AtomClicked(0)));
+    assert_eq!(info(&viewer).unwrap().coordination_number(), 4);
+
+    // One thin bond per ligand on top of the molecule.
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    let with_overlay = scene.entities.len();
+    assert!(viewer.handle_pick(&ViewerEvent::NothingClicked));
+    assert!(info(&viewer).is_none());
+    viewer.update_scene(&mut scene);
+    assert_eq!(with_overlay - scene.entities.len(), 4);
+
+    // Overlays without a coordination sphere ignore clicks.
+    let mut other = MoleculeViewer::<SelectedAtomRender>::new();
+    other.set_molecule(complex("Zn", "O", 2.0, &octahedron()[..4]));
+    other.additional_render = Some(Box::new(SelectedAtomRender::new()));
+    other.settings.coordination_on_click = true;
+    assert!(!other.handle_pick(&ViewerEvent::AtomClicked(0)));
+}
@@ -0,0 +1,101 @@
+use moleucle_3dview_rs::{DisplayList, Molecule, MoleculeDisplayState, ParseOptions};
+use nalgebra::Point3;
+
+const REJECT: ParseOptions = ParseOptions {
+    decimal_comma: false,
+};
+
+const WATER_XYZ: &str = "3
+water, written with decimal commas
+O   0,000  0,000  0,1173
+H   0,000  0,7572 -0,4692
+H   0,000 -0,7572 -0,4692
+";
+
+const WATER_MOL2: &str = "@<TRIPOS>MOLECULE
+water
+ 3 2 1 0 0
+SMALL
+USER_CHARGES
+
+@<TRIPOS>ATOM
+      1 O1   0,0000  0,0000  0,1173 O.3     1 HOH1  -0,8340
+      2 H1   0,0000  0,7572 -0,4692 H       1 HOH1   0,4170
+      3 H2   0,0000 -0,7572 -0,4692 H       1 HOH1   0,4170
+@<TRIPOS>BOND
+     1     1     2    1
+     2     1     3    1
+";
+
+// Comma separators in the fixed 8-character coordinate columns.
+const ION_GRO: &str = "ion in a box
+    1
+    1CL      CL    1   1,000   0,500   0,250
+   2,00000   2,00000   3,00000
+";
+
+fn close(a: Point3<f32>, b: Point3<f32>) -> bool {
+    (a - b).norm() < 1e-5
+}
+
+#[test]
+fn test_decimal_commas_are_read_with_a_warning() {
+    let xyz = Molecule::from_xyz_str(WATER_XYZ).unwrap();
+    assert!(close(
+        xyz.atoms[1].position,
+        Point3::new(0.0, 0.7572, -0.4692)
+    ));
+    assert_eq!(xyz.warnings.len(), 1);
+    assert!(xyz.warnings[0].contains("decimal comma"));
+
+    let mol2 = Molecule::from_mol2_str(WATER_MOL2).unwrap();
+    assert_eq!(mol2.atoms.len(), 3);
+    assert_eq!(mol2.charges, Some(vec![-0.834, 0.417, 0.417]));
+    for (a, b) in mol2.atoms.iter().zip(&xyz.atoms) {
+        assert!(close(a.position, b.position));
+    }
+    assert!(mol2.warnings[0].contains("12 numbers"));
+
+    let gro = Molecule::from_gro_str(ION_GRO).unwrap();
+    assert!(close(gro.atoms[0].position, Point3::new(10.0, 5.0, 2.5)));
+    assert!((gro.lattice.unwrap().volume() - 12_000.0).abs() < 0.1);
+
+    // Dot-separated files stay warning-free.
+    let dotted = WATER_XYZ
+        .replace(',', ".")
+        .replace("water. written", "water");
+    assert!(Molecule::from_xyz_str(&dotted).unwrap().warnings.is_empty());
+}
+
+#[test]
+fn test_decimal_commas_can_be_refused() {
+    assert!(Molecule::from_xyz_str_with(WATER_XYZ, REJECT).is_err());
+    assert!(Molecule::from_gro_str_with(ION_GRO, REJECT).is_err());
+    // Malformed mol2 records are skipped.
+    let mol2 = Molecule::from_mol2_str_with(WATER_MOL2, REJECT).unwrap();
+    assert!(mol2.atoms.is_empty());
+    // Thousands separators and lists are not decimals.
+    let ambiguous = WATER_XYZ.replace("0,7572", "0,757,2");
+    assert!(Molecule::from_xyz_str(&ambiguous).is_err());
+    let mixed = WATER_XYZ.replace("0,7572", "0.757,2");
+    assert!(Molecule::from_xyz_str(&mixed).is_err());
+}
+
+#[test]
+fn test_exports_use_decimal_points() {
+    let mol = Molecule::from_xyz_str(WATER_XYZ).unwrap();
+    let list = DisplayList::from_molecule(&mol, &MoleculeDisplayState::default());
+    let path = std::env::temp_dir().join(format!("decimal_comma_{}.json", std::process::id()));
+    list.to_json(&path).unwrap();
+    let json = std::fs::read_to_string(&path).unwrap();
+    let back = DisplayList::from_json(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(back, list);
+    // Commas only ever separate values, so a digit never follows one.
+    let bytes = json.as_bytes();
+    assert!(bytes
+        .windows(2)
+        .all(|w| !(w[0] == b',' && w[1].is_ascii_digit())));
+    assert!(json.contains("0.7572"));
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use common::{atom, bond};
+use graphics::Scene;
+use moleucle_3dview_rs::dipole::{dipole_report, DEBYE_PER_E_ANGSTROM};
+use moleucle_3dview_rs::molecule::Atom;
+use moleucle_3dview_rs::{DipoleGroups, DipoleRender, Molecule, MoleculeViewer};
+use nalgebra::{Point3, Vector3};
+
+/// Water with its oxygen at `origin`, hydrogens in the xy plane and the H-O-H
+/// bisector along +y, with point charges `q_h` on each hydrogen.
+fn water(origin: Point3<f32>, q_h: f32) -> (Vec<Atom>, Vec<f32>) {
+    let (r, half_angle) = (0.9572f32, (104.52f32 / 2.0).to_radians());
+    let h = |sign: f32| {
+        let p = origin + Vector3::new(sign * r * half_angle.sin(), r * half_angle.cos(), 0.0);
+        atom("H", p.x, p.y, p.z)
+    };
+    (
+        vec![atom("O", origin.x, origin.y, origin.z), h(1.0), h(-1.0)],
+        vec![-2.0 * q_h, q_h, q_h],
+    )
+}
+
+/// Two waters, 5 Å apart, in residues 1 and 2 and bonded internally.
+fn two_waters(q_h: f32) -> Molecule {
+    let (mut atoms, mut charges) = water(Point3::origin(), q_h);
+    let (a2, c2) = water(Point3::new(5.0, 0.0, 0.0), q_h);
+    atoms.extend(a2);
+    charges.extend(c2);
+    Molecule {
+        atoms,
+        bonds: vec![bond(0, 1), bond(0, 2), bond(3, 4), bond(3, 5)],
+        charges: Some(charges),
+        residues: Some(vec![1, 1, 1, 2, 2, 2]),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_water_dipole() {
+    // TIP3P charges give the model's 2.35 D; charges fitted to the gas-phase
+    // moment give the experimental 1.85 D. Both point along the bisector, from
+    // the oxygen towards the hydrogens.
+    for (q_h, expected) in [(0.417, 2.35), (0.3288, 1.85)] {
+        let (atoms, charges) = water(Point3::new(1.0, 2.0, 3.0), q_h);
+        let mol = Molecule {
+            atoms,
+            charges: Some(charges),
+            ..Default::default()
+        };
+        let moment = mol.dipole_moment(&[0, 1, 2]).unwrap();
+        assert!((moment.norm() - expected).abs() < 0.01, "{moment}");
+        assert!(moment.normalize().dot(&Vector3::y()) > 1.0 - 1e-6);
+    }
+}
+
+#[test]
+fn test_dipole_needs_charges() {
+    let mut mol = two_waters(0.417);
+    assert!(mol.dipole_moment(&[]).is_none());
+    assert!(mol.dipole_moment(&[0, 9]).is_none());
+    mol.charges = None;
+    assert!(mol.dipole_moment(&[0, 1, 2]).is_none());
+    assert!(mol.dipoles(DipoleGroups::Molecule).is_empty());
+}
+
+#[test]
+fn test_charged_group_uses_center_of_charge() {
+    // A lone +1 charge has no dipole about itself, wherever it is.
+    let mol = Molecule {
+        atoms: vec![atom("NA", 3.0, -2.0, 1.0)],
+        charges: Some(vec![1.0]),
+        ..Default::default()
+    };
+    assert_eq!(mol.dipole_moment(&[0]), Some(Vector3::zeros()));
+
+    // +1 and -1 one Å apart: 1 e·Å.
+    let mol = Molecule {
+        atoms: vec![atom("C", 0.0, 0.0, 0.0), atom("C", 1.0, 0.0, 0.0)],
+        charges: Some(vec![-1.0, 1.0]),
+        ..Default::default()
+    };
+    let moment = mol.dipole_moment(&[0, 1]).unwrap();
+    assert!((moment - Vector3::x() * DEBYE_PER_E_ANGSTROM).norm() < 1e-4);
+}
+
+#[test]
+fn test_dipole_groups() {
+    let mut mol = two_waters(0.417);
+    let whole = mol.dipoles(DipoleGroups::Molecule);
+    assert_eq!(whole.len(), 1);
+    // Parallel waters add up.
+    assert!((whole[0].moment.norm() - 2.0 * 2.347).abs() < 0.02);
+
+    let residues = mol.dipoles(DipoleGroups::Residue);
+    let fragments = mol.dipoles(DipoleGroups::Fragment);
+    assert_eq!(residues, fragments);
+    assert_eq!(residues.len(), 2);
+    assert_eq!(residues[1].atoms, [3, 4, 5]);
+    assert!((residues[1].centroid.x - 5.0).abs() < 1e-5);
+
+    mol.residues = None;
+    assert!(mol.dipoles(DipoleGroups::Residue).is_empty());
+    mol.bonds.clear();
+    assert_eq!(mol.fragments().len(), 6);
+
+    let report = dipole_report(&fragments);
+    let lines: Vec<_> = report.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].ends_with("magnitude (D)"));
+    assert!(lines[1].starts_with("0\t3\t") && lines[1].ends_with("\t2.347"));
+}
+
+#[test]
+fn test_dipole_render_draws_arrows() {
+    let mol = two_waters(0.417);
+    let mut render = DipoleRender::new(mol.dipoles(DipoleGroups::Fragment));
+    let mut viewer = MoleculeViewer::<DipoleRender>::new();
+    viewer.set_molecule(mol);
+    let mut count = |render: &DipoleRender| {
+        viewer.additional_render = Some(Box::new(render.clone()));
+        viewer.dirty = true;
+        let mut scene = Scene::default();
+        viewer.update_scene(&mut scene);
+        scene.entities.len()
+    };
+    let with_arrows = count(&render);
+    render.dipoles.clear();
+    // A shaft and a head per dipole.
+    assert_eq!(with_arrows - count(&render), 4);
+}
@@ -0,0 +1,163 @@
+mod common;
+
+use common::atom;
+use graphics::Scene;
+use moleucle_3dview_rs::display_list::DISPLAY_LIST_VERSION;
+use moleucle_3dview_rs::molecule::Bond;
+use moleucle_3dview_rs::viewer::{ATOM_RADIUS, BOND_RADIUS};
+use moleucle_3dview_rs::{
+    DisplayList, Molecule, MoleculeDisplayState, MoleculeViewer, SelectedAtomRender, Slab,
+};
+use nalgebra::{Point3, Vector3};
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("{}_{name}", std::process::id()))
+}
+
+/// Display list of a tilted C=O bond plus a lone atom, with the selection overlay on.
+fn display_list() -> DisplayList {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(Molecule {
+        atoms: vec![
+            atom("C", 0.1, 0.2, 0.3),
+            atom("O", 1.1, 1.0, -0.4),
+            atom("N", -3.0, 0.0, 0.0),
+        ],
+        bonds: vec![Bond {
+            atom_a: 0,
+            atom_b: 1,
+            order: 2,
+        }],
+        ..Default::default()
+    });
+    let mut overlay = SelectedAtomRender::new();
+    overlay.add_atom(0);
+    viewer.additional_render = Some(Box::new(overlay));
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    DisplayList::from_scene(&viewer, &scene)
+}
+
+#[test]
+fn test_from_scene_gives_analytic_primitives() {
+    let list = display_list();
+    assert_eq!(list.version, DISPLAY_LIST_VERSION);
+    // The selection hull is an overlay and is left out.
+    assert_eq!(list.spheres.len(), 3);
+    assert_eq!(list.spheres[2].center, Point3::new(-3.0, 0.0, 0.0));
+    assert!(list.spheres.iter().all(|s| s.radius == ATOM_RADIUS));
+
+    // Every bond cylinder lies along the C=O axis, between the two atoms.
+    assert!(!list.cylinders.is_empty());
+    let (a, b) = (Point3::new(0.1, 0.2, 0.3), Point3::new(1.1, 1.0, -0.4));
+    let axis: Vector3<f32> = (b - a).normalize();
+    for cylinder in &list.cylinders {
+        assert!(cylinder.radius <= BOND_RADIUS);
+        let dir = (cylinder.end - cylinder.start).normalize();
+        assert!(dir.dot(&axis).abs() > 1.0 - 1e-4);
+        for p in [cylinder.start, cylinder.end] {
+            let t = (p - a).dot(&axis);
+            assert!((-1e-4..=(b - a).norm() + 1e-4).contains(&t));
+        }
+    }
+}
+
+#[test]
+fn test_from_molecule_matches_scene_export() {
+    let mol = Molecule {
+        atoms: vec![
+            atom("C", 0.0, 0.0, 0.0),
+            atom("O", 1.2, 0.0, 0.0),
+            atom("H", -0.6, 0.9, 0.0),
+            atom("N", 0.0, 0.0, 5.0),
+        ],
+        bonds: vec![
+            Bond {
+                atom_a: 0,
+                atom_b: 1,
+                order: 2,
+            },
+            Bond {
+                atom_a: 0,
+                atom_b: 2,
+                order: 1,
+            },
+            Bond {
+                atom_a: 0,
+                atom_b: 3,
+                order: 1,
+            },
+        ],
+        ..Default::default()
+    };
+    let mut state = MoleculeDisplayState::default();
+    state.settings.hide_hydrogens = true;
+    state.settings.bond_stubs = Some(0.3);
+    state.settings.slab = Some(Slab::new(Point3::origin(), Vector3::z(), 2.0));
+
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(mol.clone());
+    viewer.render_style = state.render_style;
+    viewer.settings = state.settings.clone();
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+
+    let expected = DisplayList::from_scene(&viewer, &scene);
+    let list = DisplayList::from_molecule(&mol, &state);
+    // C and O, then the caps of the stubs towards the hidden H and the N
+    // beyond the slab.
+    assert_eq!(list.spheres.len(), 4);
+    assert_eq!(list.spheres, expected.spheres);
+    // Cylinders in the scene went through a quaternion; allow for rounding.
+    assert_eq!(list.cylinders.len(), expected.cylinders.len());
+    for (c, d) in list.cylinders.iter().zip(&expected.cylinders) {
+        assert!((c.start - d.start).norm() <= 1e-5 && (c.end - d.end).norm() <= 1e-5);
+        assert_eq!(
+            (c.radius, c.color, c.opacity),
+            (d.radius, d.color, d.opacity)
+        );
+    }
+}
+
+#[test]
+fn test_json_round_trip() {
+    let list = display_list();
+    let path = temp_path("display_list.json");
+    list.to_json(&path).unwrap();
+    let back = DisplayList::from_json(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(back.spheres.len(), list.spheres.len());
+    assert_eq!(back.cylinders.len(), list.cylinders.len());
+    let close = |a: f32, b: f32| (a - b).abs() <= 1e-5;
+    for (s, t) in list.spheres.iter().zip(&back.spheres) {
+        assert!((s.center - t.center).norm() <= 1e-5);
+        assert!(close(s.radius, t.radius) && close(s.opacity, t.opacity));
+        assert!(close(s.color.0, t.color.0) && close(s.color.2, t.color.2));
+    }
+    for (c, d) in list.cylinders.iter().zip(&back.cylinders) {
+        assert!((c.start - d.start).norm() <= 1e-5 && (c.end - d.end).norm() <= 1e-5);
+        assert!(close(c.radius, d.radius) && close(c.shininess, d.shininess));
+    }
+}
+
+#[test]
+fn test_from_json_rejects_bad_files() {
+    let path = temp_path("display_list_future.json");
+    std::fs::write(
+        &path,
+        format!(
+            r#"{{"version": {}, "spheres": [], "cylinders": []}}"#,
+            DISPLAY_LIST_VERSION + 1
+        ),
+    )
+    .unwrap();
+    assert!(DisplayList::from_json(&path).unwrap_err().contains("newer"));
+
+    std::fs::write(&path, r#"{"version": 1, "spheres": [{"radius": 1}]}"#).unwrap();
+    assert!(DisplayList::from_json(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(DisplayList::from_json(&temp_path("missing.json")).is_err());
+}
@@ -0,0 +1,240 @@
+use graphics::Scene;
+use lin_alg::f32::Vec3;
+use moleucle_3dview_rs::contacts::contacts_between;
+use moleucle_3dview_rs::coordination::metal_atoms;
+use moleucle_3dview_rs::molecule::{Atom, MIN_FRAME_EXTENT, UNKNOWN_ATOM_MASS};
+use moleucle_3dview_rs::snapshot::scene_snapshot;
+use moleucle_3dview_rs::viewer::ViewerEvent;
+use moleucle_3dview_rs::{
+    CompositionOptions, DipoleGroups, DisplayList, EditAction, GroundDiscRender, Lattice, Molecule,
+    MoleculeDisplayState, MoleculeViewer, OrbitalCamera, RenderStyle, SelectedAtomRender,
+};
+use nalgebra::{Point2, Point3, UnitQuaternion, Vector3};
+use std::f32::consts::FRAC_PI_2;
+
+fn single() -> Molecule {
+    Molecule {
+        atoms: vec![Atom {
+            position: Point3::new(1.0, 2.0, 3.0),
+            element: "C".to_string(),
+            id: 1,
+        }],
+        ..Default::default()
+    }
+}
+
+fn viewer(mol: Molecule) -> MoleculeViewer<SelectedAtomRender> {
+    let mut viewer = MoleculeViewer::new();
+    viewer.set_molecule(mol);
+    viewer.additional_render = Some(Box::new(SelectedAtomRender::new()));
+    viewer
+}
+
+#[test]
+fn test_molecule_analysis() {
+    let empty = Molecule::default();
+    assert!(empty.fragments().is_empty());
+    assert!(empty.find_rings().is_empty() && empty.analyze_rings().is_empty());
+    assert!(empty.dipoles(DipoleGroups::Fragment).is_empty());
+    assert!(empty.propose_bond_orders().is_empty());
+    assert!(empty.coordination_sphere(0, 3.0).is_none());
+    assert_eq!(empty.topology_hash(), Molecule::default().topology_hash());
+
+    let one = single();
+    assert_eq!(one.fragments(), [vec![0]]);
+    assert_eq!(one.adjacency(), [Vec::<usize>::new()]);
+    assert!(one.analyze_rings().is_empty());
+    assert!(one.dipole_moment(&[0]).is_none());
+    let sphere = one.coordination_sphere(0, 3.0).unwrap();
+    assert!(sphere.ligands.is_empty() && sphere.geometry_rms.is_none());
+    assert!(metal_atoms(&one).is_empty());
+    assert_eq!(one.distance(0, 0, None), Some(0.0));
+    assert!(contacts_between(&empty, &one, 4.0).is_empty());
+    assert_eq!(contacts_between(&one, &one, 4.0).len(), 1);
+
+    let mut removed = single();
+    removed.remove_atoms(&[0]);
+    assert!(removed.atoms.is_empty());
+}
+
+#[test]
+fn test_frame_bounds() {
+    let half = Vector3::repeat(MIN_FRAME_EXTENT / 2.0);
+    // Nothing to frame: a default box around the origin.
+    let (min, max) = Molecule::default().frame_bounds();
+    assert_eq!((min, max), (Point3::from(-half), Point3::from(half)));
+    // One atom gets the same box around itself.
+    let (min, max) = single().frame_bounds();
+    assert_eq!(nalgebra::center(&min, &max), Point3::new(1.0, 2.0, 3.0));
+    assert!(((max - min) - half * 2.0).norm() < 1e-6);
+
+    // Larger extents are kept; only the flat axis is padded.
+    let mut flat = single();
+    flat.atoms.push(Atom {
+        position: Point3::new(11.0, 2.0, 3.0),
+        ..flat.atoms[0].clone()
+    });
+    let (min, max) = flat.frame_bounds();
+    assert_eq!(
+        max - min,
+        Vector3::new(10.0, MIN_FRAME_EXTENT, MIN_FRAME_EXTENT)
+    );
+}
+
+#[test]
+fn test_centroid_center_of_mass_and_bounding_box() {
+    let empty = Molecule::default();
+    assert_eq!(empty.centroid(), Point3::origin());
+    assert_eq!(empty.center_of_mass(None), Point3::origin());
+    assert_eq!(empty.bounding_box(), (Point3::origin(), Point3::origin()));
+    let com = single().center_of_mass(None);
+    assert!((com - Point3::new(1.0, 2.0, 3.0)).norm() < 1e-5, "{com}");
+
+    // Carbon monoxide along x, and an atom of no known element.
+    let atom = |element: &str, x: f32| Atom {
+        position: Point3::new(x, 0.0, 0.0),
+        element: element.to_string(),
+        id: 0,
+    };
+    let mut co = Molecule {
+        atoms: vec![atom("C", 0.0), atom("O", 1.128)],
+        ..Default::default()
+    };
+    assert!((co.centroid().x - 0.564).abs() < 1e-6);
+    let com = co.center_of_mass(None).x;
+    assert!((com - 1.128 * 15.999 / 28.010).abs() < 1e-4, "{com}");
+    assert_eq!(co.center_of_mass(Some(&[1.0, 1.0])), co.centroid());
+    // Missing and zero masses.
+    assert!((co.center_of_mass(Some(&[0.0])).x - 1.128).abs() < 1e-6);
+    assert_eq!(co.center_of_mass(Some(&[0.0, 0.0])), co.centroid());
+
+    co.atoms.push(atom("Qq", -2.0));
+    // The unknown atom weighs `UNKNOWN_ATOM_MASS`.
+    let expected = (1.128 * 15.999 - 2.0 * UNKNOWN_ATOM_MASS) / (28.010 + UNKNOWN_ATOM_MASS);
+    let com = co.center_of_mass(Some(&[12.011, 15.999])).x;
+    assert!((com - expected).abs() < 1e-4, "{com}");
+    let (min, max) = co.bounding_box();
+    assert_eq!((min.x, max.x), (-2.0, 1.128));
+    assert_eq!((min.y, max.z), (0.0, 0.0));
+}
+
+#[test]
+fn test_translate_rotate_and_scale() {
+    let mol = Molecule::from_mol2(std::path::Path::new("Benzene.mol2")).unwrap();
+    let close = |a: &Molecule, b: &Molecule| {
+        a.atoms
+            .iter()
+            .zip(&b.atoms)
+            .all(|(a, b)| (a.position - b.position).norm() < 1e-5)
+    };
+
+    let mut moved = mol.translated(Vector3::x());
+    assert!((moved.atoms[0].position.x - mol.atoms[0].position.x - 1.0).abs() < 1e-5);
+    moved.translate(-Vector3::x());
+    assert!(close(&moved, &mol));
+
+    // A quarter turn about the z axis through atom 0.
+    let pivot = mol.atoms[0].position;
+    let quarter = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), FRAC_PI_2);
+    let turned = mol.rotated(quarter, pivot);
+    assert!((turned.atoms[0].position - pivot).norm() < 1e-5);
+    let (before, after) = (
+        mol.atoms[1].position - pivot,
+        turned.atoms[1].position - pivot,
+    );
+    assert!((after - Vector3::new(-before.y, before.x, before.z)).norm() < 1e-4);
+    assert!(close(&turned.rotated(quarter.inverse(), pivot), &mol));
+
+    let doubled = mol.scaled(2.0);
+    assert!((doubled.centroid() - mol.centroid()).norm() < 1e-4);
+    let d = |m: &Molecule| m.distance(0, 1, None).unwrap();
+    assert!((d(&doubled) - 2.0 * d(&mol)).abs() < 1e-4);
+    let mut back = doubled.clone();
+    back.scale(0.5);
+    assert!(close(&back, &mol));
+
+    // The lattice keeps each atom's fractional position under a turn about
+    // the origin.
+    let mut cell = single();
+    cell.lattice = Lattice::new(
+        Vector3::new(4.0, 0.0, 0.0),
+        Vector3::new(1.0, 5.0, 0.0),
+        Vector3::z() * 6.0,
+    );
+    let fractional = cell.fractional_position(0).unwrap();
+    cell.rotate(quarter, Point3::origin());
+    assert!((cell.fractional_position(0).unwrap() - fractional).norm() < 1e-5);
+}
+
+#[test]
+fn test_viewer_on_empty_and_single_atom() {
+    for (mol, atoms) in [(Molecule::default(), 0), (single(), 1)] {
+        let mut viewer = viewer(mol.clone());
+        assert_eq!(viewer.auto_style(), Some(RenderStyle::BallAndStick));
+        let mut scene = Scene::default();
+        viewer.update_scene(&mut scene);
+        assert_eq!(scene.entities.len(), atoms);
+        assert_eq!(viewer.drawn_atoms().len(), atoms);
+        assert!(!scene_snapshot(&viewer, &scene).is_empty());
+        assert_eq!(
+            DisplayList::from_scene(&viewer, &scene).spheres.len(),
+            atoms
+        );
+        let list = DisplayList::from_molecule(&mol, &MoleculeDisplayState::default());
+        assert_eq!(list.spheres.len(), atoms);
+        assert!(viewer.refresh_overlays(&mut scene));
+
+        let bar = viewer.composition_bar(CompositionOptions::default());
+        assert_eq!(bar.len(), atoms);
+        assert!(bar.iter().all(|e| e.fraction == 1.0));
+
+        let hit = viewer.pick(Vec3::new(1.0, 2.0, 10.0), Vec3::new(0.0, 0.0, -1.0));
+        let expected = match atoms {
+            0 => ViewerEvent::NothingClicked,
+            _ => ViewerEvent::AtomClicked(0),
+        };
+        assert_eq!(hit, Some(expected));
+        assert_eq!(
+            viewer
+                .atoms_in_sphere(Point3::new(1.0, 2.0, 3.0), 1.0)
+                .len(),
+            atoms
+        );
+        let camera = OrbitalCamera::default();
+        let everything = (Point2::new(0.0, 0.0), Point2::new(800.0, 600.0));
+        viewer.atoms_in_frustum(&camera, everything, 800.0, 600.0);
+
+        // The axis needs atoms to span.
+        assert!(viewer.set_reference_axis(Vector3::z(), "z"));
+        viewer.update_scene(&mut scene);
+        assert_eq!(scene.entities.len(), atoms * 2);
+        viewer.clear_reference_axis();
+
+        viewer.apply_edit(EditAction::SelectAll).unwrap();
+        viewer.apply_edit(EditAction::DeleteSelected).unwrap();
+        assert_eq!(viewer.molecule.as_ref().unwrap().atoms.len(), 0);
+        assert_eq!(GroundDiscRender::new().disc(&mol).is_some(), atoms > 0);
+    }
+}
+
+#[test]
+fn test_update_scene_clears_stale_geometry() {
+    let mut viewer = viewer(single());
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    assert_eq!(scene.entities.len(), 1);
+
+    // An empty molecule draws nothing, and leaves nothing behind.
+    viewer.set_molecule(Molecule::default());
+    viewer.update_scene(&mut scene);
+    assert!(scene.entities.is_empty());
+    assert_eq!(viewer.classify_entity(0), None);
+
+    viewer.set_molecule(single());
+    viewer.update_scene(&mut scene);
+    viewer.molecule = None;
+    viewer.dirty = true;
+    viewer.update_scene(&mut scene);
+    assert!(scene.entities.is_empty());
+    assert_eq!(viewer.classify_entity(0), None);
+}
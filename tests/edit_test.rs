@@ -0,0 +1,427 @@
+mod common;
+
+use common::{atom, bond};
+use graphics::winit::keyboard::KeyCode;
+use graphics::Scene;
+use moleucle_3dview_rs::snapshot::scene_snapshot;
+use moleucle_3dview_rs::viewer::{EntityKind, ViewerEvent};
+use moleucle_3dview_rs::{
+    CameraController, EditAction, Molecule, MoleculeViewer, OrbitalCamera, PinnedMotion,
+    SelectedAtomRender, ViewerCommand,
+};
+use nalgebra::{Isometry3, Point3, Vector3};
+
+/// H-O-C-H in a line, with charges and two residues.
+fn chain() -> Molecule {
+    Molecule {
+        atoms: vec![
+            atom("H", 0.0, 0.0, 0.0),
+            atom("O", 1.0, 0.0, 0.0),
+            atom("C", 2.4, 0.0, 0.0),
+            atom("H", 3.4, 0.0, 0.0),
+        ],
+        bonds: vec![bond(0, 1), bond(1, 2), bond(2, 3)],
+        charges: Some(vec![0.4, -0.6, 0.1, 0.1]),
+        residues: Some(vec![1, 1, 2, 2]),
+        ..Default::default()
+    }
+}
+
+fn viewer() -> MoleculeViewer<SelectedAtomRender> {
+    let mut viewer = MoleculeViewer::new();
+    viewer.set_molecule(chain());
+    viewer.additional_render = Some(Box::new(SelectedAtomRender::new()));
+    viewer
+}
+
+/// Events other than `SelectionChanged`.
+fn edit_events(viewer: &mut MoleculeViewer<SelectedAtomRender>) -> Vec<ViewerEvent> {
+    let mut events = viewer.take_events();
+    events.retain(|event| !matches!(event, ViewerEvent::SelectionChanged(_)));
+    events
+}
+
+fn selected(viewer: &MoleculeViewer<SelectedAtomRender>) -> Vec<usize> {
+    let overlay = viewer.additional_render.as_ref().unwrap();
+    overlay.selection.atoms().to_vec()
+}
+
+#[test]
+fn test_remove_atoms_renumbers() {
+    let mut mol = chain();
+    let new_index = mol.remove_atoms(&[1, 7]);
+    assert_eq!(new_index, [Some(0), None, Some(1), Some(2)]);
+    assert_eq!(mol.atoms.len(), 3);
+    assert_eq!(mol.atoms[1].element, "C");
+    // Only the C-H bond survives, renumbered.
+    assert_eq!(mol.bonds.len(), 1);
+    assert_eq!((mol.bonds[0].atom_a, mol.bonds[0].atom_b), (1, 2));
+    assert_eq!(mol.charges.as_deref(), Some(&[0.4, 0.1, 0.1][..]));
+    assert_eq!(mol.residues.as_deref(), Some(&[1, 2, 2][..]));
+}
+
+#[test]
+fn test_shortcuts_request_edits() {
+    let viewer = viewer();
+    let mut controller = CameraController::<OrbitalCamera>::new();
+    let press = |controller: &mut CameraController<OrbitalCamera>, key| {
+        let (event, _) = controller.key_input(key, true, &viewer);
+        controller.key_input(key, false, &viewer);
+        event
+    };
+    let edit = |action| Some(ViewerEvent::EditRequested(action));
+
+    assert_eq!(
+        press(&mut controller, KeyCode::Delete),
+        edit(EditAction::DeleteSelected)
+    );
+    assert_eq!(
+        press(&mut controller, KeyCode::Backspace),
+        edit(EditAction::DeleteSelected)
+    );
+    assert_eq!(
+        press(&mut controller, KeyCode::Escape),
+        edit(EditAction::ClearSelection)
+    );
+    assert_eq!(
+        press(&mut controller, KeyCode::KeyH),
+        edit(EditAction::HideSelected)
+    );
+    assert_eq!(
+        press(&mut controller, KeyCode::KeyI),
+        edit(EditAction::InvertSelection)
+    );
+    assert_eq!(press(&mut controller, KeyCode::KeyA), None);
+
+    controller.key_input(KeyCode::ControlLeft, true, &viewer);
+    assert_eq!(
+        press(&mut controller, KeyCode::KeyA),
+        edit(EditAction::SelectAll)
+    );
+    controller.key_input(KeyCode::ControlLeft, false, &viewer);
+    controller.key_input(KeyCode::ShiftRight, true, &viewer);
+    assert_eq!(
+        press(&mut controller, KeyCode::KeyH),
+        edit(EditAction::ShowAll)
+    );
+
+    // A focused text field swallows shortcuts, but modifiers keep tracking.
+    controller.set_keyboard_captured(true);
+    assert_eq!(press(&mut controller, KeyCode::Delete), None);
+    controller.key_input(KeyCode::ShiftRight, false, &viewer);
+    controller.set_keyboard_captured(false);
+    assert_eq!(
+        press(&mut controller, KeyCode::KeyH),
+        edit(EditAction::HideSelected)
+    );
+}
+
+#[test]
+fn test_selection_edits_skip_hidden_atoms() {
+    let mut viewer = viewer();
+    viewer.settings.hide_hydrogens = true;
+    viewer.apply_edit(EditAction::SelectAll).unwrap();
+    assert_eq!(selected(&viewer), [1, 2]);
+    viewer.apply_edit(EditAction::ClearSelection).unwrap();
+    assert!(selected(&viewer).is_empty());
+
+    viewer.settings.hide_hydrogens = false;
+    viewer
+        .apply_command(ViewerCommand::Select(vec![0, 2]))
+        .unwrap();
+    viewer.apply_edit(EditAction::InvertSelection).unwrap();
+    assert_eq!(selected(&viewer), [1, 3]);
+
+    viewer.apply_edit(EditAction::HideSelected).unwrap();
+    assert_eq!(viewer.drawn_atoms(), [0, 2]);
+    viewer.apply_edit(EditAction::InvertSelection).unwrap();
+    assert_eq!(selected(&viewer), [0, 2]);
+    viewer
+        .apply_command(ViewerCommand::Edit(EditAction::ShowAll))
+        .unwrap();
+    assert_eq!(viewer.drawn_atoms(), [0, 1, 2, 3]);
+
+    // Without an overlay the viewer still keeps the selection.
+    let mut plain = MoleculeViewer::<SelectedAtomRender>::new();
+    plain.set_molecule(chain());
+    assert!(plain.apply_edit(EditAction::SelectAll).is_ok());
+    assert_eq!(plain.selected_atoms(), [0, 1, 2, 3]);
+    assert!(plain.apply_edit(EditAction::ShowAll).is_ok());
+}
+
+#[test]
+fn test_delete_selected_and_undo() {
+    let mut viewer = viewer();
+    viewer.settings.color_overrides.insert(2, (1.0, 0.0, 0.0));
+    viewer.settings.hidden_atoms.insert(3);
+    viewer
+        .apply_command(ViewerCommand::Select(vec![1]))
+        .unwrap();
+    viewer.take_events();
+
+    viewer.apply_edit(EditAction::DeleteSelected).unwrap();
+    let mol = viewer.molecule.as_ref().unwrap();
+    assert_eq!(mol.atoms.len(), 3);
+    assert!(mol.bonds.len() == 1 && mol.bonds[0].atom_a == 1);
+    assert_eq!(
+        viewer.take_events(),
+        [
+            ViewerEvent::AtomsDeleted(vec![1]),
+            ViewerEvent::SelectionChanged(Vec::new())
+        ]
+    );
+    assert!(selected(&viewer).is_empty());
+    assert!(viewer.settings.color_overrides.contains_key(&1));
+    assert_eq!(
+        viewer.settings.hidden_atoms.iter().collect::<Vec<_>>(),
+        [&2]
+    );
+
+    assert!(viewer.can_undo());
+    assert!(viewer.undo());
+    let mol = viewer.molecule.as_ref().unwrap();
+    assert_eq!((mol.atoms.len(), mol.bonds.len()), (4, 3));
+    assert_eq!(selected(&viewer), [1]);
+    assert!(viewer.settings.color_overrides.contains_key(&2));
+    assert!(viewer.settings.hidden_atoms.contains(&3));
+    assert!(!viewer.undo());
+
+    assert!(viewer.delete_atoms(&[4]).is_err());
+    viewer.delete_atoms(&[]).unwrap();
+    assert!(!viewer.can_undo());
+    viewer.delete_atoms(&[0]).unwrap();
+    viewer.set_molecule(chain());
+    assert!(!viewer.can_undo());
+}
+
+#[test]
+fn test_add_bond_guards() {
+    let mut mol = chain();
+    assert_eq!(mol.find_bond(2, 1), Some(1));
+    assert!(mol.add_bond(1, 1, 1).is_err());
+    assert!(mol.add_bond(2, 1, 1).is_err());
+    assert!(mol.add_bond(0, 4, 1).is_err());
+    assert_eq!(mol.bonds.len(), 3);
+    assert_eq!(mol.add_bond(0, 3, 2), Ok(3));
+    assert_eq!(mol.find_bond(3, 0), Some(3));
+}
+
+#[test]
+fn test_bond_mode_clicks() {
+    let mut viewer = viewer();
+    viewer.take_events();
+    viewer.delete_bond(1).unwrap();
+    assert_eq!(viewer.take_events(), [ViewerEvent::BondDeleted(1)]);
+    viewer.set_bond_mode(true);
+
+    // The first atom is pending and shown selected; the second bonds to it.
+    assert!(viewer.handle_pick(&ViewerEvent::AtomClicked(1)));
+    assert_eq!(viewer.pending_bond(), Some(1));
+    assert_eq!(selected(&viewer), [1]);
+    assert!(viewer.handle_pick(&ViewerEvent::AtomClicked(2)));
+    assert_eq!(viewer.pending_bond(), None);
+    assert!(selected(&viewer).is_empty());
+    assert_eq!(edit_events(&mut viewer), [ViewerEvent::BondCreated(2)]);
+    let mol = viewer.molecule.as_ref().unwrap();
+    assert_eq!(mol.find_bond(1, 2), Some(2));
+    assert_eq!(mol.bonds[2].order, 1);
+
+    // Self-bonds keep the first atom pending; duplicates are refused.
+    viewer.handle_pick(&ViewerEvent::AtomClicked(0));
+    viewer.handle_pick(&ViewerEvent::AtomClicked(0));
+    assert_eq!(viewer.pending_bond(), Some(0));
+    viewer.handle_pick(&ViewerEvent::AtomClicked(1));
+    let events = edit_events(&mut viewer);
+    assert_eq!(events.len(), 2);
+    assert!(events
+        .iter()
+        .all(|e| matches!(e, ViewerEvent::CommandFailed(_))));
+    assert_eq!(viewer.molecule.as_ref().unwrap().bonds.len(), 3);
+
+    // Escape cancels a pending atom instead of clearing the selection.
+    let mut controller = CameraController::<OrbitalCamera>::new();
+    viewer.handle_pick(&ViewerEvent::AtomClicked(3));
+    let (event, _) = controller.key_input(KeyCode::Escape, true, &viewer);
+    assert_eq!(
+        event,
+        Some(ViewerEvent::EditRequested(EditAction::CancelBond))
+    );
+    viewer.apply_edit(EditAction::CancelBond).unwrap();
+    assert_eq!(viewer.pending_bond(), None);
+    assert!(selected(&viewer).is_empty());
+
+    // Clicking a bond deletes it; every edit undoes.
+    assert!(viewer.handle_pick(&ViewerEvent::BondClicked(0)));
+    assert_eq!(edit_events(&mut viewer), [ViewerEvent::BondDeleted(0)]);
+    assert_eq!(viewer.molecule.as_ref().unwrap().bonds.len(), 2);
+    assert!(viewer.undo() && viewer.undo() && viewer.undo());
+    let mol = viewer.molecule.as_ref().unwrap();
+    assert_eq!(mol.bonds.len(), 3);
+    assert_eq!(mol.find_bond(1, 2), Some(1));
+    assert!(!viewer.undo());
+
+    assert!(!viewer.handle_pick(&ViewerEvent::NothingClicked));
+    viewer.set_bond_mode(false);
+    assert!(!viewer.handle_pick(&ViewerEvent::AtomClicked(0)));
+}
+
+#[test]
+fn test_bond_shells() {
+    let mol = chain();
+    assert_eq!(mol.bond_shells(0, 2), [(0, 0), (1, 1), (2, 2)]);
+    assert_eq!(mol.bond_shells(2, 1), [(2, 0), (1, 1), (3, 1)]);
+    assert_eq!(mol.bond_shells(3, 0), [(3, 0)]);
+    assert!(mol.bond_shells(4, 3).is_empty());
+}
+
+#[test]
+fn test_atom_drag_falloff_and_undo() {
+    let mut viewer = viewer();
+    viewer.take_events();
+    viewer.settings.drag_falloff_shells = 2;
+    let start: Vec<_> = chain().atoms.iter().map(|a| a.position).collect();
+    let target = Point3::new(0.0, 4.0, 0.0);
+    let drag = ViewerEvent::AtomDragged {
+        atom: 0,
+        target,
+        rigid: false,
+    };
+    assert!(viewer.handle_pick(&drag));
+    assert_eq!(viewer.dragged_atom(), Some(0));
+    let moved = |viewer: &MoleculeViewer<SelectedAtomRender>| -> Vec<Vector3<f32>> {
+        let mol = viewer.molecule.as_ref().unwrap();
+        mol.atoms
+            .iter()
+            .zip(&start)
+            .map(|(a, s)| a.position - s)
+            .collect()
+    };
+    let up = Vector3::y();
+    assert_eq!(moved(&viewer), [up * 4.0, up * 2.0, up, Vector3::zeros()]);
+
+    assert!(viewer.handle_pick(&ViewerEvent::AtomDragEnded));
+    assert_eq!(viewer.dragged_atom(), None);
+    assert_eq!(
+        viewer.take_events(),
+        [ViewerEvent::AtomsMoved(vec![
+            (0, up * 4.0),
+            (1, up * 2.0),
+            (2, up)
+        ])]
+    );
+    // The whole drag is one undo step.
+    assert!(viewer.undo());
+    assert!(moved(&viewer).iter().all(|d| *d == Vector3::zeros()));
+    assert!(!viewer.can_undo());
+
+    // Rigid drags move the whole fragment; a drag that ends where it began
+    // leaves nothing to undo.
+    viewer.begin_atom_drag(2, true).unwrap();
+    viewer.drag_atom_to(Point3::new(2.4, 0.0, 1.0));
+    assert_eq!(moved(&viewer), [Vector3::z(); 4]);
+    viewer.drag_atom_to(Point3::new(2.4, 0.0, 0.0));
+    assert!(!viewer.end_atom_drag());
+    assert!(viewer.take_events().is_empty() && !viewer.can_undo());
+
+    assert!(viewer.begin_atom_drag(4, false).is_err());
+    assert!(!viewer.drag_atom_to(target));
+}
+
+#[test]
+fn test_refresh_drag_matches_rebuild() {
+    let mut viewer = viewer();
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    viewer.begin_atom_drag(1, false).unwrap();
+    viewer.drag_atom_to(Point3::new(1.0, 1.0, 0.5));
+    assert!(viewer.refresh_drag(&mut scene));
+    assert!(!viewer.dirty);
+    // Nothing new to show.
+    assert!(!viewer.refresh_drag(&mut scene));
+
+    let mut rebuilt = Scene::default();
+    viewer.dirty = true;
+    viewer.update_scene(&mut rebuilt);
+    assert_eq!(
+        scene_snapshot(&viewer, &scene),
+        scene_snapshot(&viewer, &rebuilt)
+    );
+
+    // A bond that needs other entities falls back to a rebuild.
+    viewer.settings.bond_stubs = Some(0.2);
+    viewer.settings.hidden_atoms.insert(0);
+    viewer.drag_atom_to(Point3::new(1.0, 2.0, 0.5));
+    assert!(!viewer.refresh_drag(&mut scene));
+    assert!(viewer.dirty);
+}
+
+#[test]
+fn test_pinned_atoms_stay_put() {
+    let mut viewer = viewer();
+    viewer.pin_atoms(&[1]).unwrap();
+    assert!(viewer.pin_atoms(&[4]).is_err());
+    viewer.take_events();
+    let start: Vec<_> = chain().atoms.iter().map(|a| a.position).collect();
+    let positions = |viewer: &MoleculeViewer<SelectedAtomRender>| -> Vec<Point3<f32>> {
+        let mol = viewer.molecule.as_ref().unwrap();
+        mol.atoms.iter().map(|a| a.position).collect()
+    };
+
+    let shift = Isometry3::translation(0.0, 0.0, 1.5);
+    viewer.transform_molecule(&shift);
+    let after = positions(&viewer);
+    assert_eq!(after[1], start[1]);
+    assert_eq!(after[0], start[0] + Vector3::z() * 1.5);
+
+    viewer.settings.pinned_motion = PinnedMotion::Refuse;
+    viewer.transform_atoms(&[0, 1], &shift);
+    assert_eq!(positions(&viewer), after);
+    assert!(matches!(
+        viewer.take_events()[..],
+        [ViewerEvent::CommandFailed(_)]
+    ));
+    assert!(viewer.begin_atom_drag(2, true).is_err());
+    assert!(viewer.begin_atom_drag(1, false).is_err());
+
+    // Falloff drags leave pinned atoms behind, even in refuse mode.
+    viewer.begin_atom_drag(0, false).unwrap();
+    viewer.drag_atom_to(Point3::new(0.0, 2.0, 0.0));
+    viewer.end_atom_drag();
+    assert_eq!(positions(&viewer)[1], start[1]);
+
+    viewer.apply_edit(EditAction::TogglePin(1)).unwrap();
+    assert!(!viewer.molecule.as_ref().unwrap().is_pinned(1));
+    viewer.transform_atoms(&[1], &shift);
+    assert_ne!(positions(&viewer)[1], start[1]);
+}
+
+#[test]
+fn test_pins_follow_edits_and_are_marked() {
+    let mut viewer = viewer();
+    viewer.pin_atoms(&[2, 3]).unwrap();
+    viewer.delete_atoms(&[0]).unwrap();
+    let pinned = |viewer: &MoleculeViewer<SelectedAtomRender>| -> Vec<usize> {
+        let mol = viewer.molecule.as_ref().unwrap();
+        mol.pinned.iter().copied().collect()
+    };
+    assert_eq!(pinned(&viewer), [1, 2]);
+
+    viewer.settings.hidden_atoms.insert(2);
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    let markers: Vec<_> = (0..scene.entities.len())
+        .filter_map(|i| match viewer.classify_entity(i) {
+            Some(EntityKind::PinMarker { index }) => Some((i, index)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(markers.len(), 1);
+    let (entity, index) = markers[0];
+    assert_eq!(index, 1);
+    assert!(scene.entities[entity].scale < 0.0);
+
+    assert!(viewer.undo());
+    assert_eq!(pinned(&viewer), [2, 3]);
+    viewer.unpin_all();
+    assert!(pinned(&viewer).is_empty());
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use common::{atom, bond};
+use moleucle_3dview_rs::viewer::{AddOptions, AddResult};
+use moleucle_3dview_rs::{Molecule, MoleculeViewer, SelectedAtomRender, ViewerCommand};
+use nalgebra::Vector3;
+
+/// Ethanol heavy atoms: C-C-O.
+fn ethanol() -> Molecule {
+    Molecule {
+        atoms: vec![
+            atom("C", 0.0, 0.0, 0.0),
+            atom("C", 1.5, 0.0, 0.0),
+            atom("O", 2.0, 1.4, 0.0),
+        ],
+        bonds: vec![bond(0, 1), bond(1, 2)],
+        ..Default::default()
+    }
+}
+
+/// Dimethyl ether heavy atoms: C-O-C, an isomer of `ethanol`.
+fn dimethyl_ether() -> Molecule {
+    Molecule {
+        atoms: vec![
+            atom("C", 0.0, 0.0, 0.0),
+            atom("O", 1.4, 0.0, 0.0),
+            atom("C", 2.0, 1.3, 0.0),
+        ],
+        bonds: vec![bond(0, 1), bond(1, 2)],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_topology_hash_ignores_order_and_coordinates() {
+    let mol = ethanol();
+    let mut permuted = Molecule {
+        atoms: vec![
+            mol.atoms[2].clone(),
+            mol.atoms[0].clone(),
+            mol.atoms[1].clone(),
+        ],
+        bonds: vec![bond(2, 0), bond(1, 2)],
+        ..Default::default()
+    };
+    for atom in &mut permuted.atoms {
+        atom.position += Vector3::new(4.0, -1.0, 2.0);
+    }
+    assert_eq!(mol.topology_hash(), permuted.topology_hash());
+    assert_ne!(mol.coordinate_hash(), permuted.coordinate_hash());
+
+    assert_ne!(mol.topology_hash(), dimethyl_ether().topology_hash());
+    let mut double = ethanol();
+    double.bonds[1].order = 2;
+    assert_ne!(mol.topology_hash(), double.topology_hash());
+}
+
+#[test]
+fn test_same_topology_compares_elements_and_bonds() {
+    let mol = ethanol();
+    let mut moved = ethanol();
+    moved.atoms.rotate_left(1);
+    moved.bonds = vec![bond(2, 0), bond(0, 1)];
+    assert!(mol.same_topology(&moved));
+    assert_eq!(mol.fingerprint().topology, moved.fingerprint().topology);
+
+    // Same elements, different bonds.
+    assert!(!mol.same_topology(&dimethyl_ether()));
+    let mut double = ethanol();
+    double.bonds[0].order = 2;
+    assert!(!mol.same_topology(&double));
+    let mut nitrogen = ethanol();
+    nitrogen.atoms[2].element = "N".to_string();
+    assert!(!mol.same_topology(&nitrogen));
+}
+
+#[test]
+fn test_same_positions_within_tolerance() {
+    let mol = ethanol();
+    let mut nudged = ethanol();
+    nudged.atoms[1].position.x += 5e-4;
+    assert!(mol.same_positions(&nudged));
+    nudged.atoms[1].position.x += 5e-3;
+    assert!(!mol.same_positions(&nudged));
+    assert!(!mol.same_positions(&dimethyl_ether()));
+}
+
+#[test]
+fn test_add_molecule_detects_duplicates() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    assert_eq!(viewer.add_molecule(ethanol()), AddResult::Added(0));
+    assert_eq!(viewer.add_molecule(ethanol()), AddResult::Duplicate(0));
+
+    let mut conformer = ethanol();
+    conformer.atoms[2].position.z += 0.8;
+    assert_eq!(
+        viewer.add_molecule(conformer.clone()),
+        AddResult::SameTopology(0)
+    );
+    assert_eq!(viewer.add_molecule(dimethyl_ether()), AddResult::Added(1));
+    assert_eq!(viewer.molecule_count(), 2);
+
+    let forced = viewer.add_molecule_with(conformer, AddOptions { force: true });
+    assert_eq!(forced.added(), Some(2));
+    // An exact copy of the conformer now takes precedence over the first hit.
+    let mut copy = ethanol();
+    copy.atoms[2].position.z += 0.8;
+    assert_eq!(viewer.add_molecule(copy), AddResult::Duplicate(2));
+
+    // The molecule made inactive is still found.
+    assert!(viewer.set_active_molecule(1));
+    assert_eq!(viewer.add_molecule(ethanol()), AddResult::Duplicate(0));
+    assert_eq!(
+        viewer.add_molecule(dimethyl_ether()),
+        AddResult::Duplicate(1)
+    );
+}
+
+#[test]
+fn test_add_command_reports_duplicates() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    assert!(viewer
+        .apply_command(ViewerCommand::AddMolecule(ethanol()))
+        .is_ok());
+    let err = viewer
+        .apply_command(ViewerCommand::AddMolecule(ethanol()))
+        .unwrap_err();
+    assert!(err.contains("duplicate of molecule 0"), "{err}");
+}
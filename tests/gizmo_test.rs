@@ -0,0 +1,102 @@
+mod common;
+
+use common::atom;
+use moleucle_3dview_rs::gizmo::{GizmoDrag, GizmoHandle, TransformGizmo};
+use moleucle_3dview_rs::{Molecule, MoleculeViewer, SelectedAtomRender};
+use nalgebra::{Isometry3, Point3, Translation3, UnitQuaternion, Vector3};
+
+fn gizmo() -> TransformGizmo {
+    TransformGizmo::new(Point3::new(1.0, 2.0, 3.0), 2.0)
+}
+
+#[test]
+fn test_pick_arrow_and_ring() {
+    let gizmo = gizmo();
+    let down = -Vector3::z();
+
+    // Looking down z at the x arrow, outside the y ring that crosses it nearer
+    // the center.
+    let origin = gizmo.center + Vector3::new(1.9, 0.0, 10.0);
+    assert_eq!(gizmo.pick(origin, down), Some(GizmoHandle::Translate(0)));
+
+    // Looking down z at the z ring, away from the arrows.
+    let diagonal = Vector3::new(1.0, 1.0, 0.0).normalize() * gizmo.ring_radius();
+    let origin = gizmo.center + diagonal + Vector3::z() * 10.0;
+    assert_eq!(gizmo.pick(origin, down), Some(GizmoHandle::Rotate(2)));
+
+    // Far off to the side, nothing.
+    let origin = gizmo.center + Vector3::new(5.0, 5.0, 10.0);
+    assert_eq!(gizmo.pick(origin, down), None);
+}
+
+#[test]
+fn test_translation_drag_follows_axis() {
+    let gizmo = gizmo();
+    let down = -Vector3::z();
+    let ray = |x: f32, y: f32| gizmo.center + Vector3::new(x, y, 10.0);
+
+    let mut drag = GizmoDrag::begin(&gizmo, GizmoHandle::Translate(0), ray(1.0, 0.0), down)
+        .expect("ray is across the axis");
+    // Moving 0.7 along x (and off the axis in y, which is ignored).
+    let motion = drag.update(ray(1.7, 0.4), down).unwrap();
+    assert!((motion.translation.vector - Vector3::new(0.7, 0.0, 0.0)).norm() < 1e-5);
+    assert!(motion.rotation.angle() < 1e-6);
+    // Deltas accumulate into the center.
+    let motion = drag.update(ray(1.2, 0.0), down).unwrap();
+    assert!((motion.translation.vector - Vector3::new(-0.5, 0.0, 0.0)).norm() < 1e-5);
+    assert!((drag.center() - (gizmo.center + Vector3::new(0.2, 0.0, 0.0))).norm() < 1e-5);
+
+    // A ray along the axis gives no usable position.
+    assert!(GizmoDrag::begin(&gizmo, GizmoHandle::Translate(2), ray(0.0, 0.0), down).is_none());
+}
+
+#[test]
+fn test_rotation_drag_turns_about_center() {
+    let gizmo = gizmo();
+    let down = -Vector3::z();
+    let r = gizmo.ring_radius();
+    let ray = |x: f32, y: f32| gizmo.center + Vector3::new(x, y, 10.0);
+
+    let mut drag = GizmoDrag::begin(&gizmo, GizmoHandle::Rotate(2), ray(r, 0.0), down).unwrap();
+    let motion = drag.update(ray(0.0, r), down).unwrap();
+
+    let expected = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), std::f32::consts::FRAC_PI_2);
+    assert!(motion.rotation.angle_to(&expected) < 1e-4);
+    // The center stays put; a point one unit along x ends up one unit along y.
+    assert!((motion * gizmo.center - gizmo.center).norm() < 1e-5);
+    let moved = motion * (gizmo.center + Vector3::x());
+    assert!((moved - (gizmo.center + Vector3::y())).norm() < 1e-5);
+    assert!((drag.center() - gizmo.center).norm() < 1e-5);
+}
+
+#[test]
+fn test_transform_atoms_applies_isometry() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(Molecule {
+        atoms: vec![
+            atom("C", 1.0, 0.0, 0.0),
+            atom("O", 0.0, 1.0, 0.0),
+            atom("H", 0.0, 0.0, 1.0),
+        ],
+        bonds: vec![],
+        ..Default::default()
+    });
+    viewer.dirty = false;
+
+    let motion = Isometry3::from_parts(
+        Translation3::new(0.0, 0.0, 5.0),
+        UnitQuaternion::from_axis_angle(&Vector3::z_axis(), std::f32::consts::FRAC_PI_2),
+    );
+    // Index 7 does not exist and is skipped.
+    viewer.transform_atoms(&[0, 2, 7], &motion);
+    assert!(viewer.dirty);
+
+    let atoms = &viewer.molecule.as_ref().unwrap().atoms;
+    assert!((atoms[0].position - Point3::new(0.0, 1.0, 5.0)).norm() < 1e-5);
+    assert!((atoms[1].position - Point3::new(0.0, 1.0, 0.0)).norm() < 1e-5);
+    assert!((atoms[2].position - Point3::new(0.0, 0.0, 6.0)).norm() < 1e-5);
+
+    viewer.transform_molecule(&motion.inverse());
+    let atoms = &viewer.molecule.as_ref().unwrap().atoms;
+    assert!((atoms[1].position - Point3::new(1.0, 0.0, -5.0)).norm() < 1e-5);
+}
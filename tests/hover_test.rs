@@ -0,0 +1,194 @@
+mod common;
+
+use common::atom;
+use graphics::Scene;
+use moleucle_3dview_rs::molecule::Bond;
+use moleucle_3dview_rs::viewer::{ViewerEvent, HOVER_SCALE, PICK_INDEX_MIN_ATOMS};
+use moleucle_3dview_rs::{
+    CameraController, ManualClock, Molecule, MoleculeViewer, OrbitalCamera, PickTarget,
+    SelectedAtomRender,
+};
+use nalgebra::Point2;
+use std::time::Duration;
+
+/// One atom at the origin, under the center of the default 800x600 window.
+fn setup() -> (
+    MoleculeViewer<SelectedAtomRender>,
+    CameraController<OrbitalCamera>,
+    ManualClock,
+) {
+    let mut viewer = MoleculeViewer::new();
+    viewer.set_molecule(Molecule {
+        atoms: vec![atom("C", 0.0, 0.0, 0.0)],
+        ..Default::default()
+    });
+    let mut controller = CameraController::<OrbitalCamera>::new();
+    let clock = ManualClock::new();
+    controller.set_clock(clock.clone());
+    (viewer, controller, clock)
+}
+
+#[test]
+fn test_moves_within_interval_produce_one_pick() {
+    let (viewer, mut controller, clock) = setup();
+    controller.settings.hover_dedupe = false;
+
+    let mut events = Vec::new();
+    for x in [400.0, 401.0, 402.0] {
+        events.extend(controller.hover_at(x, 300.0, &viewer));
+        clock.advance(Duration::from_millis(5));
+    }
+    assert_eq!(events, [ViewerEvent::AtomHovered(0)]);
+
+    // The coalesced moves are picked once the interval has passed, and only once.
+    assert_eq!(controller.poll_hover(&viewer), None);
+    clock.advance(Duration::from_millis(20));
+    assert_eq!(
+        controller.poll_hover(&viewer),
+        Some(ViewerEvent::AtomHovered(0))
+    );
+    clock.advance(Duration::from_millis(100));
+    assert_eq!(controller.poll_hover(&viewer), None);
+}
+
+#[test]
+fn test_duplicate_hovers_are_suppressed() {
+    let (viewer, mut controller, clock) = setup();
+    let step = Duration::from_millis(50);
+
+    assert_eq!(
+        controller.hover_at(400.0, 300.0, &viewer),
+        Some(ViewerEvent::AtomHovered(0))
+    );
+    clock.advance(step);
+    assert_eq!(controller.hover_at(401.0, 300.0, &viewer), None);
+    clock.advance(step);
+    assert_eq!(
+        controller.hover_at(10.0, 10.0, &viewer),
+        Some(ViewerEvent::NothingHovered)
+    );
+    clock.advance(step);
+    assert_eq!(controller.hover_at(20.0, 10.0, &viewer), None);
+    clock.advance(step);
+    assert_eq!(
+        controller.hover_at(400.0, 300.0, &viewer),
+        Some(ViewerEvent::AtomHovered(0))
+    );
+}
+
+#[test]
+fn test_hover_can_be_disabled() {
+    let (viewer, mut controller, _clock) = setup();
+    controller.settings.hover = false;
+    assert_eq!(controller.hover_at(400.0, 300.0, &viewer), None);
+    assert_eq!(controller.poll_hover(&viewer), None);
+}
+
+#[test]
+fn test_hovered_atom_is_highlighted() {
+    let (mut viewer, mut controller, _clock) = setup();
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    let plain = (scene.entities[0].color, scene.entities[0].scale);
+
+    let event = controller.hover_at(400.0, 300.0, &viewer).unwrap();
+    assert!(viewer.handle_pick(&event));
+    assert_eq!(viewer.hovered(), Some(PickTarget::Atom(0)));
+    // The same hover again changes nothing.
+    assert!(!viewer.handle_pick(&event));
+    viewer.update_scene(&mut scene);
+    let (color, scale) = (scene.entities[0].color, scene.entities[0].scale);
+    assert!((scale - plain.1 * HOVER_SCALE).abs() < 1e-6);
+    assert!(color.0 > plain.0 .0 && color.0 < 1.0);
+
+    assert!(viewer.handle_pick(&ViewerEvent::NothingHovered));
+    viewer.update_scene(&mut scene);
+    assert_eq!((scene.entities[0].color, scene.entities[0].scale), plain);
+
+    // A new molecule starts without a hover.
+    viewer.set_hovered(Some(PickTarget::Atom(0)));
+    viewer.set_molecule(Molecule::default());
+    assert_eq!(viewer.hovered(), None);
+}
+
+#[test]
+fn test_thin_bonds_hover_within_tolerance() {
+    let (mut viewer, mut controller, clock) = setup();
+    // A long bond across the window's center, well clear of its atoms.
+    viewer.set_molecule(Molecule {
+        atoms: vec![atom("C", -4.0, 0.0, 0.0), atom("C", 4.0, 0.0, 0.0)],
+        bonds: vec![Bond {
+            atom_a: 0,
+            atom_b: 1,
+            order: 1,
+        }],
+        ..Default::default()
+    });
+    let near = |viewer: &MoleculeViewer<SelectedAtomRender>, y: f32, tolerance: f32| {
+        let camera = controller.camera.as_ref();
+        viewer.bond_near_pixel(camera, Point2::new(400.0, y), tolerance, 800.0, 600.0)
+    };
+    assert_eq!(near(&viewer, 303.0, 4.0), Some(0));
+    assert_eq!(near(&viewer, 310.0, 4.0), None);
+
+    // Find where the exact pick first misses the bond, below its axis.
+    controller.settings.hover_dedupe = false;
+    controller.settings.bond_hover_tolerance = 0.0;
+    let hover = |controller: &mut CameraController<OrbitalCamera>, y: f32| {
+        clock.advance(Duration::from_millis(50));
+        controller.hover_at(400.0, y, &viewer)
+    };
+    assert_eq!(
+        hover(&mut controller, 300.0),
+        Some(ViewerEvent::BondHovered(0))
+    );
+    let miss = (301..340)
+        .map(|y| y as f32)
+        .find(|&y| hover(&mut controller, y) == Some(ViewerEvent::NothingHovered))
+        .unwrap();
+    // Past that, the tolerance measures from the axis.
+    controller.settings.bond_hover_tolerance = miss - 300.0 + 4.0;
+    assert_eq!(
+        hover(&mut controller, miss + 3.0),
+        Some(ViewerEvent::BondHovered(0))
+    );
+    assert_eq!(
+        hover(&mut controller, miss + 5.0),
+        Some(ViewerEvent::NothingHovered)
+    );
+}
+
+#[test]
+fn test_bond_hover_uses_the_pick_index() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    // A grid of bonded pairs, big enough for the pick index.
+    let mut mol = Molecule::default();
+    for k in 0..PICK_INDEX_MIN_ATOMS / 2 {
+        let (x, y, z) = ((k % 10) as f32, (k / 10 % 10) as f32, (k / 100) as f32);
+        let n = mol.atoms.len();
+        mol.atoms.push(atom("C", 3.0 * x, 3.0 * y, -3.0 * z));
+        mol.atoms
+            .push(atom("C", 3.0 * x + 1.5, 3.0 * y + 0.8, -3.0 * z));
+        mol.bonds.push(Bond {
+            atom_a: n,
+            atom_b: n + 1,
+            order: 1,
+        });
+    }
+    viewer.set_molecule(mol);
+    let mut camera = OrbitalCamera::default();
+    viewer.fit_camera_to_molecule(&mut camera);
+    viewer.update_scene(&mut Scene::default());
+
+    let mut hovered = 0;
+    for (u, v) in (0..20).flat_map(|i| (0..15).map(move |j| (40.0 * i as f32, 40.0 * j as f32))) {
+        let pixel = Point2::new(u + 3.0, v + 7.0);
+        let indexed = viewer.bond_near_pixel(&camera, pixel, 6.0, 800.0, 600.0);
+        viewer.dirty = true;
+        let scanned = viewer.bond_near_pixel(&camera, pixel, 6.0, 800.0, 600.0);
+        viewer.dirty = false;
+        assert_eq!(indexed, scanned, "{pixel:?}");
+        hovered += indexed.is_some() as usize;
+    }
+    assert!(hovered > 30, "{hovered}");
+}
@@ -0,0 +1,176 @@
+use moleucle_3dview_rs::molecule::Atom;
+use moleucle_3dview_rs::{Lattice, LengthUnit, Molecule, MoleculeViewer, SelectedAtomRender};
+use nalgebra::{Point3, Vector3};
+
+fn close(a: Vector3<f32>, b: Vector3<f32>) -> bool {
+    (a - b).norm() < 1e-5
+}
+
+/// Two atoms in a cubic 10 Å cell.
+fn cell() -> Molecule {
+    let atom = |x, y, z| Atom {
+        position: Point3::new(x, y, z),
+        element: "NA".to_string(),
+        id: 0,
+    };
+    Molecule {
+        atoms: vec![atom(1.0, 2.0, 3.0), atom(2.6, 4.9, 7.4)],
+        lattice: Lattice::cubic(10.0),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_lattice_round_trips() {
+    let lattice = Lattice::new(
+        Vector3::new(5.0, 0.0, 0.0),
+        Vector3::new(1.0, 6.0, 0.0),
+        Vector3::new(0.5, 0.5, 7.0),
+    )
+    .unwrap();
+    assert!((lattice.volume() - 210.0).abs() < 1e-3);
+    for p in [Point3::new(1.0, -2.0, 3.5), Point3::new(5.0, 6.0, 7.0)] {
+        assert!(close(
+            lattice.to_cartesian(lattice.to_fractional(p)).coords,
+            p.coords
+        ));
+    }
+    assert!(close(
+        lattice.to_fractional(Point3::new(6.5, 6.5, 7.0)),
+        Vector3::new(1.0, 1.0, 1.0)
+    ));
+    assert!(Lattice::new(Vector3::x(), Vector3::y(), Vector3::x() + Vector3::y()).is_none());
+    assert!(Lattice::cubic(0.0).is_none());
+}
+
+#[test]
+fn test_fractional_positions() {
+    let mut mol = cell();
+    assert!(close(
+        mol.fractional_position(0).unwrap(),
+        Vector3::new(0.1, 0.2, 0.3)
+    ));
+    assert!(mol.set_fractional_position(0, Vector3::new(0.5, 0.5, 0.5)));
+    assert!(close(
+        mol.atoms[0].position.coords,
+        Vector3::new(5.0, 5.0, 5.0)
+    ));
+    assert!(mol.fractional_position(2).is_none());
+    assert!(!mol.set_fractional_position(2, Vector3::zeros()));
+
+    // Nothing to be fractional in without a lattice.
+    mol.lattice = None;
+    assert!(mol.fractional_position(0).is_none());
+    assert!(!mol.set_fractional_position(0, Vector3::zeros()));
+    assert!(!mol.snap_to_fraction(0, 2));
+}
+
+#[test]
+fn test_snap_to_fraction_undoes() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(cell());
+    viewer.snap_to_fraction(1, 4).unwrap();
+    let mol = viewer.molecule.as_ref().unwrap();
+    assert!(close(
+        mol.fractional_position(1).unwrap(),
+        Vector3::new(0.25, 0.5, 0.75)
+    ));
+    assert!(close(
+        mol.atoms[1].position.coords,
+        Vector3::new(2.5, 5.0, 7.5)
+    ));
+    assert!(viewer.undo());
+    let mol = viewer.molecule.as_ref().unwrap();
+    assert_eq!(mol.atoms[1].position, Point3::new(2.6, 4.9, 7.4));
+
+    assert!(viewer.snap_to_fraction(1, 0).is_err());
+    assert!(viewer.snap_to_fraction(2, 4).is_err());
+    viewer.set_molecule(Molecule {
+        lattice: None,
+        ..cell()
+    });
+    assert!(viewer.snap_to_fraction(0, 4).is_err());
+    assert!(!viewer.can_undo());
+}
+
+#[test]
+fn test_gro_box_becomes_lattice() {
+    let gro = "ion in a box
+    1
+    1CL      CL    1   1.000   0.500   0.250
+   2.00000   2.00000   3.00000
+";
+    let mut mol = Molecule::from_gro_str(gro).unwrap();
+    let lattice = mol.lattice.unwrap();
+    assert!((lattice.volume() - 12_000.0).abs() < 0.1);
+    assert!(close(
+        mol.fractional_position(0).unwrap(),
+        Vector3::new(0.5, 0.25, 1.0 / 12.0)
+    ));
+    // Fractional coordinates don't depend on the unit.
+    mol.convert_units(LengthUnit::Nanometer);
+    assert!(close(
+        mol.fractional_position(0).unwrap(),
+        Vector3::new(0.5, 0.25, 1.0 / 12.0)
+    ));
+
+    let triclinic = gro.replace(
+        "3.00000\n",
+        "3.00000   0.00000   0.00000   1.00000   0.00000   0.00000   1.00000\n",
+    );
+    let lattice = Molecule::from_gro_str(&triclinic).unwrap().lattice.unwrap();
+    assert!(close(
+        lattice.vectors().column(1).into_owned(),
+        Vector3::new(10.0, 20.0, 0.0)
+    ));
+    let no_box = gro.replace("   2.00000   2.00000   3.00000\n", "");
+    assert!(Molecule::from_gro_str(&no_box).unwrap().lattice.is_none());
+}
+
+#[test]
+fn test_lattice_origin_offsets_edges_and_wrapping() {
+    let lattice = Lattice::cubic(10.0)
+        .unwrap()
+        .with_origin(Point3::new(-5.0, 0.0, 2.0));
+    assert!(close(
+        lattice.to_fractional(Point3::new(0.0, 5.0, 7.0)),
+        Vector3::new(0.5, 0.5, 0.5)
+    ));
+    assert!(close(
+        lattice.to_cartesian(Vector3::new(1.0, 0.0, 0.0)).coords,
+        Vector3::new(5.0, 0.0, 2.0)
+    ));
+    // Wrapped into [-5, 5) x [0, 10) x [2, 12), not [0, 10)^3.
+    assert!(close(
+        lattice.wrap(Point3::new(7.0, -1.0, 1.0)).coords,
+        Vector3::new(-3.0, 9.0, 11.0)
+    ));
+    assert!(close(
+        lattice.wrap(Point3::new(-4.0, 3.0, 11.0)).coords,
+        Vector3::new(-4.0, 3.0, 11.0)
+    ));
+
+    let edges = lattice.edges();
+    let corners: Vec<_> = edges.iter().flat_map(|&(a, b)| [a, b]).collect();
+    for corner in &corners {
+        let f = lattice.to_fractional(*corner);
+        assert!(f.iter().all(|&c| c.abs() < 1e-5 || (c - 1.0).abs() < 1e-5));
+    }
+    assert!(corners.contains(&Point3::new(-5.0, 0.0, 2.0)));
+    assert!(corners.contains(&Point3::new(5.0, 10.0, 12.0)));
+    assert!(edges
+        .iter()
+        .all(|&(a, b)| ((b - a).norm() - 10.0).abs() < 1e-5));
+
+    // The origin follows the molecule.
+    let mut mol = Molecule {
+        lattice: Some(lattice),
+        ..cell()
+    };
+    let before = mol.fractional_position(1).unwrap();
+    mol.translate(Vector3::new(1.0, 2.0, 3.0));
+    assert_eq!(mol.lattice.unwrap().origin(), Point3::new(-4.0, 2.0, 5.0));
+    assert!(close(mol.fractional_position(1).unwrap(), before));
+    mol.scale(2.0);
+    assert!(close(mol.fractional_position(1).unwrap(), before));
+}
@@ -0,0 +1,529 @@
+use graphics::Scene;
+use moleucle_3dview_rs::molecule::Atom;
+use moleucle_3dview_rs::viewer::{EntityKind, ViewerEvent};
+use moleucle_3dview_rs::{
+    AdditionalRender, AngleMeasurementRender, EditAction, Measurement, MeasurementMode,
+    MeasurementTool, Molecule, MoleculeViewer, Selection,
+};
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+
+fn molecule(atoms: &[(&str, [f32; 3])]) -> Molecule {
+    Molecule {
+        atoms: atoms
+            .iter()
+            .map(|&(element, [x, y, z])| Atom {
+                position: Point3::new(x, y, z),
+                element: element.to_string(),
+                id: 0,
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+/// Three atoms on the x axis at 0, 1.5 and 4 Å.
+fn line() -> Molecule {
+    molecule(&[
+        ("C", [0.0; 3]),
+        ("C", [1.5, 0.0, 0.0]),
+        ("C", [4.0, 0.0, 0.0]),
+    ])
+}
+
+/// Water with its experimental geometry: O–H 0.9572 Å, H–O–H 104.52°.
+fn water() -> Molecule {
+    let half = 104.52f32.to_radians() / 2.0;
+    let (x, y) = (0.9572 * half.sin(), 0.9572 * half.cos());
+    molecule(&[("O", [0.0; 3]), ("H", [x, y, 0.0]), ("H", [-x, y, 0.0])])
+}
+
+/// The carbon chain of butane, zig-zag with tetrahedral angles; trans
+/// (anti) unless `gauche`, which turns the last carbon by 120° about the
+/// middle bond.
+fn butane(gauche: bool) -> Molecule {
+    let half = 109.47f32.to_radians() / 2.0;
+    let (dx, dy) = (1.54 * half.sin(), 1.54 * half.cos());
+    let mut mol = molecule(&[
+        ("C", [0.0, 0.0, 0.0]),
+        ("C", [dx, dy, 0.0]),
+        ("C", [2.0 * dx, 0.0, 0.0]),
+        ("C", [3.0 * dx, dy, 0.0]),
+    ]);
+    if gauche {
+        let (b, c) = (mol.atoms[1].position, mol.atoms[2].position);
+        let turn = UnitQuaternion::from_axis_angle(
+            &nalgebra::Unit::new_normalize(c - b),
+            120f32.to_radians(),
+        );
+        mol.atoms[3].position = c + turn * (mol.atoms[3].position - c);
+    }
+    mol
+}
+
+#[test]
+fn test_angle_and_dihedral() {
+    let water = water();
+    // The vertex comes first.
+    assert!((water.angle(0, 1, 2).unwrap() - 104.52).abs() < 1e-3);
+    assert!((water.angle(1, 0, 2).unwrap() - 37.74).abs() < 1e-2);
+    assert_eq!(water.angle(0, 1, 3), None);
+    assert_eq!(water.angle(1, 1, 2), None);
+    assert!((line().angle(1, 0, 2).unwrap() - 180.0).abs() < 1e-3);
+
+    let trans = butane(false);
+    assert!((trans.dihedral(0, 1, 2, 3).unwrap().abs() - 180.0).abs() < 1e-3);
+    let gauche = butane(true).dihedral(0, 1, 2, 3).unwrap();
+    assert!((gauche.abs() - 60.0).abs() < 1e-3, "{gauche}");
+    // Reversing the chain keeps the angle.
+    let reversed = butane(true).dihedral(3, 2, 1, 0).unwrap();
+    assert!((reversed - gauche).abs() < 1e-3);
+    // A straight chain has no dihedral.
+    let mut straight = line();
+    straight.atoms.push(straight.atoms[2].clone());
+    straight.atoms[3].position.y = 1.0;
+    assert_eq!(straight.dihedral(0, 1, 2, 3), None);
+    assert_eq!(trans.dihedral(0, 1, 2, 4), None);
+}
+
+#[test]
+fn test_modes_pick_more_atoms() {
+    let mol = butane(false);
+    let mut tool = MeasurementTool::new();
+    tool.pick(&mol, 0);
+    tool.set_mode(MeasurementMode::Angle);
+    assert!(tool.pending().is_empty());
+    assert_eq!(tool.pick(&mol, 0), None);
+    assert_eq!(tool.pick(&mol, 1), None);
+    let angle = tool.pick(&mol, 2).unwrap();
+    assert_eq!(angle.mode(), MeasurementMode::Angle);
+    assert_eq!(angle.atoms(), [0, 1, 2]);
+    assert!((angle.value() - 109.47).abs() < 1e-3);
+
+    tool.set_mode(MeasurementMode::Dihedral);
+    for atom in 0..3 {
+        assert_eq!(tool.pick(&mol, atom), None);
+    }
+    assert_eq!(tool.pending(), [0, 1, 2]);
+    let dihedral = tool.pick(&mol, 3).unwrap();
+    assert!((dihedral.value().abs() - 180.0).abs() < 1e-3);
+
+    // The values follow the atoms, e.g. to another trajectory frame.
+    tool.update_values(&butane(true));
+    let values: Vec<f32> = tool.measurements().iter().map(|m| m.value()).collect();
+    assert!((values[0] - 109.47).abs() < 1e-3);
+    assert!((values[1].abs() - 60.0).abs() < 1e-3);
+}
+
+#[test]
+fn test_picks_pair_up() {
+    let mol = line();
+    let mut tool = MeasurementTool::new();
+    assert_eq!(tool.pick(&mol, 0), None);
+    assert_eq!(tool.pending(), [0]);
+    // The pending atom again, or one that does not exist, is ignored.
+    assert_eq!(tool.pick(&mol, 0), None);
+    assert_eq!(tool.pick(&mol, 3), None);
+    let first = tool.pick(&mol, 2).unwrap();
+    assert_eq!(first.atoms(), [0, 2]);
+    assert!((first.value() - 4.0).abs() < 1e-6);
+
+    // A third atom starts the next measurement.
+    assert_eq!(tool.pick(&mol, 1), None);
+    assert_eq!(tool.pending(), [1]);
+    assert!((tool.pick(&mol, 2).unwrap().value() - 2.5).abs() < 1e-6);
+    assert_eq!(tool.measurements().len(), 2);
+
+    let mut moved = mol.clone();
+    moved.atoms[2].position.x = 5.0;
+    tool.update_values(&moved);
+    let distances: Vec<f32> = tool.measurements().iter().map(|m| m.value()).collect();
+    assert_eq!(distances, [5.0, 3.5]);
+
+    assert!(tool.clear());
+    assert!(!tool.clear());
+    assert!(tool.measurements().is_empty() && tool.pending().is_empty());
+}
+
+#[test]
+fn test_viewer_measures_clicks() {
+    let mut viewer = MoleculeViewer::new();
+    viewer.set_molecule(line());
+    viewer.additional_render = Some(Box::new(MeasurementTool::new()));
+    assert!(viewer.handle_pick(&ViewerEvent::AtomClicked(0)));
+    assert!(viewer.handle_pick(&ViewerEvent::AtomClicked(1)));
+    let tool = viewer.additional_render.as_ref().unwrap();
+    assert_eq!(
+        tool.measurements(),
+        [Measurement::Distance {
+            atoms: [0, 1],
+            distance: 1.5
+        }]
+    );
+
+    // Drawn as dashes after the molecule.
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    let dashes = (0..scene.entities.len())
+        .filter(|&i| viewer.classify_entity(i) == Some(EntityKind::Overlay { renderer_id: 0 }))
+        .count();
+    assert!(dashes > 1);
+
+    // Escape's edit clears the measurements with the selection.
+    viewer.handle_pick(&ViewerEvent::AtomClicked(2));
+    viewer.apply_edit(EditAction::ClearSelection).unwrap();
+    let tool = viewer.additional_render.as_ref().unwrap();
+    assert!(tool.measurements().is_empty() && tool.pending().is_empty());
+}
+
+#[test]
+fn test_angles_draw_arcs() {
+    let mol = water();
+    let mut tool = MeasurementTool::new();
+    tool.set_mode(MeasurementMode::Angle);
+    tool.arc_radius = 2.0;
+    tool.label_size = 0.0;
+    tool.angle_arms = false;
+    for atom in [1, 0, 2] {
+        tool.pick(&mol, atom);
+    }
+    let mut scene = Scene::default();
+    tool.update_scene(&mut scene, &mol);
+    // Segments of at most 10° each, shrunk to fit inside the O–H bonds.
+    assert_eq!(scene.meshes.len(), 1);
+    assert_eq!(scene.entities.len(), 11);
+    for entity in &scene.entities {
+        let p = entity.position;
+        let r = (p.x * p.x + p.y * p.y + p.z * p.z).sqrt();
+        assert!(r < 0.8 * 0.9572 && r > 0.7 * 0.9572, "{r}");
+    }
+}
+
+#[test]
+fn test_angle_measurement_render() {
+    let mol = water();
+    let mut render = AngleMeasurementRender::new(0, 1, 2);
+    render.arc_radius = 2.0;
+    render.label_size = 0.0;
+    let mut scene = Scene::default();
+    render.update_scene(&mut scene, &mol);
+    // One cylinder for the two arms, then the 11 arc segments.
+    assert_eq!(scene.meshes.len(), 1);
+    assert_eq!(scene.entities.len(), 13);
+    let length = |e: &graphics::Entity| e.scale_partial.map_or(e.scale, |s| s.y);
+    for arm in &scene.entities[..2] {
+        assert!((length(arm) - 0.9572).abs() < 1e-3);
+    }
+    for segment in &scene.entities[2..] {
+        let p = segment.position;
+        assert!(p.z.abs() < 1e-4, "the arc lies in the molecule's plane");
+    }
+
+    // Labeled with the angle, which follows the atoms.
+    render.label_size = 0.3;
+    let mut scene = Scene::default();
+    render.update_scene(&mut scene, &mol);
+    let label = |scene: &Scene| {
+        scene
+            .entities
+            .iter()
+            .filter(|e| e.mesh > 0)
+            .map(|e| e.mesh - 1)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(label(&scene), [1, 0, 4, 10, 5]);
+    let mut bent = mol.clone();
+    bent.atoms[2].position = Point3::new(0.0, -0.9572, 0.0);
+    render.refresh(&mut scene, &bent, 0);
+    assert_eq!(label(&scene), [1, 2, 7, 10, 7]);
+
+    // Nothing for a vertex on an arm's atom.
+    let mut scene = Scene::default();
+    AngleMeasurementRender::new(1, 1, 2).update_scene(&mut scene, &mol);
+    assert!(scene.entities.is_empty());
+}
+
+#[test]
+fn test_bond_length() {
+    let mut mol = line();
+    mol.add_bond(0, 1, 1).unwrap();
+    assert_eq!(mol.bond_length(1, 0), Some(1.5));
+    // Not bonded, though the distance is known.
+    assert_eq!(mol.bond_length(1, 2), None);
+    assert_eq!(mol.distance(1, 2, None), Some(2.5));
+    assert_eq!(mol.bond_length(0, 3), None);
+}
+
+#[test]
+fn test_measurements_are_labeled() {
+    let mol = line();
+    let mut tool = MeasurementTool::new();
+    let distance = tool.measure(&mol, &[0, 2]).unwrap();
+    assert_eq!(distance.label(), "4.00");
+    assert_eq!(
+        Measurement::measure(&water(), &[1, 0, 2]).unwrap().label(),
+        "104.5"
+    );
+    assert!(tool.measure(&mol, &[0, 3]).is_none());
+    assert_eq!(tool.measurements(), [distance]);
+
+    let mut scene = Scene::default();
+    tool.update_scene(&mut scene, &mol);
+    // The dash cylinder, then one mesh per glyph.
+    assert_eq!(scene.meshes.len(), 13);
+    let labels: Vec<_> = scene.entities.iter().filter(|e| e.mesh > 0).collect();
+    assert_eq!(labels.len(), 4);
+    // Over the middle of the line, in reading order.
+    for label in &labels {
+        assert!(label.position.y > 0.0 && (label.position.x - 2.0).abs() < 1.0);
+    }
+    assert_eq!(
+        labels.iter().map(|e| e.mesh - 1).collect::<Vec<_>>(),
+        [4, 10, 0, 0]
+    );
+
+    // Refreshing after a camera turn moves the labels but not the dashes.
+    let before = scene.entities.clone();
+    scene.camera.orientation =
+        lin_alg::f32::Quaternion::from_axis_angle(lin_alg::f32::Vec3::new(0.0, 0.0, 1.0), 1.0);
+    tool.refresh(&mut scene, &mol, 0);
+    assert_eq!(scene.entities.len(), before.len());
+    let moved = |i: usize| scene.entities[i].position != before[i].position;
+    assert!((0..before.len()).all(|i| moved(i) == (before[i].mesh > 0)));
+}
+
+#[test]
+fn test_measure_selection() {
+    let mut viewer = MoleculeViewer::<MeasurementTool>::new();
+    viewer.set_molecule(line());
+    viewer.select_atom(0);
+    assert!(viewer.measure_selection().is_err());
+    viewer.select_atom(2);
+    // Nothing to draw it with yet.
+    assert!(viewer.measure_selection().is_err());
+    viewer.additional_render = Some(Box::new(MeasurementTool::new()));
+    let measurement = viewer.measure_selection().unwrap();
+    assert_eq!(measurement.value(), 4.0);
+    assert!(viewer.dirty_flags().overlays);
+    let tool = viewer.additional_render.as_ref().unwrap();
+    assert_eq!(tool.measurements(), [measurement]);
+}
+
+#[test]
+fn test_measure_selected_angle() {
+    let mut viewer = MoleculeViewer::new();
+    let mut mol = water();
+    viewer.set_molecule(mol.clone());
+    viewer.additional_render = Some(Box::new(MeasurementTool::new()));
+    for atom in 0..3 {
+        viewer.select_atom(atom);
+    }
+    // Unbonded, the vertex is where the angle is widest.
+    let measurement = viewer.measure_selection().unwrap();
+    assert_eq!(measurement.atoms(), [1, 0, 2]);
+    assert!((measurement.value() - 104.52).abs() < 0.01);
+
+    // Bonded, it is the atom bonded to the other two, though the right
+    // angle is at atom 2.
+    mol.atoms[0].position = mol.atoms[2].position + Vector3::new(0.0, 3.0, 0.0);
+    mol.add_bond(1, 0, 1).unwrap();
+    mol.add_bond(1, 2, 1).unwrap();
+    viewer.set_molecule(mol);
+    viewer.set_selection(Selection::from_atoms([0, 1, 2]));
+    let measurement = viewer.measure_selection().unwrap();
+    assert_eq!(measurement.atoms(), [0, 1, 2]);
+    viewer.deselect_atom(2);
+    viewer.deselect_atom(1);
+    assert!(viewer.measure_selection().is_err());
+
+    // The arms are dashed like distances.
+    let tool = viewer.additional_render.as_mut().unwrap();
+    tool.label_size = 0.0;
+    let mut scene = Scene::default();
+    tool.update_scene(&mut scene, &water());
+    let with_arms = scene.entities.len();
+    tool.angle_arms = false;
+    let mut scene = Scene::default();
+    tool.update_scene(&mut scene, &water());
+    assert!(with_arms > scene.entities.len() + 4);
+}
+
+/// Places an atom `length` from `c`, at `angle` b–c–new and `torsion`
+/// a–b–c–new, both in degrees (NeRF).
+fn place([a, b, c]: [Point3<f32>; 3], length: f32, angle: f32, torsion: f32) -> Point3<f32> {
+    let bc = (c - b).normalize();
+    let n = (b - a).cross(&bc).normalize();
+    let (angle, torsion) = (angle.to_radians(), torsion.to_radians());
+    let local = Vector3::new(
+        -length * angle.cos(),
+        length * angle.sin() * torsion.cos(),
+        length * angle.sin() * torsion.sin(),
+    );
+    c + bc * local.x + n.cross(&bc) * local.y + n * local.z
+}
+
+/// N–CA–C of two residues and the next N, from the given backbone
+/// torsions: psi of the first residue, then omega, phi and psi of the
+/// second.
+fn backbone(psi1: f32, omega: f32, phi2: f32, psi2: f32) -> Molecule {
+    let mut atoms = vec![
+        Point3::new(0.0, 1.458, 0.0),
+        Point3::origin(),
+        Point3::new(
+            1.525 * 68.8f32.to_radians().sin(),
+            -1.525 * 68.8f32.to_radians().cos(),
+            0.0,
+        ),
+    ];
+    // Bond lengths and angles of an ideal peptide.
+    let steps = [
+        (1.329, 116.2, psi1),
+        (1.458, 121.7, omega),
+        (1.525, 111.2, phi2),
+        (1.329, 116.2, psi2),
+    ];
+    for (length, angle, torsion) in steps {
+        let n = atoms.len();
+        let next = place(
+            [atoms[n - 3], atoms[n - 2], atoms[n - 1]],
+            length,
+            angle,
+            torsion,
+        );
+        atoms.push(next);
+    }
+    let elements = ["N", "C", "C", "N", "C", "C", "N"];
+    let atoms: Vec<_> = elements
+        .iter()
+        .zip(&atoms)
+        .map(|(&element, p)| (element, [p.x, p.y, p.z]))
+        .collect();
+    molecule(&atoms)
+}
+
+#[test]
+fn test_dihedral_sign_follows_iupac() {
+    // Looking from b to c, a turns clockwise by 90° onto d.
+    let mol = molecule(&[
+        ("C", [1.0, 0.0, 0.0]),
+        ("C", [0.0; 3]),
+        ("C", [0.0, 0.0, 1.0]),
+        ("C", [0.0, 1.0, 1.0]),
+    ]);
+    assert!((mol.dihedral(0, 1, 2, 3).unwrap() - 90.0).abs() < 1e-3);
+    assert!((mol.dihedral(3, 2, 1, 0).unwrap() - 90.0).abs() < 1e-3);
+
+    // Ramachandran regions: right-handed alpha helix, beta sheet and
+    // left-handed helix, with a trans peptide bond.
+    for (phi, psi) in [(-57.0, -47.0), (-139.0, 135.0), (57.0, 47.0)] {
+        let mol = backbone(120.0, 180.0, phi, psi);
+        let [omega, measured_phi, measured_psi] = [[1, 2, 3, 4], [2, 3, 4, 5], [3, 4, 5, 6]]
+            .map(|[a, b, c, d]| mol.dihedral(a, b, c, d).unwrap());
+        assert!((omega.abs() - 180.0).abs() < 0.01, "{omega}");
+        assert!((measured_phi - phi).abs() < 0.01, "{measured_phi} {phi}");
+        assert!((measured_psi - psi).abs() < 0.01, "{measured_psi} {psi}");
+    }
+}
+
+#[test]
+fn test_dihedral_planes() {
+    let mol = butane(true);
+    let mut tool = MeasurementTool::new();
+    tool.label_size = 0.0;
+    tool.measure(&mol, &[0, 1, 2, 3]).unwrap();
+    let mut scene = Scene::default();
+    tool.update_scene(&mut scene, &mol);
+    // Dashes, then the plane and the atom marks.
+    assert_eq!(scene.meshes.len(), 3);
+    let planes: Vec<_> = scene.entities.iter().filter(|e| e.mesh == 1).collect();
+    let marks = scene.entities.iter().filter(|e| e.mesh == 2).count();
+    assert_eq!((planes.len(), marks), (2, 4));
+    // Each as long as the middle bond and reaching out to its end atom.
+    let (b, c) = (mol.atoms[1].position, mol.atoms[2].position);
+    let hinge = (c - b).norm();
+    let height = |p: Point3<f32>| (p - b).cross(&(c - b)).norm() / hinge;
+    for (plane, end) in planes.iter().zip([0, 3]) {
+        let scale = plane.scale_partial.unwrap();
+        assert!((scale.x - hinge).abs() < 1e-4);
+        assert!((scale.y - height(mol.atoms[end].position)).abs() < 1e-4);
+        assert!(plane.opacity < 1.0);
+    }
+
+    tool.dihedral_planes = false;
+    let mut scene = Scene::default();
+    tool.update_scene(&mut scene, &mol);
+    assert_eq!(scene.meshes.len(), 1);
+    assert!(scene.entities.iter().all(|e| e.mesh == 0));
+}
+
+#[test]
+fn test_measurements_follow_deleted_atoms() {
+    let mut viewer = MoleculeViewer::new();
+    viewer.set_molecule(line());
+    viewer.additional_render = Some(Box::new(MeasurementTool::new()));
+    let measured = |viewer: &MoleculeViewer<MeasurementTool>| -> Vec<Vec<usize>> {
+        let tool = viewer.additional_render.as_ref().unwrap();
+        tool.measurements()
+            .iter()
+            .map(|m| m.atoms().to_vec())
+            .collect()
+    };
+    for atom in [1, 2, 0, 1] {
+        viewer.handle_pick(&ViewerEvent::AtomClicked(atom));
+    }
+    assert_eq!(measured(&viewer), [[1, 2], [0, 1]]);
+
+    // The 0–1 distance goes with atom 0; 1–2 is renumbered to 0–1.
+    viewer.delete_atoms(&[0]).unwrap();
+    assert_eq!(measured(&viewer), [[0, 1]]);
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    let tool = viewer.additional_render.as_ref().unwrap();
+    assert_eq!(tool.measurements()[0].value(), 2.5);
+
+    // Undo takes it back to the restored atoms.
+    assert!(viewer.undo());
+    assert_eq!(measured(&viewer), [[1, 2]]);
+
+    // Other molecules have other atoms.
+    viewer.set_molecule(water());
+    assert!(measured(&viewer).is_empty());
+    viewer.handle_pick(&ViewerEvent::AtomClicked(0));
+    viewer.handle_pick(&ViewerEvent::AtomClicked(1));
+    viewer.add_molecule(line());
+    assert!(viewer.set_active_molecule(1));
+    assert!(measured(&viewer).is_empty());
+}
+
+#[test]
+fn test_measurements_follow_dragged_atoms() {
+    let mut viewer = MoleculeViewer::new();
+    viewer.set_molecule(line());
+    let mut tool = MeasurementTool::new();
+    tool.measure(viewer.molecule.as_ref().unwrap(), &[0, 2]);
+    viewer.additional_render = Some(Box::new(tool));
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    let overlay_meshes = |viewer: &MoleculeViewer<MeasurementTool>, scene: &Scene| {
+        (0..scene.entities.len())
+            .filter(|&i| matches!(viewer.classify_entity(i), Some(EntityKind::Overlay { .. })))
+            .map(|i| scene.entities[i].mesh)
+            .collect::<Vec<_>>()
+    };
+    let before = overlay_meshes(&viewer, &scene);
+
+    viewer.begin_atom_drag(2, false).unwrap();
+    viewer.drag_atom_to(Point3::new(0.0, 3.0, 0.0));
+    assert!(viewer.refresh_drag(&mut scene));
+    let tool = viewer.additional_render.as_ref().unwrap();
+    assert!((tool.measurements()[0].value() - 3.0).abs() < 1e-5);
+    // The label reads 3.00 instead of 4.00.
+    assert_ne!(overlay_meshes(&viewer, &scene), before);
+    viewer.end_atom_drag();
+
+    // Moved atoms are measured again too.
+    let shift = nalgebra::Isometry3::translation(0.0, 1.0, 0.0);
+    viewer.transform_atoms(&[2], &shift);
+    viewer.update_scene(&mut scene);
+    let tool = viewer.additional_render.as_ref().unwrap();
+    assert!((tool.measurements()[0].value() - 4.0).abs() < 1e-5);
+}
@@ -0,0 +1,41 @@
+use graphics::Mesh;
+use moleucle_3dview_rs::convert::vec_to_na;
+use moleucle_3dview_rs::mesh::{flat_shaded, toon_shaded};
+use nalgebra::Vector3;
+
+#[test]
+fn test_flat_shaded_unshares_vertices() {
+    let sphere = Mesh::new_sphere(1.0, 2);
+    let triangles = sphere.indices.len() / 3;
+    let flat = flat_shaded(sphere);
+
+    assert_eq!(flat.indices.len(), triangles * 3);
+    assert_eq!(flat.vertices.len(), flat.indices.len());
+    for tri in flat.vertices.chunks_exact(3) {
+        let n = vec_to_na(tri[0].normal);
+        assert!((n.norm() - 1.0).abs() < 1e-4);
+        // Outward facing for a sphere centered at the origin.
+        assert!(n.dot(&Vector3::from(tri[0].position)) > 0.0);
+        for v in &tri[1..] {
+            assert!((vec_to_na(v.normal) - n).norm() < 1e-6);
+        }
+    }
+}
+
+#[test]
+fn test_toon_normals_are_banded() {
+    let light = Vector3::new(0.0, 1.0, 0.0);
+    let bands = 3;
+    let toon = toon_shaded(Mesh::new_sphere(1.0, 2), bands, light);
+
+    let band_width = std::f32::consts::PI / bands as f32;
+    for v in &toon.vertices {
+        let angle = vec_to_na(v.normal).dot(&light).clamp(-1.0, 1.0).acos();
+        let steps = angle / band_width - 0.5;
+        assert!(
+            (steps - steps.round()).abs() < 1e-3,
+            "angle {} not on a band",
+            angle
+        );
+    }
+}
@@ -0,0 +1,385 @@
+use moleucle_3dview_rs::molecule::{Atom, Bond};
+use moleucle_3dview_rs::{Format, LengthUnit, Molecule, MoleculeError};
+use nalgebra::Point3;
+use std::path::Path;
+
+/// The straightforward parser `from_mol2_str` replaced, kept to check that the
+/// faster one gives identical results.
+fn reference_parse(content: &str) -> Molecule {
+    let mut atoms = Vec::new();
+    let mut bonds = Vec::new();
+    let mut charges = Vec::new();
+    let mut residues = Vec::new();
+    let mut section = "";
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with("@<TRIPOS>") {
+            section = line;
+            continue;
+        }
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        match section {
+            "@<TRIPOS>ATOM" if parts.len() >= 6 => {
+                if let (Ok(x), Ok(y), Ok(z)) = (
+                    parts[2].parse::<f32>(),
+                    parts[3].parse::<f32>(),
+                    parts[4].parse::<f32>(),
+                ) {
+                    atoms.push(Atom {
+                        position: Point3::new(x, y, z),
+                        element: parts[5].split('.').next().unwrap_or("?").to_uppercase(),
+                        id: atoms.len() + 1,
+                    });
+                    charges.push(parts.get(8).and_then(|q| q.parse::<f32>().ok()));
+                    residues.push(parts.get(6).and_then(|r| r.parse::<usize>().ok()));
+                }
+            }
+            "@<TRIPOS>BOND" if parts.len() >= 4 => {
+                if let (Ok(a), Ok(b)) = (parts[1].parse::<usize>(), parts[2].parse::<usize>()) {
+                    let order = match parts[3] {
+                        "2" => 2,
+                        "3" => 3,
+                        "ar" => 0,
+                        _ => 1,
+                    };
+                    if a > 0 && b > 0 && a <= atoms.len() && b <= atoms.len() {
+                        bonds.push(Bond {
+                            atom_a: a - 1,
+                            atom_b: b - 1,
+                            order,
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    let molecule_line = |n| {
+        content
+            .lines()
+            .skip_while(|l| l.trim() != "@<TRIPOS>MOLECULE")
+            .nth(n)
+            .map(str::trim)
+    };
+    let no_charges = molecule_line(4) == Some("NO_CHARGES");
+    Molecule {
+        name: molecule_line(1)
+            .filter(|name| !name.is_empty() && *name != "****")
+            .map(str::to_string),
+        atoms,
+        bonds,
+        charges: charges
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .filter(|c| !c.is_empty() && !no_charges),
+        residues: residues
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .filter(|r| !r.is_empty()),
+        ..Default::default()
+    }
+}
+
+fn assert_same(content: &str) {
+    let mut parsed = Molecule::from_mol2_str(content).unwrap();
+    // Ids are fresh on every load.
+    assert_eq!(parsed.atom_ids.len(), parsed.atoms.len());
+    parsed.atom_ids = Default::default();
+    assert_eq!(
+        format!("{parsed:?}"),
+        format!("{:?}", reference_parse(content))
+    );
+}
+
+/// Records the parser must skip or normalize: short lines, bad numbers,
+/// lowercase and dotless types, out-of-range bonds, and no counts record.
+const ODD_MOL2: &str = "\
+@<TRIPOS>ATOM
+  1 C1   0.0 0.0 0.0 c.3
+  2 N1   1.4 0.0 0.0 N.am 1 RES 0.1
+  3 O1   x   0.0 0.0 O.2
+  4 Cl1  2.0 1.0 0.0 Cl
+  5 H1   3.0
+\t6 Fe  -1.0 -2.5e0 3 Fe.oh
+@<TRIPOS>SUBSTRUCTURE
+  1 RES 1
+@<TRIPOS>BOND
+  1 1 2 2
+  2 2 3 ar
+  3 3 9 1
+  4 0 1 1
+  5 4 5 3
+  6 5
+  7 a 1 1
+";
+
+#[test]
+fn test_mol2_matches_reference_parser() {
+    let benzene = std::fs::read_to_string(Path::new("Benzene.mol2")).unwrap();
+    assert_same(&benzene);
+    assert_same(ODD_MOL2);
+    assert_same("");
+    // A counts record larger than the file must not matter.
+    assert_same("@<TRIPOS>MOLECULE\nbig\n999999999999 999999999999\n@<TRIPOS>ATOM\n1 C 0 0 0 C\n");
+
+    let odd = Molecule::from_mol2_str(ODD_MOL2).unwrap();
+    let elements: Vec<_> = odd.atoms.iter().map(|a| a.element.as_str()).collect();
+    assert_eq!(elements, ["C", "N", "CL", "FE"]);
+    assert_eq!(odd.bonds.len(), 2);
+    // Aromatic bonds keep order 0.
+    assert_eq!(odd.bonds[1].order, 0);
+}
+
+/// One atom per element inference stage: SYBYL types, force-field and dummy
+/// types that fall back to the name, CA with and without a residue, a missing
+/// type column, and an atom nothing identifies.
+const JUNK_TYPES_MOL2: &str = "\
+@<TRIPOS>ATOM
+  1 C1    0.0 0.0 0.0 C.ar 1 LIG1 0.0
+  2 CL2   1.0 0.0 0.0 Cl   1 LIG1 0.0
+  3 C3    2.0 0.0 0.0 CT   1 LIG1 0.0
+  4 CL4   3.0 0.0 0.0 Du   1 LIG1 0.0
+  5 CA    4.0 0.0 0.0 CA   2 ALA2 0.0
+  6 HG21  5.0 0.0 0.0 HC   3 THR3 0.0
+  7 CA    6.0 0.0 0.0 CA   4 CA4  2.0
+  8 CA    7.0 0.0 0.0 Du
+  9 1HB   8.0 0.0 0.0
+ 10 XX9   9.0 0.0 0.0 Du   5 LIG1 0.0
+ 11 O1'  10.0 0.0 0.0 o    5 LIG1 0.0
+";
+
+#[test]
+fn test_mol2_element_inference() {
+    let mol = Molecule::from_mol2_str(JUNK_TYPES_MOL2).unwrap();
+    let elements: Vec<_> = mol.atoms.iter().map(|a| a.element.as_str()).collect();
+    assert_eq!(
+        elements,
+        ["C", "CL", "C", "CL", "C", "H", "CA", "CA", "H", "X", "O"]
+    );
+    assert_eq!(mol.warnings.len(), 1);
+    assert!(
+        mol.warnings[0].starts_with("mol2 atom 10:"),
+        "{:?}",
+        mol.warnings
+    );
+    // Missing columns drop charges and residues but keep the atom.
+    assert_eq!(mol.atoms.len(), 11);
+    assert_eq!(mol.residues, None);
+
+    let benzene = Molecule::from_mol2(Path::new("Benzene.mol2")).unwrap();
+    assert!(benzene.warnings.is_empty());
+}
+
+#[test]
+fn test_mol2_ring_nitrogens_are_not_metals() {
+    // Heme and nucleotide ring nitrogens under force-field types, beside a
+    // sodium ion in its own residue.
+    let mol = Molecule::from_mol2_str(
+        "@<TRIPOS>ATOM
+  1 NA    0.0 0.0 0.0 NR   1 HEM1 0.0
+  2 NB    1.0 0.0 0.0 NR   1 HEM1 0.0
+  3 NA    2.0 0.0 0.0 Du   2 NA2  1.0
+",
+    )
+    .unwrap();
+    let elements: Vec<_> = mol.atoms.iter().map(|a| a.element.as_str()).collect();
+    assert_eq!(elements, ["N", "N", "NA"]);
+}
+
+#[test]
+fn test_mol2_coordinates_parse_exactly() {
+    // Coordinates in the formats writers use, plus ones that need the slow
+    // path: long mantissas, many decimals, exponents and a leading '+'.
+    let mut mol2 = String::from("@<TRIPOS>ATOM\n");
+    let mut state = 12345u64;
+    for i in 0..3000 {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let v = (state >> 33) as f64 / (1u64 << 31) as f64 * 400.0 - 200.0;
+        let x = match i % 6 {
+            0 => format!("{v:.4}"),
+            1 => format!("{v:.3}"),
+            2 => format!("{v:.9}"),
+            3 => format!("{:.12}", v / 1000.0),
+            4 => format!("{v:e}"),
+            _ => format!("+{:.2}", v.abs()),
+        };
+        mol2.push_str(&format!("{} C {x} {x} -0.0000 C.3\n", i + 1));
+    }
+    assert_same(&mol2);
+}
+
+#[test]
+fn test_mol2_charges_and_residues() {
+    let header = |charge_type: &str| {
+        format!("@<TRIPOS>MOLECULE\nm\n2 0\nSMALL\n{charge_type}\n\n@<TRIPOS>ATOM\n")
+    };
+    let atoms = "1 O 0 0 0 O.3 1 HOH -0.834\n2 H 1 0 0 H 2 HOH 0.417\n";
+    let mol = Molecule::from_mol2_str(&(header("USER_CHARGES") + atoms)).unwrap();
+    assert_eq!(mol.charges, Some(vec![-0.834, 0.417]));
+    assert_eq!(mol.residues, Some(vec![1, 2]));
+    assert_same(&(header("USER_CHARGES") + atoms));
+
+    let mol = Molecule::from_mol2_str(&(header("NO_CHARGES") + atoms)).unwrap();
+    assert_eq!(mol.charges, None);
+    assert_eq!(mol.residues, Some(vec![1, 2]));
+    assert_same(&(header("NO_CHARGES") + atoms));
+
+    // One atom without a charge drops the column.
+    let partial = "1 O 0 0 0 O.3 1 HOH -0.834\n2 H 1 0 0 H 1 HOH\n";
+    let mol = Molecule::from_mol2_str(&(header("USER_CHARGES") + partial)).unwrap();
+    assert_eq!(mol.charges, None);
+    assert_eq!(mol.residues, Some(vec![1, 1]));
+    assert_same(&(header("USER_CHARGES") + partial));
+
+    let benzene = Molecule::from_mol2(Path::new("Benzene.mol2")).unwrap();
+    assert_eq!(benzene.charges, Some(vec![0.0; 12]));
+}
+
+#[test]
+fn test_from_mol2_reads_file() {
+    let mol = Molecule::from_mol2(Path::new("Benzene.mol2")).unwrap();
+    assert_eq!((mol.atoms.len(), mol.bonds.len()), (12, 12));
+    assert!(Molecule::from_mol2(Path::new("missing.mol2")).is_err());
+}
+
+#[test]
+fn test_from_file_and_formula() {
+    let benzene = Molecule::from_file(Path::new("Benzene.mol2")).unwrap();
+    assert_eq!(benzene.formula(), "C6H6");
+    let weight = benzene.molecular_weight().unwrap();
+    assert!((weight - 78.114).abs() < 1e-3, "{weight}");
+
+    let err = Molecule::from_file(Path::new("Benzene.cif")).unwrap_err();
+    assert!(matches!(err, MoleculeError::UnsupportedFormat(_)), "{err}");
+    let err = Molecule::from_file(Path::new("missing.XYZ")).unwrap_err();
+    assert!(matches!(err, MoleculeError::Io(_)), "{err}");
+    // Callers reporting strings can keep using `?`.
+    let as_string = || -> Result<Molecule, String> { Ok(Molecule::from_xyz_str("x")?) };
+    assert_eq!(as_string().unwrap_err(), "xyz: missing atom count (line 1)");
+
+    // No carbon: alphabetical, periodic-table spelling.
+    let salt = Molecule::from_xyz_str("3\n\nCL 0 0 0\nNa 2 0 0\nO 4 0 0\n").unwrap();
+    assert_eq!(salt.formula(), "ClNaO");
+    let odd = Molecule::from_xyz_str("1\n\nQq 0 0 0\n").unwrap();
+    assert_eq!((odd.formula(), odd.molecular_weight()), ("QQ".into(), None));
+}
+
+#[test]
+fn test_from_reader_parses_embedded_mol2() {
+    const BENZENE: &str = include_str!("../Benzene.mol2");
+    let from_file = Molecule::from_mol2(Path::new("Benzene.mol2")).unwrap();
+    let from_str = Molecule::from_mol2_str(BENZENE).unwrap();
+    let from_reader = Molecule::from_reader(BENZENE.as_bytes(), Format::Mol2).unwrap();
+    for mol in [&from_str, &from_reader] {
+        assert_eq!(mol.atoms.len(), from_file.atoms.len());
+        assert_eq!(mol.bonds.len(), from_file.bonds.len());
+        for (a, b) in mol.atoms.iter().zip(&from_file.atoms) {
+            assert_eq!((a.position, &a.element), (b.position, &b.element));
+        }
+    }
+
+    assert_eq!(Format::from_extension("MOL"), Some(Format::Sdf));
+    assert_eq!(Format::from_extension("cif"), None);
+    let err = Molecule::from_reader(&[0xff, 0xfe][..], Format::Xyz).unwrap_err();
+    assert!(err.to_string().contains("UTF-8"), "{err}");
+}
+
+/// Parses `mol` written as mol2 and checks that the atoms are in the same
+/// places, to 3 decimals, and bonded the same way. Returns the parsed copy.
+fn assert_round_trip(mol: &Molecule) -> Molecule {
+    let parsed = Molecule::from_mol2_str(&mol.to_mol2_string()).unwrap();
+    assert_eq!(parsed.atoms.len(), mol.atoms.len());
+    for (i, (a, b)) in parsed.atoms.iter().zip(&mol.atoms).enumerate() {
+        let b = b.position * mol.metadata.unit.in_angstrom();
+        assert!(
+            (a.position - b).abs().max() < 5e-4,
+            "atom {i}: {} vs {b}",
+            a.position
+        );
+    }
+    let bonds = |mol: &Molecule| -> Vec<_> {
+        mol.bonds
+            .iter()
+            .map(|b| (b.atom_a, b.atom_b, b.order))
+            .collect()
+    };
+    assert_eq!(bonds(&parsed), bonds(mol));
+    parsed
+}
+
+#[test]
+fn test_mol2_round_trip() {
+    let benzene = Molecule::from_mol2(Path::new("Benzene.mol2")).unwrap();
+    let parsed = assert_round_trip(&benzene);
+    assert_eq!(parsed.charges, benzene.charges);
+    assert_eq!(parsed.residues, benzene.residues);
+    // Written again, it is the same file.
+    assert_eq!(parsed.to_mol2_string(), benzene.to_mol2_string());
+
+    // Atoms are numbered per element; the name is kept when known.
+    let written = benzene.to_mol2_string();
+    assert!(
+        written.starts_with("@<TRIPOS>MOLECULE\n****\n"),
+        "{written}"
+    );
+    for name in ["C1", "C6", "H1", "H6"] {
+        assert!(written.contains(&format!(" {name} ")), "{written}");
+    }
+    assert!(!written.contains(" C7 ") && !written.contains(" H7 "));
+    assert_eq!(parsed.name, None);
+    let named = Molecule {
+        name: Some("benzene".to_string()),
+        ..benzene.clone()
+    };
+    let written = named.to_mol2_string();
+    assert!(
+        written.starts_with("@<TRIPOS>MOLECULE\nbenzene\n"),
+        "{written}"
+    );
+    assert_eq!(assert_round_trip(&named).name.as_deref(), Some("benzene"));
+
+    // Elements stored in capitals are typed as SYBYL spells them, and an
+    // unknown one as stored; aromatic bonds stay aromatic.
+    let odd = Molecule::from_mol2_str(ODD_MOL2).unwrap();
+    let written = odd.to_mol2_string();
+    assert!(written.contains(" Cl "), "{written}");
+    let elements =
+        |mol: &Molecule| -> Vec<String> { mol.atoms.iter().map(|a| a.element.clone()).collect() };
+    assert_eq!(elements(&assert_round_trip(&odd)), ["C", "N", "CL", "FE"]);
+    let junk = Molecule::from_mol2_str(JUNK_TYPES_MOL2).unwrap();
+    assert_eq!(elements(&assert_round_trip(&junk)), elements(&junk));
+
+    // Without charges, the file says so.
+    let mut uncharged = benzene.clone();
+    uncharged.charges = None;
+    assert!(uncharged.to_mol2_string().contains("\nNO_CHARGES\n"));
+    assert_eq!(assert_round_trip(&uncharged).charges, None);
+}
+
+#[test]
+fn test_mol2_round_trip_keeps_residues_and_units() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/1crn_thr1.pdb");
+    let mut protein = Molecule::from_pdb(&path).unwrap();
+    protein.charges = Some((0..protein.atoms.len()).map(|i| i as f32 / 100.0).collect());
+    let parsed = assert_round_trip(&protein);
+    assert_eq!(parsed.residues, protein.residues);
+    assert_eq!(parsed.charges, protein.charges);
+
+    // Coordinates are written in Å.
+    let mut in_nm = protein.clone();
+    in_nm.convert_units(LengthUnit::Nanometer);
+    assert_round_trip(&in_nm);
+
+    let file = std::env::temp_dir().join(format!("crambin_{}.mol2", std::process::id()));
+    protein.write_mol2(&file).unwrap();
+    let from_file = Molecule::from_mol2(&file).unwrap();
+    std::fs::remove_file(&file).unwrap();
+    assert_eq!(from_file.to_mol2_string(), parsed.to_mol2_string());
+    let missing_dir = std::env::temp_dir().join("no such dir").join("x.mol2");
+    assert!(protein.write_mol2(&missing_dir).is_err());
+}
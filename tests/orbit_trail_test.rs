@@ -0,0 +1,77 @@
+use graphics::Scene;
+use moleucle_3dview_rs::controller::ORBIT_TRAIL_LINGER;
+use moleucle_3dview_rs::molecule::Atom;
+use moleucle_3dview_rs::{
+    Camera, CameraController, DragMode, Molecule, MoleculeViewer, OrbitTrailRender, OrbitalCamera,
+};
+use nalgebra::{Point3, Vector2};
+
+#[test]
+fn test_controller_tracks_orbit_trail() {
+    let mut controller = CameraController::<OrbitalCamera>::new();
+    assert!(controller.orbit_trail().is_none());
+    controller.camera_drag(Vector2::new(0.0, 10.0), DragMode::Pan);
+    assert!(controller.orbit_trail().is_none());
+
+    // 0.005 rad per pixel, in two drags that make one trail.
+    controller.camera_drag(Vector2::new(60.0, 0.0), DragMode::Orbit);
+    controller.camera_drag(Vector2::new(40.0, 0.0), DragMode::Orbit);
+    let trail = controller.orbit_trail().unwrap();
+    assert!((trail.angle() - 0.5).abs() < 1e-4, "{}", trail.angle());
+    assert_eq!(trail.pivot, controller.camera.target());
+    assert!((trail.distance - controller.camera.radius).abs() < 1e-5);
+    assert_eq!(trail.fade, 1.0);
+
+    // With no button held it fades out over `ORBIT_TRAIL_LINGER`.
+    controller.tick(ORBIT_TRAIL_LINGER * 0.5);
+    assert!((controller.orbit_trail().unwrap().fade - 0.5).abs() < 1e-5);
+    controller.tick(ORBIT_TRAIL_LINGER * 0.6);
+    assert!(controller.orbit_trail().is_none());
+
+    // A later orbit starts from where the camera is now.
+    controller.camera_drag(Vector2::new(20.0, 0.0), DragMode::Orbit);
+    assert!((controller.orbit_trail().unwrap().angle() - 0.1).abs() < 1e-4);
+}
+
+#[test]
+fn test_trail_render_refreshes_without_rebuild() {
+    let mut viewer = MoleculeViewer::<OrbitTrailRender>::new();
+    viewer.set_molecule(Molecule {
+        atoms: vec![Atom {
+            position: Point3::origin(),
+            element: "C".to_string(),
+            id: 0,
+        }],
+        ..Default::default()
+    });
+    viewer.additional_render = Some(Box::new(OrbitTrailRender::new()));
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    let (molecule_entities, meshes) = (scene.entities.len(), scene.meshes.len());
+
+    let mut controller = CameraController::<OrbitalCamera>::new();
+    // 40° of orbit: 8 arc segments and ticks at 0°, 15° and 30°.
+    controller.camera_drag(
+        Vector2::new(40f32.to_radians() / 0.005, 0.0),
+        DragMode::Orbit,
+    );
+    let overlay = viewer.additional_render.as_mut().unwrap();
+    overlay.trail = controller.orbit_trail();
+    assert!(viewer.refresh_overlays(&mut scene));
+    assert_eq!(scene.entities.len(), molecule_entities + 8 + 3);
+    assert_eq!(scene.meshes.len(), meshes);
+    let arc = &scene.entities[molecule_entities];
+    assert!((arc.opacity - 0.35).abs() < 1e-6);
+
+    controller.tick(ORBIT_TRAIL_LINGER * 0.5);
+    let overlay = viewer.additional_render.as_mut().unwrap();
+    overlay.trail = controller.orbit_trail();
+    viewer.refresh_overlays(&mut scene);
+    assert!((scene.entities[molecule_entities].opacity - 0.175).abs() < 1e-5);
+
+    controller.tick(ORBIT_TRAIL_LINGER);
+    let overlay = viewer.additional_render.as_mut().unwrap();
+    overlay.trail = controller.orbit_trail();
+    viewer.refresh_overlays(&mut scene);
+    assert_eq!(scene.entities.len(), molecule_entities);
+}
@@ -0,0 +1,124 @@
+use moleucle_3dview_rs::{Molecule, MoleculeError, ParseOptions};
+use nalgebra::Point3;
+use std::path::Path;
+
+// The first residue of crambin (1CRN), with an alternate location, a
+// hydrogen, and a calcium ion added, none with an element column.
+const CRAMBIN: &str = "\
+HEADER    PLANT PROTEIN                           30-APR-81   1CRN
+MODEL        1
+ATOM      1  N   THR A   1      17.047  14.099   3.625  1.00 10.00           N
+ATOM      2  CA  THR A   1      16.967  12.784   4.338  1.00 10.00           C
+ATOM      3  C   THR A   1      15.685  12.755   5.133  1.00 10.00           C
+ATOM      4  O   THR A   1      15.268  13.825   5.594  1.00 10.00           O
+ATOM      5  CB ATHR A   1      18.170  12.703   5.337  1.00 10.00
+ATOM      6  CB BTHR A   1      18.200  12.650   5.300  1.00 10.00
+ATOM      7 HG21 THR A   1      18.900  11.300   6.800  1.00 10.00
+HETATM    8 CA    CA B 101      10.000  10.000  10.000  1.00 10.00
+CONECT    1    2
+CONECT    2    1    3    5
+CONECT    3    2    4
+CONECT    4    3
+ENDMDL
+MODEL        2
+ATOM      1  N   THR A   1       0.000   0.000   0.000  1.00 10.00           N
+ENDMDL
+END
+";
+
+#[test]
+fn test_pdb_atoms_of_first_model() {
+    let mol = Molecule::from_pdb_str(CRAMBIN).unwrap();
+    assert!(mol.warnings.is_empty(), "{:?}", mol.warnings);
+    let elements: Vec<&str> = mol.atoms.iter().map(|a| a.element.as_str()).collect();
+    // The alpha carbon is carbon; the ion in its own residue is calcium.
+    assert_eq!(elements, ["N", "C", "C", "O", "C", "H", "CA"]);
+    let ids: Vec<usize> = mol.atoms.iter().map(|a| a.id).collect();
+    assert_eq!(ids, [1, 2, 3, 4, 5, 7, 8]);
+    assert_eq!(mol.atoms[4].position, Point3::new(18.17, 12.703, 5.337));
+    assert_eq!(mol.residues, Some(vec![1, 1, 1, 1, 1, 1, 101]));
+    assert_eq!(mol.chains, Some(vec!['A', 'A', 'A', 'A', 'A', 'A', 'B']));
+    let names = mol.residue_names.as_deref().unwrap();
+    assert_eq!(names[..6], ["THR"; 6]);
+    assert_eq!(names[6], "CA");
+    assert_eq!(mol.formula(), "C3HCaNO");
+}
+
+#[test]
+fn test_pdb_bonds_from_conect() {
+    let mol = Molecule::from_pdb_str(CRAMBIN).unwrap();
+    let bonds: Vec<(usize, usize, u8)> = mol
+        .bonds
+        .iter()
+        .map(|b| (b.atom_a, b.atom_b, b.order))
+        .collect();
+    // Each listed from both ends, but kept once.
+    assert_eq!(bonds, [(0, 1, 1), (1, 2, 1), (1, 4, 1), (2, 3, 1)]);
+
+    let no_conect: String = CRAMBIN
+        .lines()
+        .filter(|l| !l.starts_with("CONECT"))
+        .map(|l| format!("{l}\n"))
+        .collect();
+    let mol = Molecule::from_pdb_str(&no_conect).unwrap();
+    assert_eq!((mol.atoms.len(), mol.bonds.len()), (7, 0));
+
+    // In a multi-model file the CONECT records come after the last model.
+    let (conect, rest): (Vec<&str>, Vec<&str>) =
+        CRAMBIN.lines().partition(|l| l.starts_with("CONECT"));
+    let end = rest.len() - 1;
+    let two_models = [&rest[..end], &conect, &rest[end..]].concat().join("\n");
+    assert!(two_models.contains("ENDMDL\nCONECT    1    2\n"));
+    let mol = Molecule::from_pdb_str(&two_models).unwrap();
+    assert_eq!(mol.atoms.len(), 7);
+    let bonds: Vec<(usize, usize)> = mol.bonds.iter().map(|b| (b.atom_a, b.atom_b)).collect();
+    assert_eq!(bonds, [(0, 1), (1, 2), (1, 4), (2, 3)]);
+}
+
+#[test]
+fn test_pdb_malformed_and_unknown() {
+    let broken = CRAMBIN.replace("  4.338", "   four");
+    let err = Molecule::from_pdb_str(&broken).unwrap_err();
+    assert!(
+        matches!(err, MoleculeError::InvalidAtomRecord { line: 4, .. }),
+        "{err}"
+    );
+
+    let commas = CRAMBIN.replace("10.000  10.000  10.000", "10,000  10,000  10,000");
+    let mol = Molecule::from_pdb_str(&commas).unwrap();
+    assert_eq!(mol.atoms[6].position, Point3::new(10.0, 10.0, 10.0));
+    assert_eq!(mol.warnings, ["pdb: read 3 numbers with a decimal comma"]);
+    let refuse = ParseOptions {
+        decimal_comma: false,
+    };
+    assert!(Molecule::from_pdb_str_with(&commas, refuse).is_err());
+
+    let unknown = "HETATM    1 QQ1  UNL     1       0.000   0.000   0.000\n";
+    let mol = Molecule::from_pdb_str(unknown).unwrap();
+    assert_eq!(mol.atoms[0].element, "X");
+    assert_eq!(mol.warnings.len(), 1);
+    assert!(mol.chains.is_none());
+}
+
+/// `1crn_thr1.pdb` is a small fixture of its own: the first residue of
+/// crambin (1CRN), cut from the entry. No other fixture was shortened for
+/// it. The full entry is not in the tree yet; it belongs beside this one as
+/// `1crn.pdb`, not in its place.
+#[test]
+fn test_pdb_fixture() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/1crn_thr1.pdb");
+    let mol = Molecule::from_pdb(&path).unwrap();
+    assert_eq!(mol.atoms.len(), 7);
+    assert_eq!(mol.formula(), "C4NO2");
+    assert!(mol.bonds.is_empty() && mol.warnings.is_empty());
+    assert_eq!(mol.atoms[6].position, Point3::new(18.15, 11.546, 6.304));
+    assert_eq!(mol.residue_names, Some(vec!["THR".to_string(); 7]));
+    assert_eq!(mol.chains, Some(vec!['A'; 7]));
+
+    // Removing atoms keeps the per-atom columns in step.
+    let mut side_chain = Molecule::from_file(&path).unwrap();
+    side_chain.remove_atoms(&[0, 1, 2, 3]);
+    assert_eq!(side_chain.residue_names.unwrap().len(), 3);
+    assert_eq!(side_chain.chains.unwrap().len(), 3);
+    assert_eq!(side_chain.residues, Some(vec![1; 3]));
+}
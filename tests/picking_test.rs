@@ -0,0 +1,288 @@
+use moleucle_3dview_rs::picking::{
+    ray_aabb, ray_capsule, ray_cylinder, ray_plane, ray_sphere, ray_triangle, Hit,
+};
+use moleucle_3dview_rs::spatial::RayGrid;
+use nalgebra::{Point3, Vector3};
+
+/// Small deterministic generator, so failures reproduce.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> f32 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn range(&mut self, lo: f32, hi: f32) -> f32 {
+        lo + (hi - lo) * self.next()
+    }
+
+    fn point(&mut self, extent: f32) -> Point3<f32> {
+        Point3::new(
+            self.range(-extent, extent),
+            self.range(-extent, extent),
+            self.range(-extent, extent),
+        )
+    }
+}
+
+const STEP: f32 = 2e-3;
+const MAX_T: f32 = 12.0;
+
+/// First sign change of `sdf` along the ray, found by marching, plus the
+/// smallest |sdf| seen (to recognize grazing rays the march can miss).
+fn march(
+    sdf: &dyn Fn(Point3<f32>) -> f32,
+    origin: Point3<f32>,
+    dir: Vector3<f32>,
+) -> (Option<f32>, f32) {
+    let inside = sdf(origin) < 0.0;
+    let mut closest = f32::MAX;
+    let mut t = 0.0;
+    while t < MAX_T {
+        let d = sdf(origin + dir * t);
+        closest = closest.min(d.abs());
+        if (d < 0.0) != inside {
+            return (Some(t), closest);
+        }
+        t += STEP;
+    }
+    (None, closest)
+}
+
+fn gradient(sdf: &dyn Fn(Point3<f32>) -> f32, p: Point3<f32>) -> Vector3<f32> {
+    let h = 1e-3;
+    let d = |v: Vector3<f32>| sdf(p + v * h) - sdf(p - v * h);
+    Vector3::new(d(Vector3::x()), d(Vector3::y()), d(Vector3::z())).normalize()
+}
+
+/// Compares `intersect` with marching through `sdf` for random rays, some of
+/// which start inside the solid.
+fn check_against_sdf(
+    name: &str,
+    sdf: &dyn Fn(Point3<f32>) -> f32,
+    intersect: &dyn Fn(Point3<f32>, Vector3<f32>) -> Option<Hit>,
+) {
+    let mut rng = Lcg(0x5eed ^ name.len() as u64);
+    let (mut hits, mut inside) = (0, 0);
+    for i in 0..300 {
+        // Every shape contains the region around the world origin, so every
+        // fifth ray starts there, inside the solid.
+        let origin = rng.point(if i % 5 == 0 { 0.3 } else { 4.0 });
+        // Aim near the origin so most rays hit.
+        let dir = (rng.point(1.5) - origin).normalize();
+        if sdf(origin).abs() < 0.01 {
+            continue;
+        }
+        let (marched, closest) = march(sdf, origin, dir);
+        match (intersect(origin, dir), marched) {
+            (Some(hit), Some(t)) => {
+                assert!(
+                    (hit.t - t).abs() < 2.0 * STEP,
+                    "{name}: t {} vs marched {t} for {origin:?} {dir:?}",
+                    hit.t
+                );
+                assert!((hit.point - (origin + dir * hit.t)).norm() < 1e-4);
+                assert!((hit.normal.norm() - 1.0).abs() < 1e-4);
+                // Loose bound: at edges (cylinder rims, box corners) the
+                // gradient is a blend of the faces meeting there.
+                assert!(
+                    hit.normal.dot(&gradient(sdf, hit.point)) > 0.5,
+                    "{name}: normal {:?} at {:?}",
+                    hit.normal,
+                    hit.point
+                );
+                hits += 1;
+                inside += usize::from(sdf(origin) < 0.0);
+            }
+            (None, None) => {}
+            (hit, marched) => assert!(
+                closest < 0.01,
+                "{name}: {hit:?} but marched {marched:?} for {origin:?} {dir:?}"
+            ),
+        }
+    }
+    assert!(hits > 50, "{name}: only {hits} hits");
+    assert!(inside > 0, "{name}: no rays from inside");
+}
+
+fn segment_distance(p: Point3<f32>, a: Point3<f32>, b: Point3<f32>) -> f32 {
+    let ab = b - a;
+    let s = ((p - a).dot(&ab) / ab.norm_squared()).clamp(0.0, 1.0);
+    (p - (a + ab * s)).norm()
+}
+
+#[test]
+fn test_ray_sphere_matches_marching() {
+    let center = Point3::new(0.3, -0.2, 0.1);
+    check_against_sdf("sphere", &|p| (p - center).norm() - 1.5, &|o, d| {
+        ray_sphere(o, d, center, 1.5)
+    });
+}
+
+#[test]
+fn test_ray_capsule_matches_marching() {
+    let (a, b) = (Point3::new(-1.0, -0.5, 0.0), Point3::new(1.0, 0.8, 0.3));
+    check_against_sdf("capsule", &|p| segment_distance(p, a, b) - 0.8, &|o, d| {
+        ray_capsule(o, d, a, b, 0.8)
+    });
+}
+
+#[test]
+fn test_ray_cylinder_matches_marching() {
+    let (a, b) = (Point3::new(-1.2, 0.0, -0.3), Point3::new(1.0, 0.5, 0.4));
+    let axis = (b - a).normalize();
+    let length = (b - a).norm();
+    let radius = 0.9;
+    let sdf = move |p: Point3<f32>| {
+        let v = p - a;
+        let h = v.dot(&axis);
+        let radial = (v - axis * h).norm() - radius;
+        let along = (h - length * 0.5).abs() - length * 0.5;
+        let outside = Vector3::new(radial.max(0.0), along.max(0.0), 0.0).norm();
+        outside + radial.max(along).min(0.0)
+    };
+    check_against_sdf("cylinder", &sdf, &|o, d| ray_cylinder(o, d, a, b, radius));
+}
+
+#[test]
+fn test_ray_aabb_matches_marching() {
+    let (min, max) = (Point3::new(-1.0, -0.7, -1.3), Point3::new(1.2, 0.9, 0.8));
+    let center = nalgebra::center(&min, &max);
+    let half = (max - min) * 0.5;
+    let sdf = move |p: Point3<f32>| {
+        let q = (p - center).abs() - half;
+        q.map(|x| x.max(0.0)).norm() + q.max().min(0.0)
+    };
+    check_against_sdf("aabb", &sdf, &|o, d| ray_aabb(o, d, min, max));
+}
+
+#[test]
+fn test_ray_triangle_against_plane_and_areas() {
+    let (v0, v1, v2) = (
+        Point3::new(-1.0, -1.0, 0.2),
+        Point3::new(1.5, -0.5, -0.3),
+        Point3::new(0.0, 1.2, 0.4),
+    );
+    let area = |a: Point3<f32>, b: Point3<f32>, c: Point3<f32>| (b - a).cross(&(c - a)).norm();
+    let total = area(v0, v1, v2);
+    let normal = (v1 - v0).cross(&(v2 - v0)).normalize();
+
+    let mut rng = Lcg(42);
+    let mut hits = 0;
+    for _ in 0..500 {
+        let origin = rng.point(4.0);
+        let dir = (rng.point(1.5) - origin).normalize();
+        // Independent check: where the ray meets the plane, is the point inside
+        // (sub-triangle areas add up to the whole)?
+        let t = (v0 - origin).dot(&normal) / dir.dot(&normal);
+        let p = origin + dir * t;
+        let parts = area(p, v1, v2) + area(v0, p, v2) + area(v0, v1, p);
+        let expected = t >= 0.0 && (parts - total).abs() < 1e-4 * total;
+        let near_edge = (parts - total).abs() < 1e-2 * total;
+
+        match ray_triangle(origin, dir, v0, v1, v2) {
+            Some(hit) => {
+                assert!(expected || near_edge, "unexpected hit {hit:?}");
+                assert!((hit.t - t).abs() < 1e-3);
+                assert!(hit.normal.dot(&dir) < 0.0, "normal faces the ray");
+                hits += 1;
+            }
+            None => assert!(!expected || near_edge, "missed at t {t} for {origin:?}"),
+        }
+    }
+    assert!(hits > 20, "only {hits} hits");
+}
+
+#[test]
+fn test_hits_from_inside_and_behind() {
+    let dir = Vector3::new(0.0, 0.0, -1.0);
+    // From the center, the sphere is hit on the way out.
+    let hit = ray_sphere(Point3::origin(), dir, Point3::origin(), 1.0).unwrap();
+    assert!((hit.t - 1.0).abs() < 1e-6);
+    assert!((hit.normal - dir).norm() < 1e-6);
+    // Spheres behind the origin are not hit.
+    assert!(ray_sphere(Point3::new(0.0, 0.0, -5.0), dir, Point3::origin(), 1.0).is_none());
+
+    // A ray along the axis of a cylinder hits its flat cap, and a capsule's
+    // rounded end one radius further out.
+    let (a, b) = (Point3::new(0.0, 0.0, -1.0), Point3::new(0.0, 0.0, 1.0));
+    let origin = Point3::new(0.0, 0.0, 5.0);
+    let cap = ray_cylinder(origin, dir, a, b, 0.5).unwrap();
+    assert!((cap.t - 4.0).abs() < 1e-5);
+    assert!((cap.normal - Vector3::z()).norm() < 1e-6);
+    let rounded = ray_capsule(origin, dir, a, b, 0.5).unwrap();
+    assert!((rounded.t - 3.5).abs() < 1e-5);
+}
+
+#[test]
+fn test_ray_plane_from_both_sides() {
+    let point = Point3::new(0.0, 0.0, 2.0);
+    let normal = Vector3::new(0.0, 0.0, 3.0);
+    let hit = ray_plane(
+        Point3::new(1.0, 1.0, 0.0),
+        Vector3::new(0.0, 0.0, 2.0),
+        point,
+        normal,
+    )
+    .unwrap();
+    assert_eq!((hit.t, hit.point), (1.0, Point3::new(1.0, 1.0, 2.0)));
+    assert_eq!(hit.normal, -Vector3::z());
+    let back = ray_plane(Point3::new(0.0, 0.0, 5.0), -Vector3::z(), point, normal).unwrap();
+    assert_eq!((back.t, back.normal), (3.0, Vector3::z()));
+    // Behind the origin, or parallel.
+    assert!(ray_plane(Point3::origin(), -Vector3::z(), point, normal).is_none());
+    assert!(ray_plane(Point3::origin(), Vector3::x(), point, normal).is_none());
+}
+
+#[test]
+fn test_ray_grid_matches_brute_force() {
+    let mut rng = Lcg(11);
+    let spheres: Vec<(Point3<f32>, f32)> = (0..300)
+        .map(|_| (rng.point(10.0), rng.range(0.2, 1.5)))
+        .collect();
+    let boxes: Vec<_> = spheres
+        .iter()
+        .map(|&(c, r)| (c - Vector3::repeat(r), c + Vector3::repeat(r)))
+        .collect();
+    let grid = RayGrid::new(&boxes, 2.0);
+    for k in 0..500 {
+        let origin = if k % 2 == 0 {
+            rng.point(30.0)
+        } else {
+            // From inside the grid.
+            rng.point(8.0)
+        };
+        let mut dir = rng.point(10.0) - origin;
+        if k % 5 == 0 {
+            // Along an axis, and so parallel to two sets of cell walls.
+            let axis = dir.iamax();
+            dir = Vector3::ith(axis, dir[axis]);
+        }
+        let hit = |i: usize| ray_sphere(origin, dir, spheres[i].0, spheres[i].1).map(|h| h.t);
+        let brute_force = (0..spheres.len())
+            .filter_map(|i| Some((i, hit(i)?)))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        assert_eq!(grid.cast(origin, dir, hit), brute_force, "ray {k}");
+        let along = grid.along(origin, dir);
+        assert!(along.windows(2).all(|w| w[0] < w[1]));
+        let missed =
+            (0..spheres.len()).filter(|i| hit(*i).is_some() && along.binary_search(i).is_err());
+        assert_eq!(missed.count(), 0, "ray {k}");
+
+        // Everything within `reach` of the ray, even outside the grid.
+        let reach = rng.range(0.0, 3.0);
+        let near = grid.near(origin, dir, reach);
+        let from_ray = |c: Point3<f32>| {
+            let t = ((c - origin).dot(&dir) / dir.norm_squared()).max(0.0);
+            (c - (origin + dir * t)).norm()
+        };
+        let missed = (0..spheres.len()).filter(|&i| {
+            from_ray(spheres[i].0) - spheres[i].1 <= reach && near.binary_search(&i).is_err()
+        });
+        assert_eq!(missed.count(), 0, "ray {k}");
+    }
+}
@@ -0,0 +1,81 @@
+use graphics::Scene;
+use moleucle_3dview_rs::controller::PIVOT_INDICATOR_LINGER;
+use moleucle_3dview_rs::molecule::Atom;
+use moleucle_3dview_rs::{
+    Camera, CameraController, DragMode, ManualClock, Molecule, MoleculeViewer, OrbitalCamera,
+    PivotRender, PivotVisibility,
+};
+use nalgebra::{Point3, Vector2};
+use std::time::Duration;
+
+fn linger(fraction: f32) -> Duration {
+    Duration::from_secs_f32(PIVOT_INDICATOR_LINGER * fraction)
+}
+
+#[test]
+fn test_pivot_indicator_fades_after_orbiting() {
+    let clock = ManualClock::new();
+    let mut controller = CameraController::<OrbitalCamera>::new();
+    controller.set_clock(clock.clone());
+    assert!(controller.pivot_indicator().is_none());
+    controller.camera_drag(Vector2::new(0.0, 10.0), DragMode::Pan);
+    assert!(controller.pivot_indicator().is_none());
+
+    controller.camera_drag(Vector2::new(50.0, 0.0), DragMode::Orbit);
+    let indicator = controller.pivot_indicator().unwrap();
+    assert_eq!(indicator.pivot, controller.camera.target());
+    assert!((indicator.distance - controller.camera.radius).abs() < 1e-5);
+    assert_eq!(indicator.fade, 1.0);
+
+    clock.advance(linger(0.5));
+    let fade = controller.pivot_indicator().unwrap().fade;
+    assert!((fade - 0.5).abs() < 1e-4, "{fade}");
+    clock.advance(linger(0.6));
+    assert!(controller.pivot_indicator().is_none());
+
+    controller.settings.pivot_indicator = PivotVisibility::Always;
+    assert_eq!(controller.pivot_indicator().unwrap().fade, 1.0);
+    controller.settings.pivot_indicator = PivotVisibility::Off;
+    controller.camera_drag(Vector2::new(50.0, 0.0), DragMode::Orbit);
+    assert!(controller.pivot_indicator().is_none());
+}
+
+#[test]
+fn test_pivot_render_refreshes_without_rebuild() {
+    let mut viewer = MoleculeViewer::<PivotRender>::new();
+    viewer.set_molecule(Molecule {
+        atoms: vec![Atom {
+            position: Point3::origin(),
+            element: "C".to_string(),
+            id: 0,
+        }],
+        ..Default::default()
+    });
+    viewer.additional_render = Some(Box::new(PivotRender::new()));
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    let (molecule_entities, meshes) = (scene.entities.len(), scene.meshes.len());
+
+    let clock = ManualClock::new();
+    let mut controller = CameraController::<OrbitalCamera>::new();
+    controller.set_clock(clock.clone());
+    controller.camera_drag(Vector2::new(30.0, 0.0), DragMode::Orbit);
+    let overlay = viewer.additional_render.as_mut().unwrap();
+    overlay.indicator = controller.pivot_indicator();
+    assert!(viewer.refresh_overlays(&mut scene));
+    assert_eq!(scene.entities.len(), molecule_entities + 3);
+    assert_eq!(scene.meshes.len(), meshes);
+    assert!((scene.entities[molecule_entities].opacity - 0.6).abs() < 1e-6);
+
+    clock.advance(linger(0.5));
+    let overlay = viewer.additional_render.as_mut().unwrap();
+    overlay.indicator = controller.pivot_indicator();
+    viewer.refresh_overlays(&mut scene);
+    assert!((scene.entities[molecule_entities].opacity - 0.3).abs() < 1e-4);
+
+    clock.advance(linger(1.0));
+    let overlay = viewer.additional_render.as_mut().unwrap();
+    overlay.indicator = controller.pivot_indicator();
+    viewer.refresh_overlays(&mut scene);
+    assert_eq!(scene.entities.len(), molecule_entities);
+}
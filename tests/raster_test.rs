@@ -0,0 +1,165 @@
+use moleucle_3dview_rs::camera::ViewBookmark;
+use moleucle_3dview_rs::display_list::{Cylinder, DisplayList, Sphere};
+use moleucle_3dview_rs::raster::{self, Image, Lens, Supersample, BACKGROUND};
+use moleucle_3dview_rs::style::MoleculeDisplayState;
+use moleucle_3dview_rs::Molecule;
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+use std::path::Path;
+
+fn sphere(center: Point3<f32>, radius: f32) -> Sphere {
+    Sphere {
+        center,
+        radius,
+        color: (1.0, 0.0, 0.0),
+        opacity: 1.0,
+        shininess: 1.0,
+    }
+}
+
+/// Looking down -Z at the origin from 10 away.
+fn front() -> ViewBookmark {
+    ViewBookmark {
+        center: Point3::origin(),
+        rotation: UnitQuaternion::identity(),
+        radius: 10.0,
+    }
+}
+
+#[test]
+fn test_render_perspective_and_orthographic() {
+    let list = DisplayList {
+        spheres: vec![sphere(Point3::new(2.0, 0.0, 0.0), 1.0)],
+        ..Default::default()
+    };
+    for lens in [Lens::default(), Lens::Orthographic { height: 8.0 }] {
+        let image = raster::render(&list, &front(), lens, 20, 10, BACKGROUND);
+        assert_eq!(image.pixels.len(), 200);
+        let at = |col: usize, row: usize| image.pixels[row * 20 + col];
+        // Right of the middle row, not left of it, nor above or below.
+        assert_ne!(at(12, 5), BACKGROUND, "{lens:?}");
+        assert_eq!(at(5, 5), BACKGROUND, "{lens:?}");
+        assert_eq!(at(12, 0), BACKGROUND, "{lens:?}");
+        assert_eq!(at(12, 9), BACKGROUND, "{lens:?}");
+    }
+}
+
+#[test]
+fn test_render_keeps_nearest_and_turns_with_view() {
+    let near = sphere(Point3::new(0.0, 0.0, 2.0), 1.0);
+    let far = Sphere {
+        color: (0.0, 0.0, 1.0),
+        ..sphere(Point3::origin(), 1.5)
+    };
+    let list = DisplayList {
+        spheres: vec![near, far],
+        cylinders: vec![Cylinder {
+            start: Point3::new(0.0, -4.0, 0.0),
+            end: Point3::new(0.0, -2.0, 0.0),
+            radius: 0.3,
+            color: (0.0, 1.0, 0.0),
+            opacity: 1.0,
+            shininess: 1.0,
+        }],
+        ..Default::default()
+    };
+    let image = raster::render(&list, &front(), Lens::default(), 21, 21, BACKGROUND);
+    let middle = image.pixels[10 * 21 + 10];
+    assert!(middle.0 > middle.2);
+    // The cylinder below the middle.
+    assert!((14..21).any(|row| {
+        let (r, g, _) = image.pixels[row * 21 + 10];
+        g > r
+    }));
+
+    // From behind, the blue sphere hides the red one.
+    let back = ViewBookmark {
+        rotation: UnitQuaternion::from_axis_angle(&Vector3::y_axis(), std::f32::consts::PI),
+        ..front()
+    };
+    let image = raster::render(&list, &back, Lens::default(), 21, 21, BACKGROUND);
+    let middle = image.pixels[10 * 21 + 10];
+    assert!(middle.2 > middle.0);
+}
+
+/// Benzene, filling an orthographic view from the front.
+fn benzene() -> (DisplayList, ViewBookmark, Lens) {
+    let mol = Molecule::from_mol2(Path::new("Benzene.mol2")).unwrap();
+    let list = DisplayList::from_molecule(&mol, &MoleculeDisplayState::default());
+    let view = ViewBookmark {
+        center: mol.centroid(),
+        ..front()
+    };
+    (list, view, Lens::Orthographic { height: 7.0 })
+}
+
+/// Mean squared brightness step between neighboring pixels: large where
+/// outlines jump straight from atom to background.
+fn edge_variance(image: &Image) -> f32 {
+    let brightness: Vec<f32> = image.pixels.iter().map(|&(r, g, b)| r + g + b).collect();
+    let (w, h) = (image.width, image.height);
+    let mut sum = 0.0;
+    for row in 0..h - 1 {
+        for col in 0..w - 1 {
+            let here = brightness[row * w + col];
+            sum += (brightness[row * w + col + 1] - here).powi(2)
+                + (brightness[(row + 1) * w + col] - here).powi(2);
+        }
+    }
+    sum / (2 * (w - 1) * (h - 1)) as f32
+}
+
+#[test]
+fn test_supersampling_smooths_edges() {
+    let (list, view, lens) = benzene();
+    let render = |factor| {
+        let supersample = Supersample {
+            factor,
+            ..Default::default()
+        };
+        raster::render_supersampled(&list, &view, lens, 64, 64, BACKGROUND, supersample).unwrap()
+    };
+    let (once, four) = (render(1), render(4));
+    assert_eq!(once, raster::render(&list, &view, lens, 64, 64, BACKGROUND));
+    assert_eq!(four.pixels.len(), 64 * 64);
+    let (rough, smooth) = (edge_variance(&once), edge_variance(&four));
+    assert!(smooth < 0.8 * rough, "{smooth} vs {rough}");
+
+    assert!(raster::render_supersampled(
+        &list,
+        &view,
+        lens,
+        64,
+        64,
+        BACKGROUND,
+        Supersample {
+            factor: 5,
+            ..Default::default()
+        }
+    )
+    .is_err());
+}
+
+#[test]
+fn test_supersampling_in_bands_matches_whole() {
+    let (list, view, lens) = benzene();
+    let render = |memory| {
+        let supersample = Supersample { factor: 4, memory };
+        raster::render_supersampled(&list, &view, lens, 30, 30, BACKGROUND, supersample)
+    };
+    let whole = render(usize::MAX).unwrap();
+    // Three rows of 30 pixels, 4 x 4 rays of a depth and a color each, per band.
+    let row_bytes = 30 * 4 * 4 * (4 + 12);
+    let banded = render(3 * row_bytes).unwrap();
+    assert_eq!(banded.encode_png(), whole.encode_png());
+    assert_eq!(render(7 * row_bytes).unwrap(), whole);
+    assert!(render(row_bytes - 1).is_err());
+}
+
+#[test]
+fn test_encode_png() {
+    let png = raster::encode_png(&[(1.0, 0.0, 0.0), (0.0, 0.0, 1.0)], 2, 1);
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+    assert_eq!(&png[12..16], b"IHDR");
+    assert_eq!(png[16..24], [0, 0, 0, 2, 0, 0, 0, 1]);
+    assert!(png.ends_with(&[b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]));
+}
@@ -0,0 +1,187 @@
+use graphics::{Entity, Mesh, Scene};
+use lin_alg::f32::{Quaternion, Vec3};
+use moleucle_3dview_rs::molecule::Atom;
+use moleucle_3dview_rs::viewer::{EntityKind, ViewerEvent};
+use moleucle_3dview_rs::{
+    AdditionalRender, DebugRender, MeasurementTool, Molecule, MoleculeViewer, SelectedAtomRender,
+    Selection, ViewerBuilder,
+};
+use nalgebra::Point3;
+use std::cell::Cell;
+use std::rc::Rc;
+
+fn pair() -> Molecule {
+    Molecule {
+        atoms: [0.0, 1.5]
+            .iter()
+            .map(|&x| Atom {
+                position: Point3::new(x, 0.0, 0.0),
+                element: "C".to_string(),
+                id: 0,
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+/// Pushes one mesh and `count` entities using it, re-pushing them on
+/// `refresh` so the count can change without a rebuild.
+struct Dots {
+    count: Rc<Cell<usize>>,
+}
+
+impl Dots {
+    fn push(&self, scene: &mut Scene, mesh: usize) {
+        for _ in 0..self.count.get() {
+            let entity = Entity::new(
+                mesh,
+                Vec3::new(0.0, 0.0, 0.0),
+                Quaternion::new_identity(),
+                1.0,
+                (1.0, 1.0, 1.0),
+                0.0,
+            );
+            scene.entities.push(entity);
+        }
+    }
+}
+
+impl AdditionalRender for Dots {
+    fn update_scene(&self, scene: &mut Scene, _molecule: &Molecule) {
+        let mesh = scene.meshes.len();
+        scene.meshes.push(Mesh::new_sphere(1.0, 1));
+        self.push(scene, mesh);
+    }
+
+    fn refresh(&self, scene: &mut Scene, _molecule: &Molecule, first_entity: usize) {
+        // Its own entities and mesh are the last ones.
+        assert!(scene.entities[first_entity..]
+            .iter()
+            .all(|entity| entity.mesh == scene.meshes.len() - 1));
+        scene.entities.truncate(first_entity);
+        self.push(scene, scene.meshes.len() - 1);
+    }
+}
+
+fn dots(count: usize) -> (Dots, Rc<Cell<usize>>) {
+    let count = Rc::new(Cell::new(count));
+    (
+        Dots {
+            count: count.clone(),
+        },
+        count,
+    )
+}
+
+fn overlay_ids<T: AdditionalRender>(viewer: &MoleculeViewer<T>, scene: &Scene) -> Vec<usize> {
+    (0..scene.entities.len())
+        .filter_map(|i| match viewer.classify_entity(i) {
+            Some(EntityKind::Overlay { renderer_id }) => Some(renderer_id),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_renderers_draw_in_order() {
+    let mut viewer = MoleculeViewer::new();
+    viewer.set_molecule(pair());
+    let (first, first_count) = dots(1);
+    viewer.additional_render = Some(Box::new(first));
+    let (second, second_count) = dots(2);
+    let (third, _) = dots(1);
+    let second_id = viewer.add_renderer(Box::new(second));
+    let third_id = viewer.add_renderer(Box::new(third));
+    assert_eq!((second_id, third_id), (1, 2));
+    assert_eq!(viewer.renderer_ids(), [1, 2]);
+
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    let meshes = scene.meshes.len();
+    assert_eq!(overlay_ids(&viewer, &scene), [0, 1, 1, 2]);
+    // A rebuild starts over rather than adding to the last one.
+    viewer.dirty = true;
+    viewer.update_scene(&mut scene);
+    assert_eq!(scene.meshes.len(), meshes);
+    assert_eq!(overlay_ids(&viewer, &scene), [0, 1, 1, 2]);
+
+    // Each refresh sees only its own entities, which may grow or shrink.
+    second_count.set(3);
+    first_count.set(0);
+    assert!(viewer.refresh_overlays(&mut scene));
+    assert_eq!(overlay_ids(&viewer, &scene), [1, 1, 1, 2]);
+    assert_eq!(scene.meshes.len(), meshes);
+    first_count.set(2);
+    second_count.set(0);
+    assert!(viewer.refresh_overlays(&mut scene));
+    assert_eq!(overlay_ids(&viewer, &scene), [0, 0, 2]);
+
+    assert!(viewer.remove_renderer(second_id).is_some());
+    assert!(viewer.remove_renderer(second_id).is_none());
+    assert!(viewer.dirty_flags().overlays);
+    viewer.update_scene(&mut scene);
+    assert_eq!(overlay_ids(&viewer, &scene), [0, 0, 2]);
+    // Ids are not reused.
+    assert_eq!(viewer.add_renderer(Box::new(dots(1).0)), 3);
+}
+
+#[test]
+fn test_added_renderers_get_hooks() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(pair());
+    viewer.select_atom(1);
+    let highlight = viewer.add_renderer(Box::new(SelectedAtomRender::new()));
+    let tool = viewer.add_renderer(Box::new(MeasurementTool::new()));
+    let mirrored = |viewer: &mut MoleculeViewer<SelectedAtomRender>| {
+        viewer
+            .renderer_mut(highlight)
+            .and_then(|r| r.selection_mut())
+            .map(|s| s.clone())
+    };
+    assert_eq!(mirrored(&mut viewer), Some(Selection::from_atoms([1])));
+    viewer.select_atom(0);
+    assert_eq!(mirrored(&mut viewer), Some(Selection::from_atoms([0, 1])));
+
+    // Clicks go to the added measurement tool.
+    assert!(viewer.handle_pick(&ViewerEvent::AtomClicked(0)));
+    assert!(viewer.handle_pick(&ViewerEvent::AtomClicked(1)));
+    let measurements = viewer
+        .renderer_mut(tool)
+        .and_then(|r| r.measurement_mut())
+        .map(|t| t.measurements().len());
+    assert_eq!(measurements, Some(1));
+
+    let (viewer, warnings) = ViewerBuilder::<SelectedAtomRender>::new()
+        .renderer(MeasurementTool::new())
+        .renderer(SelectedAtomRender::new())
+        .build();
+    assert!(warnings.is_empty());
+    assert_eq!(viewer.renderer_ids(), [1, 2]);
+    assert!(viewer.additional_render.is_none());
+}
+
+#[test]
+fn test_rebuilds_do_not_grow_meshes() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(pair());
+    let mut highlight = SelectedAtomRender::new();
+    highlight.selection = Selection::from_atoms([0, 1]);
+    viewer.additional_render = Some(Box::new(highlight));
+    viewer.add_renderer(Box::new(DebugRender::new((
+        Vec3::new(0.0, 0.0, 10.0),
+        Vec3::new(0.0, 0.0, -1.0),
+    ))));
+
+    let mut scene = Scene::default();
+    let mut counts = Vec::new();
+    for _ in 0..3 {
+        viewer.dirty = true;
+        viewer.update_scene(&mut scene);
+        counts.push((scene.meshes.len(), scene.entities.len()));
+    }
+    assert_eq!(counts, [counts[0]; 3]);
+    // One sphere for both highlight shells, one cylinder for the ray.
+    assert_eq!(overlay_ids(&viewer, &scene), [0, 0, 1]);
+    let shells = &scene.entities[scene.entities.len() - 3..scene.entities.len() - 1];
+    assert_eq!(shells[0].mesh, shells[1].mesh);
+}
@@ -0,0 +1,117 @@
+use graphics::Scene;
+use moleucle_3dview_rs::molecule::{Atom, Bond};
+use moleucle_3dview_rs::{Molecule, MoleculeViewer, SelectedAtomRender};
+use nalgebra::Point3;
+use std::f32::consts::{FRAC_PI_2, PI};
+use std::path::Path;
+
+/// Six-membered carbon ring with 1.54 Å bonds; `z(j)` gives each atom's height
+/// above a regular hexagon of radius 1.452 Å.
+fn ring6(z: impl Fn(usize) -> f32) -> Molecule {
+    let atoms = (0..6)
+        .map(|j| {
+            let a = 2.0 * PI * j as f32 / 6.0;
+            Atom {
+                position: Point3::new(1.452 * a.cos(), 1.452 * a.sin(), z(j)),
+                element: "C".to_string(),
+                id: j,
+            }
+        })
+        .collect();
+    let bonds = (0..6)
+        .map(|j| Bond {
+            atom_a: j,
+            atom_b: (j + 1) % 6,
+            order: 1,
+        })
+        .collect();
+    Molecule {
+        atoms,
+        bonds,
+        ..Default::default()
+    }
+}
+
+/// Ideal cyclohexane chair (C-C 1.54 Å, tetrahedral angles).
+fn chair() -> Molecule {
+    ring6(|j| if j % 2 == 0 { 0.2567 } else { -0.2567 })
+}
+
+#[test]
+fn test_find_rings() {
+    let benzene = Molecule::from_mol2(Path::new("Benzene.mol2")).unwrap();
+    let rings = benzene.find_rings();
+    assert_eq!(rings.len(), 1);
+    let mut ring = rings[0].clone();
+    ring.sort_unstable();
+    assert_eq!(ring, vec![0, 1, 2, 3, 4, 5]);
+
+    let chain = Molecule {
+        bonds: vec![Bond {
+            atom_a: 0,
+            atom_b: 1,
+            order: 1,
+        }],
+        ..chair()
+    };
+    assert!(chain.find_rings().is_empty());
+}
+
+#[test]
+fn test_chair_matches_reference() {
+    let mol = chair();
+    let ring = mol.find_rings().remove(0);
+    let pucker = mol.cremer_pople(&ring).unwrap();
+    // Published value for the ideal chair: Q = 0.63 Å, θ = 0°.
+    assert!((pucker.q - 0.63).abs() < 0.01);
+    let theta = pucker.theta.unwrap();
+    assert!(theta.min(PI - theta) < 1e-3);
+    assert!(mol.ring_planarity(&ring).unwrap() > 0.2);
+}
+
+#[test]
+fn test_boat_and_planar() {
+    let boat = ring6(|j| 0.35 * (4.0 * PI * j as f32 / 6.0).cos());
+    let ring: Vec<usize> = (0..6).collect();
+    let pucker = boat.cremer_pople(&ring).unwrap();
+    assert!((pucker.theta.unwrap() - FRAC_PI_2).abs() < 1e-3);
+
+    let flat = ring6(|_| 0.0);
+    assert!(flat.ring_planarity(&ring).unwrap() < 1e-4);
+    assert!(flat.cremer_pople(&ring).unwrap().q < 1e-4);
+
+    // Five-membered rings have no θ.
+    let envelope = Molecule {
+        atoms: boat.atoms[..5].to_vec(),
+        bonds: Vec::new(),
+        ..Default::default()
+    };
+    assert_eq!(envelope.cremer_pople(&[0, 1, 2, 3, 4]).unwrap().theta, None);
+}
+
+#[test]
+fn test_invalid_ring_input() {
+    let mol = chair();
+    assert_eq!(mol.cremer_pople(&[0, 1, 2, 3]), None);
+    assert_eq!(mol.cremer_pople(&[0, 1, 2, 3, 4, 99]), None);
+    assert_eq!(mol.ring_planarity(&[0, 1]), None);
+    assert_eq!(mol.ring_planarity(&[0, 1, 99]), None);
+}
+
+#[test]
+fn test_analyze_and_color_rings() {
+    let analysis = chair().analyze_rings();
+    assert_eq!(analysis.len(), 1);
+    assert_eq!(analysis[0].size(), 6);
+    assert!(analysis[0].pucker.is_some());
+
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(Molecule::from_mol2(Path::new("Benzene.mol2")).unwrap());
+    viewer.settings.color_rings_by_planarity = true;
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    // Planar ring carbons turn green; hydrogens keep their element color.
+    let (r, g, _) = scene.entities[0].color;
+    assert!(r < 0.11 && g > 0.79);
+    assert_eq!(scene.entities[6].color, (0.9, 0.9, 0.9));
+}
@@ -0,0 +1,143 @@
+use graphics::{EngineUpdates, EntityUpdate, Scene};
+use moleucle_3dview_rs::convert::{quat_to_la, quat_to_na};
+use moleucle_3dview_rs::molecule::{Atom, Bond};
+use moleucle_3dview_rs::snapshot::scene_snapshot;
+use moleucle_3dview_rs::viewer::ViewerEvent;
+use moleucle_3dview_rs::{DirtyFlags, MeasurementTool, Molecule, MoleculeViewer, PickTarget};
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+
+/// C-O-H in a line, bonded.
+fn chain() -> Molecule {
+    Molecule {
+        atoms: [("C", 0.0), ("O", 1.4), ("H", 2.4)]
+            .iter()
+            .map(|&(element, x)| Atom {
+                position: Point3::new(x, 0.0, 0.0),
+                element: element.to_string(),
+                id: 0,
+            })
+            .collect(),
+        bonds: vec![
+            Bond {
+                atom_a: 0,
+                atom_b: 1,
+                order: 1,
+            },
+            Bond {
+                atom_a: 1,
+                atom_b: 2,
+                order: 1,
+            },
+        ],
+        ..Default::default()
+    }
+}
+
+/// Whether `scene` shows what a full rebuild would.
+fn matches_rebuild(viewer: &mut MoleculeViewer<MeasurementTool>, scene: &Scene) -> bool {
+    let updated = scene_snapshot(viewer, scene);
+    let mut rebuilt = Scene::default();
+    viewer.dirty = true;
+    viewer.update_scene(&mut rebuilt);
+    updated == scene_snapshot(viewer, &rebuilt)
+}
+
+fn changed(updates: &EngineUpdates) -> (bool, bool) {
+    (
+        updates.meshes,
+        matches!(updates.entities, EntityUpdate::All),
+    )
+}
+
+#[test]
+fn test_partial_updates_match_rebuild() {
+    let mut viewer = MoleculeViewer::new();
+    viewer.set_molecule(chain());
+    viewer.add_molecule(Molecule {
+        atoms: chain().atoms[..1].to_vec(),
+        ..Default::default()
+    });
+    viewer.additional_render = Some(Box::new(MeasurementTool::new()));
+    let mut scene = Scene::default();
+    assert_eq!(changed(&viewer.update_scene(&mut scene)), (true, true));
+    assert!(!viewer.needs_update());
+    assert_eq!(changed(&viewer.update_scene(&mut scene)), (false, false));
+
+    // Colors and hover are rewritten in place, without new meshes.
+    viewer.set_hovered(Some(PickTarget::Atom(1)));
+    viewer.settings.color_overrides.insert(0, (0.1, 0.2, 0.3));
+    viewer.mark_colors_dirty();
+    assert_eq!(
+        viewer.dirty_flags(),
+        DirtyFlags {
+            colors: true,
+            ..Default::default()
+        }
+    );
+    assert_eq!(changed(&viewer.update_scene(&mut scene)), (false, true));
+    assert!(matches_rebuild(&mut viewer, &scene));
+    viewer.set_hovered(Some(PickTarget::Bond(0)));
+    viewer.update_scene(&mut scene);
+    assert!(matches_rebuild(&mut viewer, &scene));
+
+    // The selection and overlays are re-pushed at the end.
+    viewer.select_atom(2);
+    assert!(!viewer.dirty && viewer.dirty_flags().selection);
+    assert_eq!(changed(&viewer.update_scene(&mut scene)), (true, true));
+    assert!(matches_rebuild(&mut viewer, &scene));
+    viewer.handle_pick(&ViewerEvent::AtomClicked(0));
+    viewer.handle_pick(&ViewerEvent::AtomClicked(2));
+    assert!(!viewer.dirty && viewer.dirty_flags().overlays);
+    viewer.update_scene(&mut scene);
+    assert!(matches_rebuild(&mut viewer, &scene));
+    viewer.clear_selection();
+    viewer.update_scene(&mut scene);
+    assert!(matches_rebuild(&mut viewer, &scene));
+}
+
+#[test]
+fn test_camera_turn_moves_bond_lines_in_place() {
+    let mut viewer = MoleculeViewer::<MeasurementTool>::new();
+    let mut mol = chain();
+    mol.bonds[0].order = 2;
+    viewer.set_molecule(mol);
+    viewer.settings.show_bond_order = true;
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    let before = scene_snapshot(&viewer, &scene);
+
+    let turn = UnitQuaternion::from_axis_angle(&Vector3::x_axis(), 1.0);
+    scene.camera.orientation = quat_to_la(turn * quat_to_na(scene.camera.orientation));
+    assert_eq!(changed(&viewer.update_scene(&mut scene)), (false, true));
+    assert!(!viewer.needs_update());
+    assert_ne!(scene_snapshot(&viewer, &scene), before);
+
+    let mut rebuilt = Scene::default();
+    rebuilt.camera.orientation = scene.camera.orientation;
+    viewer.dirty = true;
+    viewer.update_scene(&mut rebuilt);
+    assert_eq!(
+        scene_snapshot(&viewer, &scene),
+        scene_snapshot(&viewer, &rebuilt)
+    );
+}
+
+#[test]
+fn test_partial_updates_fall_back_to_rebuild() {
+    let mut viewer = MoleculeViewer::<MeasurementTool>::new();
+    viewer.set_molecule(chain());
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+
+    // Hiding an atom changes what is drawn, not just its color.
+    viewer.settings.hidden_atoms.insert(2);
+    viewer.mark_colors_dirty();
+    assert_eq!(changed(&viewer.update_scene(&mut scene)), (true, true));
+    assert!(matches_rebuild(&mut viewer, &scene));
+
+    // So does a scene that was changed elsewhere.
+    scene.entities.pop();
+    viewer.mark_colors_dirty();
+    assert_eq!(changed(&viewer.update_scene(&mut scene)), (true, true));
+    assert!(matches_rebuild(&mut viewer, &scene));
+}
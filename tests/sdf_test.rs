@@ -0,0 +1,151 @@
+use moleucle_3dview_rs::{Molecule, MoleculeError};
+use nalgebra::Point3;
+
+// Aspirin without hydrogens, with its PubChem id as a data item, then
+// acetate, whose atom block charge is replaced by `M  CHG`.
+const ASPIRIN_ACETATE: &str = "\
+aspirin
+  handwritten      2D
+
+ 13 13  0  0  0  0  0  0  0  0999 V2000
+    0.0000    1.4000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+   -1.2124    0.7000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+   -1.2124   -0.7000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+    0.0000   -1.4000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+    1.2124   -0.7000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+    1.2124    0.7000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+    0.0000    2.9000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+    1.2000    3.6000    0.0000 O   0  0  0  0  0  0  0  0  0  0  0  0
+   -1.2000    3.6000    0.0000 O   0  0  0  0  0  0  0  0  0  0  0  0
+   -2.5000    1.4000    0.0000 O   0  0  0  0  0  0  0  0  0  0  0  0
+   -3.7000    0.7000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+   -3.7000   -0.7000    0.0000 O   0  0  0  0  0  0  0  0  0  0  0  0
+   -4.9000    1.4000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+  1  2  2  0
+  2  3  1  0
+  3  4  2  0
+  4  5  1  0
+  5  6  2  0
+  6  1  1  0
+  1  7  1  0
+  7  8  2  0
+  7  9  1  0
+  2 10  1  0
+ 10 11  1  0
+ 11 12  2  0
+ 11 13  1  0
+M  END
+> <PUBCHEM_COMPOUND_CID>
+2244
+
+$$$$
+acetate
+
+
+  4  3  0  0  0  0  0  0  0  0999 V2000
+    0.0000    0.0000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+    1.5000    0.0000    0.0000 C   0  0  0  0  0  0  0  0  0  0  0  0
+    2.2000    1.2000    0.0000 O   0  0  0  0  0  0  0  0  0  0  0  0
+    2.2000   -1.2000    0.0000 O   0  3  0  0  0  0  0  0  0  0  0  0
+  1  2  1  0
+  2  3  2  0
+  2  4  1  0
+M  CHG  1   4  -1
+M  END
+$$$$
+";
+
+#[test]
+fn test_sdf_records() {
+    let molecules = Molecule::from_sdf_all_str(ASPIRIN_ACETATE).unwrap();
+    assert_eq!(molecules.len(), 2);
+    let aspirin = &molecules[0];
+    assert_eq!(aspirin.name.as_deref(), Some("aspirin"));
+    assert_eq!((aspirin.atoms.len(), aspirin.bonds.len()), (13, 13));
+    assert_eq!(aspirin.formula(), "C9O4");
+    assert!(aspirin.warnings.is_empty(), "{:?}", aspirin.warnings);
+    assert_eq!(aspirin.atoms[7].position, Point3::new(1.2, 3.6, 0.0));
+    let orders: Vec<u8> = aspirin.bonds.iter().map(|b| b.order).collect();
+    assert_eq!(orders, [2, 1, 2, 1, 2, 1, 1, 2, 1, 1, 1, 2, 1]);
+    assert_eq!((aspirin.bonds[9].atom_a, aspirin.bonds[9].atom_b), (1, 9));
+    assert_eq!(aspirin.formal_charges, Some(vec![0; 13]));
+
+    let acetate = &molecules[1];
+    assert_eq!(acetate.name.as_deref(), Some("acetate"));
+    assert_eq!(acetate.formula(), "C2O2");
+    assert_eq!(acetate.formal_charges, Some(vec![0, 0, 0, -1]));
+}
+
+#[test]
+fn test_sdf_from_file() {
+    let path = std::env::temp_dir().join(format!("aspirin_{}.sdf", std::process::id()));
+    std::fs::write(&path, ASPIRIN_ACETATE).unwrap();
+    let all = Molecule::from_sdf_all(&path).unwrap();
+    // `from_sdf` and `from_file` read the first record only.
+    let first = Molecule::from_sdf(&path).unwrap();
+    let from_file = Molecule::from_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(all.len(), 2);
+    assert_eq!(first.atoms.len(), 13);
+    assert_eq!(from_file.formula(), first.formula());
+
+    // Without the trailing `$$$$` the last record is still read.
+    let unterminated = ASPIRIN_ACETATE.trim_end().trim_end_matches("$$$$");
+    assert_eq!(Molecule::from_sdf_all_str(unterminated).unwrap().len(), 2);
+    assert!(Molecule::from_sdf_all_str("").unwrap().is_empty());
+    let err = Molecule::from_sdf_str("\n\n").unwrap_err();
+    assert_eq!(err.to_string(), "sdf: missing records");
+}
+
+#[test]
+fn test_sdf_errors() {
+    let v3000 = ASPIRIN_ACETATE.replace("0999 V2000", "0999 V3000");
+    let err = Molecule::from_sdf_str(&v3000).unwrap_err();
+    assert!(
+        matches!(&err, MoleculeError::UnsupportedFormat(what) if what == "sdf record 1 is V3000"),
+        "{err}"
+    );
+
+    let truncated: String = ASPIRIN_ACETATE
+        .lines()
+        .take(10)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let err = Molecule::from_sdf_str(&truncated).unwrap_err();
+    assert!(matches!(err, MoleculeError::MissingSection { .. }), "{err}");
+    assert!(err.to_string().contains("13 atoms and 13 bonds"), "{err}");
+
+    let bad_bond = ASPIRIN_ACETATE.replace(" 11 13  1  0", " 11 14  1  0");
+    let err = Molecule::from_sdf_str(&bad_bond).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            MoleculeError::IndexOutOfRange {
+                line: 30,
+                index: 14,
+                len: 13,
+                ..
+            }
+        ),
+        "{err}"
+    );
+
+    let bad_counts = ASPIRIN_ACETATE.replacen(" 13 13", " ab 13", 1);
+    let err = Molecule::from_sdf_str(&bad_counts).unwrap_err();
+    assert!(err.to_string().contains("atom and bond counts"), "{err}");
+
+    // A malformed later record fails only the whole-file parse.
+    let bad_second = ASPIRIN_ACETATE.replace("  4  3  0", "  4  x  0");
+    assert_eq!(Molecule::from_sdf_str(&bad_second).unwrap().atoms.len(), 13);
+    let err = Molecule::from_sdf_all_str(&bad_second).unwrap_err();
+    assert!(err.to_string().ends_with("of record 2"), "{err}");
+
+    let bad_atom = ASPIRIN_ACETATE.replacen("   -1.2124", "   -1,2,24", 1);
+    let err = Molecule::from_sdf_str(&bad_atom).unwrap_err();
+    assert_eq!(err.to_string(), "sdf line 6: expected x y z");
+
+    let query = ASPIRIN_ACETATE.replacen(" C   0", " R#  0", 1);
+    let aspirin = Molecule::from_sdf_str(&query).unwrap();
+    assert_eq!(aspirin.atoms[0].element, "X");
+    assert_eq!(aspirin.warnings, ["sdf record 1 atom 1: no element \"R#\""]);
+}
@@ -0,0 +1,303 @@
+mod common;
+
+use common::{atom, bond};
+use graphics::winit::keyboard::KeyCode;
+use graphics::Scene;
+use moleucle_3dview_rs::viewer::{AddResult, EntityKind, ViewerEvent, SELECTION_OPACITY};
+use moleucle_3dview_rs::{
+    CameraController, Molecule, MoleculeViewer, OrbitalCamera, SelectedAtomRender, Selection,
+    SelectionRect,
+};
+use nalgebra::Point2;
+
+/// A chain C0-C1-O2-C3 at 1.5 Å spacing, plus an unbonded N4 far away.
+fn chain() -> Molecule {
+    Molecule {
+        atoms: vec![
+            atom("C", 0.0, 0.0, 0.0),
+            atom("C", 1.5, 0.0, 0.0),
+            atom("O", 3.0, 0.0, 0.0),
+            atom("C", 4.5, 0.0, 0.0),
+            atom("N", 20.0, 0.0, 0.0),
+        ],
+        bonds: vec![bond(0, 1), bond(1, 2), bond(2, 3)],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_basic_set_behaviour() {
+    let mut selection = Selection::from_atoms([3, 1, 3]);
+    assert_eq!(selection.atoms(), &[1, 3]);
+    assert!(!selection.insert(1));
+    assert!(selection.insert(0));
+    assert!(!selection.toggle(0));
+    assert!(selection.toggle(2));
+    assert_eq!(selection.atoms(), &[1, 2, 3]);
+    assert_eq!(selection.clear(), 3);
+    assert!(selection.is_empty());
+}
+
+#[test]
+fn test_expand_bonded() {
+    let mol = chain();
+    let mut selection = Selection::from_atoms([0]);
+    assert_eq!(selection.expand_bonded(&mol, 1), 1);
+    assert_eq!(selection.atoms(), &[0, 1]);
+    assert_eq!(selection.expand_bonded(&mol, 10), 2);
+    assert_eq!(selection.atoms(), &[0, 1, 2, 3]);
+    // The disconnected atom is never reached.
+    assert_eq!(selection.expand_bonded(&mol, 10), 0);
+
+    let mut empty = Selection::new();
+    assert_eq!(empty.expand_bonded(&mol, 3), 0);
+    assert_eq!(empty.expand_radius(&mol, 100.0), 0);
+}
+
+#[test]
+fn test_expand_radius() {
+    let mol = chain();
+    let mut selection = Selection::from_atoms([2]);
+    assert_eq!(selection.expand_radius(&mol, 1.6), 2);
+    assert_eq!(selection.atoms(), &[1, 2, 3]);
+    assert_eq!(selection.expand_radius(&mol, 100.0), 2);
+    assert_eq!(selection.len(), 5);
+}
+
+#[test]
+fn test_invert() {
+    let mol = chain();
+    let mut selection = Selection::from_atoms([0, 4, 99]);
+    assert_eq!(selection.invert(&mol), 6);
+    assert_eq!(selection.atoms(), &[1, 2, 3]);
+
+    let mut all = Selection::from_atoms(0..5);
+    assert_eq!(all.invert(&mol), 5);
+    assert!(all.is_empty());
+    assert_eq!(all.invert(&mol), 5);
+    assert_eq!(all.len(), 5);
+}
+
+#[test]
+fn test_same_element_and_set_algebra() {
+    let mut mol = chain();
+    mol.atoms[3].element = "c".to_string();
+    let mut selection = Selection::from_atoms([1]);
+    assert_eq!(selection.select_same_element(&mol), 2);
+    assert_eq!(selection.atoms(), &[0, 1, 3]);
+
+    assert_eq!(selection.intersect_with(&[1, 3, 4]), 1);
+    assert_eq!(selection.atoms(), &[1, 3]);
+    assert_eq!(selection.subtract(&[3, 4]), 1);
+    assert_eq!(selection.atoms(), &[1]);
+    assert_eq!(selection.subtract(&[]), 0);
+}
+
+#[test]
+fn test_same_residue() {
+    let mut mol = chain();
+    assert_eq!(Selection::from_atoms([0]).select_same_residue(&mol), 0);
+
+    // Residue 1 is in both chains; only chain A's is picked up.
+    mol.residues = Some(vec![1, 1, 2, 2, 1]);
+    let mut selection = Selection::from_atoms([1]);
+    mol.chains = Some(vec!['A', 'A', 'A', 'A', 'B']);
+    assert_eq!(selection.select_same_residue(&mol), 1);
+    assert_eq!(selection.atoms(), &[0, 1]);
+    mol.chains = None;
+    assert_eq!(selection.select_same_residue(&mol), 1);
+    assert_eq!(selection.atoms(), &[0, 1, 4]);
+
+    let mut selection = Selection::from_atoms([2, 9]);
+    assert_eq!(selection.select_same_residue(&mol), 1);
+    assert_eq!(selection.atoms(), &[2, 3, 9]);
+    assert_eq!(Selection::new().select_same_residue(&mol), 0);
+}
+
+#[test]
+fn test_viewer_selection() {
+    let mut viewer = MoleculeViewer::new();
+    viewer.set_molecule(chain());
+    viewer.additional_render = Some(Box::new(SelectedAtomRender::new()));
+    viewer.take_events();
+
+    assert!(viewer.select_atom(2));
+    assert!(!viewer.select_atom(2));
+    assert!(!viewer.select_atom(5));
+    assert!(viewer.select_bond(2) && !viewer.select_bond(3));
+    assert!(viewer.toggle_atom(4));
+    assert!(viewer.deselect_atom(2));
+    assert_eq!(viewer.selected_atoms(), [4]);
+    assert_eq!(viewer.selected_bonds(), [2]);
+    // Bond edits report the atoms too.
+    assert_eq!(
+        viewer.take_events(),
+        [
+            ViewerEvent::SelectionChanged(vec![2]),
+            ViewerEvent::SelectionChanged(vec![2]),
+            ViewerEvent::SelectionChanged(vec![2, 4]),
+            ViewerEvent::SelectionChanged(vec![4]),
+        ]
+    );
+    let overlay = viewer.additional_render.as_ref().unwrap();
+    assert_eq!(overlay.selected_atoms(), [4]);
+
+    // Selected bonds follow the renumbering, and undo brings them back.
+    viewer.delete_bond(0).unwrap();
+    assert_eq!(viewer.selected_bonds(), [1]);
+    assert!(viewer.undo());
+    assert_eq!(viewer.selected_bonds(), [2]);
+
+    // A shell around each selected atom and bond, after the molecule.
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    let shells: Vec<EntityKind> = (0..scene.entities.len())
+        .filter_map(|i| viewer.classify_entity(i))
+        .filter(|kind| {
+            matches!(
+                kind,
+                EntityKind::SelectedAtom { .. } | EntityKind::SelectedBond { .. }
+            )
+        })
+        .collect();
+    assert_eq!(
+        shells,
+        [
+            EntityKind::SelectedAtom { index: 4 },
+            EntityKind::SelectedBond { index: 2 }
+        ]
+    );
+    // After the five atoms and three bonds.
+    let shell = &scene.entities[5 + 3];
+    assert_eq!(shell.opacity, SELECTION_OPACITY);
+    assert!(shell.scale > scene.entities[4].scale);
+
+    assert!(viewer.clear_selection());
+    assert!(!viewer.clear_selection());
+    assert!(viewer.selection().is_empty());
+}
+
+#[test]
+fn test_selection_cleared_with_the_molecule() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(chain());
+    viewer.select_atom(1);
+    viewer.select_bond(0);
+    viewer.set_molecule(chain());
+    assert!(viewer.selected_atoms().is_empty());
+    assert!(viewer.selected_bonds().is_empty());
+
+    // Another slot doesn't inherit the indices either.
+    let mut other = chain();
+    other.atoms.pop();
+    assert_eq!(viewer.add_molecule(other), AddResult::Added(1));
+    viewer.select_atom(3);
+    assert!(viewer.set_active_molecule(1));
+    assert!(viewer.selected_atoms().is_empty());
+    viewer.select_atom(2);
+    assert!(viewer.set_active_molecule(0));
+    assert!(viewer.selected_atoms().is_empty());
+}
+
+#[test]
+fn test_click_selection() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(chain());
+    let mut controller = CameraController::<OrbitalCamera>::new();
+    let mut click = |controller: &CameraController<OrbitalCamera>, event| {
+        controller.select_on_click(&event, &mut viewer);
+        (
+            viewer.selected_atoms().to_vec(),
+            viewer.selected_bonds().to_vec(),
+        )
+    };
+
+    // Plain clicks replace the selection.
+    assert_eq!(
+        click(&controller, ViewerEvent::AtomClicked(0)),
+        (vec![0], vec![])
+    );
+    assert_eq!(
+        click(&controller, ViewerEvent::BondClicked(1)),
+        (vec![], vec![1])
+    );
+    assert_eq!(
+        click(&controller, ViewerEvent::AtomClicked(3)),
+        (vec![3], vec![])
+    );
+
+    // Ctrl-clicks add, or remove what was selected.
+    let idle = MoleculeViewer::<SelectedAtomRender>::new();
+    controller.key_input(KeyCode::ControlLeft, true, &idle);
+    assert_eq!(
+        click(&controller, ViewerEvent::AtomClicked(1)),
+        (vec![1, 3], vec![])
+    );
+    assert_eq!(
+        click(&controller, ViewerEvent::BondClicked(0)),
+        (vec![1, 3], vec![0])
+    );
+    assert_eq!(
+        click(&controller, ViewerEvent::AtomClicked(3)),
+        (vec![1], vec![0])
+    );
+    assert_eq!(
+        click(&controller, ViewerEvent::NothingClicked),
+        (vec![1], vec![0])
+    );
+    controller.key_input(KeyCode::ControlLeft, false, &idle);
+
+    // Empty space clears; other events and bond mode leave it alone.
+    assert_eq!(
+        click(&controller, ViewerEvent::AtomHovered(2)),
+        (vec![1], vec![0])
+    );
+    assert_eq!(
+        click(&controller, ViewerEvent::NothingClicked),
+        (vec![], vec![])
+    );
+    viewer.set_bond_mode(true);
+    assert!(!controller.select_on_click(&ViewerEvent::AtomClicked(2), &mut viewer));
+    assert!(viewer.selected_atoms().is_empty());
+}
+
+#[test]
+fn test_rect_selection() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(chain());
+    let mut controller = CameraController::<OrbitalCamera>::new();
+    controller.frame_molecule(viewer.molecule.as_ref().unwrap());
+    assert!(controller.selection_rect().is_none());
+
+    let rect = |x0: f32, x1: f32| SelectionRect {
+        start: Point2::new(x0, 0.5),
+        end: Point2::new(x1, -0.5),
+    };
+    // Corners may come in any order.
+    assert_eq!(rect(0.0, -1.0).min(), Point2::new(-1.0, -0.5));
+    assert!(rect(0.0, -1.0).contains(Point2::new(-0.5, 0.0)));
+    assert!(!rect(0.0, -1.0).contains(Point2::new(0.5, 0.0)));
+
+    // The chain is on the left of the framed molecule, N4 on the right.
+    let left = controller.atoms_in_rect(&rect(-1.0, 0.0), &viewer);
+    let right = controller.atoms_in_rect(&rect(0.0, 1.0), &viewer);
+    assert_eq!(
+        (left.as_slice(), right.as_slice()),
+        ([0, 1, 2, 3].as_slice(), [4].as_slice())
+    );
+    // Hidden atoms are not drawn, so not selected.
+    viewer.settings.hidden_atoms.insert(1);
+    assert_eq!(
+        controller.atoms_in_rect(&rect(-1.0, 0.0), &viewer),
+        [0, 2, 3]
+    );
+
+    // The result replaces the selection, or with Ctrl adds to it.
+    viewer.select_atom(4);
+    assert!(controller.select_on_click(&ViewerEvent::AtomsSelected(vec![0, 2]), &mut viewer));
+    assert_eq!(viewer.selected_atoms(), [0, 2]);
+    let idle = MoleculeViewer::<SelectedAtomRender>::new();
+    controller.key_input(KeyCode::ControlLeft, true, &idle);
+    controller.select_on_click(&ViewerEvent::AtomsSelected(vec![2, 3]), &mut viewer);
+    assert_eq!(viewer.selected_atoms(), [0, 2, 3]);
+}
@@ -0,0 +1,72 @@
+use moleucle_3dview_rs::{CameraController, DragMode, OrbitalCamera};
+use nalgebra::Vector2;
+
+const PIXELS: f32 = 480.0;
+
+fn controller(tau: f32) -> CameraController<OrbitalCamera> {
+    let mut controller = CameraController::<OrbitalCamera>::new();
+    controller.settings.smoothing_time_constant = tau;
+    controller
+}
+
+fn yaw(controller: &CameraController<OrbitalCamera>) -> f32 {
+    controller.camera.rotation.scaled_axis().y
+}
+
+/// Drags `PIXELS` to the right over half a second, as `events_per_frame`
+/// events per frame at `fps`, then lets the camera settle for two seconds.
+/// Returns the yaw after the first frame and at the end.
+fn drag(tau: f32, fps: f32, events_per_frame: usize) -> (f32, f32) {
+    let mut controller = controller(tau);
+    let frames = (fps * 0.5) as usize;
+    let delta = PIXELS / (frames * events_per_frame) as f32;
+    let mut first = None;
+    for _ in 0..frames {
+        for _ in 0..events_per_frame {
+            controller.camera_drag(Vector2::new(delta, 0.0), DragMode::Orbit);
+        }
+        controller.tick(1.0 / fps);
+        first.get_or_insert(yaw(&controller));
+    }
+    for _ in 0..(fps * 2.0) as usize {
+        controller.tick(1.0 / fps);
+    }
+    (first.unwrap(), yaw(&controller))
+}
+
+#[test]
+fn test_smoothed_rotation_independent_of_event_rate() {
+    let expected = PIXELS * 0.005;
+    let (_, raw) = drag(0.0, 60.0, 1);
+    assert!((raw - expected).abs() < 1e-4);
+
+    // An 8 kHz mouse, batched compositor events, and different frame rates
+    // all integrate to the same rotation.
+    for (fps, events) in [(60.0, 133), (60.0, 1), (144.0, 4), (30.0, 1)] {
+        let (first, total) = drag(0.1, fps, events);
+        assert!((total - expected).abs() < 1e-4, "{fps} fps, {events}");
+        // The filter lags the raw input at first.
+        let raw_first = expected / (fps * 0.5);
+        assert!(first < raw_first * 0.5, "{first} vs {raw_first}");
+    }
+
+    // Event batching within a frame does not change the trajectory at all.
+    assert!((drag(0.1, 60.0, 1).0 - drag(0.1, 60.0, 16).0).abs() < 1e-6);
+}
+
+#[test]
+fn test_smoothing_disabled_applies_immediately() {
+    let mut controller = controller(0.0);
+    assert!(controller.camera_drag(Vector2::new(100.0, 0.0), DragMode::Orbit));
+    assert!((yaw(&controller) - 0.5).abs() < 1e-5);
+    assert!(!controller.tick(0.1));
+
+    // Turning smoothing off flushes anything still queued.
+    controller.settings.smoothing_time_constant = 0.2;
+    assert!(!controller.camera_drag(Vector2::new(0.0, 30.0), DragMode::Dolly));
+    assert_eq!(controller.camera.radius, 10.0);
+    controller.settings.smoothing_time_constant = 0.0;
+    assert!(controller.tick(0.0));
+    assert!((controller.camera.radius - 7.0).abs() < 1e-5);
+    assert!(!controller.tick(0.1));
+}
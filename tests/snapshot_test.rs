@@ -0,0 +1,129 @@
+//! Golden-scene tests: each case builds a scene under fixed settings and compares
+//! its snapshot with `tests/golden/<name>.snap`.
+//!
+//! After an intended rendering change, regenerate the files with
+//! `UPDATE_GOLDEN=1 cargo test --test snapshot_test` and review the diff.
+
+mod common;
+
+use common::{atom, bond};
+use graphics::Scene;
+use moleucle_3dview_rs::snapshot::{scene_snapshot, snapshot_diff};
+use moleucle_3dview_rs::viewer::RenderStyle;
+use moleucle_3dview_rs::{
+    AdditionalRender, GroundDiscRender, Molecule, MoleculeViewer, SelectedAtomRender,
+};
+use std::path::{Path, PathBuf};
+
+/// Methanol, with bonds along several directions.
+fn methanol() -> Molecule {
+    Molecule {
+        atoms: vec![
+            atom("C", 0.0, 0.0, 0.0),
+            atom("O", 1.43, 0.0, 0.0),
+            atom("H", -0.36, 1.03, 0.0),
+            atom("H", -0.36, -0.51, 0.89),
+            atom("H", -0.36, -0.51, -0.89),
+            atom("H", 1.75, -0.9, 0.0),
+        ],
+        bonds: vec![bond(0, 1), bond(0, 2), bond(0, 3), bond(0, 4), bond(1, 5)],
+        ..Default::default()
+    }
+}
+
+/// A puckered four-membered ring, so ring coloring has something to show.
+fn cyclobutane() -> Molecule {
+    Molecule {
+        atoms: vec![
+            atom("C", 0.0, 0.0, 0.0),
+            atom("C", 1.55, 0.0, 0.2),
+            atom("C", 1.55, 1.55, 0.0),
+            atom("C", 0.0, 1.55, 0.2),
+        ],
+        bonds: vec![bond(0, 1), bond(1, 2), bond(2, 3), bond(3, 0)],
+        ..Default::default()
+    }
+}
+
+fn snapshot<U: AdditionalRender>(viewer: &mut MoleculeViewer<U>) -> String {
+    let mut scene = Scene::default();
+    viewer.dirty = true;
+    viewer.update_scene(&mut scene);
+    scene_snapshot(viewer, &scene)
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.snap"))
+}
+
+fn assert_golden(name: &str, actual: &str) {
+    let path = golden_path(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "cannot read {}: {e}; run with UPDATE_GOLDEN=1 to create it",
+            path.display()
+        )
+    });
+    if let Some(diff) = snapshot_diff(&expected, actual) {
+        panic!(
+            "scene differs from {}:\n{diff}run with UPDATE_GOLDEN=1 to accept the change",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn test_golden_ball_and_stick() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(methanol());
+    assert_golden("ball_and_stick", &snapshot(&mut viewer));
+}
+
+#[test]
+fn test_golden_space_filling() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(methanol());
+    viewer.set_render_style(RenderStyle::SpaceFilling);
+    assert_golden("space_filling", &snapshot(&mut viewer));
+}
+
+#[test]
+fn test_golden_ring_planarity_colors() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(cyclobutane());
+    viewer.settings.color_rings_by_planarity = true;
+    assert_golden("ring_planarity_colors", &snapshot(&mut viewer));
+}
+
+#[test]
+fn test_golden_selection_highlight() {
+    let mut viewer = MoleculeViewer::new();
+    viewer.set_molecule(methanol());
+    let mut selection = SelectedAtomRender::new();
+    selection.add_atom(1);
+    selection.add_atom(5);
+    viewer.additional_render = Some(Box::new(selection));
+    assert_golden("selection_highlight", &snapshot(&mut viewer));
+}
+
+#[test]
+fn test_golden_ground_disc() {
+    let mut viewer = MoleculeViewer::new();
+    viewer.set_molecule(methanol());
+    viewer.additional_render = Some(Box::new(GroundDiscRender::new()));
+    assert_golden("ground_disc", &snapshot(&mut viewer));
+}
+
+#[test]
+fn test_snapshot_diff_reports_changed_lines() {
+    assert_eq!(snapshot_diff("a\nb\n", "a\nb\n"), None);
+    let diff = snapshot_diff("a\nb\n", "a\nc\nd\n").unwrap();
+    assert_eq!(diff, "   2 -b\n   2 +c\n   3 +d\n");
+}
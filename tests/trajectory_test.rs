@@ -0,0 +1,229 @@
+mod common;
+
+use common::atom;
+use graphics::Scene;
+use moleucle_3dview_rs::additional_render::TrailColor;
+use moleucle_3dview_rs::camera::{Camera, OrbitalCamera};
+use moleucle_3dview_rs::colormap;
+use moleucle_3dview_rs::molecule::MoleculeError;
+use moleucle_3dview_rs::viewer::{AddOptions, ViewerEvent};
+use moleucle_3dview_rs::{
+    Molecule, MoleculeViewer, SelectedAtomRender, TrailRender, Trajectory, ViewerCommand,
+};
+use nalgebra::Point3;
+
+const W: f32 = 800.0;
+const H: f32 = 600.0;
+
+/// Atom 0 stays at y = -2 while atom 1 falls from y = 2 by 0.5 per frame.
+fn falling() -> (Molecule, Trajectory) {
+    let mol = Molecule {
+        atoms: vec![atom("C", 0.0, -2.0, 0.0), atom("C", 0.0, 2.0, 0.0)],
+        ..Default::default()
+    };
+    let frames = (0..10)
+        .map(|k| {
+            vec![
+                Point3::new(0.0, -2.0, 0.0),
+                Point3::new(0.0, 2.0 - 0.5 * k as f32, 0.0),
+            ]
+        })
+        .collect();
+    (mol, Trajectory::new(frames).unwrap())
+}
+
+fn to_screen(camera: &OrbitalCamera, p: Point3<f32>) -> (f32, f32) {
+    let ndc = camera.view_projection().transform_point(&p);
+    ((ndc.x + 1.0) * 0.5 * W, (1.0 - ndc.y) * 0.5 * H)
+}
+
+#[test]
+fn test_trajectory_validation_and_interpolation() {
+    let ragged = vec![vec![Point3::origin()], vec![]];
+    assert!(Trajectory::new(ragged).is_err());
+
+    let (mol, trajectory) = falling();
+    assert_eq!(trajectory.len(), 10);
+    assert_eq!(
+        trajectory.positions_at(4.5)[1],
+        Point3::new(0.0, -0.25, 0.0)
+    );
+    assert_eq!(
+        trajectory.positions_at(99.0)[1],
+        Point3::new(0.0, -2.5, 0.0)
+    );
+
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    assert!(viewer.set_trajectory(trajectory.clone()).is_err());
+    viewer.set_molecule(Molecule {
+        atoms: vec![atom("C", 0.0, 0.0, 0.0)],
+        ..Default::default()
+    });
+    assert!(viewer.set_trajectory(trajectory.clone()).is_err());
+    viewer.set_molecule(mol);
+    assert!(viewer.set_trajectory(trajectory).is_ok());
+}
+
+#[test]
+fn test_pick_follows_current_frame() {
+    let (mol, trajectory) = falling();
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(mol);
+    viewer.set_trajectory(trajectory).unwrap();
+    viewer.set_frame(5.0);
+
+    let mut camera = OrbitalCamera::default();
+    camera.set_aspect(W / H);
+
+    let shown = viewer.displayed_molecule().unwrap().atoms[1].position;
+    assert_eq!(shown, Point3::new(0.0, -0.5, 0.0));
+    let (u, v) = to_screen(&camera, shown);
+    let (origin, dir) = camera.ray_from_screen(u, v, W, H);
+    assert!(matches!(
+        viewer.pick(origin, dir),
+        Some(ViewerEvent::AtomClicked(1))
+    ));
+
+    // Nothing is left where the atom starts in the topology.
+    let (u, v) = to_screen(&camera, Point3::new(0.0, 2.0, 0.0));
+    let (origin, dir) = camera.ray_from_screen(u, v, W, H);
+    assert!(matches!(
+        viewer.pick(origin, dir),
+        Some(ViewerEvent::NothingClicked)
+    ));
+
+    viewer.clear_trajectory();
+    assert!(matches!(
+        viewer.pick(origin, dir),
+        Some(ViewerEvent::AtomClicked(1))
+    ));
+}
+
+#[test]
+fn test_trail_follows_playback() {
+    let (mol, trajectory) = falling();
+    let mut viewer = MoleculeViewer::new();
+    viewer.set_molecule(mol);
+    let mut trail = TrailRender::new();
+    trail.history = 4;
+    viewer.additional_render = Some(Box::new(trail));
+    viewer
+        .apply_command(ViewerCommand::Select(vec![1]))
+        .unwrap();
+    viewer.set_trajectory(trajectory).unwrap();
+    for frame in 1..=6 {
+        viewer.set_frame(frame as f32);
+    }
+
+    // Only the last `history` frames are kept.
+    let overlay = viewer.additional_render.as_ref().unwrap();
+    assert_eq!(overlay.frames().collect::<Vec<_>>(), [3.0, 4.0, 5.0, 6.0]);
+    let ys: Vec<f32> = overlay.trails()[0].iter().map(|p| p.y).collect();
+    assert_eq!(ys, [0.5, 0.0, -0.5, -1.0]);
+
+    // Four samples of one atom: three segments after the atoms and the
+    // selected atom's shell.
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    let first_trail = viewer.drawn_atoms().len() + 1;
+    assert_eq!(scene.entities.len(), first_trail + 3);
+    let first = scene.entities[first_trail].color;
+    let last = scene.entities[first_trail + 2].color;
+    assert_eq!(last, colormap::rainbow(1.0));
+    assert_ne!(first, last);
+
+    // Colored by a per-frame value; refresh swaps just the trail.
+    let overlay = viewer.additional_render.as_mut().unwrap();
+    overlay.color = TrailColor::ByValue((0..10).map(|k| (k % 4) as f32).collect());
+    assert!(viewer.refresh_overlays(&mut scene));
+    assert_eq!(scene.entities.len(), first_trail + 3);
+    // Frame 6 has value 2 of 0..=3.
+    assert_eq!(
+        scene.entities[first_trail + 2].color,
+        colormap::rainbow(2.0 / 3.0)
+    );
+
+    // Stepping back, reselecting or clearing the trajectory starts over.
+    viewer.set_frame(2.0);
+    assert_eq!(
+        viewer.additional_render.as_ref().unwrap().frames().count(),
+        1
+    );
+    viewer.set_frame(3.0);
+    viewer
+        .apply_command(ViewerCommand::Select(vec![0, 1]))
+        .unwrap();
+    assert!(viewer
+        .additional_render
+        .as_ref()
+        .unwrap()
+        .trails()
+        .is_empty());
+    viewer.set_frame(4.0);
+    let overlay = viewer.additional_render.as_ref().unwrap();
+    assert_eq!((overlay.trails().len(), overlay.frames().count()), (2, 1));
+    viewer.clear_trajectory();
+    assert_eq!(
+        viewer.additional_render.as_ref().unwrap().frames().count(),
+        0
+    );
+}
+
+#[test]
+fn test_switching_molecules_resets_trails() {
+    let (mol, trajectory) = falling();
+    let mut viewer = MoleculeViewer::new();
+    viewer.set_molecule(mol.clone());
+    viewer.add_molecule_with(mol.clone(), AddOptions { force: true });
+    viewer.additional_render = Some(Box::new(TrailRender::new()));
+    viewer.select_atom(1);
+    viewer.set_trajectory(trajectory).unwrap();
+    for frame in 1..=3 {
+        viewer.set_frame(frame as f32);
+    }
+    let overlay = viewer.additional_render.as_ref().unwrap();
+    assert_eq!(overlay.frames().count(), 4);
+
+    // The other molecule starts without the first one's frame history.
+    assert!(viewer.set_active_molecule(1));
+    assert!(viewer.trajectory().is_none());
+    assert_eq!(
+        viewer.additional_render.as_ref().unwrap().frames().count(),
+        0
+    );
+}
+
+#[test]
+fn test_xyz_trajectory_reads_every_frame() {
+    let frame = |k: usize| {
+        let y = 0.1 * k as f32;
+        format!(
+            "3\nframe {k}\nO 0.0 {y} 0.0\nH 0.96 {y} 0.0\nH -0.24 {} 0.0\n",
+            y + 0.93
+        )
+    };
+    let content = format!("{}{}\n{}\n", frame(0), frame(1), frame(2));
+    let (mol, trajectory) = Molecule::from_xyz_trajectory_str(&content).unwrap();
+    assert_eq!((mol.atoms.len(), mol.bonds.len()), (3, 2));
+    assert_eq!(trajectory.len(), 3);
+    assert_eq!(trajectory.frame(2).unwrap()[0], Point3::new(0.0, 0.2, 0.0));
+
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(mol);
+    viewer.set_trajectory(trajectory).unwrap();
+
+    let swapped = content.replacen("O 0.0 0.1", "N 0.0 0.1", 1);
+    let err = Molecule::from_xyz_trajectory_str(&swapped).unwrap_err();
+    assert!(
+        matches!(&err, MoleculeError::FrameMismatch { frame: 1, reason } if reason == "atom 1 is N, in frame 0 O"),
+        "{err}"
+    );
+    let short = format!("{}2\n\nO 0 0 0\nH 1 0 0\n", frame(0));
+    let err = Molecule::from_xyz_trajectory_str(&short).unwrap_err();
+    assert_eq!(err.to_string(), "xyz frame 1: 2 atoms, frame 0 has 3");
+    let truncated = format!("{}3\n\nO 0 0 0\n", frame(0));
+    assert!(Molecule::from_xyz_trajectory_str(&truncated).is_err());
+    // A single frame is a trajectory of one.
+    let (_, single) = Molecule::from_xyz_trajectory_str(&frame(0)).unwrap();
+    assert_eq!(single.len(), 1);
+}
@@ -0,0 +1,89 @@
+use moleucle_3dview_rs::contacts::{contact_report, contacts_between};
+use moleucle_3dview_rs::{LengthUnit, Molecule};
+
+const WATER_XYZ: &str = "3
+water
+O    0.000000    0.000000    0.117300
+H    0.000000    0.757200   -0.469200
+H    0.000000   -0.757200   -0.469200
+";
+
+const WATER_GRO: &str = "water and a chloride ion
+    4
+    1SOL     OW    1   0.000   0.000   0.012
+    1SOL    HW1    2   0.000   0.076  -0.047
+    1SOL    HW2    3   0.000  -0.076  -0.047
+    1CL      CL    4   1.000   1.000   1.000
+   2.00000   2.00000   2.00000
+";
+
+#[test]
+fn test_gro_and_xyz_agree_in_angstrom() {
+    let xyz = Molecule::from_xyz_str(WATER_XYZ).unwrap();
+    let gro = Molecule::from_gro_str(WATER_GRO).unwrap();
+
+    assert_eq!(xyz.metadata.source_unit, LengthUnit::Angstrom);
+    assert_eq!(gro.metadata.source_unit, LengthUnit::Nanometer);
+    assert_eq!(gro.metadata.unit, LengthUnit::Angstrom);
+
+    // .gro keeps three decimals of nm, i.e. 0.01 Å.
+    for (a, b) in [(0, 1), (0, 2)] {
+        let from_xyz = xyz.distance(a, b, None).unwrap();
+        let from_gro = gro.distance(a, b, None).unwrap();
+        assert!((from_xyz - 0.9572).abs() < 1e-3, "{from_xyz}");
+        assert!(
+            (from_xyz - from_gro).abs() < 0.02,
+            "{from_xyz} vs {from_gro}"
+        );
+    }
+
+    let elements: Vec<_> = gro.atoms.iter().map(|a| a.element.as_str()).collect();
+    assert_eq!(elements, ["O", "H", "H", "CL"]);
+}
+
+#[test]
+fn test_gro_names_with_leading_digits() {
+    let gro = Molecule::from_gro_str(
+        "alanine side chain, old-style hydrogen names
+    3
+    1ALA     CB    1   0.000   0.000   0.000
+    1ALA    1HB    2   0.100   0.000   0.000
+    1ALA   2HB1    3   0.000   0.100   0.000
+   2.00000   2.00000   2.00000
+",
+    )
+    .unwrap();
+    let elements: Vec<_> = gro.atoms.iter().map(|a| a.element.as_str()).collect();
+    assert_eq!(elements, ["C", "H", "H"]);
+}
+
+#[test]
+fn test_convert_units_and_measure() {
+    let mut water = Molecule::from_xyz_str(WATER_XYZ).unwrap();
+    let angstrom = water.distance(0, 1, None).unwrap();
+    let nm = water.distance(0, 1, Some(LengthUnit::Nanometer)).unwrap();
+    assert!((nm * 10.0 - angstrom).abs() < 1e-6);
+    let bohr = water.distance(0, 1, Some(LengthUnit::Bohr)).unwrap();
+    assert!((bohr - angstrom / 0.529_177_2).abs() < 1e-5);
+    assert_eq!(water.distance(0, 9, None), None);
+
+    water.convert_units(LengthUnit::Nanometer);
+    assert_eq!(water.metadata.unit, LengthUnit::Nanometer);
+    assert!((water.atoms[0].position.z - 0.01173).abs() < 1e-6);
+    assert!((water.distance(0, 1, None).unwrap() - nm).abs() < 1e-6);
+    assert!((water.distance(0, 1, Some(LengthUnit::Angstrom)).unwrap() - angstrom).abs() < 1e-5);
+
+    water.convert_units(LengthUnit::Angstrom);
+    assert!((water.atoms[0].position.z - 0.1173).abs() < 1e-6);
+}
+
+#[test]
+fn test_report_names_unit_and_bad_files_fail() {
+    let water = Molecule::from_xyz_str(WATER_XYZ).unwrap();
+    let report = contact_report(&water, &water, &contacts_between(&water, &water, 1.0));
+    assert!(report.lines().next().unwrap().contains("distance (Å)"));
+
+    assert!(Molecule::from_xyz_str("").is_err());
+    assert!(Molecule::from_xyz_str("2\n\nO 0 0 0\n").is_err());
+    assert!(Molecule::from_gro_str("t\n1\n    1SOL     OW    1   x\n").is_err());
+}
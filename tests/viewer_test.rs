@@ -0,0 +1,1283 @@
+mod common;
+
+use common::atom;
+use graphics::{Mesh, Scene};
+use lin_alg::f32::Vec3;
+use moleucle_3dview_rs::colormap::cpk_color;
+use moleucle_3dview_rs::convert::{quat_to_na, vec_to_na};
+use moleucle_3dview_rs::element;
+use moleucle_3dview_rs::molecule::{Atom, Bond};
+use moleucle_3dview_rs::snapshot::scene_snapshot;
+use moleucle_3dview_rs::viewer::{
+    AddOptions, AddResult, Detail, EntityKind, LoadOptions, RenderStyle, Slab, ViewerEvent,
+    VisibilityMode, ATOM_RADIUS, AUTO_STYLE_FULL_DETAIL_MAX, AUTO_STYLE_STICK_MAX, BOND_RADIUS,
+    BOND_STUB_FRACTION, PICK_INDEX_MIN_ATOMS, REFERENCE_AXIS_COLOR, TOON_BOND_MESHES,
+    TOON_LIGHT_DIR, WIREFRAME_RADIUS,
+};
+use moleucle_3dview_rs::{
+    AtomRadiiTable, BondColoring, Camera, CompositionOptions, Cutaway, DisplayList,
+    GroundDiscRender, IndexGlyphRender, Molecule, MoleculeViewer, OrbitalCamera, PickTarget,
+    ProjectionType, SelectedAtomRender, Shading,
+};
+use nalgebra::{Point2, Point3, Vector3};
+use std::path::Path;
+
+const FORCE: AddOptions = AddOptions { force: true };
+const NO_AUTO_STYLE: LoadOptions = LoadOptions {
+    auto_style: false,
+    auto_fit: false,
+};
+
+fn benzene() -> Molecule {
+    Molecule::from_mol2(Path::new("Benzene.mol2")).unwrap()
+}
+
+fn water() -> Molecule {
+    let bond = |atom_b| Bond {
+        atom_a: 0,
+        atom_b,
+        order: 1,
+    };
+    Molecule {
+        atoms: vec![
+            atom("O", 0.0, 0.0, 0.1173),
+            atom("H", 0.0, 0.7572, -0.4692),
+            atom("H", 0.0, -0.7572, -0.4692),
+        ],
+        bonds: vec![bond(1), bond(2)],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_classify_every_entity() {
+    let mol = benzene();
+    let n_atoms = mol.atoms.len();
+    let n_bonds = mol.bonds.len();
+
+    let mut viewer = MoleculeViewer::new();
+    viewer.set_molecule(mol);
+    let mut selection = SelectedAtomRender::new();
+    selection.add_atom(0);
+    selection.add_atom(3);
+    viewer.additional_render = Some(Box::new(selection));
+
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+
+    let mut atoms = 0;
+    let mut bonds = 0;
+    let mut overlays = 0;
+    for (i, entity) in scene.entities.iter().enumerate() {
+        match viewer
+            .classify_entity(i)
+            .expect("every entity is classified")
+        {
+            EntityKind::Atom { molecule, index } => {
+                assert_eq!(molecule, 0);
+                let atom = &viewer.molecule.as_ref().unwrap().atoms[index];
+                assert!((entity.position.x - atom.position.x).abs() < 1e-6);
+                assert!((entity.position.y - atom.position.y).abs() < 1e-6);
+                atoms += 1;
+            }
+            EntityKind::Bond { molecule, index } => {
+                assert_eq!(molecule, 0);
+                assert!(index < n_bonds);
+                bonds += 1;
+            }
+            EntityKind::Overlay { .. } => overlays += 1,
+            EntityKind::ReferenceAxis => panic!("no reference axis was set"),
+            EntityKind::Cutaway => panic!("no cutaway was set"),
+            EntityKind::PinMarker { .. } => panic!("no atom was pinned"),
+            EntityKind::SelectedAtom { .. } | EntityKind::SelectedBond { .. } => {
+                panic!("the viewer has no selection")
+            }
+        }
+    }
+
+    assert_eq!(atoms, n_atoms);
+    assert_eq!(bonds, n_bonds);
+    assert_eq!(overlays, 2);
+    assert_eq!(viewer.classify_entity(scene.entities.len()), None);
+}
+
+#[test]
+fn test_ambient_occlusion_darkens_buried_atoms() {
+    // A tight cluster around atom 0 plus one isolated atom far away.
+    let mut mol = Molecule::default();
+    mol.atoms.push(atom("C", 0.0, 0.0, 0.0));
+    for (x, y, z) in [
+        (1.4, 0.0, 0.0),
+        (-1.4, 0.0, 0.0),
+        (0.0, 1.4, 0.0),
+        (0.0, -1.4, 0.0),
+    ] {
+        mol.atoms.push(atom("C", x, y, z));
+    }
+    mol.atoms.push(atom("C", 50.0, 0.0, 0.0));
+
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(mol);
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    let flat_color = scene.entities[0].color;
+    assert_eq!(scene.entities[5].color, flat_color);
+
+    viewer.settings.ao_strength = 0.8;
+    viewer.dirty = true;
+    viewer.update_scene(&mut scene);
+
+    let buried = scene.entities[0].color;
+    let edge = scene.entities[1].color;
+    let isolated = scene.entities[5].color;
+    assert_eq!(isolated, flat_color);
+    assert!(
+        buried.0 < edge.0,
+        "{:?} should be darker than {:?}",
+        buried,
+        edge
+    );
+    assert!(edge.0 < flat_color.0);
+}
+
+#[test]
+fn test_index_glyphs() {
+    let mut viewer = MoleculeViewer::new();
+    viewer.set_molecule(benzene());
+    viewer.additional_render = Some(Box::new(IndexGlyphRender::new()));
+
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    let overlays = |viewer: &MoleculeViewer<IndexGlyphRender>, scene: &Scene| {
+        (0..scene.entities.len())
+            .filter(|&i| matches!(viewer.classify_entity(i), Some(EntityKind::Overlay { .. })))
+            .count()
+    };
+    // Atoms 0..=9 take one digit, 10 and 11 take two.
+    assert_eq!(overlays(&viewer, &scene), 14);
+    let meshes = scene.meshes.len();
+
+    scene.camera.orientation =
+        lin_alg::f32::Quaternion::from_axis_angle(lin_alg::f32::Vec3::new(0.0, 1.0, 0.0), 1.0);
+    assert!(viewer.refresh_overlays(&mut scene));
+    assert_eq!(overlays(&viewer, &scene), 14);
+    assert_eq!(scene.meshes.len(), meshes);
+
+    viewer.additional_render.as_mut().unwrap().max_atoms = 5;
+    viewer.dirty = true;
+    viewer.update_scene(&mut scene);
+    assert_eq!(overlays(&viewer, &scene), 0);
+}
+
+#[test]
+fn test_pick_hit_and_pick_all() {
+    // Three carbons stacked along z, unbonded, below the ray.
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(Molecule {
+        atoms: (0..3)
+            .map(|i| atom("C", 0.0, 0.0, 2.0 * i as f32))
+            .collect(),
+        ..Default::default()
+    });
+    let origin = Vec3::new(0.1, 0.0, 10.0);
+    // Not normalized: `t` counts in multiples of it.
+    let down = Vec3::new(0.0, 0.0, -2.0);
+
+    let hit = viewer.pick_hit(origin, down).unwrap();
+    assert_eq!(hit.target, PickTarget::Atom(2));
+    let expected = Point3::new(origin.x, origin.y, origin.z) + Vector3::new(0.0, 0.0, -2.0) * hit.t;
+    assert!((hit.world_pos - expected).norm() < 1e-5);
+    // On the top of the sphere.
+    let radius = (hit.world_pos - Point3::new(0.0, 0.0, 4.0)).norm();
+    assert!(
+        hit.world_pos.z > 4.0 && radius > 0.1 && radius < 2.0,
+        "{radius}"
+    );
+    assert_eq!(viewer.pick(origin, down), Some(ViewerEvent::AtomClicked(2)));
+
+    // Behind it, the other two, nearest first.
+    let all = viewer.pick_all(origin, down);
+    let targets: Vec<_> = all.iter().map(|hit| hit.target).collect();
+    assert_eq!(
+        targets,
+        [
+            PickTarget::Atom(2),
+            PickTarget::Atom(1),
+            PickTarget::Atom(0)
+        ]
+    );
+    assert_eq!(all[0], hit);
+    assert!(all.windows(2).all(|w| w[0].t < w[1].t));
+
+    let beside = Vec3::new(3.0, 0.0, 10.0);
+    assert_eq!(viewer.pick_hit(beside, down), None);
+    assert!(viewer.pick_all(beside, down).is_empty());
+}
+
+fn lattice(n_atoms: usize) -> Molecule {
+    let atoms = (0..n_atoms)
+        .map(|i| {
+            let element = if i % 2 == 0 { "C" } else { "H" };
+            atom(
+                element,
+                (i % 100) as f32,
+                ((i / 100) % 100) as f32,
+                (i / 10_000) as f32,
+            )
+        })
+        .collect();
+    Molecule {
+        atoms,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_auto_style_brackets() {
+    let cases = [
+        (10, RenderStyle::BallAndStick, Detail::Full, false),
+        (
+            AUTO_STYLE_FULL_DETAIL_MAX,
+            RenderStyle::BallAndStick,
+            Detail::Full,
+            false,
+        ),
+        (
+            AUTO_STYLE_FULL_DETAIL_MAX + 1,
+            RenderStyle::Stick,
+            Detail::Low,
+            true,
+        ),
+        (AUTO_STYLE_STICK_MAX, RenderStyle::Stick, Detail::Low, true),
+        (
+            AUTO_STYLE_STICK_MAX + 1,
+            RenderStyle::SpaceFilling,
+            Detail::Low,
+            false,
+        ),
+    ];
+    for (n_atoms, style, detail, hide_hydrogens) in cases {
+        let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+        viewer.set_molecule(lattice(n_atoms));
+        assert_eq!(viewer.render_style, style, "{} atoms", n_atoms);
+        assert_eq!(viewer.settings.detail, detail, "{} atoms", n_atoms);
+        assert_eq!(
+            viewer.settings.hide_hydrogens, hide_hydrogens,
+            "{} atoms",
+            n_atoms
+        );
+        let events = viewer.take_events();
+        assert!(matches!(events[..], [ViewerEvent::AutoStyleApplied(s)] if s == style));
+        assert!(viewer.take_events().is_empty());
+    }
+}
+
+#[test]
+fn test_auto_style_can_be_overridden() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule_with(lattice(AUTO_STYLE_STICK_MAX + 1), NO_AUTO_STYLE);
+    assert_eq!(viewer.render_style, RenderStyle::BallAndStick);
+    assert!(viewer.take_events().is_empty());
+
+    viewer.set_molecule(lattice(AUTO_STYLE_FULL_DETAIL_MAX + 1));
+    viewer.set_render_style(RenderStyle::SpaceFilling);
+    viewer.settings.hide_hydrogens = false;
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    // Every atom drawn, no bonds.
+    assert_eq!(scene.entities.len(), AUTO_STYLE_FULL_DETAIL_MAX + 1);
+}
+
+#[test]
+fn test_render_styles_on_water() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule_with(water(), NO_AUTO_STYLE);
+    let mut scene = Scene::default();
+    let kinds = |viewer: &MoleculeViewer<SelectedAtomRender>, scene: &Scene| {
+        (0..scene.entities.len())
+            .map(|i| viewer.classify_entity(i).unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    viewer.set_render_style(RenderStyle::SpaceFilling);
+    viewer.update_scene(&mut scene);
+    let drawn = kinds(&viewer, &scene);
+    assert_eq!(drawn.len(), 3);
+    assert!(drawn
+        .iter()
+        .all(|kind| matches!(kind, EntityKind::Atom { .. })));
+    assert!(scene.entities[0].scale > scene.entities[1].scale);
+
+    viewer.settings.stick_radius = 0.3;
+    viewer.set_render_style(RenderStyle::Stick);
+    viewer.update_scene(&mut scene);
+    assert_eq!(scene.entities.len(), 5);
+    assert_eq!(scene.entities[0].scale, 0.3);
+    assert_eq!(scene.entities[3].scale_partial.unwrap().x, 0.3);
+
+    // Wireframe draws the bonds alone, whatever the stick radius.
+    viewer.set_render_style(RenderStyle::Wireframe);
+    viewer.update_scene(&mut scene);
+    let drawn = kinds(&viewer, &scene);
+    assert!(drawn
+        .iter()
+        .all(|kind| matches!(kind, EntityKind::Bond { .. })));
+    assert_eq!(drawn.len(), 2);
+    for entity in &scene.entities {
+        assert_eq!(entity.scale_partial.unwrap().x, WIREFRAME_RADIUS);
+    }
+
+    // An atom left without drawn bonds keeps its joint.
+    viewer.settings.hidden_atoms.insert(2);
+    viewer.dirty = true;
+    viewer.update_scene(&mut scene);
+    assert_eq!(
+        kinds(&viewer, &scene),
+        [EntityKind::Bond {
+            molecule: 0,
+            index: 0
+        }]
+    );
+    viewer.settings.hidden_atoms.insert(0);
+    viewer.dirty = true;
+    viewer.update_scene(&mut scene);
+    assert_eq!(
+        kinds(&viewer, &scene),
+        [EntityKind::Atom {
+            molecule: 0,
+            index: 1
+        }]
+    );
+    assert_eq!(scene.entities[0].scale, WIREFRAME_RADIUS);
+}
+
+#[test]
+fn test_split_bond_coloring() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule_with(water(), NO_AUTO_STYLE);
+    viewer.settings.bond_coloring = BondColoring::SplitByElement;
+    viewer.settings.color_overrides.insert(2, (0.0, 0.0, 1.0));
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+
+    // Two halves per bond, both picked as the bond.
+    let bonds: Vec<_> = (3..scene.entities.len())
+        .map(|i| viewer.classify_entity(i).unwrap())
+        .collect();
+    let half = |index| EntityKind::Bond { molecule: 0, index };
+    assert_eq!(bonds, [half(0), half(0), half(1), half(1)]);
+
+    let cylinders = DisplayList::from_scene(&viewer, &scene).cylinders;
+    let mol = viewer.molecule.as_ref().unwrap();
+    let midpoint = nalgebra::center(&mol.atoms[0].position, &mol.atoms[1].position);
+    assert!((cylinders[0].start - mol.atoms[0].position).norm() < 1e-4);
+    assert!((cylinders[0].end - midpoint).norm() < 1e-4);
+    assert!((cylinders[1].start - midpoint).norm() < 1e-4);
+    let colors: Vec<_> = cylinders.iter().map(|c| c.color).collect();
+    assert_eq!(
+        colors,
+        [
+            cpk_color("O"),
+            cpk_color("H"),
+            cpk_color("O"),
+            (0.0, 0.0, 1.0)
+        ]
+    );
+
+    // A stub short of the midpoint stays whole, in its atom's color.
+    viewer.settings.bond_stubs = Some(0.1);
+    viewer.settings.hidden_atoms.insert(0);
+    viewer.dirty = true;
+    viewer.update_scene(&mut scene);
+    let list = DisplayList::from_scene(&viewer, &scene);
+    assert_eq!(list.cylinders.len(), 2);
+    assert_eq!(list.cylinders[0].color, cpk_color("H"));
+
+    viewer.settings.bond_coloring = BondColoring::Uniform((1.0, 1.0, 0.0));
+    viewer.settings.hidden_atoms.clear();
+    viewer.dirty = true;
+    viewer.update_scene(&mut scene);
+    let cylinders = DisplayList::from_scene(&viewer, &scene).cylinders;
+    assert_eq!(cylinders.len(), 2);
+    assert!(cylinders.iter().all(|c| c.color == (1.0, 1.0, 0.0)));
+}
+
+#[test]
+fn test_bond_order_lines_face_the_camera() {
+    // Bonds of order 2, 3, aromatic and 1, each 1.4 Å along x.
+    let mut mol = Molecule::default();
+    for (k, order) in [2, 3, 0, 1].into_iter().enumerate() {
+        let y = 3.0 * k as f32;
+        mol.atoms.push(atom("C", 0.0, y, 0.0));
+        mol.atoms.push(atom("C", 1.4, y, 0.0));
+        mol.bonds.push(Bond {
+            atom_a: 2 * k,
+            atom_b: 2 * k + 1,
+            order,
+        });
+    }
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule_with(mol, NO_AUTO_STYLE);
+    let mut scene = Scene::default();
+    let lines_per_bond = |viewer: &MoleculeViewer<SelectedAtomRender>, scene: &Scene| {
+        let mut counts = [0; 4];
+        for i in 0..scene.entities.len() {
+            if let Some(EntityKind::Bond { index, .. }) = viewer.classify_entity(i) {
+                counts[index] += 1;
+            }
+        }
+        counts
+    };
+    viewer.update_scene(&mut scene);
+    assert_eq!(lines_per_bond(&viewer, &scene), [1; 4]);
+
+    viewer.settings.show_bond_order = true;
+    viewer.dirty = true;
+    viewer.update_scene(&mut scene);
+    // The aromatic bond is a solid line and five dashes.
+    assert_eq!(lines_per_bond(&viewer, &scene), [2, 3, 6, 1]);
+    let cylinders = DisplayList::from_scene(&viewer, &scene).cylinders;
+    let (a, b) = (&cylinders[0], &cylinders[1]);
+    assert_eq!(a.radius, BOND_RADIUS / 2.0);
+    // Side by side across the screen, and together as wide as one bond.
+    let spread = a.start - b.start;
+    assert!((spread.y.abs() - BOND_RADIUS).abs() < 1e-5, "{spread:?}");
+    assert!(spread.x.abs() < 1e-5 && spread.z.abs() < 1e-5);
+    // The middle triple bond line is in front.
+    let middle = cylinders[2].start;
+    assert!(
+        (middle.y - 3.0).abs() < 1e-5 && middle.z < 0.0,
+        "{middle:?}"
+    );
+
+    // Looking down -y instead, the lines spread along z.
+    scene.camera.orientation = lin_alg::f32::Quaternion::from_axis_angle(
+        lin_alg::f32::Vec3::new(1.0, 0.0, 0.0),
+        std::f32::consts::FRAC_PI_2,
+    );
+    viewer.update_scene(&mut scene);
+    let cylinders = DisplayList::from_scene(&viewer, &scene).cylinders;
+    let spread = cylinders[0].start - cylinders[1].start;
+    assert!((spread.z.abs() - BOND_RADIUS).abs() < 1e-5, "{spread:?}");
+    assert!(spread.y.abs() < 1e-5);
+}
+
+#[test]
+fn test_bond_order_lines_lie_in_the_ring() {
+    // Aromatic ring flat in the xz plane, seen edge-on by the default camera.
+    let mut mol = Molecule::default();
+    for k in 0..6 {
+        let angle = std::f32::consts::FRAC_PI_3 * k as f32;
+        mol.atoms
+            .push(atom("C", 1.4 * angle.cos(), 0.0, 1.4 * angle.sin()));
+        mol.bonds.push(Bond {
+            atom_a: k,
+            atom_b: (k + 1) % 6,
+            order: 0,
+        });
+    }
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.settings.show_bond_order = true;
+    viewer.set_molecule_with(mol, NO_AUTO_STYLE);
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    let cylinders = DisplayList::from_scene(&viewer, &scene).cylinders;
+    assert_eq!(cylinders.len(), 6 * 6);
+    for c in &cylinders {
+        assert!(c.start.y.abs() < 1e-5 && c.end.y.abs() < 1e-5, "{c:?}");
+    }
+    // The dashes are on the inside.
+    let from_center = |p: Point3<f32>| p.coords.norm();
+    let (solid, dash) = (&cylinders[0], &cylinders[1]);
+    assert!(from_center(dash.start) < from_center(solid.start));
+}
+
+#[test]
+fn test_auto_fit_frames_a_new_molecule() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    let mut camera = OrbitalCamera::default();
+    assert!(!viewer.apply_auto_fit(&mut camera));
+
+    // Far off the origin, as crystal coordinates often are.
+    let mut mol = benzene();
+    for atom in &mut mol.atoms {
+        atom.position += Vector3::new(50.0, -20.0, 5.0);
+    }
+    viewer.set_molecule(mol);
+    assert!(viewer.apply_auto_fit(&mut camera));
+    assert!(!viewer.apply_auto_fit(&mut camera));
+
+    let mol = viewer.molecule.as_ref().unwrap();
+    let (min, max) = mol.frame_bounds();
+    assert!((camera.target() - nalgebra::center(&min, &max)).norm() < 1e-4);
+    // Looking down -z as before, with every atom on screen.
+    assert!((camera.position() - camera.target()).normalize().z > 0.999);
+    let view_projection = camera.view_projection();
+    for atom in &mol.atoms {
+        let ndc = view_projection.transform_point(&atom.position);
+        assert!(ndc.x.abs() < 1.0 && ndc.y.abs() < 1.0, "{ndc:?}");
+    }
+    let distance = (camera.position() - camera.target()).norm();
+
+    // A bigger molecule is viewed from further back, and the orthographic
+    // view grows to match.
+    let mut big = benzene();
+    for atom in &mut big.atoms {
+        atom.position.coords *= 3.0;
+    }
+    camera.set_projection(ProjectionType::Orthographic);
+    viewer.set_molecule(big);
+    viewer.apply_auto_fit(&mut camera);
+    assert_eq!(camera.projection(), ProjectionType::Orthographic);
+    let (min, max) = viewer.molecule.as_ref().unwrap().frame_bounds();
+    assert!(camera.ortho_scale > (max - min).norm());
+    assert!((camera.position() - camera.target()).norm() > 2.0 * distance);
+
+    // Loads can opt out.
+    viewer.set_molecule_with(benzene(), NO_AUTO_STYLE);
+    assert!(!viewer.apply_auto_fit(&mut camera));
+}
+
+#[test]
+fn test_slab_cuts_crossing_bonds() {
+    let mol = Molecule {
+        atoms: vec![atom("C", 0.0, 0.0, 0.0), atom("C", 4.0, 0.0, 0.0)],
+        bonds: vec![Bond {
+            atom_a: 0,
+            atom_b: 1,
+            order: 1,
+        }],
+        ..Default::default()
+    };
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(mol);
+    viewer.settings.slab = Some(Slab::new(Point3::origin(), Vector3::x(), 1.0));
+
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    // Kept atom, bond clipped at x = 1, and the cap at the cut.
+    assert_eq!(scene.entities.len(), 3);
+    assert_eq!(
+        viewer.classify_entity(1),
+        Some(EntityKind::Bond {
+            molecule: 0,
+            index: 0
+        })
+    );
+    assert!((scene.entities[1].position.x - 0.5).abs() < 1e-5);
+    assert!((scene.entities[2].position.x - 1.0).abs() < 1e-5);
+    assert_eq!(viewer.classify_entity(2), viewer.classify_entity(1));
+
+    let down = Vec3::new(0.0, 0.0, -1.0);
+    let hit = viewer.pick(Vec3::new(0.8, 0.0, 10.0), down);
+    assert!(matches!(hit, Some(ViewerEvent::BondClicked(0))));
+    let miss = viewer.pick(Vec3::new(3.0, 0.0, 10.0), down);
+    assert!(matches!(miss, Some(ViewerEvent::NothingClicked)));
+
+    // Without caps only the shortened bond remains.
+    viewer.settings.slab_cap_color = None;
+    viewer.dirty = true;
+    viewer.update_scene(&mut scene);
+    assert_eq!(scene.entities.len(), 2);
+}
+
+#[test]
+fn test_ghosted_filters() {
+    let mol = Molecule {
+        atoms: vec![atom("C", 0.0, 0.0, 0.0), atom("H", 1.0, 0.0, 0.0)],
+        bonds: vec![Bond {
+            atom_a: 0,
+            atom_b: 1,
+            order: 1,
+        }],
+        ..Default::default()
+    };
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(mol);
+    viewer.settings.hide_hydrogens = true;
+    viewer.settings.hydrogen_mode = VisibilityMode::Ghost { opacity: 0.3 };
+
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    // Both atoms and the bond are drawn; the hydrogen and its bond ghosted.
+    assert_eq!(scene.entities.len(), 3);
+    assert_eq!(scene.entities[0].opacity, 1.0);
+    assert_eq!(scene.entities[1].opacity, 0.3);
+    assert_eq!(scene.entities[2].opacity, 0.3);
+
+    let down = Vec3::new(0.0, 0.0, -1.0);
+    let on_hydrogen = Vec3::new(1.0, 0.0, 10.0);
+    assert!(matches!(
+        viewer.pick(on_hydrogen, down),
+        Some(ViewerEvent::NothingClicked)
+    ));
+    viewer.settings.pick_ghosts = true;
+    assert!(matches!(
+        viewer.pick(on_hydrogen, down),
+        Some(ViewerEvent::AtomClicked(1))
+    ));
+
+    // A fainter ghosting slab wins over the hydrogen filter; a hiding one hides.
+    viewer.settings.slab = Some(Slab::new(Point3::origin(), Vector3::x(), 0.5));
+    viewer.settings.slab_mode = VisibilityMode::Ghost { opacity: 0.1 };
+    assert_eq!(
+        viewer.atom_opacity(&viewer.molecule.as_ref().unwrap().atoms[1]),
+        Some(0.1)
+    );
+    viewer.settings.slab_mode = VisibilityMode::Hide;
+    assert_eq!(
+        viewer.atom_opacity(&viewer.molecule.as_ref().unwrap().atoms[1]),
+        None
+    );
+}
+
+#[test]
+fn test_cutaway_hides_atoms_inside() {
+    let bond = |atom_a, atom_b| Bond {
+        atom_a,
+        atom_b,
+        order: 1,
+    };
+    let mol = Molecule {
+        atoms: vec![
+            atom("C", 0.0, 0.0, 0.0),
+            atom("C", 1.5, 0.0, 0.0),
+            atom("C", 3.0, 0.0, 0.0),
+        ],
+        bonds: vec![bond(0, 1), bond(1, 2)],
+        ..Default::default()
+    };
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(mol);
+    let cutaway = Cutaway {
+        center: Point3::new(3.5, 0.0, 0.0),
+        radius: 1.0,
+        mode: VisibilityMode::Hide,
+    };
+    assert!(viewer.handle_pick(&ViewerEvent::CutawayChanged(cutaway)));
+    assert_eq!(viewer.cutaway(), Some(cutaway));
+    assert_eq!(viewer.drawn_atoms(), [0, 1]);
+
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    let kinds: Vec<_> = (0..scene.entities.len())
+        .map(|i| viewer.classify_entity(i).unwrap())
+        .collect();
+    // Two atoms, the bond between them, then the wireframe.
+    assert!(matches!(kinds[2], EntityKind::Bond { index: 0, .. }));
+    assert!(kinds[3..].iter().all(|&kind| kind == EntityKind::Cutaway));
+    assert!(kinds.len() > 3);
+
+    // The hidden atom cannot be picked, even through the wireframe.
+    let down = Vec3::new(0.0, 0.0, -1.0);
+    assert!(matches!(
+        viewer.pick(Vec3::new(3.0, 0.0, 10.0), down),
+        Some(ViewerEvent::NothingClicked)
+    ));
+
+    // Bonds to the hidden atom become stubs like for any other filter.
+    viewer.settings.bond_stubs = Some(BOND_STUB_FRACTION);
+    viewer.dirty = true;
+    viewer.update_scene(&mut scene);
+    let bonds = (0..scene.entities.len())
+        .filter(|&i| {
+            matches!(
+                viewer.classify_entity(i),
+                Some(EntityKind::Bond { index: 1, .. })
+            )
+        })
+        .count();
+    assert_eq!(bonds, 2); // Stub and its cap.
+
+    viewer.set_cutaway(Some(Cutaway {
+        mode: VisibilityMode::Ghost { opacity: 0.2 },
+        ..cutaway
+    }));
+    let atom = &viewer.molecule.as_ref().unwrap().atoms[2];
+    assert_eq!(viewer.atom_opacity(atom), Some(0.2));
+    viewer.set_cutaway(None);
+    assert_eq!(viewer.drawn_atoms(), [0, 1, 2]);
+}
+
+#[test]
+fn test_bond_stubs_to_hidden_atoms() {
+    let bond = |atom_a, atom_b| Bond {
+        atom_a,
+        atom_b,
+        order: 1,
+    };
+    let mol = Molecule {
+        atoms: vec![
+            atom("C", 0.0, 0.0, 0.0),
+            atom("H", 1.5, 0.0, 0.0),
+            atom("H", 0.0, 1.5, 0.0),
+        ],
+        bonds: vec![bond(0, 1), bond(1, 2)],
+        ..Default::default()
+    };
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(mol);
+    viewer.settings.hide_hydrogens = true;
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    // Without stubs, bonds to hidden atoms are dropped.
+    assert_eq!(scene.entities.len(), 1);
+
+    // The C-H bond becomes a stub plus its cap; the H-H bond stays hidden.
+    viewer.settings.bond_stubs = Some(BOND_STUB_FRACTION);
+    viewer.dirty = true;
+    viewer.update_scene(&mut scene);
+    assert_eq!(scene.entities.len(), 3);
+    let reach = ATOM_RADIUS + BOND_STUB_FRACTION * 1.5;
+    assert!((scene.entities[1].position.x - reach / 2.0).abs() < 1e-5);
+    assert!((scene.entities[2].position.x - reach).abs() < 1e-5);
+    assert_eq!(scene.entities[2].color, scene.entities[1].color);
+    for i in 1..3 {
+        assert_eq!(
+            viewer.classify_entity(i),
+            Some(EntityKind::Bond {
+                molecule: 0,
+                index: 0
+            })
+        );
+    }
+
+    let down = Vec3::new(0.0, 0.0, -1.0);
+    let on_stub = viewer.pick(Vec3::new(reach - 0.05, 0.0, 10.0), down);
+    assert!(matches!(on_stub, Some(ViewerEvent::BondClicked(0))));
+    let past_stub = viewer.pick(Vec3::new(reach + 0.2, 0.0, 10.0), down);
+    assert!(matches!(past_stub, Some(ViewerEvent::NothingClicked)));
+
+    // A hiding slab stubs bonds the same way instead of cutting them: the
+    // second hydrogen is back, and both bonds stub towards the first.
+    viewer.settings.hide_hydrogens = false;
+    viewer.settings.slab = Some(Slab::new(Point3::origin(), Vector3::x(), 1.0));
+    viewer.dirty = true;
+    viewer.update_scene(&mut scene);
+    assert_eq!(scene.entities.len(), 6);
+    assert!((scene.entities[3].position.x - reach).abs() < 1e-5);
+    assert_eq!(scene.entities[3].color, scene.entities[2].color);
+}
+
+#[test]
+fn test_scene_building_blocks_match_update_scene() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(benzene());
+    viewer.settings.hide_hydrogens = true;
+    viewer.settings.bond_stubs = Some(BOND_STUB_FRACTION);
+    viewer.settings.shading = Shading::Flat;
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+
+    let mut custom = Scene::default();
+    let meshes = viewer.ensure_meshes(&mut custom);
+    assert_eq!((meshes.sphere, meshes.cylinder), (0, 1));
+    viewer.emit_atom_entities(&mut custom.entities, meshes.sphere);
+    viewer.emit_bond_entities(&mut custom.entities, meshes);
+    assert_eq!(
+        scene_snapshot(&viewer, &custom),
+        scene_snapshot(&viewer, &scene)
+    );
+
+    // The pieces can go after the app's own meshes and entities.
+    let mut offset = Scene::default();
+    offset.meshes.push(Mesh::new_sphere(1.0, 1));
+    let meshes = viewer.ensure_meshes(&mut offset);
+    assert_eq!((meshes.sphere, meshes.cylinder), (1, 2));
+    let mut bonds = Vec::new();
+    viewer.emit_bond_entities(&mut bonds, meshes);
+    assert_eq!(bonds.len(), custom.entities.len() - 6);
+    assert!(bonds.iter().all(|e| e.mesh == 1 || e.mesh == 2));
+}
+
+#[test]
+fn test_per_molecule_display_state() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.default_state.render_style = RenderStyle::SpaceFilling;
+    let first = viewer.add_molecule(benzene());
+    assert_eq!(first, AddResult::Added(0));
+    assert_eq!(viewer.render_style, RenderStyle::SpaceFilling);
+
+    // Configure the active (first) molecule, then add a second one.
+    viewer.set_render_style(RenderStyle::BallAndStick);
+    viewer.settings.hide_hydrogens = true;
+    let second = viewer.add_molecule_with(benzene(), FORCE);
+    assert_eq!(second, AddResult::Added(1));
+    assert_eq!(viewer.molecule_count(), 2);
+    assert_eq!(viewer.active_molecule(), 0);
+    assert_eq!(
+        viewer.display_state(1).unwrap().render_style,
+        RenderStyle::SpaceFilling
+    );
+
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    let count = |molecule: usize| {
+        (0..scene.entities.len())
+            .filter(|&i| {
+                matches!(
+                    viewer.classify_entity(i),
+                    Some(EntityKind::Atom { molecule: m, .. } | EntityKind::Bond { molecule: m, .. })
+                        if m == molecule
+                )
+            })
+            .count()
+    };
+    // First: 6 carbons + 6 C-C bonds. Second: 12 atoms, no bonds.
+    assert_eq!(count(0), 12);
+    assert_eq!(count(1), 12);
+
+    // Switching swaps the states in and out of the public fields.
+    assert!(viewer.set_active_molecule(1));
+    assert_eq!(viewer.render_style, RenderStyle::SpaceFilling);
+    assert!(!viewer.settings.hide_hydrogens);
+    let first_state = viewer.display_state(0).unwrap();
+    assert_eq!(first_state.render_style, RenderStyle::BallAndStick);
+    assert!(first_state.settings.hide_hydrogens);
+    assert!(viewer.set_active_molecule(0));
+    assert!(viewer.settings.hide_hydrogens);
+    assert!(!viewer.set_active_molecule(5));
+}
+
+#[test]
+fn test_ground_disc() {
+    let mut viewer = MoleculeViewer::new();
+    viewer.set_molecule(benzene());
+    viewer.additional_render = Some(Box::new(GroundDiscRender::new()));
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+
+    let disc_idx = scene.entities.len() - 1;
+    assert_eq!(
+        viewer.classify_entity(disc_idx),
+        Some(EntityKind::Overlay { renderer_id: 0 })
+    );
+    let disc = &scene.entities[disc_idx];
+    // Benzene spans y in [-2.437, 2.437] and x in [-2.11, 2.11] (z = 0).
+    assert!(disc.position.y < -2.437 - ATOM_RADIUS);
+    let radius = disc.scale_partial.unwrap().x;
+    assert!(radius > 2.437 + ATOM_RADIUS);
+
+    // A ray that only hits the disc picks nothing.
+    let down = Vec3::new(0.0, -1.0, 0.0);
+    let beside = Vec3::new(radius - 0.5, 10.0, 0.0);
+    assert!(matches!(
+        viewer.pick(beside, down),
+        Some(ViewerEvent::NothingClicked)
+    ));
+
+    // Follows the molecule, and can be switched off.
+    viewer.set_molecule(Molecule {
+        atoms: vec![atom("C", 0.0, 10.0, 0.0)],
+        ..Default::default()
+    });
+    viewer.update_scene(&mut scene);
+    assert!(scene.entities.last().unwrap().position.y > 9.0);
+    viewer.additional_render.as_mut().unwrap().enabled = false;
+    viewer.dirty = true;
+    viewer.update_scene(&mut scene);
+    assert_eq!(scene.entities.len(), 1);
+}
+
+fn build_kinds(viewer: &mut MoleculeViewer<SelectedAtomRender>) -> Vec<EntityKind> {
+    let mut scene = Scene::default();
+    viewer.dirty = true;
+    viewer.update_scene(&mut scene);
+    (0..scene.entities.len())
+        .map(|i| viewer.classify_entity(i).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_entity_order_is_stable() {
+    let mut viewer = MoleculeViewer::new();
+    viewer.add_molecule(benzene());
+    viewer.add_molecule_with(benzene(), FORCE);
+    let mut selection = SelectedAtomRender::new();
+    selection.add_atom(2);
+    viewer.additional_render = Some(Box::new(selection));
+    let reference = build_kinds(&mut viewer);
+
+    let mut expected = Vec::new();
+    for molecule in 0..2 {
+        expected.extend((0..12).map(|index| EntityKind::Atom { molecule, index }));
+        expected.extend((0..12).map(|index| EntityKind::Bond { molecule, index }));
+    }
+    expected.push(EntityKind::Overlay { renderer_id: 0 });
+    assert_eq!(reference, expected);
+
+    // Build options and the active molecule must not change the order.
+    viewer.settings.detail = Detail::Low;
+    assert_eq!(build_kinds(&mut viewer), reference);
+    viewer.settings.shading = Shading::Toon { bands: 3 };
+    assert_eq!(build_kinds(&mut viewer), reference);
+    viewer.settings.ao_strength = 0.5;
+    viewer.settings.color_rings_by_planarity = true;
+    assert_eq!(build_kinds(&mut viewer), reference);
+    viewer.set_active_molecule(1);
+    assert_eq!(build_kinds(&mut viewer), reference);
+}
+
+#[test]
+fn test_toon_bands_follow_the_light() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(benzene());
+    viewer.settings.shading = Shading::Toon { bands: 3 };
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+
+    // Every lit face, bonds at any angle included, ends up within half a
+    // bond mesh's range of the middle of a band.
+    let light = Vector3::from(TOON_LIGHT_DIR).normalize();
+    let band = std::f32::consts::PI / 3.0;
+    let slack = std::f32::consts::PI / TOON_BOND_MESHES as f32 / 2.0 + 1e-3;
+    let mut cylinders = Vec::new();
+    for (i, entity) in scene.entities.iter().enumerate() {
+        if let Some(EntityKind::Bond { .. }) = viewer.classify_entity(i) {
+            cylinders.push(entity.mesh);
+        }
+        let rotation = quat_to_na(entity.orientation);
+        for v in &scene.meshes[entity.mesh].vertices {
+            let angle = (rotation * vec_to_na(v.normal)).angle(&light);
+            let off = (angle / band).fract() * band - band / 2.0;
+            assert!(off.abs() <= slack, "entity {i}: {angle}");
+        }
+    }
+    cylinders.sort();
+    cylinders.dedup();
+    assert!(cylinders.len() > 2, "{cylinders:?}");
+}
+
+#[test]
+fn test_atoms_in_sphere() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(benzene());
+
+    // Touching the display sphere is enough.
+    let carbon = Point3::new(-1.1579, 0.6685, 0.0);
+    assert_eq!(viewer.atoms_in_sphere(carbon, 0.0), vec![0]);
+    assert_eq!(viewer.atoms_in_sphere(carbon, 1.0), vec![0, 1, 5, 6]);
+    assert_eq!(
+        viewer.atoms_in_sphere(Point3::new(0.0, 0.0, 5.0), 1.0),
+        Vec::<usize>::new()
+    );
+
+    viewer.settings.hide_hydrogens = true;
+    assert_eq!(viewer.atoms_in_sphere(carbon, 1.0), vec![0, 1, 5]);
+}
+
+#[test]
+fn test_atoms_in_frustum() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(benzene());
+    let mut camera = OrbitalCamera::default();
+    camera.set_aspect(800.0 / 600.0);
+
+    let full = (Point2::new(0.0, 0.0), Point2::new(800.0, 600.0));
+    assert_eq!(
+        viewer.atoms_in_frustum(&camera, full, 800.0, 600.0),
+        (0..12).collect::<Vec<_>>()
+    );
+
+    // Corners may come in any order; the top half holds the atoms with y > 0.
+    let top = (Point2::new(800.0, 300.0), Point2::new(0.0, 0.0));
+    assert_eq!(
+        viewer.atoms_in_frustum(&camera, top, 800.0, 600.0),
+        vec![0, 4, 5, 6, 10, 11]
+    );
+
+    viewer.settings.hide_hydrogens = true;
+    assert_eq!(
+        viewer.atoms_in_frustum(&camera, top, 800.0, 600.0),
+        vec![0, 4, 5]
+    );
+
+    let empty = (Point2::new(400.0, 0.0), Point2::new(400.0, 600.0));
+    assert!(viewer
+        .atoms_in_frustum(&camera, empty, 800.0, 600.0)
+        .is_empty());
+}
+
+#[test]
+fn test_element_scaled_atom_radii() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(benzene());
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+
+    // Atoms 0-5 are carbons, 6-11 hydrogens.
+    let carbon = scene.entities[0].scale;
+    let hydrogen = scene.entities[6].scale;
+    assert!((carbon - ATOM_RADIUS).abs() < 1e-6);
+    assert!(hydrogen < carbon);
+
+    viewer.settings.element_scale = None;
+    viewer.dirty = true;
+    viewer.update_scene(&mut scene);
+    assert!((scene.entities[6].scale - ATOM_RADIUS).abs() < 1e-6);
+}
+
+#[test]
+fn test_radii_table() {
+    let table = AtomRadiiTable::default();
+    assert_eq!(table.radius("C"), 0.76);
+    assert_eq!(table.radius("cl"), table.radius("Cl"));
+    assert_eq!(table.radius("Xx"), table.fallback);
+
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(benzene());
+    viewer.settings.radii_table = Some(table.with_entry("h", 0.9));
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    // The table wins over `element_scale`, and picking agrees.
+    assert_eq!(scene.entities[0].scale, 0.76);
+    assert_eq!(scene.entities[6].scale, 0.9);
+    let mol = viewer.molecule.as_ref().unwrap();
+    assert_eq!(viewer.atom_radius(&mol.atoms[6]), 0.9);
+
+    // Other styles keep their own radii.
+    viewer.set_render_style(RenderStyle::SpaceFilling);
+    viewer.update_scene(&mut scene);
+    assert_eq!(scene.entities[0].scale, 1.7);
+}
+
+#[test]
+fn test_pick_scaled_hydrogen_at_its_edge() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(benzene());
+    let mol = viewer.molecule.clone().unwrap();
+    let hydrogen = &mol.atoms[6];
+    let radius = viewer.atom_radius(hydrogen);
+    assert!(radius < ATOM_RADIUS);
+
+    // Aim down the z axis just inside and just outside the sphere's rim, on the
+    // side facing away from the C-H bond.
+    let outward = (hydrogen.position - mol.atoms[0].position).normalize();
+    let ray_at = |offset: f32| {
+        let p = hydrogen.position + outward * offset;
+        viewer.pick(Vec3::new(p.x, p.y, 10.0), Vec3::new(0.0, 0.0, -1.0))
+    };
+    assert!(matches!(
+        ray_at(radius * 0.95),
+        Some(ViewerEvent::AtomClicked(6))
+    ));
+    assert!(matches!(
+        ray_at(radius * 1.05),
+        Some(ViewerEvent::NothingClicked)
+    ));
+}
+
+#[test]
+fn test_space_filling_picks_van_der_waals_spheres() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule_with(
+        Molecule {
+            atoms: vec![atom("O", 0.0, 0.0, 0.0)],
+            ..Default::default()
+        },
+        NO_AUTO_STYLE,
+    );
+    let ray_at = |viewer: &MoleculeViewer<SelectedAtomRender>, x: f32| {
+        viewer.pick(Vec3::new(x, 0.0, 10.0), Vec3::new(0.0, 0.0, -1.0))
+    };
+    let between = 1.0;
+    assert!(ATOM_RADIUS < between && between < element::vdw_radius("O"));
+    assert_eq!(ray_at(&viewer, between), Some(ViewerEvent::NothingClicked));
+
+    viewer.set_render_style(RenderStyle::SpaceFilling);
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    assert_eq!(scene.entities[0].scale, element::vdw_radius("O"));
+    assert_eq!(ray_at(&viewer, between), Some(ViewerEvent::AtomClicked(0)));
+}
+
+#[test]
+fn test_stick_bonds_pick_as_capsules() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule_with(
+        Molecule {
+            atoms: vec![atom("C", 0.0, 0.0, 0.0), atom("C", 0.0, 0.0, 3.0)],
+            bonds: vec![Bond {
+                atom_a: 0,
+                atom_b: 1,
+                order: 1,
+            }],
+            ..Default::default()
+        },
+        NO_AUTO_STYLE,
+    );
+    // The slab hides the second atom and cuts the bond at z = 1.
+    viewer.settings.slab = Some(Slab::new(Point3::origin(), Vector3::z(), 1.0));
+    let ray_at = |viewer: &MoleculeViewer<SelectedAtomRender>, z: f32| {
+        viewer.pick(Vec3::new(5.0, 0.0, z), Vec3::new(-1.0, 0.0, 0.0))
+    };
+
+    for style in [RenderStyle::BallAndStick, RenderStyle::Stick] {
+        viewer.render_style = style;
+        assert!(matches!(
+            ray_at(&viewer, 0.9),
+            Some(ViewerEvent::BondClicked(0))
+        ));
+        // Past the cut, only the rounded end of a stick bond is there.
+        let expected = match style {
+            RenderStyle::Stick => ViewerEvent::BondClicked(0),
+            _ => ViewerEvent::NothingClicked,
+        };
+        assert_eq!(ray_at(&viewer, 1.1), Some(expected), "{style:?}");
+        assert_eq!(
+            ray_at(&viewer, 1.0 + BOND_RADIUS * 1.1),
+            Some(ViewerEvent::NothingClicked)
+        );
+    }
+}
+
+#[test]
+fn test_reference_axis_line() {
+    let mut viewer = MoleculeViewer::new();
+    viewer.set_molecule(benzene());
+    let mut selection = SelectedAtomRender::new();
+    selection.add_atom(0);
+    viewer.additional_render = Some(Box::new(selection));
+    assert!(!viewer.set_reference_axis(Vector3::zeros(), "none"));
+    assert!(viewer.set_reference_axis(Vector3::new(0.0, 0.0, 2.0), "normal"));
+    assert_eq!(viewer.reference_axis().unwrap().direction, Vector3::z());
+
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    // Drawn after the molecule, before the overlays.
+    let n = scene.entities.len();
+    assert_eq!(
+        viewer.classify_entity(n - 2),
+        Some(EntityKind::ReferenceAxis)
+    );
+    assert_eq!(
+        viewer.classify_entity(n - 1),
+        Some(EntityKind::Overlay { renderer_id: 0 })
+    );
+    let line = &scene.entities[n - 2];
+    assert_eq!(line.color, REFERENCE_AXIS_COLOR);
+    // Benzene is flat, so the line only spans the margins.
+    assert!((line.scale_partial.unwrap().y - 4.0).abs() < 1e-4);
+
+    viewer.clear_reference_axis();
+    viewer.update_scene(&mut scene);
+    assert_eq!(scene.entities.len(), n - 1);
+}
+
+#[test]
+fn test_composition_bar() {
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(benzene());
+    let all = CompositionOptions::default();
+    let bar = viewer.composition_bar(all).to_vec();
+    let shares: Vec<_> = bar.iter().map(|e| (e.element.as_str(), e.count)).collect();
+    // Equal counts go alphabetically.
+    assert_eq!(shares, [("C", 6), ("H", 6)]);
+    assert_eq!(bar[0].fraction, 0.5);
+    assert_ne!(bar[0].color, bar[1].color);
+
+    let heavy = CompositionOptions {
+        heavy_atoms_only: true,
+        ..Default::default()
+    };
+    assert_eq!(viewer.composition_bar(heavy).len(), 1);
+    assert_eq!(viewer.composition_bar(heavy)[0].fraction, 1.0);
+
+    let visible = CompositionOptions {
+        visible_only: true,
+        ..Default::default()
+    };
+    let mut scene = Scene::default();
+    viewer.update_scene(&mut scene);
+    viewer.settings.hidden_atoms.extend([0, 1]);
+    viewer.dirty = true;
+    let bar = viewer.composition_bar(visible).to_vec();
+    assert_eq!((bar[0].element.as_str(), bar[0].count), ("H", 6));
+    assert!((bar[1].fraction - 0.4).abs() < 1e-6);
+
+    // Cached until something sets `dirty`.
+    viewer.update_scene(&mut scene);
+    assert_eq!(viewer.composition_bar(visible).len(), 2);
+    viewer.molecule.as_mut().unwrap().atoms.truncate(2);
+    assert_eq!(viewer.composition_bar(visible).len(), 2);
+    viewer.dirty = true;
+    assert!(viewer.composition_bar(visible).is_empty());
+    assert_eq!(viewer.composition_bar(all)[0].count, 2);
+}
+
+#[test]
+fn test_indexed_pick_matches_brute_force() {
+    // 22 x 22 x 21 carbons 1.5 Å apart, bonded along x.
+    let n = [22, 22, 21];
+    let mut atoms = Vec::new();
+    let mut bonds = Vec::new();
+    for x in 0..n[0] {
+        for y in 0..n[1] {
+            for z in 0..n[2] {
+                if x > 0 {
+                    bonds.push(Bond {
+                        atom_a: atoms.len() - n[1] * n[2],
+                        atom_b: atoms.len(),
+                        order: 1,
+                    });
+                }
+                let position = Point3::new(x as f32, y as f32, z as f32) * 1.5;
+                atoms.push(Atom {
+                    position,
+                    element: "C".to_string(),
+                    id: 0,
+                });
+            }
+        }
+    }
+    assert!(atoms.len() >= 10_000 && atoms.len() >= PICK_INDEX_MIN_ATOMS);
+    let mut viewer = MoleculeViewer::<SelectedAtomRender>::new();
+    viewer.set_molecule(Molecule {
+        atoms,
+        bonds,
+        ..Default::default()
+    });
+
+    // Rays from all around towards points in and near the lattice.
+    let mut seed = 7u64;
+    let mut next = move || {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+        (seed >> 40) as f32 / (1u64 << 24) as f32
+    };
+    let rays: Vec<(Vec3, Vec3)> = (0..40)
+        .map(|_| {
+            let target = Point3::new(next(), next(), next()) * 36.0 - Vector3::repeat(2.0);
+            let away = Vector3::new(next() - 0.5, next() - 0.5, next() - 0.5).normalize();
+            let origin = target + away * 80.0;
+            let dir = target - origin;
+            (
+                Vec3::new(origin.x, origin.y, origin.z),
+                Vec3::new(dir.x, dir.y, dir.z),
+            )
+        })
+        .collect();
+    let picks = |viewer: &MoleculeViewer<SelectedAtomRender>| -> Vec<_> {
+        rays.iter().map(|&(o, d)| viewer.pick(o, d)).collect()
+    };
+    let pick_all = |viewer: &MoleculeViewer<SelectedAtomRender>| -> Vec<_> {
+        rays.iter().map(|&(o, d)| viewer.pick_all(o, d)).collect()
+    };
+
+    for hide_some in [false, true] {
+        if hide_some {
+            viewer.settings.hidden_atoms.extend((0..10_000).step_by(3));
+            viewer.dirty = true;
+        }
+        // Dirty: every atom and bond is tested.
+        let brute_force = picks(&viewer);
+        let all_brute_force = pick_all(&viewer);
+        viewer.update_scene(&mut Scene::default());
+        assert_eq!(picks(&viewer), brute_force);
+        assert_eq!(pick_all(&viewer), all_brute_force);
+        let hits = brute_force
+            .iter()
+            .filter(|p| **p != Some(ViewerEvent::NothingClicked))
+            .count();
+        assert!(hits > 30, "{hits}");
+    }
+}